@@ -0,0 +1,129 @@
+//! Attribute macro defining GSM 07.10 control message types, re-exported by `cmux` with its
+//! `macros` feature as `cmux::control::cmux_message`.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Expr, Fields, Token};
+
+/// Arguments of the attribute: `type = <6-bit message type>`.
+struct Args {
+    message_type: Expr,
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Token![type]>()?;
+        input.parse::<Token![=]>()?;
+        let message_type = input.parse()?;
+        Ok(Args { message_type })
+    }
+}
+
+/// Defines a control message type from a struct with named fields.
+///
+/// The documentation and an example are on the re-export, `cmux::control::cmux_message`.
+#[proc_macro_attribute]
+pub fn cmux_message(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as Args);
+    let input = parse_macro_input!(item as DeriveInput);
+    match expand(&args, &input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(args: &Args, input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "a control message is a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "a control message is a struct with named fields",
+            ))
+        }
+    };
+    let name = &input.ident;
+    let message_type = &args.message_type;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let idents: Vec<_> = fields.iter().map(|field| &field.ident).collect();
+    let types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+
+    Ok(quote! {
+        #input
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// The type of the message.
+            pub const MESSAGE_TYPE: ::cmux::control::MessageType =
+                ::cmux::control::MessageType::from_bits(#message_type);
+
+            /// Encodes the value octets.
+            pub fn encode(&self) -> ::cmux::control::__private::Vec<u8> {
+                #[allow(unused_mut)]
+                let mut value = ::cmux::control::__private::Vec::new();
+                #(::cmux::control::MessageField::encode_field(&self.#idents, &mut value);)*
+                value
+            }
+
+            /// Decodes the value octets.
+            pub fn decode(value: &[u8]) -> ::core::result::Result<Self, ::cmux::error::ControlError> {
+                let min = 0 #(+ <#types as ::cmux::control::MessageField>::MIN_LEN)*;
+                let short = ::cmux::error::ControlError::BadValueLength {
+                    expected: min,
+                    actual: value.len(),
+                };
+                if value.len() < min {
+                    return ::core::result::Result::Err(short);
+                }
+                #[allow(unused_mut)]
+                let mut rest = value;
+                let message = #name {
+                    #(#idents: ::cmux::control::MessageField::decode_field(&mut rest)
+                        .ok_or_else(|| short.clone())?,)*
+                };
+                if !rest.is_empty() {
+                    return ::core::result::Result::Err(::cmux::error::ControlError::BadValueLength {
+                        expected: value.len() - rest.len(),
+                        actual: value.len(),
+                    });
+                }
+                ::core::result::Result::Ok(message)
+            }
+
+            /// Wraps the value in a message.
+            ///
+            /// # Arguments
+            ///
+            /// * `cr` - True for a command, false for a response.
+            pub fn to_message(&self, cr: bool) -> ::cmux::control::ControlMessage {
+                ::cmux::control::ControlMessage::new(Self::MESSAGE_TYPE, cr, self.encode())
+            }
+        }
+
+        impl #impl_generics ::core::convert::TryFrom<&::cmux::control::ControlMessage>
+            for #name #ty_generics #where_clause
+        {
+            type Error = ::cmux::error::ControlError;
+
+            fn try_from(
+                message: &::cmux::control::ControlMessage,
+            ) -> ::core::result::Result<Self, Self::Error> {
+                if message.message_type != Self::MESSAGE_TYPE {
+                    return ::core::result::Result::Err(::cmux::error::ControlError::UnexpectedType {
+                        expected: Self::MESSAGE_TYPE,
+                        found: message.message_type,
+                    });
+                }
+                #name::decode(&message.value)
+            }
+        }
+    })
+}
@@ -0,0 +1,163 @@
+#ifndef CMUX_H
+#define CMUX_H
+
+/* Generated by cbindgen from src/ffi.rs. Do not edit. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * Result code of every C function
+ *
+ * The values are part of the C API and never change; new codes are only appended.
+ */
+typedef enum CmuxStatus {
+  CMUX_STATUS_OK = 0,
+  /**
+   * A required pointer argument is NULL.
+   */
+  CMUX_STATUS_NULL_POINTER = -1,
+  /**
+   * The output buffer is too small; the required size is written to the length argument.
+   */
+  CMUX_STATUS_BUFFER_TOO_SMALL = -2,
+  /**
+   * The input ends before the frame or control message does.
+   */
+  CMUX_STATUS_TRUNCATED = -3,
+  /**
+   * An opening or closing flag octet is not 0xF9.
+   */
+  CMUX_STATUS_BAD_FLAG = -4,
+  /**
+   * The length indicator does not match the information field.
+   */
+  CMUX_STATUS_BAD_LENGTH = -5,
+  /**
+   * The FCS does not match the calculated checksum.
+   */
+  CMUX_STATUS_CHECKSUM_MISMATCH = -6,
+  /**
+   * The information field is too long for the length indicator.
+   */
+  CMUX_STATUS_CONTENT_TOO_LONG = -7,
+  /**
+   * A field required to build the frame is missing.
+   */
+  CMUX_STATUS_MISSING_FIELD = -8,
+  /**
+   * The control message uses an extended type field or has an invalid value.
+   */
+  CMUX_STATUS_BAD_CONTROL_MESSAGE = -9,
+} CmuxStatus;
+
+/**
+ * Frame decoded by [`cmux_frame_parse`]
+ *
+ * `content` points into the parsed buffer and is valid as long as that buffer is.
+ */
+typedef struct CmuxFrame {
+  uint8_t address;
+  uint8_t control;
+  uint8_t dlci;
+  bool cr;
+  bool pf;
+  /**
+   * Length indicator as stored in [`Frame::length`](crate::types::Frame::length).
+   */
+  uint16_t length;
+  const uint8_t *content;
+  uintptr_t content_len;
+  uint8_t checksum;
+} CmuxFrame;
+
+/**
+ * Control message decoded by [`cmux_control_decode`]
+ *
+ * `value` points into the decoded buffer and is valid as long as that buffer is.
+ */
+typedef struct CmuxControlMessage {
+  /**
+   * The 6-bit type field, without the EA and C/R bits.
+   */
+  uint8_t message_type;
+  bool cr;
+  const uint8_t *value;
+  uintptr_t value_len;
+} CmuxControlMessage;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Builds a basic option frame and writes its bytes to `out`.
+ *
+ * The content is framed as is, without appending `\r\n`.
+ *
+ * # Safety
+ *
+ * `content` must point to `content_len` readable bytes, or be NULL if `content_len` is 0.
+ * `out` must point to `out_cap` writable bytes and `out_len` to a writable `size_t`.
+ */
+enum CmuxStatus cmux_frame_build(uint8_t address,
+                                 uint8_t control,
+                                 const uint8_t *content,
+                                 uintptr_t content_len,
+                                 uint8_t *out,
+                                 uintptr_t out_cap,
+                                 uintptr_t *out_len);
+
+/**
+ * Parses one basic option frame, from opening to closing flag, without verifying it.
+ *
+ * # Safety
+ *
+ * `data` must point to `len` readable bytes and `frame` to a writable [`CmuxFrame`].
+ */
+enum CmuxStatus cmux_frame_parse(const uint8_t *data, uintptr_t len, struct CmuxFrame *frame);
+
+/**
+ * Parses one basic option frame and verifies its length indicator and FCS.
+ *
+ * # Safety
+ *
+ * `data` must point to `len` readable bytes.
+ */
+enum CmuxStatus cmux_frame_verify(const uint8_t *data, uintptr_t len);
+
+/**
+ * Encodes a control message and writes its bytes to `out`.
+ *
+ * # Safety
+ *
+ * `value` must point to `value_len` readable bytes, or be NULL if `value_len` is 0.
+ * `out` must point to `out_cap` writable bytes and `out_len` to a writable `size_t`.
+ */
+enum CmuxStatus cmux_control_encode(uint8_t message_type,
+                                    bool cr,
+                                    const uint8_t *value,
+                                    uintptr_t value_len,
+                                    uint8_t *out,
+                                    uintptr_t out_cap,
+                                    uintptr_t *out_len);
+
+/**
+ * Decodes the control message at the start of `data`, as found in a DLCI 0 information field.
+ *
+ * # Safety
+ *
+ * `data` must point to `len` readable bytes, `message` to a writable [`CmuxControlMessage`]
+ * and `used` to a writable `size_t`, which receives the size of the message.
+ */
+enum CmuxStatus cmux_control_decode(const uint8_t *data,
+                                    uintptr_t len,
+                                    struct CmuxControlMessage *message,
+                                    uintptr_t *used);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CMUX_H */
@@ -0,0 +1,348 @@
+use crate::control::PortSettings;
+use crate::error::FrameError;
+use crate::types::{Control, Frame, FrameBuilder, FrameType};
+use alloc::vec;
+use alloc::vec::Vec;
+use crc::Crc;
+
+/// Flag octet opening and closing every advanced option [`Frame`].
+pub const ADVANCED_FLAG: u8 = 0x7E;
+
+/// Control escape octet of advanced option transparency.
+pub const CONTROL_ESCAPE: u8 = 0x7D;
+
+/// Value XORed with an escaped octet.
+const ESCAPE_MASK: u8 = 0x20;
+
+/// Minimum number of octets of an advanced option frame: flag, address, control, FCS and flag.
+pub(crate) const MIN_ADVANCED_LENGTH: usize = 5;
+
+/// Octets escaped between the flags of advanced option frames
+///
+/// The flag and the control escape octet are always escaped. When the UART uses XON/XOFF flow
+/// control, as negotiated with RPN, the XON and XOFF characters must be escaped too, so that
+/// the flow control characters the link inserts can be told from data. Other characters that
+/// a link mangles can be added as well.
+///
+/// When decoding, the octets of the set other than the control escape that arrive unescaped
+/// were inserted by the link, and are dropped.
+///
+/// # Example
+///
+/// ```
+/// use cmux::advanced::EscapeSet;
+///
+/// let escapes = EscapeSet::new().with_xon_xoff(0x11, 0x13);
+/// let escaped = escapes.escape(&[0x01, 0x11, 0x7E]);
+/// assert_eq!(escaped, vec![0x01, 0x7D, 0x31, 0x7D, 0x5E]);
+/// // An XOFF sent by the UART between the octets is not data.
+/// assert_eq!(
+///     escapes.unescape(&[0x01, 0x13, 0x7D, 0x31, 0x7D, 0x5E]).unwrap(),
+///     vec![0x01, 0x11, 0x7E]
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct EscapeSet {
+    /// One bit per octet value.
+    bits: [u32; 8],
+}
+
+impl Default for EscapeSet {
+    fn default() -> Self {
+        EscapeSet::new()
+    }
+}
+
+impl EscapeSet {
+    /// Creates the set of 07.10: the flag and the control escape octet.
+    pub const fn new() -> Self {
+        EscapeSet { bits: [0; 8] }
+            .with_octet(ADVANCED_FLAG)
+            .with_octet(CONTROL_ESCAPE)
+    }
+
+    /// Adds `octet` to the set.
+    pub const fn with_octet(mut self, octet: u8) -> Self {
+        self.bits[(octet / 32) as usize] |= 1 << (octet % 32);
+        self
+    }
+
+    /// Adds the XON and XOFF characters to the set, usually DC1 (0x11) and DC3 (0x13).
+    pub const fn with_xon_xoff(self, xon: u8, xoff: u8) -> Self {
+        self.with_octet(xon).with_octet(xoff)
+    }
+
+    /// Returns true if `octet` is escaped.
+    pub const fn contains(&self, octet: u8) -> bool {
+        self.bits[(octet / 32) as usize] & (1 << (octet % 32)) != 0
+    }
+
+    /// Applies control-octet transparency to `data`.
+    ///
+    /// Every octet of the set is replaced by the control escape followed by the octet XORed
+    /// with 0x20.
+    pub fn escape(&self, data: &[u8]) -> Vec<u8> {
+        let mut escaped = Vec::with_capacity(data.len());
+        for &octet in data {
+            if self.contains(octet) {
+                escaped.push(CONTROL_ESCAPE);
+                escaped.push(octet ^ ESCAPE_MASK);
+            } else {
+                escaped.push(octet);
+            }
+        }
+        escaped
+    }
+
+    /// Removes control-octet transparency from `data`, dropping the octets of the set that
+    /// arrive unescaped.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Vec<u8>)`: The original octets.
+    /// - `Err(FrameError)`: If `data` ends with a control escape.
+    pub fn unescape(&self, data: &[u8]) -> Result<Vec<u8>, FrameError> {
+        let mut unescaped = Vec::with_capacity(data.len());
+        let mut octets = data.iter();
+        while let Some(&octet) = octets.next() {
+            if octet == CONTROL_ESCAPE {
+                let &next = octets.next().ok_or(FrameError::TruncatedFrame {
+                    expected: data.len() + 1,
+                    actual: data.len(),
+                })?;
+                unescaped.push(next ^ ESCAPE_MASK);
+            } else if !self.contains(octet) {
+                unescaped.push(octet);
+            }
+        }
+        Ok(unescaped)
+    }
+}
+
+/// Escapes the XON and XOFF characters of `settings` if they enable XON/XOFF flow control in
+/// either direction.
+impl From<&PortSettings> for EscapeSet {
+    fn from(settings: &PortSettings) -> Self {
+        let flow_control = settings.flow_control;
+        if flow_control.xon_xoff_input() || flow_control.xon_xoff_output() {
+            EscapeSet::new().with_xon_xoff(settings.xon, settings.xoff)
+        } else {
+            EscapeSet::new()
+        }
+    }
+}
+
+/// Applies control-octet transparency to `data`.
+///
+/// Every flag or control escape octet is replaced by the control escape followed by the octet
+/// XORed with 0x20. [`EscapeSet::escape`] escapes further octets.
+///
+/// # Example
+///
+/// ```
+/// use cmux::advanced::escape;
+///
+/// assert_eq!(escape(&[0x01, 0x7E, 0x7D]), vec![0x01, 0x7D, 0x5E, 0x7D, 0x5D]);
+/// ```
+pub fn escape(data: &[u8]) -> Vec<u8> {
+    EscapeSet::new().escape(data)
+}
+
+/// Removes control-octet transparency from `data`.
+///
+/// # Returns
+///
+/// - `Ok(Vec<u8>)`: The original octets.
+/// - `Err(FrameError)`: If `data` ends with a control escape.
+pub fn unescape(data: &[u8]) -> Result<Vec<u8>, FrameError> {
+    EscapeSet::new().unescape(data)
+}
+
+/// Generates the advanced option checksum of a frame.
+///
+/// The FCS covers the address and control fields, plus the information field of UI frames.
+/// Advanced option frames have no length indicator.
+pub fn checksum_advanced(addr: u8, control: u8, content: &[u8]) -> u8 {
+    let crc = Crc::<u8>::new(&crc::CRC_8_ROHC);
+    let mut digest = crc.digest();
+    digest.update(&[addr, control]);
+    if Control::from_bits(control).frame_type() == FrameType::UI {
+        digest.update(content);
+    }
+    !digest.finalize()
+}
+
+impl Frame {
+    /// Converts the frame to an advanced option byte vector.
+    ///
+    /// The frame is delimited by 0x7E flags, has no length indicator, and every octet between
+    /// the flags is escaped as described in [`escape`].
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<u8>` containing the advanced option representation of the frame.
+    pub fn to_bytes_advanced(&self) -> Vec<u8> {
+        self.to_bytes_advanced_with(&EscapeSet::new())
+    }
+
+    /// Converts the frame to an advanced option byte vector, escaping the octets of `escapes`.
+    pub fn to_bytes_advanced_with(&self, escapes: &EscapeSet) -> Vec<u8> {
+        let addr = self.address.into_bits();
+        let control = self.control.into_bits();
+        let mut body = vec![addr, control];
+        body.extend_from_slice(self.content.as_bytes());
+        body.push(checksum_advanced(addr, control, self.content.as_bytes()));
+
+        let mut data = vec![ADVANCED_FLAG];
+        data.extend(escapes.escape(&body));
+        data.push(ADVANCED_FLAG);
+        data
+    }
+
+    /// Creates a frame from an advanced option byte vector.
+    ///
+    /// The returned frame carries the basic option length indicator and FCS, so it can be
+    /// verified with [`Frame::verify`] and re-encoded with either [`Frame::to_bytes`] or
+    /// [`Frame::to_bytes_advanced`].
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The frame, including both 0x7E flags.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Frame)`: The decoded frame.
+    /// - `Err(FrameError)`: If the frame is truncated, not delimited by flags, or the FCS does not match.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cmux::types::{Frame, FrameBuilder};
+    ///
+    /// let frame = FrameBuilder::default()
+    ///     .with_binary_content(vec![0x7E, 0x01])
+    ///     .build()
+    ///     .unwrap();
+    /// let data = frame.to_bytes_advanced();
+    /// assert_eq!(hex::encode(&data), "7e07ef7d5e01057e");
+    /// assert_eq!(Frame::from_bytes_advanced(&data).unwrap(), frame);
+    /// ```
+    pub fn from_bytes_advanced(data: &[u8]) -> Result<Frame, FrameError> {
+        Frame::from_bytes_advanced_with(data, &EscapeSet::new())
+    }
+
+    /// Creates a frame from an advanced option byte vector whose octets of `escapes` are
+    /// escaped. The octets of `escapes` found unescaped are dropped.
+    pub fn from_bytes_advanced_with(data: &[u8], escapes: &EscapeSet) -> Result<Frame, FrameError> {
+        if data.len() < MIN_ADVANCED_LENGTH {
+            return Err(FrameError::TruncatedFrame {
+                expected: MIN_ADVANCED_LENGTH,
+                actual: data.len(),
+            });
+        }
+        for &flag in [data[0], data[data.len() - 1]].iter() {
+            if flag != ADVANCED_FLAG {
+                return Err(FrameError::BadFlag {
+                    expected: ADVANCED_FLAG,
+                    found: flag,
+                });
+            }
+        }
+        let body = escapes.unescape(&data[1..data.len() - 1])?;
+        if body.len() < MIN_ADVANCED_LENGTH - 2 {
+            return Err(FrameError::TruncatedFrame {
+                expected: MIN_ADVANCED_LENGTH,
+                actual: body.len() + 2,
+            });
+        }
+        let (addr, control) = (body[0], body[1]);
+        let content = &body[2..body.len() - 1];
+        let actual = body[body.len() - 1];
+        let expected = checksum_advanced(addr, control, content);
+        if actual != expected {
+            return Err(FrameError::ChecksumMismatch { expected, actual });
+        }
+        FrameBuilder::default()
+            .with_address(addr.into())
+            .with_control(control.into())
+            .with_binary_content(content.to_vec())
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::FlowControl;
+    use crate::types::{Address, DLCI};
+
+    #[test]
+    fn test_escape_roundtrip() {
+        let data: Vec<u8> = (0..=0xFF).collect();
+        let escaped = escape(&data);
+        assert!(!escaped.contains(&ADVANCED_FLAG));
+        assert_eq!(escaped.len(), data.len() + 2);
+        assert_eq!(unescape(&escaped).unwrap(), data);
+        assert!(unescape(&[0x01, CONTROL_ESCAPE]).is_err());
+    }
+
+    #[test]
+    fn test_escape_set() {
+        let settings = PortSettings::default()
+            .with_flow_control(FlowControl::new().with_xon_xoff_output(true))
+            .with_xon_xoff(0x01, 0x02);
+        let escapes = EscapeSet::from(&settings);
+        assert_eq!(escapes, EscapeSet::new().with_xon_xoff(0x01, 0x02));
+        assert_eq!(EscapeSet::from(&PortSettings::default()), EscapeSet::new());
+
+        let frame = FrameBuilder::default()
+            .with_binary_content(vec![0x01, 0x02, 0x11])
+            .build()
+            .unwrap();
+        let data = frame.to_bytes_advanced_with(&escapes);
+        assert_eq!(hex::encode_upper(&data), "7E07EF7D217D2211057E");
+        assert_eq!(
+            Frame::from_bytes_advanced_with(&data, &escapes),
+            Ok(frame.clone())
+        );
+        // Unescaped, they are taken for flow control characters and dropped.
+        let data = frame.to_bytes_advanced();
+        assert_eq!(Frame::from_bytes_advanced(&data), Ok(frame.clone()));
+        assert_ne!(Frame::from_bytes_advanced_with(&data, &escapes), Ok(frame));
+    }
+
+    #[test]
+    fn test_advanced_sabm() {
+        let frame = FrameBuilder::default()
+            .with_address(Address::default().with_dlci(DLCI::OTHER(0)))
+            .with_control(
+                Control::default()
+                    .with_frame_type(FrameType::SABM)
+                    .with_pf(true),
+            )
+            .build()
+            .unwrap();
+        let data = frame.to_bytes_advanced();
+        assert_eq!(hex::encode_upper(&data), "7E033FFC7E");
+        assert_eq!(Frame::from_bytes_advanced(&data).unwrap(), frame);
+    }
+
+    #[test]
+    fn test_advanced_errors() {
+        assert_eq!(
+            Frame::from_bytes_advanced(&[0x7E, 0x03, 0x3F, 0x00, 0x7E]),
+            Err(FrameError::ChecksumMismatch {
+                expected: 0xFC,
+                actual: 0x00
+            })
+        );
+        assert_eq!(
+            Frame::from_bytes_advanced(&[0xF9, 0x03, 0x3F, 0xFC, 0x7E]),
+            Err(FrameError::BadFlag {
+                expected: 0x7E,
+                found: 0xF9
+            })
+        );
+    }
+}
@@ -0,0 +1,238 @@
+use crate::advanced::ADVANCED_FLAG;
+use crate::at::RESET_BANNERS;
+use crate::error::FrameError;
+use crate::types::{Frame, FrameType, FLAG};
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+
+/// Number of flags in a row, without any frame, reported as [`Anomaly::ContinuousFlags`].
+pub const CONTINUOUS_FLAGS: usize = 64;
+
+/// Number of DM frames in a row on DLCI 0 reported as [`Anomaly::RepeatedDm`].
+pub const REPEATED_DM: usize = 3;
+
+/// Number of the last frames among which FCS errors are counted.
+pub const FCS_WINDOW: usize = 16;
+
+/// Number of FCS errors among the last [`FCS_WINDOW`] frames reported as
+/// [`Anomaly::FcsBurst`].
+pub const FCS_BURST: usize = 4;
+
+/// Longest line kept while looking for a reset banner.
+const MAX_LINE: usize = 16;
+
+/// Pathological pattern of a multiplexer link, found by [`AnomalyDetector`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anomaly {
+    /// A run of flags without any frame between them.
+    ContinuousFlags { count: usize },
+    /// DLCI 0 answered with DM several times in a row.
+    RepeatedDm { count: usize },
+    /// One of the [`RESET_BANNERS`] arrived as raw text in the middle of the stream.
+    ResetBanner { banner: &'static str },
+    /// Many of the last frames have a wrong FCS.
+    FcsBurst { errors: usize, frames: usize },
+}
+
+impl Anomaly {
+    /// Returns the likely cause of the anomaly, and what to check.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cmux::anomaly::Anomaly;
+    ///
+    /// let anomaly = Anomaly::ResetBanner { banner: "RDY" };
+    /// assert!(anomaly.cause().contains("restarted"));
+    /// ```
+    pub const fn cause(&self) -> &'static str {
+        match self {
+            Anomaly::ContinuousFlags { .. } => {
+                "the multiplexer of the modem idles without sending frames: it may be hung, or waiting for SABM on DLCI 0"
+            }
+            Anomaly::RepeatedDm { .. } => {
+                "the modem rejects DLCI 0: it is not in CMUX mode, or expects other AT+CMUX parameters"
+            }
+            Anomaly::ResetBanner { .. } => {
+                "the modem restarted and left CMUX mode: check its power supply, watchdog and firmware"
+            }
+            Anomaly::FcsBurst { .. } => {
+                "the serial line corrupts frames: check the baud rate, the hardware flow control and the cabling"
+            }
+        }
+    }
+}
+
+impl Display for Anomaly {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Anomaly::ContinuousFlags { count } => write!(f, "{} flags in a row", count)?,
+            Anomaly::RepeatedDm { count } => write!(f, "{} DM in a row on DLCI 0", count)?,
+            Anomaly::ResetBanner { banner } => write!(f, "reset banner {} mid-stream", banner)?,
+            Anomaly::FcsBurst { errors, frames } => {
+                write!(f, "{} FCS errors in {} frames", errors, frames)?
+            }
+        }
+        write!(f, ", {}", self.cause())
+    }
+}
+
+/// Heuristics finding the [`Anomaly`] patterns of a multiplexer link
+///
+/// The raw bytes of the link are passed to [`AnomalyDetector::feed`], to find runs of flags
+/// and reset banners, and the decoded frames to [`AnomalyDetector::record`]. Each pattern is
+/// reported once when it reaches its threshold, then again only once it starts over.
+///
+/// # Example
+///
+/// ```
+/// use cmux::anomaly::{Anomaly, AnomalyDetector};
+///
+/// let mut detector = AnomalyDetector::new();
+/// assert_eq!(
+///     detector.feed(b"\xF9\xF9\r\nRDY\r\n"),
+///     [Anomaly::ResetBanner { banner: "RDY" }]
+/// );
+/// assert!(detector.feed(&[0xF9; 63]).is_empty());
+/// assert_eq!(detector.feed(&[0xF9]), [Anomaly::ContinuousFlags { count: 64 }]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnomalyDetector {
+    flag: u8,
+    /// The flags in a row.
+    flags: usize,
+    /// The text since the last line ending, up to [`MAX_LINE`] octets.
+    line: Vec<u8>,
+    /// The DM in a row on DLCI 0.
+    dm: usize,
+    /// Whether each of the last frames has a wrong FCS.
+    fcs: VecDeque<bool>,
+}
+
+impl Default for AnomalyDetector {
+    fn default() -> Self {
+        AnomalyDetector {
+            flag: FLAG,
+            flags: 0,
+            line: Vec::new(),
+            dm: 0,
+            fcs: VecDeque::new(),
+        }
+    }
+}
+
+impl AnomalyDetector {
+    /// Creates a detector for basic option framing.
+    pub fn new() -> Self {
+        AnomalyDetector::default()
+    }
+
+    /// Creates a detector for advanced option framing.
+    pub fn advanced() -> Self {
+        AnomalyDetector {
+            flag: ADVANCED_FLAG,
+            ..AnomalyDetector::default()
+        }
+    }
+
+    /// Looks for runs of flags and reset banners in the next bytes of the link.
+    ///
+    /// # Returns
+    ///
+    /// The anomalies found, in stream order.
+    pub fn feed(&mut self, data: &[u8]) -> Vec<Anomaly> {
+        let mut anomalies = Vec::new();
+        for &octet in data {
+            if octet == self.flag {
+                self.flags += 1;
+                if self.flags == CONTINUOUS_FLAGS {
+                    anomalies.push(Anomaly::ContinuousFlags { count: self.flags });
+                }
+            } else {
+                self.flags = 0;
+            }
+            if octet == b'\r' || octet == b'\n' {
+                let banner = RESET_BANNERS
+                    .iter()
+                    .find(|banner| self.line == banner.as_bytes());
+                if let Some(banner) = banner {
+                    anomalies.push(Anomaly::ResetBanner { banner });
+                }
+                self.line.clear();
+            } else if self.line.len() < MAX_LINE {
+                self.line.push(octet);
+            }
+        }
+        anomalies
+    }
+
+    /// Records a decoded frame, whether its FCS is right or not.
+    ///
+    /// # Returns
+    ///
+    /// The anomaly completed by the frame, if any.
+    pub fn record(&mut self, frame: &Frame) -> Option<Anomaly> {
+        let fcs_error = matches!(frame.verify(), Err(FrameError::ChecksumMismatch { .. }));
+        if frame.address.dlci().into_bits() == 0 {
+            if frame.control.frame_type() == FrameType::DM {
+                self.dm += 1;
+            } else {
+                self.dm = 0;
+            }
+        }
+        let dm = (self.dm == REPEATED_DM).then_some(Anomaly::RepeatedDm { count: self.dm });
+        self.record_fcs(fcs_error).or(dm)
+    }
+
+    /// Records a frame a parser dropped because its FCS is wrong.
+    ///
+    /// # Returns
+    ///
+    /// The burst of FCS errors completed by the frame, if any.
+    pub fn record_fcs_error(&mut self) -> Option<Anomaly> {
+        self.record_fcs(true)
+    }
+
+    fn record_fcs(&mut self, error: bool) -> Option<Anomaly> {
+        if self.fcs.len() == FCS_WINDOW {
+            self.fcs.pop_front();
+        }
+        self.fcs.push_back(error);
+        let errors = self.fcs.iter().filter(|&&error| error).count();
+        if !error || errors < FCS_BURST {
+            return None;
+        }
+        let frames = self.fcs.len();
+        self.fcs.clear();
+        Some(Anomaly::FcsBurst { errors, frames })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames() {
+        let mut detector = AnomalyDetector::new();
+        let dm = Frame::from_bytes(hex::decode("F9031F0136F9").unwrap()).unwrap();
+        assert_eq!(detector.record(&dm), None);
+        assert_eq!(detector.record(&dm), None);
+        assert_eq!(detector.record(&dm), Some(Anomaly::RepeatedDm { count: 3 }));
+        assert_eq!(detector.record(&dm), None);
+
+        let mut corrupted = Frame::from_bytes(hex::decode("F9073F01DEF9").unwrap()).unwrap();
+        corrupted.checksum ^= 0xFF;
+        for _ in 0..FCS_BURST - 1 {
+            assert_eq!(detector.record(&corrupted), None);
+        }
+        assert_eq!(
+            detector.record_fcs_error(),
+            Some(Anomaly::FcsBurst {
+                errors: 4,
+                frames: 8
+            })
+        );
+        assert_eq!(detector.record_fcs_error(), None);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::control::{
+    BaudRate, ControlMessage, ConvergenceLayer, DataBits, FlowControl, MessageType, Msc, Nsc,
+    ParameterMask, ParameterNegotiation, Parity, PnFrameType, PortSettings, RemotePortNegotiation,
+    StopBits, V24Signals,
+};
+use crate::types::{
+    Address, Content, Control, Frame, FrameBuilder, FrameType, DLCI, MAX_CONTENT_LENGTH,
+};
+use ::arbitrary::{Arbitrary, Result, Unstructured};
+
+/// Every [`FrameType`], in the order of its declaration.
+const FRAME_TYPES: [FrameType; 10] = [
+    FrameType::SABM,
+    FrameType::UA,
+    FrameType::DM,
+    FrameType::DISC,
+    FrameType::UIH,
+    FrameType::UI,
+    FrameType::I,
+    FrameType::RR,
+    FrameType::RNR,
+    FrameType::REJ,
+];
+
+/// Takes a 6-bit value, such as a DLCI or a message type.
+fn six_bits(u: &mut Unstructured<'_>) -> Result<u8> {
+    u.int_in_range(0..=0x3F)
+}
+
+impl<'a> Arbitrary<'a> for DLCI {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        six_bits(u).map(DLCI::from_bits)
+    }
+}
+
+/// The EA bit is always set, as in every address of a single octet.
+impl<'a> Arbitrary<'a> for Address {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Address::new()
+            .with_ea(true)
+            .with_cr(u.arbitrary()?)
+            .with_dlci(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for FrameType {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        u.choose(&FRAME_TYPES).copied()
+    }
+}
+
+/// N(S) and N(R) are only drawn for the frame types that carry them.
+impl<'a> Arbitrary<'a> for Control {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut control = Control::new()
+            .with_frame_type(u.arbitrary()?)
+            .with_pf(u.arbitrary()?);
+        if control.ns().is_some() {
+            control = control.with_ns(u.int_in_range(0..=7)?);
+        }
+        if control.nr().is_some() {
+            control = control.with_nr(u.int_in_range(0..=7)?);
+        }
+        Ok(control)
+    }
+}
+
+/// The content is cut to the longest a length indicator can describe.
+impl<'a> Arbitrary<'a> for Content {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut data: Vec<u8> = u.arbitrary()?;
+        data.truncate(MAX_CONTENT_LENGTH);
+        Ok(Content::from(data))
+    }
+}
+
+/// Frames are built with [`FrameBuilder`], so their length indicator and FCS are valid.
+impl<'a> Arbitrary<'a> for Frame {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let frame = FrameBuilder::default()
+            .with_address(u.arbitrary()?)
+            .with_control(u.arbitrary()?)
+            .with_binary_content(Content::arbitrary(u)?.into())
+            .build()
+            .expect("arbitrary content fits in the length indicator");
+        Ok(frame)
+    }
+}
+
+impl<'a> Arbitrary<'a> for MessageType {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        six_bits(u).map(MessageType::from_bits)
+    }
+}
+
+impl<'a> Arbitrary<'a> for ControlMessage {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(ControlMessage::new(
+            u.arbitrary()?,
+            u.arbitrary()?,
+            u.arbitrary()?,
+        ))
+    }
+}
+
+/// The EA bit is left cleared, as [`Msc::decode`] does.
+impl<'a> Arbitrary<'a> for V24Signals {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(V24Signals::from_bits(u.arbitrary()?).with_ea(false))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Msc {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let msc = Msc::new(u.arbitrary()?).with_signals(u.arbitrary()?);
+        Ok(match u.arbitrary::<Option<u8>>()? {
+            Some(length) => msc.with_break(length),
+            None => msc,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Nsc {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Nsc {
+            message_type: u.arbitrary()?,
+            cr: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for PnFrameType {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        u.int_in_range(0..=0x0F).map(PnFrameType::from_bits)
+    }
+}
+
+impl<'a> Arbitrary<'a> for ConvergenceLayer {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        u.int_in_range(0..=0x0F).map(ConvergenceLayer::from_bits)
+    }
+}
+
+impl<'a> Arbitrary<'a> for ParameterNegotiation {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(ParameterNegotiation {
+            dlci: six_bits(u)?,
+            frame_type: u.arbitrary()?,
+            convergence_layer: u.arbitrary()?,
+            priority: six_bits(u)?,
+            t1: u.arbitrary()?,
+            n1: u.arbitrary()?,
+            n2: u.arbitrary()?,
+            k: u.int_in_range(0..=7)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for BaudRate {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        u.arbitrary().map(BaudRate::from_bits)
+    }
+}
+
+impl<'a> Arbitrary<'a> for DataBits {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        u.int_in_range(0..=3).map(DataBits::from_bits)
+    }
+}
+
+impl<'a> Arbitrary<'a> for StopBits {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        u.choose(&[StopBits::One, StopBits::OneAndHalf]).copied()
+    }
+}
+
+impl<'a> Arbitrary<'a> for Parity {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        u.int_in_range(0..=7).map(Parity::from_bits)
+    }
+}
+
+impl<'a> Arbitrary<'a> for FlowControl {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        u.int_in_range(0..=0x3F).map(FlowControl::from_bits)
+    }
+}
+
+impl<'a> Arbitrary<'a> for ParameterMask {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(ParameterMask::from_bits(u.arbitrary::<u16>()? & 0x3F7F))
+    }
+}
+
+impl<'a> Arbitrary<'a> for PortSettings {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(PortSettings {
+            baud_rate: u.arbitrary()?,
+            data_bits: u.arbitrary()?,
+            stop_bits: u.arbitrary()?,
+            parity: u.arbitrary()?,
+            flow_control: u.arbitrary()?,
+            xon: u.arbitrary()?,
+            xoff: u.arbitrary()?,
+            mask: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for RemotePortNegotiation {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let rpn = RemotePortNegotiation::new(u.arbitrary()?);
+        Ok(match u.arbitrary()? {
+            Some(settings) => rpn.with_settings(settings),
+            None => rpn,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Generates `len` pseudo-random bytes with xorshift.
+    fn noise(mut state: u32, len: usize) -> Vec<u8> {
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_frame_roundtrip() {
+        for seed in 1..200 {
+            let data = noise(seed, 256);
+            let mut u = Unstructured::new(&data);
+            let frame = Frame::arbitrary(&mut u).unwrap();
+            assert!(frame.verify().is_ok());
+            assert_eq!(Frame::from_bytes(frame.to_bytes()).unwrap(), frame);
+        }
+    }
+
+    #[test]
+    fn test_control_message_roundtrip() {
+        for seed in 1..200 {
+            let data = noise(seed, 256);
+            let mut u = Unstructured::new(&data);
+            let message = ControlMessage::arbitrary(&mut u).unwrap();
+            assert_eq!(
+                ControlMessage::decode(&message.encode()).unwrap(),
+                (message.clone(), message.encode().len())
+            );
+
+            let msc = Msc::arbitrary(&mut u).unwrap();
+            assert_eq!(Msc::try_from(&msc.to_message(true)).unwrap(), msc);
+            let pn = ParameterNegotiation::arbitrary(&mut u).unwrap();
+            assert_eq!(
+                ParameterNegotiation::try_from(&pn.to_message(true)).unwrap(),
+                pn
+            );
+            let rpn = RemotePortNegotiation::arbitrary(&mut u).unwrap();
+            assert_eq!(
+                RemotePortNegotiation::try_from(&rpn.to_message(true)).unwrap(),
+                rpn
+            );
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use crate::parser::FrameParser;
+use crate::types::Frame;
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_sink::Sink;
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+/// Size of the read buffer of [`AsyncFramed`].
+const BUFFER_SIZE: usize = 4096;
+
+/// Encoded octets buffered by [`AsyncFramed`] before `poll_ready` flushes them.
+const BACKPRESSURE_BOUNDARY: usize = BUFFER_SIZE;
+
+/// Frames read from and written to a `futures-io` byte stream
+///
+/// This is the runtime-agnostic counterpart of `FrameCodec` with Tokio's `Framed`: it works
+/// over any `AsyncRead` and `AsyncWrite` of the `futures` crate, such as the sockets of
+/// async-std and smol. The stream half yields the frames decoded by a [`FrameParser`], skipping corrupted
+/// bytes, and ends with the byte stream. The sink half buffers the encoded frames until it is
+/// flushed.
+///
+/// # Example
+///
+/// ```
+/// use cmux::async_framed::AsyncFramed;
+/// use cmux::types::Frame;
+/// use futures::io::Cursor;
+/// use futures::{executor, SinkExt, StreamExt};
+///
+/// executor::block_on(async {
+///     let frame = Frame::from_bytes(vec![0xF9, 0x03, 0x3F, 0x01, 0x1C, 0xF9]).unwrap();
+///     let mut framed = AsyncFramed::new(Cursor::new(Vec::new()));
+///     framed.send(frame.clone()).await.unwrap();
+///
+///     let data = framed.into_inner().into_inner();
+///     let mut framed = AsyncFramed::new(Cursor::new(data));
+///     assert_eq!(framed.next().await.unwrap().unwrap(), frame);
+///     assert!(framed.next().await.is_none());
+/// });
+/// ```
+#[derive(Debug)]
+pub struct AsyncFramed<T> {
+    io: T,
+    parser: FrameParser,
+    frames: VecDeque<Frame>,
+    read_buf: Vec<u8>,
+    write_buf: Vec<u8>,
+    eof: bool,
+}
+
+impl<T> AsyncFramed<T> {
+    /// Wraps `io`, carrying basic option frames.
+    pub fn new(io: T) -> Self {
+        AsyncFramed::with_parser(io, FrameParser::new())
+    }
+
+    /// Wraps `io`, carrying advanced option frames.
+    pub fn advanced(io: T) -> Self {
+        AsyncFramed::with_parser(io, FrameParser::advanced())
+    }
+
+    /// Wraps `io`, decoding the frames read with `parser`.
+    pub fn with_parser(io: T, parser: FrameParser) -> Self {
+        AsyncFramed {
+            io,
+            parser,
+            frames: VecDeque::new(),
+            read_buf: vec![0; BUFFER_SIZE],
+            write_buf: Vec::new(),
+            eof: false,
+        }
+    }
+
+    /// Returns the number of bytes dropped because they did not belong to a frame.
+    pub fn skipped(&self) -> usize {
+        self.parser.skipped()
+    }
+
+    /// Returns the wrapped byte stream.
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    /// Returns the wrapped byte stream. Reading or writing it directly corrupts the framing.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    /// Returns the wrapped byte stream, dropping the frames buffered in either direction.
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncFramed<T> {
+    /// Writes the buffered frames to the byte stream.
+    fn poll_write_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.write_buf.is_empty() {
+            let n = ready!(Pin::new(&mut self.io).poll_write(cx, &self.write_buf))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.write_buf.drain(..n);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncRead + Unpin> Stream for AsyncFramed<T> {
+    type Item = io::Result<Frame>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(frame) = this.frames.pop_front() {
+                return Poll::Ready(Some(Ok(frame)));
+            }
+            if this.eof {
+                return Poll::Ready(None);
+            }
+            match ready!(Pin::new(&mut this.io).poll_read(cx, &mut this.read_buf)) {
+                Ok(0) => this.eof = true,
+                Ok(n) => {
+                    let frames = this.parser.feed(&this.read_buf[..n]);
+                    this.frames.extend(frames);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+        }
+    }
+}
+
+impl<T: AsyncWrite + Unpin> Sink<Frame> for AsyncFramed<T> {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.write_buf.len() >= BACKPRESSURE_BOUNDARY {
+            ready!(this.poll_write_buf(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, frame: Frame) -> io::Result<()> {
+        let this = self.get_mut();
+        if this.parser.is_advanced() {
+            this.write_buf.extend(frame.to_bytes_advanced());
+        } else {
+            this.write_buf.extend(frame.to_bytes());
+        }
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_buf(cx))?;
+        Pin::new(&mut this.io).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_buf(cx))?;
+        Pin::new(&mut this.io).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FrameBuilder;
+    use futures::io::Cursor;
+    use futures::{executor, SinkExt, StreamExt};
+
+    #[test]
+    fn test_advanced_round_trip() {
+        executor::block_on(async {
+            let frames: Vec<Frame> = (0..3)
+                .map(|i| {
+                    FrameBuilder::default()
+                        .with_binary_content(vec![0x7E; BUFFER_SIZE * i])
+                        .build()
+                        .unwrap()
+                })
+                .collect();
+            let mut framed = AsyncFramed::advanced(Cursor::new(Vec::new()));
+            for frame in &frames {
+                framed.feed(frame.clone()).await.unwrap();
+            }
+            framed.close().await.unwrap();
+
+            let mut data = vec![0x00];
+            data.extend(framed.into_inner().into_inner());
+            let framed = AsyncFramed::advanced(Cursor::new(data));
+            let read: Vec<Frame> = framed.map(Result::unwrap).collect().await;
+            assert_eq!(read, frames);
+        });
+    }
+}
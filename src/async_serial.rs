@@ -0,0 +1,157 @@
+use crate::control::V24Signals;
+use crate::mux::LineStatus;
+use crate::serial::{self, SerialSettings};
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_serial::{SerialPort, SerialPortBuilderExt, SerialStream};
+
+/// Opens the serial port at `path` with `settings`, registered with the tokio reactor of the
+/// current runtime.
+pub fn open(path: &str, settings: &SerialSettings) -> io::Result<SerialStream> {
+    let port = tokio_serial::new(path, settings.baud)
+        .data_bits(settings.data_bits)
+        .parity(settings.parity)
+        .stop_bits(settings.stop_bits)
+        .flow_control(settings.flow_control)
+        .open_native_async()?;
+    Ok(port)
+}
+
+/// Sends an AT command switching the modem to multiplexer mode and waits for its result code,
+/// like [`serial::enter_cmux`].
+///
+/// # Returns
+///
+/// - `Ok(())`: If the modem answered `OK`.
+/// - `Err(io::Error)`: If it answered `ERROR`, did not answer in time, or the port failed.
+pub async fn enter_cmux<P: AsyncRead + AsyncWrite + Unpin>(
+    port: &mut P,
+    command: &str,
+    timeout: Duration,
+) -> io::Result<()> {
+    port.write_all(format!("{}\r", command).as_bytes()).await?;
+    port.flush().await?;
+    let exchange = async {
+        let mut response = Vec::new();
+        let mut buf = [0; 64];
+        loop {
+            let count = port.read(&mut buf).await?;
+            if count == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            response.extend_from_slice(&buf[..count]);
+            if let Some(result) = serial::cmux_result(command, &response) {
+                return result;
+            }
+        }
+    };
+    tokio::time::timeout(timeout, exchange)
+        .await
+        .unwrap_or_else(|_| Err(serial::no_response(command)))
+}
+
+/// Reads the control lines driven by the modem and the breaks detected on `port`, for
+/// [`Driver::with_line_watch`](crate::channel::Driver::with_line_watch).
+///
+/// DSR, CTS, RI and DCD are returned as RTC, RTR, IC and DV. Breaks are only counted on
+/// Linux, by serial drivers keeping interrupt counters, and are 0 elsewhere.
+///
+/// # Example
+///
+/// ```no_run
+/// use cmux::async_serial;
+/// use cmux::channel;
+/// use cmux::mux::Mux;
+/// use cmux::serial::SerialSettings;
+/// use std::time::Duration;
+///
+/// # tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(async {
+/// let port = async_serial::open("/dev/ttyUSB0", &SerialSettings::default()).unwrap();
+/// let (mux, driver) = channel::new(port, Mux::new());
+/// let driver = driver.with_line_watch(Duration::from_millis(100), async_serial::line_status);
+/// # });
+/// ```
+pub fn line_status(port: &mut SerialStream) -> io::Result<LineStatus> {
+    let signals = V24Signals::new()
+        .with_rtc(port.read_data_set_ready()?)
+        .with_rtr(port.read_clear_to_send()?)
+        .with_ic(port.read_ring_indicator()?)
+        .with_dv(port.read_carrier_detect()?);
+    Ok(LineStatus {
+        signals,
+        breaks: breaks(port),
+    })
+}
+
+/// Returns the number of breaks counted by the serial driver, or 0 if it does not count them.
+#[cfg(target_os = "linux")]
+fn breaks(port: &SerialStream) -> u32 {
+    use nix::libc::{c_int, ioctl, TIOCGICOUNT};
+    use std::os::unix::io::AsRawFd;
+
+    /// `struct serial_icounter_struct` of `<linux/serial.h>`.
+    #[repr(C)]
+    #[derive(Default)]
+    struct Counters {
+        cts: c_int,
+        dsr: c_int,
+        rng: c_int,
+        dcd: c_int,
+        rx: c_int,
+        tx: c_int,
+        frame: c_int,
+        overrun: c_int,
+        parity: c_int,
+        brk: c_int,
+        buf_overrun: c_int,
+        reserved: [c_int; 9],
+    }
+
+    let mut counters = Counters::default();
+    // SAFETY: TIOCGICOUNT fills a serial_icounter_struct, which `Counters` has the layout of.
+    match unsafe { ioctl(port.as_raw_fd(), TIOCGICOUNT, &mut counters) } {
+        0 => counters.brk as u32,
+        _ => 0,
+    }
+}
+
+/// Returns 0, as breaks are only counted on Linux.
+#[cfg(not(target_os = "linux"))]
+fn breaks(_port: &SerialStream) -> u32 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serial::AT_CMUX;
+
+    #[tokio::test]
+    async fn test_enter_cmux() {
+        let (mut host, mut modem) = tokio::io::duplex(64);
+        let answer = async {
+            let mut buf = [0; 10];
+            modem.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"AT+CMUX=0\r");
+            modem.write_all(b"AT+CMUX=0\r\r\nOK\r\n").await.unwrap();
+            modem
+        };
+        let (result, mut modem) = tokio::join!(
+            enter_cmux(&mut host, AT_CMUX, Duration::from_millis(100)),
+            answer
+        );
+        result.unwrap();
+
+        let e = enter_cmux(&mut host, AT_CMUX, Duration::from_millis(10))
+            .await
+            .unwrap_err();
+        assert_eq!(e.to_string(), "no response to AT+CMUX=0");
+
+        modem.write_all(b"\r\nERROR\r\n").await.unwrap();
+        let e = enter_cmux(&mut host, AT_CMUX, Duration::from_millis(100))
+            .await
+            .unwrap_err();
+        assert_eq!(e.to_string(), "AT+CMUX=0 failed: ERROR");
+    }
+}
@@ -0,0 +1,755 @@
+use crate::control::{ParameterNegotiation, PnFrameType};
+use crate::error::CmuxParamsError;
+use crate::mux::{MuxConfig, MuxEvent, DEFAULT_N2, DEFAULT_T1, DEFAULT_T2, DEFAULT_T3};
+use crate::transport::{Link, Transport};
+use std::fmt::{Display, Formatter};
+use std::io::{self, ErrorKind};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Default time to wait for the final result code of a command.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Final result code ending the response to an AT command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResultCode {
+    Ok,
+    Error,
+    /// `+CME ERROR: <err>`, an error of the mobile equipment.
+    CmeError(String),
+    /// `+CMS ERROR: <err>`, an error of the message service.
+    CmsError(String),
+    /// `CONNECT`, followed by the connection speed if any.
+    Connect(Option<String>),
+    NoCarrier,
+    Busy,
+    NoAnswer,
+    NoDialtone,
+}
+
+impl ResultCode {
+    /// Classifies a response line, returning `None` if it is not a final result code.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cmux::at::ResultCode;
+    ///
+    /// assert_eq!(ResultCode::parse("OK"), Some(ResultCode::Ok));
+    /// assert_eq!(
+    ///     ResultCode::parse("+CME ERROR: 10"),
+    ///     Some(ResultCode::CmeError("10".to_string()))
+    /// );
+    /// assert_eq!(ResultCode::parse("+CSQ: 20,99"), None);
+    /// ```
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if let Some(err) = line.strip_prefix("+CME ERROR:") {
+            return Some(ResultCode::CmeError(err.trim().to_string()));
+        }
+        if let Some(err) = line.strip_prefix("+CMS ERROR:") {
+            return Some(ResultCode::CmsError(err.trim().to_string()));
+        }
+        if let Some(speed) = line.strip_prefix("CONNECT") {
+            let speed = speed.trim();
+            return Some(ResultCode::Connect(
+                (!speed.is_empty()).then(|| speed.to_string()),
+            ));
+        }
+        match line {
+            "OK" => Some(ResultCode::Ok),
+            "ERROR" => Some(ResultCode::Error),
+            "NO CARRIER" => Some(ResultCode::NoCarrier),
+            "BUSY" => Some(ResultCode::Busy),
+            "NO ANSWER" => Some(ResultCode::NoAnswer),
+            "NO DIALTONE" => Some(ResultCode::NoDialtone),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the command succeeded, with `OK` or `CONNECT`.
+    pub fn is_ok(&self) -> bool {
+        matches!(self, ResultCode::Ok | ResultCode::Connect(_))
+    }
+}
+
+impl Display for ResultCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResultCode::Ok => write!(f, "OK"),
+            ResultCode::Error => write!(f, "ERROR"),
+            ResultCode::CmeError(err) => write!(f, "+CME ERROR: {}", err),
+            ResultCode::CmsError(err) => write!(f, "+CMS ERROR: {}", err),
+            ResultCode::Connect(None) => write!(f, "CONNECT"),
+            ResultCode::Connect(Some(speed)) => write!(f, "CONNECT {}", speed),
+            ResultCode::NoCarrier => write!(f, "NO CARRIER"),
+            ResultCode::Busy => write!(f, "BUSY"),
+            ResultCode::NoAnswer => write!(f, "NO ANSWER"),
+            ResultCode::NoDialtone => write!(f, "NO DIALTONE"),
+        }
+    }
+}
+
+/// Response to an AT command: the information lines and the final result code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub lines: Vec<String>,
+    pub result: ResultCode,
+}
+
+/// Collects the response to one AT command from the data received on its DLC
+///
+/// The data is split into lines on CR and LF, empty lines are skipped, and the first line is
+/// dropped if it echoes the command. The response ends with the first final result code.
+///
+/// # Example
+///
+/// ```
+/// use cmux::at::{ResponseReader, ResultCode};
+///
+/// let mut reader = ResponseReader::new("AT+CSQ");
+/// assert_eq!(reader.feed(b"AT+CSQ\r\r\n+CSQ: 20,99\r\n"), None);
+/// let response = reader.feed(b"\r\nOK\r\n").unwrap();
+/// assert_eq!(response.lines, ["+CSQ: 20,99"]);
+/// assert_eq!(response.result, ResultCode::Ok);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ResponseReader {
+    command: String,
+    buffer: Vec<u8>,
+    lines: Vec<String>,
+    /// Whether a line was received, after which no echo is expected.
+    started: bool,
+}
+
+impl ResponseReader {
+    /// Creates a reader for the response to `command`, given without the trailing CR.
+    pub fn new(command: &str) -> Self {
+        ResponseReader {
+            command: command.trim().to_string(),
+            buffer: Vec::new(),
+            lines: Vec::new(),
+            started: false,
+        }
+    }
+
+    /// Adds received data.
+    ///
+    /// # Returns
+    ///
+    /// - `Some(Response)`: When a final result code was received. Data after it is dropped.
+    /// - `None`: If the response is not complete yet.
+    pub fn feed(&mut self, data: &[u8]) -> Option<Response> {
+        self.buffer.extend_from_slice(data);
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\r' || b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line).trim().to_string();
+            if line.is_empty() {
+                continue;
+            }
+            let echo = !self.started && line.eq_ignore_ascii_case(&self.command);
+            self.started = true;
+            if echo {
+                continue;
+            }
+            if let Some(result) = ResultCode::parse(&line) {
+                self.buffer.clear();
+                return Some(Response {
+                    lines: std::mem::take(&mut self.lines),
+                    result,
+                });
+            }
+            self.lines.push(line);
+        }
+        None
+    }
+}
+
+/// Multiplexer mode of `AT+CMUX`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum CmuxMode {
+    /// Basic option, with 0xF9 flags.
+    #[default]
+    Basic,
+    /// Advanced option, with 0x7E flags and transparency.
+    Advanced,
+}
+
+/// Port speed code of `AT+CMUX`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortSpeed {
+    B9600,
+    B19200,
+    B38400,
+    B57600,
+    B115200,
+    B230400,
+    /// A code beyond 27.010, such as the 460800 or 921600 bit/s of some modems.
+    Other(u8),
+}
+
+impl PortSpeed {
+    /// Returns the speed code.
+    pub const fn into_bits(self) -> u8 {
+        match self {
+            PortSpeed::B9600 => 1,
+            PortSpeed::B19200 => 2,
+            PortSpeed::B38400 => 3,
+            PortSpeed::B57600 => 4,
+            PortSpeed::B115200 => 5,
+            PortSpeed::B230400 => 6,
+            PortSpeed::Other(value) => value,
+        }
+    }
+
+    /// Creates a port speed from its code.
+    pub const fn from_bits(value: u8) -> Self {
+        match value {
+            1 => PortSpeed::B9600,
+            2 => PortSpeed::B19200,
+            3 => PortSpeed::B38400,
+            4 => PortSpeed::B57600,
+            5 => PortSpeed::B115200,
+            6 => PortSpeed::B230400,
+            value => PortSpeed::Other(value),
+        }
+    }
+
+    /// Returns the speed in bit/s, or `None` for a code beyond 27.010.
+    pub const fn bps(self) -> Option<u32> {
+        match self {
+            PortSpeed::B9600 => Some(9600),
+            PortSpeed::B19200 => Some(19200),
+            PortSpeed::B38400 => Some(38400),
+            PortSpeed::B57600 => Some(57600),
+            PortSpeed::B115200 => Some(115200),
+            PortSpeed::B230400 => Some(230400),
+            PortSpeed::Other(_) => None,
+        }
+    }
+
+    /// Returns the port speed of `bps` bit/s, or `None` if 27.010 has no code for it.
+    pub const fn from_bps(bps: u32) -> Option<Self> {
+        match bps {
+            9600 => Some(PortSpeed::B9600),
+            19200 => Some(PortSpeed::B19200),
+            38400 => Some(PortSpeed::B38400),
+            57600 => Some(PortSpeed::B57600),
+            115200 => Some(PortSpeed::B115200),
+            230400 => Some(PortSpeed::B230400),
+            _ => None,
+        }
+    }
+}
+
+/// Parameters of the `AT+CMUX` command of 27.010 switching a modem to multiplexer mode
+///
+/// `AT+CMUX=<mode>[,<subset>[,<port_speed>[,<N1>[,<T1>[,<N2>[,<T2>[,<T3>[,<k>]]]]]]]]`
+///
+/// | **Parameter** | Range           | Default       | Unit  |
+/// |---------------|-----------------|---------------|-------|
+/// | mode          | 0 to 1          |               |       |
+/// | subset        | 0 to 2          | 0 (UIH)       |       |
+/// | port_speed    | 1 to 6          |               |       |
+/// | N1            | 1 to 32768      | 31 or 64      | octet |
+/// | T1            | 1 to 255        | 10            | 10 ms |
+/// | N2            | 0 to 100        | 3             |       |
+/// | T2            | 2 to 255        | 30            | 10 ms |
+/// | T3            | 1 to 255        | 10            | s     |
+/// | k             | 1 to 7          | 2             |       |
+///
+/// Parameters left to `None` are omitted, so the modem uses its defaults.
+///
+/// # Example
+///
+/// ```
+/// use cmux::at::{CmuxMode, CmuxParams, PortSpeed};
+///
+/// let params = CmuxParams::new(CmuxMode::Basic)
+///     .with_port_speed(PortSpeed::B115200)
+///     .with_n1(127);
+/// assert_eq!(params.to_string(), "AT+CMUX=0,,5,127");
+/// assert_eq!("AT+CMUX=0,,5,127".parse(), Ok(params));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CmuxParams {
+    pub mode: CmuxMode,
+    /// Frame type of the DLCs: UIH, UI or I.
+    pub subset: Option<PnFrameType>,
+    pub port_speed: Option<PortSpeed>,
+    pub n1: Option<u16>,
+    /// Acknowledgement timer, in units of 10 ms.
+    pub t1: Option<u8>,
+    pub n2: Option<u8>,
+    /// Response timer of DLCI 0, in units of 10 ms.
+    pub t2: Option<u8>,
+    /// Wake-up response timer, in seconds.
+    pub t3: Option<u8>,
+    /// Window size of error recovery mode.
+    pub k: Option<u8>,
+}
+
+impl CmuxParams {
+    /// Number of parameters of `AT+CMUX`.
+    const COUNT: usize = 9;
+
+    /// Names and ranges of the parameters, in order.
+    const RANGES: [(&'static str, RangeInclusive<u32>); Self::COUNT] = [
+        ("mode", 0..=1),
+        ("subset", 0..=2),
+        ("port_speed", 1..=255),
+        ("N1", 1..=32768),
+        ("T1", 1..=255),
+        ("N2", 0..=100),
+        ("T2", 2..=255),
+        ("T3", 1..=255),
+        ("k", 1..=7),
+    ];
+
+    /// Creates the parameters selecting `mode`, leaving the others to the modem.
+    pub const fn new(mode: CmuxMode) -> Self {
+        CmuxParams {
+            mode,
+            subset: None,
+            port_speed: None,
+            n1: None,
+            t1: None,
+            n2: None,
+            t2: None,
+            t3: None,
+            k: None,
+        }
+    }
+
+    pub const fn with_subset(mut self, subset: PnFrameType) -> Self {
+        self.subset = Some(subset);
+        self
+    }
+
+    pub const fn with_port_speed(mut self, port_speed: PortSpeed) -> Self {
+        self.port_speed = Some(port_speed);
+        self
+    }
+
+    pub const fn with_n1(mut self, n1: u16) -> Self {
+        self.n1 = Some(n1);
+        self
+    }
+
+    pub const fn with_t1(mut self, t1: u8) -> Self {
+        self.t1 = Some(t1);
+        self
+    }
+
+    pub const fn with_n2(mut self, n2: u8) -> Self {
+        self.n2 = Some(n2);
+        self
+    }
+
+    pub const fn with_t2(mut self, t2: u8) -> Self {
+        self.t2 = Some(t2);
+        self
+    }
+
+    pub const fn with_t3(mut self, t3: u8) -> Self {
+        self.t3 = Some(t3);
+        self
+    }
+
+    pub const fn with_k(mut self, k: u8) -> Self {
+        self.k = Some(k);
+        self
+    }
+
+    /// Returns the timers and retry counter of the engine matching these parameters, with the
+    /// defaults of 07.10 for the omitted ones.
+    pub fn to_config(&self) -> MuxConfig {
+        MuxConfig {
+            t1: self
+                .t1
+                .map_or(DEFAULT_T1, |t1| Duration::from_millis(u64::from(t1) * 10)),
+            t2: self
+                .t2
+                .map_or(DEFAULT_T2, |t2| Duration::from_millis(u64::from(t2) * 10)),
+            t3: self
+                .t3
+                .map_or(DEFAULT_T3, |t3| Duration::from_secs(u64::from(t3))),
+            n2: self.n2.unwrap_or(DEFAULT_N2),
+            ..MuxConfig::default()
+        }
+    }
+
+    /// Returns the parameters of `dlci` matching these parameters, with the defaults of
+    /// [`ParameterNegotiation::new`] for the omitted ones.
+    pub fn parameters(&self, dlci: u8) -> ParameterNegotiation {
+        let defaults = ParameterNegotiation::new(dlci);
+        ParameterNegotiation {
+            frame_type: self.subset.unwrap_or(defaults.frame_type),
+            t1: self.t1.unwrap_or(defaults.t1),
+            n1: self.n1.unwrap_or(defaults.n1),
+            n2: self.n2.unwrap_or(defaults.n2),
+            k: self.k.unwrap_or(defaults.k),
+            ..defaults
+        }
+    }
+
+    /// Returns the values of the parameters, in order.
+    fn values(&self) -> [Option<u32>; Self::COUNT] {
+        [
+            Some(match self.mode {
+                CmuxMode::Basic => 0,
+                CmuxMode::Advanced => 1,
+            }),
+            self.subset.map(|subset| subset.into_bits().into()),
+            self.port_speed.map(|speed| speed.into_bits().into()),
+            self.n1.map(u32::from),
+            self.t1.map(u32::from),
+            self.n2.map(u32::from),
+            self.t2.map(u32::from),
+            self.t3.map(u32::from),
+            self.k.map(u32::from),
+        ]
+    }
+}
+
+impl Display for CmuxParams {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let values = self.values();
+        let count = values
+            .iter()
+            .rposition(Option::is_some)
+            .map_or(0, |i| i + 1);
+        let values: Vec<String> = values[..count]
+            .iter()
+            .map(|value| value.map(|value| value.to_string()).unwrap_or_default())
+            .collect();
+        write!(f, "AT+CMUX={}", values.join(","))
+    }
+}
+
+impl FromStr for CmuxParams {
+    type Err = CmuxParamsError;
+
+    /// Parses an `AT+CMUX=` command, ignoring case and blanks around the parameters, and
+    /// checking their ranges.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let prefix = "AT+CMUX=";
+        if !s
+            .get(..prefix.len())
+            .is_some_and(|p| p.eq_ignore_ascii_case(prefix))
+        {
+            return Err(CmuxParamsError::NotCmux);
+        }
+        let fields: Vec<&str> = s[prefix.len()..].split(',').map(str::trim).collect();
+        if fields.len() > Self::COUNT {
+            return Err(CmuxParamsError::TooManyParameters);
+        }
+        let mut values = [None; Self::COUNT];
+        for (i, field) in fields.into_iter().enumerate() {
+            if field.is_empty() {
+                continue;
+            }
+            let (name, range) = &Self::RANGES[i];
+            let invalid = CmuxParamsError::InvalidParameter { name };
+            let value: u32 = field.parse().map_err(|_| invalid.clone())?;
+            if !range.contains(&value) {
+                return Err(invalid);
+            }
+            values[i] = Some(value);
+        }
+        // The ranges checked above fit the fields.
+        let octet = |i: usize| values[i].map(|value: u32| value as u8);
+        Ok(CmuxParams {
+            mode: match values[0] {
+                Some(0) => CmuxMode::Basic,
+                Some(_) => CmuxMode::Advanced,
+                None => return Err(CmuxParamsError::InvalidParameter { name: "mode" }),
+            },
+            subset: octet(1).map(PnFrameType::from_bits),
+            port_speed: octet(2).map(PortSpeed::from_bits),
+            n1: values[3].map(|value| value as u16),
+            t1: octet(4),
+            n2: octet(5),
+            t2: octet(6),
+            t3: octet(7),
+            k: octet(8),
+        })
+    }
+}
+
+/// State of an AT channel, as seen by [`UrcFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum ChannelState {
+    /// No command is running: every line is unsolicited.
+    #[default]
+    Idle,
+    /// A command was sent and its final result code is awaited.
+    Command,
+    /// `CONNECT` switched the channel to online data mode.
+    Online,
+}
+
+/// Separates the unsolicited result codes (URCs) received on an AT channel from the responses
+/// to its commands
+///
+/// After [`UrcFilter::begin`], data is passed through as the response up to and including
+/// the final result code. Outside of a command, every line, such as `RING`, `+CMTI` or
+/// `+CREG`, is a URC. After `CONNECT`, the channel is in online data mode and all data is
+/// passed through until the next command.
+///
+/// # Example
+///
+/// ```
+/// use cmux::at::UrcFilter;
+///
+/// let mut filter = UrcFilter::new();
+/// let (data, urcs) = filter.feed(b"\r\nRING\r\n");
+/// assert!(data.is_empty());
+/// assert_eq!(urcs, ["RING"]);
+///
+/// filter.begin();
+/// let (data, urcs) = filter.feed(b"\r\nOK\r\n\r\n+CREG: 1\r\n");
+/// assert_eq!(data, b"\r\nOK\r");
+/// assert_eq!(urcs, ["+CREG: 1"]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct UrcFilter {
+    state: ChannelState,
+    line: Vec<u8>,
+}
+
+impl UrcFilter {
+    /// Creates a filter for a channel with no running command.
+    pub fn new() -> Self {
+        UrcFilter::default()
+    }
+
+    /// Starts a command, dropping the partial line received before it.
+    pub fn begin(&mut self) {
+        self.state = ChannelState::Command;
+        self.line.clear();
+    }
+
+    /// Returns true if a command awaits its final result code.
+    pub fn is_pending(&self) -> bool {
+        self.state == ChannelState::Command
+    }
+
+    /// Splits received data into the response to the running command and the URCs.
+    ///
+    /// # Returns
+    ///
+    /// The data of the response or of online data mode, and the complete URC lines. The
+    /// partial line at the end of `data` is held until the next call.
+    pub fn feed(&mut self, data: &[u8]) -> (Vec<u8>, Vec<String>) {
+        if self.state == ChannelState::Online {
+            return (data.to_vec(), Vec::new());
+        }
+        let mut response = Vec::new();
+        let mut urcs = Vec::new();
+        for (i, &byte) in data.iter().enumerate() {
+            if self.state == ChannelState::Online {
+                response.extend_from_slice(&data[i..]);
+                break;
+            }
+            if self.state == ChannelState::Command {
+                response.push(byte);
+            }
+            if byte != b'\r' && byte != b'\n' {
+                self.line.push(byte);
+                continue;
+            }
+            let line = String::from_utf8_lossy(&self.line).trim().to_string();
+            self.line.clear();
+            if line.is_empty() {
+                continue;
+            }
+            if self.state == ChannelState::Idle {
+                urcs.push(line);
+                continue;
+            }
+            match ResultCode::parse(&line) {
+                Some(ResultCode::Connect(_)) => self.state = ChannelState::Online,
+                Some(_) => self.state = ChannelState::Idle,
+                None => {}
+            }
+        }
+        (response, urcs)
+    }
+}
+
+/// Unsolicited result codes modems send in command mode once they have started, such as
+/// Quectel `RDY`, SIMCom `+PBREADY` and Cinterion `^SYSSTART`
+///
+/// Received outside of frames, they tell that the modem was reset and left CMUX mode.
+pub const RESET_BANNERS: [&str; 3] = ["RDY", "+PBREADY", "^SYSSTART"];
+
+/// Returns true if `data` holds one of the [`RESET_BANNERS`] on a line of its own.
+///
+/// # Example
+///
+/// ```
+/// use cmux::at::is_reset_banner;
+///
+/// assert!(is_reset_banner(b"\r\nRDY\r\n"));
+/// assert!(!is_reset_banner(b"\r\nREADY\r\n"));
+/// ```
+pub fn is_reset_banner(data: &[u8]) -> bool {
+    data.split(|&c| c == b'\r' || c == b'\n')
+        .any(|line| RESET_BANNERS.iter().any(|banner| line == banner.as_bytes()))
+}
+
+/// Sends `command` on a connected `dlci` of `link` and waits for its final result code.
+///
+/// The command is sent with [`Mux::send_command`](crate::mux::Mux::send_command), so the URCs of
+/// an AT channel set with [`Mux::set_at_channel`](crate::mux::Mux::set_at_channel) are not taken
+/// for the response. Events raised meanwhile other than the data of `dlci` are dropped.
+///
+/// # Arguments
+///
+/// * `link` - The multiplexer link.
+/// * `dlci` - The DLCI of an AT channel.
+/// * `command` - The command without the trailing CR. Example: "AT+CSQ"
+/// * `timeout` - How long to wait for the final result code.
+///
+/// # Returns
+///
+/// - `Ok(Response)`: The response, whether the result code is `OK` or an error.
+/// - `Err(io::Error)`: If the DLC is not connected, no result code arrived in time, or the
+///   transport failed.
+pub fn command<T: Transport>(
+    link: &mut Link<T>,
+    dlci: u8,
+    command: &str,
+    timeout: Duration,
+) -> io::Result<Response> {
+    link.mux_mut()
+        .send_command(dlci, command)
+        .map_err(|e| io::Error::new(ErrorKind::NotConnected, e))?;
+    link.flush()?;
+    let mut reader = ResponseReader::new(command);
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        link.poll(Instant::now())?;
+        while let Some(event) = link.mux_mut().poll_event() {
+            let MuxEvent::Data { dlci: from, data } = event else {
+                continue;
+            };
+            if from != dlci {
+                continue;
+            }
+            if let Some(response) = reader.feed(&data) {
+                return Ok(response);
+            }
+        }
+    }
+    Err(io::Error::new(
+        ErrorKind::TimedOut,
+        format!("no response to {}", command),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mux::Mux;
+    use crate::transport::MemoryTransport;
+
+    #[test]
+    fn test_result_codes() {
+        let mut reader = ResponseReader::new("ATD*99#");
+        assert_eq!(
+            reader.feed(b"\r\nCONNECT 150000000\r\n"),
+            Some(Response {
+                lines: Vec::new(),
+                result: ResultCode::Connect(Some("150000000".to_string()))
+            })
+        );
+        let mut reader = ResponseReader::new("AT+CMGS=1");
+        let response = reader.feed(b"\r\n+CMS ERROR: 500\r\n").unwrap();
+        assert_eq!(response.result.to_string(), "+CMS ERROR: 500");
+        assert!(!response.result.is_ok());
+        // A line repeating the command after the first one is not an echo.
+        let mut reader = ResponseReader::new("ATI");
+        let response = reader.feed(b"ATI\rQuectel\rATI\r\nOK\r\n").unwrap();
+        assert_eq!(response.lines, ["Quectel", "ATI"]);
+    }
+
+    #[test]
+    fn test_cmux_params() {
+        let params: CmuxParams = "at+cmux=1, 0, 7, 1500, 20, 5, 50, 20, 3\r".parse().unwrap();
+        assert_eq!(params.mode, CmuxMode::Advanced);
+        assert_eq!(params.port_speed, Some(PortSpeed::Other(7)));
+        assert_eq!(params.to_string(), "AT+CMUX=1,0,7,1500,20,5,50,20,3");
+        assert_eq!(params.to_config().t2, Duration::from_millis(500));
+        assert_eq!(params.parameters(1).n1, 1500);
+        assert_eq!(CmuxParams::default().to_string(), "AT+CMUX=0");
+
+        assert_eq!(
+            "AT+CMUX=0,,,,0".parse::<CmuxParams>(),
+            Err(CmuxParamsError::InvalidParameter { name: "T1" })
+        );
+        assert_eq!(
+            "AT+CMUX=,0".parse::<CmuxParams>(),
+            Err(CmuxParamsError::InvalidParameter { name: "mode" })
+        );
+        assert_eq!(
+            "AT+CMUX?".parse::<CmuxParams>(),
+            Err(CmuxParamsError::NotCmux)
+        );
+        assert_eq!(
+            "AT+CMUX=0,0,5,31,10,3,30,10,2,0".parse::<CmuxParams>(),
+            Err(CmuxParamsError::TooManyParameters)
+        );
+    }
+
+    #[test]
+    fn test_urc_filter() {
+        let mut filter = UrcFilter::new();
+        assert_eq!(filter.feed(b"\r\n+CMTI: \"SM\","), (Vec::new(), Vec::new()));
+        let (_, urcs) = filter.feed(b"3\r\n");
+        assert_eq!(urcs, ["+CMTI: \"SM\",3"]);
+
+        // The prompt of AT+CMGS has no line terminator but is passed through.
+        filter.begin();
+        assert_eq!(filter.feed(b"\r\n> ").0, b"\r\n> ");
+        assert!(filter.is_pending());
+        filter.feed(b"\r\n+CMGS: 4\r\n\r\nOK\r\n");
+        assert!(!filter.is_pending());
+
+        filter.begin();
+        let (data, urcs) = filter.feed(b"\r\nCONNECT\r\n~\x7e\r\n");
+        assert_eq!(data, b"\r\nCONNECT\r\n~\x7e\r\n");
+        assert!(urcs.is_empty());
+    }
+
+    #[test]
+    fn test_command_over_link() {
+        let now = Instant::now();
+        let (a, b) = MemoryTransport::pair();
+        let mut host = Link::new(Mux::new(), a);
+        let mut modem = Link::new(Mux::new().with_initiator(false), b);
+        host.mux_mut().open(0, now).unwrap();
+        host.mux_mut().open(1, now).unwrap();
+        host.flush().unwrap();
+        modem.poll(now).unwrap();
+        host.poll(now).unwrap();
+
+        // The modem answers ahead of time; the response waits in the transport.
+        modem
+            .mux_mut()
+            .send(1, b"AT+CSQ\r\r\n+CSQ: 20,99\r\n\r\nOK\r\n")
+            .unwrap();
+        modem.flush().unwrap();
+        let response = command(&mut host, 1, "AT+CSQ", Duration::from_millis(100)).unwrap();
+        assert_eq!(response.lines, ["+CSQ: 20,99"]);
+        assert_eq!(response.result, ResultCode::Ok);
+
+        let e = command(&mut host, 2, "AT", Duration::from_millis(10)).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::NotConnected);
+    }
+}
@@ -0,0 +1,115 @@
+use crate::error::FrameError;
+use crate::types::{Address, Control, Frame, FrameBuilder};
+
+/// Errors reported to foreign-language callers.
+#[derive(Debug, uniffi::Error)]
+pub enum CmuxError {
+    /// The input is too short to hold a frame.
+    Truncated { length: u64 },
+    /// The frame could not be built, parsed or verified.
+    Invalid { reason: String },
+}
+
+impl From<FrameError> for CmuxError {
+    fn from(value: FrameError) -> Self {
+        match value {
+            FrameError::TruncatedFrame { actual, .. } => CmuxError::Truncated {
+                length: actual as u64,
+            },
+            e => CmuxError::Invalid {
+                reason: e.to_string(),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for CmuxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CmuxError::Truncated { length } => write!(f, "Frame of {} bytes is truncated", length),
+            CmuxError::Invalid { reason } => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for CmuxError {}
+
+/// Flat view of a [`Frame`] for Kotlin/Swift.
+#[derive(Debug, uniffi::Record)]
+pub struct FrameRecord {
+    pub dlci: u8,
+    pub cr: bool,
+    pub address: u8,
+    pub control: u8,
+    pub pf: bool,
+    pub length: u16,
+    pub content: String,
+    pub content_bytes: Vec<u8>,
+    pub checksum: u8,
+}
+
+impl From<&Frame> for FrameRecord {
+    fn from(frame: &Frame) -> Self {
+        FrameRecord {
+            dlci: frame.address.into_bits() >> 2,
+            cr: frame.address.cr(),
+            address: frame.address.into_bits(),
+            control: frame.control.into_bits(),
+            pf: frame.control.pf(),
+            length: frame.length,
+            content: frame.content.to_string_lossy().to_string(),
+            content_bytes: frame.content.as_bytes().to_vec(),
+            checksum: frame.checksum,
+        }
+    }
+}
+
+/// Builds a frame and returns its bytes.
+#[uniffi::export]
+pub fn build_frame(address: u8, control: u8, content: String) -> Result<Vec<u8>, CmuxError> {
+    let frame = FrameBuilder::default()
+        .with_address(Address::from_bits(address))
+        .with_control(Control::from_bits(control))
+        .with_content(content)
+        .build()?;
+    Ok(frame.to_bytes())
+}
+
+/// Parses a single frame from its bytes.
+#[uniffi::export]
+pub fn parse_frame(data: Vec<u8>) -> Result<FrameRecord, CmuxError> {
+    Ok(FrameRecord::from(&Frame::from_bytes(data)?))
+}
+
+/// Parses and verifies a single frame from its bytes.
+#[uniffi::export]
+pub fn verify_frame(data: Vec<u8>) -> Result<FrameRecord, CmuxError> {
+    let frame = Frame::from_bytes(data)?;
+    frame.verify()?;
+    Ok(FrameRecord::from(&frame))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_and_parse() {
+        let data = build_frame(0x07, 0xEF, "AT+CMUX?".to_string()).unwrap();
+        assert_eq!(hex::encode(&data), "f907ef1541542b434d55583f0d0a2cf9");
+
+        let record = verify_frame(data).unwrap();
+        assert_eq!(record.dlci, 1);
+        assert!(record.cr);
+        assert_eq!(record.content, "AT+CMUX?\r\n");
+        assert_eq!(record.checksum, 0x2C);
+    }
+
+    #[test]
+    fn test_parse_truncated() {
+        assert!(matches!(
+            parse_frame(vec![0xF9, 0x07]),
+            Err(CmuxError::Truncated { length: 2 })
+        ));
+    }
+}
@@ -0,0 +1,679 @@
+use crate::control::V24Signals;
+use crate::error::MuxError;
+use crate::mux::{Mux, MuxEvent};
+use std::collections::BTreeMap;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Local end of a DLC, carrying its bytes to and from other programs
+///
+/// Reads never block, so one thread can serve the endpoints of every DLC between two polls of
+/// the multiplexer.
+pub trait Endpoint {
+    /// Reads the bytes sent by the programs using the endpoint.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(usize)`: The number of bytes read, 0 if none are available.
+    /// - `Err(io::Error)`: If the endpoint failed.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Writes `data` received on the DLC.
+    fn write(&mut self, data: &[u8]) -> io::Result<()>;
+
+    /// Applies the V.24 signals received on the DLC to the control lines of the endpoint.
+    /// Endpoints without control lines ignore them.
+    fn modem_status(&mut self, signals: V24Signals) -> io::Result<()> {
+        let _ = signals;
+        Ok(())
+    }
+}
+
+/// Endpoints of the DLCs of a multiplexer
+///
+/// # Example
+///
+/// ```no_run
+/// use cmux::bridge::{Endpoints, SocketBridge};
+/// use cmux::mux::Mux;
+///
+/// let mut mux = Mux::new();
+/// let mut endpoints = Endpoints::new();
+/// endpoints.insert(1, Box::new(SocketBridge::tcp("127.0.0.1:5001").unwrap()));
+/// endpoints.forward(&mut mux).unwrap();
+/// while let Some(event) = mux.poll_event() {
+///     if !endpoints.deliver(&event).unwrap() {
+///         println!("{:?}", event);
+///     }
+/// }
+/// ```
+#[derive(Default)]
+pub struct Endpoints {
+    dlcs: BTreeMap<u8, Vec<Box<dyn Endpoint>>>,
+}
+
+impl Endpoints {
+    /// Creates a map without endpoints.
+    pub fn new() -> Self {
+        Endpoints::default()
+    }
+
+    /// Adds an endpoint to `dlci`. The data received on a DLC goes to all of its endpoints.
+    pub fn insert(&mut self, dlci: u8, endpoint: Box<dyn Endpoint>) {
+        self.dlcs.entry(dlci).or_default().push(endpoint);
+    }
+
+    /// Drops the endpoints of `dlci`.
+    pub fn remove(&mut self, dlci: u8) {
+        self.dlcs.remove(&dlci);
+    }
+
+    /// Returns true if `dlci` has an endpoint.
+    pub fn contains(&self, dlci: u8) -> bool {
+        self.dlcs.contains_key(&dlci)
+    }
+
+    /// Reads every endpoint once and sends the bytes read on its DLC.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Vec<MuxError>)`: The errors of the data that could not be sent.
+    /// - `Err(io::Error)`: If an endpoint failed.
+    pub fn forward(&mut self, mux: &mut Mux) -> io::Result<Vec<MuxError>> {
+        let mut errors = Vec::new();
+        let mut buf = [0; 1024];
+        for (&dlci, endpoints) in self.dlcs.iter_mut() {
+            for endpoint in endpoints {
+                let count = endpoint.read(&mut buf)?;
+                if count > 0 {
+                    if let Err(e) = mux.send(dlci, &buf[..count]) {
+                        errors.push(e);
+                    }
+                }
+            }
+        }
+        Ok(errors)
+    }
+
+    /// Writes the data of `event` to the endpoints of its DLC, passes them the V.24 signals it
+    /// carries, and drops the endpoints of the DLCs it closes.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(true)`: If the event is data written to an endpoint.
+    /// - `Ok(false)`: If the event is left to the caller.
+    /// - `Err(io::Error)`: If an endpoint failed.
+    pub fn deliver(&mut self, event: &MuxEvent) -> io::Result<bool> {
+        match event {
+            MuxEvent::Data { dlci, data } => match self.dlcs.get_mut(dlci) {
+                Some(endpoints) => {
+                    for endpoint in endpoints {
+                        endpoint.write(data)?;
+                    }
+                    Ok(true)
+                }
+                None => Ok(false),
+            },
+            MuxEvent::ModemStatus(msc) => {
+                for endpoint in self.dlcs.get_mut(&msc.dlci).into_iter().flatten() {
+                    endpoint.modem_status(msc.signals)?;
+                }
+                Ok(false)
+            }
+            MuxEvent::DlcClosed(dlci) => {
+                self.remove(*dlci);
+                Ok(false)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+/// Bytes written to an endpoint and not yet taken by its client
+///
+/// The bytes the client does not take at once are kept and written again at the next read or
+/// write of the endpoint, so a slow client never blocks the thread serving every endpoint.
+#[derive(Debug, Default)]
+pub(crate) struct Backlog {
+    data: Vec<u8>,
+    /// Since when the client has taken nothing, while bytes are waiting.
+    stalled: Option<Instant>,
+}
+
+impl Backlog {
+    /// Number of bytes kept at most for a client.
+    pub(crate) const CAPACITY: usize = 64 * 1024;
+    /// How long a client may take nothing before it is given up.
+    pub(crate) const DEADLINE: Duration = Duration::from_secs(1);
+
+    /// Queues `data` and writes as much of the backlog as `write` takes without blocking.
+    pub(crate) fn push(
+        &mut self,
+        data: &[u8],
+        write: impl FnMut(&[u8]) -> io::Result<usize>,
+    ) -> io::Result<()> {
+        self.data.extend_from_slice(data);
+        self.flush(write)
+    }
+
+    /// Writes as much of the backlog as `write` takes without blocking.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(())`: If the client keeps up.
+    /// - `Err(io::Error)`: `TimedOut` if the backlog exceeds [`Backlog::CAPACITY`] or the
+    ///   client took nothing for [`Backlog::DEADLINE`], or the error of `write`.
+    pub(crate) fn flush(
+        &mut self,
+        mut write: impl FnMut(&[u8]) -> io::Result<usize>,
+    ) -> io::Result<()> {
+        while !self.data.is_empty() {
+            match write(&self.data) {
+                Ok(0) => break,
+                Ok(count) => {
+                    self.data.drain(..count);
+                    self.stalled = None;
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        if self.data.is_empty() {
+            self.stalled = None;
+            return Ok(());
+        }
+        let stalled = *self.stalled.get_or_insert_with(Instant::now);
+        if self.data.len() > Self::CAPACITY || stalled.elapsed() >= Self::DEADLINE {
+            return Err(io::Error::new(
+                ErrorKind::TimedOut,
+                "the client stopped reading",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Drops the bytes waiting, for example when the client is gone.
+    pub(crate) fn clear(&mut self) {
+        self.data.clear();
+        self.stalled = None;
+    }
+}
+
+/// Listening socket of a [`SocketBridge`]
+pub trait Listener {
+    /// Connection accepted by the listener.
+    type Stream: Read + Write;
+
+    /// Accepts a pending connection, switched to non-blocking mode.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Stream)`: The accepted connection.
+    /// - `Err(io::Error)`: `WouldBlock` if no connection is pending, or the error of the listener.
+    fn accept_nonblocking(&self) -> io::Result<Self::Stream>;
+}
+
+impl Listener for TcpListener {
+    type Stream = TcpStream;
+
+    fn accept_nonblocking(&self) -> io::Result<TcpStream> {
+        let (stream, _) = self.accept()?;
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(stream)
+    }
+}
+
+/// Unix domain socket listening at a path, removed when the socket is dropped
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct UnixSocket {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl UnixSocket {
+    /// Listens at `path` in non-blocking mode.
+    ///
+    /// A socket left at `path` by a previous run is replaced, but any other file is kept and
+    /// fails with `AddrInUse`.
+    pub fn bind(path: impl AsRef<Path>) -> io::Result<Self> {
+        use std::os::unix::fs::FileTypeExt;
+
+        let path = path.as_ref();
+        match std::fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)?,
+            Ok(_) => {
+                return Err(io::Error::new(
+                    ErrorKind::AddrInUse,
+                    format!("{} exists and is not a socket", path.display()),
+                ))
+            }
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+            Err(_) => {}
+        }
+        let listener = UnixListener::bind(path)?;
+        listener.set_nonblocking(true)?;
+        Ok(UnixSocket {
+            listener,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Returns the path of the socket.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the listening socket, in non-blocking mode.
+    pub fn listener(&self) -> &UnixListener {
+        &self.listener
+    }
+}
+
+#[cfg(unix)]
+impl Listener for UnixSocket {
+    type Stream = UnixStream;
+
+    fn accept_nonblocking(&self) -> io::Result<UnixStream> {
+        let (stream, _) = self.listener.accept()?;
+        stream.set_nonblocking(true)?;
+        Ok(stream)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for UnixSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// [`Endpoint`] exposing a DLC on a listening socket
+///
+/// Dropping the bridge, for example when its DLC is disconnected, closes the listener and the
+/// client, and removes the file of a Unix domain socket.
+///
+/// One client is served at a time: connections made while a client is connected are closed
+/// at once, and the bytes received on the DLC while no client is connected are dropped, as on
+/// a serial line with nothing plugged in. A client failing, disconnecting or leaving the
+/// bytes of the DLC unread for a second only frees the bridge for the next one.
+///
+/// # Example
+///
+/// ```no_run
+/// use cmux::bridge::{Endpoint, SocketBridge};
+///
+/// let mut bridge = SocketBridge::tcp("127.0.0.1:5001").unwrap();
+/// let mut buf = [0; 1024];
+/// let count = bridge.read(&mut buf).unwrap();
+/// bridge.write(b"RING\r\n").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct SocketBridge<L: Listener> {
+    listener: L,
+    client: Option<L::Stream>,
+    backlog: Backlog,
+}
+
+impl SocketBridge<TcpListener> {
+    /// Listens for TCP connections at `addr`.
+    pub fn tcp(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(SocketBridge::new(listener))
+    }
+
+    /// Returns the address the bridge listens at.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+}
+
+#[cfg(unix)]
+impl SocketBridge<UnixSocket> {
+    /// Listens for connections on a Unix domain socket at `path`, removed when the bridge is
+    /// dropped. See [`UnixSocket::bind`].
+    pub fn unix(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(SocketBridge::new(UnixSocket::bind(path)?))
+    }
+
+    /// Returns the path of the socket.
+    pub fn path(&self) -> &Path {
+        self.listener.path()
+    }
+}
+
+impl<L: Listener> SocketBridge<L> {
+    /// Wraps `listener`, which must be in non-blocking mode.
+    pub fn new(listener: L) -> Self {
+        SocketBridge {
+            listener,
+            client: None,
+            backlog: Backlog::default(),
+        }
+    }
+
+    /// Returns the listening socket.
+    pub fn listener(&self) -> &L {
+        &self.listener
+    }
+
+    /// Returns true if a client is connected.
+    pub fn is_connected(&self) -> bool {
+        self.client.is_some()
+    }
+
+    /// Accepts the pending connections, keeping the first one if no client is connected.
+    fn accept(&mut self) -> io::Result<()> {
+        loop {
+            match self.listener.accept_nonblocking() {
+                Ok(stream) => {
+                    if self.client.is_none() {
+                        self.client = Some(stream);
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == ErrorKind::ConnectionAborted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl<L: Listener> SocketBridge<L> {
+    /// Drops the client, freeing the bridge for the next one.
+    fn disconnect(&mut self) {
+        self.client = None;
+        self.backlog.clear();
+    }
+}
+
+impl<L: Listener> Endpoint for SocketBridge<L> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.accept()?;
+        let Some(client) = &mut self.client else {
+            return Ok(0);
+        };
+        if self.backlog.flush(|data| client.write(data)).is_err() {
+            self.disconnect();
+            return Ok(0);
+        }
+        match client.read(buf) {
+            Ok(0) if !buf.is_empty() => {
+                self.disconnect();
+                Ok(0)
+            }
+            Ok(count) => Ok(count),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted) => Ok(0),
+            Err(_) => {
+                self.disconnect();
+                Ok(0)
+            }
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        let Some(client) = &mut self.client else {
+            return Ok(());
+        };
+        if self.backlog.push(data, |data| client.write(data)).is_err() {
+            self.disconnect();
+        }
+        Ok(())
+    }
+}
+
+/// [`Endpoint`] exposing a DLC on a Windows named pipe, such as `\\.\pipe\cmux-dlci1`
+///
+/// As with [`SocketBridge`], one client is served at a time, the bytes received on the DLC
+/// while no client is connected are dropped, and a client failing, disconnecting or leaving
+/// the bytes of the DLC unread for a second frees the pipe for the next one. Only local
+/// clients are accepted. Dropping the endpoint removes the
+/// pipe.
+#[cfg(windows)]
+#[derive(Debug)]
+pub struct NamedPipe {
+    pipe: std::fs::File,
+    path: String,
+    connected: bool,
+    backlog: Backlog,
+}
+
+#[cfg(windows)]
+impl NamedPipe {
+    /// Size of the input and output buffers of the pipe.
+    const BUFFER_SIZE: u32 = 4096;
+
+    /// Creates the pipe at `path`, in non-blocking mode.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(NamedPipe)`: The pipe, waiting for a client.
+    /// - `Err(io::Error)`: `PermissionDenied` if another process serves a pipe at `path`, or
+    ///   the error of the system.
+    pub fn create(path: &str) -> io::Result<Self> {
+        use std::os::windows::ffi::OsStrExt;
+        use std::os::windows::io::FromRawHandle;
+        use windows_sys::Win32::Foundation::INVALID_HANDLE_VALUE;
+        use windows_sys::Win32::Storage::FileSystem::{
+            FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX,
+        };
+        use windows_sys::Win32::System::Pipes::{
+            CreateNamedPipeW, PIPE_NOWAIT, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_TYPE_BYTE,
+        };
+
+        let name: Vec<u16> = std::ffi::OsStr::new(path)
+            .encode_wide()
+            .chain(Some(0))
+            .collect();
+        // SAFETY: `name` is NUL-terminated, and null security attributes are the defaults.
+        let handle = unsafe {
+            CreateNamedPipeW(
+                name.as_ptr(),
+                PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_NOWAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                1,
+                Self::BUFFER_SIZE,
+                Self::BUFFER_SIZE,
+                0,
+                std::ptr::null(),
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(NamedPipe {
+            // SAFETY: the handle is valid and owned by nothing else.
+            pipe: unsafe { std::fs::File::from_raw_handle(handle) },
+            path: path.to_string(),
+            connected: false,
+            backlog: Backlog::default(),
+        })
+    }
+
+    /// Returns the path of the pipe.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns true if a client is connected.
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Takes the client waiting for the pipe, if any.
+    fn accept(&mut self) -> io::Result<()> {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::Foundation::{
+            ERROR_NO_DATA, ERROR_PIPE_CONNECTED, ERROR_PIPE_LISTENING,
+        };
+        use windows_sys::Win32::System::Pipes::ConnectNamedPipe;
+
+        if self.connected {
+            return Ok(());
+        }
+        // SAFETY: the handle is a pipe in non-blocking mode, so the call returns at once.
+        if unsafe { ConnectNamedPipe(self.pipe.as_raw_handle(), std::ptr::null_mut()) } != 0 {
+            self.connected = true;
+            return Ok(());
+        }
+        let e = io::Error::last_os_error();
+        match e.raw_os_error().map(|code| code as u32) {
+            Some(ERROR_PIPE_CONNECTED) => self.connected = true,
+            Some(ERROR_PIPE_LISTENING) => {}
+            // A client connected and left before it was served.
+            Some(ERROR_NO_DATA) => self.disconnect(),
+            _ => return Err(e),
+        }
+        Ok(())
+    }
+
+    /// Drops the client, freeing the pipe for the next one.
+    fn disconnect(&mut self) {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::System::Pipes::DisconnectNamedPipe;
+
+        // SAFETY: the handle is a pipe owned by `self`.
+        unsafe { DisconnectNamedPipe(self.pipe.as_raw_handle()) };
+        self.connected = false;
+        self.backlog.clear();
+    }
+}
+
+#[cfg(windows)]
+impl Endpoint for NamedPipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        use windows_sys::Win32::Foundation::ERROR_NO_DATA;
+
+        self.accept()?;
+        if !self.connected {
+            return Ok(0);
+        }
+        let pipe = &mut self.pipe;
+        if self.backlog.flush(|data| pipe.write(data)).is_err() {
+            self.disconnect();
+            return Ok(0);
+        }
+        match self.pipe.read(buf) {
+            // The client closed its end.
+            Ok(0) if !buf.is_empty() => {
+                self.disconnect();
+                Ok(0)
+            }
+            Ok(count) => Ok(count),
+            // Nothing to read from a pipe in non-blocking mode.
+            Err(e) if e.raw_os_error() == Some(ERROR_NO_DATA as i32) => Ok(0),
+            Err(_) => {
+                self.disconnect();
+                Ok(0)
+            }
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        if !self.connected {
+            return Ok(());
+        }
+        // A pipe in non-blocking mode takes nothing while its buffer is full.
+        let pipe = &mut self.pipe;
+        if self.backlog.push(data, |data| pipe.write(data)).is_err() {
+            self.disconnect();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads from `bridge` until `count` bytes arrived.
+    fn read_exact(bridge: &mut impl Endpoint, count: usize) -> Vec<u8> {
+        let mut data = vec![0; count];
+        let mut read = 0;
+        while read < count {
+            read += bridge.read(&mut data[read..]).unwrap();
+        }
+        data
+    }
+
+    #[test]
+    fn test_tcp_bridge() {
+        let mut bridge = SocketBridge::tcp("127.0.0.1:0").unwrap();
+        let addr = bridge.local_addr().unwrap();
+        bridge.write(b"dropped").unwrap();
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(b"AT\r").unwrap();
+        assert_eq!(read_exact(&mut bridge, 3), b"AT\r");
+        bridge.write(b"OK\r\n").unwrap();
+        let mut buf = [0; 4];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"OK\r\n");
+
+        // A second client is turned away while the first one is connected.
+        let mut second = TcpStream::connect(addr).unwrap();
+        assert_eq!(bridge.read(&mut buf).unwrap(), 0);
+        assert_eq!(second.read(&mut buf).unwrap(), 0);
+
+        drop(client);
+        while bridge.is_connected() {
+            bridge.read(&mut buf).unwrap();
+        }
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(b"ATI\r").unwrap();
+        assert_eq!(read_exact(&mut bridge, 4), b"ATI\r");
+    }
+
+    #[test]
+    fn test_slow_client() {
+        let mut bridge = SocketBridge::tcp("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(bridge.local_addr().unwrap()).unwrap();
+        let mut buf = [0; 16];
+        while !bridge.is_connected() {
+            bridge.read(&mut buf).unwrap();
+        }
+        // The client never reads: the writes do not block, and the client is dropped once the
+        // socket buffers and the backlog are full.
+        let start = Instant::now();
+        let chunk = vec![0x55; 4096];
+        while bridge.is_connected() {
+            bridge.write(&chunk).unwrap();
+            assert!(start.elapsed() < Duration::from_secs(10));
+        }
+        assert!(bridge.backlog.data.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_bridge() {
+        let path = std::env::temp_dir().join(format!("cmux-test-{}.sock", std::process::id()));
+        // std leaves the socket file behind, as a crashed run would.
+        drop(UnixListener::bind(&path).unwrap());
+        let mut bridge = SocketBridge::unix(&path).unwrap();
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        client.write_all(b"AT\r").unwrap();
+        assert_eq!(read_exact(&mut bridge, 3), b"AT\r");
+        bridge.write(b"OK\r\n").unwrap();
+        let mut buf = [0; 4];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"OK\r\n");
+
+        drop(bridge);
+        assert!(std::fs::symlink_metadata(&path).is_err());
+        std::fs::write(&path, b"").unwrap();
+        let e = SocketBridge::unix(&path).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::AddrInUse);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,409 @@
+use crate::error::CaptureError;
+use std::io::{self, Read};
+use std::time::Duration;
+
+/// Magic number of pcap files with microsecond timestamps.
+const PCAP_MAGIC: u32 = 0xA1B2_C3D4;
+
+/// Magic number of pcap files with nanosecond timestamps.
+const PCAP_MAGIC_NANOS: u32 = 0xA1B2_3C4D;
+
+/// Block type of the pcapng section header, which is also the file magic number.
+const PCAPNG_SECTION_HEADER: u32 = 0x0A0D_0D0A;
+
+/// Byte-order magic of the pcapng section header.
+const PCAPNG_BYTE_ORDER: u32 = 0x1A2B_3C4D;
+
+/// Block types of pcapng.
+const PCAPNG_INTERFACE: u32 = 1;
+const PCAPNG_SIMPLE_PACKET: u32 = 3;
+const PCAPNG_ENHANCED_PACKET: u32 = 6;
+
+/// Packet of a capture
+///
+/// UART sniffers write the bytes seen on the line as the packet data, with a user link type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    /// Time of capture since the Unix epoch, if the format records it.
+    pub timestamp: Option<Duration>,
+    /// Link type of the interface the packet was captured on.
+    pub link_type: u16,
+    pub data: Vec<u8>,
+}
+
+/// Reads 32-bit and 16-bit fields of a header or block in the byte order of the capture.
+#[derive(Clone, Copy)]
+struct Reader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+    /// Offset of the header or block in the capture.
+    base: usize,
+}
+
+impl Reader<'_> {
+    fn u32(&self, offset: usize) -> io::Result<u32> {
+        let bytes: [u8; 4] = self
+            .data
+            .get(offset..offset + 4)
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| self.truncated())?;
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    fn u16(&self, offset: usize) -> io::Result<u16> {
+        let bytes: [u8; 2] = self
+            .data
+            .get(offset..offset + 2)
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| self.truncated())?;
+        Ok(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn bytes(&self, offset: usize, len: usize) -> io::Result<&[u8]> {
+        self.data
+            .get(offset..offset.saturating_add(len))
+            .ok_or_else(|| self.truncated())
+    }
+
+    fn truncated(&self) -> io::Error {
+        invalid(CaptureError::Truncated { offset: self.base })
+    }
+}
+
+/// Wraps an error of the capture format in an [`io::Error`].
+fn invalid(error: CaptureError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// Returns true if `data` starts with a pcap or pcapng magic number.
+pub fn is_capture(data: &[u8]) -> bool {
+    let Some(magic) = data.get(..4) else {
+        return false;
+    };
+    let magic = [magic[0], magic[1], magic[2], magic[3]];
+    [u32::from_le_bytes(magic), u32::from_be_bytes(magic)]
+        .iter()
+        .any(|m| matches!(*m, PCAP_MAGIC | PCAP_MAGIC_NANOS | PCAPNG_SECTION_HEADER))
+}
+
+/// Layout of the records of a capture
+enum Format {
+    Pcap { nanos: bool, link_type: u16 },
+    Pcapng { link_types: Vec<u16> },
+}
+
+/// Reads the packets of a pcap or pcapng capture one at a time
+///
+/// Only the record being read is held in memory, so captures are read in bounded memory
+/// whatever their size. pcapng timestamps are read with the default resolution of 1 µs.
+///
+/// Errors of the capture format are returned as [`io::ErrorKind::InvalidData`] errors
+/// wrapping a [`CaptureError`].
+///
+/// # Example
+///
+/// ```
+/// use cmux::capture::PacketReader;
+///
+/// let mut pcap = vec![0xD4, 0xC3, 0xB2, 0xA1, 2, 0, 4, 0];
+/// pcap.extend([0; 12]);
+/// pcap.extend([147, 0, 0, 0]);
+/// pcap.extend([0; 8]);
+/// pcap.extend([3, 0, 0, 0, 3, 0, 0, 0, 0xF9, 0x03, 0x3F]);
+/// let mut packets = PacketReader::new(&pcap[..]).unwrap();
+/// assert_eq!(packets.next().unwrap().unwrap().data, [0xF9, 0x03, 0x3F]);
+/// assert!(packets.next().is_none());
+/// ```
+pub struct PacketReader<R> {
+    reader: io::Chain<io::Cursor<Vec<u8>>, R>,
+    /// Offset of the next header, block or packet in the capture.
+    offset: usize,
+    big_endian: bool,
+    format: Format,
+}
+
+impl<R: Read> PacketReader<R> {
+    /// Reads the file header of the capture from `reader`.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(PacketReader)`: The reader of the packets that follow.
+    /// - `Err(io::Error)`: If reading fails, the format is unknown or the header is truncated.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = Vec::with_capacity(4);
+        (&mut reader).take(4).read_to_end(&mut magic)?;
+        if !is_capture(&magic) {
+            return Err(invalid(CaptureError::UnknownFormat));
+        }
+        let magic = u32::from_le_bytes([magic[0], magic[1], magic[2], magic[3]]);
+        if magic == PCAPNG_SECTION_HEADER {
+            // The magic number is the type of the first block.
+            return Ok(PacketReader {
+                reader: io::Cursor::new(magic.to_le_bytes().to_vec()).chain(reader),
+                offset: 0,
+                big_endian: false,
+                format: Format::Pcapng {
+                    link_types: Vec::new(),
+                },
+            });
+        }
+        let nanos = magic == PCAP_MAGIC_NANOS || magic.swap_bytes() == PCAP_MAGIC_NANOS;
+        let mut capture = PacketReader {
+            reader: io::Cursor::new(Vec::new()).chain(reader),
+            offset: 4,
+            big_endian: magic != PCAP_MAGIC && magic != PCAP_MAGIC_NANOS,
+            format: Format::Pcap {
+                nanos,
+                link_type: 0,
+            },
+        };
+        let header = capture
+            .read(20, 0)?
+            .ok_or_else(|| invalid(CaptureError::Truncated { offset: 0 }))?;
+        let link_type = capture.reader_of(&header, 0).u32(16)? as u16;
+        capture.format = Format::Pcap { nanos, link_type };
+        Ok(capture)
+    }
+
+    /// Reads `len` bytes of the header, block or packet at `start`.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Some(Vec<u8>))`: The bytes.
+    /// - `Ok(None)`: If the capture ends before them.
+    /// - `Err(io::Error)`: If reading fails, or the capture ends in the middle of them.
+    fn read(&mut self, len: usize, start: usize) -> io::Result<Option<Vec<u8>>> {
+        let mut data = Vec::new();
+        (&mut self.reader).take(len as u64).read_to_end(&mut data)?;
+        match data.len() {
+            0 if len > 0 => Ok(None),
+            n if n < len => Err(invalid(CaptureError::Truncated { offset: start })),
+            _ => {
+                self.offset += len;
+                Ok(Some(data))
+            }
+        }
+    }
+
+    fn reader_of<'a>(&self, data: &'a [u8], base: usize) -> Reader<'a> {
+        Reader {
+            data,
+            big_endian: self.big_endian,
+            base,
+        }
+    }
+
+    /// Reads the next packet, skipping the other records.
+    fn next_packet(&mut self) -> io::Result<Option<Packet>> {
+        loop {
+            let start = self.offset;
+            if let Format::Pcap { nanos, link_type } = self.format {
+                let Some(header) = self.read(16, start)? else {
+                    return Ok(None);
+                };
+                let reader = self.reader_of(&header, start);
+                let seconds = reader.u32(0)?;
+                let fraction = reader.u32(4)?;
+                let len = reader.u32(8)? as usize;
+                let timestamp = if nanos {
+                    Duration::new(seconds.into(), fraction)
+                } else {
+                    Duration::from_secs(seconds.into()) + Duration::from_micros(fraction.into())
+                };
+                let data = self
+                    .read(len, start)?
+                    .ok_or_else(|| invalid(CaptureError::Truncated { offset: start }))?;
+                return Ok(Some(Packet {
+                    timestamp: Some(timestamp),
+                    link_type,
+                    data,
+                }));
+            }
+            // Every pcapng block has a type, a length and a trailing length.
+            let Some(mut block) = self.read(12, start)? else {
+                return Ok(None);
+            };
+            let block_type = self.reader_of(&block, start).u32(0)?;
+            if block_type == PCAPNG_SECTION_HEADER {
+                self.big_endian = false;
+                if self.reader_of(&block, start).u32(8)? != PCAPNG_BYTE_ORDER {
+                    self.big_endian = true;
+                }
+                if let Format::Pcapng { link_types } = &mut self.format {
+                    link_types.clear();
+                }
+            }
+            let block_len = self.reader_of(&block, start).u32(4)? as usize;
+            if block_len < 12 {
+                return Err(invalid(CaptureError::Truncated { offset: start }));
+            }
+            let rest = self
+                .read(block_len - 12, start)?
+                .ok_or_else(|| invalid(CaptureError::Truncated { offset: start }))?;
+            block.extend(rest);
+            let reader = self.reader_of(&block, start);
+            let Format::Pcapng { link_types } = &mut self.format else {
+                unreachable!("pcap records are read above");
+            };
+            match block_type {
+                PCAPNG_INTERFACE => link_types.push(reader.u16(8)?),
+                PCAPNG_ENHANCED_PACKET => {
+                    let interface = reader.u32(8)? as usize;
+                    let high = u64::from(reader.u32(12)?);
+                    let low = u64::from(reader.u32(16)?);
+                    let len = reader.u32(20)? as usize;
+                    return Ok(Some(Packet {
+                        timestamp: Some(Duration::from_micros((high << 32) | low)),
+                        link_type: link_types.get(interface).copied().unwrap_or_default(),
+                        data: reader.bytes(28, len)?.to_vec(),
+                    }));
+                }
+                PCAPNG_SIMPLE_PACKET => {
+                    let len = (reader.u32(8)? as usize).min(block_len.saturating_sub(16));
+                    return Ok(Some(Packet {
+                        timestamp: None,
+                        link_type: link_types.first().copied().unwrap_or_default(),
+                        data: reader.bytes(12, len)?.to_vec(),
+                    }));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for PacketReader<R> {
+    type Item = io::Result<Packet>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_packet().transpose()
+    }
+}
+
+/// Reads the packets of a pcap or pcapng capture.
+///
+/// pcapng timestamps are read with the default resolution of 1 µs.
+///
+/// # Returns
+///
+/// - `Ok(Vec<Packet>)`: The packets, in capture order.
+/// - `Err(CaptureError)`: If the format is unknown or the capture is truncated.
+pub fn read_packets(data: &[u8]) -> Result<Vec<Packet>, CaptureError> {
+    PacketReader::new(data)
+        .and_then(|packets| packets.collect())
+        .map_err(|e| {
+            // Slices are read without I/O errors, so every error is one of the capture.
+            *e.into_inner()
+                .and_then(|e| e.downcast().ok())
+                .expect("errors of a capture wrap a CaptureError")
+        })
+}
+
+/// Returns the data of every packet of a capture, concatenated.
+///
+/// # Example
+///
+/// ```
+/// use cmux::capture;
+///
+/// let mut pcap = vec![0xD4, 0xC3, 0xB2, 0xA1, 2, 0, 4, 0];
+/// pcap.extend([0; 12]);
+/// pcap.extend([147, 0, 0, 0]);
+/// for chunk in [&[0xF9, 0x03, 0x3F][..], &[0x01, 0x1C, 0xF9]] {
+///     pcap.extend([0; 8]);
+///     pcap.extend([chunk.len() as u8, 0, 0, 0, chunk.len() as u8, 0, 0, 0]);
+///     pcap.extend(chunk);
+/// }
+/// assert_eq!(capture::payload(&pcap).unwrap(), [0xF9, 0x03, 0x3F, 0x01, 0x1C, 0xF9]);
+/// ```
+pub fn payload(data: &[u8]) -> Result<Vec<u8>, CaptureError> {
+    Ok(read_packets(data)?
+        .into_iter()
+        .flat_map(|packet| packet.data)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a little-endian pcapng block padded to 32 bits.
+    fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+        let padded = body.len().div_ceil(4) * 4;
+        let len = (12 + padded) as u32;
+        let mut block = block_type.to_le_bytes().to_vec();
+        block.extend(len.to_le_bytes());
+        block.extend(body);
+        block.resize(8 + padded, 0);
+        block.extend(len.to_le_bytes());
+        block
+    }
+
+    #[test]
+    fn test_pcapng() {
+        let mut data = block(PCAPNG_SECTION_HEADER, &[0x4D, 0x3C, 0x2B, 0x1A, 1, 0, 0, 0]);
+        data.extend(block(PCAPNG_INTERFACE, &[147, 0, 0, 0, 0, 0, 0, 0]));
+        let mut epb = vec![
+            0, 0, 0, 0, 0, 0, 0, 0, 0x40, 0x42, 0x0F, 0, 3, 0, 0, 0, 3, 0, 0, 0,
+        ];
+        epb.extend([0xF9, 0x03, 0x3F]);
+        data.extend(block(PCAPNG_ENHANCED_PACKET, &epb));
+        data.extend(block(PCAPNG_SIMPLE_PACKET, &[3, 0, 0, 0, 0x01, 0x1C, 0xF9]));
+
+        assert!(is_capture(&data));
+        let packets = read_packets(&data).unwrap();
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0].timestamp, Some(Duration::from_secs(1)));
+        assert_eq!(packets[0].link_type, 147);
+        assert_eq!(packets[1].data, [0x01, 0x1C, 0xF9]);
+
+        data.truncate(data.len() - 1);
+        assert_eq!(
+            read_packets(&data),
+            Err(CaptureError::Truncated {
+                offset: data.len() - 19
+            })
+        );
+        assert_eq!(read_packets(b"F9"), Err(CaptureError::UnknownFormat));
+    }
+
+    #[test]
+    fn test_packet_reader() {
+        // Big-endian pcap with nanosecond timestamps, read one byte at a time.
+        let mut data = vec![0xA1, 0xB2, 0x3C, 0x4D, 0, 2, 0, 4];
+        data.extend([0; 12]);
+        data.extend([0, 0, 0, 147]);
+        for chunk in [&[0xF9, 0x03][..], &[0x3F, 0x01, 0x1C, 0xF9]] {
+            data.extend([0, 0, 0, 1, 0, 0, 0, 5]);
+            data.extend([0, 0, 0, chunk.len() as u8, 0, 0, 0, chunk.len() as u8]);
+            data.extend(chunk);
+        }
+        let reader = io::BufReader::with_capacity(1, &data[..]);
+        let packets: Vec<Packet> = PacketReader::new(reader)
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[1].timestamp, Some(Duration::new(1, 5)));
+        assert_eq!(packets[1].link_type, 147);
+        assert_eq!(packets[1].data, [0x3F, 0x01, 0x1C, 0xF9]);
+
+        data.truncate(data.len() - 1);
+        assert_eq!(
+            read_packets(&data),
+            Err(CaptureError::Truncated { offset: 42 })
+        );
+        assert_eq!(
+            read_packets(&data[..10]),
+            Err(CaptureError::Truncated { offset: 0 })
+        );
+    }
+}
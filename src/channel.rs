@@ -0,0 +1,978 @@
+use crate::anomaly::AnomalyDetector;
+use crate::control::{Msc, V24Signals};
+use crate::error::MuxError;
+use crate::mux::{DlcState, LineStatus, Mux, MuxEvent};
+use crate::parser::{FrameParser, ParserLimits};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::sync::{mpsc, oneshot, watch};
+
+/// Size of the read buffer of [`Driver`].
+const BUFFER_SIZE: usize = 4096;
+
+/// Number of events an [`Events`] stream holds before the newer ones are dropped.
+pub const EVENTS_CAPACITY: usize = 1024;
+
+/// Request sent by the handles to the [`Driver`].
+enum Request {
+    Open {
+        dlci: u8,
+        /// Whether to send SABM, or wait for the peer to send it.
+        open: bool,
+        data: mpsc::UnboundedSender<Vec<u8>>,
+        status: watch::Sender<V24Signals>,
+        reply: oneshot::Sender<Result<(), MuxError>>,
+    },
+    Close(u8),
+    Data(u8, Vec<u8>),
+    ModemStatus(Msc),
+    Break(u8, Duration),
+    Shutdown(oneshot::Sender<()>),
+}
+
+impl std::fmt::Debug for Request {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Request::Open { dlci, .. } => write!(f, "Open({})", dlci),
+            Request::Close(dlci) => write!(f, "Close({})", dlci),
+            Request::Data(dlci, data) => write!(f, "Data({}, {} bytes)", dlci, data.len()),
+            Request::ModemStatus(msc) => write!(f, "ModemStatus({})", msc),
+            Request::Break(dlci, duration) => write!(f, "Break({}, {:?})", dlci, duration),
+            Request::Shutdown(_) => write!(f, "Shutdown"),
+        }
+    }
+}
+
+/// Senders feeding the handles of a DLC.
+struct Route {
+    data: mpsc::UnboundedSender<Vec<u8>>,
+    status: watch::Sender<V24Signals>,
+}
+
+/// Reads the [`LineStatus`] of the port of a [`Driver`].
+type LineReader<T> = Box<dyn FnMut(&mut T) -> io::Result<LineStatus> + Send>;
+
+/// Polling of the control lines of the port of a [`Driver`].
+struct LineWatch<T> {
+    interval: Duration,
+    read: LineReader<T>,
+    /// The status read last, `None` before the first poll.
+    last: Option<LineStatus>,
+    due: Instant,
+}
+
+/// Sender feeding an [`Events`] stream.
+#[derive(Debug)]
+struct Subscriber {
+    sender: mpsc::Sender<MuxEvent>,
+    /// The number of events dropped because the stream was full.
+    missed: Arc<AtomicUsize>,
+}
+
+/// Senders feeding the [`Events`] streams, shared by the handles and the [`Driver`].
+type Subscribers = Arc<Mutex<Vec<Subscriber>>>;
+
+/// Creates an asynchronous multiplexer over `io`.
+///
+/// # Returns
+///
+/// The [`AsyncMux`] handle opening channels, and the [`Driver`] that must be run for the
+/// channels to make progress.
+///
+/// # Example
+///
+/// ```
+/// use cmux::channel;
+/// use cmux::mux::Mux;
+/// use tokio::io::{AsyncReadExt, AsyncWriteExt};
+///
+/// # tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap().block_on(async {
+/// let (host, modem) = tokio::io::duplex(1024);
+/// let (host, host_driver) = channel::new(host, Mux::new());
+/// let (modem, modem_driver) = channel::new(modem, Mux::new().with_initiator(false));
+///
+/// let session = async {
+///     host.open(0).await.unwrap();
+///     let mut at = host.open(1).await.unwrap();
+///     at.write_all(b"ATI\r").await.unwrap();
+///     let mut buf = [0; 4];
+///     at.read_exact(&mut buf).await.unwrap();
+///     assert_eq!(&buf, b"OK\r\n");
+/// };
+/// let peer = async {
+///     let mut at = modem.accept(1).await.unwrap();
+///     let mut buf = [0; 4];
+///     at.read_exact(&mut buf).await.unwrap();
+///     at.write_all(b"OK\r\n").await.unwrap();
+/// };
+/// tokio::select! {
+///     _ = async { tokio::join!(session, peer) } => {}
+///     _ = host_driver.run() => unreachable!(),
+///     _ = modem_driver.run() => unreachable!(),
+/// }
+/// # });
+/// ```
+pub fn new<T: AsyncRead + AsyncWrite + Unpin>(io: T, mux: Mux) -> (AsyncMux, Driver<T>) {
+    let (requests, receiver) = mpsc::unbounded_channel();
+    let parser = FrameParser::new().with_limits(ParserLimits::STREAM);
+    let subscribers = Subscribers::default();
+    (
+        AsyncMux {
+            requests,
+            subscribers: subscribers.clone(),
+        },
+        Driver {
+            io,
+            mux,
+            parser,
+            requests: receiver,
+            routes: HashMap::new(),
+            pending: HashMap::new(),
+            shutdowns: Vec::new(),
+            subscribers,
+            detector: AnomalyDetector::new(),
+            lines: None,
+        },
+    )
+}
+
+/// Handle opening the channels of an asynchronous multiplexer
+///
+/// The handle can be cloned and sent to other tasks.
+#[derive(Debug, Clone)]
+pub struct AsyncMux {
+    requests: mpsc::UnboundedSender<Request>,
+    subscribers: Subscribers,
+}
+
+impl AsyncMux {
+    /// Opens `dlci` by sending SABM and waits for the peer to answer.
+    ///
+    /// Dropping the future before it completes is safe: a DLC connected after that is closed
+    /// again with DISC, and `dlci` can be opened again.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Channel)`: The connected channel.
+    /// - `Err(MuxError)`: If the peer rejected the DLC, did not answer, or the driver stopped.
+    pub async fn open(&self, dlci: u8) -> Result<Channel, MuxError> {
+        self.register(dlci, true).await
+    }
+
+    /// Waits for the peer to open `dlci`.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Channel)`: The connected channel.
+    /// - `Err(MuxError)`: If the driver stopped.
+    pub async fn accept(&self, dlci: u8) -> Result<Channel, MuxError> {
+        self.register(dlci, false).await
+    }
+
+    /// Closes every channel with DISC, DLCI 0 last, with [`Mux::shutdown`], and waits for the
+    /// driver to stop.
+    ///
+    /// The teardown is bounded by the T1 and N2 of the engine: a peer that does not answer
+    /// the DISC commands only delays it until they time out.
+    pub async fn shutdown(&self) {
+        let (reply, done) = oneshot::channel();
+        if self.requests.send(Request::Shutdown(reply)).is_ok() {
+            let _ = done.await;
+        }
+    }
+
+    /// Subscribes to the events of the engine.
+    ///
+    /// The stream yields every [`MuxEvent`] raised after this call, the data received
+    /// included, and ends when the driver stops. It holds up to [`EVENTS_CAPACITY`] events:
+    /// the events raised while it is full are dropped, and counted by [`Events::missed`].
+    pub fn events(&self) -> Events {
+        let (sender, receiver) = mpsc::channel(EVENTS_CAPACITY);
+        let missed = Arc::new(AtomicUsize::new(0));
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(Subscriber {
+                sender,
+                missed: missed.clone(),
+            });
+        }
+        Events { receiver, missed }
+    }
+
+    async fn register(&self, dlci: u8, open: bool) -> Result<Channel, MuxError> {
+        let dlci = dlci & 0x3F;
+        let (data, receiver) = mpsc::unbounded_channel();
+        let (status, signals) = watch::channel(V24Signals::new());
+        let (reply, result) = oneshot::channel();
+        let request = Request::Open {
+            dlci,
+            open,
+            data,
+            status,
+            reply,
+        };
+        let stopped = MuxError::NotConnected { dlci };
+        self.requests.send(request).map_err(|_| stopped.clone())?;
+        result.await.map_err(|_| stopped)??;
+        Ok(Channel {
+            dlci,
+            data: receiver,
+            buffer: Vec::new(),
+            control: ControlHandle {
+                dlci,
+                requests: self.requests.clone(),
+                signals,
+            },
+            closed: false,
+            read_timeout: None,
+            deadline: None,
+        })
+    }
+}
+
+/// Future driving an asynchronous multiplexer
+///
+/// The driver owns the byte pipe and the [`Mux`] engine. It reads frames, handles the
+/// requests of the handles and runs the engine timers. The engine is not `Send`, so the
+/// driver runs on the task that created it, for instance alongside the application with
+/// `tokio::select!`, or on a `tokio::task::LocalSet`.
+pub struct Driver<T> {
+    io: T,
+    mux: Mux,
+    parser: FrameParser,
+    requests: mpsc::UnboundedReceiver<Request>,
+    routes: HashMap<u8, Route>,
+    pending: HashMap<u8, (Route, oneshot::Sender<Result<(), MuxError>>)>,
+    /// The handles waiting for [`AsyncMux::shutdown`] to complete.
+    shutdowns: Vec<oneshot::Sender<()>>,
+    subscribers: Subscribers,
+    detector: AnomalyDetector,
+    lines: Option<LineWatch<T>>,
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> Driver<T> {
+    /// Switches to advanced option framing.
+    pub fn advanced(mut self) -> Self {
+        self.parser = FrameParser::advanced().with_limits(ParserLimits::STREAM);
+        self.detector = AnomalyDetector::advanced();
+        self
+    }
+
+    /// Reads the control lines of the pipe with `read` every `interval`.
+    ///
+    /// The lines are reported as [`MuxEvent::LineStatus`] when first read and whenever they
+    /// change, a break detected since the previous read included. An error of `read` ends
+    /// [`Driver::run`].
+    pub fn with_line_watch(
+        mut self,
+        interval: Duration,
+        read: impl FnMut(&mut T) -> io::Result<LineStatus> + Send + 'static,
+    ) -> Self {
+        self.lines = Some(LineWatch {
+            interval,
+            read: Box::new(read),
+            last: None,
+            due: Instant::now(),
+        });
+        self
+    }
+
+    /// Runs the multiplexer.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(())`: When the peer closed the pipe, every handle and channel was dropped, or
+    ///   [`AsyncMux::shutdown`] closed every DLC.
+    /// - `Err(io::Error)`: If the pipe failed.
+    pub async fn run(mut self) -> io::Result<()> {
+        let mut buf = vec![0; BUFFER_SIZE];
+        loop {
+            self.dispatch();
+            self.flush().await?;
+            if !self.shutdowns.is_empty() && self.mux.is_idle() {
+                for reply in self.shutdowns.drain(..) {
+                    let _ = reply.send(());
+                }
+                return Ok(());
+            }
+            let lines = self.lines.as_ref().map(|lines| lines.due);
+            let deadline = self.mux.next_timeout().into_iter().chain(lines).min();
+            let sleep = async {
+                match deadline {
+                    Some(deadline) => {
+                        tokio::time::sleep_until(tokio::time::Instant::from_std(deadline)).await
+                    }
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                count = self.io.read(&mut buf) => {
+                    let count = count?;
+                    if count == 0 {
+                        return Ok(());
+                    }
+                    let now = Instant::now();
+                    let data = &buf[..count];
+                    let mut anomalies = self.detector.feed(data);
+                    let (fcs_errors, skipped) = (self.parser.fcs_errors(), self.parser.skipped());
+                    for frame in self.parser.feed(data) {
+                        anomalies.extend(self.detector.record(&frame));
+                        self.mux.receive(&frame, now);
+                    }
+                    for _ in fcs_errors..self.parser.fcs_errors() {
+                        anomalies.extend(self.detector.record_fcs_error());
+                    }
+                    if self.parser.skipped() > skipped {
+                        self.publish(&MuxEvent::Skipped(self.parser.skipped() - skipped));
+                    }
+                    anomalies
+                        .into_iter()
+                        .for_each(|anomaly| self.publish(&MuxEvent::Anomaly(anomaly)));
+                }
+                request = self.requests.recv() => match request {
+                    Some(request) => self.handle(request),
+                    None => return Ok(()),
+                },
+                _ = sleep => {
+                    let now = Instant::now();
+                    self.mux.poll_timeout(now);
+                    self.watch_lines(now)?;
+                }
+            }
+        }
+    }
+
+    /// Writes every frame queued by the engine, within the deadline of the
+    /// [`WritePolicy`](crate::mux::WritePolicy) of the engine.
+    async fn flush(&mut self) -> io::Result<()> {
+        let mut data = Vec::new();
+        // The DLCI of the first frame, reported if the write does not complete in time.
+        let mut dlci = None;
+        while let Some(frame) = self.mux.poll_transmit() {
+            dlci.get_or_insert(frame.address.dlci().into_bits());
+            if self.parser.is_advanced() {
+                data.extend(frame.to_bytes_advanced());
+            } else {
+                data.extend(frame.to_bytes());
+            }
+        }
+        if data.is_empty() {
+            return Ok(());
+        }
+        let write = async {
+            self.io.write_all(&data).await?;
+            self.io.flush().await
+        };
+        let Some(deadline) = self.mux.config().write.deadline else {
+            return write.await;
+        };
+        match tokio::time::timeout(deadline, write).await {
+            Ok(result) => result,
+            Err(_) => {
+                let error = MuxError::WriteTimeout {
+                    dlci: dlci.unwrap_or(0),
+                };
+                Err(io::Error::new(io::ErrorKind::TimedOut, error))
+            }
+        }
+    }
+
+    /// Reads the control lines if they are due, and passes their changes to the engine.
+    fn watch_lines(&mut self, now: Instant) -> io::Result<()> {
+        let Some(lines) = self.lines.as_mut().filter(|lines| lines.due <= now) else {
+            return Ok(());
+        };
+        lines.due = now + lines.interval;
+        let status = (lines.read)(&mut self.io)?;
+        if lines.last.replace(status) != Some(status) {
+            self.publish(&MuxEvent::LineStatus(status));
+        }
+        Ok(())
+    }
+
+    /// Handles a request of a handle.
+    fn handle(&mut self, request: Request) {
+        let now = Instant::now();
+        match request {
+            Request::Open {
+                dlci,
+                open,
+                data,
+                status,
+                reply,
+            } => {
+                let route = Route { data, status };
+                self.pending.retain(|_, (_, reply)| !reply.is_closed());
+                if self.routes.contains_key(&dlci) || self.pending.contains_key(&dlci) {
+                    let _ = reply.send(Err(MuxError::Rejected { dlci }));
+                } else if self.mux.state(dlci) == DlcState::Connected {
+                    if reply.send(Ok(())).is_ok() {
+                        self.routes.insert(dlci, route);
+                    }
+                } else {
+                    self.pending.insert(dlci, (route, reply));
+                    if open {
+                        if let Err(e) = self.mux.open(dlci, now) {
+                            self.fail(dlci, e);
+                        }
+                    }
+                }
+            }
+            Request::Close(dlci) => {
+                self.routes.remove(&dlci);
+                let _ = self.mux.close(dlci, now);
+            }
+            Request::Data(dlci, data) => {
+                let _ = self.mux.send(dlci, &data);
+            }
+            Request::ModemStatus(msc) => {
+                let _ = self.mux.set_modem_status(msc, now);
+            }
+            Request::Break(dlci, duration) => {
+                let _ = self.mux.send_break(dlci, duration, now);
+            }
+            Request::Shutdown(reply) => {
+                self.mux.shutdown(now);
+                self.shutdowns.push(reply);
+            }
+        }
+    }
+
+    /// Fails the pending open of `dlci`.
+    fn fail(&mut self, dlci: u8, error: MuxError) {
+        if let Some((_, reply)) = self.pending.remove(&dlci) {
+            let _ = reply.send(Err(error));
+        }
+    }
+
+    /// Passes `event` to the [`Events`] streams.
+    fn publish(&self, event: &MuxEvent) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain(
+                |subscriber| match subscriber.sender.try_send(event.clone()) {
+                    Ok(()) => true,
+                    Err(mpsc::error::TrySendError::Full(_)) => {
+                        subscriber.missed.fetch_add(1, Ordering::Relaxed);
+                        true
+                    }
+                    Err(mpsc::error::TrySendError::Closed(_)) => false,
+                },
+            );
+        }
+    }
+
+    /// Passes the events of the engine to the handles.
+    fn dispatch(&mut self) {
+        while let Some(event) = self.mux.poll_event() {
+            self.publish(&event);
+            match event {
+                MuxEvent::DlcOpened(dlci) => {
+                    if let Some((route, reply)) = self.pending.remove(&dlci) {
+                        if reply.send(Ok(())).is_ok() {
+                            self.routes.insert(dlci, route);
+                        } else {
+                            // The open was cancelled.
+                            let _ = self.mux.close(dlci, Instant::now());
+                        }
+                    }
+                }
+                MuxEvent::DlcClosed(dlci) => {
+                    self.routes.remove(&dlci);
+                }
+                MuxEvent::Data { dlci, data } => {
+                    if let Some(route) = self.routes.get(&dlci) {
+                        let _ = route.data.send(data);
+                    }
+                }
+                MuxEvent::ModemStatus(msc) => {
+                    if let Some(route) = self.routes.get(&msc.dlci) {
+                        route.status.send_replace(msc.signals);
+                    }
+                }
+                MuxEvent::Urc { .. }
+                | MuxEvent::Anomaly(_)
+                | MuxEvent::PowerState(_)
+                | MuxEvent::FlowControl { .. }
+                | MuxEvent::Skipped(_)
+                | MuxEvent::LineStatus(_) => {}
+                MuxEvent::Error(e @ (MuxError::Rejected { dlci } | MuxError::Timeout { dlci })) => {
+                    self.fail(dlci, e)
+                }
+                MuxEvent::Error(_) => {}
+            }
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Driver<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Driver")
+            .field("mux", &self.mux)
+            .field("routes", &self.routes.keys())
+            .field("pending", &self.pending.keys())
+            .field("lines", &self.lines.as_ref().map(|lines| lines.last))
+            .finish()
+    }
+}
+
+/// Stream of the events of an asynchronous multiplexer, created by [`AsyncMux::events`]
+///
+/// With the `futures` feature, it implements `futures_core::Stream`, to be used with the
+/// stream combinators.
+#[derive(Debug)]
+pub struct Events {
+    receiver: mpsc::Receiver<MuxEvent>,
+    missed: Arc<AtomicUsize>,
+}
+
+impl Events {
+    /// Waits for the next event.
+    ///
+    /// # Returns
+    ///
+    /// The event, or `None` if the driver stopped.
+    pub async fn next(&mut self) -> Option<MuxEvent> {
+        self.receiver.recv().await
+    }
+
+    /// Returns the number of events dropped so far because the stream was not read fast
+    /// enough to stay under [`EVENTS_CAPACITY`].
+    pub fn missed(&self) -> usize {
+        self.missed.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(feature = "futures")]
+impl futures_core::Stream for Events {
+    type Item = MuxEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<MuxEvent>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// Connected DLC of an asynchronous multiplexer
+///
+/// Reading returns the data received on the DLC, and end of file once it is closed. Writing
+/// sends UIH frames. Shutting down or dropping the channel closes the DLC with DISC.
+#[derive(Debug)]
+pub struct Channel {
+    dlci: u8,
+    data: mpsc::UnboundedReceiver<Vec<u8>>,
+    buffer: Vec<u8>,
+    control: ControlHandle,
+    closed: bool,
+    read_timeout: Option<Duration>,
+    /// When the pending read times out.
+    deadline: Option<Pin<Box<tokio::time::Sleep>>>,
+}
+
+impl Channel {
+    /// Returns the DLCI of the channel.
+    pub fn dlci(&self) -> u8 {
+        self.dlci
+    }
+
+    /// Returns a handle to the V.24 signals of the channel.
+    pub fn control(&self) -> ControlHandle {
+        self.control.clone()
+    }
+
+    /// Sends a break signal lasting `duration`, rounded up to units of 200 ms, with
+    /// [`Mux::send_break`].
+    ///
+    /// The breaks sent by the peer are streamed by [`AsyncMux::events`] as
+    /// [`MuxEvent::ModemStatus`], with [`Msc::break_duration`] set.
+    pub fn send_break(&self, duration: Duration) -> Result<(), MuxError> {
+        self.control
+            .requests
+            .send(Request::Break(self.dlci, duration))
+            .map_err(|_| MuxError::NotConnected { dlci: self.dlci })
+    }
+
+    /// Returns the V.24 signals last received from the peer, all cleared until it sends them.
+    ///
+    /// [`ControlHandle::changed`] waits for the next ones, to follow RI and DCD with
+    /// [`V24Signals::ri`] and [`V24Signals::dcd`].
+    pub fn modem_status(&self) -> V24Signals {
+        self.control.signals()
+    }
+
+    /// Waits for the DLC to be closed, by either station, or for the driver to stop.
+    ///
+    /// The future does not borrow the channel, so it can be awaited in `tokio::select!`
+    /// alongside its reads and writes, to open the channel again once the peer disconnects.
+    pub fn closed(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut signals = self.control.signals.clone();
+        async move { while signals.changed().await.is_ok() {} }
+    }
+
+    /// Sets how long a read waits for data, or `None` to wait until the DLC is closed, the
+    /// default.
+    ///
+    /// A read finding no data in time fails with [`io::ErrorKind::TimedOut`]; the channel
+    /// stays usable, so the next read waits again.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.read_timeout = timeout;
+        self.deadline = None;
+    }
+
+    /// Returns the read timeout.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    fn close(&mut self) {
+        if !self.closed {
+            self.closed = true;
+            let _ = self.control.requests.send(Request::Close(self.dlci));
+        }
+    }
+}
+
+impl AsyncRead for Channel {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.buffer.is_empty() {
+            match self.data.poll_recv(cx) {
+                Poll::Ready(Some(data)) => self.buffer = data,
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending => {
+                    let Some(timeout) = self.read_timeout else {
+                        return Poll::Pending;
+                    };
+                    let deadline = self
+                        .deadline
+                        .get_or_insert_with(|| Box::pin(tokio::time::sleep(timeout)));
+                    if deadline.as_mut().poll(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    self.deadline = None;
+                    return Poll::Ready(Err(io::ErrorKind::TimedOut.into()));
+                }
+            }
+            self.deadline = None;
+        }
+        let count = buf.remaining().min(self.buffer.len());
+        buf.put_slice(&self.buffer[..count]);
+        self.buffer.drain(..count);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for Channel {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.closed || self.data.is_closed() {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        let request = Request::Data(self.dlci, buf.to_vec());
+        match self.control.requests.send(request) {
+            Ok(()) => Poll::Ready(Ok(buf.len())),
+            Err(_) => Poll::Ready(Err(io::ErrorKind::BrokenPipe.into())),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for Channel {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Handle to the V.24 signals of a [`Channel`]
+///
+/// The signals are exchanged with MSC commands on DLCI 0.
+#[derive(Debug, Clone)]
+pub struct ControlHandle {
+    dlci: u8,
+    requests: mpsc::UnboundedSender<Request>,
+    signals: watch::Receiver<V24Signals>,
+}
+
+impl ControlHandle {
+    /// Sends the local V.24 signals to the peer.
+    pub fn set_signals(&self, signals: V24Signals) -> Result<(), MuxError> {
+        let msc = Msc::new(self.dlci).with_signals(signals);
+        self.requests
+            .send(Request::ModemStatus(msc))
+            .map_err(|_| MuxError::NotConnected { dlci: self.dlci })
+    }
+
+    /// Returns the last V.24 signals received from the peer.
+    pub fn signals(&self) -> V24Signals {
+        *self.signals.borrow()
+    }
+
+    /// Waits for the peer to send its V.24 signals.
+    ///
+    /// # Returns
+    ///
+    /// The signals received, or `None` if the channel was closed.
+    pub async fn changed(&mut self) -> Option<V24Signals> {
+        self.signals.changed().await.ok()?;
+        Some(*self.signals.borrow_and_update())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_modem_status() {
+        let (host, modem) = tokio::io::duplex(1024);
+        let (host, host_driver) = new(host, Mux::new());
+        let (modem, modem_driver) = new(modem, Mux::new().with_initiator(false));
+
+        let test = async {
+            host.open(0).await.unwrap();
+            let (at, peer) = tokio::join!(host.open(2), modem.accept(2));
+            let (at, peer) = (at.unwrap(), peer.unwrap());
+            let mut control = peer.control();
+            at.control()
+                .set_signals(V24Signals::new().with_rtc(true).with_dv(true))
+                .unwrap();
+            let signals = control.changed().await.unwrap();
+            assert!(signals.rtc() && signals.dv() && !signals.fc());
+            assert_eq!(control.signals(), signals);
+            assert!(peer.modem_status().dcd());
+
+            let mut events = modem.events();
+            at.send_break(Duration::from_millis(200)).unwrap();
+            while let Some(event) = events.next().await {
+                if let MuxEvent::ModemStatus(msc) = event {
+                    assert_eq!(msc.break_duration(), Some(Duration::from_millis(200)));
+                    break;
+                }
+            }
+
+            let closed = peer.closed();
+            drop(at);
+            closed.await;
+            let mut peer = peer;
+            let mut buf = Vec::new();
+            assert_eq!(peer.read_to_end(&mut buf).await.unwrap(), 0);
+            assert!(peer.write_all(b"AT").await.is_err());
+        };
+        tokio::select! {
+            _ = test => {}
+            _ = host_driver.run() => unreachable!(),
+            _ = modem_driver.run() => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_break() {
+        let (host, modem) = tokio::io::duplex(1024);
+        let (host, host_driver) = new(host, Mux::new());
+        let (modem, modem_driver) = new(modem, Mux::new().with_initiator(false));
+
+        let test = async {
+            host.open(0).await.unwrap();
+            let (at, peer) = tokio::join!(host.open(2), modem.accept(2));
+            let (at, peer) = (at.unwrap(), peer.unwrap());
+            let mut control = peer.control();
+            let signals = V24Signals::new().with_rtc(true).with_rtr(true);
+            at.control().set_signals(signals).unwrap();
+            control.changed().await.unwrap();
+
+            // The break is rounded up to 200 ms units and carries the signals last set.
+            let mut events = modem.events();
+            at.send_break(Duration::from_millis(250)).unwrap();
+            let msc = loop {
+                match events.next().await {
+                    Some(MuxEvent::ModemStatus(msc)) => break msc,
+                    Some(_) => {}
+                    None => panic!("no break received"),
+                }
+            };
+            assert_eq!(msc.dlci, 2);
+            assert_eq!(msc.break_duration(), Some(Duration::from_millis(400)));
+            assert_eq!(msc.signals, signals);
+            assert_eq!(control.signals(), signals);
+        };
+        tokio::select! {
+            _ = test => {}
+            _ = host_driver.run() => unreachable!(),
+            _ = modem_driver.run() => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_events() {
+        let (host, modem) = tokio::io::duplex(1024);
+        let (host, host_driver) = new(host, Mux::new());
+        let (modem, modem_driver) = new(modem, Mux::new().with_initiator(false));
+        let mut events = modem.events();
+
+        let test = async {
+            let _control = host.open(0).await.unwrap();
+            let mut at = host.open(1).await.unwrap();
+            at.write_all(b"AT").await.unwrap();
+            drop(at);
+            let mut received = Vec::new();
+            while let Some(event) = events.next().await {
+                received.push(event);
+                if received.len() == 4 {
+                    break;
+                }
+            }
+            assert_eq!(
+                received,
+                vec![
+                    MuxEvent::DlcOpened(0),
+                    MuxEvent::DlcOpened(1),
+                    MuxEvent::Data {
+                        dlci: 1,
+                        data: b"AT".to_vec()
+                    },
+                    MuxEvent::DlcClosed(1),
+                ]
+            );
+        };
+        tokio::select! {
+            _ = test => {}
+            _ = host_driver.run() => unreachable!(),
+            _ = modem_driver.run() => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_line_watch() {
+        let (host, _modem) = tokio::io::duplex(1024);
+        let lines = Arc::new(Mutex::new(LineStatus {
+            signals: V24Signals::new().with_rtc(true).with_rtr(true),
+            breaks: 3,
+        }));
+        let port = lines.clone();
+        let (host, host_driver) = new(host, Mux::new());
+        let host_driver = host_driver
+            .with_line_watch(Duration::from_millis(5), move |_| Ok(*port.lock().unwrap()));
+        let mut events = host.events();
+
+        let test = async {
+            let status = *lines.lock().unwrap();
+            assert_eq!(events.next().await, Some(MuxEvent::LineStatus(status)));
+
+            lines.lock().unwrap().signals.set_dv(true);
+            let Some(MuxEvent::LineStatus(status)) = events.next().await else {
+                panic!("no line status");
+            };
+            assert!(status.signals.dv() && status.breaks == 3);
+
+            lines.lock().unwrap().breaks += 1;
+            let Some(MuxEvent::LineStatus(status)) = events.next().await else {
+                panic!("no line status");
+            };
+            assert_eq!(status.breaks, 4);
+        };
+        // The line watch and the callbacks of the Mux let the driver run on its own task.
+        let driver = tokio::spawn(host_driver.run());
+        test.await;
+        driver.abort();
+    }
+
+    #[tokio::test]
+    async fn test_read_timeout() {
+        let (host, modem) = tokio::io::duplex(1024);
+        let (host, host_driver) = new(host, Mux::new());
+        let (modem, modem_driver) = new(modem, Mux::new().with_initiator(false));
+
+        let test = async {
+            let _control = host.open(0).await.unwrap();
+            let (at, peer) = tokio::join!(host.open(1), modem.accept(1));
+            let (mut at, mut peer) = (at.unwrap(), peer.unwrap());
+            at.set_read_timeout(Some(Duration::from_millis(10)));
+            let mut buf = [0; 2];
+            let e = at.read(&mut buf).await.unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+
+            peer.write_all(b"OK").await.unwrap();
+            at.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"OK");
+        };
+        tokio::select! {
+            _ = test => {}
+            _ = host_driver.run() => unreachable!(),
+            _ = modem_driver.run() => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let (host, modem) = tokio::io::duplex(1024);
+        let (host, host_driver) = new(host, Mux::new());
+        let (modem, modem_driver) = new(modem, Mux::new().with_initiator(false));
+        let mut events = modem.events();
+
+        let test = async {
+            let _control = host.open(0).await.unwrap();
+            // A cancelled open leaves no DLC behind, and the DLCI can be opened again.
+            let open = host.open(1);
+            tokio::select! {
+                biased;
+                _ = open => unreachable!(),
+                _ = std::future::ready(()) => {}
+            }
+            let (at, peer) = tokio::join!(host.open(1), modem.accept(1));
+            let (mut at, mut peer) = (at.unwrap(), peer.unwrap());
+
+            host.shutdown().await;
+            let mut buf = Vec::new();
+            assert_eq!(at.read_to_end(&mut buf).await.unwrap(), 0);
+            assert_eq!(peer.read_to_end(&mut buf).await.unwrap(), 0);
+            let mut closed = Vec::new();
+            while let Some(event) = events.next().await {
+                if let MuxEvent::DlcClosed(dlci) = event {
+                    closed.push(dlci);
+                }
+                if closed.last() == Some(&0) {
+                    break;
+                }
+            }
+            assert_eq!(closed, [1, 0]);
+        };
+        // The modem may fail to write to the pipe closed by the host.
+        let (_, host, _) = tokio::join!(test, host_driver.run(), modem_driver.run());
+        assert!(host.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_open_timeout() {
+        let (host, _peer) = tokio::io::duplex(1024);
+        let (host, driver) = new(
+            host,
+            Mux::new()
+                .with_n2(1)
+                .with_t1(std::time::Duration::from_millis(1)),
+        );
+        tokio::select! {
+            result = host.open(0) => {
+                assert_eq!(result.unwrap_err(), MuxError::Timeout { dlci: 0 });
+            }
+            _ = driver.run() => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use crate::error::{CodecError, FrameError, ParseError};
+use crate::parser::{scan, Scanned};
+use crate::types::{Address, Control, Frame, FrameBuilder};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Converts hex text to bytes.
+///
+/// Whitespace between the digits and `0x` prefixes are ignored, so dumps like
+/// `F9 03 3F 01 1C F9` and `0xF9 0x03` are accepted as well as `F9033F`.
+///
+/// # Returns
+///
+/// - `Ok(Vec<u8>)`: The bytes.
+/// - `Err(CodecError)`: If a character is not a hex digit, or the number of digits is odd.
+///
+/// # Example
+///
+/// ```
+/// use cmux::codec::hex_to_bytes;
+///
+/// assert_eq!(hex_to_bytes("F9 0x03\n3f"), Ok(vec![0xF9, 0x03, 0x3F]));
+/// assert!(hex_to_bytes("F9 0").is_err());
+/// ```
+pub fn hex_to_bytes(hexstring: &str) -> Result<Vec<u8>, CodecError> {
+    let digits: String = hexstring.split_whitespace().collect();
+    let digits = digits.replace("0x", "");
+    if let Some(found) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(CodecError::InvalidHexDigit(found));
+    }
+    if !digits.len().is_multiple_of(2) {
+        return Err(CodecError::OddHexLength(digits.len()));
+    }
+    Ok(hex::decode(digits).expect("the digits were checked"))
+}
+
+/// Converts hex text to bytes chunk by chunk, like [`hex_to_bytes`]
+///
+/// A chunk may end in the middle of a byte or of a `0x` prefix, so text read in blocks of any
+/// size is decoded without holding it whole.
+///
+/// # Example
+///
+/// ```
+/// use cmux::codec::HexDecoder;
+///
+/// let mut decoder = HexDecoder::new();
+/// assert_eq!(decoder.feed(b"F9 0x0").unwrap(), vec![0xF9]);
+/// assert_eq!(decoder.feed(b"3 3").unwrap(), vec![0x03]);
+/// assert!(decoder.finish().is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HexDecoder {
+    /// The high nibble of the byte being decoded.
+    high: Option<u8>,
+    /// True if the last digit is a `0` that may open a `0x` prefix.
+    zero: bool,
+    digits: usize,
+}
+
+impl HexDecoder {
+    /// Creates a decoder at the start of the text.
+    pub fn new() -> Self {
+        HexDecoder::default()
+    }
+
+    /// Decodes the next chunk of text.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Vec<u8>)`: The bytes completed by the chunk.
+    /// - `Err(CodecError)`: If a character is neither a hex digit nor whitespace.
+    pub fn feed(&mut self, text: &[u8]) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = Vec::with_capacity(text.len() / 2 + 1);
+        for &c in text {
+            if c.is_ascii_whitespace() {
+                continue;
+            }
+            if core::mem::take(&mut self.zero) {
+                if c == b'x' {
+                    continue;
+                }
+                self.digit(0, &mut bytes);
+            }
+            if c == b'0' {
+                self.zero = true;
+                continue;
+            }
+            let nibble = char::from(c)
+                .to_digit(16)
+                .ok_or(CodecError::InvalidHexDigit(char::from(c)))?;
+            self.digit(nibble as u8, &mut bytes);
+        }
+        Ok(bytes)
+    }
+
+    /// Ends the text.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Vec<u8>)`: The last byte, if its digits ended the text.
+    /// - `Err(CodecError)`: If the number of digits is odd.
+    pub fn finish(&mut self) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = Vec::new();
+        if core::mem::take(&mut self.zero) {
+            self.digit(0, &mut bytes);
+        }
+        if self.high.is_some() {
+            return Err(CodecError::OddHexLength(self.digits));
+        }
+        Ok(bytes)
+    }
+
+    fn digit(&mut self, nibble: u8, bytes: &mut Vec<u8>) {
+        self.digits += 1;
+        match self.high.take() {
+            Some(high) => bytes.push((high << 4) | nibble),
+            None => self.high = Some(nibble),
+        }
+    }
+}
+
+/// Decodes every basic option frame of hex text.
+///
+/// The bytes go through [`scan`], so noise between the frames is dropped. Frames are returned
+/// as read: a frame whose FCS is wrong is kept, to be told by [`Frame::verify`].
+///
+/// # Returns
+///
+/// - `Ok(Vec<Frame>)`: The frames, in stream order.
+/// - `Err(CodecError)`: If the text is not hex, or delimited octets do not decode as a frame,
+///   located by their offset in the bytes.
+///
+/// # Example
+///
+/// ```
+/// use cmux::codec::parse_hex_stream;
+///
+/// let frames = parse_hex_stream("F9033F011CF9 00 F9073F01DEF9").unwrap();
+/// assert_eq!(frames.len(), 2);
+/// assert!(frames.iter().all(|frame| frame.verify().is_ok()));
+/// ```
+pub fn parse_hex_stream(hexstring: &str) -> Result<Vec<Frame>, CodecError> {
+    let data = hex_to_bytes(hexstring)?;
+    let mut frames = Vec::new();
+    for scanned in scan(&data) {
+        match scanned {
+            Scanned::Frame { frame, .. } => frames.push(frame),
+            Scanned::Invalid { offset, error, .. } => {
+                return Err(ParseError::new(&data, offset, error).into())
+            }
+            Scanned::Skipped { .. } => {}
+        }
+    }
+    Ok(frames)
+}
+
+/// Encodes `frames` as one stream of lowercase hex text, each frame with its own flags.
+///
+/// # Example
+///
+/// ```
+/// use cmux::codec::{frames_to_hex, parse_hex_stream};
+///
+/// let frames = parse_hex_stream("F9033F011CF9F9073F01DEF9").unwrap();
+/// assert_eq!(frames_to_hex(&frames), "f9033f011cf9f9073f01def9");
+/// ```
+pub fn frames_to_hex(frames: &[Frame]) -> String {
+    frames.iter().map(Frame::to_hex_string).collect()
+}
+
+/// Builds a basic option frame, like `cmux generate`.
+///
+/// # Arguments
+///
+/// * `address` - The address field.
+/// * `control` - The control field.
+/// * `content` - The information field, to which CR LF is appended, if any.
+///
+/// # Example
+///
+/// ```
+/// use cmux::codec::generate;
+/// use cmux::types::{Address, Control};
+///
+/// let frame = generate(Address::from_bits(0x03), Control::from_bits(0x3F), None).unwrap();
+/// assert_eq!(frame.to_hex_string(), "f9033f011cf9");
+/// ```
+pub fn generate(
+    address: Address,
+    control: Control,
+    content: Option<String>,
+) -> Result<Frame, FrameError> {
+    let mut builder = FrameBuilder::default();
+    builder.with_address(address).with_control(control);
+    if let Some(content) = content {
+        builder.with_content(content);
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    #[test]
+    fn test_hex_to_bytes() {
+        assert_eq!(hex_to_bytes("F9010203F9"), Ok(vec![249, 1, 2, 3, 249]));
+        assert_eq!(hex_to_bytes("F9 01 02 03 F9"), Ok(vec![249, 1, 2, 3, 249]));
+        assert_eq!(
+            hex_to_bytes("F9\n01\n02\n03\nF9"),
+            Ok(vec![249, 1, 2, 3, 249])
+        );
+        assert_eq!(
+            hex_to_bytes("0xF9 0x01 0x02 0x03 0xF9"),
+            Ok(vec![249, 1, 2, 3, 249])
+        );
+        assert_eq!(hex_to_bytes("F9G1"), Err(CodecError::InvalidHexDigit('G')));
+        assert_eq!(hex_to_bytes("F90"), Err(CodecError::OddHexLength(3)));
+    }
+
+    #[test]
+    fn test_hex_decoder() {
+        let text = b"0xF9 0x01 02\n03 F900";
+        for size in 1..text.len() {
+            let mut decoder = HexDecoder::new();
+            let mut bytes = Vec::new();
+            for chunk in text.chunks(size) {
+                bytes.extend(decoder.feed(chunk).unwrap());
+            }
+            bytes.extend(decoder.finish().unwrap());
+            assert_eq!(bytes, vec![249, 1, 2, 3, 249, 0]);
+        }
+        let mut decoder = HexDecoder::new();
+        assert_eq!(decoder.feed(b"F9G1"), Err(CodecError::InvalidHexDigit('G')));
+        let mut decoder = HexDecoder::new();
+        assert_eq!(decoder.feed(b"F9 0"), Ok(vec![249]));
+        assert_eq!(decoder.finish(), Err(CodecError::OddHexLength(3)));
+    }
+
+    #[test]
+    fn test_generate() {
+        let frame = generate(
+            Address::default(),
+            Control::default(),
+            Some("010203".to_string()),
+        )
+        .unwrap();
+        assert_eq!(frame.to_hex_string(), "f907ef113031303230330d0a2bf9");
+        let frame = generate(Address::from_bits(0x03), Control::from_bits(0x3F), None).unwrap();
+        assert_eq!(frame.to_hex_string(), "f9033f011cf9");
+    }
+
+    #[test]
+    fn test_parse_hex_stream() {
+        let frames = parse_hex_stream("F9033F011CF9 F9073F011CF9").unwrap();
+        assert_eq!(frames.len(), 2);
+        assert!(frames[1].verify().is_err());
+        assert_eq!(frames_to_hex(&frames), "f9033f011cf9f9073f011cf9");
+        assert_eq!(parse_hex_stream("12"), Ok(Vec::new()));
+    }
+}
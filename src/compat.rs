@@ -0,0 +1,115 @@
+use crate::types::{Address, Control, Frame, FrameBuilder};
+use alloc::string::ToString;
+
+/// Version of the wire format described by [`FIXTURES`].
+///
+/// It is bumped whenever the encoding of an existing fixture changes.
+pub const WIRE_VERSION: u32 = 1;
+
+/// A frozen frame and the fields it is expected to decode to.
+#[derive(Debug, Clone, Copy)]
+pub struct Fixture {
+    pub name: &'static str,
+    pub hex: &'static str,
+    pub address: u8,
+    pub control: u8,
+    pub content: &'static str,
+}
+
+/// Frames whose encoding must not change within a [`WIRE_VERSION`].
+pub const FIXTURES: &[Fixture] = &[
+    Fixture {
+        name: "sabm_dlci0",
+        hex: "F9033F011CF9",
+        address: 0x03,
+        control: 0x3F,
+        content: "",
+    },
+    Fixture {
+        name: "sabm_dlci1",
+        hex: "F9073F01DEF9",
+        address: 0x07,
+        control: 0x3F,
+        content: "",
+    },
+    Fixture {
+        name: "uih_dlci1_at_cmux",
+        hex: "F907EF1541542B434D55583F0D0A2CF9",
+        address: 0x07,
+        control: 0xEF,
+        content: "AT+CMUX?\r\n",
+    },
+    Fixture {
+        name: "uih_dlci1_digits",
+        hex: "F907EF113031303230330D0A2BF9",
+        address: 0x07,
+        control: 0xEF,
+        content: "010203\r\n",
+    },
+];
+
+/// Asserts that `frame` survives an encode/decode round trip unchanged.
+///
+/// # Panics
+///
+/// Panics if the decoded frame differs from `frame`.
+pub fn assert_roundtrip(frame: &Frame) {
+    let decoded = Frame::from_bytes(frame.to_bytes()).expect("frame decodes after a round trip");
+    assert_eq!(&decoded, frame, "frame changed after a round trip");
+}
+
+/// Asserts that this version of the crate encodes and decodes every fixture in [`FIXTURES`]
+/// exactly as wire format [`WIRE_VERSION`] requires.
+///
+/// # Panics
+///
+/// Panics with the name of the first fixture that no longer matches.
+///
+/// # Example
+///
+/// ```
+/// cmux::compat::assert_wire_compatible();
+/// ```
+pub fn assert_wire_compatible() {
+    for fixture in FIXTURES {
+        let bytes = hex::decode(fixture.hex).expect("fixture is valid hex");
+        let frame = Frame::from_bytes(bytes.clone())
+            .unwrap_or_else(|e| panic!("{}: decode: {}", fixture.name, e));
+        assert_eq!(
+            frame.address.into_bits(),
+            fixture.address,
+            "{}: address",
+            fixture.name
+        );
+        assert_eq!(
+            frame.control.into_bits(),
+            fixture.control,
+            "{}: control",
+            fixture.name
+        );
+        assert_eq!(frame.content, fixture.content, "{}: content", fixture.name);
+        assert!(frame.verify().is_ok(), "{}: verify", fixture.name);
+        assert_eq!(frame.to_bytes(), bytes, "{}: re-encode", fixture.name);
+
+        if !fixture.content.is_empty() {
+            let built = FrameBuilder::default()
+                .with_address(Address::from_bits(fixture.address))
+                .with_control(Control::from_bits(fixture.control))
+                .with_content(fixture.content.to_string())
+                .build()
+                .unwrap_or_else(|e| panic!("{}: build: {}", fixture.name, e));
+            assert_eq!(built.to_bytes(), bytes, "{}: build", fixture.name);
+        }
+        assert_roundtrip(&frame);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wire_compatible() {
+        assert_wire_compatible();
+    }
+}
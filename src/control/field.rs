@@ -0,0 +1,132 @@
+use alloc::vec::Vec;
+
+/// Field of a control message defined with [`cmux_message`](super::cmux_message)
+///
+/// Integers are encoded least significant octet first, like the N1 field of
+/// [`ParameterNegotiation`](super::ParameterNegotiation). A `Vec<u8>` takes every octet left,
+/// so it can only be the last field of a message.
+pub trait MessageField: Sized {
+    /// Smallest number of octets of the field.
+    const MIN_LEN: usize;
+
+    /// Appends the octets of the field to `value`.
+    fn encode_field(&self, value: &mut Vec<u8>);
+
+    /// Decodes the field from the start of `value` and advances `value` past its octets.
+    ///
+    /// # Returns
+    ///
+    /// - `Some(Self)`: The field.
+    /// - `None`: If `value` is too short.
+    fn decode_field(value: &mut &[u8]) -> Option<Self>;
+}
+
+/// Splits the first `N` octets off `value`.
+fn take<const N: usize>(value: &mut &[u8]) -> Option<[u8; N]> {
+    let (octets, rest) = value.split_first_chunk::<N>()?;
+    *value = rest;
+    Some(*octets)
+}
+
+macro_rules! integer_field {
+    ($($ty:ty),*) => {
+        $(
+            impl MessageField for $ty {
+                const MIN_LEN: usize = core::mem::size_of::<$ty>();
+
+                fn encode_field(&self, value: &mut Vec<u8>) {
+                    value.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode_field(value: &mut &[u8]) -> Option<Self> {
+                    take(value).map(<$ty>::from_le_bytes)
+                }
+            }
+        )*
+    };
+}
+
+integer_field!(u8, u16, u32);
+
+impl<const N: usize> MessageField for [u8; N] {
+    const MIN_LEN: usize = N;
+
+    fn encode_field(&self, value: &mut Vec<u8>) {
+        value.extend_from_slice(self);
+    }
+
+    fn decode_field(value: &mut &[u8]) -> Option<Self> {
+        take(value)
+    }
+}
+
+impl MessageField for Vec<u8> {
+    const MIN_LEN: usize = 0;
+
+    fn encode_field(&self, value: &mut Vec<u8>) {
+        value.extend_from_slice(self);
+    }
+
+    fn decode_field(value: &mut &[u8]) -> Option<Self> {
+        Some(core::mem::take(value).to_vec())
+    }
+}
+
+#[cfg(all(test, feature = "macros"))]
+mod tests {
+    use crate::control::{cmux_message, ControlMessage, MessageType};
+    use crate::error::ControlError;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[cmux_message(type = 0x3E)]
+    #[derive(Debug, PartialEq, Eq)]
+    struct Vendor {
+        id: u32,
+        tag: [u8; 2],
+        data: Vec<u8>,
+    }
+
+    #[cmux_message(type = 0x02)]
+    #[derive(Debug, PartialEq, Eq)]
+    struct Ping {}
+
+    #[test]
+    fn test_control_message() {
+        let vendor = Vendor {
+            id: 0x0102_0304,
+            tag: *b"OK",
+            data: vec![0xAA, 0xBB],
+        };
+        let msg = vendor.to_message(false);
+        assert_eq!(msg.message_type, MessageType::Other(0x3E));
+        assert_eq!(
+            msg.value,
+            vec![0x04, 0x03, 0x02, 0x01, b'O', b'K', 0xAA, 0xBB]
+        );
+        assert_eq!(Vendor::try_from(&msg), Ok(vendor));
+        assert_eq!(
+            Vendor::decode(&[0x04, 0x03, 0x02, 0x01, b'O']),
+            Err(ControlError::BadValueLength {
+                expected: 6,
+                actual: 5
+            })
+        );
+        assert_eq!(
+            Vendor::try_from(&ControlMessage::test(true, Vec::new())),
+            Err(ControlError::UnexpectedType {
+                expected: MessageType::Other(0x3E),
+                found: MessageType::Test
+            })
+        );
+
+        assert_eq!(Ping {}.to_message(true).encode(), vec![0x0B, 0x01]);
+        assert_eq!(
+            Ping::decode(&[0x01]),
+            Err(ControlError::BadValueLength {
+                expected: 0,
+                actual: 1
+            })
+        );
+    }
+}
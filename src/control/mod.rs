@@ -0,0 +1,442 @@
+use crate::error::{ControlError, FrameError};
+use crate::types::{Address, Control, Frame, FrameBuilder, FrameType, DLCI};
+use alloc::vec;
+use alloc::vec::Vec;
+
+mod field;
+mod msc;
+mod nsc;
+mod pn;
+mod rpn;
+
+pub use field::MessageField;
+pub(crate) use msc::{break_units, decode_break, encode_status};
+pub use msc::{Msc, V24Signals, BREAK_UNIT};
+pub use nsc::Nsc;
+pub use pn::{ConvergenceLayer, ParameterNegotiation, PnFrameType};
+pub use rpn::{
+    BaudRate, DataBits, FlowControl, ParameterMask, Parity, PortSettings, RemotePortNegotiation,
+    StopBits,
+};
+
+/// Defines a control message type, such as a vendor command on DLCI 0, from a struct.
+///
+/// The `type` argument of the attribute gives the 6-bit [`MessageType`] field, and the value
+/// octets are the named fields of the struct in order, each a [`MessageField`]. The struct
+/// gets the methods of the built-in messages like [`Msc`]:
+///
+/// * `MESSAGE_TYPE`, the [`MessageType`] of the message.
+/// * `encode(&self) -> Vec<u8>` and `decode(value: &[u8]) -> Result<Self, ControlError>`, for
+///   the value octets. Decoding fails with [`ControlError::BadValueLength`] if octets are
+///   missing or left over.
+/// * `to_message(&self, cr: bool) -> ControlMessage`.
+/// * `TryFrom<&ControlMessage>`, failing with [`ControlError::UnexpectedType`] for a message
+///   of another type.
+///
+/// [`ControlMessage::decode`] already keeps the messages of unknown types as
+/// [`MessageType::Other`], so no change to the parser is needed. The attribute is a procedural
+/// macro of the `cmux-macros` crate, enabled with the `macros` feature.
+///
+/// [`ControlError::BadValueLength`]: crate::error::ControlError::BadValueLength
+/// [`ControlError::UnexpectedType`]: crate::error::ControlError::UnexpectedType
+///
+/// # Example
+///
+/// ```
+/// use cmux::control::{cmux_message, ControlMessage, MessageType};
+///
+/// /// Battery report of a vendor firmware.
+/// #[cmux_message(type = 0x3C)]
+/// #[derive(Debug, Clone, PartialEq, Eq)]
+/// pub struct Battery {
+///     pub level: u8,
+///     pub millivolts: u16,
+/// }
+///
+/// let battery = Battery { level: 80, millivolts: 3900 };
+/// let msg = battery.to_message(true);
+/// assert_eq!(msg.encode(), vec![0xF3, 0x07, 0x50, 0x3C, 0x0F]);
+///
+/// let (decoded, _) = ControlMessage::decode(&msg.encode()).unwrap();
+/// assert_eq!(decoded.message_type, MessageType::Other(0x3C));
+/// assert_eq!(Battery::try_from(&decoded).unwrap(), battery);
+/// assert!(Battery::decode(&[0x50, 0x3C]).is_err());
+/// ```
+#[cfg(feature = "macros")]
+pub use cmux_macros::cmux_message;
+
+/// Items used by [`cmux_message`], not part of the API.
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::vec::Vec;
+}
+
+/// Type of a multiplexer control message carried on DLCI 0.
+///
+/// | **Type**                              | **Value** |
+/// |---------------------------------------|-----------|
+/// | PN (DLC parameter negotiation)        | 0x20      |
+/// | PSC (Power Saving Control)            | 0x10      |
+/// | CLD (Multiplexer close down)          | 0x30      |
+/// | Test (Test Command)                   | 0x08      |
+/// | FCon (Flow Control On Command)        | 0x28      |
+/// | FCoff (Flow Control Off Command)      | 0x18      |
+/// | MSC (Modem Status Command)            | 0x38      |
+/// | NSC (Non Supported Command Response)  | 0x04      |
+/// | RPN (Remote Port Negotiation Command) | 0x24      |
+/// | RLS (Remote Line Status Command)      | 0x14      |
+/// | SNC (Service Negotiation Command)     | 0x34      |
+///
+/// The value is the 6-bit type field, without the EA and C/R bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MessageType {
+    PN,
+    PSC,
+    CLD,
+    Test,
+    FCon,
+    FCoff,
+    MSC,
+    NSC,
+    RPN,
+    RLS,
+    SNC,
+    Other(u8),
+}
+
+impl MessageType {
+    /// Returns the 6-bit type field.
+    pub const fn into_bits(self) -> u8 {
+        match self {
+            MessageType::PN => 0x20,
+            MessageType::PSC => 0x10,
+            MessageType::CLD => 0x30,
+            MessageType::Test => 0x08,
+            MessageType::FCon => 0x28,
+            MessageType::FCoff => 0x18,
+            MessageType::MSC => 0x38,
+            MessageType::NSC => 0x04,
+            MessageType::RPN => 0x24,
+            MessageType::RLS => 0x14,
+            MessageType::SNC => 0x34,
+            MessageType::Other(value) => value & 0x3F,
+        }
+    }
+
+    /// Creates a message type from its 6-bit type field.
+    pub const fn from_bits(value: u8) -> Self {
+        match value & 0x3F {
+            0x20 => MessageType::PN,
+            0x10 => MessageType::PSC,
+            0x30 => MessageType::CLD,
+            0x08 => MessageType::Test,
+            0x28 => MessageType::FCon,
+            0x18 => MessageType::FCoff,
+            0x38 => MessageType::MSC,
+            0x04 => MessageType::NSC,
+            0x24 => MessageType::RPN,
+            0x14 => MessageType::RLS,
+            0x34 => MessageType::SNC,
+            value => MessageType::Other(value),
+        }
+    }
+}
+
+/// Multiplexer control message
+///
+/// Control messages are carried in the information field of UIH frames on DLCI 0:
+///
+/// | **Name** | Type    | Length          | Value 1 | Value 2 | ... | Value n |
+/// |----------|---------|-----------------|---------|---------|-----|---------|
+/// | **Size** | 1 octet | 1 or more octets| 1 octet | 1 octet | ... | 1 octet |
+///
+/// * Type: EA bit (always 1), C/R bit (1 for a command, 0 for a response) and the 6-bit [`MessageType`].
+/// * Length: number of value octets, 7 bits per octet with an EA bit set on the last octet.
+///
+/// # Example
+///
+/// ```
+/// use cmux::control::{ControlMessage, MessageType};
+///
+/// let msg = ControlMessage::new(MessageType::Test, true, vec![0x01, 0x02]);
+/// assert_eq!(msg.encode(), vec![0x23, 0x05, 0x01, 0x02]);
+///
+/// let (decoded, used) = ControlMessage::decode(&msg.encode()).unwrap();
+/// assert_eq!(decoded, msg);
+/// assert_eq!(used, 4);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ControlMessage {
+    pub message_type: MessageType,
+    pub cr: bool,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::bytes"))]
+    pub value: Vec<u8>,
+}
+
+impl ControlMessage {
+    /// Creates a control message.
+    ///
+    /// # Arguments
+    ///
+    /// * `message_type` - The type of the message.
+    /// * `cr` - True for a command, false for a response.
+    /// * `value` - The value octets.
+    pub fn new(message_type: MessageType, cr: bool, value: Vec<u8>) -> Self {
+        ControlMessage {
+            message_type,
+            cr,
+            value,
+        }
+    }
+
+    /// Creates a multiplexer close-down (CLD) message, which has no value octets.
+    ///
+    /// # Arguments
+    ///
+    /// * `cr` - True for a command, false for a response.
+    pub fn close_down(cr: bool) -> Self {
+        ControlMessage::new(MessageType::CLD, cr, Vec::new())
+    }
+
+    /// Creates a Test message. The peer echoes the value octets of a command in its response.
+    ///
+    /// # Arguments
+    ///
+    /// * `cr` - True for a command, false for a response.
+    /// * `value` - The test pattern.
+    pub fn test(cr: bool, value: Vec<u8>) -> Self {
+        ControlMessage::new(MessageType::Test, cr, value)
+    }
+
+    /// Encodes the message into type, length and value octets.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = vec![(self.message_type.into_bits() << 2) | ((self.cr as u8) << 1) | 0x1];
+        data.extend(encode_length(self.value.len()));
+        data.extend_from_slice(&self.value);
+        data
+    }
+
+    /// Decodes one message from the start of `data`.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok((ControlMessage, usize))`: The message and the number of octets it occupies.
+    /// - `Err(ControlError)`: If the message is truncated, uses an extended type field or a
+    ///   length field longer than [`MAX_LENGTH_OCTETS`].
+    pub fn decode(data: &[u8]) -> Result<(ControlMessage, usize), ControlError> {
+        let typ = *data.first().ok_or(ControlError::Truncated {
+            expected: 2,
+            actual: 0,
+        })?;
+        if typ & 0x1 == 0 {
+            return Err(ControlError::ExtendedType(typ));
+        }
+        let (len, octets) = decode_length(&data[1..])?;
+        let start = 1 + octets;
+        let end = start
+            .checked_add(len)
+            .ok_or(ControlError::LengthOverflow(MAX_LENGTH_OCTETS))?;
+        if data.len() < end {
+            return Err(ControlError::Truncated {
+                expected: end,
+                actual: data.len(),
+            });
+        }
+        let message = ControlMessage {
+            message_type: MessageType::from_bits(typ >> 2),
+            cr: typ & 0x2 != 0,
+            value: data[start..end].to_vec(),
+        };
+        Ok((message, end))
+    }
+
+    /// Checks that the message has the given type and between `min` and `max` value octets.
+    pub(crate) fn expect(
+        &self,
+        message_type: MessageType,
+        min: usize,
+        max: usize,
+    ) -> Result<(), ControlError> {
+        if self.message_type != message_type {
+            return Err(ControlError::UnexpectedType {
+                expected: message_type,
+                found: self.message_type,
+            });
+        }
+        let len = self.value.len();
+        if len < min || len > max {
+            return Err(ControlError::BadValueLength {
+                expected: len.clamp(min, max),
+                actual: len,
+            });
+        }
+        Ok(())
+    }
+
+    /// Decodes every message in `data`, such as the information field of a DLCI 0 frame.
+    pub fn decode_all(mut data: &[u8]) -> Result<Vec<ControlMessage>, ControlError> {
+        let mut messages = Vec::new();
+        while !data.is_empty() {
+            let (message, used) = ControlMessage::decode(data)?;
+            messages.push(message);
+            data = &data[used..];
+        }
+        Ok(messages)
+    }
+
+    /// Wraps the message in a UIH frame on DLCI 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `cr` - The C/R bit of the frame address.
+    pub fn to_frame(&self, cr: bool) -> Result<Frame, FrameError> {
+        FrameBuilder::default()
+            .with_address(Address::default().with_cr(cr).with_dlci(DLCI::OTHER(0)))
+            .with_control(Control::default().with_frame_type(FrameType::UIH))
+            .with_binary_content(self.encode())
+            .build()
+    }
+
+    /// Decodes the control messages carried by a frame.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Vec<ControlMessage>)`: The messages in the information field.
+    /// - `Err(ControlError)`: If the frame is not a UIH/UI frame on DLCI 0, or a message is malformed.
+    pub fn from_frame(frame: &Frame) -> Result<Vec<ControlMessage>, ControlError> {
+        let dlci = frame.address.dlci().into_bits();
+        let frame_type = frame.control.frame_type();
+        if dlci != 0 || !matches!(frame_type, FrameType::UIH | FrameType::UI) {
+            return Err(ControlError::NotControlFrame);
+        }
+        ControlMessage::decode_all(frame.content.as_bytes())
+    }
+}
+
+/// Largest number of octets of a message length field, enough for the 32767 octets of the
+/// longest information field.
+pub const MAX_LENGTH_OCTETS: usize = 3;
+
+/// Encodes a length with EA extension: 7 bits per octet, least significant first.
+fn encode_length(mut len: usize) -> Vec<u8> {
+    let mut octets = Vec::new();
+    loop {
+        let bits = ((len & 0x7F) as u8) << 1;
+        len >>= 7;
+        if len == 0 {
+            octets.push(bits | 0x1);
+            return octets;
+        }
+        octets.push(bits);
+    }
+}
+
+/// Decodes an EA-extended length, returning the length and the number of octets it occupies.
+fn decode_length(data: &[u8]) -> Result<(usize, usize), ControlError> {
+    let mut len = 0;
+    for (i, &octet) in data.iter().take(MAX_LENGTH_OCTETS).enumerate() {
+        len |= ((octet >> 1) as usize) << (7 * i);
+        if octet & 0x1 == 1 {
+            return Ok((len, i + 1));
+        }
+    }
+    if data.len() >= MAX_LENGTH_OCTETS {
+        return Err(ControlError::LengthOverflow(MAX_LENGTH_OCTETS));
+    }
+    Err(ControlError::Truncated {
+        expected: 2 + data.len(),
+        actual: 1 + data.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_type_bits() {
+        for bits in 0..0x40 {
+            assert_eq!(MessageType::from_bits(bits).into_bits(), bits);
+        }
+        assert_eq!(MessageType::from_bits(0x38), MessageType::MSC);
+    }
+
+    #[test]
+    fn test_long_length() {
+        let msg = ControlMessage::new(MessageType::Test, false, vec![0xAA; 200]);
+        let data = msg.encode();
+        assert_eq!(&data[..3], &[0x21, 0x90, 0x03]);
+        assert_eq!(ControlMessage::decode(&data).unwrap(), (msg, 203));
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(
+            ControlMessage::decode(&[0xE3, 0x05, 0x07]),
+            Err(ControlError::Truncated {
+                expected: 4,
+                actual: 3
+            })
+        );
+        assert_eq!(
+            ControlMessage::decode(&[0xE2, 0x01]),
+            Err(ControlError::ExtendedType(0xE2))
+        );
+    }
+
+    #[test]
+    fn test_length_overflow() {
+        // Ten length octets without the EA bit would shift past the width of usize.
+        let data = hex::decode("E3000000000000000000000100").unwrap();
+        assert_eq!(
+            ControlMessage::decode(&data),
+            Err(ControlError::LengthOverflow(MAX_LENGTH_OCTETS))
+        );
+        let frame = FrameBuilder::default()
+            .with_address(Address::default().with_dlci(DLCI::OTHER(0)))
+            .with_binary_content(data)
+            .build()
+            .unwrap();
+        assert!(ControlMessage::from_frame(&frame).is_err());
+        assert_eq!(
+            ControlMessage::decode(&[0xE3, 0x00]),
+            Err(ControlError::Truncated {
+                expected: 3,
+                actual: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let messages = vec![
+            ControlMessage::new(MessageType::MSC, true, vec![0x07, 0x0D]),
+            ControlMessage::new(MessageType::FCon, true, vec![]),
+        ];
+        let mut content = messages[0].encode();
+        content.extend(messages[1].encode());
+        let frame = FrameBuilder::default()
+            .with_address(Address::default().with_dlci(DLCI::OTHER(0)))
+            .with_binary_content(content)
+            .build()
+            .unwrap();
+        assert_eq!(ControlMessage::from_frame(&frame).unwrap(), messages);
+
+        let frame = messages[0].to_frame(true).unwrap();
+        assert_eq!(frame.to_hex_string(), "f903ef09e305070dfbf9");
+        assert!(frame.verify().is_ok());
+
+        let frame = FrameBuilder::default()
+            .with_content("AT".to_string())
+            .build()
+            .unwrap();
+        assert_eq!(
+            ControlMessage::from_frame(&frame),
+            Err(ControlError::NotControlFrame)
+        );
+    }
+}
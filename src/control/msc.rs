@@ -0,0 +1,319 @@
+use super::{ControlMessage, MessageType};
+use crate::error::ControlError;
+use alloc::vec;
+use alloc::vec::Vec;
+use bitfield_struct::bitfield;
+use core::fmt::{Display, Formatter};
+use core::time::Duration;
+
+/// V.24 signals octet of [`Msc`]
+///
+/// <table>
+///   <tr>
+///     <th>Bit No.</th>
+///     <td>1</td>
+///     <td>2</td>
+///     <td>3</td>
+///     <td>4</td>
+///     <td>5</td>
+///     <td>6</td>
+///     <td>7</td>
+///     <td>8</td>
+///   </tr>
+///   <tr>
+///     <th>Signal</th>
+///     <td>EA</td>
+///     <td>FC</td>
+///     <td>RTC</td>
+///     <td>RTR</td>
+///     <td colspan=2 align="center">reserved</td>
+///     <td>IC</td>
+///     <td>DV</td>
+///   </tr>
+/// </table>
+///
+/// * FC: Flow Control, set when the sender cannot accept frames.
+/// * RTC: Ready To Communicate, mapped to DTR/DSR.
+/// * RTR: Ready To Receive, mapped to RTS/CTS.
+/// * IC: Incoming Call indicator, mapped to RI.
+/// * DV: Data Valid, mapped to DCD.
+///
+/// The EA bit is managed by [`Msc::encode`] and depends on whether a break octet follows.
+///
+/// # Example
+///
+/// ```
+/// use cmux::control::V24Signals;
+///
+/// let signals = V24Signals::new().with_rtc(true).with_rtr(true);
+/// assert_eq!(signals.into_bits(), 0b1100);
+/// ```
+#[bitfield(u8, default = false)]
+#[derive(PartialEq, Eq, Default)]
+pub struct V24Signals {
+    pub ea: bool,
+    pub fc: bool,
+    pub rtc: bool,
+    pub rtr: bool,
+    #[bits(2)]
+    __: u8,
+    pub ic: bool,
+    pub dv: bool,
+}
+
+impl V24Signals {
+    /// Returns the DSR line of a DTE receiving these signals, from RTC.
+    pub const fn dsr(&self) -> bool {
+        self.rtc()
+    }
+
+    /// Returns the CTS line of a DTE receiving these signals, from RTR.
+    pub const fn cts(&self) -> bool {
+        self.rtr()
+    }
+
+    /// Returns the RI line of a DTE receiving these signals, from IC.
+    pub const fn ri(&self) -> bool {
+        self.ic()
+    }
+
+    /// Returns the DCD line of a DTE receiving these signals, from DV.
+    pub const fn dcd(&self) -> bool {
+        self.dv()
+    }
+}
+
+impl Display for V24Signals {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "FC={} RTC={} RTR={} IC={} DV={}",
+            self.fc() as u8,
+            self.rtc() as u8,
+            self.rtr() as u8,
+            self.ic() as u8,
+            self.dv() as u8
+        )
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for V24Signals {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "FC={=u8} RTC={=u8} RTR={=u8} IC={=u8} DV={=u8}",
+            self.fc() as u8,
+            self.rtc() as u8,
+            self.rtr() as u8,
+            self.ic() as u8,
+            self.dv() as u8
+        )
+    }
+}
+
+/// Unit of the break length of [`Msc`].
+pub const BREAK_UNIT: Duration = Duration::from_millis(200);
+
+/// Returns `duration` rounded up to units of [`BREAK_UNIT`], at most 15 units.
+pub(crate) fn break_units(duration: Duration) -> u8 {
+    duration
+        .as_millis()
+        .div_ceil(BREAK_UNIT.as_millis())
+        .min(15) as u8
+}
+
+/// Appends the V.24 signals octet, with EA cleared if a break octet follows, and the break
+/// octet of `break_length`.
+///
+/// These octets follow the DLCI octet of an MSC value, and open the information field of a
+/// Type 2 convergence layer frame.
+pub(crate) fn encode_status(signals: V24Signals, break_length: Option<u8>, out: &mut Vec<u8>) {
+    out.push(signals.with_ea(break_length.is_none()).into_bits());
+    if let Some(length) = break_length {
+        out.push((length << 4) | 0b11);
+    }
+}
+
+/// Decodes the break octet following V.24 signals with EA cleared.
+///
+/// # Returns
+///
+/// The break length, or `None` if the octet has B1 cleared and carries no break signal.
+pub(crate) const fn decode_break(octet: u8) -> Option<u8> {
+    if octet & 0x2 != 0 {
+        Some(octet >> 4)
+    } else {
+        None
+    }
+}
+
+/// Modem Status Command (MSC) message
+///
+/// | **Octet** | **Value**                                                      |
+/// |-----------|----------------------------------------------------------------|
+/// | 1         | EA, 1 and the DLCI the signals apply to (bits 3-8)             |
+/// | 2         | [`V24Signals`]                                                 |
+/// | 3         | Optional break: EA, B1 (set), 2 reserved bits and the length (bits 5-8) |
+///
+/// The break length is counted in units of 200 ms.
+///
+/// # Example
+///
+/// ```
+/// use cmux::control::{ControlMessage, Msc, V24Signals};
+///
+/// let msc = Msc::new(1).with_signals(V24Signals::new().with_rtc(true).with_rtr(true));
+/// let frame = msc.to_message(true).to_frame(true).unwrap();
+/// assert_eq!(frame.to_hex_string(), "f903ef09e305070dfbf9");
+///
+/// let messages = ControlMessage::from_frame(&frame).unwrap();
+/// assert_eq!(Msc::try_from(&messages[0]).unwrap(), msc);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Msc {
+    pub dlci: u8,
+    pub signals: V24Signals,
+    pub break_length: Option<u8>,
+}
+
+impl Msc {
+    /// Creates an MSC message for `dlci` with every signal cleared and no break.
+    pub const fn new(dlci: u8) -> Self {
+        Msc {
+            dlci: dlci & 0x3F,
+            signals: V24Signals::new(),
+            break_length: None,
+        }
+    }
+
+    pub const fn with_signals(mut self, signals: V24Signals) -> Self {
+        self.signals = signals;
+        self
+    }
+
+    /// Adds a break signal lasting `length` units of 200 ms (0 to 15).
+    pub const fn with_break(mut self, length: u8) -> Self {
+        self.break_length = Some(length & 0x0F);
+        self
+    }
+
+    /// Adds a break signal lasting `duration`, rounded up to units of 200 ms. Breaks longer
+    /// than 3 s are sent as 3 s.
+    pub fn with_break_duration(self, duration: Duration) -> Self {
+        self.with_break(break_units(duration))
+    }
+
+    /// Returns the duration of the break signal, if any.
+    pub fn break_duration(&self) -> Option<Duration> {
+        self.break_length
+            .map(|length| BREAK_UNIT * u32::from(length))
+    }
+
+    /// Encodes the 2 or 3 value octets.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut value = vec![(self.dlci << 2) | 0b11];
+        encode_status(self.signals, self.break_length, &mut value);
+        value
+    }
+
+    /// Decodes the value octets. Octets after the last EA-terminated octet are rejected.
+    pub fn decode(value: &[u8]) -> Result<Self, ControlError> {
+        if value.len() < 2 {
+            return Err(ControlError::BadValueLength {
+                expected: 2,
+                actual: value.len(),
+            });
+        }
+        let signals = V24Signals::from_bits(value[1]);
+        let expected = if signals.ea() { 2 } else { 3 };
+        if value.len() != expected {
+            return Err(ControlError::BadValueLength {
+                expected,
+                actual: value.len(),
+            });
+        }
+        Ok(Msc {
+            dlci: value[0] >> 2,
+            signals: signals.with_ea(false),
+            break_length: value.get(2).and_then(|&octet| decode_break(octet)),
+        })
+    }
+
+    /// Wraps the message in an MSC command (`cr` true) or response (`cr` false).
+    pub fn to_message(&self, cr: bool) -> ControlMessage {
+        ControlMessage::new(MessageType::MSC, cr, self.encode())
+    }
+}
+
+impl TryFrom<&ControlMessage> for Msc {
+    type Error = ControlError;
+
+    fn try_from(message: &ControlMessage) -> Result<Self, Self::Error> {
+        message.expect(MessageType::MSC, 2, 3)?;
+        Msc::decode(&message.value)
+    }
+}
+
+impl Display for Msc {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "DLCI={} {}", self.dlci, self.signals)?;
+        if let Some(length) = self.break_length {
+            write!(f, " Break={}ms", length as u32 * 200)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_break_octet() {
+        let msc = Msc::new(2)
+            .with_signals(V24Signals::new().with_dv(true).with_fc(true))
+            .with_break(3);
+        assert_eq!(msc.encode(), vec![0x0B, 0x82, 0x33]);
+        assert_eq!(Msc::decode(&msc.encode()).unwrap(), msc);
+        assert_eq!(
+            msc.to_string(),
+            "DLCI=2 FC=1 RTC=0 RTR=0 IC=0 DV=1 Break=600ms"
+        );
+
+        assert_eq!(msc.break_duration(), Some(Duration::from_millis(600)));
+        let msc = Msc::new(2).with_break_duration(Duration::from_millis(250));
+        assert_eq!(msc.break_length, Some(2));
+        let msc = Msc::new(2).with_break_duration(Duration::from_secs(5));
+        assert_eq!(msc.break_length, Some(15));
+
+        // Break octet present but B1 cleared.
+        let msc = Msc::decode(&[0x0B, 0x82, 0x31]).unwrap();
+        assert_eq!(msc.break_length, None);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            Msc::decode(&[0x07]),
+            Err(ControlError::BadValueLength {
+                expected: 2,
+                actual: 1
+            })
+        );
+        assert_eq!(
+            Msc::decode(&[0x07, 0x0C]),
+            Err(ControlError::BadValueLength {
+                expected: 3,
+                actual: 2
+            })
+        );
+        let msg = ControlMessage::new(MessageType::PN, true, vec![0x07, 0x0D]);
+        assert!(matches!(
+            Msc::try_from(&msg),
+            Err(ControlError::UnexpectedType { .. })
+        ));
+    }
+}
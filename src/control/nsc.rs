@@ -0,0 +1,94 @@
+use super::{ControlMessage, MessageType};
+use crate::error::ControlError;
+
+/// Non Supported Command (NSC) response
+///
+/// The peer answers a command it does not support with an NSC response whose single value
+/// octet repeats the type octet of the rejected command: EA, C/R and the 6-bit [`MessageType`].
+///
+/// # Example
+///
+/// ```
+/// use cmux::control::{ControlMessage, MessageType, Nsc};
+///
+/// let (msg, _) = ControlMessage::decode(&[0x11, 0x03, 0xD3]).unwrap();
+/// let nsc = Nsc::try_from(&msg).unwrap();
+/// assert_eq!(nsc.message_type, MessageType::SNC);
+/// assert!(nsc.cr);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Nsc {
+    pub message_type: MessageType,
+    pub cr: bool,
+}
+
+impl Nsc {
+    /// Creates the NSC response rejecting `command`.
+    pub fn new(command: &ControlMessage) -> Self {
+        Nsc {
+            message_type: command.message_type,
+            cr: command.cr,
+        }
+    }
+
+    /// Encodes the value octet.
+    pub const fn encode(&self) -> [u8; 1] {
+        [(self.message_type.into_bits() << 2) | ((self.cr as u8) << 1) | 0x1]
+    }
+
+    /// Decodes the value octet.
+    pub fn decode(value: &[u8]) -> Result<Self, ControlError> {
+        let [typ] = value else {
+            return Err(ControlError::BadValueLength {
+                expected: 1,
+                actual: value.len(),
+            });
+        };
+        Ok(Nsc {
+            message_type: MessageType::from_bits(typ >> 2),
+            cr: typ & 0x2 != 0,
+        })
+    }
+
+    /// Wraps the value in an NSC response.
+    pub fn to_message(&self) -> ControlMessage {
+        ControlMessage::new(MessageType::NSC, false, self.encode().to_vec())
+    }
+}
+
+impl TryFrom<&ControlMessage> for Nsc {
+    type Error = ControlError;
+
+    fn try_from(message: &ControlMessage) -> Result<Self, Self::Error> {
+        message.expect(MessageType::NSC, 1, 1)?;
+        Nsc::decode(&message.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let command = ControlMessage::new(MessageType::PSC, true, Vec::new());
+        let msg = Nsc::new(&command).to_message();
+        assert_eq!(msg.encode(), vec![0x11, 0x03, 0x43]);
+        assert_eq!(
+            Nsc::try_from(&msg).unwrap(),
+            Nsc {
+                message_type: MessageType::PSC,
+                cr: true
+            }
+        );
+        assert_eq!(
+            Nsc::decode(&[]),
+            Err(ControlError::BadValueLength {
+                expected: 1,
+                actual: 0
+            })
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use super::{ControlMessage, MessageType};
+use crate::error::ControlError;
+
+/// Number of value octets of a PN message.
+const PN_LENGTH: usize = 8;
+
+/// Frame type used for information transfer on a DLC, negotiated by [`ParameterNegotiation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PnFrameType {
+    UIH,
+    UI,
+    I,
+    Other(u8),
+}
+
+impl PnFrameType {
+    /// Returns the 4-bit I field.
+    pub const fn into_bits(self) -> u8 {
+        match self {
+            PnFrameType::UIH => 0x0,
+            PnFrameType::UI => 0x1,
+            PnFrameType::I => 0x2,
+            PnFrameType::Other(value) => value & 0x0F,
+        }
+    }
+
+    /// Creates a frame type from its 4-bit I field.
+    pub const fn from_bits(value: u8) -> Self {
+        match value & 0x0F {
+            0x0 => PnFrameType::UIH,
+            0x1 => PnFrameType::UI,
+            0x2 => PnFrameType::I,
+            value => PnFrameType::Other(value),
+        }
+    }
+}
+
+/// Convergence layer of a DLC, negotiated by [`ParameterNegotiation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConvergenceLayer {
+    /// Unstructured octet stream.
+    Type1,
+    /// Unstructured octet stream with V.24 status octets.
+    Type2,
+    /// Uninterruptible framed data.
+    Type3,
+    /// Interruptible framed data.
+    Type4,
+    Other(u8),
+}
+
+impl ConvergenceLayer {
+    /// Returns the 4-bit CL field.
+    pub const fn into_bits(self) -> u8 {
+        match self {
+            ConvergenceLayer::Type1 => 0x0,
+            ConvergenceLayer::Type2 => 0x1,
+            ConvergenceLayer::Type3 => 0x2,
+            ConvergenceLayer::Type4 => 0x3,
+            ConvergenceLayer::Other(value) => value & 0x0F,
+        }
+    }
+
+    /// Creates a convergence layer from its 4-bit CL field.
+    pub const fn from_bits(value: u8) -> Self {
+        match value & 0x0F {
+            0x0 => ConvergenceLayer::Type1,
+            0x1 => ConvergenceLayer::Type2,
+            0x2 => ConvergenceLayer::Type3,
+            0x3 => ConvergenceLayer::Type4,
+            value => ConvergenceLayer::Other(value),
+        }
+    }
+}
+
+/// DLC Parameter Negotiation (PN) message
+///
+/// The value of a PN message is 8 octets long:
+///
+/// | **Octet** | **Bits 1-4**         | **Bits 5-8**                 |
+/// |-----------|----------------------|------------------------------|
+/// | 1         | DLCI (bits 1-6)      |                              |
+/// | 2         | I: [`PnFrameType`]   | CL: [`ConvergenceLayer`]     |
+/// | 3         | Priority (bits 1-6)  |                              |
+/// | 4         | T1, acknowledgement timer in units of 10 ms             ||
+/// | 5-6       | N1, maximum frame size, least significant octet first   ||
+/// | 7         | N2, maximum number of retransmissions                   ||
+/// | 8         | k, window size for error recovery mode (bits 1-3)       ||
+///
+/// # Example
+///
+/// ```
+/// use cmux::control::{ControlMessage, ParameterNegotiation};
+///
+/// let pn = ParameterNegotiation::new(1).with_n1(127);
+/// let msg = pn.to_message(true);
+/// assert_eq!(msg.encode(), vec![0x83, 0x11, 0x01, 0x00, 0x07, 0x0A, 0x7F, 0x00, 0x03, 0x02]);
+/// assert_eq!(ParameterNegotiation::try_from(&msg).unwrap(), pn);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ParameterNegotiation {
+    pub dlci: u8,
+    pub frame_type: PnFrameType,
+    pub convergence_layer: ConvergenceLayer,
+    pub priority: u8,
+    pub t1: u8,
+    pub n1: u16,
+    pub n2: u8,
+    pub k: u8,
+}
+
+impl ParameterNegotiation {
+    /// Creates a PN message for `dlci` with the 07.10 default parameters.
+    ///
+    /// * Frame type UIH and convergence layer type 1.
+    /// * Priority 0 for DLCI 0, otherwise the default of the priority class of `dlci`.
+    /// * T1 of 100 ms, N1 of 31 octets, N2 of 3 retransmissions and k of 2.
+    pub const fn new(dlci: u8) -> Self {
+        let dlci = dlci & 0x3F;
+        let priority = if dlci == 0 {
+            0
+        } else if dlci | 0x7 > 61 {
+            61
+        } else {
+            dlci | 0x7
+        };
+        ParameterNegotiation {
+            dlci,
+            frame_type: PnFrameType::UIH,
+            convergence_layer: ConvergenceLayer::Type1,
+            priority,
+            t1: 10,
+            n1: 31,
+            n2: 3,
+            k: 2,
+        }
+    }
+
+    pub const fn with_frame_type(mut self, frame_type: PnFrameType) -> Self {
+        self.frame_type = frame_type;
+        self
+    }
+
+    pub const fn with_convergence_layer(mut self, convergence_layer: ConvergenceLayer) -> Self {
+        self.convergence_layer = convergence_layer;
+        self
+    }
+
+    pub const fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority & 0x3F;
+        self
+    }
+
+    pub const fn with_t1(mut self, t1: u8) -> Self {
+        self.t1 = t1;
+        self
+    }
+
+    pub const fn with_n1(mut self, n1: u16) -> Self {
+        self.n1 = n1;
+        self
+    }
+
+    pub const fn with_n2(mut self, n2: u8) -> Self {
+        self.n2 = n2;
+        self
+    }
+
+    pub const fn with_k(mut self, k: u8) -> Self {
+        self.k = k & 0x07;
+        self
+    }
+
+    /// Encodes the 8 value octets.
+    pub const fn encode(&self) -> [u8; PN_LENGTH] {
+        let n1 = self.n1.to_le_bytes();
+        [
+            self.dlci & 0x3F,
+            (self.convergence_layer.into_bits() << 4) | self.frame_type.into_bits(),
+            self.priority & 0x3F,
+            self.t1,
+            n1[0],
+            n1[1],
+            self.n2,
+            self.k & 0x07,
+        ]
+    }
+
+    /// Decodes the 8 value octets. Reserved bits are ignored.
+    pub fn decode(value: &[u8]) -> Result<Self, ControlError> {
+        if value.len() != PN_LENGTH {
+            return Err(ControlError::BadValueLength {
+                expected: PN_LENGTH,
+                actual: value.len(),
+            });
+        }
+        Ok(ParameterNegotiation {
+            dlci: value[0] & 0x3F,
+            frame_type: PnFrameType::from_bits(value[1]),
+            convergence_layer: ConvergenceLayer::from_bits(value[1] >> 4),
+            priority: value[2] & 0x3F,
+            t1: value[3],
+            n1: u16::from_le_bytes([value[4], value[5]]),
+            n2: value[6],
+            k: value[7] & 0x07,
+        })
+    }
+
+    /// Wraps the parameters in a PN command (`cr` true) or response (`cr` false).
+    pub fn to_message(&self, cr: bool) -> ControlMessage {
+        ControlMessage::new(MessageType::PN, cr, self.encode().to_vec())
+    }
+}
+
+impl TryFrom<&ControlMessage> for ParameterNegotiation {
+    type Error = ControlError;
+
+    fn try_from(message: &ControlMessage) -> Result<Self, Self::Error> {
+        message.expect(MessageType::PN, PN_LENGTH, PN_LENGTH)?;
+        ParameterNegotiation::decode(&message.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults() {
+        assert_eq!(ParameterNegotiation::new(0).priority, 0);
+        assert_eq!(ParameterNegotiation::new(1).priority, 7);
+        assert_eq!(ParameterNegotiation::new(8).priority, 15);
+        assert_eq!(ParameterNegotiation::new(60).priority, 61);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        // PN command for DLCI 2: UIH, convergence layer type 2, N1 = 1500.
+        let data = hex::decode("83110210070ADC050302").unwrap();
+        let (msg, used) = ControlMessage::decode(&data).unwrap();
+        assert_eq!(used, data.len());
+        let pn = ParameterNegotiation::try_from(&msg).unwrap();
+        assert_eq!(
+            pn,
+            ParameterNegotiation::new(2)
+                .with_convergence_layer(ConvergenceLayer::Type2)
+                .with_n1(1500)
+        );
+        assert_eq!(pn.frame_type, PnFrameType::UIH);
+        assert_eq!(pn.to_message(true).encode(), data);
+    }
+
+    #[test]
+    fn test_errors() {
+        let msg = ControlMessage::new(MessageType::MSC, true, vec![0; 8]);
+        assert_eq!(
+            ParameterNegotiation::try_from(&msg),
+            Err(ControlError::UnexpectedType {
+                expected: MessageType::PN,
+                found: MessageType::MSC
+            })
+        );
+        let msg = ControlMessage::new(MessageType::PN, true, vec![0; 7]);
+        assert_eq!(
+            ParameterNegotiation::try_from(&msg),
+            Err(ControlError::BadValueLength {
+                expected: 8,
+                actual: 7
+            })
+        );
+    }
+}
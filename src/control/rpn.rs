@@ -0,0 +1,443 @@
+use super::{ControlMessage, MessageType};
+use crate::error::ControlError;
+use alloc::vec;
+use alloc::vec::Vec;
+use bitfield_struct::bitfield;
+
+/// Number of value octets of an RPN message carrying port settings.
+const RPN_LENGTH: usize = 8;
+
+/// Baud rate of [`PortSettings`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BaudRate {
+    B2400,
+    B4800,
+    B7200,
+    B9600,
+    B19200,
+    B38400,
+    B57600,
+    B115200,
+    B230400,
+    Other(u8),
+}
+
+impl BaudRate {
+    /// Returns the baud rate octet.
+    pub const fn into_bits(self) -> u8 {
+        match self {
+            BaudRate::B2400 => 0,
+            BaudRate::B4800 => 1,
+            BaudRate::B7200 => 2,
+            BaudRate::B9600 => 3,
+            BaudRate::B19200 => 4,
+            BaudRate::B38400 => 5,
+            BaudRate::B57600 => 6,
+            BaudRate::B115200 => 7,
+            BaudRate::B230400 => 8,
+            BaudRate::Other(value) => value,
+        }
+    }
+
+    /// Creates a baud rate from its octet.
+    pub const fn from_bits(value: u8) -> Self {
+        match value {
+            0 => BaudRate::B2400,
+            1 => BaudRate::B4800,
+            2 => BaudRate::B7200,
+            3 => BaudRate::B9600,
+            4 => BaudRate::B19200,
+            5 => BaudRate::B38400,
+            6 => BaudRate::B57600,
+            7 => BaudRate::B115200,
+            8 => BaudRate::B230400,
+            value => BaudRate::Other(value),
+        }
+    }
+
+    /// Returns the rate in bit/s, or `None` for an unknown value.
+    pub const fn bps(self) -> Option<u32> {
+        match self {
+            BaudRate::B2400 => Some(2400),
+            BaudRate::B4800 => Some(4800),
+            BaudRate::B7200 => Some(7200),
+            BaudRate::B9600 => Some(9600),
+            BaudRate::B19200 => Some(19200),
+            BaudRate::B38400 => Some(38400),
+            BaudRate::B57600 => Some(57600),
+            BaudRate::B115200 => Some(115200),
+            BaudRate::B230400 => Some(230400),
+            BaudRate::Other(_) => None,
+        }
+    }
+}
+
+/// Number of data bits of [`PortSettings`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DataBits {
+    Five,
+    Six,
+    Seven,
+    Eight,
+}
+
+impl DataBits {
+    /// Returns the 2-bit data bits field.
+    pub const fn into_bits(self) -> u8 {
+        match self {
+            DataBits::Five => 0x0,
+            DataBits::Six => 0x1,
+            DataBits::Seven => 0x2,
+            DataBits::Eight => 0x3,
+        }
+    }
+
+    /// Creates the number of data bits from its 2-bit field.
+    pub const fn from_bits(value: u8) -> Self {
+        match value & 0x3 {
+            0x0 => DataBits::Five,
+            0x1 => DataBits::Six,
+            0x2 => DataBits::Seven,
+            _ => DataBits::Eight,
+        }
+    }
+}
+
+/// Number of stop bits of [`PortSettings`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StopBits {
+    One,
+    OneAndHalf,
+}
+
+/// Parity of [`PortSettings`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Parity {
+    None,
+    Odd,
+    Even,
+    Mark,
+    Space,
+}
+
+impl Parity {
+    /// Returns the P bit and the 2-bit parity type field, as bits 4 to 6 of the line octet.
+    const fn into_bits(self) -> u8 {
+        match self {
+            Parity::None => 0b000,
+            Parity::Odd => 0b001,
+            Parity::Even => 0b011,
+            Parity::Mark => 0b101,
+            Parity::Space => 0b111,
+        }
+    }
+
+    /// Creates the parity from bits 4 to 6 of the line octet.
+    pub(crate) const fn from_bits(value: u8) -> Self {
+        if value & 0x1 == 0 {
+            return Parity::None;
+        }
+        match (value >> 1) & 0x3 {
+            0 => Parity::Odd,
+            1 => Parity::Even,
+            2 => Parity::Mark,
+            _ => Parity::Space,
+        }
+    }
+}
+
+/// Flow control octet of [`PortSettings`]
+#[bitfield(u8, default = false)]
+#[derive(PartialEq, Eq)]
+pub struct FlowControl {
+    pub xon_xoff_input: bool,
+    pub xon_xoff_output: bool,
+    pub rtr_input: bool,
+    pub rtr_output: bool,
+    pub rtc_input: bool,
+    pub rtc_output: bool,
+    #[bits(2)]
+    __: u8,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for FlowControl {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "FlowControl({=u8:#04x})", self.into_bits())
+    }
+}
+
+/// Parameter mask of [`PortSettings`]
+///
+/// A set bit marks the parameter the sender wants to change, or the responder accepts.
+/// The flow control bits match the bits of [`FlowControl`].
+#[bitfield(u16, default = false)]
+#[derive(PartialEq, Eq)]
+pub struct ParameterMask {
+    pub baud_rate: bool,
+    pub data_bits: bool,
+    pub stop_bits: bool,
+    pub parity: bool,
+    pub parity_type: bool,
+    pub xon: bool,
+    pub xoff: bool,
+    __: bool,
+    #[bits(6)]
+    pub flow_control: u8,
+    #[bits(2)]
+    __: u8,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ParameterMask {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "ParameterMask({=u16:#06x})", self.into_bits())
+    }
+}
+
+/// Port settings carried by [`RemotePortNegotiation`].
+///
+/// The `with_*` methods also set the matching bits of the [`ParameterMask`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PortSettings {
+    pub baud_rate: BaudRate,
+    pub data_bits: DataBits,
+    pub stop_bits: StopBits,
+    pub parity: Parity,
+    pub flow_control: FlowControl,
+    pub xon: u8,
+    pub xoff: u8,
+    pub mask: ParameterMask,
+}
+
+impl Default for PortSettings {
+    /// 9600 bit/s, 8 data bits, 1 stop bit, no parity, no flow control, DC1/DC3 and an empty mask.
+    fn default() -> Self {
+        PortSettings {
+            baud_rate: BaudRate::B9600,
+            data_bits: DataBits::Eight,
+            stop_bits: StopBits::One,
+            parity: Parity::None,
+            flow_control: FlowControl::new(),
+            xon: 0x11,
+            xoff: 0x13,
+            mask: ParameterMask::new(),
+        }
+    }
+}
+
+impl PortSettings {
+    pub fn with_baud_rate(mut self, baud_rate: BaudRate) -> Self {
+        self.baud_rate = baud_rate;
+        self.mask.set_baud_rate(true);
+        self
+    }
+
+    pub fn with_data_bits(mut self, data_bits: DataBits) -> Self {
+        self.data_bits = data_bits;
+        self.mask.set_data_bits(true);
+        self
+    }
+
+    pub fn with_stop_bits(mut self, stop_bits: StopBits) -> Self {
+        self.stop_bits = stop_bits;
+        self.mask.set_stop_bits(true);
+        self
+    }
+
+    pub fn with_parity(mut self, parity: Parity) -> Self {
+        self.parity = parity;
+        self.mask.set_parity(true);
+        self.mask.set_parity_type(parity != Parity::None);
+        self
+    }
+
+    pub fn with_flow_control(mut self, flow_control: FlowControl) -> Self {
+        self.flow_control = flow_control;
+        self.mask.set_flow_control(0x3F);
+        self
+    }
+
+    pub fn with_xon_xoff(mut self, xon: u8, xoff: u8) -> Self {
+        self.xon = xon;
+        self.xoff = xoff;
+        self.mask.set_xon(true);
+        self.mask.set_xoff(true);
+        self
+    }
+
+    pub fn with_mask(mut self, mask: ParameterMask) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    /// Returns the line settings octet: data bits, stop bits and parity.
+    const fn line_bits(&self) -> u8 {
+        let stop = match self.stop_bits {
+            StopBits::One => 0,
+            StopBits::OneAndHalf => 1,
+        };
+        self.data_bits.into_bits() | (stop << 2) | (self.parity.into_bits() << 3)
+    }
+}
+
+/// Remote Port Negotiation (RPN) message
+///
+/// A message with only the DLCI octet asks the peer for its current port settings.
+/// Otherwise the value is 8 octets long:
+///
+/// | **Octet** | **Value**                                                                 |
+/// |-----------|---------------------------------------------------------------------------|
+/// | 1         | EA, 1 and the DLCI (bits 3-8)                                             |
+/// | 2         | [`BaudRate`]                                                              |
+/// | 3         | [`DataBits`] (bits 1-2), [`StopBits`] (bit 3), P (bit 4), parity type (bits 5-6) |
+/// | 4         | [`FlowControl`]                                                           |
+/// | 5         | XON character                                                             |
+/// | 6         | XOFF character                                                            |
+/// | 7-8       | [`ParameterMask`], least significant octet first                          |
+///
+/// # Example
+///
+/// ```
+/// use cmux::control::{BaudRate, PortSettings, RemotePortNegotiation};
+///
+/// let settings = PortSettings::default().with_baud_rate(BaudRate::B115200);
+/// let rpn = RemotePortNegotiation::new(1).with_settings(settings);
+/// let msg = rpn.to_message(true);
+/// assert_eq!(msg.encode(), vec![0x93, 0x11, 0x07, 0x07, 0x03, 0x00, 0x11, 0x13, 0x01, 0x00]);
+/// assert_eq!(RemotePortNegotiation::try_from(&msg).unwrap(), rpn);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RemotePortNegotiation {
+    pub dlci: u8,
+    pub settings: Option<PortSettings>,
+}
+
+impl RemotePortNegotiation {
+    /// Creates an RPN request for the port settings of `dlci`.
+    pub const fn new(dlci: u8) -> Self {
+        RemotePortNegotiation {
+            dlci: dlci & 0x3F,
+            settings: None,
+        }
+    }
+
+    pub const fn with_settings(mut self, settings: PortSettings) -> Self {
+        self.settings = Some(settings);
+        self
+    }
+
+    /// Encodes the 1 or 8 value octets.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut value = vec![(self.dlci << 2) | 0b11];
+        if let Some(settings) = &self.settings {
+            let mask = settings.mask.into_bits().to_le_bytes();
+            value.extend([
+                settings.baud_rate.into_bits(),
+                settings.line_bits(),
+                settings.flow_control.into_bits(),
+                settings.xon,
+                settings.xoff,
+                mask[0],
+                mask[1],
+            ]);
+        }
+        value
+    }
+
+    /// Decodes the 1 or 8 value octets. Reserved bits are ignored.
+    pub fn decode(value: &[u8]) -> Result<Self, ControlError> {
+        let settings = match value.len() {
+            1 => None,
+            RPN_LENGTH => Some(PortSettings {
+                baud_rate: BaudRate::from_bits(value[1]),
+                data_bits: DataBits::from_bits(value[2]),
+                stop_bits: if value[2] & 0x4 == 0 {
+                    StopBits::One
+                } else {
+                    StopBits::OneAndHalf
+                },
+                parity: Parity::from_bits(value[2] >> 3),
+                flow_control: FlowControl::from_bits(value[3] & 0x3F),
+                xon: value[4],
+                xoff: value[5],
+                mask: ParameterMask::from_bits(u16::from_le_bytes([value[6], value[7]]) & 0x3F7F),
+            }),
+            len => {
+                return Err(ControlError::BadValueLength {
+                    expected: if len < RPN_LENGTH { 1 } else { RPN_LENGTH },
+                    actual: len,
+                })
+            }
+        };
+        Ok(RemotePortNegotiation {
+            dlci: value[0] >> 2,
+            settings,
+        })
+    }
+
+    /// Wraps the message in an RPN command (`cr` true) or response (`cr` false).
+    pub fn to_message(&self, cr: bool) -> ControlMessage {
+        ControlMessage::new(MessageType::RPN, cr, self.encode())
+    }
+}
+
+impl TryFrom<&ControlMessage> for RemotePortNegotiation {
+    type Error = ControlError;
+
+    fn try_from(message: &ControlMessage) -> Result<Self, Self::Error> {
+        message.expect(MessageType::RPN, 1, RPN_LENGTH)?;
+        RemotePortNegotiation::decode(&message.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_settings() {
+        let settings = PortSettings::default()
+            .with_data_bits(DataBits::Seven)
+            .with_stop_bits(StopBits::OneAndHalf)
+            .with_parity(Parity::Even)
+            .with_flow_control(
+                FlowControl::new()
+                    .with_rtr_input(true)
+                    .with_rtr_output(true),
+            )
+            .with_xon_xoff(0x01, 0x02);
+        let rpn = RemotePortNegotiation::new(2).with_settings(settings);
+        let value = rpn.encode();
+        assert_eq!(value, vec![0x0B, 0x03, 0x1E, 0x0C, 0x01, 0x02, 0x7E, 0x3F]);
+        assert_eq!(RemotePortNegotiation::decode(&value).unwrap(), rpn);
+    }
+
+    #[test]
+    fn test_request() {
+        let msg = RemotePortNegotiation::new(3).to_message(true);
+        assert_eq!(msg.encode(), vec![0x93, 0x03, 0x0F]);
+        let rpn = RemotePortNegotiation::try_from(&msg).unwrap();
+        assert_eq!(rpn.settings, None);
+
+        assert_eq!(
+            RemotePortNegotiation::decode(&[0x0F, 0x03]),
+            Err(ControlError::BadValueLength {
+                expected: 1,
+                actual: 2
+            })
+        );
+    }
+}
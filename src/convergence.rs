@@ -0,0 +1,462 @@
+use crate::control::{break_units, decode_break, encode_status, V24Signals, BREAK_UNIT};
+use crate::error::{ConvergenceError, FrameError};
+use crate::recovery::Sequence;
+use crate::types::{Address, Control, Frame, FrameBuilder, FrameType, DLCI};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use bitfield_struct::bitfield;
+use core::time::Duration;
+
+/// Status octets opening the information field of a Type 2 convergence layer frame
+///
+/// | **Octet** | **Value**                                                          |
+/// |-----------|--------------------------------------------------------------------|
+/// | 1         | [`V24Signals`], with EA cleared if a break octet follows           |
+/// | 2         | Optional break: EA, B1 (set), 2 reserved bits and the length (bits 5-8) |
+///
+/// The octets have the layout of an MSC value without its DLCI octet, and are encoded like
+/// [`Msc`](crate::control::Msc). The user data follows them.
+///
+/// # Example
+///
+/// ```
+/// use cmux::control::V24Signals;
+/// use cmux::convergence::Cl2Status;
+///
+/// let status = Cl2Status::new(V24Signals::new().with_rtc(true).with_rtr(true));
+/// let content = status.encode(b"AT");
+/// assert_eq!(content, vec![0x0D, 0x41, 0x54]);
+/// assert_eq!(Cl2Status::decode(&content).unwrap(), (status, &b"AT"[..]));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Cl2Status {
+    pub signals: V24Signals,
+    pub break_length: Option<u8>,
+}
+
+impl Cl2Status {
+    /// Creates the status octets carrying `signals` and no break.
+    pub const fn new(signals: V24Signals) -> Self {
+        Cl2Status {
+            signals: signals.with_ea(false),
+            break_length: None,
+        }
+    }
+
+    /// Adds a break signal lasting `length` units of 200 ms (0 to 15).
+    pub const fn with_break(mut self, length: u8) -> Self {
+        self.break_length = Some(length & 0x0F);
+        self
+    }
+
+    /// Adds a break signal lasting `duration`, rounded up to units of 200 ms like
+    /// [`Msc::with_break_duration`](crate::control::Msc::with_break_duration).
+    pub fn with_break_duration(self, duration: Duration) -> Self {
+        self.with_break(break_units(duration))
+    }
+
+    /// Returns the duration of the break signal, if any.
+    pub fn break_duration(&self) -> Option<Duration> {
+        self.break_length
+            .map(|length| BREAK_UNIT * u32::from(length))
+    }
+
+    /// Returns the number of status octets, 1 or 2 with a break.
+    pub const fn encoded_len(&self) -> usize {
+        if self.break_length.is_some() {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Encodes the status octets followed by `data`.
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let mut content = Vec::with_capacity(self.encoded_len() + data.len());
+        encode_status(self.signals, self.break_length, &mut content);
+        content.extend_from_slice(data);
+        content
+    }
+
+    /// Splits the information field of a Type 2 frame into its status octets and user data.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok((Cl2Status, &[u8]))`: The status and the user data following it.
+    /// - `Err(ConvergenceError)`: If the information field ends inside the status octets.
+    pub fn decode(content: &[u8]) -> Result<(Self, &[u8]), ConvergenceError> {
+        let Some(&octet) = content.first() else {
+            return Err(ConvergenceError::Truncated {
+                expected: 1,
+                actual: 0,
+            });
+        };
+        let signals = V24Signals::from_bits(octet);
+        if signals.ea() {
+            return Ok((Cl2Status::new(signals), &content[1..]));
+        }
+        let Some(&octet) = content.get(1) else {
+            return Err(ConvergenceError::Truncated {
+                expected: 2,
+                actual: content.len(),
+            });
+        };
+        let status = Cl2Status {
+            signals,
+            break_length: decode_break(octet),
+        };
+        Ok((status, &content[2..]))
+    }
+}
+
+/// Control octet opening the information field of a Type 4 convergence layer frame
+///
+/// | **Bit No.** | 1-6      | 7 | 8 |
+/// |-------------|----------|---|---|
+/// | **Field**   | reserved | B | F |
+///
+/// * B: Begin, set in the first segment of a message.
+/// * F: Final, set in the last segment of a message.
+///
+/// A message fitting in one frame has both bits set. Segments of messages on different DLCs
+/// may be interleaved.
+///
+/// # Example
+///
+/// ```
+/// use cmux::convergence::{segment, Cl4Control};
+///
+/// let segments = segment(b"ATZ", 3);
+/// assert_eq!(segments, [vec![0x40, b'A', b'T'], vec![0x80, b'Z']]);
+/// assert!(Cl4Control::from_bits(segments[0][0]).b());
+/// ```
+#[bitfield(u8, default = false)]
+#[derive(PartialEq, Eq, Default)]
+pub struct Cl4Control {
+    #[bits(6)]
+    __: u8,
+    pub b: bool,
+    pub f: bool,
+}
+
+/// Splits `data` into the information fields of Type 4 frames of at most `n1` octets.
+///
+/// Every segment starts with a [`Cl4Control`] octet. Empty data yields one segment with both
+/// B and F set.
+pub fn segment(data: &[u8], n1: usize) -> Vec<Vec<u8>> {
+    let size = n1.saturating_sub(1).max(1);
+    let count = data.len().div_ceil(size).max(1);
+    (0..count)
+        .map(|i| {
+            let chunk = &data[(i * size).min(data.len())..((i + 1) * size).min(data.len())];
+            let control = Cl4Control::new().with_b(i == 0).with_f(i + 1 == count);
+            let mut content = Vec::with_capacity(chunk.len() + 1);
+            content.push(control.into_bits());
+            content.extend_from_slice(chunk);
+            content
+        })
+        .collect()
+}
+
+/// Reassembles the messages of a Type 4 DLC from the information fields of its frames.
+///
+/// # Example
+///
+/// ```
+/// use cmux::convergence::{segment, Reassembler};
+///
+/// let mut reassembler = Reassembler::new();
+/// let segments = segment(b"AT+CSQ\r", 4);
+/// assert_eq!(reassembler.push(&segments[0]), Ok(None));
+/// assert_eq!(reassembler.push(&segments[1]), Ok(None));
+/// assert_eq!(reassembler.push(&segments[2]), Ok(Some(b"AT+CSQ\r".to_vec())));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reassembler {
+    partial: Option<Vec<u8>>,
+}
+
+impl Reassembler {
+    /// Creates a reassembler awaiting the first segment of a message.
+    pub fn new() -> Self {
+        Reassembler::default()
+    }
+
+    /// Adds the information field of a received frame. A segment with B set discards the
+    /// unfinished message, if any.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Some(Vec<u8>))`: The message ended by the segment, which has F set.
+    /// - `Ok(None)`: If the message continues in the following segments.
+    /// - `Err(ConvergenceError)`: If the control octet is missing or no message was begun.
+    pub fn push(&mut self, content: &[u8]) -> Result<Option<Vec<u8>>, ConvergenceError> {
+        let Some((&octet, data)) = content.split_first() else {
+            return Err(ConvergenceError::Truncated {
+                expected: 1,
+                actual: 0,
+            });
+        };
+        let control = Cl4Control::from_bits(octet);
+        if control.b() {
+            self.partial = Some(Vec::new());
+        }
+        let Some(partial) = self.partial.as_mut() else {
+            return Err(ConvergenceError::NotBegun);
+        };
+        partial.extend_from_slice(data);
+        if control.f() {
+            return Ok(self.partial.take());
+        }
+        Ok(None)
+    }
+
+    /// Discards the unfinished message, if any.
+    pub fn reset(&mut self) {
+        self.partial = None;
+    }
+}
+
+/// Type 4 convergence layer of a DLC in error recovery mode
+///
+/// Messages are segmented with [`segment`] and sent in I frames numbered by a [`Sequence`],
+/// up to the window size k. Each I frame received in sequence is acknowledged with RR and
+/// reassembled, one out of sequence is answered with REJ. Segments stay queued until their
+/// N(S) is acknowledged, and are sent again on REJ or when [`Cl4Link::retransmit`] is called,
+/// for example when T1 expires.
+///
+/// # Example
+///
+/// ```
+/// use cmux::convergence::Cl4Link;
+///
+/// let mut host = Cl4Link::new(1, 2);
+/// let mut modem = Cl4Link::new(1, 2).with_initiator(false);
+/// host.send(b"AT\r", 31);
+/// let i = host.poll_transmit().unwrap().unwrap();
+/// assert_eq!(modem.receive(&i), Ok(Some(b"AT\r".to_vec())));
+///
+/// let rr = modem.poll_transmit().unwrap().unwrap();
+/// assert_eq!(host.outstanding(), 1);
+/// host.receive(&rr).unwrap();
+/// assert_eq!(host.outstanding(), 0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cl4Link {
+    dlci: u8,
+    initiator: bool,
+    sequence: Sequence,
+    /// Segments not sent yet, or to send again after a rewind.
+    queue: VecDeque<Vec<u8>>,
+    /// Segments sent and not acknowledged, oldest first.
+    unacked: VecDeque<Vec<u8>>,
+    /// Supervisory frames to send.
+    replies: VecDeque<FrameType>,
+    reassembler: Reassembler,
+}
+
+impl Cl4Link {
+    /// Creates the link of `dlci` for the initiating station.
+    ///
+    /// # Arguments
+    ///
+    /// * `dlci` - The DLCI of the DLC.
+    /// * `window` - The window size k, from 1 to 7.
+    pub fn new(dlci: u8, window: u8) -> Self {
+        Cl4Link {
+            dlci: dlci & 0x3F,
+            initiator: true,
+            sequence: Sequence::new(window),
+            queue: VecDeque::new(),
+            unacked: VecDeque::new(),
+            replies: VecDeque::new(),
+            reassembler: Reassembler::new(),
+        }
+    }
+
+    /// Sets whether this station started the multiplexer, which decides the C/R bits.
+    pub fn with_initiator(mut self, initiator: bool) -> Self {
+        self.initiator = initiator;
+        self
+    }
+
+    /// Returns the sequence numbers of the link.
+    pub fn sequence(&self) -> &Sequence {
+        &self.sequence
+    }
+
+    /// Returns the number of I frames sent and not yet acknowledged.
+    pub fn outstanding(&self) -> usize {
+        self.unacked.len()
+    }
+
+    /// Queues `data` in segments of at most `n1` octets.
+    pub fn send(&mut self, data: &[u8], n1: usize) {
+        self.queue.extend(segment(data, n1));
+    }
+
+    /// Takes the next frame to transmit: a pending RR or REJ, then an I frame if the window
+    /// allows it.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Some(Frame))`: The frame to transmit.
+    /// - `Ok(None)`: If nothing is queued or the window is full.
+    /// - `Err(FrameError)`: If the frame cannot be built.
+    pub fn poll_transmit(&mut self) -> Result<Option<Frame>, FrameError> {
+        if let Some(frame_type) = self.replies.pop_front() {
+            let control = self.sequence.supervisory(frame_type);
+            return self.frame(control, false, Vec::new()).map(Some);
+        }
+        if self.queue.is_empty() {
+            return Ok(None);
+        }
+        let Some(control) = self.sequence.next_i_frame() else {
+            return Ok(None);
+        };
+        let Some(segment) = self.queue.pop_front() else {
+            return Ok(None);
+        };
+        self.unacked.push_back(segment.clone());
+        self.frame(control, true, segment).map(Some)
+    }
+
+    /// Handles a frame received on the DLC.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Some(Vec<u8>))`: The message ended by an I frame received in sequence.
+    /// - `Ok(None)`: If no message was completed.
+    /// - `Err(ConvergenceError)`: If the segment of an I frame is malformed.
+    pub fn receive(&mut self, frame: &Frame) -> Result<Option<Vec<u8>>, ConvergenceError> {
+        let control = frame.control;
+        let outstanding = self.sequence.outstanding();
+        match control.frame_type() {
+            FrameType::I => {
+                let in_sequence = self.sequence.receive(control);
+                self.release(outstanding);
+                if !in_sequence {
+                    self.replies.push_back(FrameType::REJ);
+                    return Ok(None);
+                }
+                self.replies.push_back(FrameType::RR);
+                self.reassembler.push(frame.content.as_bytes())
+            }
+            FrameType::RR | FrameType::RNR | FrameType::REJ => {
+                if let Some(nr) = control.nr() {
+                    self.sequence.acknowledge(nr);
+                    self.release(outstanding);
+                }
+                if control.frame_type() == FrameType::REJ {
+                    self.retransmit();
+                }
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Queues the unacknowledged segments to be sent again, from the oldest one.
+    pub fn retransmit(&mut self) {
+        self.sequence.rewind();
+        while let Some(segment) = self.unacked.pop_back() {
+            self.queue.push_front(segment);
+        }
+    }
+
+    /// Drops the segments acknowledged since the window held `outstanding` frames.
+    fn release(&mut self, outstanding: u8) {
+        let acked = outstanding.saturating_sub(self.sequence.outstanding());
+        for _ in 0..acked {
+            self.unacked.pop_front();
+        }
+    }
+
+    /// Builds a command (`command` true) or response frame on the DLC.
+    fn frame(
+        &self,
+        control: Control,
+        command: bool,
+        content: Vec<u8>,
+    ) -> Result<Frame, FrameError> {
+        FrameBuilder::default()
+            .with_address(
+                Address::default()
+                    .with_cr(command == self.initiator)
+                    .with_dlci(DLCI::from_bits(self.dlci)),
+            )
+            .with_control(control)
+            .with_binary_content(content)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_break_octet() {
+        let status = Cl2Status::new(V24Signals::new().with_dv(true)).with_break(3);
+        let content = status.encode(&[0xF9]);
+        assert_eq!(content, [0x80, 0x33, 0xF9]);
+        assert_eq!(Cl2Status::decode(&content).unwrap(), (status, &[0xF9][..]));
+        assert_eq!(status.break_duration(), Some(Duration::from_millis(600)));
+        assert_eq!(
+            Cl2Status::new(V24Signals::new()).with_break_duration(Duration::from_millis(500)),
+            Cl2Status::new(V24Signals::new()).with_break(3)
+        );
+        assert_eq!(
+            Cl2Status::decode(&[0x81]).unwrap(),
+            (Cl2Status::new(V24Signals::new().with_dv(true)), &[][..])
+        );
+    }
+
+    #[test]
+    fn test_truncated() {
+        assert_eq!(
+            Cl2Status::decode(&[]),
+            Err(ConvergenceError::Truncated {
+                expected: 1,
+                actual: 0
+            })
+        );
+        assert_eq!(
+            Cl2Status::decode(&[0x0C]),
+            Err(ConvergenceError::Truncated {
+                expected: 2,
+                actual: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_retransmission() {
+        let mut host = Cl4Link::new(1, 7);
+        let mut modem = Cl4Link::new(1, 7).with_initiator(false);
+        host.send(b"ATE0\r", 3);
+        let frames: Vec<Frame> = core::iter::from_fn(|| host.poll_transmit().unwrap()).collect();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].content.as_bytes(), [0x40, b'A', b'T']);
+
+        // The second frame is lost: the third is rejected and both are sent again.
+        assert_eq!(modem.receive(&frames[0]), Ok(None));
+        assert_eq!(modem.receive(&frames[2]), Ok(None));
+        let rr = modem.poll_transmit().unwrap().unwrap();
+        let rej = modem.poll_transmit().unwrap().unwrap();
+        assert_eq!(rej.control.frame_type(), FrameType::REJ);
+        host.receive(&rr).unwrap();
+        host.receive(&rej).unwrap();
+        assert_eq!(host.outstanding(), 0);
+
+        let resent: Vec<Frame> = core::iter::from_fn(|| host.poll_transmit().unwrap()).collect();
+        assert_eq!(resent.len(), 2);
+        assert_eq!(resent[0].control.ns(), Some(1));
+        assert_eq!(modem.receive(&resent[0]), Ok(None));
+        assert_eq!(modem.receive(&resent[1]), Ok(Some(b"ATE0\r".to_vec())));
+        assert_eq!(
+            Reassembler::new().push(resent[1].content.as_bytes()),
+            Err(ConvergenceError::NotBegun)
+        );
+    }
+}
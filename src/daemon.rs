@@ -0,0 +1,1414 @@
+#[cfg(feature = "tokio-serial")]
+use crate::async_serial;
+use crate::at::{CmuxMode, CmuxParams};
+#[cfg(all(unix, feature = "tokio-serial"))]
+use crate::bridge::UnixSocket;
+use crate::bridge::{Endpoint, Endpoints, SocketBridge};
+#[cfg(feature = "tokio-serial")]
+use crate::channel::{self, Channel};
+use crate::error::{DaemonConfigError, MuxError};
+#[cfg(feature = "mqtt")]
+use crate::mqtt::{MqttBridge, MqttConfig};
+use crate::mux::{Mux, MuxConfig, MuxEvent, WritePolicy, DEFAULT_KEEP_ALIVE_MISSES};
+use crate::serial::{self, SerialBridge, SerialSettings};
+use crate::transport::{IoTransport, Link};
+use serde::{Deserialize, Serialize};
+use serialport::SerialPort;
+#[cfg(feature = "tokio-serial")]
+use std::collections::BTreeSet;
+use std::io::{self, ErrorKind};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+#[cfg(feature = "tokio-serial")]
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// When the daemon starts a new session after one ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// Never, the daemon exits with the session.
+    Never,
+    /// After the serial port or the modem failed, but not after the modem closed DLCI 0.
+    #[default]
+    OnFailure,
+    /// After every session.
+    Always,
+}
+
+/// How a channel is exposed to other programs
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "bridge", rename_all = "lowercase")]
+pub enum BridgeConfig {
+    /// Not exposed: the data received is logged.
+    None,
+    /// Listening for TCP connections at `address`.
+    Tcp { address: SocketAddr },
+    /// Listening on a Unix domain socket at `path`.
+    Unix { path: PathBuf },
+    /// On a pseudo-terminal linked at `link`, or at the [`DaemonConfig::pty_link`] of the
+    /// channel. Requires the `pty` feature.
+    Pty {
+        #[serde(default)]
+        link: Option<PathBuf>,
+    },
+    /// On a named pipe at `path`, or at the [`DaemonConfig::pipe_path`] of the channel.
+    /// Windows only.
+    Pipe {
+        #[serde(default)]
+        path: Option<String>,
+    },
+    /// On the serial port `port`, such as one end of a com0com pair whose other end
+    /// applications open as a COM port, with the `baud`, `data_bits`, `parity`, `stop_bits`
+    /// and `flow_control` of [`SerialSettings`]. See [`SerialBridge`].
+    Serial {
+        port: String,
+        #[serde(flatten)]
+        settings: SerialSettings,
+    },
+}
+
+/// User or group owning the pseudo-terminal or socket of a channel, by name or by id
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Owner {
+    Id(u32),
+    Name(String),
+}
+
+impl Owner {
+    /// Returns the id of the user.
+    #[cfg(unix)]
+    fn uid(&self) -> io::Result<u32> {
+        match self {
+            Owner::Id(id) => Ok(*id),
+            Owner::Name(name) => nix::unistd::User::from_name(name)?
+                .map(|user| user.uid.as_raw())
+                .ok_or_else(|| not_found("user", name)),
+        }
+    }
+
+    /// Returns the id of the group.
+    #[cfg(unix)]
+    fn gid(&self) -> io::Result<u32> {
+        match self {
+            Owner::Id(id) => Ok(*id),
+            Owner::Name(name) => nix::unistd::Group::from_name(name)?
+                .map(|group| group.gid.as_raw())
+                .ok_or_else(|| not_found("group", name)),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn not_found(kind: &str, name: &str) -> io::Error {
+    io::Error::new(ErrorKind::NotFound, format!("no {} named {}", kind, name))
+}
+
+/// Channel opened by the daemon, with `[[channel]]` tables in the configuration
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ChannelConfig {
+    /// DLCI of the channel, from 1 to 63.
+    pub dlci: u8,
+    /// Name of the channel in [`DaemonConfig::pty_link`] and [`DaemonConfig::pipe_path`],
+    /// `dlci` and the DLCI by default.
+    /// Example: at
+    pub name: Option<String>,
+    /// User owning the pseudo-terminal or Unix domain socket, by name or id. Example: root
+    pub owner: Option<Owner>,
+    /// Group owning the pseudo-terminal or Unix domain socket, by name or id. Example: dialout
+    pub group: Option<Owner>,
+    /// Permissions of the pseudo-terminal or Unix domain socket. Example: 0o660
+    pub mode: Option<u32>,
+    #[serde(flatten)]
+    pub bridge: BridgeConfig,
+    /// More bridges of the channel, with `[[channel.extra]]` tables, such as a named pipe in
+    /// addition to a TCP port. The data received on the channel goes to every bridge.
+    #[serde(default)]
+    pub extra: Vec<BridgeConfig>,
+}
+
+impl ChannelConfig {
+    /// Returns the name of the channel.
+    pub fn name(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("dlci{}", self.dlci))
+    }
+
+    /// Returns the bridge of the channel followed by its [`ChannelConfig::extra`] bridges.
+    pub fn bridges(&self) -> impl Iterator<Item = &BridgeConfig> {
+        std::iter::once(&self.bridge).chain(&self.extra)
+    }
+
+    /// Opens the endpoints of the bridges, returning them with descriptions for the logs.
+    fn open(&self, config: &DaemonConfig) -> io::Result<Vec<(Box<dyn Endpoint>, String)>> {
+        let mut endpoints = Vec::new();
+        for bridge in self.bridges() {
+            endpoints.extend(self.open_bridge(bridge, config)?);
+        }
+        Ok(endpoints)
+    }
+
+    /// Opens the endpoint of `bridge`, returning it with a description for the logs.
+    #[cfg_attr(not(any(all(unix, feature = "pty"), windows)), allow(unused_variables))]
+    fn open_bridge(
+        &self,
+        bridge: &BridgeConfig,
+        config: &DaemonConfig,
+    ) -> io::Result<Option<(Box<dyn Endpoint>, String)>> {
+        match bridge {
+            BridgeConfig::None => Ok(None),
+            BridgeConfig::Tcp { address } => {
+                let bridge = SocketBridge::tcp(address)?;
+                let name = format!("tcp://{}", bridge.local_addr()?);
+                Ok(Some((Box::new(bridge), name)))
+            }
+            #[cfg(unix)]
+            BridgeConfig::Unix { path } => {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                let bridge = SocketBridge::unix(path)?;
+                self.set_access(path)?;
+                Ok(Some((Box::new(bridge), path.display().to_string())))
+            }
+            #[cfg(all(unix, feature = "pty"))]
+            BridgeConfig::Pty { .. } => {
+                let link = config.pty_link(self);
+                if let Some(dir) = link.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                let pty = crate::pty::Pty::open()?.with_link(&link)?;
+                self.set_access(pty.path())?;
+                let name = format!("{} -> {}", link.display(), pty.path().display());
+                Ok(Some((Box::new(pty), name)))
+            }
+            BridgeConfig::Serial { port, settings } => {
+                let bridge = SerialBridge::open(port, settings)?;
+                Ok(Some((Box::new(bridge), port.clone())))
+            }
+            #[cfg(windows)]
+            BridgeConfig::Pipe { .. } => {
+                let pipe = crate::bridge::NamedPipe::create(&config.pipe_path(self))?;
+                let name = pipe.path().to_string();
+                Ok(Some((Box::new(pipe), name)))
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "bridge is not supported by this build",
+            )),
+        }
+    }
+
+    /// Gives the file at `path` the owner, group and mode of the channel.
+    #[cfg(unix)]
+    fn set_access(&self, path: &std::path::Path) -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let uid = self.owner.as_ref().map(Owner::uid).transpose()?;
+        let gid = self.group.as_ref().map(Owner::gid).transpose()?;
+        if uid.is_some() || gid.is_some() {
+            std::os::unix::fs::chown(path, uid, gid)?;
+        }
+        if let Some(mode) = self.mode {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+        }
+        Ok(())
+    }
+
+    /// Listens for the clients of the bridge of a session of [`run_async`], returning the
+    /// listener with a description for the logs.
+    #[cfg(feature = "tokio-serial")]
+    async fn listen(&self) -> io::Result<Option<(AsyncListener, String)>> {
+        match &self.bridge {
+            BridgeConfig::None => Ok(None),
+            BridgeConfig::Tcp { address } => {
+                let listener = tokio::net::TcpListener::bind(address).await?;
+                let name = format!("tcp://{}", listener.local_addr()?);
+                Ok(Some((AsyncListener::Tcp(listener), name)))
+            }
+            #[cfg(unix)]
+            BridgeConfig::Unix { path } => {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                let socket = UnixSocket::bind(path)?;
+                self.set_access(path)?;
+                let listener = tokio::net::UnixListener::from_std(socket.listener().try_clone()?)?;
+                let name = path.display().to_string();
+                Ok(Some((
+                    AsyncListener::Unix {
+                        listener,
+                        _socket: socket,
+                    },
+                    name,
+                )))
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "bridge is not supported by the asynchronous daemon",
+            )),
+        }
+    }
+}
+
+/// Connection accepted by an [`AsyncListener`].
+#[cfg(feature = "tokio-serial")]
+trait Client: AsyncRead + AsyncWrite + Unpin + Send {}
+
+#[cfg(feature = "tokio-serial")]
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Client for T {}
+
+/// Listening socket of the bridge of a channel in a session of [`run_async`]
+#[cfg(feature = "tokio-serial")]
+enum AsyncListener {
+    Tcp(tokio::net::TcpListener),
+    #[cfg(unix)]
+    Unix {
+        listener: tokio::net::UnixListener,
+        /// Removes the file of the socket when dropped.
+        _socket: UnixSocket,
+    },
+}
+
+#[cfg(feature = "tokio-serial")]
+impl AsyncListener {
+    /// Accepts the next connection.
+    async fn accept(&self) -> io::Result<Box<dyn Client>> {
+        match self {
+            AsyncListener::Tcp(listener) => {
+                let (stream, _) = listener.accept().await?;
+                stream.set_nodelay(true)?;
+                Ok(Box::new(stream))
+            }
+            #[cfg(unix)]
+            AsyncListener::Unix { listener, .. } => {
+                let (stream, _) = listener.accept().await?;
+                Ok(Box::new(stream))
+            }
+        }
+    }
+}
+
+/// Carries the bytes of `dlc` to and from the clients of `listener` until the DLC is closed.
+///
+/// As with [`SocketBridge`], one client is served at a time, and the bytes received while no
+/// client is connected are dropped.
+#[cfg(feature = "tokio-serial")]
+async fn serve(mut dlc: Channel, listener: AsyncListener) -> io::Result<()> {
+    let mut client: Option<Box<dyn Client>> = None;
+    let mut received = [0; 1024];
+    let mut sent = [0; 1024];
+    loop {
+        let read_client = async {
+            match client.as_mut() {
+                Some(client) => client.read(&mut sent).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            accepted = listener.accept() => {
+                // Connections made while a client is connected are closed at once.
+                client.get_or_insert(accepted?);
+            }
+            count = dlc.read(&mut received) => {
+                let count = count?;
+                if count == 0 {
+                    return Ok(());
+                }
+                if let Some(stream) = client.as_mut() {
+                    if stream.write_all(&received[..count]).await.is_err() {
+                        client = None;
+                    }
+                }
+            }
+            count = read_client => match count {
+                Ok(count) if count > 0 => {
+                    if dlc.write_all(&sent[..count]).await.is_err() {
+                        return Ok(());
+                    }
+                }
+                // A client failing or disconnecting frees the bridge for the next one.
+                _ => client = None,
+            },
+        }
+    }
+}
+
+fn default_baud() -> u32 {
+    115200
+}
+
+fn default_restart_delay() -> u64 {
+    1000
+}
+
+fn default_keep_alive_misses() -> u8 {
+    DEFAULT_KEEP_ALIVE_MISSES
+}
+
+fn default_pty_link() -> String {
+    "/dev/cmux/{name}".to_string()
+}
+
+fn default_pipe_path() -> String {
+    r"\\.\pipe\cmux-{name}".to_string()
+}
+
+/// Configuration of the daemon
+///
+/// # Example
+///
+/// ```
+/// use cmux::daemon::{BridgeConfig, DaemonConfig};
+///
+/// let config: DaemonConfig = r#"
+/// device = "/dev/ttyUSB0"
+/// restart = "always"
+///
+/// [[channel]]
+/// dlci = 1
+/// bridge = "tcp"
+/// address = "127.0.0.1:5001"
+/// "#
+/// .parse()
+/// .unwrap();
+/// assert_eq!(config.baud, 115200);
+/// assert_eq!(config.init(), Some("AT+CMUX=0".to_string()));
+/// assert_eq!(
+///     config.channels[0].bridge,
+///     BridgeConfig::Tcp { address: "127.0.0.1:5001".parse().unwrap() }
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DaemonConfig {
+    /// Serial port of the modem. Example: /dev/ttyUSB0
+    pub device: String,
+    /// Baud rate of the serial port.
+    #[serde(default = "default_baud")]
+    pub baud: u32,
+    /// Framing of the multiplexer, `basic` or `advanced`.
+    #[serde(default)]
+    pub mode: CmuxMode,
+    /// AT command switching the modem to CMUX mode, `AT+CMUX` with the mode by default. An
+    /// empty command is not sent, for a modem already in CMUX mode.
+    pub init: Option<String>,
+    #[serde(default)]
+    pub restart: RestartPolicy,
+    /// Milliseconds to wait before restarting a session.
+    #[serde(default = "default_restart_delay")]
+    pub restart_delay_ms: u64,
+    /// Maximum number of restarts in a row without connecting DLCI 0, unlimited by default.
+    pub max_restarts: Option<u32>,
+    /// Milliseconds between the Test commands checking that the modem still answers, none
+    /// by default.
+    pub keep_alive_ms: Option<u64>,
+    /// Number of Test commands left unanswered in a row after which the session fails.
+    #[serde(default = "default_keep_alive_misses")]
+    pub keep_alive_misses: u8,
+    /// Milliseconds a frame may wait for the serial port, which fails the session, or for
+    /// FCon after the modem sent FCoff, which drops the data held; no limit by default.
+    pub write_timeout_ms: Option<u64>,
+    /// Whether to switch the modem to CMUX mode again and reopen the channels, keeping their
+    /// bridges, when the modem resets instead of ending the session. A reset is told by the
+    /// startup banner of the modem, DLCI 0 closed by the modem, or unanswered Test commands.
+    #[serde(default)]
+    pub reconnect: bool,
+    /// Path of the links to the pseudo-terminals of the channels without `link`, where
+    /// `{name}` stands for the [`ChannelConfig::name`] and `{dlci}` for the DLCI. The links
+    /// are the same across restarts, and removed when the daemon stops.
+    #[serde(default = "default_pty_link")]
+    pub pty_link: String,
+    /// Path of the named pipes of the channels without `path`, with the placeholders of
+    /// `pty_link`.
+    #[serde(default = "default_pipe_path")]
+    pub pipe_path: String,
+    /// Broker every frame is published to, with the `[mqtt]` table. Requires the `mqtt`
+    /// feature.
+    #[cfg(feature = "mqtt")]
+    pub mqtt: Option<MqttConfig>,
+    #[serde(default, rename = "channel")]
+    pub channels: Vec<ChannelConfig>,
+}
+
+impl DaemonConfig {
+    /// Returns the AT command switching the modem to CMUX mode, or `None` if it is not sent.
+    pub fn init(&self) -> Option<String> {
+        match &self.init {
+            Some(init) if init.is_empty() => None,
+            Some(init) => Some(init.clone()),
+            None => Some(CmuxParams::new(self.mode).to_string()),
+        }
+    }
+
+    /// Returns the path of the link to the pseudo-terminal of `channel`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cmux::daemon::DaemonConfig;
+    /// use std::path::Path;
+    ///
+    /// let config: DaemonConfig = r#"
+    /// device = "/dev/ttyUSB0"
+    ///
+    /// [[channel]]
+    /// dlci = 1
+    /// name = "at"
+    /// bridge = "pty"
+    ///
+    /// [[channel]]
+    /// dlci = 2
+    /// bridge = "pty"
+    /// "#
+    /// .parse()
+    /// .unwrap();
+    /// assert_eq!(config.pty_link(&config.channels[0]), Path::new("/dev/cmux/at"));
+    /// assert_eq!(config.pty_link(&config.channels[1]), Path::new("/dev/cmux/dlci2"));
+    /// ```
+    pub fn pty_link(&self, channel: &ChannelConfig) -> PathBuf {
+        channel
+            .bridges()
+            .find_map(|bridge| match bridge {
+                BridgeConfig::Pty { link } => link.clone(),
+                _ => None,
+            })
+            .unwrap_or_else(|| PathBuf::from(expand(&self.pty_link, channel)))
+    }
+
+    /// Returns the path of the named pipe of `channel`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cmux::daemon::DaemonConfig;
+    ///
+    /// let config: DaemonConfig = r#"
+    /// device = "COM3"
+    ///
+    /// [[channel]]
+    /// dlci = 1
+    /// bridge = "pipe"
+    ///
+    /// [[channel]]
+    /// dlci = 2
+    /// bridge = "tcp"
+    /// address = "127.0.0.1:5002"
+    ///
+    /// [[channel.extra]]
+    /// bridge = "pipe"
+    /// path = '\\.\pipe\modem-gnss'
+    /// "#
+    /// .parse()
+    /// .unwrap();
+    /// assert_eq!(config.pipe_path(&config.channels[0]), r"\\.\pipe\cmux-dlci1");
+    /// assert_eq!(config.pipe_path(&config.channels[1]), r"\\.\pipe\modem-gnss");
+    /// ```
+    pub fn pipe_path(&self, channel: &ChannelConfig) -> String {
+        channel
+            .bridges()
+            .find_map(|bridge| match bridge {
+                BridgeConfig::Pipe { path } => path.clone(),
+                _ => None,
+            })
+            .unwrap_or_else(|| expand(&self.pipe_path, channel))
+    }
+}
+
+/// Replaces the `{name}` and `{dlci}` placeholders of `pattern` for `channel`.
+fn expand(pattern: &str, channel: &ChannelConfig) -> String {
+    pattern
+        .replace("{name}", &channel.name())
+        .replace("{dlci}", &channel.dlci.to_string())
+}
+
+impl FromStr for DaemonConfig {
+    type Err = DaemonConfigError;
+
+    /// Parses the TOML configuration.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let config: DaemonConfig =
+            toml::from_str(s).map_err(|e| DaemonConfigError::Syntax(e.to_string()))?;
+        for (i, channel) in config.channels.iter().enumerate() {
+            if !(1..64).contains(&channel.dlci) {
+                return Err(DaemonConfigError::InvalidDlci(channel.dlci));
+            }
+            if config.channels[..i].iter().any(|c| c.dlci == channel.dlci) {
+                return Err(DaemonConfigError::DuplicateChannel(channel.dlci));
+            }
+            if let Some(mode) = channel.mode.filter(|mode| mode & !0o7777 != 0) {
+                return Err(DaemonConfigError::InvalidMode(mode));
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Event logged by the daemon
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum DaemonEvent {
+    /// DLCI 0 is connected.
+    Started { device: String },
+    /// A DLC is connected, by either station.
+    DlcOpened { dlci: u8 },
+    /// The modem rejected or did not answer the SABM of a configured channel.
+    DlcRefused { dlci: u8 },
+    /// A channel is exposed to other programs.
+    Listening { dlci: u8, endpoint: String },
+    /// A DLC is closed, by either station.
+    DlcClosed { dlci: u8 },
+    /// Data received on a channel without bridge.
+    Data { dlci: u8, text: String },
+    /// Unsolicited result code received on an AT channel.
+    Urc { dlci: u8, line: String },
+    /// The modem sent its V.24 signals.
+    ModemStatus { status: String },
+    /// The link follows a pattern of a failing modem, with its likely cause.
+    Anomaly { message: String },
+    /// The power saving state of the multiplexer changed.
+    PowerState { state: &'static str },
+    /// The modem stopped (`enabled` false) or resumed the data on every channel.
+    FlowControl { enabled: bool },
+    /// Bytes read from the modem that are not part of a valid frame.
+    Skipped { bytes: usize },
+    /// The control lines of the serial port, as V.24 signals, and the number of breaks it
+    /// detected.
+    LineStatus { lines: String, breaks: u32 },
+    /// The engine raised an error.
+    Error { message: String },
+    /// The session ended, with the error that ended it if any.
+    Stopped { error: Option<String> },
+    /// A new session starts after `delay_ms`.
+    Restarting { attempt: u32, delay_ms: u64 },
+    /// The modem was switched to CMUX mode again after a reset, and the channels reopened.
+    Reconnected { reason: String },
+}
+
+impl DaemonEvent {
+    /// Returns the event as a JSON object, with a `time` field holding the seconds since the
+    /// UNIX epoch.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cmux::daemon::DaemonEvent;
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// let event = DaemonEvent::DlcOpened { dlci: 1 };
+    /// let time = UNIX_EPOCH + Duration::from_millis(1500);
+    /// assert_eq!(event.to_json(time), r#"{"time":1.5,"event":"dlc_opened","dlci":1}"#);
+    /// ```
+    pub fn to_json(&self, time: SystemTime) -> String {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            time: f64,
+            #[serde(flatten)]
+            event: &'a DaemonEvent,
+        }
+        let time = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let record = Record {
+            time: time.as_secs_f64(),
+            event: self,
+        };
+        serde_json::to_string(&record).unwrap_or_default()
+    }
+}
+
+impl From<MuxEvent> for DaemonEvent {
+    /// Returns the event logged for an event of the engine.
+    fn from(event: MuxEvent) -> Self {
+        match event {
+            MuxEvent::DlcOpened(dlci) => DaemonEvent::DlcOpened { dlci },
+            MuxEvent::DlcClosed(dlci) => DaemonEvent::DlcClosed { dlci },
+            MuxEvent::Data { dlci, data } => DaemonEvent::Data {
+                dlci,
+                text: String::from_utf8_lossy(&data).into_owned(),
+            },
+            MuxEvent::ModemStatus(msc) => DaemonEvent::ModemStatus {
+                status: msc.to_string(),
+            },
+            MuxEvent::Urc { dlci, line } => DaemonEvent::Urc { dlci, line },
+            MuxEvent::Anomaly(anomaly) => DaemonEvent::Anomaly {
+                message: anomaly.to_string(),
+            },
+            MuxEvent::PowerState(state) => DaemonEvent::PowerState {
+                state: state.as_str(),
+            },
+            MuxEvent::FlowControl { enabled } => DaemonEvent::FlowControl { enabled },
+            MuxEvent::Skipped(bytes) => DaemonEvent::Skipped { bytes },
+            MuxEvent::LineStatus(status) => DaemonEvent::LineStatus {
+                lines: status.signals.to_string(),
+                breaks: status.breaks,
+            },
+            MuxEvent::Error(e) => DaemonEvent::Error {
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+/// Set by [`stop`] to end the sessions run by [`run`] and [`run_async`].
+static STOP: AtomicBool = AtomicBool::new(false);
+
+/// Ends the session run by [`run`] or [`run_async`], which returns without restarting it.
+///
+/// The endpoints are closed with the session, removing the links to the pseudo-terminals and
+/// the files of the Unix domain sockets. Only a flag is set, so a signal handler can call it.
+pub fn stop() {
+    STOP.store(true, Ordering::SeqCst);
+}
+
+/// Calls [`stop`] when the process receives SIGINT, SIGTERM or SIGHUP.
+///
+/// A read of the serial port waiting when the signal arrives fails with
+/// [`ErrorKind::Interrupted`], so the session ends at once.
+#[cfg(unix)]
+pub fn stop_on_signals() -> io::Result<()> {
+    use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+
+    extern "C" fn handle(_: nix::libc::c_int) {
+        stop();
+    }
+    let action = SigAction::new(
+        SigHandler::Handler(handle),
+        SaFlags::empty(),
+        SigSet::empty(),
+    );
+    for signal in [Signal::SIGINT, Signal::SIGTERM, Signal::SIGHUP] {
+        // SAFETY: the handler only stores to an atomic.
+        unsafe { sigaction(signal, &action) }?;
+    }
+    Ok(())
+}
+
+/// Runs multiplexer sessions on the configured modem until the restart policy ends them.
+///
+/// Each session switches the modem to CMUX mode, connects DLCI 0 and the configured channels,
+/// and bridges the channels until DLCI 0 is closed or the serial port fails. The endpoints are
+/// closed with the session. With [`DaemonConfig::reconnect`], a modem reset does not end the
+/// session: the modem is switched to CMUX mode again and the channels reopened behind the
+/// same endpoints. [`stop`] ends the session and returns.
+///
+/// With [`DaemonConfig::mqtt`], every frame read or written is published to the broker, and
+/// the frames received on the command topic are written to the modem.
+///
+/// # Arguments
+///
+/// * `config` - The configuration.
+/// * `log` - Called with every event.
+///
+/// # Returns
+///
+/// - `Ok(())`: If the last session ended with DLCI 0 closed, or was stopped.
+/// - `Err(io::Error)`: The error of the last session.
+pub fn run(config: &DaemonConfig, mut log: impl FnMut(&DaemonEvent)) -> io::Result<()> {
+    let mut restarts = 0;
+    loop {
+        let mut started = false;
+        let result = match session(config, &mut log, &mut started) {
+            Err(e) if e.kind() == ErrorKind::Interrupted && STOP.load(Ordering::SeqCst) => Ok(()),
+            result => result,
+        };
+        if !restart(config, &result, started, &mut restarts, &mut log) {
+            return result;
+        }
+        std::thread::sleep(Duration::from_millis(config.restart_delay_ms));
+    }
+}
+
+/// Runs multiplexer sessions on the configured modem like [`run`], with the serial port and
+/// the bridges driven by the tokio runtime instead of a polling loop.
+///
+/// The control lines of the serial port and the breaks it detects are logged as
+/// [`DaemonEvent::LineStatus`]. Channels are bridged over TCP and Unix domain sockets only,
+/// without [`ChannelConfig::extra`] bridges, [`DaemonConfig::reconnect`] or an MQTT broker. A
+/// modem reset ends the session.
+///
+/// The future drives the engine on the task awaiting it, so it can run on a current-thread
+/// runtime. It requires the `tokio-serial` feature.
+///
+/// # Returns
+///
+/// - `Ok(())`: If the last session ended with DLCI 0 closed, or was stopped.
+/// - `Err(io::Error)`: `Unsupported` if `config` uses a setting the asynchronous daemon does
+///   not support, or the error of the last session.
+#[cfg(feature = "tokio-serial")]
+pub async fn run_async(config: &DaemonConfig, mut log: impl FnMut(&DaemonEvent)) -> io::Result<()> {
+    check_async(config)?;
+    let mut restarts = 0;
+    loop {
+        let mut started = false;
+        let result = session_async(config, &mut log, &mut started).await;
+        if !restart(config, &result, started, &mut restarts, &mut log) {
+            return result;
+        }
+        tokio::time::sleep(Duration::from_millis(config.restart_delay_ms)).await;
+    }
+}
+
+/// Logs the end of a session and applies the restart policy, counting in `restarts` the
+/// restarts in a row without connecting DLCI 0.
+///
+/// # Returns
+///
+/// Whether to start a new session after [`DaemonConfig::restart_delay_ms`].
+fn restart(
+    config: &DaemonConfig,
+    result: &io::Result<()>,
+    started: bool,
+    restarts: &mut u32,
+    log: &mut impl FnMut(&DaemonEvent),
+) -> bool {
+    log(&DaemonEvent::Stopped {
+        error: result.as_ref().err().map(ToString::to_string),
+    });
+    if STOP.load(Ordering::SeqCst) {
+        return false;
+    }
+    if started {
+        *restarts = 0;
+    }
+    let restart = match config.restart {
+        RestartPolicy::Never => false,
+        RestartPolicy::OnFailure => result.is_err(),
+        RestartPolicy::Always => true,
+    };
+    if !restart || config.max_restarts.is_some_and(|max| *restarts >= max) {
+        return false;
+    }
+    *restarts += 1;
+    log(&DaemonEvent::Restarting {
+        attempt: *restarts,
+        delay_ms: config.restart_delay_ms,
+    });
+    true
+}
+
+/// Returns an error naming the first setting of `config` that [`run_async`] does not support.
+#[cfg(feature = "tokio-serial")]
+fn check_async(config: &DaemonConfig) -> io::Result<()> {
+    let unsupported = |setting: String| {
+        Err(io::Error::new(
+            ErrorKind::Unsupported,
+            format!("{} is not supported by the asynchronous daemon", setting),
+        ))
+    };
+    if config.reconnect {
+        return unsupported("reconnect".to_string());
+    }
+    #[cfg(feature = "mqtt")]
+    if config.mqtt.is_some() {
+        return unsupported("[mqtt]".to_string());
+    }
+    for channel in &config.channels {
+        let bridge = match &channel.bridge {
+            BridgeConfig::None | BridgeConfig::Tcp { .. } => None,
+            #[cfg(unix)]
+            BridgeConfig::Unix { .. } => None,
+            #[cfg(not(unix))]
+            BridgeConfig::Unix { .. } => Some("unix"),
+            BridgeConfig::Pty { .. } => Some("pty"),
+            BridgeConfig::Pipe { .. } => Some("pipe"),
+            BridgeConfig::Serial { .. } => Some("serial"),
+        };
+        if let Some(bridge) = bridge {
+            return unsupported(format!("the {} bridge of DLCI {}", bridge, channel.dlci));
+        }
+        if !channel.extra.is_empty() {
+            return unsupported(format!("[[channel.extra]] of DLCI {}", channel.dlci));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the settings of the serial port of the modem.
+fn serial_settings(config: &DaemonConfig) -> SerialSettings {
+    SerialSettings {
+        baud: config.baud,
+        ..Default::default()
+    }
+}
+
+/// End of a session, told by the events of its engine in [`session`] and [`session_async`]
+#[derive(Debug, Default)]
+struct Ending {
+    /// Set when the modem stopped answering, failing the session once DLCI 0 is closed.
+    unresponsive: Option<MuxError>,
+}
+
+impl Ending {
+    /// Logs `event`.
+    ///
+    /// # Returns
+    ///
+    /// The result of the session if `event` ends it, DLCI 0 being closed, otherwise `None`.
+    fn log(
+        &mut self,
+        event: MuxEvent,
+        log: &mut impl FnMut(&DaemonEvent),
+    ) -> Option<io::Result<()>> {
+        if let MuxEvent::Error(e @ MuxError::Unresponsive { .. }) = &event {
+            self.unresponsive = Some(e.clone());
+        }
+        let closed = matches!(event, MuxEvent::DlcClosed(0));
+        log(&DaemonEvent::from(event));
+        closed.then(|| match self.unresponsive.take() {
+            Some(e) => Err(io::Error::new(ErrorKind::TimedOut, e)),
+            None => Ok(()),
+        })
+    }
+}
+
+/// Builds the engine of a session.
+fn engine(config: &DaemonConfig) -> Mux {
+    // The timers given to the modem in AT+CMUX apply to the engine too.
+    let params = config
+        .init()
+        .and_then(|init| init.parse::<CmuxParams>().ok());
+    let mut mux_config = params.map_or_else(MuxConfig::default, |p| p.to_config());
+    mux_config.keep_alive = config.keep_alive_ms.map(Duration::from_millis);
+    mux_config.keep_alive_misses = config.keep_alive_misses;
+    if let Some(timeout) = config.write_timeout_ms {
+        mux_config.write = WritePolicy::default()
+            .with_retries(u32::MAX)
+            .with_deadline(Duration::from_millis(timeout));
+    }
+    let mut mux = Mux::new().with_config(mux_config);
+    if let Some(params) = params {
+        for channel in &config.channels {
+            mux.set_parameters(params.parameters(channel.dlci));
+        }
+    }
+    mux
+}
+
+/// Switches the modem to CMUX mode and connects DLCI 0 over `link`.
+fn connect(
+    config: &DaemonConfig,
+    link: &mut Link<IoTransport<Box<dyn SerialPort>>>,
+) -> io::Result<()> {
+    if let Some(init) = &config.init() {
+        serial::enter_cmux(link.transport_mut().get_mut(), init, Duration::from_secs(1))?;
+    }
+    if !link.connect(0)? {
+        return Err(io::Error::new(
+            ErrorKind::ConnectionRefused,
+            "DLCI 0 was not connected",
+        ));
+    }
+    Ok(())
+}
+
+/// Publishes the frames traced by `link`, logging the frames dropped, and transmits the
+/// frames received on the command topic.
+#[cfg(feature = "mqtt")]
+fn exchange_mqtt(
+    mqtt: &MqttBridge,
+    link: &mut Link<IoTransport<Box<dyn SerialPort>>>,
+    log: &mut impl FnMut(&DaemonEvent),
+) -> io::Result<()> {
+    while let Some(command) = mqtt.poll_command() {
+        match command {
+            Ok(frames) => {
+                for frame in &frames {
+                    link.send_frame(frame)?;
+                }
+            }
+            Err(e) => log(&DaemonEvent::Error {
+                message: e.to_string(),
+            }),
+        }
+    }
+    while let Some(sniffed) = link.poll_frame() {
+        mqtt.publish(&sniffed);
+    }
+    let dropped = mqtt.take_dropped();
+    if dropped > 0 {
+        log(&DaemonEvent::Error {
+            message: format!("{} frames were not published to the MQTT broker", dropped),
+        });
+    }
+    Ok(())
+}
+
+/// Runs one session, setting `started` once DLCI 0 is connected.
+fn session(
+    config: &DaemonConfig,
+    log: &mut impl FnMut(&DaemonEvent),
+    started: &mut bool,
+) -> io::Result<()> {
+    let port = serial::open(&config.device, &serial_settings(config))?;
+    let mut link = Link::new(engine(config), IoTransport::new(port));
+    if config.mode == CmuxMode::Advanced {
+        link = link.advanced();
+    }
+    #[cfg(feature = "mqtt")]
+    let mqtt = config.mqtt.as_ref().map(MqttBridge::connect).transpose()?;
+    #[cfg(feature = "mqtt")]
+    if mqtt.is_some() {
+        link = link.with_trace(Instant::now());
+    }
+    connect(config, &mut link)?;
+    *started = true;
+    log(&DaemonEvent::Started {
+        device: config.device.clone(),
+    });
+
+    let mut endpoints = Endpoints::new();
+    for channel in &config.channels {
+        let dlci = channel.dlci;
+        if !link.connect(dlci)? {
+            log(&DaemonEvent::DlcRefused { dlci });
+            continue;
+        }
+        log(&DaemonEvent::DlcOpened { dlci });
+        for (endpoint, name) in channel.open(config)? {
+            log(&DaemonEvent::Listening {
+                dlci,
+                endpoint: name,
+            });
+            endpoints.insert(dlci, endpoint);
+        }
+    }
+    let mut ending = Ending::default();
+    while !STOP.load(Ordering::SeqCst) {
+        for e in endpoints.forward(link.mux_mut())? {
+            log(&DaemonEvent::Error {
+                message: e.to_string(),
+            });
+        }
+        link.poll(Instant::now())?;
+        #[cfg(feature = "mqtt")]
+        if let Some(mqtt) = &mqtt {
+            exchange_mqtt(mqtt, &mut link, log)?;
+        }
+        // Set when the modem was reset and the session goes on with a new engine.
+        let mut reset = None;
+        while let Some(event) = link.poll_event() {
+            match &event {
+                MuxEvent::Error(e @ (MuxError::Unresponsive { .. } | MuxError::ModemReset))
+                    if config.reconnect =>
+                {
+                    reset = Some(e.to_string());
+                }
+                MuxEvent::DlcClosed(0) if config.reconnect => {
+                    reset.get_or_insert_with(|| "DLCI 0 was closed by the modem".to_string());
+                }
+                _ => {}
+            }
+            // The bridges of the channels closed by a reset are kept while they are reopened.
+            let kept = reset.is_some() && matches!(event, MuxEvent::DlcClosed(_));
+            if !kept && endpoints.deliver(&event)? {
+                continue;
+            }
+            match ending.log(event, log) {
+                Some(result) if reset.is_none() => return result,
+                _ => {}
+            }
+        }
+        if let Some(reason) = reset {
+            ending = Ending::default();
+            *link.mux_mut() = engine(config);
+            connect(config, &mut link)?;
+            for channel in &config.channels {
+                let dlci = channel.dlci;
+                if link.connect(dlci)? {
+                    log(&DaemonEvent::DlcOpened { dlci });
+                } else {
+                    log(&DaemonEvent::DlcRefused { dlci });
+                    endpoints.remove(dlci);
+                }
+            }
+            log(&DaemonEvent::Reconnected { reason });
+        }
+    }
+    Ok(())
+}
+
+/// Interval between the reads of the control lines of the serial port, and between the
+/// checks of [`stop`], in the sessions of [`run_async`].
+#[cfg(feature = "tokio-serial")]
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Runs one session of [`run_async`], setting `started` once DLCI 0 is connected.
+#[cfg(feature = "tokio-serial")]
+async fn session_async(
+    config: &DaemonConfig,
+    log: &mut impl FnMut(&DaemonEvent),
+    started: &mut bool,
+) -> io::Result<()> {
+    let mut port = async_serial::open(&config.device, &serial_settings(config))?;
+    if let Some(init) = &config.init() {
+        async_serial::enter_cmux(&mut port, init, Duration::from_secs(1)).await?;
+    }
+    // Pseudo-terminals and some adapters have no control lines to watch.
+    let lines = async_serial::line_status(&mut port);
+    let (mux, mut driver) = channel::new(port, engine(config));
+    match lines {
+        Ok(_) => driver = driver.with_line_watch(POLL_INTERVAL, async_serial::line_status),
+        Err(e) => log(&DaemonEvent::Error {
+            message: format!("control lines are not watched: {}", e),
+        }),
+    }
+    if config.mode == CmuxMode::Advanced {
+        driver = driver.advanced();
+    }
+    let mut events = mux.events();
+    let bridges = async {
+        let _control = mux.open(0).await.map_err(|_| {
+            io::Error::new(ErrorKind::ConnectionRefused, "DLCI 0 was not connected")
+        })?;
+        *started = true;
+        log(&DaemonEvent::Started {
+            device: config.device.clone(),
+        });
+
+        let mut tasks = tokio::task::JoinSet::new();
+        // The DLCs whose data goes to a bridge rather than to the logs.
+        let mut bridged = BTreeSet::new();
+        for channel in &config.channels {
+            let dlci = channel.dlci;
+            let Ok(mut dlc) = mux.open(dlci).await else {
+                log(&DaemonEvent::DlcRefused { dlci });
+                continue;
+            };
+            match channel.listen().await? {
+                Some((listener, name)) => {
+                    log(&DaemonEvent::Listening {
+                        dlci,
+                        endpoint: name,
+                    });
+                    bridged.insert(dlci);
+                    tasks.spawn(serve(dlc, listener));
+                }
+                None => {
+                    // The data is logged from the events, and dropped from the channel.
+                    tasks.spawn(async move {
+                        tokio::io::copy(&mut dlc, &mut tokio::io::sink()).await?;
+                        Ok(())
+                    });
+                }
+            }
+        }
+        let mut stop = tokio::time::interval(POLL_INTERVAL);
+        let mut ending = Ending::default();
+        loop {
+            tokio::select! {
+                event = events.next() => {
+                    let Some(event) = event else {
+                        return Ok(());
+                    };
+                    if matches!(&event, MuxEvent::Data { dlci, .. } if bridged.contains(dlci)) {
+                        continue;
+                    }
+                    if let Some(result) = ending.log(event, log) {
+                        return result;
+                    }
+                }
+                Some(result) = tasks.join_next() => result.map_err(io::Error::other)??,
+                _ = stop.tick() => {
+                    if STOP.load(Ordering::SeqCst) {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    };
+    tokio::select! {
+        result = driver.run() => result,
+        result = bridges => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config() {
+        let config: DaemonConfig = r#"
+            device = "/dev/ttyUSB0"
+            mode = "advanced"
+            init = ""
+            max_restarts = 3
+            keep_alive_ms = 30000
+            write_timeout_ms = 2000
+            reconnect = true
+
+            [[channel]]
+            dlci = 1
+            bridge = "unix"
+            path = "/run/cmux/dlci1.sock"
+            group = "dialout"
+            mode = 0o660
+
+            [[channel]]
+            dlci = 2
+            bridge = "none"
+
+            [[channel]]
+            dlci = 3
+            name = "data"
+            bridge = "pty"
+
+            [[channel.extra]]
+            bridge = "pipe"
+
+            [[channel.extra]]
+            bridge = "tcp"
+            address = "127.0.0.1:5003"
+
+            [[channel]]
+            dlci = 4
+            bridge = "serial"
+            port = "CNCB0"
+            baud = 9600
+            data_bits = 7
+            parity = "even"
+            flow_control = "hardware"
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(config.mode, CmuxMode::Advanced);
+        assert_eq!(config.init(), None);
+        assert_eq!(config.restart, RestartPolicy::OnFailure);
+        assert_eq!(config.restart_delay_ms, 1000);
+        assert_eq!(
+            (config.keep_alive_ms, config.keep_alive_misses),
+            (Some(30000), 3)
+        );
+        assert_eq!(
+            engine(&config).config().write.deadline,
+            Some(Duration::from_secs(2))
+        );
+        assert!(config.reconnect);
+        assert_eq!(
+            config.channels[0].bridge,
+            BridgeConfig::Unix {
+                path: PathBuf::from("/run/cmux/dlci1.sock")
+            }
+        );
+        assert_eq!(config.channels[1].bridge, BridgeConfig::None);
+        assert_eq!(config.channels[2].bridge, BridgeConfig::Pty { link: None });
+        assert_eq!(
+            config.channels[2].bridges().skip(1).collect::<Vec<_>>(),
+            [
+                &BridgeConfig::Pipe { path: None },
+                &BridgeConfig::Tcp {
+                    address: "127.0.0.1:5003".parse().unwrap()
+                }
+            ]
+        );
+        assert_eq!(config.pipe_path(&config.channels[2]), r"\\.\pipe\cmux-data");
+        assert_eq!(
+            config.channels[3].bridge,
+            BridgeConfig::Serial {
+                port: "CNCB0".to_string(),
+                settings: SerialSettings {
+                    baud: 9600,
+                    data_bits: serialport::DataBits::Seven,
+                    parity: serialport::Parity::Even,
+                    flow_control: serialport::FlowControl::Hardware,
+                    ..Default::default()
+                }
+            }
+        );
+        assert_eq!(
+            (&config.channels[0].group, config.channels[0].mode),
+            (&Some(Owner::Name("dialout".to_string())), Some(0o660))
+        );
+        assert_eq!(config.channels[1].name(), "dlci2");
+        assert_eq!(
+            config.pty_link(&config.channels[2]),
+            PathBuf::from("/dev/cmux/data")
+        );
+
+        let channels = |channels: &str| {
+            format!("device = \"/dev/ttyUSB0\"\n{}", channels).parse::<DaemonConfig>()
+        };
+        assert_eq!(
+            channels("[[channel]]\ndlci = 0\nbridge = \"none\""),
+            Err(DaemonConfigError::InvalidDlci(0))
+        );
+        assert_eq!(
+            channels("[[channel]]\ndlci = 1\nbridge = \"none\"\n[[channel]]\ndlci = 1\nbridge = \"none\""),
+            Err(DaemonConfigError::DuplicateChannel(1))
+        );
+        assert_eq!(
+            channels("[[channel]]\ndlci = 1\nbridge = \"none\"\nmode = 0o10660"),
+            Err(DaemonConfigError::InvalidMode(0o10660))
+        );
+        let e = channels("[[channel]]\ndlci = 1\nbridge = \"tcp\"").unwrap_err();
+        assert!(e.to_string().contains("missing field `address`"), "{}", e);
+        let e =
+            channels("[[channel]]\ndlci = 1\nbridge = \"serial\"\nport = \"COM1\"\nstop_bits = 3")
+                .unwrap_err();
+        assert!(e.to_string().contains("stop_bits 3 is not 1 or 2"), "{}", e);
+    }
+
+    #[cfg(feature = "mqtt")]
+    #[test]
+    fn test_mqtt_config() {
+        let config: DaemonConfig = r#"
+            device = "/dev/ttyUSB0"
+
+            [mqtt]
+            host = "broker.local"
+            topic = "modem/cmux"
+        "#
+        .parse()
+        .unwrap();
+        let mqtt = config.mqtt.unwrap();
+        assert_eq!(
+            mqtt,
+            MqttConfig::new("broker.local").with_topic("modem/cmux")
+        );
+        assert_eq!(mqtt.frame_topic(2), "modem/cmux/dlci/2");
+    }
+
+    #[cfg(feature = "tokio-serial")]
+    #[tokio::test]
+    async fn test_serve() {
+        let (host, modem) = tokio::io::duplex(1024);
+        let (host, host_driver) = channel::new(host, Mux::new());
+        let (modem, modem_driver) = channel::new(modem, Mux::new().with_initiator(false));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let test = async {
+            let _control = host.open(0).await.unwrap();
+            let (dlc, peer) = tokio::join!(host.open(1), modem.accept(1));
+            let mut peer = peer.unwrap();
+            let client = async move {
+                let mut client = tokio::net::TcpStream::connect(address).await.unwrap();
+                client.write_all(b"ATI\r").await.unwrap();
+                let mut buf = [0; 4];
+                peer.read_exact(&mut buf).await.unwrap();
+                assert_eq!(&buf, b"ATI\r");
+                peer.write_all(b"OK\r\n").await.unwrap();
+                client.read_exact(&mut buf).await.unwrap();
+                assert_eq!(&buf, b"OK\r\n");
+                // Closing the DLC ends the bridge.
+                drop(peer);
+            };
+            let (result, ()) =
+                tokio::join!(serve(dlc.unwrap(), AsyncListener::Tcp(listener)), client);
+            result.unwrap();
+        };
+        tokio::select! {
+            _ = test => {}
+            _ = host_driver.run() => unreachable!(),
+            _ = modem_driver.run() => unreachable!(),
+        }
+    }
+
+    #[cfg(all(unix, feature = "tokio-serial", feature = "pty"))]
+    #[tokio::test]
+    async fn test_session_async() {
+        use crate::simulator::Simulator;
+
+        let modem = crate::pty::Pty::open().unwrap();
+        let device = modem.path().display().to_string();
+        let config: DaemonConfig = format!(
+            "device = {:?}\ninit = \"\"\nrestart = \"never\"\n[[channel]]\ndlci = 1\nbridge = \"tcp\"\naddress = \"127.0.0.1:0\"",
+            device
+        )
+        .parse()
+        .unwrap();
+        let reconnect = DaemonConfig {
+            reconnect: true,
+            ..config.clone()
+        };
+        let e = run_async(&reconnect, |_| {}).await.unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Unsupported);
+
+        // The modem closes DLCI 0 once told to, ending the session.
+        let (close, closing) = std::sync::mpsc::channel();
+        let modem = std::thread::spawn(move || {
+            let mut simulator = Simulator::new(modem);
+            loop {
+                let now = Instant::now();
+                if simulator.poll(now).unwrap() == 0 {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                if closing.try_recv().is_ok() {
+                    simulator.mux_mut().close(0, now).unwrap();
+                }
+                while let Some(event) = simulator.poll_event() {
+                    if event == MuxEvent::DlcClosed(0) {
+                        return;
+                    }
+                }
+            }
+        });
+
+        let (listening, mut endpoint) = tokio::sync::mpsc::unbounded_channel();
+        let mut events = Vec::new();
+        let client = async {
+            let address = endpoint.recv().await.unwrap();
+            let mut client = tokio::net::TcpStream::connect(address).await.unwrap();
+            client.write_all(b"AT\r").await.unwrap();
+            let mut response = Vec::new();
+            while !response.ends_with(b"OK\r\n") {
+                let mut buf = [0; 64];
+                let count = client.read(&mut buf).await.unwrap();
+                response.extend_from_slice(&buf[..count]);
+            }
+            close.send(()).unwrap();
+        };
+        let session = run_async(&config, |event| {
+            if let DaemonEvent::Listening { endpoint, .. } = event {
+                let address = endpoint.trim_start_matches("tcp://").to_string();
+                listening.send(address).unwrap();
+            }
+            events.push(event.clone());
+        });
+        let (result, ()) = tokio::join!(session, client);
+        result.unwrap();
+        modem.join().unwrap();
+        // A pseudo-terminal has no control lines.
+        assert!(matches!(&events[0], DaemonEvent::Error { message }
+            if message.starts_with("control lines are not watched")));
+        assert_eq!(events[1], DaemonEvent::Started { device });
+        assert!(events.contains(&DaemonEvent::DlcOpened { dlci: 1 }));
+        assert_eq!(
+            events[events.len() - 2..],
+            [
+                DaemonEvent::DlcClosed { dlci: 0 },
+                DaemonEvent::Stopped { error: None }
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_access() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let dir = std::env::temp_dir().join(format!("cmux-daemon-{}", std::process::id()));
+        let path = dir.join("dlci1.sock");
+        let uid = nix::unistd::getuid().as_raw();
+        let config: DaemonConfig = format!(
+            "device = \"/dev/null\"\n[[channel]]\ndlci = 1\nbridge = \"unix\"\npath = {:?}\nowner = {}\nmode = 0o640",
+            path, uid
+        )
+        .parse()
+        .unwrap();
+        let endpoint = config.channels[0].open(&config).unwrap();
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o7777, 0o640);
+        assert_eq!(metadata.uid(), uid);
+        drop(endpoint);
+        assert!(!path.exists());
+        std::fs::remove_dir(&dir).unwrap();
+
+        let owner = Owner::Name("no-such-cmux-user".to_string());
+        assert_eq!(owner.uid().unwrap_err().kind(), ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::error::FrameError;
+use crate::types::{fcs, Frame, FrameRef, FrameType, Length, LengthEncoding, FLAG};
+use alloc::vec::Vec;
+
+/// Fields covered by the FCS of a [`Dialect`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FcsCoverage {
+    /// Address, control and length fields, and the information field of UI frames only, as
+    /// 07.10 specifies.
+    #[default]
+    Spec,
+    /// Address, control and length fields of every frame, UI frames included.
+    Header,
+    /// Address, control and length fields and the information field of every frame.
+    All,
+}
+
+/// Framing quirks of a multiplexer stack
+///
+/// Some vendor stacks do not frame basic option frames as 07.10 specifies: they delimit frames
+/// with another flag octet, send the first frame without its opening flag, write two-octet
+/// length indicators swapped or compute the FCS over other fields. A dialect describes these
+/// quirks, so that their traces can still be decoded by [`scan_with`](crate::parser::scan_with),
+/// [`FrameIter::with_dialect`](crate::parser::FrameIter::with_dialect) and
+/// [`FrameParser::with_dialect`](crate::parser::FrameParser::with_dialect).
+///
+/// Frames decoded in a dialect keep their FCS as read: check it with [`Dialect::verify`].
+///
+/// # Example
+///
+/// ```
+/// use cmux::dialect::Dialect;
+/// use cmux::parser::{scan_with, Scanned};
+///
+/// // Frames delimited by 0x7E, the first one without opening flag.
+/// let dialect = Dialect::SPEC.with_flag(0x7E).with_leading_flag(false);
+/// let data = hex::decode("033F011C7E073F01DE7E").unwrap();
+/// let scanned: Vec<Scanned> = scan_with(&data, &dialect).collect();
+/// assert_eq!(scanned.len(), 2);
+/// assert!(matches!(&scanned[0], Scanned::Frame { offset: 0, frame } if dialect.encode(frame) == data[..5]));
+/// assert!(matches!(&scanned[1], Scanned::Frame { offset: 4, frame } if dialect.verify(frame).is_ok()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dialect {
+    /// Octet opening and closing every frame.
+    pub flag: u8,
+    /// False if the first frame of a stream has no opening flag. The closing flag of each
+    /// frame then opens the next one.
+    pub leading_flag: bool,
+    /// Byte order of two-octet length indicators.
+    pub length_encoding: LengthEncoding,
+    /// Fields covered by the FCS.
+    pub fcs_coverage: FcsCoverage,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Dialect::SPEC
+    }
+}
+
+impl Dialect {
+    /// Basic option framing as 07.10 specifies.
+    pub const SPEC: Dialect = Dialect {
+        flag: FLAG,
+        leading_flag: true,
+        length_encoding: LengthEncoding::Spec,
+        fcs_coverage: FcsCoverage::Spec,
+    };
+
+    /// Basic option framing as written by cmux 0.2 and earlier, with swapped two-octet length
+    /// indicators.
+    pub const LEGACY: Dialect = Dialect {
+        length_encoding: LengthEncoding::Legacy,
+        ..Dialect::SPEC
+    };
+
+    /// Sets the flag octet, 0xF9 by default.
+    pub const fn with_flag(mut self, flag: u8) -> Self {
+        self.flag = flag;
+        self
+    }
+
+    /// Sets whether the first frame of a stream has an opening flag, true by default.
+    pub const fn with_leading_flag(mut self, leading_flag: bool) -> Self {
+        self.leading_flag = leading_flag;
+        self
+    }
+
+    /// Sets the byte order of two-octet length indicators.
+    pub const fn with_length_encoding(mut self, encoding: LengthEncoding) -> Self {
+        self.length_encoding = encoding;
+        self
+    }
+
+    /// Sets the fields covered by the FCS.
+    pub const fn with_fcs_coverage(mut self, coverage: FcsCoverage) -> Self {
+        self.fcs_coverage = coverage;
+        self
+    }
+
+    /// Computes the FCS of `frame` in this dialect.
+    pub fn checksum(&self, frame: &FrameRef<'_>) -> u8 {
+        let covered = match self.fcs_coverage {
+            FcsCoverage::Spec => frame.control.frame_type() == FrameType::UI,
+            FcsCoverage::Header => false,
+            FcsCoverage::All => true,
+        };
+        let content = if covered { frame.content } else { &[] };
+        fcs(
+            frame.address.into_bits(),
+            frame.control.into_bits(),
+            Length::from_indicator(frame.length),
+            self.length_encoding,
+            content,
+        )
+    }
+
+    /// Verifies the length indicator and the FCS of `frame` in this dialect.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(())`: If the frame is valid.
+    /// - `Err(FrameError)`: If the length indicator or the FCS does not match.
+    pub fn verify(&self, frame: &Frame) -> Result<(), FrameError> {
+        self.verify_ref(&frame.as_frame_ref())
+    }
+
+    /// Verifies a borrowed frame, like [`Dialect::verify`].
+    pub fn verify_ref(&self, frame: &FrameRef<'_>) -> Result<(), FrameError> {
+        let expected = Length::new(frame.content.len())?.indicator();
+        if frame.length != expected {
+            return Err(FrameError::BadLength {
+                expected,
+                actual: frame.length,
+            });
+        }
+        let expected = self.checksum(frame);
+        if frame.checksum != expected {
+            return Err(FrameError::ChecksumMismatch {
+                expected,
+                actual: frame.checksum,
+            });
+        }
+        Ok(())
+    }
+
+    /// Encodes `frame` in this dialect.
+    ///
+    /// The FCS is written as the frame carries it, so frames decoded in this dialect are
+    /// encoded back unchanged. Without a leading flag, only the closing flag is written, and
+    /// encoded frames are meant to be sent one after another.
+    pub fn encode(&self, frame: &Frame) -> Vec<u8> {
+        let mut data = Vec::with_capacity(frame.content.len() + 7);
+        if self.leading_flag {
+            data.push(self.flag);
+        }
+        data.push(frame.address.into_bits());
+        data.push(frame.control.into_bits());
+        let (octets, count) = Length::from_indicator(frame.length).encode(self.length_encoding);
+        data.extend_from_slice(&octets[..count]);
+        data.extend_from_slice(frame.content.as_bytes());
+        data.push(frame.checksum);
+        data.push(self.flag);
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{scan_with, Scanned};
+    use crate::types::FrameBuilder;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    #[test]
+    fn test_fcs_coverage() {
+        let frame = FrameBuilder::default()
+            .with_content("AT".to_string())
+            .build()
+            .unwrap();
+        let frame = frame.as_frame_ref();
+        assert_eq!(Dialect::SPEC.checksum(&frame), frame.checksum);
+        // The FCS of UIH frames leaves the information field out.
+        let header = Dialect::SPEC.with_fcs_coverage(FcsCoverage::Header);
+        assert_eq!(header.checksum(&frame), frame.checksum);
+        let all = Dialect::SPEC.with_fcs_coverage(FcsCoverage::All);
+        assert_ne!(all.checksum(&frame), frame.checksum);
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let dialect = Dialect::LEGACY.with_flag(0x7E).with_leading_flag(false);
+        let mut frame = FrameBuilder::default()
+            .with_binary_content(vec![0x7E; 300])
+            .build()
+            .unwrap();
+        frame.checksum = dialect.checksum(&frame.as_frame_ref());
+        let mut data = dialect.encode(&frame);
+        data.extend(dialect.encode(&frame));
+        let scanned: Vec<Scanned> = scan_with(&data, &dialect).collect();
+        assert_eq!(scanned.len(), 2);
+        for scanned in scanned {
+            let Scanned::Frame { frame: decoded, .. } = scanned else {
+                panic!("not a frame: {:?}", scanned);
+            };
+            assert_eq!(decoded.content, frame.content);
+            assert_eq!(dialect.verify(&decoded), Ok(()));
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use crate::parser::FrameParser;
+use crate::types::Frame;
+use alloc::vec::Vec;
+use embedded_io::{Read, ReadReady, Write};
+
+/// Size of the buffer of [`EmbeddedTransport::read_frames`].
+const BUFFER_SIZE: usize = 64;
+
+/// Byte pipe over the `embedded-io` traits, such as the UART of a microcontroller wired to a
+/// cellular modem
+///
+/// Reads only block when bytes are ready, as told by `ReadReady`, so a main loop can run the
+/// timers of the multiplexer between them. Without `std`, frames are read and written with
+/// [`EmbeddedTransport::read_frames`] and [`EmbeddedTransport::write_frame`]. With `std`, the
+/// pipe is a [`Transport`](crate::transport::Transport) a [`Mux`](crate::mux::Mux) can be
+/// driven over with a [`Link`](crate::transport::Link).
+///
+/// # Example
+///
+/// ```
+/// use cmux::embedded::EmbeddedTransport;
+/// use cmux::parser::FrameParser;
+/// use embedded_io::{Read, ReadReady, Write};
+///
+/// /// Echoes the valid frames received, from the main loop of the firmware.
+/// fn poll<T: Read + ReadReady + Write>(
+///     uart: &mut EmbeddedTransport<T>,
+///     parser: &mut FrameParser,
+/// ) -> Result<(), T::Error> {
+///     for frame in uart.read_frames(parser)? {
+///         if frame.verify().is_ok() {
+///             uart.write_frame(&frame)?;
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct EmbeddedTransport<T> {
+    inner: T,
+}
+
+impl<T> EmbeddedTransport<T> {
+    /// Wraps `inner`.
+    pub fn new(inner: T) -> Self {
+        EmbeddedTransport { inner }
+    }
+
+    /// Returns the wrapped pipe.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwraps the pipe.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: Read + ReadReady> EmbeddedTransport<T> {
+    /// Reads the bytes ready, if any, and feeds them to `parser`.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Vec<Frame>)`: The frames completed by the bytes read, in stream order.
+    /// - `Err(T::Error)`: If the pipe failed.
+    pub fn read_frames(&mut self, parser: &mut FrameParser) -> Result<Vec<Frame>, T::Error> {
+        let mut frames = Vec::new();
+        let mut buffer = [0; BUFFER_SIZE];
+        while self.inner.read_ready()? {
+            let count = self.inner.read(&mut buffer)?;
+            if count == 0 {
+                break;
+            }
+            frames.extend(parser.feed(&buffer[..count]));
+        }
+        Ok(frames)
+    }
+}
+
+impl<T: Write> EmbeddedTransport<T> {
+    /// Writes `frame` with basic option framing and flushes the pipe.
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<(), T::Error> {
+        self.inner.write_all(&frame.to_bytes())?;
+        self.inner.flush()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Read + ReadReady + Write> crate::transport::Transport for EmbeddedTransport<T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() || !self.inner.read_ready().map_err(io_error)? {
+            return Ok(0);
+        }
+        match self.inner.read(buf) {
+            Ok(0) => Err(std::io::ErrorKind::UnexpectedEof.into()),
+            Ok(count) => Ok(count),
+            Err(e)
+                if matches!(
+                    embedded_io::Error::kind(&e),
+                    embedded_io::ErrorKind::TimedOut | embedded_io::ErrorKind::Interrupted
+                ) =>
+            {
+                Ok(0)
+            }
+            Err(e) => Err(io_error(e)),
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
+        self.inner.write_all(data).map_err(io_error)?;
+        self.inner.flush().map_err(io_error)
+    }
+}
+
+/// Converts an `embedded-io` error to the `std::io` error of the same kind.
+#[cfg(feature = "std")]
+fn io_error(e: impl embedded_io::Error) -> std::io::Error {
+    std::io::ErrorKind::from(e.kind()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FrameBuilder;
+    use alloc::collections::VecDeque;
+    use alloc::string::ToString;
+    use core::convert::Infallible;
+
+    /// UART receiving what it sends.
+    #[derive(Default)]
+    struct Loopback(VecDeque<u8>);
+
+    impl embedded_io::ErrorType for Loopback {
+        type Error = Infallible;
+    }
+
+    impl Read for Loopback {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Infallible> {
+            let count = buf.len().min(self.0.len());
+            for (dst, src) in buf.iter_mut().zip(self.0.drain(..count)) {
+                *dst = src;
+            }
+            Ok(count)
+        }
+    }
+
+    impl ReadReady for Loopback {
+        fn read_ready(&mut self) -> Result<bool, Infallible> {
+            Ok(!self.0.is_empty())
+        }
+    }
+
+    impl Write for Loopback {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Infallible> {
+            self.0.extend(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_then_read() {
+        // The frames span several reads of the buffer.
+        let frame = FrameBuilder::default()
+            .with_content("AT+CMUX=0,0,5,127,10,3,30,10,2".to_string())
+            .build()
+            .unwrap();
+        let mut uart = EmbeddedTransport::new(Loopback::default());
+        uart.write_frame(&frame).unwrap();
+        uart.write_frame(&frame).unwrap();
+        let mut parser = FrameParser::new();
+        let frames = uart.read_frames(&mut parser).unwrap();
+        assert_eq!(frames, [frame.clone(), frame]);
+        assert_eq!(uart.read_frames(&mut parser).unwrap(), []);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_link() {
+        use crate::mux::{DlcState, Mux};
+        use crate::transport::{Link, Transport};
+        use std::time::Instant;
+
+        let now = Instant::now();
+        let mut host = Link::new(Mux::new(), EmbeddedTransport::new(Loopback::default()));
+        let mut modem = Link::new(
+            Mux::new().with_initiator(false),
+            EmbeddedTransport::new(Loopback::default()),
+        );
+        host.mux_mut().open(0, now).unwrap();
+        host.flush().unwrap();
+        // Carry the bytes over, as the wires between the two UARTs would.
+        let mut buf = [0; 64];
+        let count = host.transport_mut().read(&mut buf).unwrap();
+        modem.transport_mut().get_mut().0.extend(&buf[..count]);
+        assert_eq!(modem.poll(now).unwrap(), count);
+        assert_eq!(modem.mux().state(0), DlcState::Connected);
+    }
+}
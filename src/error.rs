@@ -0,0 +1,594 @@
+use crate::control::MessageType;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt::{Display, Formatter};
+
+/// Errors raised while building, parsing or verifying a [`Frame`](crate::types::Frame).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameError {
+    /// The input ends before the frame does.
+    TruncatedFrame {
+        /// Number of octets the frame needs at least.
+        expected: usize,
+        /// Number of octets available.
+        actual: usize,
+    },
+    /// An opening or closing flag octet is not the expected flag.
+    BadFlag {
+        /// The flag octet of the framing.
+        expected: u8,
+        /// The octet found in place of the flag.
+        found: u8,
+    },
+    /// The length indicator does not match the information field.
+    BadLength {
+        /// The length indicator computed from the information field.
+        expected: u16,
+        /// The length indicator carried by the frame.
+        actual: u16,
+    },
+    /// The FCS does not match the calculated checksum.
+    ChecksumMismatch {
+        /// The calculated checksum.
+        expected: u8,
+        /// The checksum carried by the frame.
+        actual: u8,
+    },
+    /// The information field is too long to be described by the length indicator, or to fit
+    /// in a fixed-capacity frame.
+    ContentTooLong {
+        /// Number of octets in the information field.
+        len: usize,
+    },
+    /// A field required by [`FrameBuilder`](crate::types::FrameBuilder) was not set.
+    MissingField(&'static str),
+}
+
+impl Display for FrameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FrameError::TruncatedFrame { expected, actual } => write!(
+                f,
+                "Frame is truncated: expected at least {} bytes, got {}",
+                expected, actual
+            ),
+            FrameError::BadFlag { expected, found } => write!(
+                f,
+                "Flag is invalid: expected 0x{:02X}, found 0x{:02X}",
+                expected, found
+            ),
+            FrameError::BadLength { expected, actual } => write!(
+                f,
+                "Length field is invalid: expected 0x{:X}, found 0x{:X}",
+                expected, actual
+            ),
+            FrameError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "Checksum is invalid: expected 0x{:02X}, found 0x{:02X}",
+                expected, actual
+            ),
+            FrameError::ContentTooLong { len } => {
+                write!(f, "Content of {} bytes is too long", len)
+            }
+            FrameError::MissingField(field) => write!(f, "{} is required", field),
+        }
+    }
+}
+
+impl Error for FrameError {}
+
+/// The buffer given to [`Frame::encode_into`](crate::types::Frame::encode_into) cannot hold
+/// the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferTooSmall {
+    /// Number of octets the encoded frame occupies.
+    pub needed: usize,
+    /// Number of octets of the buffer.
+    pub available: usize,
+}
+
+impl Display for BufferTooSmall {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "Buffer is too small: the frame needs {} bytes, got {}",
+            self.needed, self.available
+        )
+    }
+}
+
+impl Error for BufferTooSmall {}
+
+/// Octets of context kept before the offset of a [`ParseError`].
+const CONTEXT_BEFORE: usize = 4;
+
+/// Octets of context kept from the offset of a [`ParseError`].
+const CONTEXT_AFTER: usize = 8;
+
+/// A [`FrameError`] located in a byte stream
+///
+/// The error keeps a few octets around the offset, so a corrupted frame can be found in a
+/// large capture.
+///
+/// # Example
+///
+/// ```
+/// use cmux::error::{FrameError, ParseError};
+///
+/// let data = [0x00, 0xF9, 0x07, 0x3F, 0x01, 0x1C, 0xF9];
+/// let error = ParseError::new(&data, 1, FrameError::ChecksumMismatch { expected: 0xDE, actual: 0x1C });
+/// assert_eq!(
+///     error.to_string(),
+///     "Checksum is invalid: expected 0xDE, found 0x1C at offset 1: 00 [F9] 07 3F 01 1C F9"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Offset of the failing frame in the input.
+    pub offset: usize,
+    pub error: FrameError,
+    /// Octets around the offset.
+    pub context: Vec<u8>,
+    /// Offset of the first octet of `context` in the input.
+    pub context_offset: usize,
+}
+
+impl ParseError {
+    /// Locates `error` at `offset` in `data`, keeping the octets around it.
+    pub fn new(data: &[u8], offset: usize, error: FrameError) -> Self {
+        let context_offset = offset.saturating_sub(CONTEXT_BEFORE).min(data.len());
+        let end = offset.saturating_add(CONTEXT_AFTER).min(data.len());
+        ParseError {
+            offset,
+            error,
+            context: data[context_offset..end].to_vec(),
+            context_offset,
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} at offset {}", self.error, self.offset)?;
+        for (i, octet) in self.context.iter().enumerate() {
+            let separator = if i == 0 { ": " } else { " " };
+            if self.context_offset + i == self.offset {
+                write!(f, "{}[{:02X}]", separator, octet)?;
+            } else {
+                write!(f, "{}{:02X}", separator, octet)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Error for ParseError {}
+
+/// Errors raised while decoding a multiplexer [`ControlMessage`](crate::control::ControlMessage).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlError {
+    /// The input ends before the message does.
+    Truncated {
+        /// Number of octets the message needs at least.
+        expected: usize,
+        /// Number of octets available.
+        actual: usize,
+    },
+    /// The type octet has its EA bit cleared, announcing an unsupported multi-octet type.
+    ExtendedType(u8),
+    /// The length field has more octets than the given maximum.
+    LengthOverflow(usize),
+    /// The frame is not a UIH or UI frame on DLCI 0.
+    NotControlFrame,
+    /// The message is not of the type being decoded.
+    UnexpectedType {
+        /// The type being decoded.
+        expected: MessageType,
+        /// The type of the message.
+        found: MessageType,
+    },
+    /// The value octets do not have the length required by the message type.
+    BadValueLength {
+        /// The required number of value octets.
+        expected: usize,
+        /// The number of value octets in the message.
+        actual: usize,
+    },
+}
+
+impl Display for ControlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ControlError::Truncated { expected, actual } => write!(
+                f,
+                "Control message is truncated: expected at least {} bytes, got {}",
+                expected, actual
+            ),
+            ControlError::ExtendedType(typ) => {
+                write!(
+                    f,
+                    "Extended control message type 0x{:02X} is not supported",
+                    typ
+                )
+            }
+            ControlError::LengthOverflow(max) => write!(
+                f,
+                "Control message length is invalid: more than {} length octets",
+                max
+            ),
+            ControlError::NotControlFrame => write!(f, "Frame is not a DLCI 0 UIH/UI frame"),
+            ControlError::UnexpectedType { expected, found } => write!(
+                f,
+                "Control message type is invalid: expected {:?}, found {:?}",
+                expected, found
+            ),
+            ControlError::BadValueLength { expected, actual } => write!(
+                f,
+                "Control message value is invalid: expected {} bytes, got {}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl Error for ControlError {}
+
+/// Errors raised while decoding the information field of a [`convergence`](crate::convergence)
+/// layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConvergenceError {
+    /// The information field ends inside the convergence layer octets.
+    Truncated {
+        /// Number of octets the convergence layer needs at least.
+        expected: usize,
+        /// Number of octets available.
+        actual: usize,
+    },
+    /// A Type 4 segment without B continues a message that was not begun.
+    NotBegun,
+}
+
+impl Display for ConvergenceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ConvergenceError::Truncated { expected, actual } => write!(
+                f,
+                "Convergence layer octets are truncated: expected at least {} bytes, got {}",
+                expected, actual
+            ),
+            ConvergenceError::NotBegun => {
+                write!(f, "Segment continues a message that was not begun")
+            }
+        }
+    }
+}
+
+impl Error for ConvergenceError {}
+
+/// Errors raised by the [`Mux`](crate::mux::Mux) engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MuxError {
+    /// The peer did not answer a SABM or DISC within N2 retransmissions.
+    Timeout {
+        /// The DLCI of the unanswered command.
+        dlci: u8,
+    },
+    /// The peer answered SABM or DISC with DM.
+    Rejected {
+        /// The DLCI of the rejected command.
+        dlci: u8,
+    },
+    /// The peer did not answer a control command within N2 retransmissions.
+    NoResponse(MessageType),
+    /// Data was sent on a DLC that is not connected.
+    NotConnected {
+        /// The DLCI of the data.
+        dlci: u8,
+    },
+    /// The peer left the configured number of keep-alive Test commands unanswered in a row.
+    Unresponsive {
+        /// The number of unanswered Test commands.
+        missed: u8,
+    },
+    /// A frame could not be built.
+    Frame(FrameError),
+    /// A control message received on DLCI 0 is malformed.
+    Control(ControlError),
+    /// The convergence layer octets of a received frame are malformed.
+    Convergence(ConvergenceError),
+    /// The modem sent its startup banner outside of frames: it was reset and left CMUX mode.
+    ModemReset,
+    /// A frame could not be sent within the deadline of the
+    /// [`WritePolicy`](crate::mux::WritePolicy), or its retries were exhausted.
+    WriteTimeout {
+        /// The DLCI of the frame.
+        dlci: u8,
+    },
+    /// The peer did not answer the wake-up flags within T3.
+    WakeUpTimeout,
+}
+
+impl Display for MuxError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MuxError::Timeout { dlci } => write!(f, "DLCI {} did not respond", dlci),
+            MuxError::Rejected { dlci } => write!(f, "DLCI {} was rejected with DM", dlci),
+            MuxError::NoResponse(message_type) => {
+                write!(f, "Control command {:?} was not answered", message_type)
+            }
+            MuxError::NotConnected { dlci } => write!(f, "DLCI {} is not connected", dlci),
+            MuxError::Unresponsive { missed } => {
+                write!(
+                    f,
+                    "Peer left {} keep-alive Test commands unanswered",
+                    missed
+                )
+            }
+            MuxError::Frame(e) => write!(f, "{}", e),
+            MuxError::Control(e) => write!(f, "{}", e),
+            MuxError::Convergence(e) => write!(f, "{}", e),
+            MuxError::ModemReset => write!(f, "Modem was reset and left CMUX mode"),
+            MuxError::WriteTimeout { dlci } => {
+                write!(f, "Frame on DLCI {} could not be sent in time", dlci)
+            }
+            MuxError::WakeUpTimeout => write!(f, "Peer did not answer the wake-up flags"),
+        }
+    }
+}
+
+impl Error for MuxError {}
+
+impl From<FrameError> for MuxError {
+    fn from(value: FrameError) -> Self {
+        MuxError::Frame(value)
+    }
+}
+
+impl From<ControlError> for MuxError {
+    fn from(value: ControlError) -> Self {
+        MuxError::Control(value)
+    }
+}
+
+impl From<ConvergenceError> for MuxError {
+    fn from(value: ConvergenceError) -> Self {
+        MuxError::Convergence(value)
+    }
+}
+
+/// Errors raised while reading a pcap or pcapng [`capture`](crate::capture).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureError {
+    /// The input does not start with a pcap or pcapng magic number.
+    UnknownFormat,
+    /// The input ends inside a header, block or packet.
+    Truncated {
+        /// Offset of the header, block or packet in the input.
+        offset: usize,
+    },
+}
+
+impl Display for CaptureError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CaptureError::UnknownFormat => write!(f, "Capture is neither pcap nor pcapng"),
+            CaptureError::Truncated { offset } => {
+                write!(f, "Capture is truncated at offset {}", offset)
+            }
+        }
+    }
+}
+
+impl Error for CaptureError {}
+
+/// Errors raised while parsing an `AT+CMUX` command into [`CmuxParams`](crate::at::CmuxParams).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CmuxParamsError {
+    /// The string is not an `AT+CMUX=` command.
+    NotCmux,
+    /// The command has more than the 9 parameters of 27.010.
+    TooManyParameters,
+    /// A parameter is missing, not a number or out of its range.
+    InvalidParameter {
+        /// The name of the parameter, as in 27.010.
+        name: &'static str,
+    },
+}
+
+impl Display for CmuxParamsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CmuxParamsError::NotCmux => write!(f, "Command is not AT+CMUX="),
+            CmuxParamsError::TooManyParameters => {
+                write!(f, "AT+CMUX has at most 9 parameters")
+            }
+            CmuxParamsError::InvalidParameter { name } => {
+                write!(f, "AT+CMUX parameter <{}> is invalid", name)
+            }
+        }
+    }
+}
+
+impl Error for CmuxParamsError {}
+
+/// Errors raised while reading a [`DaemonConfig`](crate::daemon::DaemonConfig).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DaemonConfigError {
+    /// The file is not valid TOML, or a setting is missing or has a wrong type.
+    Syntax(String),
+    /// A channel has a DLCI outside of 1 to 63.
+    InvalidDlci(u8),
+    /// Two channels have the same DLCI.
+    DuplicateChannel(u8),
+    /// A channel has a mode with bits other than the permission bits.
+    InvalidMode(u32),
+}
+
+impl Display for DaemonConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DaemonConfigError::Syntax(message) => write!(f, "{}", message.trim_end()),
+            DaemonConfigError::InvalidDlci(dlci) => {
+                write!(f, "DLCI {} is not between 1 and 63", dlci)
+            }
+            DaemonConfigError::DuplicateChannel(dlci) => {
+                write!(f, "DLCI {} is configured twice", dlci)
+            }
+            DaemonConfigError::InvalidMode(mode) => {
+                write!(f, "mode {:#o} is not a file mode", mode)
+            }
+        }
+    }
+}
+
+impl Error for DaemonConfigError {}
+
+/// Errors raised while converting hex text with the [`codec`](crate::codec) functions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodecError {
+    /// A character is neither a hex digit nor whitespace.
+    InvalidHexDigit(char),
+    /// The text holds an odd number of hex digits.
+    OddHexLength(usize),
+    /// Delimited octets do not decode as a frame.
+    Parse(ParseError),
+}
+
+impl Display for CodecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CodecError::InvalidHexDigit(found) => write!(f, "{:?} is not a hex digit", found),
+            CodecError::OddHexLength(digits) => {
+                write!(f, "Hex text has an odd number of digits: {}", digits)
+            }
+            CodecError::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for CodecError {}
+
+impl From<ParseError> for CodecError {
+    fn from(value: ParseError) -> Self {
+        CodecError::Parse(value)
+    }
+}
+
+/// Errors raised by a [`SelfTest`](crate::selftest::SelfTest).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelfTestError {
+    /// A data DLCI is outside of 2 to 63, DLCI 1 being the AT channel.
+    InvalidDlci(u8),
+    /// The engine raised an error, or a DLC was not connected.
+    Mux(MuxError),
+    /// The simulator did not answer an AT command with OK.
+    AtCommand(String),
+    /// The data received on a DLC differs from the data sent.
+    Corrupted {
+        /// The DLCI of the data.
+        dlci: u8,
+        /// Whether the host sent the data, otherwise the simulated modem.
+        from_host: bool,
+        /// Offset of the first octet lost or changed in the data sent on the DLC.
+        offset: usize,
+    },
+}
+
+impl Display for SelfTestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SelfTestError::InvalidDlci(dlci) => {
+                write!(f, "DLCI {} is not between 2 and 63", dlci)
+            }
+            SelfTestError::Mux(e) => write!(f, "{}", e),
+            SelfTestError::AtCommand(command) => write!(f, "{} was not answered with OK", command),
+            SelfTestError::Corrupted {
+                dlci,
+                from_host,
+                offset,
+            } => write!(
+                f,
+                "Data sent by the {} on DLCI {} differs from offset {}",
+                if *from_host { "host" } else { "modem" },
+                dlci,
+                offset
+            ),
+        }
+    }
+}
+
+impl Error for SelfTestError {}
+
+impl From<MuxError> for SelfTestError {
+    fn from(value: MuxError) -> Self {
+        SelfTestError::Mux(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Frame;
+
+    /// Locates the error of the frame `bad` between two valid frames.
+    fn locate(bad: &str) -> (Vec<u8>, ParseError) {
+        let data = hex::decode(format!("F9033F011CF9{}F9073F01DEF9", bad)).unwrap();
+        let frame = hex::decode(bad).unwrap();
+        let error = match Frame::from_bytes(frame) {
+            Ok(frame) => frame.verify().unwrap_err(),
+            Err(e) => e,
+        };
+        let error = ParseError::new(&data, 6, error);
+        (data, error)
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let (data, error) = locate("F9073F01DE00");
+        assert_eq!(
+            error.error,
+            FrameError::BadFlag {
+                expected: 0xF9,
+                found: 0x00
+            }
+        );
+        assert_eq!(error.offset, 6);
+        assert_eq!(error.context_offset, 2);
+        assert_eq!(error.context, &data[2..14]);
+        assert_eq!(
+            error.to_string(),
+            "Flag is invalid: expected 0xF9, found 0x00 at offset 6: 3F 01 1C F9 [F9] 07 3F 01 DE 00 F9 07"
+        );
+
+        let (data, error) = locate("F9073F05DEF9");
+        assert_eq!(
+            error.error,
+            FrameError::BadLength {
+                expected: 1,
+                actual: 5
+            }
+        );
+        assert_eq!((error.offset, error.context_offset), (6, 2));
+        assert_eq!(error.context, &data[2..14]);
+
+        let (data, error) = locate("F9073F0341DEF9");
+        assert_eq!(
+            error.error,
+            FrameError::ChecksumMismatch {
+                expected: 0x3D,
+                actual: 0xDE
+            }
+        );
+        assert_eq!((error.offset, error.context_offset), (6, 2));
+        assert_eq!(error.context, &data[2..14]);
+        assert!(error
+            .to_string()
+            .ends_with("at offset 6: 3F 01 1C F9 [F9] 07 3F 03 41 DE F9 F9"));
+
+        // The context is cut at both ends of the input.
+        let error = ParseError::new(&data[..8], 1, error.error);
+        assert_eq!(error.context_offset, 0);
+        assert_eq!(error.context, &data[..8]);
+    }
+}
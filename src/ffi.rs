@@ -0,0 +1,318 @@
+use crate::control::{ControlMessage, MessageType};
+use crate::error::{ControlError, FrameError};
+use crate::types::{Address, Control, FrameBuilder, FrameRef};
+use std::ptr;
+use std::slice;
+
+/// Result code of every C function
+///
+/// The values are part of the C API and never change; new codes are only appended.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmuxStatus {
+    Ok = 0,
+    /// A required pointer argument is NULL.
+    NullPointer = -1,
+    /// The output buffer is too small; the required size is written to the length argument.
+    BufferTooSmall = -2,
+    /// The input ends before the frame or control message does.
+    Truncated = -3,
+    /// An opening or closing flag octet is not 0xF9.
+    BadFlag = -4,
+    /// The length indicator does not match the information field.
+    BadLength = -5,
+    /// The FCS does not match the calculated checksum.
+    ChecksumMismatch = -6,
+    /// The information field is too long for the length indicator.
+    ContentTooLong = -7,
+    /// A field required to build the frame is missing.
+    MissingField = -8,
+    /// The control message uses an extended type field or has an invalid value.
+    BadControlMessage = -9,
+}
+
+impl From<FrameError> for CmuxStatus {
+    fn from(value: FrameError) -> Self {
+        match value {
+            FrameError::TruncatedFrame { .. } => CmuxStatus::Truncated,
+            FrameError::BadFlag { .. } => CmuxStatus::BadFlag,
+            FrameError::BadLength { .. } => CmuxStatus::BadLength,
+            FrameError::ChecksumMismatch { .. } => CmuxStatus::ChecksumMismatch,
+            FrameError::ContentTooLong { .. } => CmuxStatus::ContentTooLong,
+            FrameError::MissingField(_) => CmuxStatus::MissingField,
+        }
+    }
+}
+
+impl From<ControlError> for CmuxStatus {
+    fn from(value: ControlError) -> Self {
+        match value {
+            ControlError::Truncated { .. } => CmuxStatus::Truncated,
+            _ => CmuxStatus::BadControlMessage,
+        }
+    }
+}
+
+/// Frame decoded by [`cmux_frame_parse`]
+///
+/// `content` points into the parsed buffer and is valid as long as that buffer is.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct CmuxFrame {
+    pub address: u8,
+    pub control: u8,
+    pub dlci: u8,
+    pub cr: bool,
+    pub pf: bool,
+    /// Length indicator as stored in [`Frame::length`](crate::types::Frame::length).
+    pub length: u16,
+    pub content: *const u8,
+    pub content_len: usize,
+    pub checksum: u8,
+}
+
+/// Control message decoded by [`cmux_control_decode`]
+///
+/// `value` points into the decoded buffer and is valid as long as that buffer is.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct CmuxControlMessage {
+    /// The 6-bit type field, without the EA and C/R bits.
+    pub message_type: u8,
+    pub cr: bool,
+    pub value: *const u8,
+    pub value_len: usize,
+}
+
+/// Borrows `len` bytes at `data`, which may be NULL if `len` is 0.
+unsafe fn input<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    if len == 0 {
+        Some(&[])
+    } else if data.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts(data, len))
+    }
+}
+
+/// Copies `bytes` to `out` if they fit in `out_cap` octets, and writes their size to `out_len`.
+unsafe fn output(bytes: &[u8], out: *mut u8, out_cap: usize, out_len: *mut usize) -> CmuxStatus {
+    if out_len.is_null() {
+        return CmuxStatus::NullPointer;
+    }
+    *out_len = bytes.len();
+    if bytes.len() > out_cap {
+        return CmuxStatus::BufferTooSmall;
+    }
+    if out.is_null() {
+        return CmuxStatus::NullPointer;
+    }
+    ptr::copy_nonoverlapping(bytes.as_ptr(), out, bytes.len());
+    CmuxStatus::Ok
+}
+
+/// Builds a basic option frame and writes its bytes to `out`.
+///
+/// The content is framed as is, without appending `\r\n`.
+///
+/// # Safety
+///
+/// `content` must point to `content_len` readable bytes, or be NULL if `content_len` is 0.
+/// `out` must point to `out_cap` writable bytes and `out_len` to a writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn cmux_frame_build(
+    address: u8,
+    control: u8,
+    content: *const u8,
+    content_len: usize,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> CmuxStatus {
+    let Some(content) = input(content, content_len) else {
+        return CmuxStatus::NullPointer;
+    };
+    let frame = FrameBuilder::default()
+        .with_address(Address::from_bits(address))
+        .with_control(Control::from_bits(control))
+        .with_binary_content(content.to_vec())
+        .build();
+    match frame {
+        Ok(frame) => output(&frame.to_bytes(), out, out_cap, out_len),
+        Err(e) => e.into(),
+    }
+}
+
+/// Parses one basic option frame, from opening to closing flag, without verifying it.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes and `frame` to a writable [`CmuxFrame`].
+#[no_mangle]
+pub unsafe extern "C" fn cmux_frame_parse(
+    data: *const u8,
+    len: usize,
+    frame: *mut CmuxFrame,
+) -> CmuxStatus {
+    let (Some(data), false) = (input(data, len), frame.is_null()) else {
+        return CmuxStatus::NullPointer;
+    };
+    match FrameRef::parse(data) {
+        Ok(parsed) => {
+            *frame = CmuxFrame {
+                address: parsed.address.into_bits(),
+                control: parsed.control.into_bits(),
+                dlci: parsed.address.dlci().into_bits(),
+                cr: parsed.address.cr(),
+                pf: parsed.control.pf(),
+                length: parsed.length,
+                content: parsed.content.as_ptr(),
+                content_len: parsed.content.len(),
+                checksum: parsed.checksum,
+            };
+            CmuxStatus::Ok
+        }
+        Err(e) => e.into(),
+    }
+}
+
+/// Parses one basic option frame and verifies its length indicator and FCS.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn cmux_frame_verify(data: *const u8, len: usize) -> CmuxStatus {
+    let Some(data) = input(data, len) else {
+        return CmuxStatus::NullPointer;
+    };
+    match FrameRef::parse(data).and_then(|frame| frame.verify()) {
+        Ok(()) => CmuxStatus::Ok,
+        Err(e) => e.into(),
+    }
+}
+
+/// Encodes a control message and writes its bytes to `out`.
+///
+/// # Safety
+///
+/// `value` must point to `value_len` readable bytes, or be NULL if `value_len` is 0.
+/// `out` must point to `out_cap` writable bytes and `out_len` to a writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn cmux_control_encode(
+    message_type: u8,
+    cr: bool,
+    value: *const u8,
+    value_len: usize,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> CmuxStatus {
+    let Some(value) = input(value, value_len) else {
+        return CmuxStatus::NullPointer;
+    };
+    let message = ControlMessage::new(MessageType::from_bits(message_type), cr, value.to_vec());
+    output(&message.encode(), out, out_cap, out_len)
+}
+
+/// Decodes the control message at the start of `data`, as found in a DLCI 0 information field.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, `message` to a writable [`CmuxControlMessage`]
+/// and `used` to a writable `size_t`, which receives the size of the message.
+#[no_mangle]
+pub unsafe extern "C" fn cmux_control_decode(
+    data: *const u8,
+    len: usize,
+    message: *mut CmuxControlMessage,
+    used: *mut usize,
+) -> CmuxStatus {
+    let (Some(data), false, false) = (input(data, len), message.is_null(), used.is_null()) else {
+        return CmuxStatus::NullPointer;
+    };
+    match ControlMessage::decode(data) {
+        Ok((decoded, end)) => {
+            let value = &data[end - decoded.value.len()..end];
+            *message = CmuxControlMessage {
+                message_type: decoded.message_type.into_bits(),
+                cr: decoded.cr,
+                value: value.as_ptr(),
+                value_len: value.len(),
+            };
+            *used = end;
+            CmuxStatus::Ok
+        }
+        Err(e) => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame() {
+        let mut out = [0; 16];
+        let mut len = 0;
+        let status =
+            unsafe { cmux_frame_build(0x03, 0x3F, ptr::null(), 0, out.as_mut_ptr(), 4, &mut len) };
+        assert_eq!((status, len), (CmuxStatus::BufferTooSmall, 6));
+        let status =
+            unsafe { cmux_frame_build(0x03, 0x3F, ptr::null(), 0, out.as_mut_ptr(), 16, &mut len) };
+        assert_eq!(status, CmuxStatus::Ok);
+        assert_eq!(out[..len], [0xF9, 0x03, 0x3F, 0x01, 0x1C, 0xF9]);
+
+        let data = [0xF9, 0x07, 0xEF, 0x05, 0x41, 0x54, 0x30, 0xF9];
+        let mut frame = std::mem::MaybeUninit::<CmuxFrame>::uninit();
+        let frame = unsafe {
+            assert_eq!(
+                cmux_frame_parse(data.as_ptr(), data.len(), frame.as_mut_ptr()),
+                CmuxStatus::Ok
+            );
+            frame.assume_init()
+        };
+        assert_eq!((frame.dlci, frame.cr, frame.content_len), (1, true, 2));
+        assert_eq!(frame.content, data[4..].as_ptr());
+        assert_eq!(
+            unsafe { cmux_frame_verify(data.as_ptr(), data.len()) },
+            CmuxStatus::Ok
+        );
+        assert_eq!(
+            unsafe { cmux_frame_verify(data.as_ptr(), 3) },
+            CmuxStatus::Truncated
+        );
+        assert_eq!(
+            unsafe { cmux_frame_parse(ptr::null(), 6, ptr::null_mut()) },
+            CmuxStatus::NullPointer
+        );
+    }
+
+    #[test]
+    fn test_control_message() {
+        let mut out = [0; 8];
+        let mut len = 0;
+        let value = [0x07, 0x0D];
+        let status = unsafe {
+            cmux_control_encode(0x38, true, value.as_ptr(), 2, out.as_mut_ptr(), 8, &mut len)
+        };
+        assert_eq!(status, CmuxStatus::Ok);
+        assert_eq!(out[..len], [0xE3, 0x05, 0x07, 0x0D]);
+
+        let mut message = CmuxControlMessage {
+            message_type: 0,
+            cr: false,
+            value: ptr::null(),
+            value_len: 0,
+        };
+        let mut used = 0;
+        let status = unsafe { cmux_control_decode(out.as_ptr(), len, &mut message, &mut used) };
+        assert_eq!(status, CmuxStatus::Ok);
+        assert_eq!((message.message_type, message.cr, used), (0x38, true, 4));
+        assert_eq!(message.value, out[2..].as_ptr());
+        assert_eq!(
+            unsafe { cmux_control_decode(out.as_ptr(), 3, &mut message, &mut used) },
+            CmuxStatus::Truncated
+        );
+    }
+}
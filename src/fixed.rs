@@ -0,0 +1,305 @@
+use crate::error::{BufferTooSmall, FrameError};
+use crate::types::{frame_checksum, Address, Control, FrameRef, Length, LengthEncoding, FLAG};
+use heapless::Vec;
+
+/// Octets a basic option frame occupies besides its information field, at most.
+const OVERHEAD: usize = 7;
+
+/// Basic option frame holding up to `N` octets of information field inline
+///
+/// The fields are those of [`Frame`](crate::types::Frame), but the content is a
+/// `heapless::Vec`, so firmware can build, parse and encode frames in static or stack memory,
+/// with the largest payload it handles fixed at compile time. Content that does not fit is
+/// rejected with [`FrameError::ContentTooLong`].
+///
+/// # Example
+///
+/// ```
+/// use cmux::fixed::{FixedFrame, FixedFrameBuilder};
+///
+/// let frame: FixedFrame<32> = FixedFrameBuilder::default()
+///     .with_content("AT")
+///     .build()
+///     .unwrap();
+/// let mut buf = [0; FixedFrame::<32>::MAX_ENCODED_LEN];
+/// let len = frame.encode_into(&mut buf).unwrap();
+/// assert_eq!(FixedFrame::<32>::parse(&buf[..len]), Ok(frame));
+/// // "AT\r\n" does not fit in two octets.
+/// assert!(FixedFrame::<2>::parse(&buf[..len]).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedFrame<const N: usize> {
+    pub header: u8,
+    pub address: Address,
+    pub control: Control,
+    pub length: u16,
+    pub content: Vec<u8, N>,
+    pub checksum: u8,
+    pub footer: u8,
+}
+
+impl<const N: usize> FixedFrame<N> {
+    /// Number of octets of the largest encoded frame, to size transmit buffers.
+    pub const MAX_ENCODED_LEN: usize = N + OVERHEAD;
+
+    /// Parses a frame from a byte slice, like [`FrameRef::parse`].
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The byte representation of the frame, from opening to closing flag.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(FixedFrame)`: The frame, its content copied.
+    /// - `Err(FrameError)`: If the data is not a frame, or its content does not fit.
+    pub fn parse(data: &[u8]) -> Result<Self, FrameError> {
+        FrameRef::parse(data)?.try_into()
+    }
+
+    /// Parses a frame from a byte slice whose length indicator is in `encoding`.
+    pub fn parse_with(data: &[u8], encoding: LengthEncoding) -> Result<Self, FrameError> {
+        FrameRef::parse_with(data, encoding)?.try_into()
+    }
+
+    /// Borrows the frame as a [`FrameRef`].
+    pub fn as_frame_ref(&self) -> FrameRef<'_> {
+        FrameRef {
+            header: self.header,
+            address: self.address,
+            control: self.control,
+            length: self.length,
+            content: &self.content,
+            checksum: self.checksum,
+            footer: self.footer,
+        }
+    }
+
+    /// Returns the number of octets written by [`FixedFrame::encode_into`].
+    pub fn encoded_len(&self) -> usize {
+        self.as_frame_ref().encoded_len()
+    }
+
+    /// Encodes the frame at the start of `buf`, like
+    /// [`Frame::encode_into`](crate::types::Frame::encode_into).
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(usize)`: The number of octets written.
+    /// - `Err(BufferTooSmall)`: If `buf` cannot hold the frame. Nothing is written.
+    pub fn encode_into(&self, buf: &mut [u8]) -> Result<usize, BufferTooSmall> {
+        self.as_frame_ref().encode_into(buf)
+    }
+
+    /// Verifies the length indicator and the FCS of the frame, like
+    /// [`Frame::verify`](crate::types::Frame::verify).
+    pub fn verify(&self) -> Result<(), FrameError> {
+        self.as_frame_ref().verify()
+    }
+
+    /// Verifies the integrity of the frame, computing the FCS over the length indicator
+    /// encoded as `encoding`.
+    pub fn verify_with(&self, encoding: LengthEncoding) -> Result<(), FrameError> {
+        self.as_frame_ref().verify_with(encoding)
+    }
+}
+
+impl<const N: usize> TryFrom<FrameRef<'_>> for FixedFrame<N> {
+    type Error = FrameError;
+
+    fn try_from(frame: FrameRef<'_>) -> Result<Self, FrameError> {
+        let content = Vec::from_slice(frame.content).map_err(|()| FrameError::ContentTooLong {
+            len: frame.content.len(),
+        })?;
+        Ok(FixedFrame {
+            header: frame.header,
+            address: frame.address,
+            control: frame.control,
+            length: frame.length,
+            content,
+            checksum: frame.checksum,
+            footer: frame.footer,
+        })
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<const N: usize> defmt::Format for FixedFrame<N> {
+    fn format(&self, f: defmt::Formatter) {
+        self.as_frame_ref().format(f)
+    }
+}
+
+/// Builder of [`FixedFrame`]
+///
+/// It mirrors [`types::FrameBuilder`](crate::types::FrameBuilder), taking borrowed content
+/// instead of owned strings and vectors. Content that does not fit in `N` octets makes
+/// [`FixedFrameBuilder::build`] fail.
+///
+/// # Example
+///
+/// ```
+/// use cmux::fixed::FixedFrameBuilder;
+/// use cmux::types::{Address, Control, FrameBuilder};
+///
+/// let fixed = FixedFrameBuilder::<16>::default()
+///     .with_address(Address::default())
+///     .with_content("AT+CMUX?")
+///     .with_control(Control::default())
+///     .build()
+///     .unwrap();
+/// let frame = FrameBuilder::default()
+///     .with_content("AT+CMUX?".to_string())
+///     .build()
+///     .unwrap();
+/// assert_eq!(fixed.as_frame_ref(), frame.as_frame_ref());
+/// ```
+#[derive(Debug, Clone)]
+pub struct FixedFrameBuilder<const N: usize> {
+    address: Address,
+    control: Control,
+    content: Vec<u8, N>,
+    /// Length of the content set, if it does not fit.
+    overflow: Option<usize>,
+}
+
+impl<const N: usize> Default for FixedFrameBuilder<N> {
+    fn default() -> Self {
+        FixedFrameBuilder {
+            address: Address::default(),
+            control: Control::default(),
+            content: Vec::new(),
+            overflow: None,
+        }
+    }
+}
+
+impl<const N: usize> FixedFrameBuilder<N> {
+    /// Sets the address of the frame.
+    pub fn with_address(&mut self, address: Address) -> &mut Self {
+        self.address = address;
+        self
+    }
+
+    /// Sets the control of the frame.
+    pub fn with_control(&mut self, control: Control) -> &mut Self {
+        self.control = control;
+        self
+    }
+
+    /// Sets the content of the frame, appending `\r\n` if it is not present.
+    pub fn with_content(&mut self, content: &str) -> &mut Self {
+        let crlf: &[u8] = if content.ends_with("\r\n") {
+            b""
+        } else {
+            b"\r\n"
+        };
+        self.set_content(&[content.as_bytes(), crlf])
+    }
+
+    /// Sets text content of the frame as is, without appending `\r\n`.
+    pub fn with_raw_content(&mut self, content: &str) -> &mut Self {
+        self.set_content(&[content.as_bytes()])
+    }
+
+    /// Sets binary content of the frame, without appending `\r\n`.
+    pub fn with_binary_content(&mut self, content: &[u8]) -> &mut Self {
+        self.set_content(&[content])
+    }
+
+    /// Sets the content of the frame to the concatenation of `parts`, if it fits.
+    fn set_content(&mut self, parts: &[&[u8]]) -> &mut Self {
+        let len = parts.iter().map(|part| part.len()).sum();
+        self.content.clear();
+        self.overflow = None;
+        if len > N {
+            self.overflow = Some(len);
+        } else {
+            for part in parts {
+                self.content
+                    .extend_from_slice(part)
+                    .expect("the content was checked to fit");
+            }
+        }
+        self
+    }
+
+    /// Builds the frame.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(FixedFrame)`: The built frame.
+    /// - `Err(FrameError)`: If the content does not fit in `N` octets or is too long.
+    pub fn build(&self) -> Result<FixedFrame<N>, FrameError> {
+        if let Some(len) = self.overflow {
+            return Err(FrameError::ContentTooLong { len });
+        }
+        let length = Length::new(self.content.len())?.indicator();
+        Ok(FixedFrame {
+            header: FLAG,
+            address: self.address,
+            control: self.control,
+            length,
+            content: self.content.clone(),
+            checksum: frame_checksum(
+                self.address,
+                self.control,
+                length,
+                &self.content,
+                LengthEncoding::Spec,
+            ),
+            footer: FLAG,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FrameBuilder;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_matches_frame_builder() {
+        let fixed = FixedFrameBuilder::<8>::default()
+            .with_content("AT")
+            .build()
+            .unwrap();
+        let frame = FrameBuilder::default()
+            .with_content("AT".to_string())
+            .build()
+            .unwrap();
+        assert_eq!(fixed.as_frame_ref(), frame.as_frame_ref());
+        assert_eq!(fixed.verify(), Ok(()));
+        let mut buf = [0; FixedFrame::<8>::MAX_ENCODED_LEN];
+        let len = fixed.encode_into(&mut buf).unwrap();
+        assert_eq!(buf[..len], frame.to_bytes());
+    }
+
+    #[test]
+    fn test_capacity() {
+        // The appended CR LF must fit as well.
+        assert_eq!(
+            FixedFrameBuilder::<4>::default()
+                .with_content("ATE")
+                .build(),
+            Err(FrameError::ContentTooLong { len: 5 })
+        );
+        assert_eq!(
+            FixedFrameBuilder::<4>::default()
+                .with_binary_content(&[0; 5])
+                .build(),
+            Err(FrameError::ContentTooLong { len: 5 })
+        );
+        let frame = FixedFrameBuilder::<4>::default()
+            .with_content("AT")
+            .build()
+            .unwrap();
+        let mut buf = [0; FixedFrame::<4>::MAX_ENCODED_LEN];
+        let len = frame.encode_into(&mut buf).unwrap();
+        assert_eq!(FixedFrame::<4>::parse(&buf[..len]), Ok(frame));
+        assert_eq!(
+            FixedFrame::<3>::parse(&buf[..len]),
+            Err(FrameError::ContentTooLong { len: 4 })
+        );
+    }
+}
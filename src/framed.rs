@@ -0,0 +1,119 @@
+use crate::parser::FrameParser;
+use crate::types::Frame;
+use bytes::BytesMut;
+use std::collections::VecDeque;
+use std::io;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Tokio codec for GSM 07.10 [`Frame`]
+///
+/// Decoding goes through a [`FrameParser`], so corrupted bytes are skipped and the codec
+/// resynchronizes on the next flag. [`FrameCodec::advanced`] creates a codec for advanced
+/// option frames.
+///
+/// # Example
+///
+/// ```
+/// use bytes::BytesMut;
+/// use cmux::framed::FrameCodec;
+/// use cmux::types::FrameBuilder;
+/// use tokio_util::codec::{Decoder, Encoder};
+///
+/// let mut codec = FrameCodec::new();
+/// let mut buf = BytesMut::new();
+/// let frame = FrameBuilder::default().with_content("AT".to_string()).build().unwrap();
+/// codec.encode(&frame, &mut buf).unwrap();
+/// assert_eq!(codec.decode(&mut buf).unwrap(), Some(frame));
+/// ```
+#[derive(Debug, Default)]
+pub struct FrameCodec {
+    parser: FrameParser,
+    frames: VecDeque<Frame>,
+}
+
+impl FrameCodec {
+    /// Creates a codec for basic option frames.
+    pub fn new() -> Self {
+        FrameCodec::default()
+    }
+
+    /// Creates a codec for advanced option frames.
+    pub fn advanced() -> Self {
+        FrameCodec {
+            parser: FrameParser::advanced(),
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Returns the number of bytes dropped because they did not belong to a frame.
+    pub fn skipped(&self) -> usize {
+        self.parser.skipped()
+    }
+}
+
+impl Decoder for FrameCodec {
+    type Item = Frame;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Frame>, io::Error> {
+        if !src.is_empty() {
+            let data = src.split();
+            self.frames.extend(self.parser.feed(&data));
+        }
+        Ok(self.frames.pop_front())
+    }
+}
+
+impl Encoder<&Frame> for FrameCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, frame: &Frame, dst: &mut BytesMut) -> Result<(), io::Error> {
+        if self.parser.is_advanced() {
+            dst.extend_from_slice(&frame.to_bytes_advanced());
+        } else {
+            dst.extend_from_slice(&frame.to_bytes());
+        }
+        Ok(())
+    }
+}
+
+impl Encoder<Frame> for FrameCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, frame: Frame, dst: &mut BytesMut) -> Result<(), io::Error> {
+        self.encode(&frame, dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_chunks() {
+        let mut codec = FrameCodec::new();
+        let mut buf = BytesMut::from(&[0x00, 0xF9, 0x03, 0x3F][..]);
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+        assert!(buf.is_empty());
+
+        buf.extend_from_slice(&[0x01, 0x1C, 0xF9, 0x03, 0x3F, 0x01, 0x1C, 0xF9]);
+        let first = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(first.to_hex_string(), "f9033f011cf9");
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(first));
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+        assert_eq!(codec.skipped(), 1);
+    }
+
+    #[test]
+    fn test_advanced() {
+        let mut codec = FrameCodec::advanced();
+        let mut buf = BytesMut::new();
+        let frame = crate::types::FrameBuilder::default()
+            .with_binary_content(vec![0x7E, 0x01])
+            .build()
+            .unwrap();
+        codec.encode(&frame, &mut buf).unwrap();
+        assert_eq!(hex::encode(&buf), "7e07ef7d5e01057e");
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(frame));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::sniff::{Direction, SniffedFrame};
+use crate::text::{PayloadView, TextEncoding};
+use crate::types::Frame;
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+/// Quotes and escapes `value` as a JSON string.
+///
+/// # Example
+///
+/// ```
+/// use cmux::json::json_string;
+///
+/// assert_eq!(json_string("AT\r\""), r#""AT\r\"""#);
+/// ```
+pub fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// JSON record of a frame, on a single line
+///
+/// This is the record printed by `cmux parse` and `cmux sniff` with JSON output, and published
+/// by the MQTT bridge. Frames seen on a tap or in a log start with their `timestamp` and
+/// `direction`, and frames found in a stream of bytes with their `offset`.
+///
+/// # Example
+///
+/// ```
+/// use cmux::json::FrameRecord;
+/// use cmux::sniff::{Direction, SniffedFrame};
+/// use cmux::types::Frame;
+/// use std::time::Duration;
+///
+/// let sniffed = SniffedFrame {
+///     timestamp: Duration::from_millis(1500),
+///     direction: Direction::Tx,
+///     frame: Frame::from_bytes(vec![0xF9, 0x03, 0x3F, 0x01, 0x1C, 0xF9]).unwrap(),
+/// };
+/// let record = FrameRecord::sniffed(&sniffed).to_string();
+/// assert!(record.starts_with(r#"{"timestamp":1.500000,"direction":"TX","offset":null,"dlci":0,"#));
+/// assert!(record.ends_with(r#""checksum":28,"valid":true,"error":null}"#));
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameRecord<'a> {
+    frame: &'a Frame,
+    offset: Option<usize>,
+    /// Time and direction of the frame, written when it was seen on a tap or in a log.
+    seen: Option<(Option<Duration>, Option<Direction>)>,
+    error: Option<String>,
+    payload: Option<TextEncoding>,
+}
+
+impl<'a> FrameRecord<'a> {
+    /// Creates the record of `frame`, checked with [`Frame::verify`].
+    pub fn new(frame: &'a Frame) -> Self {
+        FrameRecord {
+            frame,
+            offset: None,
+            seen: None,
+            error: frame.verify().err().map(|e| e.to_string()),
+            payload: None,
+        }
+    }
+
+    /// Creates the record of a frame seen on a tap.
+    pub fn sniffed(sniffed: &'a SniffedFrame) -> Self {
+        Self::new(&sniffed.frame).with_seen(Some(sniffed.timestamp), Some(sniffed.direction))
+    }
+
+    /// Sets the position of the frame in the input, in octets.
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Sets the time and direction of the frame, written as `null` when they are unknown.
+    pub fn with_seen(mut self, timestamp: Option<Duration>, direction: Option<Direction>) -> Self {
+        self.seen = Some((timestamp, direction));
+        self
+    }
+
+    /// Replaces the result of [`Frame::verify`] with `verify`, such as the result of a
+    /// stricter check.
+    pub fn with_verify<E: Display>(mut self, verify: &Result<(), E>) -> Self {
+        self.error = verify.as_ref().err().map(|e| e.to_string());
+        self
+    }
+
+    /// Adds the content decoded with `encoding` as `payload`, and its `payload_kind` with
+    /// [`TextEncoding::Auto`].
+    pub fn with_payload(mut self, encoding: TextEncoding) -> Self {
+        self.payload = Some(encoding);
+        self
+    }
+}
+
+impl Display for FrameRecord<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frame = self.frame;
+        f.write_str("{")?;
+        if let Some((timestamp, direction)) = self.seen {
+            write!(
+                f,
+                r#""timestamp":{},"direction":{},"#,
+                timestamp.map_or("null".to_string(), |t| format!("{:.6}", t.as_secs_f64())),
+                direction.map_or("null".to_string(), |d| format!("\"{}\"", d.as_str()))
+            )?;
+        }
+        write!(
+            f,
+            r#""offset":{},"dlci":{},"cr":{},"frame_type":"{:?}","pf":{},"control":{},"length":{},"content":{{"text":{},"hex":"{}"}},"checksum":{},"valid":{},"error":{}"#,
+            self.offset
+                .map_or("null".to_string(), |offset| offset.to_string()),
+            frame.address.dlci().into_bits(),
+            frame.address.cr(),
+            frame.control.frame_type(),
+            frame.control.pf(),
+            frame.control.into_bits(),
+            frame.content.len(),
+            json_string(&frame.content.to_string_lossy()),
+            hex::encode_upper(frame.content.as_bytes()),
+            frame.checksum,
+            self.error.is_none(),
+            self.error
+                .as_deref()
+                .map_or("null".to_string(), json_string)
+        )?;
+        if let Some(encoding) = self.payload {
+            let (kind, text) = PayloadView::new(encoding).render_kind(frame);
+            write!(
+                f,
+                r#","payload_kind":{},"payload":{}"#,
+                kind.map_or("null".to_string(), |kind| format!("\"{}\"", kind.as_str())),
+                json_string(&text)
+            )?;
+        }
+        f.write_str("}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::generate;
+    use crate::types::{Address, Control};
+
+    #[test]
+    fn test_frame_record() {
+        let frame = generate(
+            Address::default(),
+            Control::default(),
+            Some("AT\r\n\"".to_string()),
+        )
+        .unwrap();
+        assert_eq!(
+            FrameRecord::new(&frame).with_offset(2).to_string(),
+            r#"{"offset":2,"dlci":1,"cr":true,"frame_type":"UIH","pf":false,"control":239,"length":7,"content":{"text":"AT\r\n\"\r\n","hex":"41540D0A220D0A"},"checksum":221,"valid":true,"error":null}"#
+        );
+        let record = FrameRecord::new(&frame).with_payload(TextEncoding::Auto);
+        assert!(record
+            .to_string()
+            .ends_with(r#""payload_kind":"at_text","payload":"AT\r\n\"\r\n"}"#));
+        let record = FrameRecord::new(&frame).with_payload(TextEncoding::Utf8);
+        assert!(record
+            .to_string()
+            .ends_with(r#""payload_kind":null,"payload":"AT\r\n\"\r\n"}"#));
+        let record = FrameRecord::new(&frame).with_seen(None, Some(Direction::Rx));
+        assert!(record
+            .to_string()
+            .starts_with(r#"{"timestamp":null,"direction":"RX","offset":null,"#));
+        let record = FrameRecord::new(&frame).with_verify(&Err("FCS mismatch"));
+        assert!(record
+            .to_string()
+            .ends_with(r#""valid":false,"error":"FCS mismatch"}"#));
+    }
+}
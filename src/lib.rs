@@ -1,2 +1,188 @@
+//! GSM 07.10 frame codec and multiplexer.
+//!
+//! Without the default `std` feature the crate is `no_std` and needs only `alloc`. The frame
+//! codec, the control messages, the session bookkeeping and the streaming parser remain
+//! available; the modules doing I/O or reading the clock require `std`.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+// Lets the code generated by `cmux_message` name this crate from its own tests.
+#[cfg(feature = "macros")]
+extern crate self as cmux;
+
+/// This module contains the errors raised by the frame codec.
+pub mod error;
+
 /// This module contains types and functions related to GSM 07.10 protocol.
 pub mod types;
+
+/// This module contains the advanced option framing with 0x7E flags and transparency.
+pub mod advanced;
+
+/// This module contains the multiplexer control messages carried on DLCI 0.
+pub mod control;
+
+/// This module contains the convergence layers carrying user data in the information field.
+pub mod convergence;
+
+/// This module tracks the DLCIs of a multiplexer session and generates its close-down sequence.
+pub mod session;
+
+/// This module contains a Tokio codec for reading and writing frames with `Framed`.
+#[cfg(feature = "async")]
+pub mod framed;
+
+/// This module reads and writes frames over the byte streams of the `futures` crate, for any
+/// async runtime.
+#[cfg(feature = "futures")]
+pub mod async_framed;
+
+/// This module orders the frames to transmit by DLCI priority.
+pub mod scheduler;
+
+/// This module contains the multiplexer engine driving the DLC state machines and timers.
+#[cfg(feature = "std")]
+pub mod mux;
+
+/// This module exposes the DLCs of a multiplexer as asynchronous byte streams.
+#[cfg(feature = "async")]
+pub mod channel;
+
+/// This module contains the byte pipes a multiplexer engine can be driven over.
+#[cfg(feature = "std")]
+pub mod transport;
+
+/// This module carries frames over the `embedded-io` traits of microcontroller UARTs.
+#[cfg(feature = "embedded-io")]
+pub mod embedded;
+
+/// This module opens serial ports and switches modems to multiplexer mode.
+#[cfg(feature = "serial")]
+pub mod serial;
+
+/// This module drives the asynchronous multiplexer over a serial port with tokio-serial.
+#[cfg(feature = "tokio-serial")]
+pub mod async_serial;
+
+/// This module creates pseudo-terminals exposing DLCs to other programs.
+#[cfg(all(unix, feature = "pty"))]
+pub mod pty;
+
+/// This module simulates a modem answering as the responding station of a session.
+#[cfg(feature = "std")]
+pub mod simulator;
+
+/// This module checks the engine against the simulator over an in-memory pipe.
+#[cfg(feature = "std")]
+pub mod selftest;
+
+/// This module exposes DLCs to other programs over sockets.
+#[cfg(feature = "std")]
+pub mod bridge;
+
+/// This module supervises multiplexer sessions described by a configuration file.
+#[cfg(feature = "daemon")]
+pub mod daemon;
+
+/// This module measures the round-trip time of Test commands.
+#[cfg(feature = "std")]
+pub mod ping;
+
+/// This module sends AT commands over a DLC and classifies their result codes.
+#[cfg(feature = "std")]
+pub mod at;
+
+/// This module contains the sequence number bookkeeping of error recovery mode.
+pub mod recovery;
+
+/// This module encodes Bluetooth RFCOMM frames and accounts for their credit-based flow control.
+pub mod rfcomm;
+
+/// This module describes the framing quirks of vendor multiplexer stacks.
+pub mod dialect;
+
+/// This module contains a streaming parser decoding frames from a byte stream.
+pub mod parser;
+
+/// This module converts hex text to frames and back, like the command-line tool.
+pub mod codec;
+
+/// This module reads the bytes captured by UART sniffers from pcap and pcapng files.
+#[cfg(feature = "std")]
+pub mod capture;
+
+/// This module decodes both directions of a tapped multiplexer link with timestamps.
+#[cfg(feature = "std")]
+pub mod sniff;
+
+/// This module publishes frames to an MQTT broker and takes the frames to transmit from it.
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+
+/// This module re-sends recorded frames with their original timing.
+#[cfg(feature = "std")]
+pub mod replay;
+
+/// This module extracts the bytes exchanged with a modem from the text logs of common tools.
+#[cfg(feature = "std")]
+pub mod logfile;
+
+/// This module gathers per-DLCI statistics of the frames of a session.
+#[cfg(feature = "std")]
+pub mod stats;
+
+/// This module finds the patterns of a failing link, such as a modem reset, with their causes.
+#[cfg(feature = "std")]
+pub mod anomaly;
+
+/// This module generates boundary-length frames for stress testing.
+pub mod stress;
+
+/// This module contains ready-made frame sequences, such as the startup of a session.
+pub mod templates;
+
+/// This module contains a typestate frame builder checked at compile time.
+pub mod typed;
+
+/// This module contains frames of fixed capacity, which are built and parsed without allocating.
+#[cfg(feature = "heapless")]
+pub mod fixed;
+
+/// This module decodes frame content as GSM 7-bit, UCS-2 or UTF-8 text.
+#[cfg(feature = "std")]
+pub mod text;
+
+/// This module builds the JSON records of frames printed by the CLI and published over MQTT.
+#[cfg(feature = "std")]
+pub mod json;
+
+/// This module breaks frames down field by field for people to read.
+pub mod pretty;
+
+/// This module implements serde for the protocol types. Byte strings are written as hex.
+#[cfg(feature = "serde")]
+pub mod serialization;
+
+/// This module contains frozen wire-format fixtures for downstream compatibility tests.
+#[cfg(any(test, feature = "compat-test"))]
+pub mod compat;
+
+/// This module generates structured random frames and control messages for fuzzing.
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+
+/// This module exports the frame codec and control messages to C.
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+/// This module exports the frame codec to JavaScript through wasm-bindgen.
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+/// This module exports the frame codec to Kotlin/Swift through UniFFI.
+#[cfg(feature = "uniffi")]
+pub mod bindings;
+
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
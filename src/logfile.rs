@@ -0,0 +1,452 @@
+use crate::dialect::Dialect;
+use crate::parser::FrameParser;
+use crate::sniff::Direction;
+use crate::types::Frame;
+use std::time::Duration;
+
+/// Bytes logged on one line of a log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+    /// Time the bytes were logged, if the line or a previous header records it.
+    pub timestamp: Option<Duration>,
+    /// Direction of the bytes, if the log records it.
+    pub direction: Option<Direction>,
+    pub data: Vec<u8>,
+}
+
+/// Reader extracting the bytes exchanged with a modem from the lines of a text log
+///
+/// Readers may keep state between lines, for logs whose headers hold the timestamp of the
+/// following lines.
+pub trait LogReader {
+    /// Reads one line of the log, returning `None` if it carries no bytes.
+    fn read_line(&mut self, line: &str) -> Option<LogRecord>;
+
+    /// Reads every line of `text`.
+    fn read(&mut self, text: &str) -> Vec<LogRecord> {
+        text.lines()
+            .filter_map(|line| self.read_line(line))
+            .collect()
+    }
+}
+
+/// Log formats with a [`LogReader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Hex display of minicom, optionally with line timestamps.
+    Minicom,
+    /// Debug log of ModemManager.
+    ModemManager,
+    /// Hex dumps of QXDM text exports and of vendor tools.
+    HexLog,
+}
+
+impl LogFormat {
+    /// Returns a reader of this format.
+    pub fn reader(self) -> Box<dyn LogReader> {
+        match self {
+            LogFormat::Minicom => Box::new(MinicomReader),
+            LogFormat::ModemManager => Box::new(ModemManagerReader),
+            LogFormat::HexLog => Box::new(HexLogReader::new()),
+        }
+    }
+}
+
+/// Reader of the debug log of ModemManager
+///
+/// The serial ports of ModemManager log the bytes written with `-->` and the bytes read with
+/// `<--`, quoted, with CR and LF as `<CR>` and `<LF>` and the other unprintable bytes as a
+/// backslash followed by their decimal value. The timestamp is the first bracketed number of
+/// seconds with a fraction, which skips the process ID of syslog lines.
+///
+/// # Example
+///
+/// ```
+/// use cmux::logfile::{LogReader, ModemManagerReader};
+/// use cmux::sniff::Direction;
+/// use std::time::Duration;
+///
+/// let line = "<debug> [1686000000.250000] [ttyUSB2] <-- '<CR><LF>OK<CR><LF>\\249'";
+/// let record = ModemManagerReader.read_line(line).unwrap();
+/// assert_eq!(record.timestamp, Some(Duration::from_millis(1686000000250)));
+/// assert_eq!(record.direction, Some(Direction::Rx));
+/// assert_eq!(record.data, b"\r\nOK\r\n\xF9");
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ModemManagerReader;
+
+impl ModemManagerReader {
+    /// Decodes the escapes of a quoted string of the log.
+    fn unescape(text: &str) -> Vec<u8> {
+        let mut data = Vec::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(c) = rest.chars().next() {
+            if let Some(after) = rest.strip_prefix("<CR>") {
+                data.push(b'\r');
+                rest = after;
+                continue;
+            }
+            if let Some(after) = rest.strip_prefix("<LF>") {
+                data.push(b'\n');
+                rest = after;
+                continue;
+            }
+            if let Some(after) = rest.strip_prefix('\\') {
+                let digits = after.bytes().take(3).take_while(u8::is_ascii_digit).count();
+                if let Ok(value) = after[..digits].parse::<u8>() {
+                    data.push(value);
+                    rest = &after[digits..];
+                    continue;
+                }
+            }
+            let mut buf = [0; 4];
+            data.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            rest = &rest[c.len_utf8()..];
+        }
+        data
+    }
+
+    /// Returns the first bracketed number of seconds with a fraction of `line`.
+    fn timestamp(line: &str) -> Option<Duration> {
+        line.split('[')
+            .skip(1)
+            .filter_map(|part| part.split_once(']'))
+            .map(|(inside, _)| inside.trim())
+            .find(|inside| {
+                inside.contains('.') && inside.chars().all(|c| c.is_ascii_digit() || c == '.')
+            })
+            .and_then(|seconds| seconds.parse::<f64>().ok())
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+    }
+}
+
+impl LogReader for ModemManagerReader {
+    fn read_line(&mut self, line: &str) -> Option<LogRecord> {
+        let (head, direction, quoted) = [("--> '", Direction::Tx), ("<-- '", Direction::Rx)]
+            .into_iter()
+            .find_map(|(arrow, direction)| {
+                let (head, quoted) = line.split_once(arrow)?;
+                Some((head, direction, quoted))
+            })?;
+        let text = quoted.rsplit_once('\'').map_or(quoted, |(text, _)| text);
+        Some(LogRecord {
+            timestamp: ModemManagerReader::timestamp(head),
+            direction: Some(direction),
+            data: ModemManagerReader::unescape(text),
+        })
+    }
+}
+
+/// Reader of the hex display of minicom
+///
+/// Each line holds the received bytes as hex digit pairs, optionally after a
+/// `[YYYY-MM-DD HH:MM:SS.mmm]` timestamp, taken as UTC. minicom only displays the bytes
+/// received from the modem.
+///
+/// # Example
+///
+/// ```
+/// use cmux::logfile::{LogReader, MinicomReader};
+///
+/// let record = MinicomReader.read_line("[2024-01-10 12:00:00.500] f9 03 73 01 d7 f9").unwrap();
+/// assert_eq!(record.timestamp.unwrap().as_millis(), 1704888000500);
+/// assert_eq!(record.data, [0xF9, 0x03, 0x73, 0x01, 0xD7, 0xF9]);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MinicomReader;
+
+impl LogReader for MinicomReader {
+    fn read_line(&mut self, line: &str) -> Option<LogRecord> {
+        let line = line.trim();
+        let (timestamp, bytes) = match line.strip_prefix('[') {
+            Some(rest) => {
+                let (stamp, bytes) = rest.split_once(']')?;
+                let (date, time) = stamp.trim().split_once(' ')?;
+                let days = parse_date(date)?;
+                let time = parse_time(time.trim())?;
+                (Some(Duration::from_secs(days * 86400) + time), bytes)
+            }
+            None => (None, line),
+        };
+        let data = bytes
+            .split_whitespace()
+            .map(parse_byte)
+            .collect::<Option<Vec<u8>>>()?;
+        if data.is_empty() {
+            return None;
+        }
+        Some(LogRecord {
+            timestamp,
+            direction: Some(Direction::Rx),
+            data,
+        })
+    }
+}
+
+/// Reader of hex dumps, such as the text exports of QXDM
+///
+/// A line carries bytes if it is made of hex digit pairs, optionally preceded by a date, a
+/// `HH:MM:SS.fff` time of day, a direction among `TX`, `RX`, `>>` and `<<`, and an offset
+/// ending with a colon, and optionally followed by an ASCII column. Other lines are headers:
+/// their time and direction apply to the following lines, as in QXDM exports where the
+/// payload is dumped below the header of the log packet.
+///
+/// Timestamps are times of day, as hex dumps rarely record the date.
+///
+/// # Example
+///
+/// ```
+/// use cmux::logfile::{HexLogReader, LogReader};
+/// use cmux::sniff::Direction;
+///
+/// let log = "2024 Jan 10  12:00:01.250  [9C]  0x1544  UART RX\n\
+///            0000: F9 03 73 01 D7 F9  ..s...\n";
+/// let records = HexLogReader::new().read(log);
+/// assert_eq!(records.len(), 1);
+/// assert_eq!(records[0].timestamp.unwrap().as_millis(), 43201250);
+/// assert_eq!(records[0].direction, Some(Direction::Rx));
+/// assert_eq!(records[0].data, [0xF9, 0x03, 0x73, 0x01, 0xD7, 0xF9]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HexLogReader {
+    timestamp: Option<Duration>,
+    direction: Option<Direction>,
+}
+
+impl HexLogReader {
+    /// Creates a reader with no time nor direction.
+    pub fn new() -> Self {
+        HexLogReader::default()
+    }
+
+    /// Returns the direction named by `token`.
+    fn direction(token: &str) -> Option<Direction> {
+        let token = token.trim_matches(|c: char| matches!(c, '[' | ']' | '(' | ')' | ':'));
+        match token.to_ascii_uppercase().as_str() {
+            "TX" | ">>" => Some(Direction::Tx),
+            "RX" | "<<" => Some(Direction::Rx),
+            _ => None,
+        }
+    }
+}
+
+impl LogReader for HexLogReader {
+    fn read_line(&mut self, line: &str) -> Option<LogRecord> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let prefix = tokens
+            .iter()
+            .take_while(|token| {
+                parse_time(token).is_some()
+                    || HexLogReader::direction(token).is_some()
+                    || parse_date(token).is_some()
+                    || token
+                        .strip_suffix(':')
+                        .is_some_and(|offset| u32::from_str_radix(offset, 16).is_ok())
+            })
+            .count();
+        // The bytes end at the ASCII column, if any.
+        let data: Vec<u8> = tokens[prefix..]
+            .iter()
+            .map_while(|t| parse_byte(t))
+            .collect();
+        let context = if data.is_empty() {
+            &tokens[..]
+        } else {
+            &tokens[..prefix]
+        };
+        for token in context {
+            if let Some(time) = parse_time(token) {
+                self.timestamp = Some(time);
+            } else if let Some(direction) = HexLogReader::direction(token) {
+                self.direction = Some(direction);
+            }
+        }
+        if data.is_empty() {
+            return None;
+        }
+        Some(LogRecord {
+            timestamp: self.timestamp,
+            direction: self.direction,
+            data,
+        })
+    }
+}
+
+/// Parses a byte written as two hex digits, optionally prefixed with `0x`.
+fn parse_byte(token: &str) -> Option<u8> {
+    let digits = token.strip_prefix("0x").unwrap_or(token);
+    if digits.len() != 2 {
+        return None;
+    }
+    u8::from_str_radix(digits, 16).ok()
+}
+
+/// Parses a `HH:MM:SS` time of day with optional fractional seconds.
+fn parse_time(token: &str) -> Option<Duration> {
+    let mut fields = token.splitn(3, ':');
+    let hours: u64 = fields.next()?.parse().ok()?;
+    let minutes: u64 = fields.next()?.parse().ok()?;
+    let seconds = fields.next()?;
+    if hours > 23 || minutes > 59 || !seconds.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let seconds: f64 = seconds.parse().ok()?;
+    if !(0.0..61.0).contains(&seconds) {
+        return None;
+    }
+    Some(Duration::from_secs(hours * 3600 + minutes * 60) + Duration::from_secs_f64(seconds))
+}
+
+/// Parses a `YYYY-MM-DD` or `YYYY/MM/DD` date into days since the Unix epoch.
+fn parse_date(token: &str) -> Option<u64> {
+    let mut fields = token.split(['-', '/']);
+    let year: i64 = fields.next()?.parse().ok()?;
+    let month: i64 = fields.next()?.parse().ok()?;
+    let day: i64 = fields.next()?.parse().ok()?;
+    if fields.next().is_some() || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // Days from civil of the proleptic Gregorian calendar, with years starting in March.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    u64::try_from(era * 146097 + day_of_era - 719468).ok()
+}
+
+/// Frame decoded from a log by a [`LogDecoder`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggedFrame {
+    /// Timestamp of the record completing the frame.
+    pub timestamp: Option<Duration>,
+    pub direction: Option<Direction>,
+    pub frame: Frame,
+}
+
+/// Decoder of the frames of the records of a log
+///
+/// Like [`Sniffer`](crate::sniff::Sniffer), each direction has its own [`FrameParser`], so
+/// frames split across records interleaved with the other direction are reassembled.
+///
+/// # Example
+///
+/// ```
+/// use cmux::logfile::{LogDecoder, LogFormat};
+///
+/// let log = "[1.0] [ttyUSB2] --> '\\249\\3?\\1'\n\
+///            [1.1] [ttyUSB2] <-- '\\249\\3s\\1\\215\\249'\n\
+///            [1.2] [ttyUSB2] --> '\\28\\249'\n";
+/// let mut decoder = LogDecoder::new();
+/// let frames: Vec<_> = LogFormat::ModemManager
+///     .reader()
+///     .read(log)
+///     .iter()
+///     .flat_map(|record| decoder.feed(record))
+///     .collect();
+/// assert_eq!(frames[0].frame.to_hex_string(), "f9037301d7f9");
+/// assert_eq!(frames[1].frame.to_hex_string(), "f9033f011cf9");
+/// ```
+#[derive(Debug)]
+pub struct LogDecoder {
+    /// The parsers of records without direction, of TX and of RX.
+    parsers: [FrameParser; 3],
+}
+
+impl Default for LogDecoder {
+    fn default() -> Self {
+        LogDecoder::new()
+    }
+}
+
+impl LogDecoder {
+    /// Creates a decoder of basic option frames.
+    pub fn new() -> Self {
+        LogDecoder {
+            parsers: [FrameParser::new(), FrameParser::new(), FrameParser::new()],
+        }
+    }
+
+    /// Creates a decoder of advanced option frames.
+    pub fn advanced() -> Self {
+        LogDecoder {
+            parsers: [
+                FrameParser::advanced(),
+                FrameParser::advanced(),
+                FrameParser::advanced(),
+            ],
+        }
+    }
+
+    /// Sets the framing of basic option frames, like [`FrameParser::with_dialect`].
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.parsers = self.parsers.map(|parser| parser.with_dialect(dialect));
+        self
+    }
+
+    fn index(direction: Option<Direction>) -> usize {
+        match direction {
+            None => 0,
+            Some(Direction::Tx) => 1,
+            Some(Direction::Rx) => 2,
+        }
+    }
+
+    /// Feeds the bytes of a record to the parser of its direction.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<LoggedFrame>` of every frame completed by this record, in stream order.
+    pub fn feed(&mut self, record: &LogRecord) -> Vec<LoggedFrame> {
+        self.parsers[LogDecoder::index(record.direction)]
+            .feed(&record.data)
+            .into_iter()
+            .map(|frame| LoggedFrame {
+                timestamp: record.timestamp,
+                direction: record.direction,
+                frame,
+            })
+            .collect()
+    }
+
+    /// Returns the number of bytes dropped because they did not belong to a frame.
+    pub fn skipped(&self) -> usize {
+        self.parsers.iter().map(FrameParser::skipped).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modemmanager_escapes() {
+        let mut reader = ModemManagerReader;
+        let line = "ModemManager[884]: <debug> [1700000000.5] [modem0/ttyUSB2] --> 'AT+CMUX=0<CR>'";
+        let record = reader.read_line(line).unwrap();
+        assert_eq!(record.direction, Some(Direction::Tx));
+        assert_eq!(record.timestamp, Some(Duration::from_millis(1700000000500)));
+        assert_eq!(record.data, b"AT+CMUX=0\r");
+        // A quote inside the data and a backslash not followed by digits are kept.
+        let record = reader.read_line("<-- 'it's \\x'").unwrap();
+        assert_eq!(record.timestamp, None);
+        assert_eq!(record.data, b"it's \\x");
+        assert_eq!(reader.read_line("<info> modem state changed"), None);
+    }
+
+    #[test]
+    fn test_hexlog_headers() {
+        let log = "12:00:00.000 TX: F9 03 3F 01 1C F9\n\
+                   Length: 12\n\
+                   12:00:00.020 << F9 03 73\n\
+                   01 D7 F9\n";
+        let records = HexLogReader::new().read(log);
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].direction, Some(Direction::Tx));
+        assert_eq!(records[1].data, [0xF9, 0x03, 0x73]);
+        assert_eq!(records[2].direction, Some(Direction::Rx));
+        assert_eq!(records[2].timestamp, Some(Duration::from_millis(43200020)));
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2000/03/01"), Some(11017));
+    }
+}
@@ -1,176 +1,2928 @@
-use clap::{Args, Parser, Subcommand};
-use cmux::types::{Address, Control, Frame, FrameBuilder};
+use clap::builder::PossibleValuesParser;
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use cmux::advanced::ADVANCED_FLAG;
+use cmux::capture::{self, PacketReader};
+use cmux::codec::{self, generate, HexDecoder};
+use cmux::control::{
+    ControlMessage, MessageType, Msc, Nsc, ParameterNegotiation, RemotePortNegotiation,
+};
+use cmux::dialect::{Dialect, FcsCoverage};
+use cmux::error::{FrameError, ParseError};
+use cmux::json::{json_string, FrameRecord};
+use cmux::logfile::{LogDecoder, LogFormat};
+use cmux::parser::{Detection, FrameParser, Scanned};
+#[cfg(feature = "serial")]
+use cmux::ping;
+use cmux::pretty::{self, Annotation, Field, Pretty};
+use cmux::session::Session;
+use cmux::stats::Stats;
+use cmux::stress;
+use cmux::templates::{self, Template};
+use cmux::text::{self, TextEncoding};
+use cmux::types::{Address, Control, Frame, FrameBuilder, FrameType, DLCI, FLAG};
+use std::cell::Cell;
+use std::fmt::Display;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// Every frame decoded or generated was valid.
+const EXIT_OK: u8 = 0;
+/// At least one frame failed length or checksum verification.
+const EXIT_INVALID_FRAMES: u8 = 1;
+/// The command line, or a line of `generate --from-file`, is invalid.
+const EXIT_INVALID_ARGS: u8 = 2;
+/// The input did not contain any frame.
+const EXIT_NO_FRAMES: u8 = 3;
+/// The summary file could not be written.
+const EXIT_SUMMARY_FAILED: u8 = 4;
+/// An input, output or device could not be opened, read or written.
+const EXIT_IO_FAILED: u8 = 5;
+
+const EXIT_CODES_HELP: &str = "Exit codes:
+  0  every frame is valid
+  1  at least one frame failed verification
+  2  invalid command line
+  3  no frame found in the input
+  4  the summary file could not be written
+  5  an input, output or device could not be opened, read or written";
 
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, after_help = EXIT_CODES_HELP)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Print a JSON summary of the run to stderr
+    #[arg(long, global = true)]
+    summary: bool,
+    /// Write a JSON summary of the run to the given file
+    #[arg(long, global = true, value_name = "PATH")]
+    summary_file: Option<PathBuf>,
+    /// Framing of the frames read and written
+    #[arg(long, global = true, value_enum, default_value = "basic")]
+    mode: FrameMode,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum FrameMode {
+    /// 0xF9 flags with a length indicator
+    Basic,
+    /// 0x7E flags with control-octet transparency
+    Advanced,
+}
+
+impl FrameMode {
+    fn encode(self, frame: &Frame) -> String {
+        self.encode_in(frame, &Dialect::SPEC)
+    }
+
+    /// Encodes `frame`, framing basic option frames in `dialect`.
+    fn encode_in(self, frame: &Frame, dialect: &Dialect) -> String {
+        let bytes = match self {
+            FrameMode::Basic => dialect.encode(frame),
+            FrameMode::Advanced => frame.to_bytes_advanced(),
+        };
+        hex::encode_upper(bytes)
+    }
+
+    /// Returns the field by field breakdown of `frame` in this framing.
+    fn pretty(self, frame: &Frame) -> Pretty<'_> {
+        match self {
+            FrameMode::Basic => frame.pretty(),
+            FrameMode::Advanced => frame.pretty().advanced(),
+        }
+    }
+
+    /// Creates the parser of a stream of frames, reporting the regions skipped between them.
+    /// Basic option frames are framed in `dialect`.
+    fn parser(self, dialect: &Dialect) -> FrameParser {
+        match self {
+            FrameMode::Basic => FrameParser::new().with_dialect(*dialect),
+            FrameMode::Advanced => FrameParser::advanced(),
+        }
+    }
+}
+
+/// Framing quirks of the basic option frames read, for traces of vendor stacks
+#[derive(Args)]
+struct DialectArgs {
+    /// Profile of the basic option framing, adjusted by the options below
+    #[arg(long, value_enum, default_value = "spec")]
+    dialect: DialectProfile,
+    /// Flag octet delimiting basic option frames, instead of F9
+    #[arg(long, value_parser = parse_hexbyte)]
+    flag: Option<u8>,
+    /// The first basic option frame has no opening flag
+    #[arg(long)]
+    no_leading_flag: bool,
+    /// Fields covered by the FCS of basic option frames
+    #[arg(long, value_enum)]
+    fcs_coverage: Option<DialectFcsCoverage>,
+}
+
+impl DialectArgs {
+    fn dialect(&self) -> Dialect {
+        let mut dialect = match self.dialect {
+            DialectProfile::Spec => Dialect::SPEC,
+            DialectProfile::Legacy => Dialect::LEGACY,
+        };
+        if let Some(flag) = self.flag {
+            dialect = dialect.with_flag(flag);
+        }
+        if self.no_leading_flag {
+            dialect = dialect.with_leading_flag(false);
+        }
+        if let Some(coverage) = self.fcs_coverage {
+            dialect = dialect.with_fcs_coverage(coverage.into());
+        }
+        dialect
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DialectProfile {
+    /// As 07.10 specifies
+    Spec,
+    /// Two-octet length indicators swapped, as written by cmux 0.2 and earlier
+    Legacy,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DialectFcsCoverage {
+    /// Address, control and length fields, and the content of UI frames
+    Spec,
+    /// Address, control and length fields only
+    Header,
+    /// Address, control and length fields and the content of every frame
+    All,
+}
+
+impl From<DialectFcsCoverage> for FcsCoverage {
+    fn from(value: DialectFcsCoverage) -> Self {
+        match value {
+            DialectFcsCoverage::Spec => FcsCoverage::Spec,
+            DialectFcsCoverage::Header => FcsCoverage::Header,
+            DialectFcsCoverage::All => FcsCoverage::All,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Generate GSM 07.10 Frame by given address, control and content field
+    #[command(visible_alias = "g")]
+    Generate(GenerateArgs),
+    /// Parse a byte array to GSM 07.10 Frame
+    #[command(visible_alias = "p")]
+    Parse(ParseArgs),
+    /// Check the length and FCS of every frame, exiting with 0 only if all of them are valid
+    Verify(VerifyArgs),
+    /// Generate frames for exercising peers and parsers
+    Fuzz(FuzzArgs),
+    /// Generate the frames closing down a multiplexer session
+    Closedown(ClosedownArgs),
+    /// List or print ready-made frame sequences, such as the startup of a session
+    Template(TemplateArgs),
+    /// Print every octet of a basic option frame with its field and bits
+    Explain(ExplainArgs),
+    /// Send Test commands over a serial port in CMUX mode and measure the round-trip time
+    #[cfg(feature = "serial")]
+    Ping(PingArgs),
+    /// Switch a modem to CMUX mode, open channels and print the data received
+    #[cfg(feature = "serial")]
+    Attach(AttachArgs),
+    /// Send an AT command on a channel of a modem in CMUX mode and print its response
+    #[cfg(feature = "serial")]
+    At(AtArgs),
+    /// Passively decode the frames read from one or two serial taps of a multiplexer link
+    #[cfg(feature = "serial")]
+    Sniff(SniffArgs),
+    /// Re-send the frames of a JSON recording to a serial port, keeping the delays between them
+    #[cfg(feature = "serial")]
+    Replay(ReplayArgs),
+    /// Supervise the multiplexer sessions described by a TOML file, logging events as JSON lines
+    #[cfg(feature = "daemon")]
+    Daemon(DaemonArgs),
+    /// Act as a modem in CMUX mode, answering the host on a pseudo-terminal or a TCP socket
+    Simulate(SimulateArgs),
+    /// Run the engine against the simulator in memory, checking random data arrives intact
+    Selftest(SelftestArgs),
+}
+
+#[derive(Args)]
+struct GenerateArgs {
+    #[command(flatten)]
+    frame: FrameArgs,
+    /// Generate one frame per line of the given file: the options above, or the address, control
+    /// and content columns of a .csv file
+    #[arg(long, value_name = "PATH", conflicts_with = "content")]
+    from_file: Option<PathBuf>,
+    /// Write the frames of --from-file to the given file as binary instead of printing them as
+    /// a hex stream
+    #[arg(long, value_name = "PATH", requires = "from_file")]
+    output_file: Option<PathBuf>,
+    /// Let the closing flag of each frame of --from-file open the next one, as 07.10 allows
+    #[arg(long, requires = "from_file")]
+    shared_flags: bool,
+}
+
+/// A line of `generate --from-file`
+#[derive(Parser)]
+#[command(no_binary_name = true)]
+struct GenerateLine {
+    #[command(flatten)]
+    frame: FrameArgs,
+}
+
+#[derive(Args)]
+struct FrameArgs {
+    /// address field
+    #[arg(short, long, default_value = "7", value_parser = parse_hexbyte)]
+    address: u8,
+    /// control field
+    #[arg(short, long, default_value = "EF", value_parser = parse_hexbyte)]
+    control: u8,
+    /// DLCI of the address field, replacing the one of --address
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..64))]
+    dlci: Option<u8>,
+    /// Whether the frame is a command or a response, setting the C/R bit of --address
+    #[arg(long, value_enum)]
+    cr: Option<CommandResponse>,
+    /// The frame is sent by the responding station, which inverts the C/R bit of --cr
+    #[arg(long, requires = "cr")]
+    responder: bool,
+    /// Frame type of the control field, replacing the one of --control
+    #[arg(long = "type", value_name = "TYPE", value_enum, ignore_case = true)]
+    frame_type: Option<GenerateFrameType>,
+    /// Set the P/F bit of the control field
+    #[arg(long)]
+    pf: bool,
+    /// Frame the content as is, without appending CR LF
+    #[arg(long, requires = "content")]
+    raw: bool,
+    /// content field, omitted for frames without information field such as SABM or DISC
+    content: Option<String>,
+}
+
+impl FrameArgs {
+    /// Returns the address field: --address with --dlci and --cr applied.
+    fn address(&self) -> Address {
+        let mut address = Address::from_bits(self.address);
+        if let Some(dlci) = self.dlci {
+            address = address.with_dlci(DLCI::from_bits(dlci));
+        }
+        if let Some(cr) = self.cr {
+            address = address.with_cr((cr == CommandResponse::Command) != self.responder);
+        }
+        address
+    }
+
+    /// Returns the control field: --control with --type and --pf applied.
+    fn control(&self) -> Control {
+        let mut control = Control::from_bits(self.control);
+        if let Some(frame_type) = self.frame_type {
+            control = control.with_frame_type(frame_type.into());
+        }
+        if self.pf {
+            control = control.with_pf(true);
+        }
+        control
+    }
+
+    /// Builds the frame, appending CR LF to the content unless --raw is given.
+    fn build(&self) -> Result<Frame, FrameError> {
+        match &self.content {
+            Some(content) if self.raw => FrameBuilder::default()
+                .with_address(self.address())
+                .with_control(self.control())
+                .with_raw_content(content.clone())
+                .build(),
+            content => generate(self.address(), self.control(), content.clone()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CommandResponse {
+    Command,
+    Response,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GenerateFrameType {
+    /// Set Asynchronous Balanced Mode
+    Sabm,
+    /// Unnumbered Acknowledgement
+    Ua,
+    /// Disconnected Mode
+    Dm,
+    /// Disconnect
+    Disc,
+    /// Unnumbered Information with Header check
+    Uih,
+    /// Unnumbered Information
+    Ui,
+    /// Information frame of error recovery mode
+    I,
+    /// Receive Ready
+    Rr,
+    /// Receive Not Ready
+    Rnr,
+    /// Reject
+    Rej,
+}
+
+impl From<GenerateFrameType> for FrameType {
+    fn from(value: GenerateFrameType) -> Self {
+        match value {
+            GenerateFrameType::Sabm => FrameType::SABM,
+            GenerateFrameType::Ua => FrameType::UA,
+            GenerateFrameType::Dm => FrameType::DM,
+            GenerateFrameType::Disc => FrameType::DISC,
+            GenerateFrameType::Uih => FrameType::UIH,
+            GenerateFrameType::Ui => FrameType::UI,
+            GenerateFrameType::I => FrameType::I,
+            GenerateFrameType::Rr => FrameType::RR,
+            GenerateFrameType::Rnr => FrameType::RNR,
+            GenerateFrameType::Rej => FrameType::REJ,
+        }
+    }
+}
+
+#[derive(Args)]
+struct ParseArgs {
+    /// Bytes array like string, or "-" to read stdin. Stdin is read when omitted. Example: "F9010203F9 F9010203F9"
+    #[arg(conflicts_with = "file")]
+    hexstring: Option<String>,
+    /// Read the bytes from a file, as hex text, raw binary, or the packets of a pcap or pcapng capture
+    #[arg(short, long, value_name = "PATH")]
+    file: Option<PathBuf>,
+    /// Format of the parsed frames
+    #[arg(short, long, value_enum, default_value = "text")]
+    output: OutputFormat,
+    /// Also print the content decoded as text, as the payload of the JSON records
+    #[arg(long, value_enum)]
+    payload: Option<PayloadEncoding>,
+    /// Format of the input. With a log format, HEXSTRING is the path of the log, and the frames
+    /// are printed with the time and direction they were logged with
+    #[arg(long, value_enum, default_value = "bytes")]
+    format: InputFormat,
+    /// Print per-DLCI statistics, a timeline of the connections and the anomalies of the link
+    /// after the frames, to stderr with JSON output
+    #[arg(long)]
+    stats: bool,
+    /// Rewrite the FCS of every basic option frame before printing it, for example to replay
+    /// frames edited by hand
+    #[arg(long)]
+    fix_fcs: bool,
+    /// Detect whether the bytes hold basic or advanced option frames and decode them
+    /// accordingly, instead of using --mode
+    #[arg(long)]
+    detect_mode: bool,
+    /// Do not show the progress on stderr, shown when it is a terminal and stdout is not
+    #[arg(long)]
+    no_progress: bool,
+    #[command(flatten)]
+    dialect: DialectArgs,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum InputFormat {
+    /// Hex text, raw binary, or a pcap or pcapng capture
+    Bytes,
+    /// Hex display of minicom, optionally with timestamps
+    Minicom,
+    /// Debug log of ModemManager
+    #[value(name = "modemmanager")]
+    ModemManager,
+    /// Hex dumps of QXDM text exports and vendor tools
+    Hexlog,
+}
+
+impl InputFormat {
+    fn log_format(self) -> Option<LogFormat> {
+        match self {
+            InputFormat::Bytes => None,
+            InputFormat::Minicom => Some(LogFormat::Minicom),
+            InputFormat::ModemManager => Some(LogFormat::ModemManager),
+            InputFormat::Hexlog => Some(LogFormat::HexLog),
+        }
+    }
+}
+
+#[derive(Args)]
+struct VerifyArgs {
+    /// Bytes array like string, or "-" to read stdin. Stdin is read when omitted. Example: "F9010203F9 F9010203F9"
+    #[arg(conflicts_with = "file")]
+    hexstring: Option<String>,
+    /// Read the bytes from a file, as hex text, raw binary, or the packets of a pcap or pcapng capture
+    #[arg(short, long, value_name = "PATH")]
+    file: Option<PathBuf>,
+    /// Print nothing, only set the exit code
+    #[arg(short, long)]
+    quiet: bool,
+    /// Do not show the progress on stderr, shown when it is a terminal
+    #[arg(long)]
+    no_progress: bool,
+    #[command(flatten)]
+    dialect: DialectArgs,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Frame fields, one per line
+    Text,
+    /// One JSON array of frame records
+    Json,
+    /// One JSON frame record per line
+    Jsonl,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PayloadEncoding {
+    /// Detect the payload kind per frame
+    Auto,
+    Utf8,
+    /// GSM 7-bit packed, raw or as hex digits
+    Gsm7,
+    /// UCS-2, raw or as hex digits
+    Ucs2,
+}
+
+impl From<PayloadEncoding> for TextEncoding {
+    fn from(value: PayloadEncoding) -> Self {
+        match value {
+            PayloadEncoding::Auto => TextEncoding::Auto,
+            PayloadEncoding::Utf8 => TextEncoding::Utf8,
+            PayloadEncoding::Gsm7 => TextEncoding::Gsm7,
+            PayloadEncoding::Ucs2 => TextEncoding::Ucs2,
+        }
+    }
+}
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("kind").required(true).args(["stress_long"])))]
+struct FuzzArgs {
+    /// Generate frames with boundary and maximum content lengths
+    #[arg(long)]
+    stress_long: bool,
+    /// address field
+    #[arg(short, long, default_value = "7", value_parser = parse_hexbyte)]
+    address: u8,
+    /// control field
+    #[arg(short, long, default_value = "EF", value_parser = parse_hexbyte)]
+    control: u8,
+}
+
+#[derive(Args)]
+struct ClosedownArgs {
+    /// Open DLCIs to disconnect before closing down. Example: "1,2,3"
+    #[arg(short, long, value_delimiter = ',')]
+    dlci: Vec<u8>,
+    /// Generate commands for the responding station
+    #[arg(long)]
+    responder: bool,
+}
+
+#[derive(Args)]
+struct TemplateArgs {
+    #[command(subcommand)]
+    command: TemplateCommand,
+}
+
+#[derive(Subcommand)]
+enum TemplateCommand {
+    /// List the templates with their description
+    List,
+    /// Print the frames of a template, one per line, after its wake-up flags if any
+    Show {
+        /// Name of the template
+        #[arg(value_parser = PossibleValuesParser::new(templates::TEMPLATES.iter().map(|t| t.name)))]
+        name: String,
+    },
+}
+
+#[derive(Args)]
+struct ExplainArgs {
+    /// Bytes array like string of a single frame. Example: "F9 07 EF 05 41 54 30 F9"
+    hexstring: String,
+    /// When to color the fields
+    #[arg(long, value_enum, default_value = "auto")]
+    color: ColorChoice,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ColorChoice {
+    /// Color when stdout is a terminal and NO_COLOR is not set
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    fn enabled(self) -> bool {
+        use std::io::IsTerminal;
+
+        match self {
+            ColorChoice::Auto => {
+                std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+            }
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+#[cfg(feature = "serial")]
+#[derive(Args)]
+struct PingArgs {
+    /// Serial port the multiplexer runs on. Example: /dev/ttyUSB0
+    port: String,
+    /// Baud rate of the serial port
+    #[arg(short, long, default_value = "115200")]
+    baud: u32,
+    /// Number of Test commands to send
+    #[arg(short = 'n', long, default_value = "4")]
+    count: u32,
+    /// Milliseconds between Test commands
+    #[arg(short, long, default_value = "1000")]
+    interval: u64,
+    /// Milliseconds to wait for each response
+    #[arg(short, long, default_value = "1000")]
+    timeout: u64,
+}
+
+#[cfg(feature = "serial")]
+#[derive(Args)]
+struct AttachArgs {
+    /// Serial port of the modem. Example: /dev/ttyUSB0
+    port: String,
+    /// DLCIs to open after DLCI 0. Example: "1,2,3"
+    #[arg(short, long, value_delimiter = ',', default_value = "1")]
+    channels: Vec<u8>,
+    /// Baud rate of the serial port
+    #[arg(short, long, default_value = "115200")]
+    baud: u32,
+    /// Parity of the serial port
+    #[arg(long, value_enum, default_value = "none")]
+    parity: SerialParity,
+    /// Flow control of the serial port
+    #[arg(long, value_enum, default_value = "none")]
+    flow_control: SerialFlowControl,
+    /// AT command switching the modem to CMUX mode. The N1, T1, N2, T2 and T3 parameters of
+    /// AT+CMUX also apply to the multiplexer. Example: "AT+CMUX=0,0,5,127"
+    #[arg(long, default_value = cmux::serial::AT_CMUX)]
+    init: String,
+    /// Do not send the AT command, the modem is already in CMUX mode
+    #[arg(long)]
+    no_init: bool,
+    /// Acknowledgement timer T1 of SABM and DISC, in milliseconds [default: 100]
+    #[arg(long, value_name = "MS")]
+    t1: Option<u64>,
+    /// Response timer T2 of the control commands, in milliseconds [default: 300]
+    #[arg(long, value_name = "MS")]
+    t2: Option<u64>,
+    /// Wake-up response timer T3, in seconds [default: 10]
+    #[arg(long, value_name = "S")]
+    t3: Option<u64>,
+    /// Maximum number of retransmissions N2 [default: 3]
+    #[arg(long, value_name = "COUNT")]
+    n2: Option<u8>,
+    /// Expose each channel on a pseudo-terminal linked at the given prefix followed by the
+    /// DLCI. Example: "/tmp/cmux" creates /tmp/cmux1, /tmp/cmux2...
+    #[cfg(all(unix, feature = "pty"))]
+    #[arg(long, value_name = "PREFIX")]
+    pty: Option<String>,
+    /// Listen for TCP connections to each channel at the given address, on its port plus the
+    /// DLCI. Example: "127.0.0.1:5000" exposes DLCI 1 at 127.0.0.1:5001
+    #[arg(long, value_name = "ADDR")]
+    tcp: Option<std::net::SocketAddr>,
+    /// Listen for connections to each channel on a Unix domain socket named after the DLCI in
+    /// the given directory, created if missing. Example: "/run/cmux" creates
+    /// /run/cmux/dlci1.sock, /run/cmux/dlci2.sock...
+    #[cfg(unix)]
+    #[arg(long, value_name = "DIR")]
+    unix: Option<PathBuf>,
+}
+
+#[cfg(feature = "serial")]
+#[derive(Args)]
+struct AtArgs {
+    /// Serial port of the modem. Example: /dev/ttyUSB0
+    port: String,
+    /// AT command to send, without the trailing carriage return. Example: "AT+CSQ"
+    command: String,
+    /// DLCI of the AT channel, opened after DLCI 0
+    #[arg(short, long, default_value = "1")]
+    dlci: u8,
+    /// Baud rate of the serial port
+    #[arg(short, long, default_value = "115200")]
+    baud: u32,
+    /// AT command switching the modem to CMUX mode
+    #[arg(long, default_value = cmux::serial::AT_CMUX)]
+    init: String,
+    /// Do not send the AT command, the modem is already in CMUX mode
+    #[arg(long)]
+    no_init: bool,
+    /// Milliseconds to wait for the final result code
+    #[arg(short, long, default_value = "5000")]
+    timeout: u64,
+}
+
+#[cfg(feature = "daemon")]
+#[derive(Args)]
+struct DaemonArgs {
+    /// Configuration of the device, the channels and their bridges, and the restart policy.
+    /// Example: /etc/cmux/cmuxd.toml
+    #[arg(short, long, value_name = "PATH")]
+    config: PathBuf,
+    /// Drive the serial port and the bridges asynchronously, reporting the control lines of the
+    /// port. Only TCP and Unix socket bridges are supported, and modem resets end the session
+    #[cfg(feature = "tokio-serial")]
+    #[arg(long = "async")]
+    asynchronous: bool,
+}
+
+#[cfg(feature = "serial")]
+#[derive(Args)]
+struct SniffArgs {
+    /// Serial port tapping the line from the host to the modem, labelled TX. Example: /dev/ttyUSB0
+    port: String,
+    /// Serial port tapping the line from the modem to the host, labelled RX
+    #[arg(long, value_name = "PORT")]
+    rx: Option<String>,
+    /// Baud rate of the serial ports
+    #[arg(short, long, default_value = "115200")]
+    baud: u32,
+    /// Format of the decoded frames
+    #[arg(short, long, value_enum, default_value = "text")]
+    output: SniffFormat,
+    /// MQTT broker to also publish every frame to, as HOST or HOST:PORT. The taps are never
+    /// written to, so the command topic is ignored
+    #[cfg(feature = "mqtt")]
+    #[arg(long, value_name = "BROKER", value_parser = parse_mqtt_broker)]
+    mqtt: Option<cmux::mqtt::MqttConfig>,
+    /// Prefix of the MQTT topics: the frames of DLCI N are published on PREFIX/dlci/N
+    #[cfg(feature = "mqtt")]
+    #[arg(long, value_name = "PREFIX", default_value = "cmux")]
+    mqtt_topic: String,
+}
+
+#[cfg(feature = "serial")]
+#[derive(Args)]
+struct ReplayArgs {
+    /// Frame records written by `sniff --output jsonl` or `parse --output json|jsonl`
+    file: PathBuf,
+    /// Serial port to send the frames to. Example: /dev/ttyUSB1
+    #[arg(short, long)]
+    port: String,
+    /// Baud rate of the serial port
+    #[arg(short, long, default_value = "115200")]
+    baud: u32,
+    /// Speed factor of the replay: 2x halves the delays, 0.5x doubles them
+    #[arg(short, long, default_value = "1x", value_parser = parse_speed)]
+    speed: f64,
+    /// Only send the frames recorded in this direction
+    #[arg(short, long, value_enum)]
+    direction: Option<ReplayDirection>,
+}
+
+#[cfg(feature = "serial")]
+#[derive(Clone, Copy, ValueEnum)]
+enum ReplayDirection {
+    /// From the host to the modem, labelled TX
+    Tx,
+    /// From the modem to the host, labelled RX
+    Rx,
+}
+
+#[cfg(feature = "serial")]
+impl From<ReplayDirection> for cmux::sniff::Direction {
+    fn from(direction: ReplayDirection) -> Self {
+        match direction {
+            ReplayDirection::Tx => cmux::sniff::Direction::Tx,
+            ReplayDirection::Rx => cmux::sniff::Direction::Rx,
+        }
+    }
+}
+
+#[derive(Args)]
+struct SimulateArgs {
+    /// Where the host connects: "pty" creates a pseudo-terminal and prints its path, an address
+    /// listens for TCP connections. Example: 127.0.0.1:5000
+    #[arg(short, long, value_parser = parse_simulate_port)]
+    port: SimulatePort,
+    /// DLCI answering AT commands with canned replies
+    #[arg(long, default_value = "1")]
+    at_dlci: u8,
+}
+
+#[derive(Args)]
+struct SelftestArgs {
+    /// Data DLCIs to open after DLCI 0 and the AT channel on DLCI 1. Example: "2,3,4"
+    #[arg(short, long, value_delimiter = ',', default_value = "2,3,4")]
+    #[arg(value_parser = clap::value_parser!(u8).range(2..64))]
+    dlcis: Vec<u8>,
+    /// Number of payloads sent per DLCI and direction
+    #[arg(short = 'n', long, default_value = "16")]
+    count: usize,
+    /// Largest payload length, in octets
+    #[arg(long, default_value = "1024", value_parser = clap::value_parser!(u64).range(1..))]
+    max_len: u64,
+    /// Seed of the payload generator, to reproduce a failure [default: random]
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+#[derive(Clone, Copy)]
+enum SimulatePort {
+    #[cfg(all(unix, feature = "pty"))]
+    Pty,
+    Tcp(std::net::SocketAddr),
+}
+
+#[cfg(feature = "serial")]
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SniffFormat {
+    /// One line per frame, followed by its control messages
+    Text,
+    /// One JSON frame record per line
+    Jsonl,
+}
+
+#[cfg(feature = "serial")]
+#[derive(Clone, Copy, ValueEnum)]
+enum SerialParity {
+    None,
+    Odd,
+    Even,
+}
+
+#[cfg(feature = "serial")]
+#[derive(Clone, Copy, ValueEnum)]
+enum SerialFlowControl {
+    None,
+    /// XON/XOFF
+    Software,
+    /// RTS/CTS
+    Hardware,
+}
+
+#[derive(Debug, Default)]
+struct Summary {
+    command: &'static str,
+    frames_ok: usize,
+    frames_invalid: usize,
+    bytes_skipped: usize,
+    violations: Vec<String>,
+    /// The framing in which the FCS of basic option frames is verified.
+    dialect: Dialect,
+    /// An input, output or device failed, whatever the frames handled before.
+    io_failed: bool,
+}
+
+impl Summary {
+    fn new(command: &'static str) -> Self {
+        Summary {
+            command,
+            ..Default::default()
+        }
+    }
+
+    fn record(&mut self, frame: &Frame) -> Result<(), FrameError> {
+        match self.dialect.verify(frame) {
+            Ok(_) => {
+                self.frames_ok += 1;
+                Ok(())
+            }
+            Err(e) => {
+                self.record_error(&e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Records `frame`, found at `offset` in the input, locating its error if it is invalid.
+    fn record_at(
+        &mut self,
+        frame: &Frame,
+        window: &Window,
+        offset: usize,
+    ) -> Result<(), ParseError> {
+        match self.dialect.verify(frame) {
+            Ok(_) => {
+                self.frames_ok += 1;
+                Ok(())
+            }
+            Err(e) => {
+                let e = window.locate(offset, e);
+                self.record_error(&e);
+                Err(e)
+            }
+        }
+    }
+
+    fn record_error(&mut self, e: &impl Display) {
+        let index = self.frames_ok + self.frames_invalid;
+        self.frames_invalid += 1;
+        self.violations.push(format!("frame {}: {}", index, e));
+    }
+
+    fn exit_code(&self) -> u8 {
+        if self.io_failed {
+            EXIT_IO_FAILED
+        } else if self.frames_invalid > 0 {
+            EXIT_INVALID_FRAMES
+        } else if self.frames_ok == 0 {
+            EXIT_NO_FRAMES
+        } else {
+            EXIT_OK
+        }
+    }
+
+    fn to_json(&self) -> String {
+        let violations = self
+            .violations
+            .iter()
+            .map(|v| json_string(v))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            r#"{{"command":"{}","frames_ok":{},"frames_invalid":{},"bytes_skipped":{},"violations":[{}],"exit_code":{}}}"#,
+            self.command,
+            self.frames_ok,
+            self.frames_invalid,
+            self.bytes_skipped,
+            violations,
+            self.exit_code()
+        )
+    }
+}
+
+/// Builds the JSON record of an input that could not be decoded as a frame.
+fn error_to_json(offset: Option<usize>, e: &impl Display) -> String {
+    let offset = offset.map_or("null".to_string(), |offset| offset.to_string());
+    format!(
+        r#"{{"offset":{},"valid":false,"error":{}}}"#,
+        offset,
+        json_string(&e.to_string())
+    )
+}
+
+/// Prints the JSON records of `parse` as they are produced, as a JSON array or one per line.
+struct Records {
+    output: OutputFormat,
+    count: usize,
+}
+
+impl Records {
+    fn new(output: OutputFormat) -> Self {
+        Records { output, count: 0 }
+    }
+
+    fn push(&mut self, record: &str) {
+        match (self.output, self.count) {
+            (OutputFormat::Json, 0) => print!("[{}", record),
+            (OutputFormat::Json, _) => print!(",{}", record),
+            _ => println!("{}", record),
+        }
+        self.count += 1;
+    }
+
+    /// Closes the JSON array.
+    fn finish(self) {
+        match (self.output, self.count) {
+            (OutputFormat::Json, 0) => println!("[]"),
+            (OutputFormat::Json, _) => println!("]"),
+            _ => {}
+        }
+    }
+}
+
+/// Builds the JSON record of octets skipped between frames.
+fn skipped_to_json(offset: usize, len: usize) -> String {
+    format!(r#"{{"offset":{},"skipped":{}}}"#, offset, len)
+}
+
+/// Size of the blocks in which the input of `parse` and `verify` is read.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Input of `parse` and `verify`, read from the argument, a file or stdin chunk by chunk
+///
+/// Input is hex text if its first chunk is, and raw bytes otherwise. The packets of pcap and
+/// pcapng captures are concatenated. An argument that is not hex text fails to open with
+/// [`std::io::ErrorKind::InvalidInput`].
+struct Input {
+    reader: Box<dyn Read>,
+    /// The number of bytes read from the file or stdin.
+    read: Rc<Cell<u64>>,
+    /// The size of the file, if any.
+    len: Option<u64>,
+    /// The decoder of hex text input.
+    hex: Option<HexDecoder>,
+    /// The packets of capture input, read one at a time.
+    packets: Option<PacketReader<Box<dyn Read>>>,
+    /// The decoded first chunk, until it is read.
+    pending: Option<Vec<u8>>,
+}
+
+impl Input {
+    fn open(hexstring: Option<String>, file: Option<&Path>) -> std::io::Result<Self> {
+        let (reader, len): (Box<dyn Read>, _) = match (hexstring, file) {
+            (_, Some(path)) => {
+                let file = std::fs::File::open(path)?;
+                let len = file.metadata()?.len();
+                (Box::new(file), Some(len))
+            }
+            (Some(hexstring), None) if hexstring != "-" => {
+                let data = codec::hex_to_bytes(&hexstring)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+                return Ok(Input::from_bytes(data));
+            }
+            _ => (Box::new(std::io::stdin()), None),
+        };
+        let read = Rc::default();
+        let mut reader: Box<dyn Read> = Box::new(Counted {
+            inner: reader,
+            count: Rc::clone(&read),
+        });
+        let prefix = read_chunk(&mut reader)?;
+        if capture::is_capture(&prefix) {
+            let reader: Box<dyn Read> = Box::new(std::io::Cursor::new(prefix).chain(reader));
+            let mut packets = PacketReader::new(reader)?;
+            return Ok(Input {
+                reader: Box::new(std::io::empty()),
+                read,
+                len,
+                hex: None,
+                pending: Some(read_packets(&mut packets)?),
+                packets: Some(packets),
+            });
+        }
+        let (hex, pending) = match decode_hex_prefix(&prefix, prefix.len() < CHUNK_SIZE) {
+            Some((decoder, bytes)) => (Some(decoder), bytes),
+            None => (None, prefix),
+        };
+        Ok(Input {
+            reader,
+            read,
+            len,
+            hex,
+            pending: Some(pending),
+            packets: None,
+        })
+    }
+
+    /// Creates the input of bytes already read.
+    fn from_bytes(data: Vec<u8>) -> Self {
+        Input {
+            reader: Box::new(std::io::empty()),
+            read: Rc::default(),
+            len: None,
+            hex: None,
+            pending: Some(data),
+            packets: None,
+        }
+    }
+
+    /// Returns the bytes of the first chunk, until it is read.
+    fn prefix(&self) -> &[u8] {
+        self.pending.as_deref().unwrap_or_default()
+    }
+
+    /// Reads the bytes of the next chunk, or `None` at the end of the input.
+    fn next_chunk(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+        if let Some(chunk) = self.pending.take() {
+            return Ok(Some(chunk));
+        }
+        if let Some(packets) = &mut self.packets {
+            let chunk = read_packets(packets)?;
+            return Ok((!chunk.is_empty()).then_some(chunk));
+        }
+        let chunk = read_chunk(&mut self.reader)?;
+        let Some(decoder) = &mut self.hex else {
+            return Ok((!chunk.is_empty()).then_some(chunk));
+        };
+        let decoded = if chunk.is_empty() {
+            let last = decoder.finish();
+            self.hex = None;
+            last
+        } else {
+            decoder.feed(&chunk)
+        };
+        decoded
+            .map(Some)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Reader counting the bytes read from `inner`
+struct Counted<R> {
+    inner: R,
+    count: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.set(self.count.get() + n as u64);
+        Ok(n)
+    }
+}
+
+/// Progress of `parse` and `verify` through their input, drawn on one line of stderr
+///
+/// The line shows the bytes read, the frames decoded and, when the size of the input is known,
+/// the estimated time left. It is drawn at most every [`Progress::INTERVAL`], from the first
+/// interval on, so small inputs print nothing, and cleared at the end.
+struct Progress {
+    enabled: bool,
+    /// The size of the input, if known.
+    total: Option<u64>,
+    frames: usize,
+    start: Instant,
+    drawn: Instant,
+    shown: bool,
+}
+
+impl Progress {
+    const INTERVAL: Duration = Duration::from_millis(200);
+
+    fn new(enabled: bool, total: Option<u64>) -> Self {
+        let start = Instant::now();
+        Progress {
+            enabled,
+            total,
+            frames: 0,
+            start,
+            drawn: start,
+            shown: false,
+        }
+    }
+
+    /// Creates a progress shown only if `enabled` and stderr is a terminal.
+    fn terminal(enabled: bool, total: Option<u64>) -> Self {
+        use std::io::IsTerminal;
+
+        Progress::new(enabled && std::io::stderr().is_terminal(), total)
+    }
+
+    /// Creates the progress of a command writing its results to stdout, shown only if
+    /// `enabled` and stdout is not the terminal stderr draws on.
+    fn redirected(enabled: bool, total: Option<u64>) -> Self {
+        use std::io::IsTerminal;
+
+        Progress::terminal(enabled && !std::io::stdout().is_terminal(), total)
+    }
+
+    /// Redraws the line after `bytes` bytes were read, if the interval has passed.
+    fn update(&mut self, bytes: u64) {
+        let now = Instant::now();
+        if !self.enabled || now - self.drawn < Self::INTERVAL {
+            return;
+        }
+        self.drawn = now;
+        self.shown = true;
+        eprint!("\r\x1b[K{}", self.line(bytes, now - self.start));
+    }
+
+    /// Clears the line, if drawn.
+    fn finish(&mut self) {
+        if self.shown {
+            eprint!("\r\x1b[K");
+            self.shown = false;
+        }
+    }
+
+    fn line(&self, bytes: u64, elapsed: Duration) -> String {
+        let Some(total) = self.total.filter(|&total| total > 0) else {
+            return format!("{} read, {} frames", format_size(bytes), self.frames);
+        };
+        let bytes = bytes.min(total);
+        let mut line = format!(
+            "{} / {} ({}%), {} frames",
+            format_size(bytes),
+            format_size(total),
+            bytes * 100 / total,
+            self.frames
+        );
+        if bytes > 0 {
+            let left = elapsed.as_secs_f64() * (total - bytes) as f64 / bytes as f64;
+            let left = left.round() as u64;
+            line.push_str(&format!(", ETA {}:{:02}", left / 60, left % 60));
+        }
+        line
+    }
+}
+
+/// Formats a number of bytes with a binary unit.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Reads up to [`CHUNK_SIZE`] bytes, less only at the end of `reader`.
+fn read_chunk(reader: &mut impl Read) -> std::io::Result<Vec<u8>> {
+    let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+    reader.take(CHUNK_SIZE as u64).read_to_end(&mut chunk)?;
+    Ok(chunk)
+}
+
+/// Reads packets until their data fill a chunk, or the end of the capture.
+fn read_packets(packets: &mut PacketReader<Box<dyn Read>>) -> std::io::Result<Vec<u8>> {
+    let mut chunk = Vec::new();
+    while chunk.len() < CHUNK_SIZE {
+        match packets.next() {
+            Some(packet) => chunk.extend(packet?.data),
+            None => break,
+        }
+    }
+    Ok(chunk)
+}
+
+/// Decodes the first chunk of the input if it is hex text, returning the decoder of the rest.
+/// At the end of the input, an odd number of digits makes it binary.
+fn decode_hex_prefix(prefix: &[u8], end: bool) -> Option<(HexDecoder, Vec<u8>)> {
+    if prefix.iter().all(u8::is_ascii_whitespace) {
+        return None;
+    }
+    let mut decoder = HexDecoder::new();
+    let mut bytes = decoder.feed(prefix).ok()?;
+    if end {
+        bytes.extend(decoder.finish().ok()?);
+    }
+    Some((decoder, bytes))
+}
+
+/// The last bytes read from an [`Input`], to show the octets around errors
+#[derive(Default)]
+struct Window {
+    data: Vec<u8>,
+    /// Offset of the first byte of `data` in the input.
+    offset: usize,
+}
+
+impl Window {
+    /// Octets kept before the bytes buffered by the parser.
+    const MARGIN: usize = 16;
+
+    /// Drops the bytes before the last `buffered` ones and a margin.
+    fn keep(&mut self, buffered: usize) {
+        let drop = self.data.len().saturating_sub(buffered + Self::MARGIN);
+        self.data.drain(..drop);
+        self.offset += drop;
+    }
+
+    /// Locates `error` at `offset` in the input.
+    fn locate(&self, offset: usize, error: FrameError) -> ParseError {
+        let mut error = ParseError::new(&self.data, offset.saturating_sub(self.offset), error);
+        error.offset = offset;
+        error.context_offset += self.offset;
+        error
+    }
+}
+
+/// What [`scan_input`] passes to its callback
+enum Scan<'a> {
+    /// The next bytes read, before the regions decoded from them.
+    Chunk(&'a [u8]),
+    Region(Scanned),
+}
+
+/// Decodes `input` chunk by chunk with `parser`, passing every chunk, frame and skipped
+/// region to `f` along with the bytes around it, and drawing the `progress` through the input.
+fn scan_input(
+    input: &mut Input,
+    mut parser: FrameParser,
+    progress: &mut Progress,
+    mut f: impl FnMut(Scan, &Window),
+) -> std::io::Result<()> {
+    let mut window = Window::default();
+    let result = loop {
+        let chunk = match input.next_chunk() {
+            Ok(chunk) => chunk,
+            Err(e) => break Err(e),
+        };
+        let scanned = match &chunk {
+            Some(chunk) => {
+                window.data.extend_from_slice(chunk);
+                f(Scan::Chunk(chunk), &window);
+                parser.feed_scanned(chunk)
+            }
+            None => parser.finish(),
+        };
+        for scanned in scanned {
+            if let Scanned::Frame { .. } = scanned {
+                progress.frames += 1;
+            }
+            f(Scan::Region(scanned), &window);
+        }
+        if chunk.is_none() {
+            break Ok(());
+        }
+        window.keep(parser.buffered());
+        progress.update(input.read.get());
+    };
+    progress.finish();
+    result
+}
+
+/// Opens a text log from the file, the path given as argument, or stdin, returning the size
+/// of the file, if any.
+fn open_log(
+    path: Option<&str>,
+    file: Option<&Path>,
+) -> std::io::Result<(Box<dyn BufRead>, Option<u64>)> {
+    let path = file.or(path.filter(|&path| path != "-").map(Path::new));
+    Ok(match path {
+        Some(path) => {
+            let file = std::fs::File::open(path)?;
+            let len = file.metadata()?.len();
+            (Box::new(BufReader::new(file)), Some(len))
+        }
+        None => (Box::new(std::io::stdin().lock()), None),
+    })
+}
+
+/// Reads the next line of a log, without its line ending, or `None` at the end of the log.
+fn read_log_line(log: &mut impl BufRead, line: &mut Vec<u8>) -> std::io::Result<Option<String>> {
+    line.clear();
+    if log.read_until(b'\n', line)? == 0 {
+        return Ok(None);
+    }
+    let text = String::from_utf8_lossy(line);
+    Ok(Some(text.trim_end_matches(['\n', '\r']).to_string()))
+}
+
+/// Prints the content of `frame` decoded as text, if `encoding` is given.
+fn print_payload(encoding: Option<PayloadEncoding>, frame: &Frame) {
+    if let Some(encoding) = encoding {
+        match text::PayloadView::new(encoding.into()).render_kind(frame) {
+            (Some(kind), text) => println!("Payload ({}): {:?}", kind.as_str(), text),
+            (None, text) => println!("Payload: {:?}", text),
+        }
+    }
+}
+
+/// Rewrites the FCS of `frame` in `dialect` if `enabled` and it is a basic option frame, and
+/// returns the old and new FCS if they differ.
+fn fix_fcs(
+    enabled: bool,
+    mode: FrameMode,
+    dialect: &Dialect,
+    frame: &mut Frame,
+) -> Option<(u8, u8)> {
+    if !enabled || mode != FrameMode::Basic {
+        return None;
+    }
+    let checksum = frame.checksum;
+    frame.checksum = dialect.checksum(&frame.as_frame_ref());
+    (frame.checksum != checksum).then_some((checksum, frame.checksum))
+}
+
+/// Prints the FCS rewritten by [`fix_fcs`].
+fn print_fixed(fixed: Option<(u8, u8)>) {
+    if let Some((old, new)) = fixed {
+        println!("FCS fixed: 0x{:02X} -> 0x{:02X}", old, new);
+    }
+}
+
+/// Decodes the frames of a log, each direction on its own, and prints them with the time and
+/// direction of the record completing them.
+fn parse_log(
+    args: &ParseArgs,
+    format: LogFormat,
+    mode: FrameMode,
+    summary: &mut Summary,
+    stats: &mut Stats,
+) -> std::io::Result<()> {
+    let (mut log, len) = open_log(args.hexstring.as_deref(), args.file.as_deref())?;
+    let mut progress = Progress::redirected(!args.no_progress, len);
+    let mut read = 0;
+    let dialect = args.dialect.dialect();
+    let mut decoder = match mode {
+        FrameMode::Basic => LogDecoder::new().with_dialect(dialect),
+        FrameMode::Advanced => LogDecoder::advanced(),
+    };
+    let mut records = Records::new(args.output);
+    let mut reader = format.reader();
+    let mut line = Vec::new();
+    loop {
+        let text = match read_log_line(&mut log, &mut line) {
+            Ok(Some(text)) => text,
+            Ok(None) => break,
+            Err(e) => {
+                progress.finish();
+                records.finish();
+                return Err(e);
+            }
+        };
+        read += line.len() as u64;
+        progress.update(read);
+        let Some(record) = reader.read_line(&text) else {
+            continue;
+        };
+        stats.record_bytes(&record.data, record.timestamp);
+        for mut logged in decoder.feed(&record) {
+            progress.frames += 1;
+            let fixed = fix_fcs(args.fix_fcs, mode, &dialect, &mut logged.frame);
+            let frame = &logged.frame;
+            let verify = summary.record(frame);
+            stats.record(frame, logged.direction, logged.timestamp);
+            let timestamp = logged.timestamp.map(|t| t.as_secs_f64());
+            let direction = logged.direction.map(|d| d.as_str());
+            if args.output != OutputFormat::Text {
+                let record = FrameRecord::new(frame)
+                    .with_seen(logged.timestamp, logged.direction)
+                    .with_verify(&verify);
+                let record = match args.payload {
+                    Some(encoding) => record.with_payload(encoding.into()),
+                    None => record,
+                };
+                records.push(&record.to_string());
+                continue;
+            }
+            let verify = match verify {
+                Ok(_) => "OK".to_string(),
+                Err(e) => e.to_string(),
+            };
+            if let Some(timestamp) = timestamp {
+                print!("{:.6} ", timestamp);
+            }
+            if let Some(direction) = direction {
+                print!("{} ", direction);
+            }
+            println!(
+                "Origin: {} Verify: {}\n{}",
+                mode.encode_in(frame, &dialect),
+                verify,
+                mode.pretty(frame)
+            );
+            print_fixed(fixed);
+            if let Ok(messages) = ControlMessage::from_frame(frame) {
+                for message in messages {
+                    println!("Control: {}", describe_control(&message));
+                }
+            }
+            print_payload(args.payload, frame);
+        }
+    }
+    progress.finish();
+    records.finish();
+    summary.bytes_skipped = decoder.skipped();
+    Ok(())
+}
+
+/// Parses an octet given as hex, with or without `0x`.
+fn parse_hexbyte(hexbyte: &str) -> Result<u8, String> {
+    u8::from_str_radix(hexbyte.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("{:?} is not a hex octet", hexbyte))
+}
+
+/// Splits a line of `generate --from-file` into arguments. Quotes group words, and a backslash
+/// escapes the next character outside single quotes.
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('\''), c) => word.get_or_insert_with(String::new).push(c),
+            (_, '\\') => {
+                let c = chars.next().ok_or("trailing backslash")?;
+                word.get_or_insert_with(String::new).push(c);
+            }
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (_, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err("unterminated quote".to_string());
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Splits a CSV record into its fields. Double quotes group commas, and `""` stands for a quote
+/// inside them.
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().unwrap();
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => field.push(c),
+        }
+    }
+    fields
+}
+
+/// Generates the frames of `generate --from-file`, one per line. Empty lines and lines starting
+/// with `#` are skipped, as is a header line of a CSV file.
+///
+/// # Arguments
+///
+/// * `text` - The content of the file.
+/// * `csv` - Whether the lines are CSV records of address, control and content, instead of the
+///   options of `generate`.
+///
+/// # Returns
+///
+/// The frames, or the first invalid line with its number.
+fn generate_batch(text: &str, csv: bool) -> Result<Vec<Frame>, String> {
+    let mut frames = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |e: String| format!("line {}: {}", index + 1, e);
+        let frame = if csv {
+            let mut fields = split_csv(line).into_iter();
+            let address = fields.next().unwrap_or_default();
+            if frames.is_empty() && address.trim().eq_ignore_ascii_case("address") {
+                continue;
+            }
+            let address = parse_hexbyte(address.trim()).map_err(error)?;
+            let control = fields
+                .next()
+                .ok_or_else(|| error("no control field".to_string()))?;
+            let control = parse_hexbyte(control.trim()).map_err(error)?;
+            let content = fields.next().filter(|content| !content.is_empty());
+            if fields.next().is_some() {
+                return Err(error("more than 3 fields".to_string()));
+            }
+            generate(
+                Address::from_bits(address),
+                Control::from_bits(control),
+                content,
+            )
+        } else {
+            let words = split_words(line).map_err(error)?;
+            let args = GenerateLine::try_parse_from(words).map_err(|e| {
+                let message = e.to_string();
+                let message = message.lines().next().unwrap_or_default();
+                error(message.trim_start_matches("error: ").to_string())
+            })?;
+            args.frame.build()
+        };
+        frames.push(frame.map_err(|e| error(e.to_string()))?);
+    }
+    Ok(frames)
+}
+
+/// Encodes `frames` as one hex stream. With `shared_flags`, the closing flag of each frame
+/// also opens the next one.
+fn join_frames(mode: FrameMode, frames: &[Frame], shared_flags: bool) -> String {
+    let mut stream = String::new();
+    for frame in frames {
+        let encoded = mode.encode(frame);
+        if shared_flags && !stream.is_empty() {
+            // Skip the two hex digits of the opening flag.
+            stream += &encoded[2..];
+        } else {
+            stream += &encoded;
+        }
+    }
+    stream
 }
 
-#[derive(Subcommand)]
-enum Commands {
-    /// Generate GSM 07.10 Frame by given address, control and content field
-    #[command(visible_alias = "g")]
-    Generate(GenerateArgs),
-    /// Parse a byte array to GSM 07.10 Frame
-    #[command(visible_alias = "p")]
-    Parse(ParseArgs),
+/// Describes the framing detected by `parse --detect-mode`, or the fallback `mode` when no
+/// valid frame was found.
+fn describe_detection(detection: Option<Detection>, mode: FrameMode) -> String {
+    let Some(detection) = detection else {
+        let mode = match mode {
+            FrameMode::Basic => "basic",
+            FrameMode::Advanced => "advanced",
+        };
+        return format!("Detected mode: none, decoding as {}", mode);
+    };
+    match (detection.advanced, detection.escaped) {
+        (false, _) => format!("Detected mode: basic, {} valid frames", detection.frames),
+        (true, escaped) => format!(
+            "Detected mode: advanced, {} valid frames, {}",
+            detection.frames,
+            if escaped { "escaped" } else { "no escapes" }
+        ),
+    }
 }
 
-#[derive(Args)]
-struct GenerateArgs {
-    /// address field
-    #[arg(short, long, default_value = "7")]
-    address: String,
-    /// control field
-    #[arg(short, long, default_value = "EF")]
-    control: String,
-    /// content field
-    content: String,
+/// Decodes and verifies every frame of `input`, recording the results in `summary`.
+fn verify(
+    mode: FrameMode,
+    input: &mut Input,
+    summary: &mut Summary,
+    progress: &mut Progress,
+) -> std::io::Result<()> {
+    let parser = mode.parser(&summary.dialect);
+    scan_input(input, parser, progress, |scan, window| match scan {
+        Scan::Chunk(_) => {}
+        Scan::Region(Scanned::Frame { offset, frame }) => {
+            let _ = summary.record_at(&frame, window, offset);
+        }
+        Scan::Region(Scanned::Invalid { offset, error, .. }) => {
+            summary.record_error(&window.locate(offset, error))
+        }
+        Scan::Region(Scanned::Skipped { len, .. }) => summary.bytes_skipped += len,
+    })
 }
 
-#[derive(Args)]
-struct ParseArgs {
-    /// Bytes array like string. Example: "F9010203F9 F9010203F9"
-    hexstring: Option<String>,
+/// Builds the frames of `template`, recording them in `summary`.
+fn record_template(template: &Template, summary: &mut Summary) -> Vec<Frame> {
+    match template.frames() {
+        Ok(frames) => {
+            for frame in &frames {
+                let _ = summary.record(frame);
+            }
+            frames
+        }
+        Err(e) => {
+            summary.record_error(&e);
+            eprintln!("Error: {}: {}", template.name, e);
+            Vec::new()
+        }
+    }
+}
+
+fn closedown(dlcis: &[u8], responder: bool) -> Result<Vec<Frame>, FrameError> {
+    let mut session = Session::new().with_initiator(!responder);
+    for &dlci in dlcis {
+        session.open(dlci);
+    }
+    session.shutdown()
+}
+
+#[cfg(feature = "serial")]
+fn ping(args: &PingArgs) -> std::io::Result<ping::PingStats> {
+    use std::time::Duration;
+
+    let mut port = serialport::new(&args.port, args.baud)
+        .timeout(Duration::from_millis(10))
+        .open()?;
+    let mut stats = ping::PingStats::default();
+    for seq in 0..args.count {
+        if seq > 0 {
+            std::thread::sleep(Duration::from_millis(args.interval));
+        }
+        let rtt = ping::ping(&mut port, seq, Duration::from_millis(args.timeout))?;
+        match rtt {
+            Some(rtt) => println!(
+                "Test response: seq={} time={:.3} ms",
+                seq,
+                rtt.as_secs_f64() * 1000.0
+            ),
+            None => println!("Request timeout for seq {}", seq),
+        }
+        stats.record(rtt);
+    }
+    Ok(stats)
 }
 
-fn hexstring_to_bytes(hexstring: &str) -> Vec<u8> {
-    let hexstring = hexstring
-        .to_string()
-        .replace([' ', '\n'], "")
-        .replace("0x", "");
-    hexstring
-        .as_bytes()
-        .chunks(2)
-        .map(|chunk| u8::from_str_radix(std::str::from_utf8(chunk).unwrap(), 16).unwrap())
-        .collect()
+#[cfg(feature = "serial")]
+fn attach(args: &AttachArgs, summary: &mut Summary) -> std::io::Result<()> {
+    use cmux::at::CmuxParams;
+    use cmux::bridge::{Endpoints, SocketBridge};
+    use cmux::mux::{Mux, MuxConfig, MuxEvent};
+    use cmux::serial::SerialSettings;
+    use std::time::{Duration, Instant};
+
+    let settings = SerialSettings {
+        baud: args.baud,
+        parity: match args.parity {
+            SerialParity::None => serialport::Parity::None,
+            SerialParity::Odd => serialport::Parity::Odd,
+            SerialParity::Even => serialport::Parity::Even,
+        },
+        flow_control: match args.flow_control {
+            SerialFlowControl::None => serialport::FlowControl::None,
+            SerialFlowControl::Software => serialport::FlowControl::Software,
+            SerialFlowControl::Hardware => serialport::FlowControl::Hardware,
+        },
+        ..Default::default()
+    };
+    // The timers given to the modem in AT+CMUX apply to the engine too.
+    let params = args
+        .init
+        .parse::<CmuxParams>()
+        .ok()
+        .filter(|_| !args.no_init);
+    let mut config = params.map_or_else(MuxConfig::default, |params| params.to_config());
+    if let Some(t1) = args.t1 {
+        config.t1 = Duration::from_millis(t1);
+    }
+    if let Some(t2) = args.t2 {
+        config.t2 = Duration::from_millis(t2);
+    }
+    if let Some(t3) = args.t3 {
+        config.t3 = Duration::from_secs(t3);
+    }
+    if let Some(n2) = args.n2 {
+        config.n2 = n2;
+    }
+    let init = (!args.no_init).then_some(args.init.as_str());
+    let mut mux = Mux::new().with_config(config);
+    if let Some(params) = params {
+        for &dlci in &args.channels {
+            mux.set_parameters(params.parameters(dlci));
+        }
+    }
+    let mut link = cmux::serial::attach(&args.port, &settings, init, mux)?;
+    println!("DLCI 0 connected");
+    for &dlci in &args.channels {
+        if link.connect(dlci)? {
+            println!("DLCI {} connected", dlci);
+        } else {
+            eprintln!("DLCI {} was not connected", dlci);
+        }
+    }
+    let mut endpoints = Endpoints::new();
+    for dlci in link.mux().session().open_dlcis() {
+        if dlci == 0 {
+            continue;
+        }
+        #[cfg(all(unix, feature = "pty"))]
+        if let Some(prefix) = &args.pty {
+            let pty = cmux::pty::Pty::open()?.with_link(format!("{}{}", prefix, dlci))?;
+            println!("DLCI {}: {}", dlci, pty.path().display());
+            endpoints.insert(dlci, Box::new(pty));
+        }
+        if let Some(addr) = args.tcp {
+            let port = addr.port().checked_add(dlci.into()).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("no TCP port for DLCI {} after {}", dlci, addr.port()),
+                )
+            })?;
+            let bridge = SocketBridge::tcp((addr.ip(), port))?;
+            println!("DLCI {}: tcp://{}", dlci, bridge.local_addr()?);
+            endpoints.insert(dlci, Box::new(bridge));
+        }
+        #[cfg(unix)]
+        if let Some(dir) = &args.unix {
+            std::fs::create_dir_all(dir)?;
+            let bridge = SocketBridge::unix(dir.join(format!("dlci{}.sock", dlci)))?;
+            println!("DLCI {}: {}", dlci, bridge.path().display());
+            endpoints.insert(dlci, Box::new(bridge));
+        }
+    }
+    loop {
+        for e in endpoints.forward(link.mux_mut())? {
+            eprintln!("Error: {}", e);
+        }
+        link.poll(Instant::now())?;
+        while let Some(event) = link.mux_mut().poll_event() {
+            if endpoints.deliver(&event)? {
+                summary.frames_ok += 1;
+                continue;
+            }
+            match event {
+                MuxEvent::DlcOpened(dlci) => println!("DLCI {} opened by the modem", dlci),
+                MuxEvent::DlcClosed(0) => {
+                    println!("DLCI 0 closed");
+                    return Ok(());
+                }
+                MuxEvent::DlcClosed(dlci) => println!("DLCI {} closed", dlci),
+                MuxEvent::Data { dlci, data } => {
+                    summary.frames_ok += 1;
+                    println!("DLCI {}: {:?}", dlci, String::from_utf8_lossy(&data));
+                }
+                MuxEvent::ModemStatus(msc) => println!("Modem status: {}", msc),
+                MuxEvent::Urc { dlci, line } => println!("DLCI {} URC: {}", dlci, line),
+                MuxEvent::Anomaly(anomaly) => eprintln!("Anomaly: {}", anomaly),
+                MuxEvent::PowerState(state) => println!("Power state: {}", state.as_str()),
+                MuxEvent::FlowControl { enabled } => {
+                    println!("Flow control: {}", if enabled { "FCon" } else { "FCoff" })
+                }
+                MuxEvent::Skipped(bytes) => eprintln!("Skipped {} bytes", bytes),
+                MuxEvent::LineStatus(status) => println!("Control lines: {}", status.signals),
+                MuxEvent::Error(e) => eprintln!("Error: {}", e),
+            }
+        }
+    }
 }
 
-fn hexbyte_to_bytes(hexbyte: &str) -> u8 {
-    let hexbyte = hexbyte.replace("0x", "");
-    u8::from_str_radix(&hexbyte, 16).unwrap()
+#[cfg(feature = "serial")]
+fn at(args: &AtArgs, summary: &mut Summary) -> std::io::Result<()> {
+    use cmux::mux::Mux;
+    use cmux::serial::SerialSettings;
+    use std::time::Duration;
+
+    let settings = SerialSettings {
+        baud: args.baud,
+        ..Default::default()
+    };
+    let init = (!args.no_init).then_some(args.init.as_str());
+    let mut link = cmux::serial::attach(&args.port, &settings, init, Mux::new())?;
+    if !link.connect(args.dlci)? {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            format!("DLCI {} was not connected", args.dlci),
+        ));
+    }
+    let timeout = Duration::from_millis(args.timeout);
+    let response = cmux::at::command(&mut link, args.dlci, &args.command, timeout)?;
+    response.lines.iter().for_each(|line| println!("{}", line));
+    println!("{}", response.result);
+    summary.frames_ok += 1;
+    if !response.result.is_ok() {
+        summary.frames_invalid += 1;
+        summary
+            .violations
+            .push(format!("{}: {}", args.command, response.result));
+    }
+    Ok(())
 }
 
-fn string_eater<'a>(ori: &'a str, d: &str) -> Option<(&'a str, &'a str)> {
-    let len = d.len();
-    let start = match ori.find(d) {
-        Some(i) => i,
-        None => return None,
+#[cfg(feature = "serial")]
+fn sniff(args: &SniffArgs, mode: FrameMode, summary: &mut Summary) -> std::io::Result<()> {
+    use cmux::serial::SerialSettings;
+    use cmux::sniff::{Direction, Sniffer};
+    use cmux::transport::{IoTransport, Transport};
+    use std::time::Instant;
+
+    let settings = SerialSettings {
+        baud: args.baud,
+        ..Default::default()
+    };
+    let mut taps = vec![(
+        Direction::Tx,
+        IoTransport::new(cmux::serial::open(&args.port, &settings)?),
+    )];
+    if let Some(rx) = &args.rx {
+        taps.push((
+            Direction::Rx,
+            IoTransport::new(cmux::serial::open(rx, &settings)?),
+        ));
+    }
+    let start = Instant::now();
+    let mut sniffer = match mode {
+        FrameMode::Basic => Sniffer::new(start),
+        FrameMode::Advanced => Sniffer::advanced(start),
+    };
+    #[cfg(feature = "mqtt")]
+    let mqtt = match &args.mqtt {
+        Some(config) => {
+            let config = config.clone().with_topic(&args.mqtt_topic);
+            Some(cmux::mqtt::MqttBridge::connect(&config)?)
+        }
+        None => None,
     };
-    let end = match ori[start + len..].find(d) {
-        Some(i) => i,
-        None => return None,
+    let mut buf = [0; 1024];
+    loop {
+        for (direction, tap) in taps.iter_mut() {
+            let count = tap.read(&mut buf)?;
+            if count == 0 {
+                continue;
+            }
+            for sniffed in sniffer.feed(*direction, &buf[..count], Instant::now()) {
+                #[cfg(feature = "mqtt")]
+                if let Some(mqtt) = &mqtt {
+                    mqtt.publish(&sniffed);
+                    let dropped = mqtt.take_dropped();
+                    if dropped > 0 {
+                        eprintln!("{} frames were not published to the MQTT broker", dropped);
+                    }
+                }
+                let frame = &sniffed.frame;
+                let verify = summary.record(frame);
+                let timestamp = sniffed.timestamp.as_secs_f64();
+                let direction = sniffed.direction.as_str();
+                if args.output == SniffFormat::Jsonl {
+                    println!("{}", FrameRecord::sniffed(&sniffed).with_verify(&verify));
+                    continue;
+                }
+                let verify = match verify {
+                    Ok(_) => "OK".to_string(),
+                    Err(e) => e.to_string(),
+                };
+                println!(
+                    "{:>12.6} {} DLCI {} {:?} {} Verify: {}",
+                    timestamp,
+                    direction,
+                    frame.address.dlci().into_bits(),
+                    frame.control.frame_type(),
+                    mode.encode(frame),
+                    verify
+                );
+                if let Ok(messages) = ControlMessage::from_frame(frame) {
+                    for message in messages {
+                        println!("{:>12} Control: {}", "", describe_control(&message));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Parses an MQTT broker such as `localhost` or `10.0.0.2:1884`.
+#[cfg(feature = "mqtt")]
+fn parse_mqtt_broker(broker: &str) -> Result<cmux::mqtt::MqttConfig, String> {
+    match broker.rsplit_once(':') {
+        Some((host, port)) => port
+            .parse()
+            .map(|port| cmux::mqtt::MqttConfig::new(host).with_port(port))
+            .map_err(|_| format!("{:?} is not a port", port)),
+        None => Ok(cmux::mqtt::MqttConfig::new(broker)),
+    }
+}
+
+/// Parses a speed factor such as `2x`, `0.5x` or `3`.
+#[cfg(feature = "serial")]
+fn parse_speed(speed: &str) -> Result<f64, String> {
+    speed
+        .strip_suffix('x')
+        .unwrap_or(speed)
+        .parse::<f64>()
+        .ok()
+        .filter(|speed| speed.is_finite() && *speed > 0.0)
+        .ok_or_else(|| format!("{:?} is not a positive speed factor", speed))
+}
+
+/// Decodes the frame records of a JSON array or of JSON lines, as written by `sniff` and
+/// `parse`. Records of skipped octets and undecodable input are left out.
+#[cfg(feature = "serial")]
+fn read_recording(text: &str) -> Result<Vec<cmux::replay::RecordedFrame>, String> {
+    use serde_json::Value;
+
+    let records: Vec<Value> = if text.trim_start().starts_with('[') {
+        serde_json::from_str(text).map_err(|e| e.to_string())?
+    } else {
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?
     };
-    Some((
-        &ori[start..start + end + 2 * len],
-        &ori[start + end + 2 * len..],
-    ))
+    let mut frames = Vec::new();
+    for (index, record) in records.iter().enumerate() {
+        if record.get("dlci").is_some() {
+            let frame = recorded_frame(record)
+                .ok_or_else(|| format!("record {} is not a valid frame record", index + 1))?;
+            frames.push(frame);
+        }
+    }
+    Ok(frames)
 }
 
-fn generate(address: &str, control: &str, content: String) -> Frame {
-    let address = Address::from_bits(hexbyte_to_bytes(address));
-    let control = Control::from_bits(hexbyte_to_bytes(control));
+/// Rebuilds the frame of a [`FrameRecord`], keeping its FCS as recorded.
+#[cfg(feature = "serial")]
+fn recorded_frame(record: &serde_json::Value) -> Option<cmux::replay::RecordedFrame> {
+    use cmux::sniff::Direction;
+    use std::time::Duration;
 
-    FrameBuilder::default()
+    let dlci = u8::try_from(record["dlci"].as_u64()?)
+        .ok()
+        .filter(|&dlci| dlci < 64)?;
+    let address = Address::default()
+        .with_cr(record["cr"].as_bool()?)
+        .with_dlci(DLCI::from_bits(dlci));
+    // Records written before the control octet was added are rebuilt from the frame type.
+    let control = match &record["control"] {
+        serde_json::Value::Null => {
+            let frame_type =
+                GenerateFrameType::from_str(record["frame_type"].as_str()?, true).ok()?;
+            Control::default()
+                .with_frame_type(frame_type.into())
+                .with_pf(record["pf"].as_bool()?)
+        }
+        control => Control::from_bits(u8::try_from(control.as_u64()?).ok()?),
+    };
+    let content = hex::decode(record["content"]["hex"].as_str()?).ok()?;
+    let mut frame = FrameBuilder::default()
         .with_address(address)
         .with_control(control)
-        .with_content(content)
+        .with_binary_content(content)
         .build()
+        .ok()?;
+    frame.checksum = u8::try_from(record["checksum"].as_u64()?).ok()?;
+    let timestamp = match &record["timestamp"] {
+        serde_json::Value::Null => None,
+        timestamp => Some(Duration::try_from_secs_f64(timestamp.as_f64()?).ok()?),
+    };
+    let direction = match &record["direction"] {
+        serde_json::Value::Null => None,
+        direction => match direction.as_str()? {
+            "TX" => Some(Direction::Tx),
+            "RX" => Some(Direction::Rx),
+            _ => return None,
+        },
+    };
+    Some(cmux::replay::RecordedFrame {
+        timestamp,
+        direction,
+        frame,
+    })
 }
 
-fn parse(hexstring: String) -> Vec<Frame> {
-    let hex = hexstring.to_uppercase();
-    let mut hex = hex.as_str();
-    let mut frames = Vec::new();
-    while let Some((curr, rest)) = string_eater(hex, "F9") {
-        let frame = Frame::from_bytes(hexstring_to_bytes(curr));
-        frames.push(frame);
-        hex = rest;
+/// Sends `frames` to the serial port of `args`, printing each of them once sent.
+#[cfg(feature = "serial")]
+fn replay(
+    args: &ReplayArgs,
+    frames: &[cmux::replay::RecordedFrame],
+    mode: FrameMode,
+    summary: &mut Summary,
+) -> std::io::Result<()> {
+    use cmux::replay::Replayer;
+    use cmux::serial::SerialSettings;
+    use cmux::transport::IoTransport;
+    use std::time::Instant;
+
+    let settings = SerialSettings {
+        baud: args.baud,
+        ..Default::default()
+    };
+    let mut port = IoTransport::new(cmux::serial::open(&args.port, &settings)?);
+    let mut replayer = Replayer::new().with_speed(args.speed);
+    if mode == FrameMode::Advanced {
+        replayer = replayer.advanced();
+    }
+    let start = Instant::now();
+    replayer.run(frames, &mut port, |recorded| {
+        let frame = &recorded.frame;
+        let _ = summary.record(frame);
+        println!(
+            "{:>12.6} {} DLCI {} {:?} {}",
+            start.elapsed().as_secs_f64(),
+            recorded.direction.map_or("--", |d| d.as_str()),
+            frame.address.dlci().into_bits(),
+            frame.control.frame_type(),
+            mode.encode(frame)
+        );
+    })
+}
+
+/// Parses the `--port` of `simulate`: "pty" or a TCP address to listen at.
+fn parse_simulate_port(port: &str) -> Result<SimulatePort, String> {
+    if port == "pty" {
+        #[cfg(all(unix, feature = "pty"))]
+        return Ok(SimulatePort::Pty);
+        #[cfg(not(all(unix, feature = "pty")))]
+        return Err("pseudo-terminals require the pty feature on Unix".to_string());
+    }
+    port.parse()
+        .map(SimulatePort::Tcp)
+        .map_err(|_| format!("expected \"pty\" or a TCP address, got {:?}", port))
+}
+
+fn simulate(args: &SimulateArgs, mode: FrameMode, summary: &mut Summary) -> std::io::Result<()> {
+    use cmux::transport::IoTransport;
+    use std::io::ErrorKind;
+    use std::time::Duration;
+
+    match args.port {
+        #[cfg(all(unix, feature = "pty"))]
+        SimulatePort::Pty => {
+            let pty = cmux::pty::Pty::open()?;
+            println!("Simulating a modem on {}", pty.path().display());
+            run_simulator(pty, args, mode, summary)
+        }
+        SimulatePort::Tcp(addr) => {
+            let listener = std::net::TcpListener::bind(addr)?;
+            println!("Simulating a modem on tcp://{}", listener.local_addr()?);
+            for stream in listener.incoming() {
+                let stream = stream?;
+                println!("Host connected from {}", stream.peer_addr()?);
+                stream.set_read_timeout(Some(Duration::from_millis(100)))?;
+                match run_simulator(IoTransport::new(stream), args, mode, summary) {
+                    Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                        println!("Host disconnected")
+                    }
+                    result => result?,
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Simulates a modem on `transport` until it fails, printing the events of the session.
+fn run_simulator<T: cmux::transport::Transport>(
+    transport: T,
+    args: &SimulateArgs,
+    mode: FrameMode,
+    summary: &mut Summary,
+) -> std::io::Result<()> {
+    use cmux::mux::MuxEvent;
+    use cmux::simulator::Simulator;
+    use std::time::{Duration, Instant};
+
+    let mut simulator = Simulator::new(transport).with_at_dlci(args.at_dlci);
+    if mode == FrameMode::Advanced {
+        simulator = simulator.advanced();
+    }
+    loop {
+        if simulator.poll(Instant::now())? == 0 {
+            // Pseudo-terminals do not block, do not spin while the host is silent.
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        while let Some(event) = simulator.poll_event() {
+            match event {
+                MuxEvent::DlcOpened(dlci) => println!("DLCI {} opened", dlci),
+                MuxEvent::DlcClosed(dlci) => println!("DLCI {} closed", dlci),
+                MuxEvent::Data { dlci, data } => {
+                    summary.frames_ok += 1;
+                    println!("DLCI {}: {:?}", dlci, String::from_utf8_lossy(&data));
+                }
+                MuxEvent::ModemStatus(msc) => println!("Modem status: {}", msc),
+                MuxEvent::Urc { dlci, line } => println!("DLCI {} URC: {}", dlci, line),
+                MuxEvent::Anomaly(anomaly) => eprintln!("Anomaly: {}", anomaly),
+                MuxEvent::PowerState(state) => println!("Power state: {}", state.as_str()),
+                MuxEvent::FlowControl { enabled } => {
+                    println!("Flow control: {}", if enabled { "FCon" } else { "FCoff" })
+                }
+                MuxEvent::Skipped(bytes) => eprintln!("Skipped {} bytes", bytes),
+                MuxEvent::LineStatus(status) => println!("Control lines: {}", status.signals),
+                MuxEvent::Error(e) => eprintln!("Error: {}", e),
+            }
+        }
+    }
+}
+
+/// Returns the SGR parameters coloring `annotation`: red if it is wrong, otherwise by field.
+fn field_color(annotation: &Annotation) -> &'static str {
+    if !annotation.ok {
+        return "1;31";
+    }
+    match annotation.field {
+        Field::Flag => "90",
+        Field::Address => "33",
+        Field::Control => "35",
+        Field::Length => "36",
+        Field::Content => "32",
+        Field::Fcs => "34",
+        Field::Trailing => "31",
+    }
+}
+
+/// Prints the annotated octets of a frame: a colored hex line, then one row per octet.
+fn explain(data: &[u8], color: bool) {
+    let paint = |annotation: &Annotation, text: String| {
+        if color {
+            format!("\x1b[{}m{}\x1b[0m", field_color(annotation), text)
+        } else {
+            text
+        }
+    };
+    let annotations = pretty::annotate(data);
+    let line: Vec<String> = annotations
+        .iter()
+        .map(|a| paint(a, format!("{:02X}", a.octet)))
+        .collect();
+    println!("{}\n", line.join(" "));
+    println!("Offset  Octet  Bits        Field");
+    for a in &annotations {
+        let row = format!(
+            "{:>6}  {}     {:<12}{} {}",
+            a.offset,
+            paint(a, format!("{:02X}", a.octet)),
+            a.bits,
+            paint(a, format!("{:<9}", a.field.as_str())),
+            a.label
+        );
+        println!("{}", row.trim_end());
+    }
+}
+
+fn describe_control(message: &ControlMessage) -> String {
+    let kind = if message.cr { "command" } else { "response" };
+    let value = match message.message_type {
+        MessageType::MSC => Msc::try_from(message).map(|msc| msc.to_string()),
+        MessageType::PN => ParameterNegotiation::try_from(message).map(|pn| format!("{:?}", pn)),
+        MessageType::NSC => Nsc::try_from(message).map(|nsc| {
+            let kind = if nsc.cr { "command" } else { "response" };
+            format!("{:?} {} not supported", nsc.message_type, kind)
+        }),
+        MessageType::RPN => {
+            RemotePortNegotiation::try_from(message).map(|rpn| format!("{:?}", rpn))
+        }
+        _ => Ok(hex::encode_upper(&message.value)),
+    };
+    match value {
+        Ok(value) => format!("{:?} {}: {}", message.message_type, kind, value),
+        Err(e) => format!("{:?} {}: {}", message.message_type, kind, e),
     }
-    frames
 }
 
-fn main() {
+fn main() -> ExitCode {
     let cli = Cli::parse();
+    let mut summary;
 
     match cli.command {
+        Commands::Generate(args) if args.from_file.is_some() => {
+            summary = Summary::new("generate");
+            let path = args.from_file.unwrap();
+            let csv = path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+            let text = match std::fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(e) => {
+                    eprintln!("Error: {}: {}", path.display(), e);
+                    return ExitCode::from(EXIT_IO_FAILED);
+                }
+            };
+            let frames = match generate_batch(&text, csv) {
+                Ok(frames) => frames,
+                Err(e) => {
+                    eprintln!("Error: {}: {}", path.display(), e);
+                    return ExitCode::from(EXIT_INVALID_ARGS);
+                }
+            };
+            frames.iter().for_each(|frame| {
+                let _ = summary.record(frame);
+            });
+            let stream = join_frames(cli.mode, &frames, args.shared_flags);
+            match args.output_file {
+                Some(output) => {
+                    if let Err(e) = std::fs::write(&output, hex::decode(&stream).unwrap()) {
+                        eprintln!("Error: {}: {}", output.display(), e);
+                        summary.io_failed = true;
+                    }
+                }
+                None => println!("{}", stream.to_lowercase()),
+            }
+        }
         Commands::Generate(args) => {
-            let p = generate(&args.address, &args.control, args.content);
-            println!("{}", p.to_hex_string());
-            println!("{:?}", p);
+            summary = Summary::new("generate");
+            match args.frame.build() {
+                Ok(p) => {
+                    let _ = summary.record(&p);
+                    println!("{}", cli.mode.encode(&p).to_lowercase());
+                    println!("{}", cli.mode.pretty(&p));
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    return ExitCode::from(EXIT_INVALID_ARGS);
+                }
+            }
         }
         Commands::Parse(args) => {
-            if let Some(hexstring) = args.hexstring {
-                let frames = parse(hexstring);
-                for frame in frames {
-                    let verify = match frame.verify() {
-                        Ok(_) => "OK".to_string(),
-                        Err(e) => e.to_string(),
-                    };
-                    println!(
-                        "Origin: {} Verify: {}\n{:?}",
-                        frame.to_hex_string().to_uppercase(),
-                        verify,
-                        frame
-                    );
+            summary = Summary::new("parse");
+            let dialect = args.dialect.dialect();
+            summary.dialect = dialect;
+            let mut stats = match cli.mode {
+                FrameMode::Basic => Stats::new(),
+                FrameMode::Advanced => Stats::advanced(),
+            };
+            if let Some(format) = args.format.log_format() {
+                if let Err(e) = parse_log(&args, format, cli.mode, &mut summary, &mut stats) {
+                    eprintln!("Error: {}", e);
+                    summary.io_failed = true;
+                }
+            } else {
+                match Input::open(args.hexstring, args.file.as_deref()) {
+                    Ok(mut input) => {
+                        let mut mode = cli.mode;
+                        if args.detect_mode {
+                            let detection = FrameParser::detect(input.prefix());
+                            if let Some(detection) = detection {
+                                mode = if detection.advanced {
+                                    FrameMode::Advanced
+                                } else {
+                                    FrameMode::Basic
+                                };
+                            }
+                            let report = describe_detection(detection, mode);
+                            match args.output {
+                                OutputFormat::Text => println!("{}", report),
+                                _ => eprintln!("{}", report),
+                            }
+                        }
+                        let mut records = Records::new(args.output);
+                        let parser = mode.parser(&dialect);
+                        let progress = &mut Progress::redirected(!args.no_progress, input.len);
+                        if mode == FrameMode::Advanced {
+                            stats = Stats::advanced();
+                        }
+                        let result = scan_input(&mut input, parser, progress, |scan, window| {
+                            let scanned = match scan {
+                                Scan::Chunk(chunk) => return stats.record_bytes(chunk, None),
+                                Scan::Region(scanned) => scanned,
+                            };
+                            let (offset, mut frame) = match scanned {
+                                Scanned::Frame { offset, frame } => (offset, frame),
+                                Scanned::Invalid { offset, error, .. } => {
+                                    let error = window.locate(offset, error);
+                                    summary.record_error(&error);
+                                    match args.output {
+                                        OutputFormat::Text => println!("Error: {}", error),
+                                        _ => records.push(&error_to_json(Some(offset), &error)),
+                                    }
+                                    return;
+                                }
+                                Scanned::Skipped { offset, len } => {
+                                    summary.bytes_skipped += len;
+                                    match args.output {
+                                        OutputFormat::Text => {
+                                            println!("Skipped {} bytes at offset {}", len, offset)
+                                        }
+                                        _ => records.push(&skipped_to_json(offset, len)),
+                                    }
+                                    return;
+                                }
+                            };
+                            let fixed = fix_fcs(args.fix_fcs, mode, &dialect, &mut frame);
+                            stats.record(&frame, None, None);
+                            let verify = summary.record_at(&frame, window, offset);
+                            if args.output != OutputFormat::Text {
+                                let record = FrameRecord::new(&frame)
+                                    .with_offset(offset)
+                                    .with_verify(&verify);
+                                let record = match args.payload {
+                                    Some(encoding) => record.with_payload(encoding.into()),
+                                    None => record,
+                                };
+                                records.push(&record.to_string());
+                                return;
+                            }
+                            let verify = match verify {
+                                Ok(_) => "OK".to_string(),
+                                Err(e) => e.to_string(),
+                            };
+                            println!(
+                                "Origin: {} Verify: {}\n{}",
+                                mode.encode_in(&frame, &dialect),
+                                verify,
+                                mode.pretty(&frame)
+                            );
+                            print_fixed(fixed);
+                            if let Ok(messages) = ControlMessage::from_frame(&frame) {
+                                for message in messages {
+                                    println!("Control: {}", describe_control(&message));
+                                }
+                            }
+                            print_payload(args.payload, &frame);
+                        });
+                        records.finish();
+                        if let Err(e) = result {
+                            eprintln!("Error: {}", e);
+                            summary.io_failed = true;
+                        }
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => {
+                        eprintln!("Error: {}", e);
+                        return ExitCode::from(EXIT_INVALID_ARGS);
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        summary.io_failed = true;
+                    }
+                }
+            }
+            if args.stats {
+                let report = format!("\n--- cmux parse statistics ---\n{}", stats);
+                match args.output {
+                    OutputFormat::Text => print!("{}", report),
+                    _ => eprint!("{}", report),
+                }
+            }
+        }
+        Commands::Verify(args) => {
+            summary = Summary::new("verify");
+            summary.dialect = args.dialect.dialect();
+            let result = Input::open(args.hexstring, args.file.as_deref()).and_then(|mut input| {
+                let enabled = !args.no_progress && !args.quiet;
+                let mut progress = Progress::terminal(enabled, input.len);
+                verify(cli.mode, &mut input, &mut summary, &mut progress)
+            });
+            match result {
+                Ok(()) => {
+                    if !args.quiet {
+                        summary.violations.iter().for_each(|v| println!("{}", v));
+                        println!(
+                            "{} valid, {} invalid",
+                            summary.frames_ok, summary.frames_invalid
+                        );
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => {
+                    if !args.quiet {
+                        eprintln!("Error: {}", e);
+                    }
+                    return ExitCode::from(EXIT_INVALID_ARGS);
+                }
+                Err(e) => {
+                    if !args.quiet {
+                        eprintln!("Error: {}", e);
+                    }
+                    summary.io_failed = true;
+                }
+            }
+        }
+        Commands::Fuzz(args) => {
+            summary = Summary::new("fuzz");
+            if args.stress_long {
+                let address = Address::from_bits(args.address);
+                let control = Control::from_bits(args.control);
+                for frame in stress::long_frames(address, control) {
+                    let _ = summary.record(&frame);
+                    println!("{}", cli.mode.encode(&frame));
+                }
+            }
+        }
+        Commands::Closedown(args) => {
+            summary = Summary::new("closedown");
+            match closedown(&args.dlci, args.responder) {
+                Ok(frames) => {
+                    for frame in frames {
+                        let _ = summary.record(&frame);
+                        println!("{}", cli.mode.encode(&frame));
+                    }
+                }
+                Err(e) => {
+                    summary.record_error(&e);
+                    eprintln!("Error: {}", e);
+                }
+            }
+        }
+        Commands::Template(args) => {
+            summary = Summary::new("template");
+            match args.command {
+                TemplateCommand::List => {
+                    for template in templates::TEMPLATES {
+                        println!("{:<12}{}", template.name, template.description);
+                        record_template(template, &mut summary);
+                    }
+                }
+                TemplateCommand::Show { name } => {
+                    let template = templates::find(&name).expect("the name was validated");
+                    if template.wake_flags > 0 {
+                        let flag = match cli.mode {
+                            FrameMode::Basic => FLAG,
+                            FrameMode::Advanced => ADVANCED_FLAG,
+                        };
+                        println!("{}", hex::encode_upper(vec![flag; template.wake_flags]));
+                    }
+                    for frame in record_template(template, &mut summary) {
+                        println!("{}", cli.mode.encode(&frame));
+                    }
+                }
+            }
+        }
+        Commands::Explain(args) => {
+            summary = Summary::new("explain");
+            if cli.mode == FrameMode::Advanced {
+                eprintln!("Error: explain only decodes basic option frames");
+                return ExitCode::from(EXIT_INVALID_ARGS);
+            }
+            let data = match codec::hex_to_bytes(&args.hexstring) {
+                Ok(data) => data,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    return ExitCode::from(EXIT_INVALID_ARGS);
+                }
+            };
+            explain(&data, args.color.enabled());
+            match Frame::from_bytes(data) {
+                Ok(frame) => {
+                    if let Err(e) = summary.record(&frame) {
+                        println!("\nVerify: {}", e);
+                    }
+                }
+                Err(e) => {
+                    summary.record_error(&e);
+                    println!("\nError: {}", e);
+                }
+            }
+        }
+        #[cfg(feature = "serial")]
+        Commands::Ping(args) => {
+            summary = Summary::new("ping");
+            match ping(&args) {
+                Ok(stats) => {
+                    println!("\n--- {} cmux ping statistics ---\n{}", args.port, stats);
+                    summary.frames_ok = stats.received as usize;
+                    summary.frames_invalid = (stats.transmitted - stats.received) as usize;
+                }
+                Err(e) => {
+                    eprintln!("Error: {}: {}", args.port, e);
+                    summary.io_failed = true;
+                }
+            }
+        }
+        #[cfg(feature = "serial")]
+        Commands::Attach(args) => {
+            summary = Summary::new("attach");
+            if let Err(e) = attach(&args, &mut summary) {
+                eprintln!("Error: {}: {}", args.port, e);
+                summary.io_failed = true;
+            }
+        }
+        #[cfg(feature = "serial")]
+        Commands::At(args) => {
+            summary = Summary::new("at");
+            if let Err(e) = at(&args, &mut summary) {
+                eprintln!("Error: {}: {}", args.port, e);
+                summary.io_failed = true;
+            }
+        }
+        #[cfg(feature = "serial")]
+        Commands::Sniff(args) => {
+            summary = Summary::new("sniff");
+            if let Err(e) = sniff(&args, cli.mode, &mut summary) {
+                eprintln!("Error: {}", e);
+                summary.io_failed = true;
+            }
+        }
+        #[cfg(feature = "serial")]
+        Commands::Replay(args) => {
+            summary = Summary::new("replay");
+            let text = match std::fs::read_to_string(&args.file) {
+                Ok(text) => text,
+                Err(e) => {
+                    eprintln!("Error: {}: {}", args.file.display(), e);
+                    return ExitCode::from(EXIT_IO_FAILED);
+                }
+            };
+            let mut frames = match read_recording(&text) {
+                Ok(frames) => frames,
+                Err(e) => {
+                    eprintln!("Error: {}: {}", args.file.display(), e);
+                    return ExitCode::from(EXIT_INVALID_ARGS);
+                }
+            };
+            if let Some(direction) = args.direction {
+                frames.retain(|recorded| recorded.direction == Some(direction.into()));
+            }
+            if let Err(e) = replay(&args, &frames, cli.mode, &mut summary) {
+                eprintln!("Error: {}: {}", args.port, e);
+                summary.io_failed = true;
+            }
+        }
+        #[cfg(feature = "daemon")]
+        Commands::Daemon(args) => {
+            use cmux::daemon::{DaemonConfig, DaemonEvent};
+
+            summary = Summary::new("daemon");
+            let text = match std::fs::read_to_string(&args.config) {
+                Ok(text) => text,
+                Err(e) => {
+                    eprintln!("Error: {}: {}", args.config.display(), e);
+                    return ExitCode::from(EXIT_IO_FAILED);
+                }
+            };
+            let config = match text.parse::<DaemonConfig>() {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!("Error: {}: {}", args.config.display(), e);
+                    return ExitCode::from(EXIT_INVALID_ARGS);
+                }
+            };
+            #[cfg(unix)]
+            if let Err(e) = cmux::daemon::stop_on_signals() {
+                eprintln!("Error: {}", e);
+            }
+            let log = |event: &DaemonEvent| {
+                if matches!(event, DaemonEvent::Data { .. } | DaemonEvent::Urc { .. }) {
+                    summary.frames_ok += 1;
+                }
+                println!("{}", event.to_json(std::time::SystemTime::now()));
+            };
+            #[cfg(feature = "tokio-serial")]
+            let result = if args.asynchronous {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .and_then(|runtime| runtime.block_on(cmux::daemon::run_async(&config, log)))
+            } else {
+                cmux::daemon::run(&config, log)
+            };
+            #[cfg(not(feature = "tokio-serial"))]
+            let result = cmux::daemon::run(&config, log);
+            if let Err(e) = result {
+                eprintln!("Error: {}: {}", config.device, e);
+                summary.io_failed = true;
+            }
+        }
+        Commands::Selftest(args) => {
+            use cmux::selftest::SelfTest;
+
+            summary = Summary::new("selftest");
+            let seed = args.seed.unwrap_or_else(|| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(1, |elapsed| elapsed.as_nanos() as u64)
+            });
+            println!("Seed {}", seed);
+            let mut test = SelfTest::new()
+                .with_dlcis(&args.dlcis)
+                .with_payloads(args.count)
+                .with_max_len(args.max_len as usize)
+                .with_seed(seed);
+            if cli.mode == FrameMode::Advanced {
+                test = test.advanced();
+            }
+            match test.run() {
+                Ok(report) => {
+                    println!("{}", report);
+                    summary.frames_ok = report.payloads;
                 }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    summary.frames_invalid += 1;
+                    summary.violations.push(e.to_string());
+                }
+            }
+        }
+        Commands::Simulate(args) => {
+            summary = Summary::new("simulate");
+            if let Err(e) = simulate(&args, cli.mode, &mut summary) {
+                eprintln!("Error: {}", e);
+                summary.io_failed = true;
             }
         }
     }
+
+    let json = summary.to_json();
+    if cli.summary {
+        eprintln!("{}", json);
+    }
+    if let Some(path) = cli.summary_file {
+        if let Err(e) = std::fs::write(&path, json + "\n") {
+            eprintln!("Failed to write summary to {}: {}", path.display(), e);
+            return ExitCode::from(EXIT_SUMMARY_FAILED);
+        }
+    }
+    ExitCode::from(summary.exit_code())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Decodes `hexstring`, which must hold nothing but valid frames.
+    fn decode_frames(mode: FrameMode, hexstring: &str) -> Vec<Frame> {
+        let mut parser = mode.parser(&Dialect::SPEC);
+        let mut scanned = parser.feed_scanned(&codec::hex_to_bytes(hexstring).unwrap());
+        scanned.extend(parser.finish());
+        scanned
+            .into_iter()
+            .map(|scanned| match scanned {
+                Scanned::Frame { frame, .. } => frame,
+                scanned => panic!("unexpected {:?}", scanned),
+            })
+            .collect()
+    }
+
+    /// Reads every chunk of `input`.
+    fn read_all(mut input: Input) -> Vec<u8> {
+        let mut data = Vec::new();
+        while let Some(chunk) = input.next_chunk().unwrap() {
+            data.extend(chunk);
+        }
+        data
+    }
+
+    #[test]
+    fn test_input() {
+        let text = b"F9 03 3F 01 1C F9\r\n0xF9";
+        let (decoder, bytes) = decode_hex_prefix(text, true).unwrap();
+        assert_eq!(bytes, vec![0xF9, 0x03, 0x3F, 0x01, 0x1C, 0xF9, 0xF9]);
+        assert!(decoder.clone().finish().unwrap().is_empty());
+        assert!(decode_hex_prefix(&[0xF9, 0x03, 0x3F, 0x01, 0x1C, 0xF9], true).is_none());
+        // Text that is not hex, such as an odd number of digits, is parsed as binary.
+        assert!(decode_hex_prefix(b"F90", true).is_none());
+        assert!(decode_hex_prefix(b"F90", false).is_some());
+
+        // Hex text longer than a chunk is decoded as it is read.
+        let mut text = "F9033F011CF9 ".repeat(CHUNK_SIZE / 13 + 1).into_bytes();
+        text.push(b'0');
+        let (decoder, bytes) = decode_hex_prefix(&text[..CHUNK_SIZE], false).unwrap();
+        let mut input = Input {
+            reader: Box::new(std::io::Cursor::new(text[CHUNK_SIZE..].to_vec())),
+            read: Rc::default(),
+            len: None,
+            hex: Some(decoder),
+            pending: Some(bytes),
+            packets: None,
+        };
+        let mut data = Vec::new();
+        let error = loop {
+            match input.next_chunk() {
+                Ok(Some(chunk)) => data.extend(chunk),
+                Ok(None) => panic!("odd number of digits accepted"),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(data.len(), 6 * (CHUNK_SIZE / 13 + 1));
+        assert_eq!(read_all(Input::from_bytes(vec![1, 2])), vec![1, 2]);
+    }
+
+    #[cfg(feature = "serial")]
+    #[test]
+    fn test_read_recording() {
+        use cmux::sniff::Direction;
+        use std::time::Duration;
+
+        let mut frame = generate(Address::default(), Control::default(), None).unwrap();
+        frame.checksum ^= 0xFF;
+        let record = FrameRecord::new(&frame).to_string();
+        let text = format!(
+            "{}\n{}\n{}\n",
+            skipped_to_json(0, 2),
+            record,
+            FrameRecord::new(&frame)
+                .with_seen(Some(Duration::from_millis(1500)), Some(Direction::Rx))
+        );
+        let frames = read_recording(&text).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].frame, frame);
+        assert_eq!(frames[0].timestamp, None);
+        assert_eq!(frames[1].timestamp, Some(Duration::from_millis(1500)));
+        assert_eq!(frames[1].direction, Some(Direction::Rx));
+        let text = format!("[{}]", record.replace(r#""dlci":1"#, r#""dlci":64"#));
+        assert!(read_recording(&text).is_err());
+
+        // The control octet is replayed as recorded, even with bits the frame type leaves out.
+        let frame = generate(Address::default(), Control::from_bits(0xA1), None).unwrap();
+        let record = FrameRecord::new(&frame).to_string();
+        assert_eq!(read_recording(&record).unwrap()[0].frame, frame);
+        let record = record.replace(r#""control":161,"#, "");
+        assert_ne!(
+            read_recording(&record).unwrap()[0].frame.control,
+            frame.control
+        );
+        assert_eq!(parse_speed("2x"), Ok(2.0));
+        assert!(parse_speed("0x").is_err());
+    }
+
+    #[cfg(feature = "mqtt")]
+    #[test]
+    fn test_parse_mqtt_broker() {
+        assert_eq!(parse_mqtt_broker("10.0.0.2:1884").unwrap().port, 1884);
+        assert_eq!(parse_mqtt_broker("localhost").unwrap().port, 1883);
+        assert!(parse_mqtt_broker("localhost:mqtt").is_err());
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut summary = Summary::new("verify");
+        let data = codec::hex_to_bytes("F9033F011CF9 F9073F01DEF9").unwrap();
+        let mut progress = Progress::new(false, None);
+        verify(
+            FrameMode::Basic,
+            &mut Input::from_bytes(data),
+            &mut summary,
+            &mut progress,
+        )
+        .unwrap();
+        assert_eq!((summary.frames_ok, summary.frames_invalid), (2, 0));
+        assert_eq!(summary.exit_code(), EXIT_OK);
+
+        let mut summary = Summary::new("verify");
+        let data = codec::hex_to_bytes("F9033F011CF9 F9073F011CF9").unwrap();
+        let mut progress = Progress::new(false, None);
+        verify(
+            FrameMode::Basic,
+            &mut Input::from_bytes(data),
+            &mut summary,
+            &mut progress,
+        )
+        .unwrap();
+        assert_eq!(summary.violations.len(), 1);
+        assert!(summary.violations[0].starts_with("frame 1: "));
+        assert!(summary.violations[0].ends_with("at offset 6: 3F 01 1C F9 [F9] 07 3F 01 1C F9"));
+        assert_eq!(summary.exit_code(), EXIT_INVALID_FRAMES);
+    }
+
+    #[test]
+    fn test_progress() {
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(3 << 29), "1.5 GiB");
+        let mut progress = Progress::new(false, Some(4 << 20));
+        progress.frames = 7;
+        assert_eq!(
+            progress.line(1 << 20, Duration::from_secs(10)),
+            "1.0 MiB / 4.0 MiB (25%), 7 frames, ETA 0:30"
+        );
+        progress.total = None;
+        assert_eq!(progress.line(512, Duration::ZERO), "512 B read, 7 frames");
+
+        // A run without a terminal draws nothing.
+        let mut progress = Progress::new(false, None);
+        let data = codec::hex_to_bytes("F9033F011CF9").unwrap();
+        let mut input = Input::from_bytes(data);
+        scan_input(&mut input, FrameParser::new(), &mut progress, |_, _| {}).unwrap();
+        assert_eq!(progress.frames, 1);
+        assert!(!progress.shown);
+    }
+
+    #[test]
+    fn test_verify_dialect() {
+        let cli = Cli::try_parse_from([
+            "cmux",
+            "verify",
+            "--flag",
+            "7E",
+            "--no-leading-flag",
+            "--fcs-coverage",
+            "all",
+        ])
+        .unwrap();
+        let Commands::Verify(args) = cli.command else {
+            panic!("not a verify command");
+        };
+        let mut summary = Summary::new("verify");
+        summary.dialect = args.dialect.dialect();
+        // The FCS of the UIH frame covers its content.
+        let data = codec::hex_to_bytes("033F011C7E07EF054154797E").unwrap();
+        let mut progress = Progress::new(false, None);
+        verify(
+            FrameMode::Basic,
+            &mut Input::from_bytes(data),
+            &mut summary,
+            &mut progress,
+        )
+        .unwrap();
+        assert_eq!((summary.frames_ok, summary.frames_invalid), (2, 0));
+        assert_eq!(summary.bytes_skipped, 0);
+    }
+
+    #[test]
+    fn test_fuzz_args() {
+        let cli = Cli::try_parse_from(["cmux", "fuzz", "--stress-long", "-a", "0xF9"]).unwrap();
+        let Commands::Fuzz(args) = cli.command else {
+            unreachable!()
+        };
+        assert_eq!((args.address, args.control), (0xF9, 0xEF));
+        assert!(Cli::try_parse_from(["cmux", "fuzz", "--stress-long", "-a", "zz"]).is_err());
+    }
+
     #[test]
-    fn test_hexstring_to_bytes() {
-        assert_eq!(hexstring_to_bytes("F9010203F9"), vec![249, 1, 2, 3, 249]);
+    fn test_generate_args() {
+        let fields = |args: &[&str]| {
+            let cli = Cli::try_parse_from([&["cmux", "generate"], args].concat()).unwrap();
+            let Commands::Generate(args) = cli.command else {
+                unreachable!()
+            };
+            (
+                args.frame.address().into_bits(),
+                args.frame.control().into_bits(),
+            )
+        };
+        assert_eq!(fields(&[]), (0x07, 0xEF));
+        assert_eq!(
+            fields(&["--dlci", "0", "--type", "SABM", "--cr", "command", "--pf"]),
+            (0x03, 0x3F)
+        );
+        assert_eq!(
+            fields(&["--dlci", "2", "--type", "ua", "--cr", "response", "--pf"]),
+            (0x09, 0x73)
+        );
         assert_eq!(
-            hexstring_to_bytes("F9 01 02 03 F9"),
-            vec![249, 1, 2, 3, 249]
+            fields(&["-a", "0B", "--cr", "command", "--responder"]),
+            (0x09, 0xEF)
         );
+        assert!(Cli::try_parse_from(["cmux", "generate", "--dlci", "64"]).is_err());
+        assert!(Cli::try_parse_from(["cmux", "generate", "-a", "0x1G"]).is_err());
+    }
+
+    #[test]
+    fn test_generate_batch() {
+        let text =
+            "# bring-up\n--dlci 0 --type sabm --cr command --pf\n\n-a 0x07 'AT+CGMI'\n--raw ATZ\n";
+        let frames = generate_batch(text, false).unwrap();
+        let hex: Vec<_> = frames[..2].iter().map(Frame::to_hex_string).collect();
+        assert_eq!(hex, ["f9033f011cf9", "f907ef1341542b43474d490d0ac8f9"]);
+        assert_eq!(frames[2].content.as_bytes(), b"ATZ");
+
+        let csv = "address,control,content\n03,3F,\n07,EF,\"AT,\"\"x\"\"\"\n";
+        let frames = generate_batch(csv, true).unwrap();
+        assert_eq!(frames[0].to_hex_string(), "f9033f011cf9");
+        assert_eq!(frames[1].content.as_bytes(), b"AT,\"x\"\r\n");
+
         assert_eq!(
-            hexstring_to_bytes("F9\n01\n02\n03\nF9"),
-            vec![249, 1, 2, 3, 249]
+            generate_batch("--dlci 0\n--dlci 64\n", false).unwrap_err(),
+            "line 2: invalid value '64' for '--dlci <DLCI>': 64 is not in 0..64"
         );
         assert_eq!(
-            hexstring_to_bytes("0xF9 0x01 0x02 0x03 0xF9"),
-            vec![249, 1, 2, 3, 249]
+            generate_batch("07,XY\n", true).unwrap_err(),
+            "line 1: \"XY\" is not a hex octet"
         );
     }
 
     #[test]
-    fn test_hexbyte_to_bytes() {
-        assert_eq!(hexbyte_to_bytes("F9"), 249);
-        assert_eq!(hexbyte_to_bytes("0xF9"), 249);
+    fn test_shared_flags() {
+        let frames = decode_frames(FrameMode::Basic, "F9033F011CF9 F9073F01DEF9");
+        let stream = join_frames(FrameMode::Basic, &frames, true);
+        assert_eq!(stream, "F9033F011CF9073F01DEF9");
+        assert_eq!(decode_frames(FrameMode::Basic, &stream), frames);
+
+        let stream = join_frames(FrameMode::Advanced, &frames, true);
+        assert_eq!(stream, "7E033FFC7E073F897E");
+        assert_eq!(decode_frames(FrameMode::Advanced, &stream), frames);
     }
 
     #[test]
-    fn test_string_eater() {
-        let s = "F9010203F9\r\nF9010203F9F9010203F9F9";
+    fn test_summary() {
+        let mut summary = Summary::new("parse");
+        assert_eq!(summary.exit_code(), EXIT_NO_FRAMES);
+
+        let frame = generate(
+            Address::default(),
+            Control::default(),
+            Some("AT".to_string()),
+        )
+        .unwrap();
+        assert!(summary.record(&frame).is_ok());
+        assert_eq!(summary.exit_code(), EXIT_OK);
 
-        let (curr, rest) = string_eater(s, "F9").unwrap();
-        assert_eq!(curr, "F9010203F9",);
-        assert_eq!(rest, "\r\nF9010203F9F9010203F9F9",);
+        let mut frame = generate(
+            Address::default(),
+            Control::default(),
+            Some("AT".to_string()),
+        )
+        .unwrap();
+        frame.checksum ^= 0xFF;
+        assert!(summary.record(&frame).is_err());
+        assert_eq!(summary.exit_code(), EXIT_INVALID_FRAMES);
+        assert_eq!(
+            summary.to_json(),
+            r#"{"command":"parse","frames_ok":1,"frames_invalid":1,"bytes_skipped":0,"violations":["frame 1: Checksum is invalid: expected 0x39, found 0xC6"],"exit_code":1}"#
+        );
+
+        summary.io_failed = true;
+        assert_eq!(summary.exit_code(), EXIT_IO_FAILED);
+    }
 
-        let (curr, rest) = string_eater(rest, "F9").unwrap();
-        assert_eq!(curr, "F9010203F9",);
-        assert_eq!(rest, "F9010203F9F9",);
+    #[test]
+    fn test_closedown() {
+        let frames = closedown(&[1], false).unwrap();
+        let hex: Vec<String> = frames.iter().map(|f| f.to_hex_string()).collect();
+        assert_eq!(hex, ["f90753013ff9", "f903ef05c301f2f9"]);
+    }
 
-        let (curr, rest) = string_eater(rest, "F9").unwrap();
-        assert_eq!(curr, "F9010203F9",);
-        assert_eq!(rest, "F9",);
+    #[test]
+    fn test_describe_control() {
+        let frame = decode_frames(FrameMode::Basic, "F903EF09E305070DFBF9").remove(0);
+        let messages = ControlMessage::from_frame(&frame).unwrap();
+        assert_eq!(
+            describe_control(&messages[0]),
+            "MSC command: DLCI=1 FC=0 RTC=1 RTR=1 IC=0 DV=0"
+        );
+        let message = ControlMessage::new(MessageType::MSC, false, vec![0x07]);
+        assert_eq!(
+            describe_control(&message),
+            "MSC response: Control message value is invalid: expected 2 bytes, got 1"
+        );
+    }
 
-        assert_eq!(string_eater(rest, "F9"), None);
+    #[test]
+    fn test_parse_advanced() {
+        let frames = decode_frames(FrameMode::Advanced, "7E033FFC7E 7E 7E07EF7D5E01057E");
+        assert_eq!(frames.len(), 2);
+        assert_eq!(FrameMode::Advanced.encode(&frames[1]), "7E07EF7D5E01057E");
+        assert_eq!(FrameMode::Basic.encode(&frames[0]), "F9033F011CF9");
     }
 
     #[test]
-    fn test_generate() {
-        let frame = generate("7", "EF", "010203".to_string());
-        assert_eq!(frame.to_hex_string(), "f907ef113031303230330d0a2bf9");
+    fn test_pretty_advanced() {
+        let cli = Cli::try_parse_from(["cmux", "--mode", "advanced", "generate", "AT"]).unwrap();
+        let Commands::Generate(args) = cli.command else {
+            unreachable!()
+        };
+        let frame = args.frame.build().unwrap();
+        assert_eq!(cli.mode.encode(&frame), "7E07EF41540D0A057E");
+        let generated = cli.mode.pretty(&frame).to_string();
+        assert!(generated.starts_with("Flag     7E\n"), "{}", generated);
+        assert!(generated.ends_with("FCS      05     OK\nFlag     7E"));
+        assert!(!generated.contains("Length"));
+
+        // The frame parsed back is broken down the same way.
+        let frames = decode_frames(FrameMode::Advanced, "7e07ef41540d0a057e");
+        assert_eq!(
+            FrameMode::Advanced.pretty(&frames[0]).to_string(),
+            generated
+        );
+        assert!(FrameMode::Basic
+            .pretty(&frames[0])
+            .to_string()
+            .contains("Length   09"));
     }
 
     #[test]
@@ -185,7 +2937,7 @@ mod tests {
         F91B3F01D3F9
         F91F3F0111F9
         "#;
-        let frames = parse(str.to_string());
+        let frames = decode_frames(FrameMode::Basic, str);
         assert_eq!(frames.len(), 8);
         let mut i = 0;
         str.to_string().replace(' ', "").split('\n').for_each(|s| {
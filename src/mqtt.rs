@@ -0,0 +1,256 @@
+use crate::codec::parse_hex_stream;
+use crate::error::CodecError;
+use crate::json::FrameRecord;
+use crate::sniff::SniffedFrame;
+use crate::types::Frame;
+use rumqttc::{Client, Event, MqttOptions, Outgoing, Packet, QoS};
+use serde::Deserialize;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Delay before connecting to the broker again after the connection failed.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+fn default_port() -> u16 {
+    1883
+}
+
+fn default_client_id() -> String {
+    "cmux".to_string()
+}
+
+fn default_topic() -> String {
+    "cmux".to_string()
+}
+
+/// MQTT broker the frames are published to, with the `[mqtt]` table of the daemon
+/// configuration
+///
+/// Each frame is published as a JSON record on `{topic}/dlci/{dlci}`, and the frames to
+/// transmit are taken from `{topic}/tx`, as hex text.
+///
+/// # Example
+///
+/// ```
+/// use cmux::mqtt::MqttConfig;
+///
+/// let config = MqttConfig::new("localhost").with_topic("modem");
+/// assert_eq!(config.frame_topic(1), "modem/dlci/1");
+/// assert_eq!(config.command_topic(), "modem/tx");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct MqttConfig {
+    /// Host name or address of the broker. Example: localhost
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default = "default_client_id")]
+    pub client_id: String,
+    /// Prefix of the topics. Example: modem/cmux
+    #[serde(default = "default_topic")]
+    pub topic: String,
+}
+
+impl MqttConfig {
+    /// Creates the configuration of the broker at `host`, on port 1883, with `cmux` as client
+    /// id and topic prefix.
+    pub fn new(host: &str) -> Self {
+        MqttConfig {
+            host: host.to_string(),
+            port: default_port(),
+            client_id: default_client_id(),
+            topic: default_topic(),
+        }
+    }
+
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    pub fn with_client_id(mut self, client_id: &str) -> Self {
+        self.client_id = client_id.to_string();
+        self
+    }
+
+    pub fn with_topic(mut self, topic: &str) -> Self {
+        self.topic = topic.to_string();
+        self
+    }
+
+    /// Returns the topic of the frames of `dlci`.
+    pub fn frame_topic(&self, dlci: u8) -> String {
+        format!("{}/dlci/{}", self.topic, dlci)
+    }
+
+    /// Returns the topic of the frames to transmit.
+    pub fn command_topic(&self) -> String {
+        format!("{}/tx", self.topic)
+    }
+}
+
+/// Returns the JSON record published for `sniffed`, the record of `cmux sniff -o jsonl`.
+///
+/// # Example
+///
+/// ```
+/// use cmux::mqtt::frame_record;
+/// use cmux::sniff::{Direction, SniffedFrame};
+/// use cmux::types::Frame;
+/// use std::time::Duration;
+///
+/// let sniffed = SniffedFrame {
+///     timestamp: Duration::from_millis(1500),
+///     direction: Direction::Tx,
+///     frame: Frame::from_bytes(vec![0xF9, 0x03, 0x3F, 0x01, 0x1C, 0xF9]).unwrap(),
+/// };
+/// let record = frame_record(&sniffed);
+/// assert!(record.starts_with(r#"{"timestamp":1.500000,"direction":"TX","offset":null,"#));
+/// assert!(record.ends_with(r#""hex":""},"checksum":28,"valid":true,"error":null}"#));
+/// ```
+pub fn frame_record(sniffed: &SniffedFrame) -> String {
+    FrameRecord::sniffed(sniffed).to_string()
+}
+
+/// Connection to an MQTT broker publishing frames and receiving the frames to transmit
+///
+/// The connection is driven by a thread, which connects again after the broker went away and
+/// subscribes to the command topic at every connection. Frames are published at most once,
+/// and dropped while the broker is unreachable or the connection is not keeping up; the
+/// dropped records are counted by [`MqttBridge::take_dropped`].
+pub struct MqttBridge {
+    config: MqttConfig,
+    client: Client,
+    commands: Receiver<Vec<u8>>,
+    /// Records dropped since the last call to [`MqttBridge::take_dropped`].
+    dropped: AtomicUsize,
+    /// Set when the bridge is dropped, to end the thread.
+    stopped: Arc<AtomicBool>,
+}
+
+impl MqttBridge {
+    /// Number of requests queued to the connection thread.
+    const CAPACITY: usize = 64;
+
+    /// Starts connecting to the broker.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(MqttBridge)`: The bridge, connected in the background.
+    /// - `Err(io::Error)`: If the connection thread could not be started.
+    pub fn connect(config: &MqttConfig) -> io::Result<Self> {
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        let (client, mut connection) = Client::new(options, Self::CAPACITY);
+        let (sender, commands) = mpsc::channel();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let topic = config.command_topic();
+        let subscriber = client.clone();
+        let stop = stopped.clone();
+        std::thread::Builder::new()
+            .name("cmux-mqtt".to_string())
+            .spawn(move || {
+                for event in connection.iter() {
+                    match event {
+                        Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                            let _ = subscriber.try_subscribe(&topic, QoS::AtMostOnce);
+                        }
+                        Ok(Event::Incoming(Packet::Publish(publish))) if publish.topic == topic => {
+                            if sender.send(publish.payload.to_vec()).is_err() {
+                                break;
+                            }
+                        }
+                        Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+                        Ok(_) => {}
+                        Err(_) if stop.load(Ordering::SeqCst) => break,
+                        Err(_) => std::thread::sleep(RECONNECT_DELAY),
+                    }
+                }
+            })?;
+        Ok(MqttBridge {
+            config: config.clone(),
+            client,
+            commands,
+            dropped: AtomicUsize::new(0),
+            stopped,
+        })
+    }
+
+    /// Publishes the record of `sniffed` on the topic of its DLCI, or drops it if the
+    /// connection is not keeping up.
+    pub fn publish(&self, sniffed: &SniffedFrame) {
+        let topic = self
+            .config
+            .frame_topic(sniffed.frame.address.dlci().into_bits());
+        let record = frame_record(sniffed);
+        if self
+            .client
+            .try_publish(topic, QoS::AtMostOnce, false, record)
+            .is_err()
+        {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the number of records dropped by [`MqttBridge::publish`] since the last call,
+    /// because the queue to the connection thread was full.
+    pub fn take_dropped(&self) -> usize {
+        self.dropped.swap(0, Ordering::Relaxed)
+    }
+
+    /// Takes the next frames received on the command topic, as hex text of one or more
+    /// basic option frames.
+    ///
+    /// # Returns
+    ///
+    /// - `Some(Ok(Vec<Frame>))`: The frames of the next command.
+    /// - `Some(Err(CodecError))`: If the next command is not hex text of frames.
+    /// - `None`: If no command is waiting.
+    pub fn poll_command(&self) -> Option<Result<Vec<Frame>, CodecError>> {
+        let payload = self.commands.try_recv().ok()?;
+        Some(parse_hex_stream(&String::from_utf8_lossy(&payload)))
+    }
+}
+
+impl std::fmt::Debug for MqttBridge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MqttBridge")
+            .field("config", &self.config)
+            .field("dropped", &self.dropped)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for MqttBridge {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        let _ = self.client.try_disconnect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sniff::Direction;
+
+    #[test]
+    fn test_unreachable_broker() {
+        // Nothing listens on port 1: the queue fills up, frames are dropped and no command
+        // arrives.
+        let bridge = MqttBridge::connect(&MqttConfig::new("127.0.0.1").with_port(1)).unwrap();
+        let sniffed = SniffedFrame {
+            timestamp: Duration::ZERO,
+            direction: Direction::Rx,
+            frame: parse_hex_stream("F9073F01DEF9").unwrap().remove(0),
+        };
+        for _ in 0..MqttBridge::CAPACITY * 2 {
+            bridge.publish(&sniffed);
+        }
+        assert!(bridge.take_dropped() > 0);
+        assert_eq!(bridge.take_dropped(), 0);
+        assert!(bridge.poll_command().is_none());
+    }
+}
@@ -0,0 +1,1787 @@
+use crate::anomaly::Anomaly;
+use crate::at::UrcFilter;
+use crate::control::{
+    ControlMessage, ConvergenceLayer, MessageType, Msc, Nsc, ParameterNegotiation, V24Signals,
+};
+use crate::convergence::{segment, Cl2Status, Reassembler};
+use crate::error::MuxError;
+use crate::ping::test_pattern;
+use crate::scheduler::Scheduler;
+use crate::session::Session;
+use crate::types::{Address, Control, Frame, FrameBuilder, FrameType, DLCI, MAX_CONTENT_LENGTH};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Default acknowledgement timer T1 of 100 ms.
+pub const DEFAULT_T1: Duration = Duration::from_millis(100);
+
+/// Default response timer T2 of the control channel, 300 ms.
+pub const DEFAULT_T2: Duration = Duration::from_millis(300);
+
+/// Default wake-up response timer T3 of 10 s.
+pub const DEFAULT_T3: Duration = Duration::from_secs(10);
+
+/// Default maximum number of retransmissions N2.
+pub const DEFAULT_N2: u8 = 3;
+
+/// Default number of keep-alive Test commands left unanswered in a row before the peer is
+/// deemed hung.
+pub const DEFAULT_KEEP_ALIVE_MISSES: u8 = 3;
+
+/// Default interval between two attempts to write to a blocked transport, 10 ms.
+pub const DEFAULT_WRITE_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long and how often the frames are written again when they cannot be sent
+///
+/// * Retries: how many times [`Link`](crate::transport::Link) writes a frame again, after
+///   waiting for the retry interval, when the transport reports `WouldBlock`, `TimedOut` or
+///   `Interrupted`.
+/// * Deadline: how long a frame may wait, on a blocked transport or while the peer has
+///   stopped the data with FCoff, before the write fails with [`MuxError::WriteTimeout`]. The
+///   asynchronous driver waits for the transport up to the deadline, and the engine drops
+///   the data held by FCoff for longer.
+///
+/// By default, a blocked write fails at once and FCoff holds the data until FCon.
+///
+/// # Example
+///
+/// ```
+/// use cmux::mux::{MuxConfig, WritePolicy};
+/// use std::time::Duration;
+///
+/// let policy = WritePolicy::default()
+///     .with_retries(5)
+///     .with_deadline(Duration::from_secs(2));
+/// let config = MuxConfig::default().with_write_policy(policy);
+/// assert_eq!(config.write.retry_interval, Duration::from_millis(10));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WritePolicy {
+    pub retries: u32,
+    pub retry_interval: Duration,
+    pub deadline: Option<Duration>,
+}
+
+impl Default for WritePolicy {
+    fn default() -> Self {
+        WritePolicy {
+            retries: 0,
+            retry_interval: DEFAULT_WRITE_RETRY_INTERVAL,
+            deadline: None,
+        }
+    }
+}
+
+impl WritePolicy {
+    pub const fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    pub const fn with_retry_interval(mut self, interval: Duration) -> Self {
+        self.retry_interval = interval;
+        self
+    }
+
+    pub const fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Returns true if a write started at `start` may be tried again at `now`, after
+    /// `retries` retries.
+    pub fn may_retry(&self, retries: u32, start: Instant, now: Instant) -> bool {
+        retries < self.retries
+            && self
+                .deadline
+                .is_none_or(|deadline| now + self.retry_interval <= start + deadline)
+    }
+}
+
+/// Timers and retry counter of [`Mux`]
+///
+/// * T1: acknowledgement timer of SABM and DISC commands.
+/// * T2: response timer of the control commands sent on DLCI 0, such as MSC.
+/// * T3: wake-up response timer, started by [`Mux::wake_up`].
+/// * N2: maximum number of retransmissions when T1 or T2 expires.
+/// * Keep-alive: interval of the Test commands sent on DLCI 0 to detect a hung peer, and the
+///   number of them left unanswered in a row after which the session is torn down. Disabled
+///   by default.
+/// * Write: the [`WritePolicy`] of the frames that cannot be sent.
+///
+/// The defaults are the values of 07.10.
+///
+/// # Example
+///
+/// ```
+/// use cmux::mux::{Mux, MuxConfig};
+/// use std::time::Duration;
+///
+/// let config = MuxConfig::default().with_t1(Duration::from_millis(200)).with_n2(5);
+/// let mux = Mux::new().with_config(config);
+/// assert_eq!(mux.config().t2, Duration::from_millis(300));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MuxConfig {
+    pub t1: Duration,
+    pub t2: Duration,
+    pub t3: Duration,
+    pub n2: u8,
+    pub keep_alive: Option<Duration>,
+    pub keep_alive_misses: u8,
+    pub write: WritePolicy,
+}
+
+impl Default for MuxConfig {
+    fn default() -> Self {
+        MuxConfig {
+            t1: DEFAULT_T1,
+            t2: DEFAULT_T2,
+            t3: DEFAULT_T3,
+            n2: DEFAULT_N2,
+            keep_alive: None,
+            keep_alive_misses: DEFAULT_KEEP_ALIVE_MISSES,
+            write: WritePolicy::default(),
+        }
+    }
+}
+
+impl MuxConfig {
+    pub const fn with_t1(mut self, t1: Duration) -> Self {
+        self.t1 = t1;
+        self
+    }
+
+    pub const fn with_t2(mut self, t2: Duration) -> Self {
+        self.t2 = t2;
+        self
+    }
+
+    pub const fn with_t3(mut self, t3: Duration) -> Self {
+        self.t3 = t3;
+        self
+    }
+
+    pub const fn with_n2(mut self, n2: u8) -> Self {
+        self.n2 = n2;
+        self
+    }
+
+    pub const fn with_keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = Some(interval);
+        self
+    }
+
+    pub const fn with_keep_alive_misses(mut self, misses: u8) -> Self {
+        self.keep_alive_misses = misses;
+        self
+    }
+
+    pub const fn with_write_policy(mut self, policy: WritePolicy) -> Self {
+        self.write = policy;
+        self
+    }
+}
+
+/// State of a data link connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DlcState {
+    #[default]
+    Closed,
+    /// SABM was sent and UA is awaited.
+    Connecting,
+    Connected,
+    /// DISC was sent and UA is awaited.
+    Disconnecting,
+}
+
+/// Power saving state of the multiplexer, returned by [`Mux::power_state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PowerState {
+    #[default]
+    Active,
+    /// PSC was sent and its response is awaited.
+    Entering,
+    /// Both stations are in power saving mode.
+    Sleeping,
+    /// Wake-up flags are sent and the flags of the peer are awaited for T3.
+    WakingUp,
+}
+
+impl PowerState {
+    /// Returns the label used for this state in reports.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            PowerState::Active => "active",
+            PowerState::Entering => "entering",
+            PowerState::Sleeping => "sleeping",
+            PowerState::WakingUp => "waking_up",
+        }
+    }
+}
+
+/// Control lines of the port carrying the multiplexer, reported by the driver running the
+/// engine as [`MuxEvent::LineStatus`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LineStatus {
+    /// The lines driven by the modem: DSR as RTC, CTS as RTR, RI as IC and DCD as DV.
+    pub signals: V24Signals,
+    /// Number of breaks detected on the port since it was opened.
+    pub breaks: u32,
+}
+
+/// Event raised by [`Mux`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MuxEvent {
+    /// The DLC is connected, by either station.
+    DlcOpened(u8),
+    /// The DLC is closed, by either station.
+    DlcClosed(u8),
+    /// Data received in UIH or UI frames on a connected DLC other than 0, reassembled up to
+    /// the first frame shorter than N1.
+    Data {
+        dlci: u8,
+        data: Vec<u8>,
+    },
+    /// The peer sent its V.24 signals in an MSC command, which was answered, or changed them
+    /// in the status octets of a Type 2 convergence layer frame.
+    ModemStatus(Msc),
+    /// Unsolicited result code received on an AT channel outside of a command, such as `RING`
+    /// or `+CMTI: "SM",3`.
+    Urc {
+        dlci: u8,
+        line: String,
+    },
+    /// The bytes or frames received follow a pattern of a failing link, found by the
+    /// [`AnomalyDetector`](crate::anomaly::AnomalyDetector) of the driver running the engine.
+    Anomaly(Anomaly),
+    /// The power saving state changed, after a PSC exchange, wake-up flags or T3.
+    PowerState(PowerState),
+    /// The peer stopped (FCoff, `enabled` false) or resumed (FCon) the data on every DLC
+    /// but 0.
+    FlowControl {
+        enabled: bool,
+    },
+    /// The parser of the driver running the engine dropped this many bytes read: noise
+    /// between the frames, and frames that do not decode or whose FCS is wrong.
+    Skipped(usize),
+    /// The control lines of the port carrying the multiplexer, first read or changed, as
+    /// watched by the driver running the engine.
+    LineStatus(LineStatus),
+    Error(MuxError),
+}
+
+/// Control command sent on DLCI 0 and awaiting its response.
+#[derive(Debug)]
+struct PendingControl {
+    message_type: MessageType,
+    frame: Frame,
+    retries: u8,
+    deadline: Instant,
+}
+
+/// Callback set with [`Mux::on_closed`] or [`Mux::on_modem_status`].
+struct Callback<T>(Box<dyn FnMut(T) + Send>);
+
+impl<T> std::fmt::Debug for Callback<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Callback")
+    }
+}
+
+/// Keep-alive Test commands of [`Mux`].
+#[derive(Debug, Default)]
+struct KeepAlive {
+    /// When the next Test command is sent.
+    due: Option<Instant>,
+    /// The value octets of the Test command awaiting its response.
+    pending: Option<Vec<u8>>,
+    seq: u32,
+    /// The number of Test commands left unanswered in a row.
+    missed: u8,
+}
+
+/// Per-DLC state of [`Mux`].
+#[derive(Debug, Default)]
+struct Dlc {
+    state: DlcState,
+    /// The command awaiting a response, sent again when T1 expires.
+    pending: Option<Frame>,
+    retries: u8,
+    deadline: Option<Instant>,
+    /// The V.24 signals sent in the status octets of Type 2 convergence layer frames.
+    signals: V24Signals,
+    /// The status octets last received in a Type 2 convergence layer frame.
+    status: Option<Cl2Status>,
+    /// The V.24 signals last received from the peer, in MSC or Type 2 status octets.
+    received: Option<V24Signals>,
+}
+
+/// Multiplexer engine
+///
+/// `Mux` tracks the state of every DLC and drives the SABM/UA/DM/DISC exchanges. It performs
+/// no I/O: received frames are passed to [`Mux::receive`], frames to transmit are taken from
+/// [`Mux::poll_transmit`], events from [`Mux::poll_event`], and [`Mux::poll_timeout`] must be
+/// called when [`Mux::next_timeout`] is reached to retransmit commands up to N2 times. The
+/// timers are set with [`MuxConfig`].
+///
+/// Control messages and data on DLCI 0 are handled by the embedded [`Session`].
+///
+/// Frames without information field and frames on DLCI 0 are transmitted first, in order.
+/// Data passed to [`Mux::send`] on other DLCIs goes through a [`Scheduler`], which serves
+/// DLCIs by priority. Priorities are taken from the PN responses of the peer, or set with
+/// [`Mux::set_priority`].
+///
+/// Data longer than the maximum frame size N1 of the DLC is split across UIH frames of N1
+/// octets, and received frames of N1 octets are held until the message ends with a shorter
+/// frame, so [`MuxEvent::Data`] carries whole messages. A message that fills its last frame
+/// is ended by an empty frame. N1 is taken from the PN responses of the peer, or set with
+/// [`Mux::set_parameters`].
+///
+/// On a DLC negotiated with the Type 2 convergence layer, every data frame starts with status
+/// octets: the engine inserts the signals last set with [`Mux::set_modem_status`], RTC and
+/// RTR until then, and strips the received ones, raising [`MuxEvent::ModemStatus`] when they
+/// change.
+///
+/// On a DLC negotiated with the Type 4 convergence layer, data is split into segments whose
+/// B and F bits mark the messages, instead of relying on frames shorter than N1.
+///
+/// With a deadline in the [`WritePolicy`] of [`MuxConfig`], the data held while the peer has
+/// stopped it with FCoff is dropped once the deadline is past, raising
+/// [`MuxError::WriteTimeout`] for each DLCI, and again at every deadline while FCoff lasts.
+///
+/// With a keep-alive interval in [`MuxConfig`], a Test command is sent on DLCI 0 at every
+/// interval while it is connected. When the peer leaves as many of them unanswered in a row
+/// as the configured misses, [`MuxError::Unresponsive`] is raised and every DLC is closed
+/// without waiting for the peer, so the application can start a new session.
+///
+/// On an AT channel set with [`Mux::set_at_channel`], the lines received outside of the
+/// commands sent with [`Mux::send_command`] are raised as [`MuxEvent::Urc`] instead of
+/// [`MuxEvent::Data`], so the responses are not mixed with the unsolicited result codes.
+///
+/// [`Mux::sleep`] sends PSC and enters power saving mode once the peer answers, as it does
+/// when the peer sends PSC. While sleeping, the driver sends flags after [`Mux::wake_up`]
+/// until the peer answers with flags, and answers the flags of the peer, reporting them with
+/// [`Mux::wake_up_received`]. Any frame received also ends power saving mode. Every change is
+/// raised as [`MuxEvent::PowerState`].
+///
+/// # Example
+///
+/// ```
+/// use cmux::mux::{DlcState, Mux, MuxEvent};
+/// use std::time::Instant;
+///
+/// let now = Instant::now();
+/// let mut host = Mux::new();
+/// let mut modem = Mux::new().with_initiator(false);
+/// host.open(0, now).unwrap();
+/// assert_eq!(host.state(0), DlcState::Connecting);
+///
+/// let sabm = host.poll_transmit().unwrap();
+/// modem.receive(&sabm, now);
+/// let ua = modem.poll_transmit().unwrap();
+/// host.receive(&ua, now);
+/// assert_eq!(host.state(0), DlcState::Connected);
+/// assert_eq!(host.poll_event(), Some(MuxEvent::DlcOpened(0)));
+/// ```
+#[derive(Debug)]
+pub struct Mux {
+    config: MuxConfig,
+    session: Session,
+    dlcs: BTreeMap<u8, Dlc>,
+    outbox: VecDeque<Frame>,
+    scheduler: Scheduler,
+    /// The parameters accepted by the peer, by DLCI.
+    parameters: BTreeMap<u8, ParameterNegotiation>,
+    /// The data received in frames of N1 octets, by DLCI, awaiting the end of the message.
+    partial: BTreeMap<u8, Vec<u8>>,
+    /// The messages received in Type 4 segments, by DLCI.
+    segments: BTreeMap<u8, Reassembler>,
+    /// The AT channels, by DLCI.
+    at_channels: BTreeMap<u8, UrcFilter>,
+    controls: VecDeque<PendingControl>,
+    keep_alive: KeepAlive,
+    events: VecDeque<MuxEvent>,
+    /// Set by [`Mux::shutdown`] until every DLC is closed.
+    shutting_down: bool,
+    /// When the data held by FCoff is dropped, under the deadline of [`WritePolicy`].
+    flow_deadline: Option<Instant>,
+    power: PowerState,
+    /// When T3 expires during [`PowerState::WakingUp`].
+    wake_deadline: Option<Instant>,
+    on_closed: Option<Callback<u8>>,
+    on_modem_status: Option<Callback<Msc>>,
+}
+
+impl Default for Mux {
+    fn default() -> Self {
+        Mux {
+            config: MuxConfig::default(),
+            session: Session::new(),
+            dlcs: BTreeMap::new(),
+            outbox: VecDeque::new(),
+            scheduler: Scheduler::new(),
+            parameters: BTreeMap::new(),
+            partial: BTreeMap::new(),
+            segments: BTreeMap::new(),
+            at_channels: BTreeMap::new(),
+            controls: VecDeque::new(),
+            keep_alive: KeepAlive::default(),
+            events: VecDeque::new(),
+            shutting_down: false,
+            flow_deadline: None,
+            power: PowerState::Active,
+            wake_deadline: None,
+            on_closed: None,
+            on_modem_status: None,
+        }
+    }
+}
+
+impl Mux {
+    /// Control commands received on DLCI 0 that [`Mux::receive`] answers itself, or with its
+    /// [`Session`]. A peer built on the engine answers the other commands.
+    pub const ANSWERED: [MessageType; 4] = [
+        MessageType::MSC,
+        MessageType::PSC,
+        MessageType::FCon,
+        MessageType::FCoff,
+    ];
+
+    /// Creates an engine for the initiating station with the default [`MuxConfig`].
+    pub fn new() -> Self {
+        Mux::default()
+    }
+
+    /// Sets whether this station started the multiplexer, which decides the C/R bits.
+    pub fn with_initiator(mut self, initiator: bool) -> Self {
+        self.session = self.session.with_initiator(initiator);
+        self
+    }
+
+    /// Sets the timers and the retry counter.
+    pub fn with_config(mut self, config: MuxConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Sets the acknowledgement timer T1.
+    pub fn with_t1(mut self, t1: Duration) -> Self {
+        self.config.t1 = t1;
+        self
+    }
+
+    /// Sets the maximum number of retransmissions N2.
+    pub fn with_n2(mut self, n2: u8) -> Self {
+        self.config.n2 = n2;
+        self
+    }
+
+    /// Returns the timers and the retry counter.
+    pub fn config(&self) -> &MuxConfig {
+        &self.config
+    }
+
+    /// Returns the session handling control messages and flow control.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Returns the session handling control messages and flow control.
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    /// Sets the transmit priority of `dlci`, from 0 (highest) to 63 (lowest).
+    pub fn set_priority(&mut self, dlci: u8, priority: u8) {
+        self.scheduler.set_priority(dlci, priority);
+    }
+
+    /// Returns the transmit priority of `dlci`.
+    pub fn priority(&self, dlci: u8) -> u8 {
+        self.scheduler.priority(dlci)
+    }
+
+    /// Sets the parameters of the DLC, such as its maximum frame size N1 and its priority.
+    pub fn set_parameters(&mut self, pn: ParameterNegotiation) {
+        self.scheduler.set_priority(pn.dlci, pn.priority);
+        self.parameters.insert(pn.dlci & 0x3F, pn);
+    }
+
+    /// Returns the parameters of `dlci`, or the defaults of [`ParameterNegotiation::new`] if
+    /// none were negotiated.
+    pub fn parameters(&self, dlci: u8) -> ParameterNegotiation {
+        let dlci = dlci & 0x3F;
+        match self.parameters.get(&dlci) {
+            Some(pn) => *pn,
+            None => ParameterNegotiation::new(dlci),
+        }
+    }
+
+    /// Sets whether `dlci` carries AT commands, whose unsolicited result codes are raised as
+    /// [`MuxEvent::Urc`].
+    pub fn set_at_channel(&mut self, dlci: u8, enabled: bool) {
+        let dlci = dlci & 0x3F;
+        if enabled {
+            self.at_channels.entry(dlci).or_default();
+        } else {
+            self.at_channels.remove(&dlci);
+        }
+    }
+
+    /// Returns the maximum number of octets in the information field of a frame on `dlci`.
+    fn n1(&self, dlci: u8) -> usize {
+        usize::from(self.parameters(dlci).n1).clamp(1, MAX_CONTENT_LENGTH)
+    }
+
+    /// Sets the callback called with the DLCI of every DLC closed by either station, when the
+    /// [`MuxEvent::DlcClosed`] event is raised.
+    ///
+    /// The callback runs inside the engine, so it cannot call it back: blocking applications
+    /// use it to mark the channel for reconnection in their own loop.
+    pub fn on_closed(&mut self, callback: impl FnMut(u8) + Send + 'static) {
+        self.on_closed = Some(Callback(Box::new(callback)));
+    }
+
+    /// Sets the callback called with the V.24 signals the peer sends on a DLC, when the
+    /// [`MuxEvent::ModemStatus`] event is raised, so dial-up applications can follow RI and
+    /// DCD with [`V24Signals::ri`] and [`V24Signals::dcd`].
+    pub fn on_modem_status(&mut self, callback: impl FnMut(Msc) + Send + 'static) {
+        self.on_modem_status = Some(Callback(Box::new(callback)));
+    }
+
+    /// Returns the V.24 signals last received from the peer on `dlci`, or `None` if it sent
+    /// none since the DLC was connected.
+    pub fn modem_status(&self, dlci: u8) -> Option<V24Signals> {
+        self.dlcs.get(&(dlci & 0x3F)).and_then(|dlc| dlc.received)
+    }
+
+    /// Returns the state of `dlci`.
+    pub fn state(&self, dlci: u8) -> DlcState {
+        self.dlcs
+            .get(&(dlci & 0x3F))
+            .map_or(DlcState::Closed, |dlc| dlc.state)
+    }
+
+    /// Builds a frame without information field on `dlci`.
+    fn frame(&self, dlci: u8, frame_type: FrameType, command: bool) -> Result<Frame, MuxError> {
+        // Commands carry C/R 1 from the initiator, responses carry C/R 1 from the responder.
+        let cr = command == self.session.initiator();
+        let frame = FrameBuilder::default()
+            .with_address(
+                Address::default()
+                    .with_cr(cr)
+                    .with_dlci(DLCI::from_bits(dlci)),
+            )
+            .with_control(Control::default().with_frame_type(frame_type).with_pf(true))
+            .build()?;
+        Ok(frame)
+    }
+
+    /// Sends a command on `dlci` and starts T1.
+    fn command(
+        &mut self,
+        dlci: u8,
+        frame_type: FrameType,
+        state: DlcState,
+        now: Instant,
+    ) -> Result<(), MuxError> {
+        let frame = self.frame(dlci, frame_type, true)?;
+        self.outbox.push_back(frame.clone());
+        let dlc = self.dlcs.entry(dlci).or_default();
+        dlc.state = state;
+        dlc.pending = Some(frame);
+        dlc.retries = 0;
+        dlc.deadline = Some(now + self.config.t1);
+        Ok(())
+    }
+
+    /// Sends a response on `dlci`.
+    fn respond(&mut self, dlci: u8, frame_type: FrameType) {
+        match self.frame(dlci, frame_type, false) {
+            Ok(frame) => self.outbox.push_back(frame),
+            Err(e) => self.events.push_back(MuxEvent::Error(e)),
+        }
+    }
+
+    /// Moves `dlci` to `state`, raising the matching event. Only a DLC that was connected
+    /// raises [`MuxEvent::DlcClosed`].
+    fn transition(&mut self, dlci: u8, state: DlcState) {
+        let dlc = self.dlcs.entry(dlci).or_default();
+        let previous = dlc.state;
+        dlc.state = state;
+        dlc.pending = None;
+        dlc.deadline = None;
+        if state == DlcState::Connected && previous != DlcState::Connected {
+            dlc.signals = V24Signals::new().with_rtc(true).with_rtr(true);
+            dlc.status = None;
+            dlc.received = None;
+        }
+        match state {
+            DlcState::Connected if previous != DlcState::Connected => {
+                self.session.open(dlci);
+                self.events.push_back(MuxEvent::DlcOpened(dlci));
+            }
+            DlcState::Closed
+                if matches!(previous, DlcState::Connected | DlcState::Disconnecting) =>
+            {
+                self.session.close(dlci);
+                self.scheduler.clear(dlci);
+                self.partial.remove(&dlci);
+                self.segments.remove(&dlci);
+                if let Some(filter) = self.at_channels.get_mut(&dlci) {
+                    *filter = UrcFilter::new();
+                }
+                self.events.push_back(MuxEvent::DlcClosed(dlci));
+                if let Some(Callback(callback)) = &mut self.on_closed {
+                    callback(dlci);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Opens `dlci` by sending SABM. DLCI 0 must be opened before any other DLCI.
+    pub fn open(&mut self, dlci: u8, now: Instant) -> Result<(), MuxError> {
+        self.command(dlci & 0x3F, FrameType::SABM, DlcState::Connecting, now)
+    }
+
+    /// Closes `dlci` by sending DISC.
+    pub fn close(&mut self, dlci: u8, now: Instant) -> Result<(), MuxError> {
+        self.command(dlci & 0x3F, FrameType::DISC, DlcState::Disconnecting, now)
+    }
+
+    /// Closes the multiplexer: every DLC other than 0 is closed with DISC, then DLCI 0 once
+    /// they are all closed or timed out, so the teardown takes at most two rounds of T1 and
+    /// N2 retransmissions.
+    ///
+    /// [`Mux::is_idle`] returns true once the teardown is over.
+    pub fn shutdown(&mut self, now: Instant) {
+        self.shutting_down = true;
+        let dlcis: Vec<u8> = self
+            .dlcis()
+            .filter(|&dlci| dlci != 0 && self.state(dlci) == DlcState::Connected)
+            .collect();
+        for dlci in dlcis {
+            let _ = self.close(dlci, now);
+        }
+        self.poll_shutdown(now);
+    }
+
+    /// Returns true if no DLC is connected or awaiting a response to SABM or DISC.
+    pub fn is_idle(&self) -> bool {
+        self.dlcs.values().all(|dlc| dlc.state == DlcState::Closed)
+    }
+
+    /// Returns the DLCIs known to the engine, in ascending order.
+    fn dlcis(&self) -> impl Iterator<Item = u8> + '_ {
+        self.dlcs.keys().copied()
+    }
+
+    /// Closes DLCI 0 once [`Mux::shutdown`] closed the other DLCs.
+    fn poll_shutdown(&mut self, now: Instant) {
+        if !self.shutting_down {
+            return;
+        }
+        if self
+            .dlcis()
+            .any(|dlci| dlci != 0 && self.state(dlci) != DlcState::Closed)
+        {
+            return;
+        }
+        match self.state(0) {
+            DlcState::Connected => {
+                let _ = self.close(0, now);
+            }
+            DlcState::Closed => self.shutting_down = false,
+            _ => {}
+        }
+    }
+
+    /// Sends `data` in UIH frames of at most N1 octets on a connected `dlci`, subject to flow
+    /// control.
+    pub fn send(&mut self, dlci: u8, data: &[u8]) -> Result<(), MuxError> {
+        let dlci = dlci & 0x3F;
+        if self.state(dlci) != DlcState::Connected {
+            return Err(MuxError::NotConnected { dlci });
+        }
+        let contents = self.split(dlci, data);
+        let mut frames = Vec::with_capacity(contents.len());
+        for content in contents {
+            frames.extend(self.session.send(dlci, content)?);
+        }
+        self.scheduler.extend(frames);
+        Ok(())
+    }
+
+    /// Sends the AT `command`, without its trailing carriage return, on a connected `dlci`.
+    ///
+    /// On an AT channel, the data received until the final result code is the response and is
+    /// raised as [`MuxEvent::Data`].
+    pub fn send_command(&mut self, dlci: u8, command: &str) -> Result<(), MuxError> {
+        let dlci = dlci & 0x3F;
+        self.send(dlci, format!("{}\r", command).as_bytes())?;
+        if let Some(filter) = self.at_channels.get_mut(&dlci) {
+            filter.begin();
+        }
+        Ok(())
+    }
+
+    /// Splits `data` into the information fields of frames of at most N1 octets, adding the
+    /// octets of the convergence layer of `dlci`.
+    fn split(&self, dlci: u8, data: &[u8]) -> Vec<Vec<u8>> {
+        let status = match self.parameters(dlci).convergence_layer {
+            ConvergenceLayer::Type2 => self.dlcs.get(&dlci).map(|dlc| Cl2Status::new(dlc.signals)),
+            ConvergenceLayer::Type4 => return segment(data, self.n1(dlci)),
+            _ => None,
+        };
+        let header = status.map_or(0, |status| status.encoded_len());
+        let size = self.n1(dlci).saturating_sub(header).max(1);
+        let mut chunks: Vec<&[u8]> = data.chunks(size).collect();
+        if data.len().is_multiple_of(size) {
+            chunks.push(&[]);
+        }
+        chunks
+            .into_iter()
+            .map(|chunk| match status {
+                Some(status) => status.encode(chunk),
+                None => chunk.to_vec(),
+            })
+            .collect()
+    }
+
+    /// Handles a frame received from the peer.
+    pub fn receive(&mut self, frame: &Frame, now: Instant) {
+        let dlci = frame.address.dlci().into_bits();
+        let state = self.state(dlci);
+        if matches!(self.power, PowerState::Sleeping | PowerState::WakingUp) {
+            self.set_power(PowerState::Active);
+        }
+        match frame.control.frame_type() {
+            FrameType::SABM => {
+                self.respond(dlci, FrameType::UA);
+                self.transition(dlci, DlcState::Connected);
+            }
+            FrameType::DISC => {
+                if state == DlcState::Closed {
+                    self.respond(dlci, FrameType::DM);
+                } else {
+                    self.respond(dlci, FrameType::UA);
+                    self.transition(dlci, DlcState::Closed);
+                }
+            }
+            FrameType::UA => match state {
+                DlcState::Connecting => self.transition(dlci, DlcState::Connected),
+                DlcState::Disconnecting => self.transition(dlci, DlcState::Closed),
+                _ => {}
+            },
+            FrameType::DM => {
+                if matches!(state, DlcState::Connecting | DlcState::Disconnecting) {
+                    self.events
+                        .push_back(MuxEvent::Error(MuxError::Rejected { dlci }));
+                }
+                self.transition(dlci, DlcState::Closed);
+            }
+            FrameType::UIH | FrameType::UI if dlci == 0 => {
+                self.receive_modem_status(frame);
+                self.receive_parameters(frame);
+                self.receive_power_saving(frame);
+                self.receive_responses(frame);
+                let enabled = self.session.flow_enabled();
+                match self.session.receive(frame) {
+                    Ok(frames) => frames.into_iter().for_each(|frame| self.transmit(frame)),
+                    Err(e) => self.events.push_back(MuxEvent::Error(e.into())),
+                }
+                if self.session.flow_enabled() != enabled {
+                    self.events
+                        .push_back(MuxEvent::FlowControl { enabled: !enabled });
+                }
+                self.flow_deadline = match self.config.write.deadline {
+                    Some(deadline) if !self.session.flow_enabled() => {
+                        Some(self.flow_deadline.unwrap_or(now + deadline))
+                    }
+                    _ => None,
+                };
+            }
+            FrameType::UIH | FrameType::UI if state == DlcState::Connected => {
+                self.receive_data(dlci, frame.content.as_bytes());
+            }
+            _ => {}
+        }
+        self.poll_timeout(now);
+    }
+
+    /// Strips the convergence layer octets of `content` and reassembles the data up to the
+    /// first frame shorter than N1, or the segment with F set.
+    fn receive_data(&mut self, dlci: u8, content: &[u8]) {
+        let mut data = content;
+        match self.parameters(dlci).convergence_layer {
+            ConvergenceLayer::Type2 => match Cl2Status::decode(content) {
+                Ok((status, rest)) => {
+                    self.receive_status(dlci, status);
+                    data = rest;
+                }
+                Err(e) => {
+                    self.events.push_back(MuxEvent::Error(e.into()));
+                    return;
+                }
+            },
+            ConvergenceLayer::Type4 => {
+                match self.segments.entry(dlci).or_default().push(content) {
+                    Ok(Some(data)) => self.deliver(dlci, data),
+                    Ok(None) => {}
+                    Err(e) => self.events.push_back(MuxEvent::Error(e.into())),
+                }
+                return;
+            }
+            _ => {}
+        }
+        let n1 = self.n1(dlci);
+        self.partial
+            .entry(dlci)
+            .or_default()
+            .extend_from_slice(data);
+        if content.len() != n1 {
+            let data = self.partial.remove(&dlci).unwrap_or_default();
+            self.deliver(dlci, data);
+        }
+    }
+
+    /// Raises the data received on `dlci`, separating the URCs of an AT channel.
+    fn deliver(&mut self, dlci: u8, data: Vec<u8>) {
+        let Some(filter) = self.at_channels.get_mut(&dlci) else {
+            self.events.push_back(MuxEvent::Data { dlci, data });
+            return;
+        };
+        let (data, urcs) = filter.feed(&data);
+        if !data.is_empty() {
+            self.events.push_back(MuxEvent::Data { dlci, data });
+        }
+        for line in urcs {
+            self.events.push_back(MuxEvent::Urc { dlci, line });
+        }
+    }
+
+    /// Raises [`MuxEvent::ModemStatus`] if the Type 2 status octets changed or carry a break.
+    fn receive_status(&mut self, dlci: u8, status: Cl2Status) {
+        let dlc = self.dlcs.entry(dlci).or_default();
+        if dlc.status == Some(status) && status.break_length.is_none() {
+            return;
+        }
+        dlc.status = Some(status);
+        self.modem_status_received(Msc {
+            dlci,
+            signals: status.signals,
+            break_length: status.break_length,
+        });
+    }
+
+    /// Records the V.24 signals sent by the peer and raises [`MuxEvent::ModemStatus`].
+    fn modem_status_received(&mut self, msc: Msc) {
+        if let Some(dlc) = self.dlcs.get_mut(&msc.dlci) {
+            dlc.received = Some(msc.signals);
+        }
+        self.events.push_back(MuxEvent::ModemStatus(msc));
+        if let Some(Callback(callback)) = &mut self.on_modem_status {
+            callback(msc);
+        }
+    }
+
+    /// Answers the MSC commands carried by `frame` and raises [`MuxEvent::ModemStatus`].
+    fn receive_modem_status(&mut self, frame: &Frame) {
+        let Ok(messages) = ControlMessage::from_frame(frame) else {
+            return;
+        };
+        for message in messages {
+            if message.message_type != MessageType::MSC || !message.cr {
+                continue;
+            }
+            match Msc::try_from(&message) {
+                Ok(msc) => {
+                    let response = msc.to_message(false).to_frame(self.session.initiator());
+                    match response {
+                        Ok(frame) => self.outbox.push_back(frame),
+                        Err(e) => self.events.push_back(MuxEvent::Error(e.into())),
+                    }
+                    self.modem_status_received(msc);
+                }
+                Err(e) => self.events.push_back(MuxEvent::Error(e.into())),
+            }
+        }
+    }
+
+    /// Applies the parameters accepted by the peer in the PN responses carried by `frame`.
+    fn receive_parameters(&mut self, frame: &Frame) {
+        let Ok(messages) = ControlMessage::from_frame(frame) else {
+            return;
+        };
+        for message in messages {
+            if message.message_type != MessageType::PN || message.cr {
+                continue;
+            }
+            match ParameterNegotiation::try_from(&message) {
+                Ok(pn) => self.set_parameters(pn),
+                Err(e) => self.events.push_back(MuxEvent::Error(e.into())),
+            }
+        }
+    }
+
+    /// Answers the PSC commands carried by `frame` and enters power saving mode on PSC, or
+    /// on the response to the PSC sent by [`Mux::sleep`].
+    fn receive_power_saving(&mut self, frame: &Frame) {
+        let Ok(messages) = ControlMessage::from_frame(frame) else {
+            return;
+        };
+        for message in messages {
+            if message.message_type != MessageType::PSC {
+                continue;
+            }
+            if message.cr {
+                let response = ControlMessage::new(MessageType::PSC, false, Vec::new());
+                match response.to_frame(self.session.initiator()) {
+                    Ok(frame) => self.outbox.push_back(frame),
+                    Err(e) => self.events.push_back(MuxEvent::Error(e.into())),
+                }
+                self.set_power(PowerState::Sleeping);
+            } else if self.power == PowerState::Entering {
+                self.set_power(PowerState::Sleeping);
+            }
+        }
+    }
+
+    /// Returns the power saving state.
+    pub fn power_state(&self) -> PowerState {
+        self.power
+    }
+
+    /// Asks the peer to enter power saving mode with a PSC command, awaiting the response for
+    /// T2. The engine goes back to [`PowerState::Active`] if the peer does not answer.
+    pub fn sleep(&mut self, now: Instant) -> Result<(), MuxError> {
+        if self.state(0) != DlcState::Connected {
+            return Err(MuxError::NotConnected { dlci: 0 });
+        }
+        self.send_control(ControlMessage::new(MessageType::PSC, true, Vec::new()), now)?;
+        self.set_power(PowerState::Entering);
+        Ok(())
+    }
+
+    /// Starts waking the peer up: the driver sends flags until [`Mux::power_state`] is no
+    /// longer [`PowerState::WakingUp`]. Without flags from the peer within T3,
+    /// [`MuxError::WakeUpTimeout`] is raised and the engine goes back to sleep.
+    pub fn wake_up(&mut self, now: Instant) {
+        if self.power == PowerState::Sleeping {
+            self.wake_deadline = Some(now + self.config.t3);
+            self.set_power(PowerState::WakingUp);
+        }
+    }
+
+    /// Reports flags received while sleeping, which end power saving mode. Flags received
+    /// in [`PowerState::Sleeping`] must be answered with flags by the driver.
+    pub fn wake_up_received(&mut self) {
+        if matches!(self.power, PowerState::Sleeping | PowerState::WakingUp) {
+            self.set_power(PowerState::Active);
+        }
+    }
+
+    /// Changes the power saving state, raising [`MuxEvent::PowerState`].
+    fn set_power(&mut self, power: PowerState) {
+        if power != PowerState::WakingUp {
+            self.wake_deadline = None;
+        }
+        if self.power != power {
+            self.power = power;
+            self.events.push_back(MuxEvent::PowerState(power));
+        }
+    }
+
+    /// Stops T2 for the control commands answered by the responses carried by `frame`,
+    /// including the commands the peer does not support.
+    fn receive_responses(&mut self, frame: &Frame) {
+        let Ok(messages) = ControlMessage::from_frame(frame) else {
+            return;
+        };
+        for message in messages.iter().filter(|message| !message.cr) {
+            if message.message_type == MessageType::Test
+                && self.keep_alive.pending.as_ref() == Some(&message.value)
+            {
+                self.keep_alive.pending = None;
+                self.keep_alive.missed = 0;
+                continue;
+            }
+            let answered = match message.message_type {
+                MessageType::NSC => match Nsc::try_from(message) {
+                    Ok(nsc) => nsc.message_type,
+                    Err(_) => continue,
+                },
+                message_type => message_type,
+            };
+            let position = self
+                .controls
+                .iter()
+                .position(|control| control.message_type == answered);
+            if let Some(position) = position {
+                self.controls.remove(position);
+            }
+        }
+    }
+
+    /// Sends `message` as a command on DLCI 0 and starts T2. The command is sent again when
+    /// T2 expires, until a response of the same type arrives or N2 retransmissions are
+    /// exhausted, which raises [`MuxError::NoResponse`].
+    pub fn send_control(&mut self, message: ControlMessage, now: Instant) -> Result<(), MuxError> {
+        let message_type = message.message_type;
+        let frame = message.to_frame(self.session.initiator())?;
+        self.outbox.push_back(frame.clone());
+        self.controls.push_back(PendingControl {
+            message_type,
+            frame,
+            retries: 0,
+            deadline: now + self.config.t2,
+        });
+        Ok(())
+    }
+
+    /// Queues `frame` for transmission: data on DLCIs other than 0 through the scheduler,
+    /// anything else ahead of it.
+    fn transmit(&mut self, frame: Frame) {
+        let dlci = frame.address.dlci().into_bits();
+        let data = matches!(frame.control.frame_type(), FrameType::UIH | FrameType::UI);
+        if data && dlci != 0 {
+            self.scheduler.push(frame);
+        } else {
+            self.outbox.push_back(frame);
+        }
+    }
+
+    /// Sends the V.24 signals of a connected DLC to the peer in an MSC command, awaiting the
+    /// response for T2. They are also carried by the following data frames of a Type 2
+    /// convergence layer DLC.
+    pub fn set_modem_status(&mut self, msc: Msc, now: Instant) -> Result<(), MuxError> {
+        if self.state(msc.dlci) != DlcState::Connected {
+            return Err(MuxError::NotConnected { dlci: msc.dlci });
+        }
+        self.send_control(msc.to_message(true), now)?;
+        if let Some(dlc) = self.dlcs.get_mut(&msc.dlci) {
+            dlc.signals = msc.signals;
+        }
+        Ok(())
+    }
+
+    /// Sends a break signal lasting `duration` on a connected DLC, in an MSC command carrying
+    /// the V.24 signals last set, like the break of a physical serial port.
+    ///
+    /// The breaks sent by the peer are raised as [`MuxEvent::ModemStatus`], with
+    /// [`Msc::break_duration`] set.
+    pub fn send_break(
+        &mut self,
+        dlci: u8,
+        duration: Duration,
+        now: Instant,
+    ) -> Result<(), MuxError> {
+        let dlci = dlci & 0x3F;
+        let signals = match self.dlcs.get(&dlci) {
+            Some(dlc) if dlc.state == DlcState::Connected => dlc.signals,
+            _ => return Err(MuxError::NotConnected { dlci }),
+        };
+        let msc = Msc::new(dlci)
+            .with_signals(signals)
+            .with_break_duration(duration);
+        self.send_control(msc.to_message(true), now)
+    }
+
+    /// Returns the earliest instant at which [`Mux::poll_timeout`] has work to do.
+    pub fn next_timeout(&self) -> Option<Instant> {
+        let controls = self.controls.iter().map(|control| control.deadline);
+        self.dlcs
+            .values()
+            .filter_map(|dlc| dlc.deadline)
+            .chain(controls)
+            .chain(self.keep_alive.due)
+            .chain(self.flow_deadline)
+            .chain(self.wake_deadline)
+            .min()
+    }
+
+    /// Retransmits the commands whose T1 or T2 expired, and closes the DLCs that exhausted N2.
+    /// During [`Mux::shutdown`], closes DLCI 0 once the other DLCs are closed. Drops the data
+    /// held by FCoff past the deadline of [`WritePolicy`]. Goes back to sleep when T3 expires.
+    pub fn poll_timeout(&mut self, now: Instant) {
+        let mut expired = Vec::new();
+        for (&dlci, dlc) in self.dlcs.iter_mut() {
+            match dlc.deadline {
+                Some(deadline) if deadline <= now => {}
+                _ => continue,
+            }
+            if dlc.retries < self.config.n2 {
+                dlc.retries += 1;
+                dlc.deadline = Some(now + self.config.t1);
+                if let Some(frame) = &dlc.pending {
+                    self.outbox.push_back(frame.clone());
+                }
+            } else {
+                expired.push(dlci);
+            }
+        }
+        for dlci in expired {
+            self.events
+                .push_back(MuxEvent::Error(MuxError::Timeout { dlci }));
+            self.transition(dlci, DlcState::Closed);
+        }
+
+        let config = self.config;
+        let outbox = &mut self.outbox;
+        let events = &mut self.events;
+        self.controls.retain_mut(|control| {
+            if control.deadline > now {
+                return true;
+            }
+            if control.retries < config.n2 {
+                control.retries += 1;
+                control.deadline = now + config.t2;
+                outbox.push_back(control.frame.clone());
+                return true;
+            }
+            let error = MuxError::NoResponse(control.message_type);
+            events.push_back(MuxEvent::Error(error));
+            false
+        });
+        if self.power == PowerState::Entering
+            && !self
+                .controls
+                .iter()
+                .any(|control| control.message_type == MessageType::PSC)
+        {
+            self.set_power(PowerState::Active);
+        }
+        if self.wake_deadline.is_some_and(|deadline| deadline <= now) {
+            self.events
+                .push_back(MuxEvent::Error(MuxError::WakeUpTimeout));
+            self.set_power(PowerState::Sleeping);
+        }
+        self.poll_keep_alive(now);
+        self.poll_shutdown(now);
+        self.poll_flow_deadline(now);
+    }
+
+    /// Drops the data held by FCoff past the deadline of [`WritePolicy`].
+    fn poll_flow_deadline(&mut self, now: Instant) {
+        let (Some(due), Some(deadline)) = (self.flow_deadline, self.config.write.deadline) else {
+            return;
+        };
+        if due > now {
+            return;
+        }
+        let dlcis: BTreeSet<u8> = self
+            .session
+            .take_queued()
+            .iter()
+            .map(|frame| frame.address.dlci().into_bits())
+            .collect();
+        for dlci in dlcis {
+            self.events
+                .push_back(MuxEvent::Error(MuxError::WriteTimeout { dlci }));
+        }
+        self.flow_deadline = Some(now + deadline);
+    }
+
+    /// Sends the keep-alive Test command when it is due, and tears the session down when too
+    /// many of them were left unanswered.
+    fn poll_keep_alive(&mut self, now: Instant) {
+        let Some(interval) = self.config.keep_alive else {
+            return;
+        };
+        if self.state(0) != DlcState::Connected {
+            self.keep_alive = KeepAlive::default();
+            return;
+        }
+        let due = *self.keep_alive.due.get_or_insert(now + interval);
+        if due > now {
+            return;
+        }
+        if self.keep_alive.pending.take().is_some() {
+            self.keep_alive.missed += 1;
+            let missed = self.keep_alive.missed;
+            if missed >= self.config.keep_alive_misses {
+                self.events
+                    .push_back(MuxEvent::Error(MuxError::Unresponsive { missed }));
+                self.tear_down();
+                return;
+            }
+        }
+        let pattern = test_pattern(self.keep_alive.seq);
+        self.keep_alive.seq = self.keep_alive.seq.wrapping_add(1);
+        match ControlMessage::test(true, pattern.clone()).to_frame(self.session.initiator()) {
+            Ok(frame) => self.outbox.push_back(frame),
+            Err(e) => self.events.push_back(MuxEvent::Error(e.into())),
+        }
+        self.keep_alive.pending = Some(pattern);
+        self.keep_alive.due = Some(now + interval);
+    }
+
+    /// Closes every DLC, DLCI 0 last, without waiting for the peer.
+    fn tear_down(&mut self) {
+        let dlcis: Vec<u8> = self.dlcs.keys().rev().copied().collect();
+        for dlci in dlcis {
+            self.transition(dlci, DlcState::Closed);
+        }
+        self.controls.clear();
+        self.keep_alive = KeepAlive::default();
+    }
+
+    /// Takes the next frame to transmit.
+    pub fn poll_transmit(&mut self) -> Option<Frame> {
+        self.outbox.pop_front().or_else(|| self.scheduler.pop())
+    }
+
+    /// Takes the next event.
+    pub fn poll_event(&mut self) -> Option<MuxEvent> {
+        self.events.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Moves every frame queued by `from` into `to`.
+    fn deliver(from: &mut Mux, to: &mut Mux, now: Instant) {
+        while let Some(frame) = from.poll_transmit() {
+            to.receive(&frame, now);
+        }
+    }
+
+    #[test]
+    fn test_open_send_close() {
+        let now = Instant::now();
+        let mut host = Mux::new();
+        let mut modem = Mux::new().with_initiator(false);
+
+        host.open(0, now).unwrap();
+        host.open(2, now).unwrap();
+        deliver(&mut host, &mut modem, now);
+        deliver(&mut modem, &mut host, now);
+        assert_eq!(host.state(2), DlcState::Connected);
+        assert_eq!(modem.state(2), DlcState::Connected);
+        assert_eq!(host.next_timeout(), None);
+
+        host.send(2, b"AT\r").unwrap();
+        deliver(&mut host, &mut modem, now);
+        let events: Vec<_> = std::iter::from_fn(|| modem.poll_event()).collect();
+        assert_eq!(
+            events,
+            [
+                MuxEvent::DlcOpened(0),
+                MuxEvent::DlcOpened(2),
+                MuxEvent::Data {
+                    dlci: 2,
+                    data: b"AT\r".to_vec()
+                }
+            ]
+        );
+
+        host.close(2, now).unwrap();
+        deliver(&mut host, &mut modem, now);
+        deliver(&mut modem, &mut host, now);
+        assert_eq!(host.state(2), DlcState::Closed);
+        assert_eq!(modem.poll_event(), Some(MuxEvent::DlcClosed(2)));
+        assert_eq!(host.send(2, &[]), Err(MuxError::NotConnected { dlci: 2 }));
+    }
+
+    #[test]
+    fn test_retransmission() {
+        let start = Instant::now();
+        let mut mux = Mux::new().with_n2(2);
+        mux.open(1, start).unwrap();
+        let mut sent = 0;
+        let mut now = start;
+        while mux.state(1) == DlcState::Connecting {
+            while mux.poll_transmit().is_some() {
+                sent += 1;
+            }
+            now = mux.next_timeout().unwrap();
+            mux.poll_timeout(now);
+        }
+        assert_eq!(sent, 3);
+        assert_eq!(now - start, DEFAULT_T1 * 3);
+        assert_eq!(
+            mux.poll_event(),
+            Some(MuxEvent::Error(MuxError::Timeout { dlci: 1 }))
+        );
+        // The DLC never opened, so no DlcClosed is raised.
+        assert_eq!(mux.poll_event(), None);
+    }
+
+    #[test]
+    fn test_priority() {
+        let now = Instant::now();
+        let mut host = Mux::new();
+        let mut modem = Mux::new().with_initiator(false);
+        for dlci in [0, 1, 9] {
+            host.open(dlci, now).unwrap();
+        }
+        deliver(&mut host, &mut modem, now);
+        deliver(&mut modem, &mut host, now);
+
+        let pn = ParameterNegotiation::new(9).with_priority(0);
+        let response = pn.to_message(false).to_frame(false).unwrap();
+        host.receive(&response, now);
+        assert_eq!(host.priority(9), 0);
+        host.set_priority(9, 20);
+
+        for _ in 0..3 {
+            host.send(9, &[0; 30]).unwrap();
+        }
+        host.send(1, b"AT\r").unwrap();
+        let order: Vec<u8> = std::iter::from_fn(|| host.poll_transmit())
+            .map(|frame| frame.address.dlci().into_bits())
+            .collect();
+        assert_eq!(order, [1, 9, 9, 9]);
+    }
+
+    #[test]
+    fn test_fragmentation() {
+        let now = Instant::now();
+        let mut host = Mux::new();
+        let mut modem = Mux::new().with_initiator(false);
+        host.open(0, now).unwrap();
+        host.open(1, now).unwrap();
+        deliver(&mut host, &mut modem, now);
+        deliver(&mut modem, &mut host, now);
+        while modem.poll_event().is_some() {}
+
+        let pn = ParameterNegotiation::new(1).with_n1(4);
+        host.set_parameters(pn);
+        modem.set_parameters(pn);
+        assert_eq!(host.parameters(1).n1, 4);
+
+        let data: Vec<u8> = (0..10).collect();
+        host.send(1, &data).unwrap();
+        host.send(1, &data[..8]).unwrap();
+        let lengths: Vec<usize> = std::iter::from_fn(|| host.poll_transmit())
+            .inspect(|frame| modem.receive(frame, now))
+            .map(|frame| frame.content.as_bytes().len())
+            .collect();
+        assert_eq!(lengths, [4, 4, 2, 4, 4, 0]);
+        let events: Vec<_> = std::iter::from_fn(|| modem.poll_event()).collect();
+        assert_eq!(
+            events,
+            [
+                MuxEvent::Data { dlci: 1, data },
+                MuxEvent::Data {
+                    dlci: 1,
+                    data: (0..8).collect()
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_urc() {
+        let now = Instant::now();
+        let mut host = Mux::new();
+        let mut modem = Mux::new().with_initiator(false);
+        host.open(0, now).unwrap();
+        host.open(1, now).unwrap();
+        deliver(&mut host, &mut modem, now);
+        deliver(&mut modem, &mut host, now);
+        while host.poll_event().is_some() {}
+        host.set_at_channel(1, true);
+
+        modem.send(1, b"\r\nRING\r\n").unwrap();
+        deliver(&mut modem, &mut host, now);
+        host.send_command(1, "AT+CREG?").unwrap();
+        deliver(&mut host, &mut modem, now);
+        modem.send(1, b"\r\n+CREG: 0,1\r\n\r\nOK\r\n").unwrap();
+        modem.send(1, b"\r\n+CREG: 5\r\n").unwrap();
+        deliver(&mut modem, &mut host, now);
+        let events: Vec<_> = std::iter::from_fn(|| host.poll_event()).collect();
+        assert_eq!(
+            events,
+            [
+                MuxEvent::Urc {
+                    dlci: 1,
+                    line: "RING".to_string()
+                },
+                MuxEvent::Data {
+                    dlci: 1,
+                    data: b"\r\n+CREG: 0,1\r\n\r\nOK\r".to_vec()
+                },
+                MuxEvent::Urc {
+                    dlci: 1,
+                    line: "+CREG: 5".to_string()
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_modem_status() {
+        let now = Instant::now();
+        let mut host = Mux::new();
+        let mut modem = Mux::new().with_initiator(false);
+        host.open(0, now).unwrap();
+        host.open(1, now).unwrap();
+        deliver(&mut host, &mut modem, now);
+        deliver(&mut modem, &mut host, now);
+        while modem.poll_event().is_some() {}
+        while host.poll_event().is_some() {}
+        let received = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = received.clone();
+        modem.on_modem_status(move |msc| sink.lock().unwrap().push(msc));
+        let answered = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = answered.clone();
+        host.on_modem_status(move |msc| sink.lock().unwrap().push(msc));
+
+        // The MSC command fires the callback with the signals it carries, its response does not.
+        let signals = V24Signals::new().with_rtc(true).with_ic(true);
+        let msc = Msc::new(1).with_signals(signals);
+        host.set_modem_status(msc, now).unwrap();
+        deliver(&mut host, &mut modem, now);
+        deliver(&mut modem, &mut host, now);
+        let status = received.lock().unwrap()[0];
+        assert_eq!(*received.lock().unwrap(), [msc]);
+        assert!(status.signals.rtc() && status.signals.ic() && !status.signals.rtr());
+        assert_eq!(status.break_duration(), None);
+        assert_eq!(modem.poll_event(), Some(MuxEvent::ModemStatus(msc)));
+        assert_eq!(modem.modem_status(1), Some(signals));
+        assert!(answered.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_convergence_layer_2() {
+        let now = Instant::now();
+        let mut host = Mux::new();
+        let mut modem = Mux::new().with_initiator(false);
+        let pn = ParameterNegotiation::new(1).with_convergence_layer(ConvergenceLayer::Type2);
+        host.set_parameters(pn);
+        modem.set_parameters(pn);
+        host.open(0, now).unwrap();
+        host.open(1, now).unwrap();
+        deliver(&mut host, &mut modem, now);
+        deliver(&mut modem, &mut host, now);
+        while modem.poll_event().is_some() {}
+        let received = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = received.clone();
+        modem.on_modem_status(move |msc| sink.lock().unwrap().push(msc.dlci));
+        assert_eq!(modem.modem_status(1), None);
+
+        host.send(1, b"AT\r").unwrap();
+        host.send(1, b"AT\r").unwrap();
+        let frame = host.poll_transmit().unwrap();
+        assert_eq!(frame.content.as_bytes(), b"\x0DAT\r");
+        modem.receive(&frame, now);
+        deliver(&mut host, &mut modem, now);
+        let signals = V24Signals::new().with_rtc(true).with_rtr(true);
+        let data = MuxEvent::Data {
+            dlci: 1,
+            data: b"AT\r".to_vec(),
+        };
+        let events: Vec<_> = std::iter::from_fn(|| modem.poll_event()).collect();
+        assert_eq!(
+            events,
+            [
+                MuxEvent::ModemStatus(Msc::new(1).with_signals(signals)),
+                data.clone(),
+                data
+            ]
+        );
+        assert_eq!(modem.modem_status(1), Some(signals));
+        host.send_break(1, Duration::from_millis(400), now).unwrap();
+        deliver(&mut host, &mut modem, now);
+        let Some(MuxEvent::ModemStatus(msc)) = modem.poll_event() else {
+            panic!("no break received");
+        };
+        assert_eq!(msc.break_duration(), Some(Duration::from_millis(400)));
+        assert_eq!(msc.signals, signals);
+        assert!(signals.dsr() && signals.cts() && !signals.dcd());
+        assert_eq!(*received.lock().unwrap(), [1, 1]);
+
+        let content = Cl2Status::new(signals).with_break(2).encode(&[]);
+        modem.receive_data(1, &content[..1]);
+        assert_eq!(
+            modem.poll_event(),
+            Some(MuxEvent::Error(MuxError::Convergence(
+                crate::error::ConvergenceError::Truncated {
+                    expected: 2,
+                    actual: 1
+                }
+            )))
+        );
+    }
+
+    #[test]
+    fn test_convergence_layer_4() {
+        let now = Instant::now();
+        let mut host = Mux::new();
+        let mut modem = Mux::new().with_initiator(false);
+        let pn = ParameterNegotiation::new(1)
+            .with_convergence_layer(ConvergenceLayer::Type4)
+            .with_n1(4);
+        host.set_parameters(pn);
+        modem.set_parameters(pn);
+        host.open(0, now).unwrap();
+        host.open(1, now).unwrap();
+        deliver(&mut host, &mut modem, now);
+        deliver(&mut modem, &mut host, now);
+        while modem.poll_event().is_some() {}
+
+        host.send(1, b"ATI\r").unwrap();
+        host.send(1, b"").unwrap();
+        let contents: Vec<Vec<u8>> = std::iter::from_fn(|| host.poll_transmit())
+            .inspect(|frame| modem.receive(frame, now))
+            .map(|frame| frame.content.as_bytes().to_vec())
+            .collect();
+        assert_eq!(contents, [&b"\x40ATI"[..], b"\x80\r", b"\xC0"]);
+        let events: Vec<_> = std::iter::from_fn(|| modem.poll_event()).collect();
+        assert_eq!(
+            events,
+            [
+                MuxEvent::Data {
+                    dlci: 1,
+                    data: b"ATI\r".to_vec()
+                },
+                MuxEvent::Data {
+                    dlci: 1,
+                    data: Vec::new()
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_control_timeout() {
+        let start = Instant::now();
+        let mut host = Mux::new().with_config(MuxConfig::default().with_n2(1));
+        let mut modem = Mux::new().with_initiator(false);
+        host.open(0, start).unwrap();
+        deliver(&mut host, &mut modem, start);
+        deliver(&mut modem, &mut host, start);
+        while host.poll_event().is_some() {}
+
+        // The MSC is answered, the Test command is not.
+        host.set_modem_status(Msc::new(0), start).unwrap();
+        let test = ControlMessage::new(MessageType::Test, true, vec![0x55]);
+        host.send_control(test, start).unwrap();
+        let msc = host.poll_transmit().unwrap();
+        modem.receive(&msc, start);
+        deliver(&mut modem, &mut host, start);
+        assert_eq!(host.next_timeout(), Some(start + DEFAULT_T2));
+
+        let mut now = start;
+        let mut sent = 0;
+        while let Some(deadline) = host.next_timeout() {
+            now = deadline;
+            host.poll_timeout(now);
+            while host.poll_transmit().is_some() {
+                sent += 1;
+            }
+        }
+        assert_eq!((sent, now - start), (2, DEFAULT_T2 * 2));
+        let events: Vec<_> = std::iter::from_fn(|| host.poll_event()).collect();
+        assert_eq!(
+            events.last(),
+            Some(&MuxEvent::Error(MuxError::NoResponse(MessageType::Test)))
+        );
+    }
+
+    #[test]
+    fn test_keep_alive() {
+        let start = Instant::now();
+        let second = Duration::from_secs(1);
+        let config = MuxConfig::default()
+            .with_keep_alive(second)
+            .with_keep_alive_misses(2);
+        let mut host = Mux::new().with_config(config);
+        let mut modem = Mux::new().with_initiator(false);
+        let closed = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = closed.clone();
+        host.on_closed(move |dlci| sink.lock().unwrap().push(dlci));
+        host.open(0, start).unwrap();
+        host.open(1, start).unwrap();
+        deliver(&mut host, &mut modem, start);
+        deliver(&mut modem, &mut host, start);
+        while host.poll_event().is_some() {}
+        assert_eq!(host.next_timeout(), Some(start + second));
+
+        // The echoed Test command resets the count of misses.
+        host.poll_timeout(start + second);
+        let test = host.poll_transmit().unwrap();
+        let mut echo = ControlMessage::from_frame(&test).unwrap().remove(0);
+        echo.cr = false;
+        host.receive(&echo.to_frame(false).unwrap(), start + second);
+        host.poll_timeout(start + second * 2);
+        assert!(host.poll_transmit().is_some());
+        host.poll_timeout(start + second * 3);
+        assert!(host.poll_transmit().is_some());
+        assert_eq!(host.poll_event(), None);
+
+        host.poll_timeout(start + second * 4);
+        assert_eq!(host.poll_transmit(), None);
+        let events: Vec<_> = std::iter::from_fn(|| host.poll_event()).collect();
+        assert_eq!(
+            events,
+            [
+                MuxEvent::Error(MuxError::Unresponsive { missed: 2 }),
+                MuxEvent::DlcClosed(1),
+                MuxEvent::DlcClosed(0)
+            ]
+        );
+        assert_eq!(*closed.lock().unwrap(), [1, 0]);
+        assert_eq!(host.next_timeout(), None);
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let start = Instant::now();
+        let mut host = Mux::new();
+        let mut modem = Mux::new().with_initiator(false);
+        for dlci in [0, 1, 2] {
+            host.open(dlci, start).unwrap();
+        }
+        deliver(&mut host, &mut modem, start);
+        deliver(&mut modem, &mut host, start);
+        while host.poll_event().is_some() {}
+
+        // DLCI 0 is closed once the other DLCs answered their DISC.
+        host.shutdown(start);
+        let frames: Vec<_> = std::iter::from_fn(|| host.poll_transmit()).collect();
+        let dlcis: Vec<_> = frames
+            .iter()
+            .map(|f| f.address.dlci().into_bits())
+            .collect();
+        assert_eq!(dlcis, [1, 2]);
+        assert!(!host.is_idle());
+        frames.iter().for_each(|frame| modem.receive(frame, start));
+        deliver(&mut modem, &mut host, start);
+        let disc = host.poll_transmit().unwrap();
+        assert_eq!(disc.address.dlci().into_bits(), 0);
+        assert_eq!(disc.control.frame_type(), FrameType::DISC);
+
+        // An unanswered DISC only delays the teardown until it times out.
+        let mut now = start;
+        while let Some(deadline) = host.next_timeout() {
+            now = deadline;
+            host.poll_timeout(now);
+        }
+        assert!(host.is_idle());
+        assert_eq!(now - start, DEFAULT_T1 * (u32::from(DEFAULT_N2) + 1));
+        let events: Vec<_> = std::iter::from_fn(|| host.poll_event()).collect();
+        assert_eq!(
+            events,
+            [
+                MuxEvent::DlcClosed(1),
+                MuxEvent::DlcClosed(2),
+                MuxEvent::Error(MuxError::Timeout { dlci: 0 }),
+                MuxEvent::DlcClosed(0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_flow_deadline() {
+        let start = Instant::now();
+        let second = Duration::from_secs(1);
+        let policy = WritePolicy::default().with_deadline(second);
+        let mut host = Mux::new().with_config(MuxConfig::default().with_write_policy(policy));
+        let mut modem = Mux::new().with_initiator(false);
+        host.open(0, start).unwrap();
+        host.open(1, start).unwrap();
+        deliver(&mut host, &mut modem, start);
+        deliver(&mut modem, &mut host, start);
+        while host.poll_event().is_some() {}
+
+        let fcoff = ControlMessage::new(MessageType::FCoff, true, Vec::new());
+        host.receive(&fcoff.to_frame(false).unwrap(), start);
+        assert_eq!(
+            host.poll_event(),
+            Some(MuxEvent::FlowControl { enabled: false })
+        );
+        while host.poll_transmit().is_some() {}
+        host.send(1, b"AT").unwrap();
+        assert_eq!(host.poll_transmit(), None);
+        assert_eq!(host.next_timeout(), Some(start + second));
+
+        host.poll_timeout(start + second);
+        assert_eq!(
+            host.poll_event(),
+            Some(MuxEvent::Error(MuxError::WriteTimeout { dlci: 1 }))
+        );
+        assert_eq!(host.session().queued(), 0);
+        // The deadline is renewed while FCoff lasts, and stopped by FCon.
+        assert_eq!(host.next_timeout(), Some(start + second * 2));
+        let fcon = ControlMessage::new(MessageType::FCon, true, Vec::new());
+        host.receive(&fcon.to_frame(false).unwrap(), start + second);
+        assert_eq!(host.next_timeout(), None);
+        assert_eq!(
+            host.poll_event(),
+            Some(MuxEvent::FlowControl { enabled: true })
+        );
+    }
+
+    #[test]
+    fn test_rejected() {
+        let now = Instant::now();
+        let mut host = Mux::new();
+        let mut modem = Mux::new().with_initiator(false);
+        host.close(3, now).unwrap();
+        deliver(&mut host, &mut modem, now);
+        deliver(&mut modem, &mut host, now);
+        assert_eq!(
+            host.poll_event(),
+            Some(MuxEvent::Error(MuxError::Rejected { dlci: 3 }))
+        );
+    }
+
+    #[test]
+    fn test_power_saving() {
+        let start = Instant::now();
+        let mut host = Mux::new();
+        let mut modem = Mux::new().with_initiator(false);
+        assert_eq!(host.sleep(start), Err(MuxError::NotConnected { dlci: 0 }));
+        host.open(0, start).unwrap();
+        deliver(&mut host, &mut modem, start);
+        deliver(&mut modem, &mut host, start);
+        while host.poll_event().is_some() {}
+        while modem.poll_event().is_some() {}
+
+        // Both stations sleep once the peer answers PSC.
+        host.sleep(start).unwrap();
+        assert_eq!(host.power_state(), PowerState::Entering);
+        deliver(&mut host, &mut modem, start);
+        assert_eq!(modem.power_state(), PowerState::Sleeping);
+        deliver(&mut modem, &mut host, start);
+        assert_eq!(host.power_state(), PowerState::Sleeping);
+        assert_eq!(host.next_timeout(), None);
+
+        // Without wake-up flags from the peer, T3 expires and the host sleeps again.
+        host.wake_up(start);
+        assert_eq!(host.next_timeout(), Some(start + DEFAULT_T3));
+        host.poll_timeout(start + DEFAULT_T3);
+        assert_eq!(host.power_state(), PowerState::Sleeping);
+        host.wake_up(start);
+        host.wake_up_received();
+        modem.wake_up_received();
+        assert_eq!(host.power_state(), PowerState::Active);
+        assert_eq!(modem.power_state(), PowerState::Active);
+        let events: Vec<_> = std::iter::from_fn(|| host.poll_event()).collect();
+        assert_eq!(
+            events,
+            [
+                MuxEvent::PowerState(PowerState::Entering),
+                MuxEvent::PowerState(PowerState::Sleeping),
+                MuxEvent::PowerState(PowerState::WakingUp),
+                MuxEvent::Error(MuxError::WakeUpTimeout),
+                MuxEvent::PowerState(PowerState::Sleeping),
+                MuxEvent::PowerState(PowerState::WakingUp),
+                MuxEvent::PowerState(PowerState::Active)
+            ]
+        );
+
+        // An unanswered PSC leaves the host active.
+        host.sleep(start).unwrap();
+        while let Some(deadline) = host.next_timeout() {
+            host.poll_timeout(deadline);
+        }
+        assert_eq!(host.power_state(), PowerState::Active);
+    }
+}
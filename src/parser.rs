@@ -0,0 +1,1258 @@
+use crate::advanced::{EscapeSet, ADVANCED_FLAG, CONTROL_ESCAPE, MIN_ADVANCED_LENGTH};
+use crate::dialect::Dialect;
+use crate::error::{FrameError, ParseError};
+use crate::types::{Frame, FrameRef, Length, LengthEncoding, ParseOutcome};
+use alloc::vec::Vec;
+
+/// Octets before the length indicator: flag, address and control.
+const HEADER_LENGTH: usize = 3;
+
+/// Octets after the information field: FCS and flag.
+const TRAILER_LENGTH: usize = 2;
+
+/// Result of looking for a frame at the start of the buffer.
+enum Candidate {
+    /// A complete frame of the given size in octets.
+    Frame(usize),
+    /// More octets are needed to decide.
+    Incomplete,
+    /// The opening flag is followed by another flag.
+    Fill,
+    /// The opening flag does not start a frame.
+    Invalid,
+}
+
+/// Framing of a byte stream, as told by [`FrameParser::detect`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Detection {
+    /// True for advanced option frames, false for basic option frames.
+    pub advanced: bool,
+    /// True if control escape octets were found in advanced option frames.
+    pub escaped: bool,
+    /// Number of frames with a valid FCS decoded in the detected framing.
+    pub frames: usize,
+}
+
+impl Detection {
+    /// Creates a parser for the detected framing.
+    pub fn parser(&self) -> FrameParser {
+        if self.advanced {
+            FrameParser::advanced()
+        } else {
+            FrameParser::new()
+        }
+    }
+}
+
+/// Resource limits of a [`FrameParser`]
+///
+/// Without limits, an advanced option frame whose closing flag never comes is buffered
+/// forever, and a basic option frame is waited for up to the 32 KiB its length indicator may
+/// announce. The limits bound the memory a corrupt or hostile stream can hold in a daemon.
+///
+/// # Example
+///
+/// ```
+/// use cmux::parser::{FrameParser, ParserLimits};
+///
+/// let limits = ParserLimits::new().with_max_buffered(64);
+/// let mut parser = FrameParser::advanced().with_limits(limits);
+/// parser.feed(&[0x7E; 2]);
+/// parser.feed(&[0x41; 100]);
+/// assert_eq!(parser.buffered(), 0);
+/// assert_eq!(parser.skipped(), 101);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParserLimits {
+    /// Longest frame returned, flags included. Longer frames are dropped as skipped bytes,
+    /// and are not waited for beyond this length either.
+    pub max_frame_length: Option<usize>,
+    /// Most bytes buffered while waiting for the rest of a frame. Beyond, the buffered bytes
+    /// are dropped as skipped and the parser resynchronizes on the next flag.
+    pub max_buffered: Option<usize>,
+    /// Longest run of skipped bytes left unreported. Once a run reaches it, it is returned as
+    /// [`Scanned::Skipped`] and counted by [`FrameParser::skipped`] at the end of the chunk
+    /// fed, instead of at the next flag, so a stream without flags is noticed.
+    pub max_skipped_run: Option<usize>,
+}
+
+impl ParserLimits {
+    /// Limits fitting a frame of the largest size, escaped: 64 KiB of buffered bytes, and
+    /// runs of skipped bytes reported every 64 KiB.
+    pub const STREAM: ParserLimits = ParserLimits {
+        max_frame_length: None,
+        max_buffered: Some(0x10100),
+        max_skipped_run: Some(0x10000),
+    };
+
+    /// Creates limits that limit nothing.
+    pub const fn new() -> Self {
+        ParserLimits {
+            max_frame_length: None,
+            max_buffered: None,
+            max_skipped_run: None,
+        }
+    }
+
+    pub const fn with_max_frame_length(mut self, len: usize) -> Self {
+        self.max_frame_length = Some(len);
+        self
+    }
+
+    pub const fn with_max_buffered(mut self, len: usize) -> Self {
+        self.max_buffered = Some(len);
+        self
+    }
+
+    pub const fn with_max_skipped_run(mut self, len: usize) -> Self {
+        self.max_skipped_run = Some(len);
+        self
+    }
+
+    /// Returns true if a frame of `len` octets is too long to be returned.
+    fn too_long(&self, len: usize) -> bool {
+        self.max_frame_length.is_some_and(|max| len > max)
+    }
+
+    /// Returns true if `len` buffered octets are too many to wait for the rest of a frame.
+    fn overflow(&self, len: usize) -> bool {
+        [self.max_buffered, self.max_frame_length]
+            .into_iter()
+            .flatten()
+            .any(|max| len > max)
+    }
+}
+
+/// Region of a byte stream decoded by [`scan`] or [`scan_advanced`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scanned {
+    /// A frame starting at `offset`. Its FCS is valid, unless no valid frame starts inside it.
+    Frame { offset: usize, frame: Frame },
+    /// Octets between two flags that do not decode as a frame.
+    Invalid {
+        offset: usize,
+        len: usize,
+        error: FrameError,
+    },
+    /// Octets that do not belong to any frame: noise, or the start of a truncated frame.
+    Skipped { offset: usize, len: usize },
+}
+
+/// Decodes every basic option frame of a complete byte stream.
+///
+/// A flag opens a candidate frame when its length indicator leads to a closing flag. The
+/// candidate is accepted when its FCS is valid. Otherwise, if a valid frame starts at a flag
+/// inside it, the flag is noise and the scan goes on from there; if not, the candidate is a
+/// corrupted frame and is returned as such, so its FCS can be reported or fixed.
+///
+/// Frames sharing their flags, and repeated flags of idle fill, are not counted as skipped.
+///
+/// # Arguments
+///
+/// * `data` - The byte stream.
+///
+/// # Returns
+///
+/// An iterator over the frames and the skipped regions, in stream order.
+///
+/// # Example
+///
+/// ```
+/// use cmux::parser::{scan, Scanned};
+///
+/// let data = hex::decode("1234F9033F011CF907F9073F01DEF9").unwrap();
+/// let scanned: Vec<Scanned> = scan(&data).collect();
+/// assert_eq!(scanned.len(), 4);
+/// assert_eq!(scanned[0], Scanned::Skipped { offset: 0, len: 2 });
+/// assert!(matches!(scanned[1], Scanned::Frame { offset: 2, .. }));
+/// assert_eq!(scanned[2], Scanned::Skipped { offset: 8, len: 1 });
+/// assert!(matches!(scanned[3], Scanned::Frame { offset: 9, .. }));
+/// ```
+pub fn scan(data: &[u8]) -> Scan<'_> {
+    scan_with(data, &Dialect::SPEC)
+}
+
+/// Decodes every frame of a complete byte stream framed in `dialect`, like [`scan`].
+///
+/// Frames are returned as read: their FCS must be checked with [`Dialect::verify`].
+pub fn scan_with<'a>(data: &'a [u8], dialect: &Dialect) -> Scan<'a> {
+    Scan {
+        data,
+        scanner: Scanner::new(*dialect),
+    }
+}
+
+/// Region of the bytes walked by a [`Scanner`].
+enum Region<'a> {
+    Frame {
+        offset: usize,
+        len: usize,
+        frame: Result<FrameRef<'a>, FrameError>,
+    },
+    /// An advanced option frame, unescaped into a new buffer.
+    Advanced {
+        offset: usize,
+        len: usize,
+        frame: Result<Frame, FrameError>,
+    },
+    Skipped {
+        offset: usize,
+        len: usize,
+    },
+}
+
+impl Region<'_> {
+    /// Copies the region into a [`Scanned`].
+    fn into_scanned(self) -> Scanned {
+        match self {
+            Region::Frame {
+                offset,
+                frame: Ok(frame),
+                ..
+            } => Scanned::Frame {
+                offset,
+                frame: frame.to_owned(),
+            },
+            Region::Advanced {
+                offset,
+                frame: Ok(frame),
+                ..
+            } => Scanned::Frame { offset, frame },
+            Region::Frame {
+                offset,
+                len,
+                frame: Err(error),
+            }
+            | Region::Advanced {
+                offset,
+                len,
+                frame: Err(error),
+            } => Scanned::Invalid { offset, len, error },
+            Region::Skipped { offset, len } => Scanned::Skipped { offset, len },
+        }
+    }
+}
+
+/// Search for the frames of a byte slice, shared by [`FrameIter`] and [`FrameParser`]
+///
+/// The slice is either a complete stream, or the part of a stream buffered by
+/// [`FrameParser`]. In a partial slice, the search stops at a frame that is not complete yet,
+/// unless a valid frame starts after its opening flag, which proves the flag is noise.
+#[derive(Debug, Clone, Default)]
+struct Scanner {
+    dialect: Dialect,
+    advanced: bool,
+    escapes: EscapeSet,
+    /// Offset of the first octet of the slice in the stream.
+    base: usize,
+    /// Position of the next octet to examine in the slice.
+    pos: usize,
+    /// The closing flag of the last frame, which may also open the next one.
+    closing: Option<usize>,
+    /// The stream offset of the region being skipped.
+    skipping: Option<usize>,
+    skipped: usize,
+    /// True until the first frame of a dialect without leading flag is found or ruled out.
+    first: bool,
+}
+
+impl Scanner {
+    fn new(dialect: Dialect) -> Self {
+        Scanner {
+            dialect,
+            first: !dialect.leading_flag,
+            ..Default::default()
+        }
+    }
+
+    /// Starts over at the beginning of a stream.
+    fn restart(&mut self) {
+        *self = Scanner {
+            dialect: self.dialect,
+            advanced: self.advanced,
+            escapes: self.escapes,
+            first: !self.dialect.leading_flag,
+            ..Default::default()
+        };
+    }
+
+    /// Finds the next frame or skipped region of `data`.
+    ///
+    /// Returns `None` at the end of a complete slice, or when a partial slice needs more octets.
+    fn next_region<'a>(&mut self, data: &'a [u8], partial: bool) -> Option<Region<'a>> {
+        if self.advanced {
+            return self.next_advanced(data, partial);
+        }
+        let dialect = self.dialect;
+        if self.first {
+            if let Some(region) = self.first_fields(data, partial) {
+                return Some(region);
+            }
+            if self.first {
+                return None;
+            }
+        }
+        while self.pos < data.len() {
+            let pos = self.pos;
+            let candidate = if data[pos] == dialect.flag {
+                FrameParser::candidate(&data[pos..], &dialect)
+            } else {
+                Candidate::Invalid
+            };
+            let len = match candidate {
+                Candidate::Frame(len) => len,
+                Candidate::Incomplete if partial && !valid_after(data, pos + 1, &dialect) => {
+                    return None;
+                }
+                Candidate::Incomplete if pos + 1 == data.len() => {
+                    self.pos = data.len();
+                    return self.end_skipping(pos);
+                }
+                Candidate::Fill => {
+                    self.pos += 1;
+                    match self.end_skipping(pos) {
+                        Some(region) => return Some(region),
+                        None => continue,
+                    }
+                }
+                Candidate::Incomplete | Candidate::Invalid => {
+                    self.skip(pos);
+                    continue;
+                }
+            };
+            let frame = FrameRef::parse_fields(
+                data[pos],
+                &data[pos + 1..pos + len],
+                dialect.flag,
+                dialect.length_encoding,
+            );
+            let valid = matches!(&frame, Ok(frame) if dialect.verify_ref(frame).is_ok());
+            if !valid && (pos + 1..pos + len - 1).any(|i| valid_at(data, i, &dialect)) {
+                self.skip(pos);
+                continue;
+            }
+            // The frame is found again on the next call.
+            if let Some(region) = self.end_skipping(pos) {
+                return Some(region);
+            }
+            self.pos = pos + len - 1;
+            self.closing = Some(self.pos);
+            return Some(Region::Frame {
+                offset: self.base + pos,
+                len,
+                frame,
+            });
+        }
+        if partial {
+            return None;
+        }
+        self.end_skipping(data.len())
+    }
+
+    /// Decodes the first frame of a dialect without leading flag, if it is valid.
+    fn first_fields<'a>(&mut self, data: &'a [u8], partial: bool) -> Option<Region<'a>> {
+        let dialect = self.dialect;
+        let len = match FrameParser::candidate_fields(data, &dialect) {
+            Candidate::Frame(len) => len,
+            Candidate::Incomplete if partial && !valid_after(data, 1, &dialect) => return None,
+            _ => {
+                self.first = false;
+                return None;
+            }
+        };
+        self.first = false;
+        let frame = FrameRef::parse_fields(
+            dialect.flag,
+            &data[..len],
+            dialect.flag,
+            dialect.length_encoding,
+        )
+        .ok()
+        .filter(|frame| dialect.verify_ref(frame).is_ok())?;
+        self.pos = len - 1;
+        self.closing = Some(self.pos);
+        Some(Region::Frame {
+            offset: self.base,
+            len,
+            frame: Ok(frame),
+        })
+    }
+
+    /// Finds the next advanced option frame or skipped region of `data`.
+    ///
+    /// Every run of octets between two flags is a frame candidate, since the flag never appears
+    /// inside an escaped frame.
+    fn next_advanced<'a>(&mut self, data: &'a [u8], partial: bool) -> Option<Region<'a>> {
+        loop {
+            let found = data[self.pos..].iter().position(|&b| b == ADVANCED_FLAG);
+            let Some(start) = self.closing else {
+                // Octets before the first flag.
+                let Some(i) = found else {
+                    if self.pos < data.len() {
+                        self.skipping.get_or_insert(self.base + self.pos);
+                        self.pos = data.len();
+                    }
+                    return if partial {
+                        None
+                    } else {
+                        self.end_skipping(data.len())
+                    };
+                };
+                if i > 0 {
+                    self.skipping.get_or_insert(self.base + self.pos);
+                }
+                let flag = self.pos + i;
+                self.closing = Some(flag);
+                self.pos = flag + 1;
+                match self.end_skipping(flag) {
+                    Some(region) => return Some(region),
+                    None => continue,
+                }
+            };
+            let Some(i) = found else {
+                self.pos = data.len();
+                if partial {
+                    return None;
+                }
+                // Octets after the last flag.
+                self.closing = None;
+                if start + 1 < data.len() {
+                    self.skipping = Some(self.base + start + 1);
+                }
+                return self.end_skipping(data.len());
+            };
+            let end = self.pos + i;
+            self.closing = Some(end);
+            self.pos = end + 1;
+            if end > start + 1 {
+                return Some(Region::Advanced {
+                    offset: self.base + start,
+                    len: end - start + 1,
+                    frame: Frame::from_bytes_advanced_with(&data[start..=end], &self.escapes),
+                });
+            }
+        }
+    }
+
+    /// Skips the byte at `pos`, unless it is the closing flag of the last frame.
+    fn skip(&mut self, pos: usize) {
+        if self.closing != Some(pos) {
+            self.skipping.get_or_insert(self.base + pos);
+        }
+        self.pos += 1;
+    }
+
+    /// Drops the `len` octets at the start of the slice, ending the region being skipped
+    /// after them.
+    fn discard<'a>(&mut self, len: usize) -> Option<Region<'a>> {
+        self.skipping.get_or_insert(self.base);
+        self.base += len;
+        self.pos = self.pos.saturating_sub(len);
+        self.closing = None;
+        self.first = false;
+        self.end_skipping(0)
+    }
+
+    /// Returns the number of octets of the region being skipped up to the next octet to
+    /// examine.
+    fn skipping_len(&self) -> usize {
+        self.skipping
+            .map_or(0, |offset| self.base + self.pos - offset)
+    }
+
+    /// Ends the region being skipped, if any, before `end`.
+    fn end_skipping<'a>(&mut self, end: usize) -> Option<Region<'a>> {
+        let offset = self.skipping.take()?;
+        let len = self.base + end - offset;
+        self.skipped += len;
+        Some(Region::Skipped { offset, len })
+    }
+
+    /// Forgets the octets of the slice that are no longer needed, and returns their number.
+    fn consume(&mut self) -> usize {
+        let n = match self.closing {
+            Some(start) if self.advanced => start,
+            _ => self.pos,
+        };
+        self.base += n;
+        self.pos -= n;
+        self.closing = self.closing.and_then(|closing| closing.checked_sub(n));
+        n
+    }
+}
+
+/// Iterator over the basic option frames of a byte slice, created by [`Frame::iter_from`]
+///
+/// Frames are found as [`scan`] finds them, one at a time and without copying their content,
+/// so large captures are processed lazily. Noise between the frames is skipped and counted by
+/// [`FrameIter::skipped`]; delimited octets that do not decode as a frame are yielded as a
+/// [`ParseError`] locating them.
+#[derive(Debug, Clone)]
+pub struct FrameIter<'a> {
+    data: &'a [u8],
+    scanner: Scanner,
+}
+
+impl<'a> FrameIter<'a> {
+    /// Creates an iterator over the frames of `data`.
+    pub fn new(data: &'a [u8]) -> Self {
+        FrameIter::with_dialect(data, Dialect::SPEC)
+    }
+
+    /// Creates an iterator over the frames of `data` framed in `dialect`.
+    pub fn with_dialect(data: &'a [u8], dialect: Dialect) -> Self {
+        FrameIter {
+            data,
+            scanner: Scanner::new(dialect),
+        }
+    }
+
+    /// Returns the number of bytes skipped so far.
+    pub fn skipped(&self) -> usize {
+        self.scanner.skipped
+    }
+
+    /// Returns the offset of the next byte to examine.
+    pub fn offset(&self) -> usize {
+        self.scanner.pos
+    }
+}
+
+impl<'a> Iterator for FrameIter<'a> {
+    type Item = Result<FrameRef<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Region::Frame { offset, frame, .. } =
+                self.scanner.next_region(self.data, false)?
+            {
+                return Some(frame.map_err(|e| ParseError::new(self.data, offset, e)));
+            }
+        }
+    }
+}
+
+/// Returns the number of octets of `region` dropped by a [`FrameParser`]: none for a valid
+/// frame, and all of them but the flags for a frame that does not decode or whose FCS is
+/// wrong.
+fn dropped(region: &Region<'_>, dialect: &Dialect) -> usize {
+    match region {
+        Region::Frame {
+            frame: Ok(frame), ..
+        } if dialect.verify_ref(frame).is_ok() => 0,
+        Region::Advanced { frame: Ok(_), .. } => 0,
+        Region::Frame { len, .. } | Region::Advanced { len, .. } => len.saturating_sub(2),
+        Region::Skipped { len, .. } => *len,
+    }
+}
+
+/// Returns true if a basic option frame with a valid FCS in `dialect` starts at or after
+/// `from`.
+fn valid_after(data: &[u8], from: usize, dialect: &Dialect) -> bool {
+    (from..data.len()).any(|i| valid_at(data, i, dialect))
+}
+
+/// Returns true if a basic option frame with a valid FCS in `dialect` starts at `pos`.
+fn valid_at(data: &[u8], pos: usize, dialect: &Dialect) -> bool {
+    if data[pos] != dialect.flag {
+        return false;
+    }
+    match FrameParser::candidate(&data[pos..], dialect) {
+        Candidate::Frame(len) => FrameRef::parse_fields(
+            data[pos],
+            &data[pos + 1..pos + len],
+            dialect.flag,
+            dialect.length_encoding,
+        )
+        .is_ok_and(|frame| dialect.verify_ref(&frame).is_ok()),
+        _ => false,
+    }
+}
+
+/// Decodes every advanced option frame of a complete byte stream.
+///
+/// Every run of octets between two flags is a frame, or [`Scanned::Invalid`] when it does not
+/// decode. Octets before the first flag and after the last one are skipped.
+///
+/// # Example
+///
+/// ```
+/// use cmux::parser::{scan_advanced, Scanned};
+///
+/// let data = hex::decode("007E033FFC7E7E07EF7D5E01057E").unwrap();
+/// let scanned: Vec<Scanned> = scan_advanced(&data).collect();
+/// assert_eq!(scanned[0], Scanned::Skipped { offset: 0, len: 1 });
+/// assert!(matches!(scanned[2], Scanned::Frame { offset: 6, .. }));
+/// ```
+pub fn scan_advanced(data: &[u8]) -> Scan<'_> {
+    Scan {
+        data,
+        scanner: Scanner {
+            advanced: true,
+            ..Default::default()
+        },
+    }
+}
+
+/// Iterator over the frames and skipped regions of a byte slice, created by [`scan`],
+/// [`scan_with`] and [`scan_advanced`]
+///
+/// Regions are decoded one at a time, in stream order, so only the frame being looked at is
+/// held in memory.
+#[derive(Debug, Clone)]
+pub struct Scan<'a> {
+    data: &'a [u8],
+    scanner: Scanner,
+}
+
+impl Scan<'_> {
+    /// Returns the number of bytes skipped so far.
+    pub fn skipped(&self) -> usize {
+        self.scanner.skipped
+    }
+}
+
+impl Iterator for Scan<'_> {
+    type Item = Scanned;
+
+    fn next(&mut self) -> Option<Scanned> {
+        self.scanner
+            .next_region(self.data, false)
+            .map(Region::into_scanned)
+    }
+}
+
+/// Streaming Parser for GSM 07.10 [`Frame`]
+///
+/// The parser accepts bytes in chunks of any size, buffers partial frames between calls to
+/// [`FrameParser::feed`], and resynchronizes on the next flag when it meets corrupted data.
+/// Frames are found as [`scan`] finds them: a frame whose FCS is wrong is dropped, and the
+/// search goes on after its opening flag if a valid frame starts inside it. A frame that is not
+/// complete yet is waited for, unless a valid frame already follows its opening flag.
+///
+/// Bytes that cannot belong to a valid frame are dropped and counted by
+/// [`FrameParser::skipped`]. Repeated flags are idle fill and are dropped without being
+/// counted.
+///
+/// The closing flag of a frame is kept as a candidate opening flag, so frames separated by
+/// a single shared flag are decoded as well as frames separated by two flags.
+///
+/// [`FrameParser::advanced`] creates a parser for advanced option frames, delimited by 0x7E
+/// flags and decoded with [`Frame::from_bytes_advanced`].
+///
+/// [`FrameParser::with_limits`] bounds the bytes buffered and the frames returned.
+///
+/// # Example
+///
+/// ```
+/// use cmux::parser::FrameParser;
+///
+/// let mut parser = FrameParser::new();
+/// assert!(parser.feed(&[0x00, 0xF9, 0x03, 0x3F]).is_empty());
+/// let frames = parser.feed(&[0x01, 0x1C, 0xF9]);
+/// assert_eq!(frames.len(), 1);
+/// assert_eq!(frames[0].to_hex_string(), "f9033f011cf9");
+/// assert_eq!(parser.skipped(), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct FrameParser {
+    buffer: Vec<u8>,
+    scanner: Scanner,
+    skipped: usize,
+    /// The frames dropped by [`FrameParser::feed`] because their FCS is wrong.
+    fcs_errors: usize,
+    limits: ParserLimits,
+}
+
+impl FrameParser {
+    /// Creates a parser with an empty buffer.
+    pub fn new() -> Self {
+        FrameParser::default()
+    }
+
+    /// Creates a parser for advanced option frames with an empty buffer.
+    pub fn advanced() -> Self {
+        FrameParser {
+            scanner: Scanner {
+                advanced: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Sets the octets escaped in advanced option frames, [`EscapeSet::new`] by default.
+    ///
+    /// The octets of the set found unescaped between the flags are dropped.
+    pub fn with_escape_set(mut self, escapes: EscapeSet) -> Self {
+        self.scanner.escapes = escapes;
+        self
+    }
+
+    /// Returns the octets escaped in advanced option frames.
+    pub fn escape_set(&self) -> EscapeSet {
+        self.scanner.escapes
+    }
+
+    /// Sets the encoding of two-octet length indicators, [`LengthEncoding::Spec`] by default.
+    ///
+    /// [`LengthEncoding::Legacy`] reads the output of cmux 0.2 and earlier.
+    pub fn with_length_encoding(mut self, encoding: LengthEncoding) -> Self {
+        self.scanner.dialect.length_encoding = encoding;
+        self
+    }
+
+    /// Sets the framing of basic option frames, [`Dialect::SPEC`] by default.
+    ///
+    /// Only the frames whose FCS is valid in `dialect` are returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cmux::dialect::Dialect;
+    /// use cmux::parser::FrameParser;
+    ///
+    /// let dialect = Dialect::SPEC.with_leading_flag(false);
+    /// let mut parser = FrameParser::new().with_dialect(dialect);
+    /// let frames = parser.feed(&hex::decode("033F011CF9073F01DEF9").unwrap());
+    /// assert_eq!(frames.len(), 2);
+    /// assert_eq!(parser.skipped(), 0);
+    /// ```
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.scanner = Scanner {
+            advanced: self.scanner.advanced,
+            escapes: self.scanner.escapes,
+            ..Scanner::new(dialect)
+        };
+        self
+    }
+
+    /// Detects whether `data` holds basic option or advanced option frames.
+    ///
+    /// `data` is decoded in both framings, and the one yielding more frames with a valid FCS
+    /// wins, basic option on a tie. A captured stream does not tell which option the modem
+    /// negotiated, but the FCS of a frame almost never matches in the wrong framing.
+    ///
+    /// # Returns
+    ///
+    /// - `Some(Detection)`: The detected framing.
+    /// - `None`: If no valid frame was found in either framing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cmux::parser::FrameParser;
+    ///
+    /// let data = hex::decode("7E07EF7D5E01057E").unwrap();
+    /// let detection = FrameParser::detect(&data).unwrap();
+    /// assert!(detection.advanced && detection.escaped);
+    /// assert_eq!(detection.parser().feed(&data).len(), 1);
+    /// ```
+    pub fn detect(data: &[u8]) -> Option<Detection> {
+        let valid = |mut parser: FrameParser| {
+            let frames = parser.feed(data);
+            frames.iter().filter(|frame| frame.verify().is_ok()).count()
+        };
+        let basic = valid(FrameParser::new());
+        let advanced = valid(FrameParser::advanced());
+        if basic == 0 && advanced == 0 {
+            return None;
+        }
+        let detection = if advanced > basic {
+            Detection {
+                advanced: true,
+                // The escape octet never appears unescaped between advanced option flags.
+                escaped: data.contains(&CONTROL_ESCAPE),
+                frames: advanced,
+            }
+        } else {
+            Detection {
+                advanced: false,
+                escaped: false,
+                frames: basic,
+            }
+        };
+        Some(detection)
+    }
+
+    /// Sets the resource limits, none by default.
+    pub fn with_limits(mut self, limits: ParserLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Returns the resource limits.
+    pub fn limits(&self) -> ParserLimits {
+        self.limits
+    }
+
+    /// Returns true if the parser decodes advanced option frames.
+    pub fn is_advanced(&self) -> bool {
+        self.scanner.advanced
+    }
+
+    /// Feeds bytes to the parser.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The next chunk of the byte stream.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<Frame>` of every valid frame completed by this chunk, in stream order.
+    pub fn feed(&mut self, data: &[u8]) -> Vec<Frame> {
+        self.buffer.extend_from_slice(data);
+        let dialect = self.scanner.dialect;
+        let mut frames = Vec::new();
+        while let Some(region) = self.scanner.next_region(&self.buffer, true) {
+            let dropped = self.dropped(&region, &dialect);
+            self.skipped += dropped;
+            match region {
+                Region::Frame {
+                    frame: Ok(frame), ..
+                } if dropped == 0 => frames.push(frame.to_owned()),
+                Region::Advanced {
+                    frame: Ok(frame), ..
+                } if dropped == 0 => frames.push(frame),
+                Region::Frame {
+                    frame: Ok(frame), ..
+                } if matches!(
+                    dialect.verify_ref(&frame),
+                    Err(FrameError::ChecksumMismatch { .. })
+                ) =>
+                {
+                    self.fcs_errors += 1
+                }
+                Region::Advanced {
+                    frame: Err(FrameError::ChecksumMismatch { .. }),
+                    ..
+                } => self.fcs_errors += 1,
+                _ => {}
+            }
+        }
+        let consumed = self.scanner.consume();
+        self.buffer.drain(..consumed);
+        if let Some(region) = self.limit() {
+            self.skipped += self.dropped(&region, &dialect);
+        }
+        frames
+    }
+
+    /// Feeds bytes to the parser, returning the frames and regions they complete as [`scan`]
+    /// returns them, located by their offset in the stream.
+    ///
+    /// Unlike [`FrameParser::feed`], a frame whose FCS is wrong is returned when no valid
+    /// frame starts inside it, and octets that do not decode are returned as
+    /// [`Scanned::Invalid`]. [`FrameParser::finish`] returns the regions of the bytes still
+    /// buffered at the end of the stream.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cmux::parser::{FrameParser, Scanned};
+    ///
+    /// let mut parser = FrameParser::new();
+    /// assert!(parser.feed_scanned(&[0x00, 0xF9, 0x03, 0x3F]).is_empty());
+    /// let scanned = parser.feed_scanned(&[0x01, 0x1C, 0xF9, 0x00]);
+    /// assert_eq!(scanned[0], Scanned::Skipped { offset: 0, len: 1 });
+    /// assert!(matches!(scanned[1], Scanned::Frame { offset: 1, .. }));
+    /// assert_eq!(parser.finish(), vec![Scanned::Skipped { offset: 7, len: 1 }]);
+    /// ```
+    pub fn feed_scanned(&mut self, data: &[u8]) -> Vec<Scanned> {
+        self.buffer.extend_from_slice(data);
+        self.regions(true)
+    }
+
+    /// Ends the stream, returning the regions of the buffered bytes. The start of a frame
+    /// that is not complete is skipped.
+    pub fn finish(&mut self) -> Vec<Scanned> {
+        self.regions(false)
+    }
+
+    /// Copies the regions found in the buffer, and drops the bytes no longer needed.
+    fn regions(&mut self, partial: bool) -> Vec<Scanned> {
+        let dialect = self.scanner.dialect;
+        let mut scanned = Vec::new();
+        while let Some(region) = self.scanner.next_region(&self.buffer, partial) {
+            self.skipped += self.dropped(&region, &dialect);
+            scanned.push(self.limited(region));
+        }
+        let consumed = self.scanner.consume();
+        self.buffer.drain(..consumed);
+        if let Some(region) = self.limit() {
+            self.skipped += self.dropped(&region, &dialect);
+            scanned.push(region.into_scanned());
+        }
+        scanned
+    }
+
+    /// Returns the number of octets of `region` dropped, those of a frame over the maximum
+    /// length included.
+    fn dropped(&self, region: &Region<'_>, dialect: &Dialect) -> usize {
+        match region {
+            Region::Frame { len, .. } | Region::Advanced { len, .. }
+                if self.limits.too_long(*len) =>
+            {
+                len.saturating_sub(2)
+            }
+            region => dropped(region, dialect),
+        }
+    }
+
+    /// Copies `region` into a [`Scanned`], a frame over the maximum length as skipped.
+    fn limited(&self, region: Region<'_>) -> Scanned {
+        match region {
+            Region::Frame { offset, len, .. } | Region::Advanced { offset, len, .. }
+                if self.limits.too_long(len) =>
+            {
+                Scanned::Skipped { offset, len }
+            }
+            region => region.into_scanned(),
+        }
+    }
+
+    /// Drops the buffered bytes once they overflow the limits, and ends a long run of skipped
+    /// bytes.
+    fn limit(&mut self) -> Option<Region<'static>> {
+        if self.limits.overflow(self.buffer.len()) {
+            let len = self.buffer.len();
+            self.buffer.clear();
+            return self.scanner.discard(len);
+        }
+        let run = self.limits.max_skipped_run?;
+        if self.scanner.skipping_len() < run.max(1) {
+            return None;
+        }
+        self.scanner.end_skipping(self.scanner.pos)
+    }
+
+    /// Checks whether the flag at the start of `buf` opens a complete frame.
+    fn candidate(buf: &[u8], dialect: &Dialect) -> Candidate {
+        match buf.get(1) {
+            None => Candidate::Incomplete,
+            Some(&address) if address == dialect.flag => Candidate::Fill,
+            Some(_) => match Self::candidate_fields(&buf[1..], dialect) {
+                Candidate::Frame(len) => Candidate::Frame(len + 1),
+                candidate => candidate,
+            },
+        }
+    }
+
+    /// Checks whether `buf`, following an opening flag, holds the fields of a complete frame
+    /// up to its closing flag.
+    fn candidate_fields(buf: &[u8], dialect: &Dialect) -> Candidate {
+        let Some(&address) = buf.first() else {
+            return Candidate::Incomplete;
+        };
+        // The address EA bit is always 1 in basic option.
+        if address & 0x1 == 0 {
+            return Candidate::Invalid;
+        }
+        let Some((length, octets)) = buf
+            .get(HEADER_LENGTH - 1..)
+            .and_then(|data| Length::decode(data, dialect.length_encoding))
+        else {
+            return Candidate::Incomplete;
+        };
+        let len = HEADER_LENGTH - 1 + octets + length.len() + TRAILER_LENGTH;
+        match buf.get(len - 1) {
+            None => Candidate::Incomplete,
+            Some(&flag) if flag == dialect.flag => Candidate::Frame(len),
+            Some(_) => Candidate::Invalid,
+        }
+    }
+
+    /// Returns the number of bytes dropped since the parser was created: noise between the
+    /// frames, and the octets of frames that do not decode or whose FCS is wrong.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Returns the number of frames [`FrameParser::feed`] dropped since the parser was created
+    /// because their FCS is wrong.
+    pub fn fcs_errors(&self) -> usize {
+        self.fcs_errors
+    }
+
+    /// Returns the number of bytes buffered while waiting for the rest of a frame.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns the number of bytes the buffered frame still needs, at least, or `None` when no
+    /// frame is buffered.
+    ///
+    /// The length of an advanced option frame is only known at its closing flag, so only the
+    /// minimum frame length is accounted for.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cmux::parser::FrameParser;
+    ///
+    /// let mut parser = FrameParser::new();
+    /// assert_eq!(parser.needed(), None);
+    /// parser.feed(&[0xF9, 0x07, 0xEF, 0x05, 0x41]);
+    /// assert_eq!(parser.needed(), Some(3));
+    /// ```
+    pub fn needed(&self) -> Option<usize> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+        if self.scanner.advanced {
+            return Some(MIN_ADVANCED_LENGTH.saturating_sub(self.buffer.len()).max(1));
+        }
+        let needed = |data: &[u8]| match FrameRef::parse_prefix_with(
+            data,
+            self.scanner.dialect.length_encoding,
+        ) {
+            Ok(ParseOutcome::Incomplete { needed }) => Some(needed),
+            _ => None,
+        };
+        if self.scanner.first {
+            // The first frame of a dialect without leading flag.
+            let mut data = Vec::with_capacity(self.buffer.len() + 1);
+            data.push(self.scanner.dialect.flag);
+            data.extend_from_slice(&self.buffer);
+            return needed(&data);
+        }
+        needed(&self.buffer)
+    }
+
+    /// Drops buffered bytes and clears the skipped byte count.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.skipped = 0;
+        self.scanner.restart();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FrameBuilder;
+
+    fn at_frame() -> Vec<u8> {
+        FrameBuilder::default()
+            .with_content("AT+CMUX?".to_string())
+            .build()
+            .unwrap()
+            .to_bytes()
+    }
+
+    #[test]
+    fn test_feed_byte_by_byte() {
+        let mut data = at_frame();
+        data.extend(at_frame());
+        let mut parser = FrameParser::new();
+        let frames: Vec<Frame> = data.iter().flat_map(|b| parser.feed(&[*b])).collect();
+        assert_eq!(frames.len(), 2);
+        assert!(frames.iter().all(|f| f.verify().is_ok()));
+        assert_eq!(parser.skipped(), 0);
+    }
+
+    #[test]
+    fn test_shared_flag() {
+        let data = hex::decode("F9033F011CF9073F01DEF9").unwrap();
+        let frames = FrameParser::new().feed(&data);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].to_hex_string(), "f9073f01def9");
+    }
+
+    #[test]
+    fn test_resync_after_garbage() {
+        let mut data = vec![0x12, 0x34];
+        // A truncated frame whose closing flag is missing.
+        data.extend([0xF9, 0x07, 0xEF, 0x15, 0x41]);
+        data.extend(at_frame());
+        data.extend([0x57]);
+
+        let mut parser = FrameParser::new();
+        let frames = parser.feed(&data);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].to_bytes(), at_frame());
+        assert_eq!(parser.skipped(), 2 + 5);
+        assert_eq!(parser.buffered(), 2);
+        assert_eq!(parser.needed(), Some(4));
+    }
+
+    #[test]
+    fn test_corrupted_frames() {
+        // A frame with a wrong FCS sharing its closing flag with a valid frame.
+        let mut data = at_frame();
+        let fcs = data.len() - 2;
+        data[fcs] ^= 0xFF;
+        data.pop();
+        data.extend(at_frame());
+        let mut parser = FrameParser::new();
+        let frames = parser.feed(&data);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].to_bytes(), at_frame());
+        assert_eq!(parser.skipped(), at_frame().len() - 2);
+        assert_eq!(parser.fcs_errors(), 1);
+
+        // A corrupted two-octet length indicator does not hold back the frames after it.
+        let mut data = vec![0xF9, 0x07, 0xEF, 0x00, 0xFF];
+        data.extend(at_frame());
+        let mut parser = FrameParser::new();
+        assert_eq!(parser.feed(&data).len(), 1);
+        assert_eq!(parser.skipped(), 5);
+        assert_eq!(parser.fcs_errors(), 0);
+        assert_eq!(parser.buffered(), 1);
+    }
+
+    #[test]
+    fn test_advanced() {
+        let mut data = vec![0x00, 0x7E, 0x7E];
+        data.extend(hex::decode("033FFC7E07EF7D5E01057E7E0102037E").unwrap());
+        let mut parser = FrameParser::advanced();
+        assert!(parser.feed(&[0x55]).is_empty());
+        let frames: Vec<Frame> = data.chunks(3).flat_map(|c| parser.feed(c)).collect();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].content, &[0x7E, 0x01][..]);
+        assert!(frames.iter().all(|f| f.verify().is_ok()));
+        assert_eq!(parser.skipped(), 2 + 3);
+        assert_eq!(parser.buffered(), 1);
+    }
+
+    #[test]
+    fn test_detect() {
+        let mut data = at_frame();
+        data.extend(at_frame());
+        let detection = FrameParser::detect(&data).unwrap();
+        assert_eq!(
+            detection,
+            Detection {
+                advanced: false,
+                escaped: false,
+                frames: 2
+            }
+        );
+
+        let data = hex::decode("7E033FFC7E07EF41057E").unwrap();
+        let detection = FrameParser::detect(&data).unwrap();
+        assert!(detection.advanced && !detection.escaped);
+        assert_eq!(detection.frames, 2);
+        assert_eq!(FrameParser::detect(&[0x12, 0xF9, 0x7E]), None);
+    }
+
+    #[test]
+    fn test_scan() {
+        // Noise before a valid frame, idle fill, a corrupted frame and trailing noise.
+        let mut data = hex::decode("F9033F01").unwrap();
+        data.extend(at_frame());
+        data.extend(hex::decode("F9F9073F011CF9AA").unwrap());
+        let scanned: Vec<Scanned> = scan(&data).collect();
+        let offset = 5 + at_frame().len();
+        assert_eq!(scanned[0], Scanned::Skipped { offset: 0, len: 4 });
+        assert!(
+            matches!(&scanned[1], Scanned::Frame { offset: 4, frame } if frame.verify().is_ok())
+        );
+        assert!(matches!(
+            &scanned[2],
+            Scanned::Frame { offset: o, frame } if *o == offset && frame.verify().is_err()
+        ));
+        assert_eq!(
+            scanned[3],
+            Scanned::Skipped {
+                offset: offset + 6,
+                len: 1
+            }
+        );
+        assert_eq!(scanned.len(), 4);
+
+        // The length indicator of the noise spans a valid frame.
+        let data = hex::decode("F9073F09F9033F011CF9").unwrap();
+        let scanned: Vec<Scanned> = scan(&data).collect();
+        assert_eq!(scanned[0], Scanned::Skipped { offset: 0, len: 4 });
+        assert!(matches!(scanned[1], Scanned::Frame { offset: 4, .. }));
+    }
+
+    #[test]
+    fn test_frame_iter() {
+        let mut data = vec![0x00];
+        data.extend(at_frame());
+        data.extend([0xF9, 0x03, 0x3F, 0x01, 0x1C, 0xF9, 0x00]);
+        let mut iter = Frame::iter_from(&data);
+        assert_eq!(iter.next().unwrap().unwrap().content, b"AT+CMUX?\r\n");
+        assert_eq!(iter.skipped(), 1);
+        let frame = iter.next().unwrap().unwrap();
+        assert_eq!(frame.address.dlci().into_bits(), 0);
+        assert_eq!(iter.next(), None);
+        assert_eq!((iter.skipped(), iter.offset()), (2, data.len()));
+    }
+
+    #[test]
+    fn test_dialect() {
+        // 0x7E flags, the first frame without opening flag, noise and a fill flag.
+        let dialect = Dialect::SPEC.with_flag(0x7E).with_leading_flag(false);
+        let data = hex::decode("033F011C7E007E7E073F01DE7E").unwrap();
+        let mut parser = FrameParser::new().with_dialect(dialect);
+        let frames: Vec<Frame> = data.iter().flat_map(|b| parser.feed(&[*b])).collect();
+        assert_eq!(frames.len(), 2);
+        assert!(frames.iter().all(|f| dialect.verify(f).is_ok()));
+        assert_eq!(parser.skipped(), 1);
+
+        let scanned: Vec<Scanned> = scan_with(&data, &dialect).collect();
+        assert!(matches!(scanned[0], Scanned::Frame { offset: 0, .. }));
+        assert_eq!(scanned[1], Scanned::Skipped { offset: 5, len: 1 });
+        assert!(matches!(scanned[2], Scanned::Frame { offset: 7, .. }));
+        assert_eq!(FrameIter::new(&data).count(), 0);
+    }
+
+    #[test]
+    fn test_idle_flags() {
+        let data = hex::decode("F9F9F9F9033F011CF9F9").unwrap();
+        let mut parser = FrameParser::new();
+        assert_eq!(parser.feed(&data).len(), 1);
+        assert_eq!(parser.skipped(), 0);
+        assert_eq!(parser.buffered(), 1);
+    }
+
+    #[test]
+    fn test_legacy_length() {
+        let mut frame = FrameBuilder::default()
+            .with_binary_content(vec![0xAA; 300])
+            .build()
+            .unwrap();
+        frame.checksum = Dialect::LEGACY.checksum(&frame.as_frame_ref());
+        let data = Dialect::LEGACY.encode(&frame);
+        let mut parser = FrameParser::new().with_length_encoding(LengthEncoding::Legacy);
+        let frames = parser.feed(&data);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].content, frame.content);
+        assert_eq!(parser.skipped(), 0);
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = ParserLimits::new().with_max_buffered(64);
+        let mut parser = FrameParser::advanced().with_limits(limits);
+        parser.feed(&[0x7E, 0x07]);
+        for _ in 0..10 {
+            parser.feed(&[0x41; 50]);
+            assert!(parser.buffered() <= 64);
+        }
+        let frame = hex::decode("7E07EF7D5E01057E").unwrap();
+        assert_eq!(parser.feed(&frame).len(), 1);
+
+        let mut data = at_frame();
+        let len = data.len();
+        data.extend(hex::decode("F9033F011CF9").unwrap());
+        let limits = ParserLimits::new().with_max_frame_length(len - 1);
+        let mut parser = FrameParser::new().with_limits(limits);
+        let frames = parser.feed(&data);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].to_hex_string(), "f9033f011cf9");
+        assert_eq!(parser.skipped(), len - 2);
+        let scanned = FrameParser::new().with_limits(limits).feed_scanned(&data);
+        assert_eq!(scanned[0], Scanned::Skipped { offset: 0, len });
+
+        let limits = ParserLimits::new().with_max_skipped_run(16);
+        let mut parser = FrameParser::new().with_limits(limits);
+        let scanned = parser.feed_scanned(&[0x41; 20]);
+        assert_eq!(scanned, vec![Scanned::Skipped { offset: 0, len: 20 }]);
+        assert_eq!(parser.skipped(), 20);
+        assert!(parser.feed_scanned(&[0x41; 4]).is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+use crate::control::{ControlMessage, MessageType};
+use crate::parser::FrameParser;
+use std::fmt::{Display, Formatter};
+use std::io::{self, ErrorKind, Read, Write};
+use std::time::{Duration, Instant};
+
+/// Round-trip statistics of a series of Test commands.
+///
+/// # Example
+///
+/// ```
+/// use cmux::ping::PingStats;
+/// use std::time::Duration;
+///
+/// let mut stats = PingStats::default();
+/// stats.record(Some(Duration::from_millis(10)));
+/// stats.record(Some(Duration::from_millis(30)));
+/// stats.record(None);
+/// assert_eq!(stats.avg(), Some(Duration::from_millis(20)));
+/// assert_eq!(
+///     stats.to_string(),
+///     "3 packets transmitted, 2 received, 33% packet loss\nrtt min/avg/max = 10.000/20.000/30.000 ms"
+/// );
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PingStats {
+    pub transmitted: u32,
+    pub received: u32,
+    pub min: Option<Duration>,
+    pub max: Option<Duration>,
+    pub total: Duration,
+}
+
+impl PingStats {
+    /// Records one Test command and its round-trip time, or `None` if no response arrived.
+    pub fn record(&mut self, rtt: Option<Duration>) {
+        self.transmitted += 1;
+        if let Some(rtt) = rtt {
+            self.received += 1;
+            self.total += rtt;
+            self.min = Some(self.min.map_or(rtt, |min| min.min(rtt)));
+            self.max = Some(self.max.map_or(rtt, |max| max.max(rtt)));
+        }
+    }
+
+    /// Returns the average round-trip time of the received responses.
+    pub fn avg(&self) -> Option<Duration> {
+        (self.received > 0).then(|| self.total / self.received)
+    }
+
+    /// Returns the percentage of Test commands left without a response.
+    pub fn loss(&self) -> u32 {
+        match self.transmitted {
+            0 => 0,
+            sent => (sent - self.received) * 100 / sent,
+        }
+    }
+}
+
+impl Display for PingStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} packets transmitted, {} received, {}% packet loss",
+            self.transmitted,
+            self.received,
+            self.loss()
+        )?;
+        if let (Some(min), Some(avg), Some(max)) = (self.min, self.avg(), self.max) {
+            let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+            write!(
+                f,
+                "\nrtt min/avg/max = {:.3}/{:.3}/{:.3} ms",
+                ms(min),
+                ms(avg),
+                ms(max)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the value octets of the Test command with sequence number `seq`.
+pub fn test_pattern(seq: u32) -> Vec<u8> {
+    let mut value = b"cmux".to_vec();
+    value.extend(seq.to_be_bytes());
+    value
+}
+
+/// Sends one Test command and waits for the peer to echo it.
+///
+/// Reads that time out or would block are retried until `timeout` elapses, so `port` may be
+/// a serial port configured with a short read timeout.
+///
+/// # Arguments
+///
+/// * `port` - The multiplexed link, already in CMUX mode.
+/// * `seq` - The sequence number carried by the Test value octets.
+/// * `timeout` - How long to wait for the response.
+///
+/// # Returns
+///
+/// - `Ok(Some(Duration))`: The round-trip time.
+/// - `Ok(None)`: If no matching response arrived in time.
+/// - `Err(io::Error)`: If the port fails.
+pub fn ping<P: Read + Write>(
+    port: &mut P,
+    seq: u32,
+    timeout: Duration,
+) -> io::Result<Option<Duration>> {
+    let pattern = test_pattern(seq);
+    let frame = ControlMessage::test(true, pattern.clone())
+        .to_frame(true)
+        .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+    let start = Instant::now();
+    port.write_all(&frame.to_bytes())?;
+    port.flush()?;
+
+    let mut parser = FrameParser::new();
+    let mut buf = [0u8; 256];
+    while start.elapsed() < timeout {
+        let n = match port.read(&mut buf) {
+            Ok(0) => return Ok(None),
+            Ok(n) => n,
+            Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => continue,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        for frame in parser.feed(&buf[..n]) {
+            let Ok(messages) = ControlMessage::from_frame(&frame) else {
+                continue;
+            };
+            if messages
+                .iter()
+                .any(|m| m.message_type == MessageType::Test && !m.cr && m.value == pattern)
+            {
+                return Ok(Some(start.elapsed()));
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// A peer answering every Test command with a Test response.
+    #[derive(Default)]
+    struct EchoPeer {
+        rx: VecDeque<u8>,
+    }
+
+    impl Write for EchoPeer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            for frame in FrameParser::new().feed(buf) {
+                for mut message in ControlMessage::from_frame(&frame).unwrap() {
+                    message.cr = false;
+                    self.rx.extend(message.to_frame(false).unwrap().to_bytes());
+                }
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Read for EchoPeer {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.rx.is_empty() {
+                return Err(ErrorKind::TimedOut.into());
+            }
+            let n = buf.len().min(self.rx.len());
+            for (dst, src) in buf.iter_mut().zip(self.rx.drain(..n)) {
+                *dst = src;
+            }
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_ping_echo() {
+        let mut peer = EchoPeer::default();
+        let rtt = ping(&mut peer, 7, Duration::from_secs(1)).unwrap();
+        assert!(rtt.is_some());
+    }
+
+    #[test]
+    fn test_ping_timeout() {
+        let mut silent = io::Cursor::new(Vec::new());
+        let rtt = ping(&mut silent, 1, Duration::from_millis(10)).unwrap();
+        assert_eq!(rtt, None);
+        assert_eq!(
+            hex::encode_upper(silent.get_ref()),
+            "F903EF152311636D757800000001EEF9"
+        );
+    }
+}
@@ -0,0 +1,390 @@
+use crate::advanced::{checksum_advanced, ADVANCED_FLAG};
+use crate::types::{frame_checksum, Address, Control, Frame, Length, LengthEncoding, FLAG};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter, Write};
+
+/// Number of content octets written on each line.
+const OCTETS_PER_LINE: usize = 16;
+
+/// Multi-line breakdown of a [`Frame`]
+///
+/// Every field of the basic option encoding is written on its own line: the raw octets,
+/// followed by their meaning. The content is written as hex, 16 octets per line, and as
+/// text. The FCS line tells whether the frame passes [`Frame::verify`].
+///
+/// With [`Pretty::advanced`], the fields are those of the advanced option encoding: 0x7E
+/// flags, no length indicator, and the advanced option FCS, before control-octet
+/// transparency.
+///
+/// # Example
+///
+/// ```
+/// use cmux::pretty::Pretty;
+/// use cmux::types::Frame;
+///
+/// let frame = Frame::from_bytes(vec![0xF9, 0x07, 0xEF, 0x05, 0x41, 0x54, 0x30, 0xF9]).unwrap();
+/// assert_eq!(
+///     Pretty::new(&frame).to_string(),
+///     "Flag     F9
+/// Address  07     DLCI=1 C/R=1 EA=1
+/// Control  EF     UIH P/F=0
+/// Length   05     2 octets
+/// Content  41 54
+///          \"AT\"
+/// FCS      30     OK
+/// Flag     F9"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Pretty<'a> {
+    frame: &'a Frame,
+    advanced: bool,
+}
+
+impl<'a> Pretty<'a> {
+    /// Creates the breakdown of `frame`.
+    pub fn new(frame: &'a Frame) -> Self {
+        Pretty {
+            frame,
+            advanced: false,
+        }
+    }
+
+    /// Breaks the frame down as it is encoded by [`Frame::to_bytes_advanced`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cmux::pretty::Pretty;
+    /// use cmux::types::Frame;
+    ///
+    /// let frame = Frame::from_bytes_advanced(&[0x7E, 0x03, 0x3F, 0xFC, 0x7E]).unwrap();
+    /// assert_eq!(
+    ///     Pretty::new(&frame).advanced().to_string(),
+    ///     "Flag     7E
+    /// Address  03     DLCI=0 C/R=1 EA=1
+    /// Control  3F     SABM P/F=1
+    /// Content  (empty)
+    /// FCS      FC     OK
+    /// Flag     7E"
+    /// );
+    /// ```
+    pub fn advanced(mut self) -> Self {
+        self.advanced = true;
+        self
+    }
+}
+
+/// Writes the octets of `data` as upper case hex separated by spaces.
+fn hex_octets(data: &[u8]) -> String {
+    let mut text = String::with_capacity(data.len() * 3);
+    for (i, octet) in data.iter().enumerate() {
+        if i > 0 {
+            text.push(' ');
+        }
+        let _ = write!(text, "{:02X}", octet);
+    }
+    text
+}
+
+impl Display for Pretty<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let frame = self.frame;
+        let length = Length::from_indicator(frame.length);
+        let fcs = match frame.verify() {
+            Ok(()) => String::from("OK"),
+            Err(e) => e.to_string(),
+        };
+        let field = |f: &mut Formatter<'_>, name: &str, octets: &[u8], meaning: &dyn Display| {
+            writeln!(f, "{:<9}{:<7}{}", name, hex_octets(octets), meaning)
+        };
+
+        let content = frame.content.as_bytes();
+        let (header, footer) = if self.advanced {
+            (ADVANCED_FLAG, ADVANCED_FLAG)
+        } else {
+            (frame.header, frame.footer)
+        };
+
+        writeln!(f, "{:<9}{:02X}", "Flag", header)?;
+        field(f, "Address", &[frame.address.into_bits()], &frame.address)?;
+        field(f, "Control", &[frame.control.into_bits()], &frame.control)?;
+        if !self.advanced {
+            field(
+                f,
+                "Length",
+                &length.to_bytes(),
+                &format_args!("{} octets", length.len()),
+            )?;
+        }
+        if content.is_empty() {
+            writeln!(f, "{:<9}(empty)", "Content")?;
+        } else {
+            let lines: Vec<&[u8]> = content.chunks(OCTETS_PER_LINE).collect();
+            for (i, line) in lines.iter().enumerate() {
+                let name = if i == 0 { "Content" } else { "" };
+                writeln!(f, "{:<9}{}", name, hex_octets(line))?;
+            }
+            writeln!(f, "{:<9}{:?}", "", frame.content.to_string_lossy())?;
+        }
+        if self.advanced {
+            // The advanced option FCS is computed as the frame is encoded.
+            let fcs = checksum_advanced(
+                frame.address.into_bits(),
+                frame.control.into_bits(),
+                content,
+            );
+            field(f, "FCS", &[fcs], &"OK")?;
+        } else {
+            field(f, "FCS", &[frame.checksum], &fcs)?;
+        }
+        write!(f, "{:<9}{:02X}", "Flag", footer)
+    }
+}
+
+/// Field of a basic option frame an octet belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Flag,
+    Address,
+    Control,
+    Length,
+    Content,
+    Fcs,
+    /// An octet after the closing flag.
+    Trailing,
+}
+
+impl Field {
+    /// Returns the name of the field.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Field::Flag => "Flag",
+            Field::Address => "Address",
+            Field::Control => "Control",
+            Field::Length => "Length",
+            Field::Content => "Content",
+            Field::Fcs => "FCS",
+            Field::Trailing => "Trailing",
+        }
+    }
+}
+
+/// One octet of a frame, labeled by [`annotate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    /// Position of the octet in the input.
+    pub offset: usize,
+    pub octet: u8,
+    pub field: Field,
+    /// The octet in binary, bit 8 first, with a space between the subfields.
+    pub bits: String,
+    /// What the octet means, such as `DLCI=1 C/R=1 EA=1`.
+    pub label: String,
+    /// False if the octet is wrong: a flag other than 0xF9, a bad FCS or a trailing octet.
+    pub ok: bool,
+}
+
+/// Writes `octet` in binary, bit 8 first, splitting it before the given bit numbers.
+///
+/// Bits are numbered from 1, the least significant bit, as 07.10 does.
+fn bits(octet: u8, splits: &[u8]) -> String {
+    let mut text = String::with_capacity(12);
+    for bit in (1..=8).rev() {
+        text.push(if octet & (1 << (bit - 1)) != 0 {
+            '1'
+        } else {
+            '0'
+        });
+        if splits.contains(&bit) {
+            text.push(' ');
+        }
+    }
+    text
+}
+
+/// Labels every octet of a basic option frame.
+///
+/// The octets are read as flag, address, control, length indicator, content, FCS and flag.
+/// Malformed input is labeled as far as it goes: a truncated frame yields fewer annotations
+/// and octets after the closing flag are marked [`Field::Trailing`].
+///
+/// # Example
+///
+/// ```
+/// use cmux::pretty::{annotate, Field};
+///
+/// let annotations = annotate(&[0xF9, 0x07, 0xEF, 0x05, 0x41, 0x54, 0x00, 0xF9]);
+/// assert_eq!(annotations[1].bits, "000001 1 1");
+/// assert_eq!(annotations[1].label, "DLCI=1 C/R=1 EA=1");
+/// assert_eq!(annotations[6].field, Field::Fcs);
+/// assert_eq!(annotations[6].label, "expected 0x30");
+/// assert!(!annotations[6].ok);
+/// ```
+pub fn annotate(data: &[u8]) -> Vec<Annotation> {
+    let mut annotations = Vec::with_capacity(data.len());
+    let mut push = |field: Field, bits: String, label: String, ok: bool| {
+        let offset = annotations.len();
+        annotations.push(Annotation {
+            offset,
+            octet: data[offset],
+            field,
+            bits,
+            label,
+            ok,
+        });
+    };
+    let flag = |octet: u8| {
+        let label = match octet {
+            FLAG => String::new(),
+            _ => format!("expected 0x{:02X}", FLAG),
+        };
+        (bits(octet, &[]), label, octet == FLAG)
+    };
+
+    let Some(&header) = data.first() else {
+        return annotations;
+    };
+    let (b, label, ok) = flag(header);
+    push(Field::Flag, b, label, ok);
+    let Some(&address) = data.get(1) else {
+        return annotations;
+    };
+    let address = Address::from_bits(address);
+    push(
+        Field::Address,
+        bits(address.into_bits(), &[3, 2]),
+        address.to_string(),
+        true,
+    );
+    let Some(&control) = data.get(2) else {
+        return annotations;
+    };
+    let control = Control::from_bits(control);
+    push(
+        Field::Control,
+        bits(control.into_bits(), &[6, 5]),
+        control.to_string(),
+        true,
+    );
+    let Some((length, octets)) = Length::decode(&data[3..], LengthEncoding::Spec) else {
+        if data.len() > 3 {
+            push(
+                Field::Length,
+                bits(data[3], &[2]),
+                String::from("L1-L7 EA=0"),
+                true,
+            );
+        }
+        return annotations;
+    };
+    if octets == 1 {
+        let label = format!("{} octets EA=1", length.len());
+        push(Field::Length, bits(data[3], &[2]), label, true);
+    } else {
+        push(
+            Field::Length,
+            bits(data[3], &[2]),
+            String::from("L1-L7 EA=0"),
+            true,
+        );
+        let label = format!("L8-L15, {} octets", length.len());
+        push(Field::Length, bits(data[4], &[]), label, true);
+    }
+
+    let start = 3 + octets;
+    let end = (start + length.len()).min(data.len());
+    for &octet in &data[start..end] {
+        let label = match octet {
+            0x20..=0x7E => format!("{:?}", octet as char),
+            _ => String::new(),
+        };
+        push(Field::Content, bits(octet, &[]), label, true);
+    }
+    let Some(&checksum) = data.get(end).filter(|_| end == start + length.len()) else {
+        return annotations;
+    };
+    let expected = frame_checksum(
+        address,
+        control,
+        length.indicator(),
+        &data[start..end],
+        LengthEncoding::Spec,
+    );
+    let label = if checksum == expected {
+        String::from("OK")
+    } else {
+        format!("expected 0x{:02X}", expected)
+    };
+    push(Field::Fcs, bits(checksum, &[]), label, checksum == expected);
+    let Some(&footer) = data.get(end + 1) else {
+        return annotations;
+    };
+    let (b, label, ok) = flag(footer);
+    push(Field::Flag, b, label, ok);
+    for &octet in &data[end + 2..] {
+        push(Field::Trailing, bits(octet, &[]), String::new(), false);
+    }
+    annotations
+}
+
+impl Frame {
+    /// Returns the multi-line breakdown of the frame described in [`Pretty`].
+    pub fn pretty(&self) -> Pretty<'_> {
+        Pretty::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FrameBuilder;
+
+    #[test]
+    fn test_pretty_long_content() {
+        let frame = FrameBuilder::default()
+            .with_binary_content((0..130).collect())
+            .build()
+            .unwrap();
+        let text = frame.pretty().to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[3], "Length   04 01  130 octets");
+        assert_eq!(
+            lines[4],
+            "Content  00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F"
+        );
+        assert_eq!(lines[12], "         80 81");
+        assert_eq!(lines.len(), 16);
+    }
+
+    #[test]
+    fn test_annotate_malformed() {
+        let data = [0xF9, 0x07, 0xEF, 0x08, 0x01, 0x41, 0x54];
+        let annotations = annotate(&data);
+        assert_eq!(annotations.len(), data.len());
+        assert_eq!(annotations[3].bits, "0000100 0");
+        assert_eq!(annotations[4].label, "L8-L15, 132 octets");
+        assert_eq!(annotations[6].field, Field::Content);
+
+        let annotations = annotate(&[0xF9, 0x03, 0x3F, 0x01, 0x1C, 0xF9, 0x00]);
+        assert_eq!(annotations[2].bits, "001 1 1111");
+        assert_eq!(annotations[4].label, "OK");
+        assert_eq!(annotations[6].field, Field::Trailing);
+        assert!(!annotations[6].ok);
+    }
+
+    #[test]
+    fn test_pretty_bad_checksum() {
+        let mut frame = Frame::from_bytes(vec![0xF9, 0x03, 0x3F, 0x01, 0x1C, 0xF9]).unwrap();
+        assert!(frame.pretty().to_string().contains("Content  (empty)\n"));
+        frame.checksum = 0x00;
+        let text = frame.pretty().to_string();
+        let fcs = text.lines().find(|line| line.starts_with("FCS")).unwrap();
+        assert_eq!(
+            fcs,
+            format!("FCS      00     {}", frame.verify().unwrap_err())
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use crate::bridge::Endpoint;
+use crate::transport::Transport;
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
+use nix::pty::openpty;
+use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg};
+use nix::unistd::ttyname;
+use std::fs::File;
+use std::io::{self, ErrorKind, Read, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::{Path, PathBuf};
+
+/// Pseudo-terminal exposing one DLC to other programs
+///
+/// Programs such as minicom or pppd open the slave side, found at [`Pty::path`] or at the
+/// symbolic link created by [`Pty::with_link`]. The multiplexer reads and writes the master
+/// side. The slave is in raw mode, and stays open so the master does not fail when the last
+/// program closes it.
+///
+/// # Example
+///
+/// ```no_run
+/// use cmux::pty::Pty;
+///
+/// let mut pty = Pty::open().unwrap().with_link("/tmp/cmux1").unwrap();
+/// pty.write(b"RING\r\n").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Pty {
+    master: File,
+    _slave: File,
+    path: PathBuf,
+    link: Option<PathBuf>,
+}
+
+impl Pty {
+    /// Opens a pseudo-terminal in raw mode with a non-blocking master.
+    pub fn open() -> io::Result<Self> {
+        let pty = openpty(None, None)?;
+        // SAFETY: openpty returned two open descriptors that nothing else owns.
+        let (master, slave) =
+            unsafe { (File::from_raw_fd(pty.master), File::from_raw_fd(pty.slave)) };
+        let mut termios = tcgetattr(slave.as_raw_fd())?;
+        cfmakeraw(&mut termios);
+        tcsetattr(slave.as_raw_fd(), SetArg::TCSANOW, &termios)?;
+        fcntl(master.as_raw_fd(), FcntlArg::F_SETFL(OFlag::O_NONBLOCK))?;
+        let path = ttyname(slave.as_raw_fd())?;
+        Ok(Pty {
+            master,
+            _slave: slave,
+            path,
+            link: None,
+        })
+    }
+
+    /// Creates a symbolic link to the slave, removed when the pseudo-terminal is dropped.
+    ///
+    /// An existing file at `link` is replaced.
+    pub fn with_link(mut self, link: impl AsRef<Path>) -> io::Result<Self> {
+        let link = link.as_ref();
+        match std::fs::remove_file(link) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        std::os::unix::fs::symlink(&self.path, link)?;
+        self.link = Some(link.to_path_buf());
+        Ok(self)
+    }
+
+    /// Returns the path of the slave. Example: /dev/pts/3
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the symbolic link to the slave.
+    pub fn link(&self) -> Option<&Path> {
+        self.link.as_deref()
+    }
+
+    /// Reads the bytes written to the slave.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(usize)`: The number of bytes read, 0 if none are available.
+    /// - `Err(io::Error)`: If the master failed.
+    pub fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.master.read(buf) {
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(0),
+            result => result,
+        }
+    }
+
+    /// Writes `data` to be read from the slave.
+    pub fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.master.write_all(data)
+    }
+}
+
+impl Endpoint for Pty {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Pty::read(self, buf)
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        Pty::write(self, data)
+    }
+}
+
+/// A program opening the slave, such as a host driver under test, can also run a whole
+/// multiplexer session over the pseudo-terminal.
+impl Transport for Pty {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Pty::read(self, buf)
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        Pty::write(self, data)
+    }
+}
+
+impl Drop for Pty {
+    fn drop(&mut self) {
+        if let Some(link) = &self.link {
+            let _ = std::fs::remove_file(link);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pty() {
+        let link = std::env::temp_dir().join(format!("cmux-test-{}", std::process::id()));
+        let mut pty = Pty::open().unwrap().with_link(&link).unwrap();
+        assert_eq!(std::fs::read_link(&link).unwrap(), pty.path());
+
+        let mut slave = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&link)
+            .unwrap();
+        slave.write_all(b"AT\r").unwrap();
+        let mut buf = [0; 8];
+        let mut count = 0;
+        while count < 3 {
+            count += pty.read(&mut buf[count..]).unwrap();
+        }
+        assert_eq!(&buf[..3], b"AT\r");
+
+        pty.write(b"OK\r\n").unwrap();
+        let mut buf = [0; 4];
+        slave.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"OK\r\n");
+
+        drop(pty);
+        assert!(std::fs::symlink_metadata(&link).is_err());
+    }
+
+    #[test]
+    fn test_stale_link() {
+        // A link left behind by a daemon that did not exit cleanly is replaced.
+        let link = std::env::temp_dir().join(format!("cmux-stale-{}", std::process::id()));
+        let _ = std::fs::remove_file(&link);
+        std::os::unix::fs::symlink("/dev/pts/cmux-gone", &link).unwrap();
+        let pty = Pty::open().unwrap().with_link(&link).unwrap();
+        assert_eq!(std::fs::read_link(&link).unwrap(), pty.path());
+        assert_eq!(pty.link(), Some(link.as_path()));
+        drop(pty);
+        assert!(std::fs::symlink_metadata(&link).is_err());
+
+        // So is a file.
+        std::fs::write(&link, b"").unwrap();
+        let pty = Pty::open().unwrap().with_link(&link).unwrap();
+        assert_eq!(std::fs::read_link(&link).unwrap(), pty.path());
+        drop(pty);
+        assert!(std::fs::symlink_metadata(&link).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use crate::types::{Control, FrameType};
+
+/// Modulus of the N(S) and N(R) sequence numbers.
+pub const MODULUS: u8 = 8;
+
+/// Sequence number state of one DLC in error recovery mode
+///
+/// * V(S): send state variable, the N(S) of the next I frame to send.
+/// * V(A): acknowledge state variable, the oldest unacknowledged N(S).
+/// * V(R): receive state variable, the N(S) expected in the next I frame received.
+///
+/// # Example
+///
+/// ```
+/// use cmux::recovery::Sequence;
+///
+/// let mut seq = Sequence::new(2);
+/// let i0 = seq.next_i_frame().unwrap();
+/// let i1 = seq.next_i_frame().unwrap();
+/// assert_eq!((i0.ns(), i1.ns()), (Some(0), Some(1)));
+/// // The window of 2 frames is full until the peer acknowledges.
+/// assert!(seq.next_i_frame().is_none());
+/// assert!(seq.acknowledge(1));
+/// assert_eq!(seq.outstanding(), 1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sequence {
+    vs: u8,
+    va: u8,
+    vr: u8,
+    window: u8,
+}
+
+impl Default for Sequence {
+    /// A sequence with the default window size k of 2.
+    fn default() -> Self {
+        Sequence::new(2)
+    }
+}
+
+impl Sequence {
+    /// Creates a sequence with every state variable at 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The window size k, from 1 to 7.
+    pub const fn new(window: u8) -> Self {
+        let window = if window == 0 {
+            1
+        } else if window >= MODULUS {
+            MODULUS - 1
+        } else {
+            window
+        };
+        Sequence {
+            vs: 0,
+            va: 0,
+            vr: 0,
+            window,
+        }
+    }
+
+    /// Returns the send state variable V(S).
+    pub const fn vs(&self) -> u8 {
+        self.vs
+    }
+
+    /// Returns the acknowledge state variable V(A).
+    pub const fn va(&self) -> u8 {
+        self.va
+    }
+
+    /// Returns the receive state variable V(R).
+    pub const fn vr(&self) -> u8 {
+        self.vr
+    }
+
+    /// Returns the number of I frames sent and not yet acknowledged.
+    pub const fn outstanding(&self) -> u8 {
+        (self.vs + MODULUS - self.va) % MODULUS
+    }
+
+    /// Returns the control field of the next I frame, or `None` if the window is full.
+    ///
+    /// The frame carries N(S) = V(S) and N(R) = V(R); V(S) is then incremented.
+    pub fn next_i_frame(&mut self) -> Option<Control> {
+        if self.outstanding() >= self.window {
+            return None;
+        }
+        let control = Control::new()
+            .with_frame_type(FrameType::I)
+            .with_ns(self.vs)
+            .with_nr(self.vr);
+        self.vs = (self.vs + 1) % MODULUS;
+        Some(control)
+    }
+
+    /// Returns the control field of a supervisory frame acknowledging every frame received.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_type` - RR, RNR or REJ. Other frame types are sent as RR.
+    pub fn supervisory(&self, frame_type: FrameType) -> Control {
+        let frame_type = match frame_type {
+            FrameType::RNR | FrameType::REJ => frame_type,
+            _ => FrameType::RR,
+        };
+        Control::new().with_frame_type(frame_type).with_nr(self.vr)
+    }
+
+    /// Accepts the N(R) of a received frame, acknowledging every I frame before it.
+    ///
+    /// # Returns
+    ///
+    /// False if `nr` does not lie between V(A) and V(S), which is a sequence error.
+    pub fn acknowledge(&mut self, nr: u8) -> bool {
+        let acked = (nr + MODULUS - self.va) % MODULUS;
+        if acked > self.outstanding() {
+            return false;
+        }
+        self.va = nr % MODULUS;
+        true
+    }
+
+    /// Handles the control field of a received I frame.
+    ///
+    /// # Returns
+    ///
+    /// True if the frame is the next one expected, in which case V(R) is incremented and its
+    /// N(R) acknowledged. False if it is out of sequence and should be answered with REJ.
+    pub fn receive(&mut self, control: Control) -> bool {
+        let (Some(ns), Some(nr)) = (control.ns(), control.nr()) else {
+            return false;
+        };
+        if ns != self.vr {
+            return false;
+        }
+        self.vr = (self.vr + 1) % MODULUS;
+        self.acknowledge(nr)
+    }
+
+    /// Returns V(S) to V(A), so the unacknowledged I frames are sent again after REJ or T1 expiry.
+    pub fn rewind(&mut self) {
+        self.vs = self.va;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_control_fields() {
+        let i = Control::new()
+            .with_frame_type(FrameType::I)
+            .with_pf(true)
+            .with_ns(5)
+            .with_nr(3);
+        assert_eq!(i.into_bits(), 0b0111_1010);
+        assert_eq!(Control::from_bits(0b0111_1010).frame_type(), FrameType::I);
+
+        let rnr = Control::new().with_frame_type(FrameType::RNR).with_nr(7);
+        assert_eq!(rnr.into_bits(), 0b1110_0101);
+        assert_eq!(Control::from_bits(0xE5).frame_type(), FrameType::RNR);
+        assert_eq!(Control::from_bits(0x09).frame_type(), FrameType::REJ);
+        assert_eq!(Control::from_bits(0x21).nr(), Some(1));
+        assert_eq!(Control::default().nr(), None);
+    }
+
+    #[test]
+    fn test_wraparound() {
+        let mut tx = Sequence::new(7);
+        let mut rx = Sequence::new(7);
+        for _ in 0..20 {
+            let control = tx.next_i_frame().unwrap();
+            assert!(rx.receive(control));
+            assert!(tx.acknowledge(rx.supervisory(FrameType::RR).nr().unwrap()));
+            assert_eq!(tx.outstanding(), 0);
+        }
+        assert_eq!(tx.vs(), 20 % MODULUS);
+
+        // A frame received twice is out of sequence.
+        let control = tx.next_i_frame().unwrap();
+        assert!(rx.receive(control));
+        assert!(!rx.receive(control));
+        assert!(!tx.acknowledge(tx.vs() + 1));
+    }
+}
@@ -0,0 +1,182 @@
+use crate::sniff::Direction;
+use crate::transport::Transport;
+use crate::types::Frame;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// Frame of a recorded session, such as a record of `cmux sniff --output jsonl`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedFrame {
+    /// Time the frame was recorded at, from the start of the recording.
+    pub timestamp: Option<Duration>,
+    pub direction: Option<Direction>,
+    pub frame: Frame,
+}
+
+/// Sender of recorded frames, keeping the delays between them
+///
+/// Replaying the frames exchanged when a modem misbehaved, with their original timing,
+/// reproduces issues that depend on it. The delays can be shortened or stretched by a speed
+/// factor. Frames without timestamp are sent right after the previous one.
+///
+/// # Example
+///
+/// ```
+/// use cmux::replay::{RecordedFrame, Replayer};
+/// use cmux::transport::{MemoryTransport, Transport};
+/// use cmux::types::Frame;
+/// use std::time::Duration;
+///
+/// let frame = Frame::from_bytes(vec![0xF9, 0x03, 0x3F, 0x01, 0x1C, 0xF9]).unwrap();
+/// let frames: Vec<RecordedFrame> = [10, 30]
+///     .map(|ms| RecordedFrame {
+///         timestamp: Some(Duration::from_millis(ms)),
+///         direction: None,
+///         frame: frame.clone(),
+///     })
+///     .into();
+///
+/// let replayer = Replayer::new().with_speed(2.0);
+/// assert_eq!(replayer.schedule(&frames), [Duration::ZERO, Duration::from_millis(10)]);
+///
+/// let (mut port, mut peer) = MemoryTransport::pair();
+/// replayer.run(&frames, &mut port, |_| {}).unwrap();
+/// let mut buf = [0; 16];
+/// assert_eq!(peer.read(&mut buf).unwrap(), 12);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Replayer {
+    speed: f64,
+    advanced: bool,
+}
+
+impl Default for Replayer {
+    fn default() -> Self {
+        Replayer::new()
+    }
+}
+
+impl Replayer {
+    /// Creates a replayer sending basic option frames at the recorded pace.
+    pub fn new() -> Self {
+        Replayer {
+            speed: 1.0,
+            advanced: false,
+        }
+    }
+
+    /// Sets the speed factor: 2.0 halves the delays, 0.5 doubles them.
+    ///
+    /// # Panics
+    ///
+    /// If `speed` is not a positive finite number.
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        assert!(
+            speed.is_finite() && speed > 0.0,
+            "the speed factor must be positive"
+        );
+        self.speed = speed;
+        self
+    }
+
+    /// Sends advanced option frames instead of basic option frames.
+    pub fn advanced(mut self) -> Self {
+        self.advanced = true;
+        self
+    }
+
+    /// Returns when each frame is due, from the start of the replay.
+    ///
+    /// The first frame is due at once. Timestamps going backwards, as in recordings merged
+    /// from several taps, do not make a frame due before the previous one.
+    pub fn schedule(&self, frames: &[RecordedFrame]) -> Vec<Duration> {
+        let mut first = None;
+        let mut due = Duration::ZERO;
+        frames
+            .iter()
+            .map(|recorded| {
+                if let Some(timestamp) = recorded.timestamp {
+                    let first = *first.get_or_insert(timestamp);
+                    let offset = timestamp.saturating_sub(first).div_f64(self.speed);
+                    due = due.max(offset);
+                }
+                due
+            })
+            .collect()
+    }
+
+    /// Writes `frames` to `transport` when they are due, calling `sent` after each of them.
+    ///
+    /// The delays are measured from the start of the replay, so the time spent writing does
+    /// not add up over long recordings.
+    pub fn run<T: Transport>(
+        &self,
+        frames: &[RecordedFrame],
+        transport: &mut T,
+        mut sent: impl FnMut(&RecordedFrame),
+    ) -> io::Result<()> {
+        let start = Instant::now();
+        for (recorded, due) in frames.iter().zip(self.schedule(frames)) {
+            std::thread::sleep((start + due).saturating_duration_since(Instant::now()));
+            let data = if self.advanced {
+                recorded.frame.to_bytes_advanced()
+            } else {
+                recorded.frame.to_bytes()
+            };
+            transport.write(&data)?;
+            sent(recorded);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MemoryTransport;
+    use crate::types::FrameBuilder;
+
+    fn recorded(timestamp: Option<u64>) -> RecordedFrame {
+        RecordedFrame {
+            timestamp: timestamp.map(Duration::from_millis),
+            direction: Some(Direction::Tx),
+            frame: FrameBuilder::default().build().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_schedule() {
+        let frames = [
+            recorded(None),
+            recorded(Some(100)),
+            recorded(None),
+            recorded(Some(400)),
+            recorded(Some(300)),
+        ];
+        let ms = |ms| Duration::from_millis(ms);
+        assert_eq!(
+            Replayer::new().schedule(&frames),
+            [ms(0), ms(0), ms(0), ms(300), ms(300)]
+        );
+        assert_eq!(
+            Replayer::new().with_speed(0.5).schedule(&frames),
+            [ms(0), ms(0), ms(0), ms(600), ms(600)]
+        );
+    }
+
+    #[test]
+    fn test_run_keeps_delays() {
+        let frames = [recorded(Some(1000)), recorded(Some(1020))];
+        let (mut port, mut peer) = MemoryTransport::pair();
+        let start = Instant::now();
+        let mut sent = Vec::new();
+        Replayer::new()
+            .advanced()
+            .run(&frames, &mut port, |_| sent.push(start.elapsed()))
+            .unwrap();
+        assert!(sent[1] - sent[0] >= Duration::from_millis(15));
+        let mut buf = [0; 32];
+        let count = peer.read(&mut buf).unwrap();
+        assert_eq!(buf[..count].iter().filter(|&&b| b == 0x7E).count(), 4);
+    }
+}
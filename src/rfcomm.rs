@@ -0,0 +1,331 @@
+use crate::control::{ConvergenceLayer, ParameterNegotiation};
+use crate::error::FrameError;
+use crate::types::{Address, Content, Control, FrameType, Length, LengthEncoding};
+use alloc::vec::Vec;
+use crc::Crc;
+
+/// CL value of a PN command requesting credit-based flow control.
+pub const CFC_REQUEST: u8 = 0xF;
+
+/// CL value of a PN response accepting credit-based flow control.
+pub const CFC_RESPONSE: u8 = 0xE;
+
+/// Returns the DLCI of an RFCOMM server channel, from 1 to 30.
+///
+/// The direction bit is cleared for the channels of the responder, the device that did not
+/// open the RFCOMM session, and set for the channels of the initiator.
+///
+/// # Example
+///
+/// ```
+/// use cmux::rfcomm::{dlci, server_channel};
+///
+/// assert_eq!(dlci(1, false), 2);
+/// assert_eq!(dlci(3, true), 7);
+/// assert_eq!(server_channel(7), 3);
+/// ```
+pub const fn dlci(server_channel: u8, initiator: bool) -> u8 {
+    ((server_channel & 0x1F) << 1) | initiator as u8
+}
+
+/// Returns the server channel of an RFCOMM DLCI.
+pub const fn server_channel(dlci: u8) -> u8 {
+    (dlci >> 1) & 0x1F
+}
+
+/// Returns the initial credits of a PN message negotiating credit-based flow control, or
+/// `None` if it does not. RFCOMM carries them in the k field.
+pub fn initial_credits(pn: &ParameterNegotiation) -> Option<u8> {
+    match pn.convergence_layer {
+        ConvergenceLayer::Other(CFC_REQUEST | CFC_RESPONSE) => Some(pn.k),
+        _ => None,
+    }
+}
+
+/// RFCOMM frame
+///
+/// RFCOMM frames are carried in L2CAP packets, so they have no flags:
+///
+/// | **Field** | Address | Control | Length | Credits     | Information | FCS |
+/// |-----------|---------|---------|--------|-------------|-------------|-----|
+/// | **Octets**| 1       | 1       | 1 or 2 | 0 or 1      | 0 to 32767  | 1   |
+///
+/// A UIH frame with P/F set on a DLCI other than 0 carries a credits octet, granting the
+/// peer that many frames under credit-based flow control. The FCS of UIH frames covers the
+/// address and control fields only, unlike 07.10 which also covers the length indicator.
+///
+/// # Example
+///
+/// ```
+/// use cmux::rfcomm::RfcommFrame;
+/// use cmux::types::{Address, Control, FrameType, DLCI};
+///
+/// let address = Address::new().with_ea(true).with_cr(true).with_dlci(DLCI::from_bits(2));
+/// let control = Control::new().with_frame_type(FrameType::UIH);
+/// let frame = RfcommFrame::new(address, control).with_credits(33).with_content(b"AT".to_vec());
+/// let bytes = frame.to_bytes().unwrap();
+/// assert_eq!(bytes, [0x0B, 0xFF, 0x05, 0x21, 0x41, 0x54, 0x86]);
+/// assert_eq!(RfcommFrame::from_bytes(&bytes).unwrap(), frame);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RfcommFrame {
+    pub address: Address,
+    pub control: Control,
+    pub credits: Option<u8>,
+    pub content: Content,
+}
+
+impl RfcommFrame {
+    /// Creates a frame without credits nor information field.
+    pub fn new(address: Address, control: Control) -> Self {
+        RfcommFrame {
+            address,
+            control,
+            credits: None,
+            content: Content::default(),
+        }
+    }
+
+    /// Grants `credits` to the peer, setting the P/F bit.
+    pub fn with_credits(mut self, credits: u8) -> Self {
+        self.control = self.control.with_pf(true);
+        self.credits = Some(credits);
+        self
+    }
+
+    pub fn with_content(mut self, content: Vec<u8>) -> Self {
+        self.content = Content::from(content);
+        self
+    }
+
+    /// Returns true if a frame with this address and control field carries a credits octet.
+    fn has_credits(address: Address, control: Control) -> bool {
+        control.frame_type() == FrameType::UIH && control.pf() && address.dlci().into_bits() != 0
+    }
+
+    /// Calculates the FCS over the address, control and, except for UIH frames, length fields.
+    fn fcs(address: Address, control: Control, length: &[u8]) -> u8 {
+        let crc = Crc::<u8>::new(&crc::CRC_8_ROHC);
+        let mut digest = crc.digest();
+        digest.update(&[address.into_bits(), control.into_bits()]);
+        if control.frame_type() != FrameType::UIH {
+            digest.update(length);
+        }
+        !digest.finalize()
+    }
+
+    /// Encodes the frame.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Vec<u8>)`: The octets of the frame.
+    /// - `Err(FrameError)`: If the information field is too long for the length indicator.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, FrameError> {
+        let content = self.content.as_bytes();
+        let length = Length::new(content.len())?.to_bytes();
+        let mut bytes = Vec::with_capacity(content.len() + 6);
+        bytes.push(self.address.into_bits());
+        bytes.push(self.control.into_bits());
+        bytes.extend_from_slice(&length);
+        if RfcommFrame::has_credits(self.address, self.control) {
+            bytes.push(self.credits.unwrap_or(0));
+        }
+        bytes.extend_from_slice(content);
+        bytes.push(RfcommFrame::fcs(self.address, self.control, &length));
+        Ok(bytes)
+    }
+
+    /// Decodes a frame from the payload of an L2CAP packet.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(RfcommFrame)`: The decoded frame.
+    /// - `Err(FrameError)`: If the frame is truncated, has octets after its FCS or a bad FCS.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, FrameError> {
+        let truncated = |expected: usize| FrameError::TruncatedFrame {
+            expected,
+            actual: data.len(),
+        };
+        if data.len() < 4 {
+            return Err(truncated(4));
+        }
+        let address = Address::from_bits(data[0]);
+        let control = Control::from_bits(data[1]);
+        let (length, octets) =
+            Length::decode(&data[2..], LengthEncoding::Spec).ok_or(truncated(5))?;
+        let mut start = 2 + octets;
+        let credits = if RfcommFrame::has_credits(address, control) {
+            start += 1;
+            data.get(start - 1).copied()
+        } else {
+            None
+        };
+        let end = start + length.len();
+        if data.len() < end + 1 {
+            return Err(truncated(end + 1));
+        }
+        if data.len() > end + 1 {
+            return Err(FrameError::BadLength {
+                expected: Length::new(data.len() - start - 1)?.indicator(),
+                actual: length.indicator(),
+            });
+        }
+        let expected = RfcommFrame::fcs(address, control, &data[2..2 + octets]);
+        if data[end] != expected {
+            return Err(FrameError::ChecksumMismatch {
+                expected,
+                actual: data[end],
+            });
+        }
+        Ok(RfcommFrame {
+            address,
+            control,
+            credits,
+            content: Content::from(data[start..end].to_vec()),
+        })
+    }
+}
+
+/// Credit accounting of one DLC under RFCOMM credit-based flow control
+///
+/// Each UIH frame carrying data uses one credit of the sender. Credits are granted with the
+/// initial credits of PN and the credits octet of UIH frames.
+///
+/// # Example
+///
+/// ```
+/// use cmux::rfcomm::Credits;
+///
+/// let mut credits = Credits::new(1, 4);
+/// assert!(credits.send());
+/// assert!(!credits.send());
+/// credits.add_tx(2);
+/// assert_eq!(credits.tx(), 2);
+/// for _ in 0..3 {
+///     assert!(credits.consume_rx());
+/// }
+/// assert_eq!(credits.grant(4), Some(3));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Credits {
+    tx: u16,
+    rx: u16,
+}
+
+impl Credits {
+    /// Creates the accounting of a DLC.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx` - The initial credits granted by the peer.
+    /// * `rx` - The initial credits granted to the peer.
+    pub const fn new(tx: u8, rx: u8) -> Self {
+        Credits {
+            tx: tx as u16,
+            rx: rx as u16,
+        }
+    }
+
+    /// Returns the number of frames this station may still send.
+    pub const fn tx(&self) -> u16 {
+        self.tx
+    }
+
+    /// Returns the number of frames the peer may still send.
+    pub const fn rx(&self) -> u16 {
+        self.rx
+    }
+
+    /// Uses one credit to send a frame with data. Returns false if no credit is left.
+    pub fn send(&mut self) -> bool {
+        if self.tx == 0 {
+            return false;
+        }
+        self.tx -= 1;
+        true
+    }
+
+    /// Adds the credits granted by the peer.
+    pub fn add_tx(&mut self, credits: u8) {
+        self.tx = self.tx.saturating_add(credits.into());
+    }
+
+    /// Uses one credit of the peer for a received frame with data. Returns false if the peer
+    /// had no credit left.
+    pub fn consume_rx(&mut self) -> bool {
+        if self.rx == 0 {
+            return false;
+        }
+        self.rx -= 1;
+        true
+    }
+
+    /// Accounts for a received frame: adds the credits it grants and, if it carries data,
+    /// uses one credit of the peer. Returns false if the peer had no credit left.
+    pub fn receive(&mut self, frame: &RfcommFrame) -> bool {
+        if let Some(credits) = frame.credits {
+            self.add_tx(credits);
+        }
+        frame.content.as_bytes().is_empty() || self.consume_rx()
+    }
+
+    /// Returns the credits to grant the peer to bring its credits back to `target`, and
+    /// counts them as granted. Returns `None` if the peer has at least half of `target` left.
+    pub fn grant(&mut self, target: u8) -> Option<u8> {
+        let target = u16::from(target);
+        if self.rx * 2 >= target {
+            return None;
+        }
+        let credits = target - self.rx;
+        self.rx = target;
+        u8::try_from(credits).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DLCI;
+
+    #[test]
+    fn test_sabm_fcs() {
+        // SABM on DLCI 0 and its UA, as seen in HCI captures of an RFCOMM session.
+        let address = Address::new().with_ea(true).with_cr(true);
+        let sabm = RfcommFrame::new(address, Control::from_bits(0x3F));
+        assert_eq!(sabm.to_bytes().unwrap(), [0x03, 0x3F, 0x01, 0x1C]);
+        let ua = RfcommFrame::from_bytes(&[0x03, 0x73, 0x01, 0xD7]).unwrap();
+        assert_eq!(ua.control.frame_type(), FrameType::UA);
+        assert_eq!(
+            RfcommFrame::from_bytes(&[0x03, 0x73, 0x01, 0xD6]),
+            Err(FrameError::ChecksumMismatch {
+                expected: 0xD7,
+                actual: 0xD6
+            })
+        );
+    }
+
+    #[test]
+    fn test_credits() {
+        let address = Address::new()
+            .with_ea(true)
+            .with_dlci(DLCI::from_bits(dlci(1, false)));
+        let control = Control::new().with_frame_type(FrameType::UIH);
+        let data = RfcommFrame::new(address, control).with_content(b"OK".to_vec());
+        assert_eq!(
+            RfcommFrame::from_bytes(&data.to_bytes().unwrap()),
+            Ok(data.clone())
+        );
+
+        let mut credits = Credits::new(0, 1);
+        assert!(credits.receive(&data));
+        assert!(!credits.receive(&data));
+        assert!(credits.receive(&RfcommFrame::new(address, control).with_credits(5)));
+        assert_eq!((credits.tx(), credits.rx()), (5, 0));
+
+        let pn = ParameterNegotiation::new(2)
+            .with_convergence_layer(ConvergenceLayer::Other(CFC_REQUEST))
+            .with_k(7);
+        assert_eq!(initial_credits(&pn), Some(7));
+        assert_eq!(initial_credits(&ParameterNegotiation::new(2)), None);
+    }
+}
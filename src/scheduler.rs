@@ -0,0 +1,148 @@
+use crate::control::ParameterNegotiation;
+use crate::types::Frame;
+use alloc::collections::{BTreeMap, VecDeque};
+
+/// Transmit scheduler ordering frames by DLCI priority
+///
+/// Frames are queued per DLCI. [`Scheduler::pop`] serves the DLCIs of the highest priority
+/// class first, 0 being the highest and 63 the lowest, and takes turns between the DLCIs of
+/// a class, one frame each, so bulk data on one DLCI cannot starve another.
+///
+/// A DLCI without a priority set by [`Scheduler::set_priority`] gets the default priority of
+/// 07.10, as given by [`ParameterNegotiation::new`].
+///
+/// # Example
+///
+/// ```
+/// use cmux::scheduler::Scheduler;
+/// use cmux::session::Session;
+///
+/// let mut session = Session::new();
+/// let mut scheduler = Scheduler::new();
+/// for dlci in [9, 9, 1, 2, 1] {
+///     scheduler.extend(session.send(dlci, vec![dlci]).unwrap());
+/// }
+///
+/// let order: Vec<u8> = std::iter::from_fn(|| scheduler.pop())
+///     .map(|frame| frame.address.dlci().into_bits())
+///     .collect();
+/// assert_eq!(order, [1, 2, 1, 9, 9]);
+/// ```
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    priorities: BTreeMap<u8, u8>,
+    queues: BTreeMap<u8, VecDeque<Frame>>,
+    /// The DLCI served last in each priority class.
+    last: BTreeMap<u8, u8>,
+}
+
+impl Scheduler {
+    /// Creates a scheduler with no queued frame and the default priorities.
+    pub fn new() -> Self {
+        Scheduler::default()
+    }
+
+    /// Sets the priority of `dlci`, from 0 (highest) to 63 (lowest).
+    pub fn set_priority(&mut self, dlci: u8, priority: u8) {
+        self.priorities.insert(dlci & 0x3F, priority & 0x3F);
+    }
+
+    /// Returns the priority of `dlci`.
+    pub fn priority(&self, dlci: u8) -> u8 {
+        let dlci = dlci & 0x3F;
+        match self.priorities.get(&dlci) {
+            Some(&priority) => priority,
+            None => ParameterNegotiation::new(dlci).priority,
+        }
+    }
+
+    /// Queues `frame` behind the other frames of its DLCI.
+    pub fn push(&mut self, frame: Frame) {
+        let dlci = frame.address.dlci().into_bits();
+        self.queues.entry(dlci).or_default().push_back(frame);
+    }
+
+    /// Queues every frame of `frames` in order.
+    pub fn extend(&mut self, frames: impl IntoIterator<Item = Frame>) {
+        frames.into_iter().for_each(|frame| self.push(frame));
+    }
+
+    /// Takes the next frame to transmit.
+    pub fn pop(&mut self) -> Option<Frame> {
+        let priority = self.queues.keys().map(|&dlci| self.priority(dlci)).min()?;
+        let last = self.last.get(&priority).copied();
+        let mut class = self
+            .queues
+            .keys()
+            .copied()
+            .filter(|&dlci| self.priority(dlci) == priority);
+        let first = class.clone().next()?;
+        let dlci = match last {
+            Some(last) => class.find(|&dlci| dlci > last).unwrap_or(first),
+            None => first,
+        };
+        self.last.insert(priority, dlci);
+
+        let queue = self.queues.get_mut(&dlci)?;
+        let frame = queue.pop_front();
+        if queue.is_empty() {
+            self.queues.remove(&dlci);
+        }
+        frame
+    }
+
+    /// Drops the frames queued on `dlci`.
+    pub fn clear(&mut self, dlci: u8) {
+        self.queues.remove(&(dlci & 0x3F));
+    }
+
+    /// Returns the number of queued frames.
+    pub fn len(&self) -> usize {
+        self.queues.values().map(VecDeque::len).sum()
+    }
+
+    /// Returns true if no frame is queued.
+    pub fn is_empty(&self) -> bool {
+        self.queues.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Session;
+
+    #[test]
+    fn test_priority() {
+        let mut session = Session::new();
+        let mut scheduler = Scheduler::new();
+        assert_eq!(scheduler.priority(0), 0);
+        assert_eq!(scheduler.priority(62), 61);
+
+        scheduler.set_priority(20, 1);
+        for dlci in [1, 1, 20, 0] {
+            scheduler.extend(session.send(dlci, vec![dlci]).unwrap());
+        }
+        assert_eq!(scheduler.len(), 4);
+        let order: Vec<u8> = core::iter::from_fn(|| scheduler.pop())
+            .map(|frame| frame.address.dlci().into_bits())
+            .collect();
+        assert_eq!(order, [0, 20, 1, 1]);
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let mut session = Session::new();
+        let mut scheduler = Scheduler::new();
+        for dlci in [3, 3, 3, 5, 5] {
+            scheduler.extend(session.send(dlci, vec![dlci]).unwrap());
+        }
+        assert_eq!(scheduler.pop().unwrap().content.as_bytes(), [3]);
+        scheduler.extend(session.send(4, vec![4]).unwrap());
+        let order: Vec<u8> = core::iter::from_fn(|| scheduler.pop())
+            .map(|frame| frame.address.dlci().into_bits())
+            .collect();
+        assert_eq!(order, [4, 5, 3, 5, 3]);
+    }
+}
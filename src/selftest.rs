@@ -0,0 +1,315 @@
+use crate::at::ResponseReader;
+use crate::error::{MuxError, SelfTestError};
+use crate::mux::{DlcState, Mux, MuxEvent};
+use crate::simulator::Simulator;
+use crate::transport::{Link, MemoryTransport};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+
+/// DLCI of the AT channel of the simulated modem, checked before the data DLCIs.
+const AT_DLCI: u8 = 1;
+
+/// Outcome of a successful [`SelfTest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfTestReport {
+    /// Number of data DLCIs exercised.
+    pub dlcis: usize,
+    /// Number of payloads sent, in both directions.
+    pub payloads: usize,
+    /// Number of octets sent, in both directions.
+    pub bytes: usize,
+    pub elapsed: Duration,
+}
+
+impl Display for SelfTestReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} payloads, {} octets verified on {} DLCIs in {:.3} ms",
+            self.payloads,
+            self.bytes,
+            self.dlcis,
+            self.elapsed.as_secs_f64() * 1000.0
+        )
+    }
+}
+
+/// Loopback test of the whole stack: the initiator [`Mux`] against the [`Simulator`]
+///
+/// Both stations run over an in-memory pipe. The test opens DLCI 0, the AT channel and the
+/// data DLCIs, checks the simulator answers `ATI`, sends random payloads both ways on every
+/// data DLCI, verifies each side received every octet in order, and closes the DLCs.
+///
+/// The payloads are drawn from a seeded generator, so a failure can be reproduced with the
+/// same seed.
+///
+/// # Example
+///
+/// ```
+/// use cmux::selftest::SelfTest;
+///
+/// let report = SelfTest::new()
+///     .with_dlcis(&[2, 3])
+///     .with_payloads(4)
+///     .with_seed(7)
+///     .run()
+///     .unwrap();
+/// assert_eq!(report.payloads, 16);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SelfTest {
+    dlcis: Vec<u8>,
+    payloads: usize,
+    max_len: usize,
+    seed: u64,
+    advanced: bool,
+}
+
+impl Default for SelfTest {
+    fn default() -> Self {
+        SelfTest::new()
+    }
+}
+
+impl SelfTest {
+    /// Creates a test of DLCIs 2 to 4 with 16 payloads of up to 1024 octets per DLCI and
+    /// direction, in basic option framing.
+    pub fn new() -> Self {
+        SelfTest {
+            dlcis: vec![2, 3, 4],
+            payloads: 16,
+            max_len: 1024,
+            seed: 1,
+            advanced: false,
+        }
+    }
+
+    /// Sets the data DLCIs, between 2 and 63.
+    pub fn with_dlcis(mut self, dlcis: &[u8]) -> Self {
+        self.dlcis = dlcis.to_vec();
+        self
+    }
+
+    /// Sets the number of payloads sent per DLCI and direction.
+    pub fn with_payloads(mut self, payloads: usize) -> Self {
+        self.payloads = payloads;
+        self
+    }
+
+    /// Sets the largest payload length. Payloads longer than N1 are sent in several frames.
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len.max(1);
+        self
+    }
+
+    /// Sets the seed of the payload generator.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Uses advanced option framing instead of basic option framing.
+    pub fn advanced(mut self) -> Self {
+        self.advanced = true;
+        self
+    }
+
+    /// Runs the test.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(SelfTestReport)`: If every payload arrived intact and in order.
+    /// - `Err(SelfTestError)`: The first failure found.
+    pub fn run(&self) -> Result<SelfTestReport, SelfTestError> {
+        if let Some(&dlci) = self.dlcis.iter().find(|dlci| !(2..64).contains(*dlci)) {
+            return Err(SelfTestError::InvalidDlci(dlci));
+        }
+        let start = Instant::now();
+        let mut loopback = Loopback::new(self.advanced);
+        let mut channels = vec![0, AT_DLCI];
+        channels.extend(&self.dlcis);
+        for &dlci in &channels {
+            loopback.host.mux_mut().open(dlci, start)?;
+        }
+        loopback.exchange()?;
+        if let Some(&dlci) = channels
+            .iter()
+            .find(|&&dlci| loopback.host.mux().state(dlci) != DlcState::Connected)
+        {
+            return Err(MuxError::NotConnected { dlci }.into());
+        }
+
+        loopback.host.mux_mut().send_command(AT_DLCI, "ATI")?;
+        loopback.exchange()?;
+        let response = loopback.to_host.remove(&AT_DLCI).unwrap_or_default();
+        match ResponseReader::new("ATI").feed(&response) {
+            Some(response) if response.result.is_ok() => {}
+            _ => return Err(SelfTestError::AtCommand("ATI".to_string())),
+        }
+
+        let mut rng = Rng::new(self.seed);
+        let mut sent: BTreeMap<(u8, bool), Vec<u8>> = BTreeMap::new();
+        for _ in 0..self.payloads {
+            for &dlci in &self.dlcis {
+                for from_host in [true, false] {
+                    let payload = rng.payload(self.max_len);
+                    if from_host {
+                        loopback.host.mux_mut().send(dlci, &payload)?;
+                    } else {
+                        loopback.modem.mux_mut().send(dlci, &payload)?;
+                    }
+                    sent.entry((dlci, from_host)).or_default().extend(payload);
+                }
+            }
+            loopback.exchange()?;
+        }
+        for (&(dlci, from_host), data) in &sent {
+            let received = match from_host {
+                true => loopback.to_modem.get(&dlci),
+                false => loopback.to_host.get(&dlci),
+            };
+            let received = received.map_or(&[][..], Vec::as_slice);
+            if received != data.as_slice() {
+                let offset = data
+                    .iter()
+                    .zip(received)
+                    .position(|(sent, received)| sent != received)
+                    .unwrap_or(data.len().min(received.len()));
+                return Err(SelfTestError::Corrupted {
+                    dlci,
+                    from_host,
+                    offset,
+                });
+            }
+        }
+
+        // DLCI 0 is closed last, which closes the multiplexer.
+        for &dlci in channels.iter().rev() {
+            loopback.host.mux_mut().close(dlci, Instant::now())?;
+        }
+        loopback.exchange()?;
+        if let Some(&dlci) = channels
+            .iter()
+            .find(|&&dlci| loopback.host.mux().state(dlci) != DlcState::Closed)
+        {
+            return Err(MuxError::Timeout { dlci }.into());
+        }
+        Ok(SelfTestReport {
+            dlcis: self.dlcis.len(),
+            payloads: self.payloads * self.dlcis.len() * 2,
+            bytes: sent.values().map(Vec::len).sum(),
+            elapsed: start.elapsed(),
+        })
+    }
+}
+
+/// Host and simulated modem connected by an in-memory pipe, with the data each received.
+struct Loopback {
+    host: Link<MemoryTransport>,
+    modem: Simulator<MemoryTransport>,
+    to_host: BTreeMap<u8, Vec<u8>>,
+    to_modem: BTreeMap<u8, Vec<u8>>,
+}
+
+impl Loopback {
+    fn new(advanced: bool) -> Self {
+        let (a, b) = MemoryTransport::pair();
+        let mut host = Link::new(Mux::new(), a);
+        let mut modem = Simulator::new(b).with_at_dlci(AT_DLCI);
+        if advanced {
+            host = host.advanced();
+            modem = modem.advanced();
+        }
+        Loopback {
+            host,
+            modem,
+            to_host: BTreeMap::new(),
+            to_modem: BTreeMap::new(),
+        }
+    }
+
+    /// Moves the pending frames between both stations until the pipe is idle, collecting
+    /// the data received.
+    ///
+    /// The in-memory pipe does not fail, so its errors are not reported.
+    fn exchange(&mut self) -> Result<(), SelfTestError> {
+        const PIPE: &str = "the in-memory pipe does not fail";
+        self.host.flush().expect(PIPE);
+        loop {
+            let now = Instant::now();
+            let modem_read = self.modem.poll(now).expect(PIPE);
+            let host_read = self.host.poll(now).expect(PIPE);
+            while let Some(event) = self.modem.poll_event() {
+                Self::collect(&mut self.to_modem, event)?;
+            }
+            while let Some(event) = self.host.mux_mut().poll_event() {
+                Self::collect(&mut self.to_host, event)?;
+            }
+            if modem_read == 0 && host_read == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Appends the data of `event` to `received`, failing on engine errors.
+    fn collect(received: &mut BTreeMap<u8, Vec<u8>>, event: MuxEvent) -> Result<(), SelfTestError> {
+        match event {
+            MuxEvent::Data { dlci, data } => received.entry(dlci).or_default().extend(data),
+            MuxEvent::Error(e) => return Err(e.into()),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// xorshift64* generator, enough to vary the payloads reproducibly.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // The state must not be zero.
+        Rng(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns between 1 and `max_len` random octets.
+    fn payload(&mut self, max_len: usize) -> Vec<u8> {
+        let len = 1 + (self.next() % max_len as u64) as usize;
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selftest_passes() {
+        let test = SelfTest::new().with_payloads(8).with_seed(42);
+        let report = test.run().unwrap();
+        assert_eq!(report.dlcis, 3);
+        assert_eq!(report.payloads, 48);
+        assert!(report.bytes >= 48);
+        let report = test.advanced().run().unwrap();
+        assert_eq!(report.payloads, 48);
+    }
+
+    #[test]
+    fn test_invalid_dlci() {
+        assert_eq!(
+            SelfTest::new().with_dlcis(&[2, 1]).run(),
+            Err(SelfTestError::InvalidDlci(1))
+        );
+        assert_eq!(
+            SelfTest::new().with_dlcis(&[64]).run(),
+            Err(SelfTestError::InvalidDlci(64))
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use crate::bridge::{Backlog, Endpoint};
+use crate::control::V24Signals;
+use crate::mux::Mux;
+use crate::transport::{IoTransport, Link};
+use serialport::{DataBits, FlowControl, Parity, SerialPort, StopBits};
+use std::io::{self, ErrorKind, Read, Write};
+use std::time::{Duration, Instant};
+
+/// Command switching a modem to multiplexer mode with the default parameters.
+pub const AT_CMUX: &str = "AT+CMUX=0";
+
+/// Read timeout of the serial port, which bounds the latency of the [`Mux`] timers.
+const READ_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Serial port settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerialSettings {
+    pub baud: u32,
+    pub data_bits: DataBits,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    pub flow_control: FlowControl,
+}
+
+impl Default for SerialSettings {
+    /// 115200 baud, 8N1, no flow control.
+    fn default() -> Self {
+        SerialSettings {
+            baud: 115200,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SerialSettings {
+    /// Reads `baud`, `data_bits` (5 to 8), `parity` (`none`, `odd` or `even`), `stop_bits`
+    /// (1 or 2) and `flow_control` (`none`, `software` or `hardware`), each defaulting to
+    /// [`SerialSettings::default`].
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum ParityName {
+            None,
+            Odd,
+            Even,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum FlowControlName {
+            None,
+            Software,
+            Hardware,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct Settings {
+            baud: Option<u32>,
+            data_bits: Option<u8>,
+            parity: Option<ParityName>,
+            stop_bits: Option<u8>,
+            flow_control: Option<FlowControlName>,
+        }
+
+        let settings = Settings::deserialize(deserializer)?;
+        let default = SerialSettings::default();
+        Ok(SerialSettings {
+            baud: settings.baud.unwrap_or(default.baud),
+            data_bits: match settings.data_bits {
+                None => default.data_bits,
+                Some(5) => DataBits::Five,
+                Some(6) => DataBits::Six,
+                Some(7) => DataBits::Seven,
+                Some(8) => DataBits::Eight,
+                Some(bits) => {
+                    return Err(D::Error::custom(format!(
+                        "data_bits {} is not between 5 and 8",
+                        bits
+                    )))
+                }
+            },
+            parity: match settings.parity {
+                None => default.parity,
+                Some(ParityName::None) => Parity::None,
+                Some(ParityName::Odd) => Parity::Odd,
+                Some(ParityName::Even) => Parity::Even,
+            },
+            stop_bits: match settings.stop_bits {
+                None => default.stop_bits,
+                Some(1) => StopBits::One,
+                Some(2) => StopBits::Two,
+                Some(bits) => {
+                    return Err(D::Error::custom(format!(
+                        "stop_bits {} is not 1 or 2",
+                        bits
+                    )))
+                }
+            },
+            flow_control: match settings.flow_control {
+                None => default.flow_control,
+                Some(FlowControlName::None) => FlowControl::None,
+                Some(FlowControlName::Software) => FlowControl::Software,
+                Some(FlowControlName::Hardware) => FlowControl::Hardware,
+            },
+        })
+    }
+}
+
+/// Opens the serial port at `path` with `settings`.
+pub fn open(path: &str, settings: &SerialSettings) -> io::Result<Box<dyn SerialPort>> {
+    let port = serialport::new(path, settings.baud)
+        .data_bits(settings.data_bits)
+        .parity(settings.parity)
+        .stop_bits(settings.stop_bits)
+        .flow_control(settings.flow_control)
+        .timeout(READ_TIMEOUT)
+        .open()?;
+    Ok(port)
+}
+
+/// Sends an AT command switching the modem to multiplexer mode and waits for its result code.
+///
+/// # Arguments
+///
+/// * `port` - The serial port, in command mode.
+/// * `command` - The command without the trailing carriage return, usually [`AT_CMUX`].
+/// * `timeout` - How long to wait for the result code.
+///
+/// # Returns
+///
+/// - `Ok(())`: If the modem answered `OK`.
+/// - `Err(io::Error)`: If it answered `ERROR`, did not answer in time, or the port failed.
+pub fn enter_cmux<P: Read + Write>(
+    port: &mut P,
+    command: &str,
+    timeout: Duration,
+) -> io::Result<()> {
+    port.write_all(format!("{}\r", command).as_bytes())?;
+    port.flush()?;
+    let deadline = Instant::now() + timeout;
+    let mut response = Vec::new();
+    let mut buf = [0; 64];
+    while Instant::now() < deadline {
+        match port.read(&mut buf) {
+            Ok(count) => response.extend_from_slice(&buf[..count]),
+            Err(e) if e.kind() == ErrorKind::TimedOut => continue,
+            Err(e) => return Err(e),
+        }
+        if let Some(result) = cmux_result(command, &response) {
+            return result;
+        }
+    }
+    Err(no_response(command))
+}
+
+/// Returns the result of `command` from the `response` of the modem so far, or `None` if
+/// the result code did not arrive yet.
+pub(crate) fn cmux_result(command: &str, response: &[u8]) -> Option<io::Result<()>> {
+    let text = String::from_utf8_lossy(response);
+    if text.contains("OK\r\n") {
+        return Some(Ok(()));
+    }
+    if text.contains("ERROR") {
+        return Some(Err(io::Error::other(format!(
+            "{} failed: {}",
+            command,
+            text.trim()
+        ))));
+    }
+    None
+}
+
+/// Returns the error of `command` left without result code.
+pub(crate) fn no_response(command: &str) -> io::Error {
+    io::Error::new(ErrorKind::TimedOut, format!("no response to {}", command))
+}
+
+/// Opens the serial port, switches the modem to multiplexer mode and connects DLCI 0.
+///
+/// # Arguments
+///
+/// * `path` - The serial port. Example: /dev/ttyUSB0
+/// * `settings` - The serial port settings.
+/// * `command` - The AT command sent first, or `None` if the modem is already in multiplexer mode.
+/// * `mux` - The engine driven over the port.
+///
+/// # Returns
+///
+/// - `Ok(Link)`: The link with DLCI 0 connected.
+/// - `Err(io::Error)`: If the port failed or the modem did not accept the multiplexer.
+pub fn attach(
+    path: &str,
+    settings: &SerialSettings,
+    command: Option<&str>,
+    mux: Mux,
+) -> io::Result<Link<IoTransport<Box<dyn SerialPort>>>> {
+    let mut port = open(path, settings)?;
+    if let Some(command) = command {
+        enter_cmux(&mut port, command, Duration::from_secs(1))?;
+    }
+    let mut link = Link::new(mux, IoTransport::new(port));
+    if !link.connect(0)? {
+        return Err(io::Error::new(
+            ErrorKind::ConnectionRefused,
+            "DLCI 0 was not connected",
+        ));
+    }
+    Ok(link)
+}
+
+/// [`Endpoint`] exposing a DLC on a serial port, such as one end of a virtual null-modem pair
+///
+/// With a com0com pair on Windows, the bridge opens one end, for example `CNCB0`, and an
+/// application insisting on a COM port opens the other end, renamed `COM10` with
+/// `setupc change CNCA0 PortName=COM10`. The V.24 signals of the DLC drive DTR from RTC and
+/// RTS from RTR, which the null-modem wiring of the pair crosses over to the DSR, DCD and CTS
+/// of the application. On Linux, tty0tty pairs work the same way. The bytes the application
+/// does not take right away are kept, and dropped once it leaves them unread for a second.
+///
+/// # Example
+///
+/// ```no_run
+/// use cmux::bridge::Endpoint;
+/// use cmux::serial::{SerialBridge, SerialSettings};
+///
+/// let mut bridge = SerialBridge::open("CNCB0", &SerialSettings::default()).unwrap();
+/// bridge.write(b"RING\r\n").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct SerialBridge {
+    port: Box<dyn SerialPort>,
+    backlog: Backlog,
+}
+
+impl SerialBridge {
+    /// Timeout of the reads and writes of the port, short for the reads of [`Endpoint`]
+    /// not to block.
+    const TIMEOUT: Duration = Duration::from_millis(1);
+    /// Bytes handed to the port at once: its writes block once it reported room, so the
+    /// chunk bounds how long the endpoint waits on a port with little room left.
+    const CHUNK: usize = 64;
+
+    /// Opens the serial port at `path` with `settings`.
+    pub fn open(path: &str, settings: &SerialSettings) -> io::Result<Self> {
+        Self::new(open(path, settings)?)
+    }
+
+    /// Wraps an open serial port.
+    pub fn new(mut port: Box<dyn SerialPort>) -> io::Result<Self> {
+        port.set_timeout(Self::TIMEOUT)?;
+        Ok(SerialBridge {
+            port,
+            backlog: Backlog::default(),
+        })
+    }
+}
+
+impl SerialBridge {
+    /// Drops the backlog once the other end left it unread for [`Backlog::DEADLINE`], as on
+    /// a serial line with nothing plugged in.
+    fn drop_stalled(&mut self, result: io::Result<()>) -> io::Result<()> {
+        match result {
+            Err(e) if e.kind() == ErrorKind::TimedOut => {
+                self.backlog.clear();
+                Ok(())
+            }
+            result => result,
+        }
+    }
+}
+
+impl Endpoint for SerialBridge {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let port = &mut self.port;
+        let result = self
+            .backlog
+            .flush(|data| port.write(&data[..data.len().min(Self::CHUNK)]));
+        self.drop_stalled(result)?;
+        match self.port.read(buf) {
+            Ok(count) => Ok(count),
+            Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        let port = &mut self.port;
+        let result = self.backlog.push(data, |data| {
+            port.write(&data[..data.len().min(Self::CHUNK)])
+        });
+        self.drop_stalled(result)
+    }
+
+    fn modem_status(&mut self, signals: V24Signals) -> io::Result<()> {
+        // Ports without control lines, such as pseudo-terminals, only carry the data.
+        let _ = self.port.write_data_terminal_ready(signals.rtc());
+        let _ = self.port.write_request_to_send(signals.rtr());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Modem answering every command with a fixed response.
+    struct Modem {
+        sent: Vec<u8>,
+        response: &'static [u8],
+    }
+
+    impl Read for Modem {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let count = buf.len().min(self.response.len());
+            buf[..count].copy_from_slice(&self.response[..count]);
+            self.response = &self.response[count..];
+            Ok(count)
+        }
+    }
+
+    impl Write for Modem {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.sent.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_enter_cmux() {
+        let mut modem = Modem {
+            sent: Vec::new(),
+            response: b"AT+CMUX=0\r\r\nOK\r\n",
+        };
+        enter_cmux(&mut modem, AT_CMUX, Duration::from_millis(100)).unwrap();
+        assert_eq!(modem.sent, b"AT+CMUX=0\r");
+
+        let mut modem = Modem {
+            sent: Vec::new(),
+            response: b"\r\nERROR\r\n",
+        };
+        let e = enter_cmux(&mut modem, AT_CMUX, Duration::from_millis(100)).unwrap_err();
+        assert_eq!(e.to_string(), "AT+CMUX=0 failed: ERROR");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_serial_bridge() {
+        let (port, mut application) = serialport::TTYPort::pair().unwrap();
+        let mut bridge = SerialBridge::new(Box::new(port)).unwrap();
+        let mut buf = [0; 4];
+        assert_eq!(Endpoint::read(&mut bridge, &mut buf).unwrap(), 0);
+
+        application.write_all(b"AT\r").unwrap();
+        let mut read = 0;
+        while read < 3 {
+            read += Endpoint::read(&mut bridge, &mut buf[read..]).unwrap();
+        }
+        assert_eq!(&buf[..3], b"AT\r");
+        Endpoint::write(&mut bridge, b"OK\r\n").unwrap();
+        application.set_timeout(Duration::from_secs(1)).unwrap();
+        application.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"OK\r\n");
+        bridge
+            .modem_status(V24Signals::new().with_rtc(true).with_dv(true))
+            .unwrap();
+
+        // More than the pseudo-terminal holds waits for the application to read it.
+        let data: Vec<u8> = (0..64 * 1024).map(|i| b'a' + (i % 26) as u8).collect();
+        Endpoint::write(&mut bridge, &data).unwrap();
+        let mut received = vec![0; data.len()];
+        let mut read = 0;
+        while read < received.len() {
+            read += application.read(&mut received[read..]).unwrap();
+            Endpoint::read(&mut bridge, &mut buf).unwrap();
+        }
+        assert!(received == data);
+    }
+}
@@ -0,0 +1,298 @@
+use crate::control::{FlowControl, ParameterMask, V24Signals};
+use crate::types::{Address, Content, Control, Frame, FrameBuilder, FrameType, DLCI};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Compact form of [`Frame`]: the hex string of its basic option encoding
+///
+/// # Example
+///
+/// ```
+/// use cmux::types::Frame;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Fixture {
+///     #[serde(with = "cmux::serialization::hex")]
+///     frame: Frame,
+/// }
+///
+/// let fixture: Fixture = serde_json::from_str(r#"{"frame":"F9033F011CF9"}"#).unwrap();
+/// assert_eq!(serde_json::to_string(&fixture).unwrap(), r#"{"frame":"F9033F011CF9"}"#);
+/// ```
+pub mod hex {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(frame: &Frame, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&::hex::encode_upper(frame.to_bytes()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Frame, D::Error> {
+        let data = super::bytes::deserialize(deserializer)?;
+        Frame::from_bytes(data).map_err(D::Error::custom)
+    }
+}
+
+/// Byte strings as hex strings.
+pub(crate) mod bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&::hex::encode_upper(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let digits: String = text.split_whitespace().collect();
+        ::hex::decode(digits).map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for Content {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        bytes::serialize(self.as_bytes(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Content {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        bytes::deserialize(deserializer).map(Content::from)
+    }
+}
+
+impl Serialize for DLCI {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.into_bits())
+    }
+}
+
+impl<'de> Deserialize<'de> for DLCI {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match u8::deserialize(deserializer)? {
+            dlci @ 0..=63 => Ok(DLCI::from_bits(dlci)),
+            dlci => Err(D::Error::custom(format!("DLCI {} is not 6 bits", dlci))),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct AddressFields {
+    #[serde(default = "default_true")]
+    ea: bool,
+    cr: bool,
+    dlci: DLCI,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        AddressFields {
+            ea: self.ea(),
+            cr: self.cr(),
+            dlci: self.dlci(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = AddressFields::deserialize(deserializer)?;
+        Ok(Address::new()
+            .with_ea(fields.ea)
+            .with_cr(fields.cr)
+            .with_dlci(fields.dlci))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ControlFields {
+    frame_type: FrameType,
+    pf: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ns: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nr: Option<u8>,
+}
+
+impl Serialize for Control {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ControlFields {
+            frame_type: self.frame_type(),
+            pf: self.pf(),
+            ns: self.ns(),
+            nr: self.nr(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Control {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = ControlFields::deserialize(deserializer)?;
+        let mut control = Control::new()
+            .with_frame_type(fields.frame_type)
+            .with_pf(fields.pf);
+        if let Some(ns) = fields.ns {
+            control = control.with_ns(ns);
+        }
+        if let Some(nr) = fields.nr {
+            control = control.with_nr(nr);
+        }
+        Ok(control)
+    }
+}
+
+/// Structured form of [`Frame`].
+///
+/// The length indicator and FCS are computed when they are missing, and kept as given
+/// otherwise, so fixtures can describe invalid frames.
+#[derive(Serialize, Deserialize)]
+struct FrameFields {
+    address: Address,
+    control: Control,
+    content: Content,
+    #[serde(default)]
+    length: Option<u16>,
+    #[serde(default)]
+    checksum: Option<u8>,
+}
+
+impl Serialize for Frame {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        FrameFields {
+            address: self.address,
+            control: self.control,
+            content: self.content.clone(),
+            length: Some(self.length),
+            checksum: Some(self.checksum),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Frame {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = FrameFields::deserialize(deserializer)?;
+        let mut frame = FrameBuilder::default()
+            .with_address(fields.address)
+            .with_control(fields.control)
+            .with_binary_content(fields.content.into())
+            .build()
+            .map_err(D::Error::custom)?;
+        if let Some(length) = fields.length {
+            frame.length = length;
+        }
+        if let Some(checksum) = fields.checksum {
+            frame.checksum = checksum;
+        }
+        Ok(frame)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct V24Fields {
+    fc: bool,
+    rtc: bool,
+    rtr: bool,
+    ic: bool,
+    dv: bool,
+}
+
+impl Serialize for V24Signals {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        V24Fields {
+            fc: self.fc(),
+            rtc: self.rtc(),
+            rtr: self.rtr(),
+            ic: self.ic(),
+            dv: self.dv(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for V24Signals {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = V24Fields::deserialize(deserializer)?;
+        Ok(V24Signals::new()
+            .with_fc(fields.fc)
+            .with_rtc(fields.rtc)
+            .with_rtr(fields.rtr)
+            .with_ic(fields.ic)
+            .with_dv(fields.dv))
+    }
+}
+
+/// The flow control octet is written as a number, as it is in the RPN value.
+impl Serialize for FlowControl {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.into_bits())
+    }
+}
+
+impl<'de> Deserialize<'de> for FlowControl {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u8::deserialize(deserializer).map(FlowControl::from_bits)
+    }
+}
+
+/// The parameter mask is written as a number, as it is in the RPN value.
+impl Serialize for ParameterMask {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(self.into_bits())
+    }
+}
+
+impl<'de> Deserialize<'de> for ParameterMask {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u16::deserialize(deserializer).map(ParameterMask::from_bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::{ControlMessage, MessageType, Msc, RemotePortNegotiation};
+
+    #[test]
+    fn test_frame() {
+        let frame = Frame::from_bytes(::hex::decode("F907EF0541540D0A0A2CF9").unwrap()).unwrap();
+        let json = serde_json::to_string(&frame).unwrap();
+        assert_eq!(
+            json,
+            r#"{"address":{"ea":true,"cr":true,"dlci":1},"control":{"frame_type":"UIH","pf":false},"content":"41540D0A0A","length":5,"checksum":44}"#
+        );
+        assert_eq!(serde_json::from_str::<Frame>(&json).unwrap(), frame);
+
+        // The length indicator and FCS are computed when missing.
+        let frame: Frame = serde_json::from_str(
+            r#"{"address":{"cr":true,"dlci":0},"control":{"frame_type":"SABM","pf":true},"content":""}"#,
+        )
+        .unwrap();
+        assert_eq!(frame.to_hex_string(), "f9033f011cf9");
+        assert!(serde_json::from_str::<DLCI>("64").is_err());
+    }
+
+    #[test]
+    fn test_control_messages() {
+        let msg = Msc::new(1)
+            .with_signals(V24Signals::new().with_rtc(true))
+            .to_message(true);
+        let json = serde_json::to_string(&msg).unwrap();
+        assert_eq!(json, r#"{"message_type":"MSC","cr":true,"value":"0705"}"#);
+        assert_eq!(serde_json::from_str::<ControlMessage>(&json).unwrap(), msg);
+
+        let rpn = RemotePortNegotiation::new(2);
+        let json = serde_json::to_string(&rpn).unwrap();
+        assert_eq!(
+            serde_json::from_str::<RemotePortNegotiation>(&json).unwrap(),
+            rpn
+        );
+        let other: MessageType = serde_json::from_str(r#"{"Other":5}"#).unwrap();
+        assert_eq!(other, MessageType::Other(5));
+    }
+}
@@ -0,0 +1,314 @@
+use crate::control::{ControlMessage, MessageType, Nsc};
+use crate::error::{ControlError, FrameError};
+use crate::types::{Address, Control, Frame, FrameBuilder, FrameType, DLCI};
+use alloc::boxed::Box;
+use alloc::collections::{BTreeSet, VecDeque};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{Debug, Formatter};
+
+/// Event reported by [`Session`] to the callback set with [`Session::on_event`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEvent {
+    /// The peer sent FCon (`enabled` true) or FCoff (`enabled` false).
+    FlowControl { enabled: bool },
+    /// The peer answered a command of the given type with NSC.
+    NotSupported { message_type: MessageType },
+}
+
+/// Callback receiving the events of a [`Session`].
+type EventCallback = Box<dyn FnMut(&SessionEvent) + Send>;
+
+/// Multiplexer session
+///
+/// Tracks the DLCIs opened on a multiplexer so the session can be torn down in order.
+///
+/// The session also applies aggregate flow control: after the peer sends FCoff, the UIH
+/// frames passed to [`Session::send`] are queued on every DLCI but 0 until FCon arrives.
+///
+/// # Example
+///
+/// ```
+/// use cmux::session::Session;
+///
+/// let mut session = Session::new();
+/// session.open(1);
+/// session.open(2);
+///
+/// let frames = session.shutdown().unwrap();
+/// let hex: Vec<String> = frames.iter().map(|f| f.to_hex_string()).collect();
+/// assert_eq!(hex, ["f90753013ff9", "f90b5301b8f9", "f903ef05c301f2f9"]);
+/// assert!(session.open_dlcis().is_empty());
+/// ```
+pub struct Session {
+    initiator: bool,
+    open: BTreeSet<u8>,
+    flow_enabled: bool,
+    queue: VecDeque<Frame>,
+    rejected: Vec<MessageType>,
+    on_event: Option<EventCallback>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session {
+            initiator: true,
+            open: BTreeSet::new(),
+            flow_enabled: true,
+            queue: VecDeque::new(),
+            rejected: Vec::new(),
+            on_event: None,
+        }
+    }
+}
+
+impl Debug for Session {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Session")
+            .field("initiator", &self.initiator)
+            .field("open", &self.open)
+            .field("flow_enabled", &self.flow_enabled)
+            .field("queued", &self.queue.len())
+            .field("rejected", &self.rejected)
+            .finish()
+    }
+}
+
+impl Session {
+    /// Creates a session for the initiating station with no open DLCI.
+    pub fn new() -> Self {
+        Session::default()
+    }
+
+    /// Sets whether this station started the multiplexer, which decides the C/R bit of commands.
+    pub fn with_initiator(mut self, initiator: bool) -> Self {
+        self.initiator = initiator;
+        self
+    }
+
+    /// Returns true if this station started the multiplexer.
+    pub fn initiator(&self) -> bool {
+        self.initiator
+    }
+
+    /// Marks `dlci` as open.
+    pub fn open(&mut self, dlci: u8) {
+        self.open.insert(dlci & 0x3F);
+    }
+
+    /// Marks `dlci` as closed.
+    pub fn close(&mut self, dlci: u8) {
+        self.open.remove(&(dlci & 0x3F));
+    }
+
+    /// Returns true if `dlci` is open.
+    pub fn is_open(&self, dlci: u8) -> bool {
+        self.open.contains(&(dlci & 0x3F))
+    }
+
+    /// Returns the open DLCIs in ascending order.
+    pub fn open_dlcis(&self) -> Vec<u8> {
+        self.open.iter().copied().collect()
+    }
+
+    /// Sets the callback receiving the [`SessionEvent`]s raised by [`Session::receive`].
+    pub fn on_event(&mut self, callback: impl FnMut(&SessionEvent) + Send + 'static) {
+        self.on_event = Some(Box::new(callback));
+    }
+
+    /// Returns false while the peer has stopped the transmission of data frames with FCoff.
+    pub fn flow_enabled(&self) -> bool {
+        self.flow_enabled
+    }
+
+    /// Returns the number of frames waiting for FCon.
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Drops the frames waiting for FCon.
+    ///
+    /// # Returns
+    ///
+    /// The dropped frames, in the order they were queued.
+    pub fn take_queued(&mut self) -> Vec<Frame> {
+        self.queue.drain(..).collect()
+    }
+
+    /// Returns the types of the commands the peer rejected with NSC, in the order first rejected.
+    pub fn rejected(&self) -> &[MessageType] {
+        &self.rejected
+    }
+
+    /// Sends `data` in a UIH frame on `dlci`.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Vec<Frame>)`: The frame to transmit, or nothing if it was queued by flow control.
+    /// - `Err(FrameError)`: If the frame cannot be built.
+    pub fn send(&mut self, dlci: u8, data: Vec<u8>) -> Result<Vec<Frame>, FrameError> {
+        let frame = FrameBuilder::default()
+            .with_address(self.command_address(dlci))
+            .with_control(Control::default().with_frame_type(FrameType::UIH))
+            .with_binary_content(data)
+            .build()?;
+        if !self.flow_enabled && dlci & 0x3F != 0 {
+            self.queue.push_back(frame);
+            return Ok(Vec::new());
+        }
+        Ok(vec![frame])
+    }
+
+    /// Handles a frame received from the peer.
+    ///
+    /// FCon and FCoff commands on DLCI 0 update the flow control state, raise a
+    /// [`SessionEvent::FlowControl`] and are answered with a response. NSC responses record the
+    /// rejected command type and raise a [`SessionEvent::NotSupported`]. Other frames are ignored.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Vec<Frame>)`: The frames to transmit in reply, followed by the frames released by FCon.
+    /// - `Err(ControlError)`: If a control message on DLCI 0 is malformed.
+    pub fn receive(&mut self, frame: &Frame) -> Result<Vec<Frame>, ControlError> {
+        let messages = match ControlMessage::from_frame(frame) {
+            Ok(messages) => messages,
+            Err(ControlError::NotControlFrame) => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut frames = Vec::new();
+        for message in &messages {
+            match (message.message_type, message.cr) {
+                (MessageType::FCon | MessageType::FCoff, true) => {
+                    let enabled = message.message_type == MessageType::FCon;
+                    let response = ControlMessage::new(message.message_type, false, Vec::new());
+                    frames.push(
+                        response
+                            .to_frame(self.initiator)
+                            .expect("an empty control message fits in a frame"),
+                    );
+                    self.flow_enabled = enabled;
+                    if enabled {
+                        frames.extend(self.queue.drain(..));
+                    }
+                    self.emit(SessionEvent::FlowControl { enabled });
+                }
+                (MessageType::NSC, _) => {
+                    let message_type = Nsc::try_from(message)?.message_type;
+                    if !self.rejected.contains(&message_type) {
+                        self.rejected.push(message_type);
+                    }
+                    self.emit(SessionEvent::NotSupported { message_type });
+                }
+                _ => {}
+            }
+        }
+        Ok(frames)
+    }
+
+    /// Passes `event` to the callback set with [`Session::on_event`].
+    fn emit(&mut self, event: SessionEvent) {
+        if let Some(callback) = self.on_event.as_mut() {
+            callback(&event);
+        }
+    }
+
+    /// Builds the command address of `dlci` for this station.
+    fn command_address(&self, dlci: u8) -> Address {
+        Address::default()
+            .with_cr(self.initiator)
+            .with_dlci(DLCI::from_bits(dlci))
+    }
+
+    /// Closes the session.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Vec<Frame>)`: A DISC command for every open DLCI other than 0, followed by a CLD
+    ///   command on DLCI 0. Every DLCI is marked as closed.
+    /// - `Err(FrameError)`: If a frame cannot be built.
+    pub fn shutdown(&mut self) -> Result<Vec<Frame>, FrameError> {
+        let mut frames = Vec::new();
+        for &dlci in self.open.iter().filter(|&&dlci| dlci != 0) {
+            let frame = FrameBuilder::default()
+                .with_address(self.command_address(dlci))
+                .with_control(
+                    Control::default()
+                        .with_frame_type(FrameType::DISC)
+                        .with_pf(true),
+                )
+                .build()?;
+            frames.push(frame);
+        }
+        frames.push(ControlMessage::close_down(true).to_frame(self.initiator)?);
+        self.open.clear();
+        self.queue.clear();
+        Ok(frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_shutdown_responder() {
+        let mut session = Session::new().with_initiator(false);
+        session.open(0);
+        session.open(3);
+        let frames = session.shutdown().unwrap();
+        assert_eq!(frames.len(), 2);
+        assert!(!frames[0].address.cr());
+        assert_eq!(frames[0].address.dlci().into_bits(), 3);
+        assert_eq!(frames[0].control.frame_type(), FrameType::DISC);
+        let messages = ControlMessage::from_frame(&frames[1]).unwrap();
+        assert_eq!(messages[0].message_type, MessageType::CLD);
+        assert!(frames.iter().all(|f| f.verify().is_ok()));
+    }
+
+    #[test]
+    fn test_flow_control() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut session = Session::new();
+        let log = events.clone();
+        session.on_event(move |event| log.lock().unwrap().push(*event));
+
+        let fcoff = ControlMessage::new(MessageType::FCoff, true, Vec::new());
+        let replies = session.receive(&fcoff.to_frame(false).unwrap()).unwrap();
+        assert_eq!(replies.len(), 1);
+        assert!(!session.flow_enabled());
+
+        assert!(session.send(1, b"AT\r".to_vec()).unwrap().is_empty());
+        assert!(session.send(2, b"AT\r".to_vec()).unwrap().is_empty());
+        assert_eq!(session.send(0, Vec::new()).unwrap().len(), 1);
+        assert_eq!(session.queued(), 2);
+
+        let fcon = ControlMessage::new(MessageType::FCon, true, Vec::new());
+        let replies = session.receive(&fcon.to_frame(false).unwrap()).unwrap();
+        assert_eq!(replies.len(), 3);
+        let response = &ControlMessage::from_frame(&replies[0]).unwrap()[0];
+        assert_eq!(response.message_type, MessageType::FCon);
+        assert!(!response.cr);
+        assert_eq!(replies[2].address.dlci().into_bits(), 2);
+        assert_eq!(session.queued(), 0);
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                SessionEvent::FlowControl { enabled: false },
+                SessionEvent::FlowControl { enabled: true }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_not_supported() {
+        let mut session = Session::new();
+        let command = ControlMessage::new(MessageType::SNC, true, vec![0x07]);
+        for _ in 0..2 {
+            let frame = Nsc::new(&command).to_message().to_frame(false).unwrap();
+            assert!(session.receive(&frame).unwrap().is_empty());
+        }
+        assert_eq!(session.rejected(), [MessageType::SNC]);
+    }
+}
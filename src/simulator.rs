@@ -0,0 +1,390 @@
+use crate::control::{ControlMessage, MessageType, Nsc, ParameterNegotiation};
+use crate::mux::{Mux, MuxEvent};
+use crate::parser::FrameParser;
+use crate::transport::Transport;
+use crate::types::Frame;
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind};
+use std::time::Instant;
+
+/// Canned replies of the simulated modem: the AT command and its information line, if any.
+pub const REPLIES: &[(&str, &str)] = &[
+    ("AT", ""),
+    ("ATI", "cmux simulator"),
+    ("AT+CGMI", "cmux"),
+    ("AT+CGMM", "simulator"),
+    ("AT+CGMR", env!("CARGO_PKG_VERSION")),
+    ("AT+CPIN?", "+CPIN: READY"),
+    ("AT+CSQ", "+CSQ: 20,99"),
+    ("AT+CREG?", "+CREG: 0,1"),
+    ("AT+COPS?", "+COPS: 0,0,\"cmux\",7"),
+];
+
+/// Responding station of a multiplexer session, standing in for a modem
+///
+/// The simulator answers SABM and DISC like the responder [`Mux`], and also echoes Test
+/// commands, accepts the parameters of PN commands, acknowledges PSC and CLD, and rejects the
+/// other commands it does not handle with NSC. Command lines received on the AT channel, DLCI 1
+/// by default, get the canned replies of [`REPLIES`], or `ERROR`. Command echo can be switched
+/// off with `ATE0`, like on a modem.
+///
+/// Application code and the engine can then be tested without hardware.
+///
+/// # Example
+///
+/// ```
+/// use cmux::at::{ResponseReader, ResultCode};
+/// use cmux::mux::{Mux, MuxEvent};
+/// use cmux::simulator::Simulator;
+/// use cmux::transport::{Link, MemoryTransport};
+/// use std::time::Instant;
+///
+/// let (a, b) = MemoryTransport::pair();
+/// let mut host = Link::new(Mux::new(), a);
+/// let mut modem = Simulator::new(b);
+///
+/// let now = Instant::now();
+/// host.mux_mut().open(0, now).unwrap();
+/// host.mux_mut().open(1, now).unwrap();
+/// host.flush().unwrap();
+/// modem.poll(now).unwrap();
+/// host.poll(now).unwrap();
+///
+/// host.mux_mut().send_command(1, "AT+CSQ").unwrap();
+/// host.flush().unwrap();
+/// modem.poll(now).unwrap();
+/// host.poll(now).unwrap();
+///
+/// let mut reader = ResponseReader::new("AT+CSQ");
+/// let response = std::iter::from_fn(|| host.mux_mut().poll_event())
+///     .find_map(|event| match event {
+///         MuxEvent::Data { dlci: 1, data } => reader.feed(&data),
+///         _ => None,
+///     })
+///     .unwrap();
+/// assert_eq!(response.lines, ["+CSQ: 20,99"]);
+/// assert_eq!(response.result, ResultCode::Ok);
+/// ```
+#[derive(Debug)]
+pub struct Simulator<T> {
+    mux: Mux,
+    parser: FrameParser,
+    transport: T,
+    buffer: Vec<u8>,
+    at_dlci: u8,
+    replies: Vec<(String, String)>,
+    /// Command line received on the AT channel, up to its terminating CR.
+    line: Vec<u8>,
+    echo: bool,
+    events: VecDeque<MuxEvent>,
+}
+
+impl<T: Transport> Simulator<T> {
+    /// Size of the read buffer.
+    const BUFFER_SIZE: usize = 4096;
+
+    /// Simulates a modem on `transport` with basic option framing.
+    pub fn new(transport: T) -> Self {
+        Simulator {
+            mux: Mux::new().with_initiator(false),
+            parser: FrameParser::new(),
+            transport,
+            buffer: vec![0; Self::BUFFER_SIZE],
+            at_dlci: 1,
+            replies: REPLIES
+                .iter()
+                .map(|&(command, info)| (command.to_string(), info.to_string()))
+                .collect(),
+            line: Vec::new(),
+            echo: true,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Switches to advanced option framing.
+    pub fn advanced(mut self) -> Self {
+        self.parser = FrameParser::advanced();
+        self
+    }
+
+    /// Answers AT commands on `dlci` instead of DLCI 1.
+    pub fn with_at_dlci(mut self, dlci: u8) -> Self {
+        self.at_dlci = dlci;
+        self
+    }
+
+    /// Answers `command` with the information line `info`, followed by `OK`. An empty `info`
+    /// answers `OK` alone.
+    pub fn with_reply(mut self, command: &str, info: &str) -> Self {
+        let command = command.trim().to_ascii_uppercase();
+        self.replies.retain(|(known, _)| *known != command);
+        self.replies.push((command, info.to_string()));
+        self
+    }
+
+    /// Returns the engine.
+    pub fn mux(&self) -> &Mux {
+        &self.mux
+    }
+
+    /// Returns the engine, to send data as the modem.
+    pub fn mux_mut(&mut self) -> &mut Mux {
+        &mut self.mux
+    }
+
+    /// Returns the transport.
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Writes `frame` with the framing of the session.
+    fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        let data = if self.parser.is_advanced() {
+            frame.to_bytes_advanced()
+        } else {
+            frame.to_bytes()
+        };
+        self.transport.write(&data)
+    }
+
+    /// Writes every frame queued by the engine.
+    fn flush(&mut self) -> io::Result<()> {
+        while let Some(frame) = self.mux.poll_transmit() {
+            self.write_frame(&frame)?;
+        }
+        Ok(())
+    }
+
+    /// Reads once from the transport, answers the frames received, and runs the engine.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(usize)`: The number of bytes read.
+    /// - `Err(io::Error)`: If the transport failed.
+    pub fn poll(&mut self, now: Instant) -> io::Result<usize> {
+        let count = self.transport.read(&mut self.buffer)?;
+        let frames = self.parser.feed(&self.buffer[..count]);
+        for frame in frames {
+            self.mux.receive(&frame, now);
+            self.receive_events()?;
+            self.flush()?;
+            self.answer_control(&frame)?;
+        }
+        self.mux.poll_timeout(now);
+        self.receive_events()?;
+        self.flush()?;
+        Ok(count)
+    }
+
+    /// Moves the engine events to the queue of [`Simulator::poll_event`], answering the data
+    /// of the AT channel.
+    fn receive_events(&mut self) -> io::Result<()> {
+        while let Some(event) = self.mux.poll_event() {
+            if let MuxEvent::Data { dlci, data } = &event {
+                if *dlci == self.at_dlci {
+                    self.receive_at(data)?;
+                }
+            }
+            self.events.push_back(event);
+        }
+        Ok(())
+    }
+
+    /// Returns the next event of the session, the engine events included.
+    pub fn poll_event(&mut self) -> Option<MuxEvent> {
+        self.events.pop_front()
+    }
+
+    /// Answers the control commands carried by `frame` that the engine leaves unanswered.
+    fn answer_control(&mut self, frame: &Frame) -> io::Result<()> {
+        let Ok(messages) = ControlMessage::from_frame(frame) else {
+            return Ok(());
+        };
+        for message in messages.iter().filter(|message| message.cr) {
+            let response = match message.message_type {
+                message_type if Mux::ANSWERED.contains(&message_type) => continue,
+                // Only sent as a response.
+                MessageType::NSC => continue,
+                MessageType::Test => ControlMessage::test(false, message.value.clone()),
+                MessageType::PN => match ParameterNegotiation::try_from(message) {
+                    Ok(pn) => {
+                        self.mux.set_parameters(pn);
+                        pn.to_message(false)
+                    }
+                    Err(_) => Nsc::new(message).to_message(),
+                },
+                MessageType::CLD => ControlMessage::new(MessageType::CLD, false, Vec::new()),
+                _ => Nsc::new(message).to_message(),
+            };
+            let frame = response
+                .to_frame(false)
+                .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+            self.write_frame(&frame)?;
+            if message.message_type == MessageType::CLD {
+                self.mux = Mux::new()
+                    .with_initiator(false)
+                    .with_config(*self.mux.config());
+                self.line.clear();
+                self.echo = true;
+                self.events.push_back(MuxEvent::DlcClosed(0));
+            }
+        }
+        Ok(())
+    }
+
+    /// Answers the command lines completed by `data`, received on the AT channel.
+    fn receive_at(&mut self, data: &[u8]) -> io::Result<()> {
+        for &byte in data {
+            if byte != b'\r' {
+                if byte != b'\n' {
+                    self.line.push(byte);
+                }
+                continue;
+            }
+            let line = String::from_utf8_lossy(&self.line).trim().to_string();
+            self.line.clear();
+            if line.is_empty() {
+                continue;
+            }
+            let mut response = if self.echo {
+                format!("{}\r", line)
+            } else {
+                String::new()
+            };
+            response.push_str(&self.reply(&line));
+            self.mux
+                .send(self.at_dlci, response.as_bytes())
+                .map_err(|e| io::Error::new(ErrorKind::NotConnected, e))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the response to the command `line`, with its final result code.
+    fn reply(&mut self, line: &str) -> String {
+        let command = line.to_ascii_uppercase();
+        match command.as_str() {
+            "ATE0" | "ATE1" => {
+                self.echo = command == "ATE1";
+                return "\r\nOK\r\n".to_string();
+            }
+            _ => {}
+        }
+        match self.replies.iter().find(|(known, _)| *known == command) {
+            Some((_, info)) if info.is_empty() => "\r\nOK\r\n".to_string(),
+            Some((_, info)) => format!("\r\n{}\r\n\r\nOK\r\n", info),
+            None => "\r\nERROR\r\n".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mux::DlcState;
+    use crate::transport::{Link, MemoryTransport};
+
+    fn connect() -> (Link<MemoryTransport>, Simulator<MemoryTransport>) {
+        let now = Instant::now();
+        let (a, b) = MemoryTransport::pair();
+        let mut host = Link::new(Mux::new(), a);
+        let mut modem = Simulator::new(b);
+        host.mux_mut().open(0, now).unwrap();
+        host.mux_mut().open(1, now).unwrap();
+        assert_eq!(
+            exchange(&mut host, &mut modem),
+            [MuxEvent::DlcOpened(0), MuxEvent::DlcOpened(1)]
+        );
+        (host, modem)
+    }
+
+    fn exchange(
+        host: &mut Link<MemoryTransport>,
+        modem: &mut Simulator<MemoryTransport>,
+    ) -> Vec<MuxEvent> {
+        let now = Instant::now();
+        host.flush().unwrap();
+        modem.poll(now).unwrap();
+        host.poll(now).unwrap();
+        std::iter::from_fn(|| host.mux_mut().poll_event()).collect()
+    }
+
+    #[test]
+    fn test_control_commands() {
+        let (mut host, mut modem) = connect();
+        let now = Instant::now();
+        let pn = ParameterNegotiation::new(2).with_priority(7);
+        let mux = host.mux_mut();
+        mux.send_control(ControlMessage::test(true, b"ping".to_vec()), now)
+            .unwrap();
+        mux.send_control(pn.to_message(true), now).unwrap();
+        mux.send_control(ControlMessage::new(MessageType::SNC, true, Vec::new()), now)
+            .unwrap();
+        let events = exchange(&mut host, &mut modem);
+        assert!(events.is_empty(), "{:?}", events);
+        assert_eq!(modem.mux().parameters(2).priority, 7);
+        assert_eq!(host.mux().parameters(2).priority, 7);
+
+        // T2 would expire and raise NoResponse if a command was left unanswered.
+        let later = now + host.mux().config().t2 * 2;
+        host.mux_mut().poll_timeout(later);
+        assert_eq!(host.mux_mut().poll_event(), None);
+
+        host.mux_mut()
+            .send_control(ControlMessage::new(MessageType::CLD, true, Vec::new()), now)
+            .unwrap();
+        exchange(&mut host, &mut modem);
+        assert_eq!(modem.mux().state(1), DlcState::Closed);
+        assert!(std::iter::from_fn(|| modem.poll_event()).any(|e| e == MuxEvent::DlcClosed(0)));
+    }
+
+    #[test]
+    fn test_power_saving() {
+        let (mut host, mut modem) = connect();
+        let now = Instant::now();
+        host.mux_mut().sleep(now).unwrap();
+        host.flush().unwrap();
+        modem.poll(now).unwrap();
+
+        // The engine answers PSC, so the simulator leaves it alone.
+        let mut buf = [0; 64];
+        let count = host.transport_mut().read(&mut buf).unwrap();
+        let frames = FrameParser::new().feed(&buf[..count]);
+        assert_eq!(frames.len(), 1);
+        let messages = ControlMessage::from_frame(&frames[0]).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].message_type, MessageType::PSC);
+        assert!(!messages[0].cr);
+        host.mux_mut().receive(&frames[0], now);
+        assert_eq!(host.mux().power_state(), crate::mux::PowerState::Sleeping);
+    }
+
+    #[test]
+    fn test_at_commands() {
+        let (mut host, modem) = connect();
+        let mut modem = modem.with_reply("at+cgsn", "490154203237518");
+        let mut modem_at = |host: &mut Link<MemoryTransport>, command: &str| {
+            host.mux_mut().send_command(1, command).unwrap();
+            exchange(host, &mut modem)
+                .into_iter()
+                .filter_map(|event| match event {
+                    MuxEvent::Data { dlci: 1, data } => Some(String::from_utf8(data).unwrap()),
+                    _ => None,
+                })
+                .collect::<String>()
+        };
+        assert_eq!(
+            modem_at(&mut host, "ATI"),
+            "ATI\r\r\ncmux simulator\r\n\r\nOK\r\n"
+        );
+        assert_eq!(modem_at(&mut host, "ate0"), "ate0\r\r\nOK\r\n");
+        assert_eq!(
+            modem_at(&mut host, "AT+CPIN?"),
+            "\r\n+CPIN: READY\r\n\r\nOK\r\n"
+        );
+        assert_eq!(modem_at(&mut host, "AT+CFUN=0"), "\r\nERROR\r\n");
+        assert_eq!(
+            modem_at(&mut host, "AT+CGSN"),
+            "\r\n490154203237518\r\n\r\nOK\r\n"
+        );
+    }
+}
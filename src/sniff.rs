@@ -0,0 +1,143 @@
+use crate::parser::FrameParser;
+use crate::types::Frame;
+use std::time::{Duration, Instant};
+
+/// Direction of the tapped line a frame was seen on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// From the host to the modem.
+    Tx,
+    /// From the modem to the host.
+    Rx,
+}
+
+impl Direction {
+    /// Returns the label used for this direction in logs.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Direction::Tx => "TX",
+            Direction::Rx => "RX",
+        }
+    }
+}
+
+/// Frame decoded by a [`Sniffer`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SniffedFrame {
+    /// Time since the sniffer was created, taken when the closing flag arrived.
+    pub timestamp: Duration,
+    pub direction: Direction,
+    pub frame: Frame,
+}
+
+/// Passive decoder of both directions of a multiplexer link
+///
+/// Each direction has its own [`FrameParser`], so frames interleaved on two taps do not
+/// corrupt each other. The sniffer never writes to the link; it performs no I/O and is fed
+/// the bytes read from the taps together with the time they were read.
+///
+/// # Example
+///
+/// ```
+/// use cmux::sniff::{Direction, Sniffer};
+/// use std::time::{Duration, Instant};
+///
+/// let start = Instant::now();
+/// let mut sniffer = Sniffer::new(start);
+/// assert!(sniffer.feed(Direction::Tx, &[0xF9, 0x03, 0x3F], start).is_empty());
+/// let frames = sniffer.feed(Direction::Tx, &[0x01, 0x1C, 0xF9], start + Duration::from_millis(5));
+/// assert_eq!(frames[0].direction, Direction::Tx);
+/// assert_eq!(frames[0].timestamp, Duration::from_millis(5));
+/// ```
+#[derive(Debug)]
+pub struct Sniffer {
+    start: Instant,
+    tx: FrameParser,
+    rx: FrameParser,
+}
+
+impl Sniffer {
+    /// Creates a sniffer for basic option frames, timestamping frames from `start`.
+    pub fn new(start: Instant) -> Self {
+        Sniffer {
+            start,
+            tx: FrameParser::new(),
+            rx: FrameParser::new(),
+        }
+    }
+
+    /// Creates a sniffer for advanced option frames, timestamping frames from `start`.
+    pub fn advanced(start: Instant) -> Self {
+        Sniffer {
+            start,
+            tx: FrameParser::advanced(),
+            rx: FrameParser::advanced(),
+        }
+    }
+
+    /// Feeds the bytes read from one tap.
+    ///
+    /// # Arguments
+    ///
+    /// * `direction` - The tap the bytes were read from.
+    /// * `data` - The next chunk of that direction's byte stream.
+    /// * `now` - The time the chunk was read.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec<SniffedFrame>` of every frame completed by this chunk, in stream order.
+    pub fn feed(&mut self, direction: Direction, data: &[u8], now: Instant) -> Vec<SniffedFrame> {
+        let timestamp = now.saturating_duration_since(self.start);
+        self.parser_mut(direction)
+            .feed(data)
+            .into_iter()
+            .map(|frame| SniffedFrame {
+                timestamp,
+                direction,
+                frame,
+            })
+            .collect()
+    }
+
+    /// Returns the number of bytes of `direction` dropped because they did not belong to a frame.
+    pub fn skipped(&self, direction: Direction) -> usize {
+        match direction {
+            Direction::Tx => self.tx.skipped(),
+            Direction::Rx => self.rx.skipped(),
+        }
+    }
+
+    fn parser_mut(&mut self, direction: Direction) -> &mut FrameParser {
+        match direction {
+            Direction::Tx => &mut self.tx,
+            Direction::Rx => &mut self.rx,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interleaved_directions() {
+        let start = Instant::now();
+        let mut sniffer = Sniffer::new(start);
+        assert!(sniffer.feed(Direction::Tx, &[0xF9, 0x03], start).is_empty());
+        assert!(sniffer
+            .feed(Direction::Rx, &[0x00, 0xF9, 0x03, 0x73], start)
+            .is_empty());
+
+        let later = start + Duration::from_millis(20);
+        let rx = sniffer.feed(Direction::Rx, &[0x01, 0xD7, 0xF9], later);
+        assert_eq!(rx.len(), 1);
+        assert_eq!(rx[0].frame.to_hex_string(), "f9037301d7f9");
+        assert_eq!(rx[0].timestamp, Duration::from_millis(20));
+
+        let tx = sniffer.feed(Direction::Tx, &[0x3F, 0x01, 0x1C, 0xF9], later);
+        assert_eq!(tx[0].direction, Direction::Tx);
+        assert_eq!(tx[0].frame.to_hex_string(), "f9033f011cf9");
+        assert_eq!(sniffer.skipped(Direction::Rx), 1);
+        assert_eq!(sniffer.skipped(Direction::Tx), 0);
+    }
+}
@@ -0,0 +1,390 @@
+use crate::anomaly::{Anomaly, AnomalyDetector};
+use crate::error::FrameError;
+use crate::sniff::Direction;
+use crate::types::{Frame, FrameType};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// Statistics of one DLC
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DlcStats {
+    /// Number of frames of each type, in order of first appearance.
+    pub frame_types: Vec<(FrameType, usize)>,
+    /// Octets of information field sent by the initiator, or logged as TX.
+    pub tx_bytes: usize,
+    /// Octets of information field sent by the responder, or logged as RX.
+    pub rx_bytes: usize,
+    /// Number of frames whose FCS does not match.
+    pub fcs_errors: usize,
+    /// Number of SABM, DISC and DLCI 0 commands sent again before their response.
+    pub retransmissions: usize,
+    /// Number of times the DLC was connected.
+    pub opened: usize,
+    /// Number of times the DLC was closed or rejected.
+    pub closed: usize,
+}
+
+impl DlcStats {
+    /// Returns the total number of frames.
+    pub fn frames(&self) -> usize {
+        self.frame_types.iter().map(|(_, count)| count).sum()
+    }
+}
+
+/// Change of state of a DLC in the [`Stats::timeline`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DlcEvent {
+    /// UA answered SABM.
+    Opened,
+    /// UA answered DISC, or DM answered DISC.
+    Closed,
+    /// DM answered SABM.
+    Rejected,
+}
+
+impl DlcEvent {
+    /// Returns the label used for this event in reports.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            DlcEvent::Opened => "opened",
+            DlcEvent::Closed => "closed",
+            DlcEvent::Rejected => "rejected",
+        }
+    }
+}
+
+/// Entry of the [`Stats::timeline`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimelineEntry {
+    /// Index of the frame raising the event, from 0.
+    pub index: usize,
+    pub timestamp: Option<Duration>,
+    pub dlci: u8,
+    pub event: DlcEvent,
+}
+
+/// Pattern of a failing link in the [`Stats::anomalies`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnomalyEntry {
+    /// Index of the frame completing the anomaly, or of the next frame when it is found in
+    /// the bytes between frames.
+    pub index: usize,
+    pub timestamp: Option<Duration>,
+    pub anomaly: Anomaly,
+}
+
+/// Statistics of the frames of a session
+///
+/// Frames are recorded in the order they were captured. Without a logged direction, the
+/// direction is told by the C/R bit: frames sent by the initiator count as TX.
+///
+/// A SABM or DISC, or a frame on DLCI 0, identical to the last one sent in the same direction
+/// and not yet followed by a frame of the other direction on that DLC, is a retransmission.
+///
+/// The frames, and the raw bytes passed to [`Stats::record_bytes`], also go through an
+/// [`AnomalyDetector`], whose findings are kept in [`Stats::anomalies`].
+///
+/// # Example
+///
+/// ```
+/// use cmux::stats::{DlcEvent, Stats};
+/// use cmux::types::Frame;
+///
+/// let mut stats = Stats::new();
+/// for hex in ["F9033F011CF9", "F9033F011CF9", "F9037301D7F9"] {
+///     let frame = Frame::from_bytes(hex::decode(hex).unwrap()).unwrap();
+///     stats.record(&frame, None, None);
+/// }
+/// let dlc = &stats.dlcs()[&0];
+/// assert_eq!(dlc.frames(), 3);
+/// assert_eq!(dlc.retransmissions, 1);
+/// assert_eq!(stats.timeline()[0].event, DlcEvent::Opened);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    dlcs: BTreeMap<u8, DlcStats>,
+    timeline: Vec<TimelineEntry>,
+    detector: AnomalyDetector,
+    anomalies: Vec<AnomalyEntry>,
+    /// The last command of each DLC awaiting a frame of the other direction.
+    pending: BTreeMap<u8, (Direction, Frame)>,
+    frames: usize,
+}
+
+impl Stats {
+    /// Creates empty statistics.
+    pub fn new() -> Self {
+        Stats::default()
+    }
+
+    /// Creates empty statistics of advanced option frames.
+    pub fn advanced() -> Self {
+        Stats {
+            detector: AnomalyDetector::advanced(),
+            ..Stats::default()
+        }
+    }
+
+    /// Returns the direction of `frame`, as told by its C/R bit.
+    fn direction(frame: &Frame) -> Direction {
+        let command = !matches!(frame.control.frame_type(), FrameType::UA | FrameType::DM);
+        if frame.address.cr() == command {
+            Direction::Tx
+        } else {
+            Direction::Rx
+        }
+    }
+
+    /// Records a frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The captured frame.
+    /// * `direction` - The direction it was logged with, if any.
+    /// * `timestamp` - The time it was logged, if any.
+    pub fn record(
+        &mut self,
+        frame: &Frame,
+        direction: Option<Direction>,
+        timestamp: Option<Duration>,
+    ) {
+        let index = self.frames;
+        self.frames += 1;
+        let dlci = frame.address.dlci().into_bits();
+        let frame_type = frame.control.frame_type();
+        let direction = direction.unwrap_or_else(|| Stats::direction(frame));
+        let dlc = self.dlcs.entry(dlci).or_default();
+        match dlc.frame_types.iter_mut().find(|(t, _)| *t == frame_type) {
+            Some((_, count)) => *count += 1,
+            None => dlc.frame_types.push((frame_type, 1)),
+        }
+        match direction {
+            Direction::Tx => dlc.tx_bytes += frame.content.len(),
+            Direction::Rx => dlc.rx_bytes += frame.content.len(),
+        }
+        if matches!(frame.verify(), Err(FrameError::ChecksumMismatch { .. })) {
+            dlc.fcs_errors += 1;
+        }
+        if let Some(anomaly) = self.detector.record(frame) {
+            self.anomalies.push(AnomalyEntry {
+                index,
+                timestamp,
+                anomaly,
+            });
+        }
+
+        let pending = self.pending.remove(&dlci);
+        let event = match (&pending, frame_type) {
+            (Some((from, last)), _) if *from == direction && last == frame => {
+                dlc.retransmissions += 1;
+                None
+            }
+            (Some((from, last)), FrameType::UA) if *from != direction => {
+                match last.control.frame_type() {
+                    FrameType::SABM => Some(DlcEvent::Opened),
+                    FrameType::DISC => Some(DlcEvent::Closed),
+                    _ => None,
+                }
+            }
+            (Some((from, last)), FrameType::DM) if *from != direction => {
+                match last.control.frame_type() {
+                    FrameType::SABM => Some(DlcEvent::Rejected),
+                    FrameType::DISC => Some(DlcEvent::Closed),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        match event {
+            Some(DlcEvent::Opened) => dlc.opened += 1,
+            Some(_) => dlc.closed += 1,
+            None => {}
+        }
+        if let Some(event) = event {
+            self.timeline.push(TimelineEntry {
+                index,
+                timestamp,
+                dlci,
+                event,
+            });
+        }
+
+        let command = matches!(frame_type, FrameType::SABM | FrameType::DISC) || dlci == 0;
+        match pending {
+            // A frame of the same direction keeps the command awaiting its response.
+            Some((from, last)) if from == direction && !command => {
+                self.pending.insert(dlci, (from, last));
+            }
+            _ if command && event.is_none() => {
+                self.pending.insert(dlci, (direction, frame.clone()));
+            }
+            _ => {}
+        }
+    }
+
+    /// Looks for the anomalies of the raw bytes of the link, such as runs of flags and reset
+    /// banners.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The next bytes captured.
+    /// * `timestamp` - The time they were logged, if any.
+    pub fn record_bytes(&mut self, data: &[u8], timestamp: Option<Duration>) {
+        for anomaly in self.detector.feed(data) {
+            self.anomalies.push(AnomalyEntry {
+                index: self.frames,
+                timestamp,
+                anomaly,
+            });
+        }
+    }
+
+    /// Returns the statistics of each DLC seen.
+    pub fn dlcs(&self) -> &BTreeMap<u8, DlcStats> {
+        &self.dlcs
+    }
+
+    /// Returns the connections and disconnections of the DLCs, in order.
+    pub fn timeline(&self) -> &[TimelineEntry] {
+        &self.timeline
+    }
+
+    /// Returns the anomalies found, in order.
+    pub fn anomalies(&self) -> &[AnomalyEntry] {
+        &self.anomalies
+    }
+}
+
+impl Display for Stats {
+    /// Prints one line per DLC, followed by the timeline and the anomalies.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} frames on {} DLCs", self.frames, self.dlcs.len())?;
+        for (dlci, dlc) in &self.dlcs {
+            let types: Vec<String> = dlc
+                .frame_types
+                .iter()
+                .map(|(frame_type, count)| format!("{:?} {}", frame_type, count))
+                .collect();
+            writeln!(
+                f,
+                "DLCI {}: {} frames ({}), TX {} bytes, RX {} bytes, {} FCS errors, {} retransmissions, opened {}, closed {}",
+                dlci,
+                dlc.frames(),
+                types.join(", "),
+                dlc.tx_bytes,
+                dlc.rx_bytes,
+                dlc.fcs_errors,
+                dlc.retransmissions,
+                dlc.opened,
+                dlc.closed
+            )?;
+        }
+        if !self.timeline.is_empty() {
+            writeln!(f, "Timeline:")?;
+        }
+        for entry in &self.timeline {
+            write!(f, "  frame {}", entry.index)?;
+            if let Some(timestamp) = entry.timestamp {
+                write!(f, " at {:.6}", timestamp.as_secs_f64())?;
+            }
+            writeln!(f, ": DLCI {} {}", entry.dlci, entry.event.as_str())?;
+        }
+        if !self.anomalies.is_empty() {
+            writeln!(f, "Anomalies:")?;
+        }
+        for entry in &self.anomalies {
+            write!(f, "  frame {}", entry.index)?;
+            if let Some(timestamp) = entry.timestamp {
+                write!(f, " at {:.6}", timestamp.as_secs_f64())?;
+            }
+            writeln!(f, ": {}", entry.anomaly)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mux::Mux;
+    use std::time::Instant;
+
+    /// Moves every frame queued by `from` into `to`, keeping a copy in `frames`.
+    fn exchange(from: &mut Mux, to: &mut Mux, frames: &mut Vec<Frame>) {
+        while let Some(frame) = from.poll_transmit() {
+            to.receive(&frame, Instant::now());
+            frames.push(frame);
+        }
+    }
+
+    #[test]
+    fn test_session_stats() {
+        let now = Instant::now();
+        let mut host = Mux::new();
+        let mut modem = Mux::new().with_initiator(false);
+        let mut frames = Vec::new();
+        host.open(0, now).unwrap();
+        host.open(1, now).unwrap();
+        exchange(&mut host, &mut modem, &mut frames);
+        exchange(&mut modem, &mut host, &mut frames);
+        host.send(1, b"AT\r").unwrap();
+        exchange(&mut host, &mut modem, &mut frames);
+        modem.send(1, b"\r\nOK\r\n").unwrap();
+        exchange(&mut modem, &mut host, &mut frames);
+        host.close(1, now).unwrap();
+        exchange(&mut host, &mut modem, &mut frames);
+        exchange(&mut modem, &mut host, &mut frames);
+
+        let mut stats = Stats::new();
+        for (i, frame) in frames.iter().enumerate() {
+            stats.record(frame, None, Some(Duration::from_millis(i as u64 * 10)));
+        }
+        let dlc = &stats.dlcs()[&1];
+        assert_eq!(dlc.frames(), 6);
+        assert_eq!((dlc.tx_bytes, dlc.rx_bytes), (3, 6));
+        assert_eq!((dlc.opened, dlc.closed), (1, 1));
+        assert_eq!(dlc.retransmissions, 0);
+        let events: Vec<_> = stats
+            .timeline()
+            .iter()
+            .map(|entry| (entry.index, entry.dlci, entry.event))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                (2, 0, DlcEvent::Opened),
+                (3, 1, DlcEvent::Opened),
+                (7, 1, DlcEvent::Closed)
+            ]
+        );
+        assert!(stats
+            .to_string()
+            .contains("frame 7 at 0.070000: DLCI 1 closed"));
+    }
+
+    #[test]
+    fn test_anomalies() {
+        let dm = Frame::from_bytes(hex::decode("F9031F0136F9").unwrap()).unwrap();
+        let mut stats = Stats::new();
+        for _ in 0..3 {
+            stats.record(&dm, None, None);
+        }
+        stats.record_bytes(b"\r\nRDY\r\n", Some(Duration::from_secs(1)));
+        let anomalies: Vec<_> = stats
+            .anomalies()
+            .iter()
+            .map(|entry| (entry.index, entry.anomaly))
+            .collect();
+        assert_eq!(
+            anomalies,
+            [
+                (2, Anomaly::RepeatedDm { count: 3 }),
+                (3, Anomaly::ResetBanner { banner: "RDY" })
+            ]
+        );
+        assert!(stats
+            .to_string()
+            .contains("frame 3 at 1.000000: reset banner RDY mid-stream"));
+    }
+}
@@ -0,0 +1,87 @@
+use crate::types::{Address, Control, Frame, FrameBuilder};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Content lengths exercised by [`long_frames`].
+///
+/// * 2: the shortest content `FrameBuilder` produces (`\r\n` only).
+/// * 126..=129: around the single/two octet length boundary.
+/// * 255/256: around the high length octet boundary.
+/// * 32766/32767: the largest content a 15-bit length indicator can describe.
+pub const STRESS_LENGTHS: [usize; 9] = [2, 126, 127, 128, 129, 255, 256, 32766, 32767];
+
+/// Generates the content for a frame of exactly `len` bytes, terminated by `\r\n`.
+///
+/// # Arguments
+///
+/// * `len` - The total content length. Must be at least 2.
+///
+/// # Returns
+///
+/// A `String` of `len` bytes.
+pub fn content_of_length(len: usize) -> String {
+    let fill = len.saturating_sub(2);
+    let mut content: String = (0..fill)
+        .map(|i| char::from(b'A' + (i % 26) as u8))
+        .collect();
+    content.push_str("\r\n");
+    content
+}
+
+/// Generates frames whose content lengths sit on the boundaries listed in [`STRESS_LENGTHS`].
+///
+/// # Arguments
+///
+/// * `address` - The address field of every frame.
+/// * `control` - The control field of every frame.
+///
+/// # Returns
+///
+/// A `Vec<Frame>` with one frame per entry of [`STRESS_LENGTHS`].
+///
+/// # Example
+///
+/// ```
+/// use cmux::stress::{long_frames, STRESS_LENGTHS};
+/// use cmux::types::{Address, Control};
+///
+/// let frames = long_frames(Address::default(), Control::default());
+/// assert_eq!(frames.len(), STRESS_LENGTHS.len());
+/// ```
+pub fn long_frames(address: Address, control: Control) -> Vec<Frame> {
+    STRESS_LENGTHS
+        .iter()
+        .map(|&len| {
+            FrameBuilder::default()
+                .with_address(address)
+                .with_control(control)
+                .with_content(content_of_length(len))
+                .build()
+                .expect("stress lengths fit in the length indicator")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_of_length() {
+        assert_eq!(content_of_length(2), "\r\n");
+        assert_eq!(content_of_length(5), "ABC\r\n");
+        assert_eq!(content_of_length(128).len(), 128);
+    }
+
+    #[test]
+    fn test_long_frames() {
+        let frames = long_frames(Address::default(), Control::default());
+        for (frame, len) in frames.iter().zip(STRESS_LENGTHS) {
+            assert_eq!(frame.content.len(), len);
+            assert!(frame.verify().is_ok());
+        }
+        assert_eq!(frames[2].length, 0xFF);
+        assert_eq!(frames[3].length, 128 << 1);
+        assert_eq!(frames[8].to_bytes().len(), 32767 + 7);
+    }
+}
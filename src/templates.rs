@@ -0,0 +1,189 @@
+use crate::control::{ControlMessage, MessageType, Msc, V24Signals};
+use crate::error::FrameError;
+use crate::session::Session;
+use crate::types::{Address, Control, Frame, FrameBuilder, FrameType, DLCI, FLAG};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// DLCIs opened and closed by the templates of [`TEMPLATES`], typically carrying AT commands,
+/// data and GNSS sentences.
+const CHANNELS: [u8; 3] = [1, 2, 3];
+
+/// Flag octets sent by the [`TEMPLATES`] waking the peer up.
+///
+/// 07.10 sends flags until the peer answers with flags; a burst of this length wakes up
+/// common modems.
+pub const WAKE_FLAGS: usize = 8;
+
+/// Ready-made sequence of frames sent by the initiating station
+///
+/// # Example
+///
+/// ```
+/// use cmux::templates;
+///
+/// let template = templates::find("startup").unwrap();
+/// let frames = template.frames().unwrap();
+/// assert_eq!(frames[0].to_hex_string(), "f9033f011cf9");
+/// assert_eq!(frames.len(), 4);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Template {
+    /// Name of the template, as given to `cmux template show`.
+    pub name: &'static str,
+    /// What the frames do, in one line.
+    pub description: &'static str,
+    /// Number of flag octets sent before the frames, to wake the peer up.
+    pub wake_flags: usize,
+    frames: fn() -> Result<Vec<Frame>, FrameError>,
+}
+
+impl Template {
+    /// Builds the frames of the template.
+    pub fn frames(&self) -> Result<Vec<Frame>, FrameError> {
+        (self.frames)()
+    }
+
+    /// Encodes the wake-up flags and the frames of the template as one stream of basic option
+    /// frames.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, FrameError> {
+        let mut data = vec![FLAG; self.wake_flags];
+        for frame in self.frames()? {
+            data.extend(frame.to_bytes());
+        }
+        Ok(data)
+    }
+}
+
+/// Templates of the common sequences, in the order `cmux template list` prints them.
+pub const TEMPLATES: &[Template] = &[
+    Template {
+        name: "startup",
+        description: "Open the control channel, then DLCIs 1 to 3",
+        wake_flags: 0,
+        frames: || startup(&CHANNELS),
+    },
+    Template {
+        name: "close",
+        description: "Close DLCI 1",
+        wake_flags: 0,
+        frames: || close(1).map(|frame| vec![frame]),
+    },
+    Template {
+        name: "closedown",
+        description: "Close DLCIs 1 to 3, then the multiplexer",
+        wake_flags: 0,
+        frames: || closedown(&CHANNELS),
+    },
+    Template {
+        name: "dtr-rts",
+        description: "Assert DTR and RTS on DLCI 1",
+        wake_flags: 0,
+        frames: || assert_dtr_rts(1).map(|frame| vec![frame]),
+    },
+    Template {
+        name: "sleep",
+        description: "Ask the peer to enter power saving mode",
+        wake_flags: 0,
+        frames: || sleep().map(|frame| vec![frame]),
+    },
+    Template {
+        name: "wake",
+        description: "Wake the peer up with flags, then check it answers a Test command",
+        wake_flags: WAKE_FLAGS,
+        frames: || wake().map(|frame| vec![frame]),
+    },
+];
+
+/// Returns the template named `name`, if any.
+pub fn find(name: &str) -> Option<&'static Template> {
+    TEMPLATES.iter().find(|template| template.name == name)
+}
+
+/// Builds a command frame of the initiating station without information field.
+fn command(dlci: u8, frame_type: FrameType) -> Result<Frame, FrameError> {
+    FrameBuilder::default()
+        .with_address(
+            Address::default()
+                .with_cr(true)
+                .with_dlci(DLCI::from_bits(dlci)),
+        )
+        .with_control(Control::default().with_frame_type(frame_type).with_pf(true))
+        .build()
+}
+
+/// Builds the SABM commands starting a session: DLCI 0, then every DLCI of `dlcis`.
+pub fn startup(dlcis: &[u8]) -> Result<Vec<Frame>, FrameError> {
+    core::iter::once(&0)
+        .chain(dlcis)
+        .map(|&dlci| command(dlci, FrameType::SABM))
+        .collect()
+}
+
+/// Builds the DISC command closing `dlci`.
+pub fn close(dlci: u8) -> Result<Frame, FrameError> {
+    command(dlci, FrameType::DISC)
+}
+
+/// Builds the DISC commands closing `dlcis`, followed by the CLD command closing the
+/// multiplexer, like [`Session::shutdown`].
+pub fn closedown(dlcis: &[u8]) -> Result<Vec<Frame>, FrameError> {
+    let mut session = Session::new();
+    for &dlci in dlcis {
+        session.open(dlci);
+    }
+    session.shutdown()
+}
+
+/// Builds the MSC command asserting DTR (RTC) and RTS (RTR) on `dlci`, which many modems
+/// wait for before sending data on a DLC.
+pub fn assert_dtr_rts(dlci: u8) -> Result<Frame, FrameError> {
+    Msc::new(dlci)
+        .with_signals(V24Signals::new().with_rtc(true).with_rtr(true))
+        .to_message(true)
+        .to_frame(true)
+}
+
+/// Builds the PSC command asking the peer to enter power saving mode.
+pub fn sleep() -> Result<Frame, FrameError> {
+    ControlMessage::new(MessageType::PSC, true, Vec::new()).to_frame(true)
+}
+
+/// Builds the Test command sent after the wake-up flags, answered once the peer is awake.
+pub fn wake() -> Result<Frame, FrameError> {
+    ControlMessage::test(true, b"WAKE".to_vec()).to_frame(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_templates_are_valid() {
+        for template in TEMPLATES {
+            let frames = template.frames().unwrap();
+            assert!(!frames.is_empty(), "{}", template.name);
+            assert!(frames.iter().all(|frame| frame.verify().is_ok()));
+            assert_eq!(find(template.name).unwrap().name, template.name);
+        }
+        assert!(find("reboot").is_none());
+    }
+
+    #[test]
+    fn test_sequences() {
+        let frames = startup(&[1, 2]).unwrap();
+        let dlcis: Vec<u8> = frames
+            .iter()
+            .map(|frame| frame.address.dlci().into_bits())
+            .collect();
+        assert_eq!(dlcis, [0, 1, 2]);
+        assert_eq!(frames[1].to_hex_string(), "f9073f01def9");
+        assert_eq!(close(1).unwrap().to_hex_string(), "f90753013ff9");
+        assert_eq!(
+            assert_dtr_rts(1).unwrap().to_hex_string(),
+            "f903ef09e305070dfbf9"
+        );
+        let data = find("wake").unwrap().to_bytes().unwrap();
+        assert_eq!(data[..WAKE_FLAGS], [FLAG; WAKE_FLAGS]);
+    }
+}
@@ -0,0 +1,417 @@
+use crate::types::Frame;
+use std::collections::HashMap;
+
+/// Escape septet that switches to the GSM 7-bit extension table.
+const GSM7_ESCAPE: u8 = 0x1B;
+
+/// GSM 03.38 default alphabet, indexed by septet value.
+const GSM7_BASIC: [char; 128] = [
+    '@', '£', '$', '¥', 'è', 'é', 'ù', 'ì', 'ò', 'Ç', '\n', 'Ø', 'ø', '\r', 'Å', 'å', //
+    'Δ', '_', 'Φ', 'Γ', 'Λ', 'Ω', 'Π', 'Ψ', 'Σ', 'Θ', 'Ξ', '\u{1B}', 'Æ', 'æ', 'ß', 'É', //
+    ' ', '!', '"', '#', '¤', '%', '&', '\'', '(', ')', '*', '+', ',', '-', '.', '/', //
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ':', ';', '<', '=', '>', '?', //
+    '¡', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', //
+    'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', 'Ä', 'Ö', 'Ñ', 'Ü', '§', //
+    '¿', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', //
+    'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', 'ä', 'ö', 'ñ', 'ü', 'à', //
+];
+
+/// Looks up a septet following [`GSM7_ESCAPE`] in the GSM 03.38 extension table.
+const fn gsm7_extension(septet: u8) -> Option<char> {
+    match septet {
+        0x0A => Some('\u{0C}'),
+        0x14 => Some('^'),
+        0x28 => Some('{'),
+        0x29 => Some('}'),
+        0x2F => Some('\\'),
+        0x3C => Some('['),
+        0x3D => Some('~'),
+        0x3E => Some(']'),
+        0x40 => Some('|'),
+        0x65 => Some('€'),
+        _ => None,
+    }
+}
+
+/// Unpacks GSM 7-bit packed octets into septets.
+///
+/// When the octets hold exactly a multiple of 8 septets and the last one is a CR,
+/// it is treated as padding and dropped, as specified by 3GPP TS 23.038.
+///
+/// # Arguments
+///
+/// * `data` - The packed octets.
+///
+/// # Returns
+///
+/// A `Vec<u8>` of septets, each in the range `0..=0x7F`.
+pub fn unpack_septets(data: &[u8]) -> Vec<u8> {
+    let count = data.len() * 8 / 7;
+    let mut septets: Vec<u8> = (0..count)
+        .map(|i| {
+            let bit = i * 7;
+            let (byte, shift) = (bit / 8, bit % 8);
+            let mut value = (data[byte] as u16) >> shift;
+            if shift > 1 {
+                value |= (data[byte + 1] as u16) << (8 - shift);
+            }
+            (value & 0x7F) as u8
+        })
+        .collect();
+    if count.is_multiple_of(8) && septets.last() == Some(&0x0D) {
+        septets.pop();
+    }
+    septets
+}
+
+/// Decodes unpacked GSM 7-bit septets, including extension table escapes.
+///
+/// Unknown extension characters decode to a space, following the fallback in 3GPP TS 23.038.
+pub fn decode_gsm7(septets: &[u8]) -> String {
+    let mut text = String::with_capacity(septets.len());
+    let mut iter = septets.iter().map(|s| s & 0x7F);
+    while let Some(septet) = iter.next() {
+        if septet == GSM7_ESCAPE {
+            match iter.next() {
+                Some(ext) => text.push(gsm7_extension(ext).unwrap_or(' ')),
+                None => break,
+            }
+        } else {
+            text.push(GSM7_BASIC[septet as usize]);
+        }
+    }
+    text
+}
+
+/// Decodes GSM 7-bit packed octets into text.
+///
+/// # Example
+///
+/// ```
+/// use cmux::text::decode_gsm7_packed;
+///
+/// let data = hex::decode("E8329BFD4697D9EC37").unwrap();
+/// assert_eq!(decode_gsm7_packed(&data), "hellohello");
+/// ```
+pub fn decode_gsm7_packed(data: &[u8]) -> String {
+    decode_gsm7(&unpack_septets(data))
+}
+
+/// Decodes big-endian UCS-2 octets into text.
+///
+/// Surrogate pairs are accepted. Unpaired surrogates and a trailing odd octet decode to U+FFFD.
+///
+/// # Example
+///
+/// ```
+/// use cmux::text::decode_ucs2;
+///
+/// assert_eq!(decode_ucs2(&[0x4F, 0x60, 0x59, 0x7D]), "你好");
+/// ```
+pub fn decode_ucs2(data: &[u8]) -> String {
+    let units = data
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]));
+    let mut text: String = char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+    if data.len() % 2 == 1 {
+        text.push(char::REPLACEMENT_CHARACTER);
+    }
+    text
+}
+
+/// Text encoding used to display the content of a [`Frame`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextEncoding {
+    /// UTF-8, with invalid sequences replaced by U+FFFD.
+    #[default]
+    Utf8,
+    /// GSM 7-bit packed default alphabet.
+    Gsm7,
+    /// Big-endian UCS-2.
+    Ucs2,
+    /// Chosen per payload by [`classify`].
+    Auto,
+}
+
+impl TextEncoding {
+    /// Decodes raw octets with this encoding.
+    pub fn decode(self, data: &[u8]) -> String {
+        match self {
+            TextEncoding::Utf8 => String::from_utf8_lossy(data).to_string(),
+            TextEncoding::Gsm7 => decode_gsm7_packed(data),
+            TextEncoding::Ucs2 => decode_ucs2(data),
+            TextEncoding::Auto => render_auto(data).1,
+        }
+    }
+}
+
+/// Kind of payload detected by [`classify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadKind {
+    /// Printable ASCII, such as AT commands and responses.
+    AtText,
+    /// Anything that is not recognised as text.
+    Binary,
+    /// GSM 7-bit packed text, raw or hex-encoded.
+    Gsm7,
+    /// UCS-2 text, raw or hex-encoded.
+    Ucs2,
+}
+
+impl PayloadKind {
+    /// Returns the name used for this kind in machine-readable output.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            PayloadKind::AtText => "at_text",
+            PayloadKind::Binary => "binary",
+            PayloadKind::Gsm7 => "gsm7",
+            PayloadKind::Ucs2 => "ucs2",
+        }
+    }
+}
+
+/// Minimum number of hex digits before hex text is considered GSM 7-bit or UCS-2.
+const MIN_ENCODED_HEX_DIGITS: usize = 8;
+
+fn is_printable_ascii(data: &[u8]) -> bool {
+    data.iter()
+        .all(|&b| b.is_ascii_graphic() || matches!(b, b' ' | b'\r' | b'\n' | b'\t'))
+}
+
+/// Returns true if `raw` decodes as printable UCS-2, optionally requiring mostly Latin-1 code units.
+fn looks_like_ucs2(raw: &[u8], latin_only: bool) -> bool {
+    if raw.is_empty() || raw.len() % 2 == 1 {
+        return false;
+    }
+    let printable = char::decode_utf16(
+        raw.chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]])),
+    )
+    .all(|c| matches!(c, Ok(c) if !c.is_control() || c == '\r' || c == '\n'));
+    let latin = raw.chunks_exact(2).filter(|pair| pair[0] == 0x00).count();
+    printable && (!latin_only || latin * 2 >= raw.len() / 2)
+}
+
+/// Returns true if `raw` unpacks to GSM 7-bit text made mostly of letters, digits and punctuation.
+fn looks_like_gsm7(raw: &[u8]) -> bool {
+    let text = decode_gsm7_packed(raw);
+    let total = text.chars().count();
+    let common = text
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || c.is_ascii_punctuation() || *c == ' ')
+        .count();
+    total > 0 && common * 10 >= total * 9
+}
+
+/// Heuristically classifies a payload.
+///
+/// * Printable ASCII is [`PayloadKind::AtText`], unless it is at least 8 hex digits that decode
+///   as mostly-Latin UCS-2, mostly-alphanumeric GSM 7-bit, or other printable UCS-2, in that
+///   order. Decimal-only strings are only considered as mostly-Latin UCS-2.
+/// * Raw octets that decode as mostly Latin-1 UCS-2 are [`PayloadKind::Ucs2`].
+/// * Everything else is [`PayloadKind::Binary`].
+///
+/// A trailing `\r\n` is ignored.
+///
+/// # Example
+///
+/// ```
+/// use cmux::text::{classify, PayloadKind};
+///
+/// assert_eq!(classify(b"+CSQ: 21,99\r\n"), PayloadKind::AtText);
+/// assert_eq!(classify(b"00480069\r\n"), PayloadKind::Ucs2);
+/// assert_eq!(classify(b"E8329BFD4697D9EC37\r\n"), PayloadKind::Gsm7);
+/// assert_eq!(classify(&[0x7E, 0xFF, 0x03, 0xC0]), PayloadKind::Binary);
+/// ```
+pub fn classify(data: &[u8]) -> PayloadKind {
+    let body = data.strip_suffix(b"\r\n").unwrap_or(data);
+    if is_printable_ascii(body) {
+        if body.len() < MIN_ENCODED_HEX_DIGITS {
+            return PayloadKind::AtText;
+        }
+        let decimal = body.iter().all(u8::is_ascii_digit);
+        return match hex::decode(body) {
+            Ok(raw) if looks_like_ucs2(&raw, true) => PayloadKind::Ucs2,
+            _ if decimal => PayloadKind::AtText,
+            Ok(raw) if looks_like_gsm7(&raw) => PayloadKind::Gsm7,
+            Ok(raw) if looks_like_ucs2(&raw, false) => PayloadKind::Ucs2,
+            _ => PayloadKind::AtText,
+        };
+    }
+    if looks_like_ucs2(body, true) {
+        PayloadKind::Ucs2
+    } else {
+        PayloadKind::Binary
+    }
+}
+
+/// Classifies `data` with [`classify`] and renders it accordingly.
+///
+/// Binary payloads are rendered as upper-case hex.
+pub fn render_auto(data: &[u8]) -> (PayloadKind, String) {
+    let kind = classify(data);
+    let text = match kind {
+        PayloadKind::AtText => String::from_utf8_lossy(data).to_string(),
+        PayloadKind::Binary => hex::encode_upper(data),
+        PayloadKind::Gsm7 => render_as(data, TextEncoding::Gsm7),
+        PayloadKind::Ucs2 => render_as(data, TextEncoding::Ucs2),
+    };
+    (kind, text)
+}
+
+/// Renders frame content as text, with the encoding selectable per DLCI.
+///
+/// AT channels usually carry GSM 7-bit and UCS-2 text as hex digits (for example after
+/// `AT+CSCS="UCS2"`). For these two encodings, content that is an even number of hex digits,
+/// optionally terminated by `\r\n`, is hex-decoded before being decoded as text.
+///
+/// # Example
+///
+/// ```
+/// use cmux::text::{PayloadView, TextEncoding};
+/// use cmux::types::FrameBuilder;
+///
+/// let frame = FrameBuilder::default()
+///     .with_content("004100420043".to_string())
+///     .build()
+///     .unwrap();
+/// let view = PayloadView::default().with_dlci(1, TextEncoding::Ucs2);
+/// assert_eq!(view.render(&frame), "ABC\r\n");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PayloadView {
+    default: TextEncoding,
+    dlci: HashMap<u8, TextEncoding>,
+}
+
+impl PayloadView {
+    /// Creates a view that decodes every DLCI with `encoding`.
+    pub fn new(encoding: TextEncoding) -> Self {
+        PayloadView {
+            default: encoding,
+            dlci: HashMap::new(),
+        }
+    }
+
+    /// Overrides the encoding used for frames on `dlci`.
+    pub fn with_dlci(mut self, dlci: u8, encoding: TextEncoding) -> Self {
+        self.dlci.insert(dlci, encoding);
+        self
+    }
+
+    /// Returns the encoding used for frames on `dlci`.
+    pub fn encoding(&self, dlci: u8) -> TextEncoding {
+        self.dlci.get(&dlci).copied().unwrap_or(self.default)
+    }
+
+    /// Renders the content of `frame` with the encoding of its DLCI.
+    pub fn render(&self, frame: &Frame) -> String {
+        let encoding = self.encoding(frame.address.dlci().into_bits());
+        render_as(frame.content.as_bytes(), encoding)
+    }
+
+    /// Renders the content of `frame` like [`PayloadView::render`].
+    ///
+    /// # Returns
+    ///
+    /// The kind found by [`classify`] if the DLCI uses [`TextEncoding::Auto`], `None` with
+    /// the other encodings, and the text.
+    pub fn render_kind(&self, frame: &Frame) -> (Option<PayloadKind>, String) {
+        match self.encoding(frame.address.dlci().into_bits()) {
+            TextEncoding::Auto => {
+                let (kind, text) = render_auto(frame.content.as_bytes());
+                (Some(kind), text)
+            }
+            _ => (None, self.render(frame)),
+        }
+    }
+}
+
+/// Renders `data` with `encoding`, hex-decoding AT-style hex text first for GSM 7-bit and UCS-2.
+fn render_as(data: &[u8], encoding: TextEncoding) -> String {
+    if matches!(encoding, TextEncoding::Utf8 | TextEncoding::Auto) {
+        return encoding.decode(data);
+    }
+    let (body, crlf) = match data.strip_suffix(b"\r\n") {
+        Some(body) => (body, "\r\n"),
+        None => (data, ""),
+    };
+    match hex::decode(body) {
+        Ok(raw) if !body.is_empty() => encoding.decode(&raw) + crlf,
+        _ => encoding.decode(data),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Address, FrameBuilder, DLCI};
+
+    #[test]
+    fn test_unpack_septets_padding() {
+        // "1234567" packed into 7 octets, with the spare septet padded by CR.
+        let data = hex::decode("31D98C56B3DD1A").unwrap();
+        assert_eq!(decode_gsm7_packed(&data), "1234567");
+    }
+
+    #[test]
+    fn test_decode_gsm7_extension() {
+        assert_eq!(decode_gsm7(&[0x1B, 0x65, 0x31, 0x1B, 0x28, 0x00]), "€1{@");
+        assert_eq!(decode_gsm7(&[0x1B, 0x01]), " ");
+    }
+
+    #[test]
+    fn test_decode_ucs2() {
+        assert_eq!(decode_ucs2(&[0x00, 0x41, 0x00, 0x42]), "AB");
+        assert_eq!(decode_ucs2(&[0xD8, 0x3D, 0xDE, 0x00]), "😀");
+        assert_eq!(decode_ucs2(&[0x00, 0x41, 0x00]), "A\u{FFFD}");
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(b""), PayloadKind::AtText);
+        assert_eq!(classify(b"OK\r\n"), PayloadKind::AtText);
+        assert_eq!(classify(b"12345678\r\n"), PayloadKind::AtText);
+        assert_eq!(classify(b"4F60597D\r\n"), PayloadKind::Ucs2);
+        assert_eq!(classify(b"0123ABCDEF\r\n"), PayloadKind::AtText);
+        assert_eq!(classify(&[0x00, 0x48, 0x00, 0x69]), PayloadKind::Ucs2);
+        assert_eq!(classify("héllo".as_bytes()), PayloadKind::Binary);
+    }
+
+    #[test]
+    fn test_render_auto() {
+        assert_eq!(
+            render_auto(b"00480069\r\n"),
+            (PayloadKind::Ucs2, "Hi\r\n".to_string())
+        );
+        assert_eq!(
+            render_auto(&[0x01, 0xFF]),
+            (PayloadKind::Binary, "01FF".to_string())
+        );
+        let view = PayloadView::new(TextEncoding::Auto);
+        let frame = FrameBuilder::default()
+            .with_content("E8329BFD4697D9EC37".to_string())
+            .build()
+            .unwrap();
+        assert_eq!(view.render(&frame), "hellohello\r\n");
+    }
+
+    #[test]
+    fn test_payload_view_per_dlci() {
+        let view = PayloadView::new(TextEncoding::Utf8).with_dlci(3, TextEncoding::Gsm7);
+        let frame = FrameBuilder::default()
+            .with_address(Address::default().with_dlci(DLCI::SMS(3)))
+            .with_content("E8329BFD4697D9EC37".to_string())
+            .build()
+            .unwrap();
+        assert_eq!(view.render(&frame), "hellohello\r\n");
+
+        let frame = FrameBuilder::default()
+            .with_content("E8329BFD4697D9EC37".to_string())
+            .build()
+            .unwrap();
+        assert_eq!(view.render(&frame), "E8329BFD4697D9EC37\r\n");
+    }
+}
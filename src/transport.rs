@@ -0,0 +1,655 @@
+use crate::advanced::{EscapeSet, ADVANCED_FLAG};
+use crate::anomaly::AnomalyDetector;
+use crate::at::is_reset_banner;
+use crate::error::MuxError;
+use crate::mux::{DlcState, Mux, MuxEvent, PowerState};
+use crate::parser::{FrameParser, ParserLimits};
+use crate::sniff::{Direction, SniffedFrame};
+use crate::types::{Frame, FLAG};
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, ErrorKind, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Byte pipe carrying the multiplexed frames
+///
+/// Reads are blocking but may give up after a backend-specific timeout, in which case they
+/// return `Ok(0)`, so the caller gets a chance to run the [`Mux`] timers.
+pub trait Transport {
+    /// Reads the bytes available into `buf`.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(usize)`: The number of bytes read, 0 if none arrived before the read timeout.
+    /// - `Err(io::Error)`: If the pipe failed or was closed.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Writes all of `data`.
+    fn write(&mut self, data: &[u8]) -> io::Result<()>;
+}
+
+/// Number of flags written to wake the peer up, or to answer its wake-up flags.
+const WAKE_UP_FLAGS: usize = 8;
+
+/// [`Transport`] over any `std::io::Read + Write`, such as a serial port or a TCP socket
+///
+/// `TimedOut`, `WouldBlock` and `Interrupted` errors are reported as reads of 0 bytes.
+#[derive(Debug)]
+pub struct IoTransport<T> {
+    inner: T,
+}
+
+impl<T: Read + Write> IoTransport<T> {
+    /// Wraps `inner`.
+    pub fn new(inner: T) -> Self {
+        IoTransport { inner }
+    }
+
+    /// Returns the wrapped pipe.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwraps the pipe.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: Read + Write> Transport for IoTransport<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.inner.read(buf) {
+            Ok(0) if !buf.is_empty() => Err(ErrorKind::UnexpectedEof.into()),
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    ErrorKind::TimedOut | ErrorKind::WouldBlock | ErrorKind::Interrupted
+                ) =>
+            {
+                Ok(0)
+            }
+            result => result,
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.inner.write_all(data)?;
+        self.inner.flush()
+    }
+}
+
+/// In-memory [`Transport`] for tests
+///
+/// [`MemoryTransport::pair`] creates two connected ends: the bytes written to one are read
+/// from the other. Reads never block.
+#[derive(Debug, Clone)]
+pub struct MemoryTransport {
+    rx: Arc<Mutex<VecDeque<u8>>>,
+    tx: Arc<Mutex<VecDeque<u8>>>,
+}
+
+impl MemoryTransport {
+    /// Creates two connected ends.
+    pub fn pair() -> (Self, Self) {
+        let a = Arc::new(Mutex::new(VecDeque::new()));
+        let b = Arc::new(Mutex::new(VecDeque::new()));
+        (
+            MemoryTransport {
+                rx: a.clone(),
+                tx: b.clone(),
+            },
+            MemoryTransport { rx: b, tx: a },
+        )
+    }
+
+    /// Returns the number of bytes waiting to be read.
+    pub fn available(&self) -> usize {
+        self.rx.lock().map_or(0, |rx| rx.len())
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut rx = self
+            .rx
+            .lock()
+            .map_err(|_| io::Error::from(ErrorKind::BrokenPipe))?;
+        let count = buf.len().min(rx.len());
+        for (dst, src) in buf.iter_mut().zip(rx.drain(..count)) {
+            *dst = src;
+        }
+        Ok(count)
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.tx
+            .lock()
+            .map_err(|_| io::Error::from(ErrorKind::BrokenPipe))?
+            .extend(data);
+        Ok(())
+    }
+}
+
+/// [`Mux`] driven over a [`Transport`]
+///
+/// Each call to [`Link::poll`] reads once from the transport, feeds the bytes to the frame
+/// parser and the frames to the engine, runs the engine timers, then writes the pending frames.
+///
+/// When a read holds no flag and one of the [`RESET_BANNERS`](crate::at::RESET_BANNERS) on a
+/// line of its own, the modem was reset: [`Link::poll_event`] returns
+/// [`MuxError::ModemReset`](crate::error::MuxError::ModemReset). The engine still holds the
+/// DLCs of the lost session, so the application switches the modem to CMUX mode again and
+/// drives a new [`Mux`].
+///
+/// The bytes and frames read also go through an [`AnomalyDetector`], whose findings are
+/// returned by [`Link::poll_event`] as [`MuxEvent::Anomaly`].
+///
+/// In power saving mode, flags read wake the engine up and are answered with flags, and
+/// flags are written at every poll while the engine wakes the peer up, as set by
+/// [`Mux::wake_up`].
+///
+/// With [`Link::with_trace`], the frames read and written are also kept for
+/// [`Link::poll_frame`], to log or publish them.
+///
+/// # Example
+///
+/// ```
+/// use cmux::mux::{DlcState, Mux};
+/// use cmux::transport::{Link, MemoryTransport};
+/// use std::time::Instant;
+///
+/// let (a, b) = MemoryTransport::pair();
+/// let mut host = Link::new(Mux::new(), a);
+/// let mut modem = Link::new(Mux::new().with_initiator(false), b);
+///
+/// let now = Instant::now();
+/// host.mux_mut().open(0, now).unwrap();
+/// host.flush().unwrap();
+/// modem.poll(now).unwrap();
+/// host.poll(now).unwrap();
+/// assert_eq!(host.mux().state(0), DlcState::Connected);
+/// ```
+#[derive(Debug)]
+pub struct Link<T> {
+    mux: Mux,
+    parser: FrameParser,
+    transport: T,
+    buffer: Vec<u8>,
+    /// The read timeouts of [`Link::read`], by DLCI.
+    read_timeouts: HashMap<u8, Duration>,
+    /// The events taken from the engine by [`Link::read`] and not returned yet.
+    events: VecDeque<MuxEvent>,
+    detector: AnomalyDetector,
+    /// When the trace started, if the frames are traced.
+    trace: Option<Instant>,
+    /// The frames traced and not returned yet.
+    frames: VecDeque<SniffedFrame>,
+}
+
+impl<T: Transport> Link<T> {
+    /// Size of the read buffer.
+    const BUFFER_SIZE: usize = 4096;
+
+    /// Drives `mux` over `transport` with basic option framing.
+    pub fn new(mux: Mux, transport: T) -> Self {
+        Link {
+            mux,
+            parser: FrameParser::new().with_limits(ParserLimits::STREAM),
+            transport,
+            buffer: vec![0; Self::BUFFER_SIZE],
+            read_timeouts: HashMap::new(),
+            events: VecDeque::new(),
+            detector: AnomalyDetector::new(),
+            trace: None,
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Switches to advanced option framing.
+    pub fn advanced(mut self) -> Self {
+        self.parser = FrameParser::advanced().with_limits(ParserLimits::STREAM);
+        self.detector = AnomalyDetector::advanced();
+        self
+    }
+
+    /// Sets the octets escaped in advanced option frames, in both directions.
+    ///
+    /// With XON/XOFF flow control, as negotiated with RPN, use
+    /// `EscapeSet::from(&settings)` so that the flow control characters are escaped.
+    pub fn with_escape_set(mut self, escapes: EscapeSet) -> Self {
+        self.parser = self.parser.with_escape_set(escapes);
+        self
+    }
+
+    /// Keeps the frames read, labelled RX, and written, labelled TX, for
+    /// [`Link::poll_frame`], with their time since `start`.
+    pub fn with_trace(mut self, start: Instant) -> Self {
+        self.trace = Some(start);
+        self
+    }
+
+    /// Returns the engine.
+    pub fn mux(&self) -> &Mux {
+        &self.mux
+    }
+
+    /// Returns the engine.
+    pub fn mux_mut(&mut self) -> &mut Mux {
+        &mut self.mux
+    }
+
+    /// Returns the transport.
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Writes every frame queued by the engine.
+    ///
+    /// A write the transport reports as `WouldBlock`, `TimedOut` or `Interrupted` is tried
+    /// again as set by the [`WritePolicy`](crate::mux::WritePolicy) of the engine.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(())`: If every frame was written.
+    /// - `Err(io::Error)`: If the transport failed, or stayed blocked past the retries or the
+    ///   deadline of the policy, with [`ErrorKind::TimedOut`] and
+    ///   [`MuxError::WriteTimeout`] as inner error.
+    pub fn flush(&mut self) -> io::Result<()> {
+        while let Some(frame) = self.mux.poll_transmit() {
+            self.send_frame(&frame)?;
+        }
+        Ok(())
+    }
+
+    /// Writes `frame` as it is, bypassing the engine, for example to inject a frame while
+    /// debugging a modem. The write is retried as in [`Link::flush`].
+    pub fn send_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        let data = if self.parser.is_advanced() {
+            frame.to_bytes_advanced_with(&self.parser.escape_set())
+        } else {
+            frame.to_bytes()
+        };
+        self.write(frame.address.dlci().into_bits(), &data)?;
+        self.traced(Direction::Tx, frame, Instant::now());
+        Ok(())
+    }
+
+    /// Keeps `frame` for [`Link::poll_frame`] if the frames are traced.
+    fn traced(&mut self, direction: Direction, frame: &Frame, now: Instant) {
+        if let Some(start) = self.trace {
+            self.frames.push_back(SniffedFrame {
+                timestamp: now.saturating_duration_since(start),
+                direction,
+                frame: frame.clone(),
+            });
+        }
+    }
+
+    /// Writes the frame `data` of `dlci`, or the wake-up flags with DLCI 0, retrying as set by
+    /// the write policy of the engine.
+    fn write(&mut self, dlci: u8, data: &[u8]) -> io::Result<()> {
+        let policy = self.mux.config().write;
+        let start = Instant::now();
+        let mut retries = 0;
+        loop {
+            match self.transport.write(data) {
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        ErrorKind::TimedOut | ErrorKind::WouldBlock | ErrorKind::Interrupted
+                    ) =>
+                {
+                    if !policy.may_retry(retries, start, Instant::now()) {
+                        let error = MuxError::WriteTimeout { dlci };
+                        return Err(io::Error::new(ErrorKind::TimedOut, error));
+                    }
+                    retries += 1;
+                    std::thread::sleep(policy.retry_interval);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Reads once from the transport and runs the engine.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(usize)`: The number of bytes read.
+    /// - `Err(io::Error)`: If the transport failed.
+    pub fn poll(&mut self, now: Instant) -> io::Result<usize> {
+        let count = self.transport.read(&mut self.buffer)?;
+        let data = &self.buffer[..count];
+        let flag = if self.parser.is_advanced() {
+            ADVANCED_FLAG
+        } else {
+            FLAG
+        };
+        let power = self.mux.power_state();
+        // Set when the peer woke the sleeping link up, to answer with flags.
+        let mut woken = false;
+        if matches!(power, PowerState::Sleeping | PowerState::WakingUp) && data.contains(&flag) {
+            self.mux.wake_up_received();
+            woken = power == PowerState::Sleeping;
+        }
+        // A modem back in command mode sends text without flags.
+        let reset = !data.contains(&flag) && is_reset_banner(data);
+        let mut anomalies = self.detector.feed(data);
+        let (fcs_errors, skipped) = (self.parser.fcs_errors(), self.parser.skipped());
+        for frame in self.parser.feed(data) {
+            anomalies.extend(self.detector.record(&frame));
+            self.mux.receive(&frame, now);
+            self.traced(Direction::Rx, &frame, now);
+        }
+        for _ in fcs_errors..self.parser.fcs_errors() {
+            anomalies.extend(self.detector.record_fcs_error());
+        }
+        if self.parser.skipped() > skipped {
+            self.events
+                .push_back(MuxEvent::Skipped(self.parser.skipped() - skipped));
+        }
+        if reset {
+            self.events.push_back(MuxEvent::Error(MuxError::ModemReset));
+        }
+        self.events
+            .extend(anomalies.into_iter().map(MuxEvent::Anomaly));
+        if woken {
+            self.write(0, &[flag; WAKE_UP_FLAGS])?;
+        }
+        self.mux.poll_timeout(now);
+        if self.mux.power_state() == PowerState::WakingUp {
+            self.write(0, &[flag; WAKE_UP_FLAGS])?;
+        }
+        self.flush()?;
+        Ok(count)
+    }
+
+    /// Opens `dlci` and polls the transport until the peer answers or N2 retransmissions expire.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(true)`: If the DLC is connected.
+    /// - `Ok(false)`: If the peer rejected it or did not answer.
+    /// - `Err(io::Error)`: If the transport failed.
+    pub fn connect(&mut self, dlci: u8) -> io::Result<bool> {
+        self.mux
+            .open(dlci, Instant::now())
+            .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+        self.flush()?;
+        while self.mux.state(dlci) == DlcState::Connecting {
+            self.poll(Instant::now())?;
+        }
+        Ok(self.mux.state(dlci) == DlcState::Connected)
+    }
+
+    /// Sets how long [`Link::read`] waits for data on `dlci`, or `None` to wait until the DLC
+    /// is closed, the default.
+    pub fn set_read_timeout(&mut self, dlci: u8, timeout: Option<Duration>) {
+        match timeout {
+            Some(timeout) => self.read_timeouts.insert(dlci & 0x3F, timeout),
+            None => self.read_timeouts.remove(&(dlci & 0x3F)),
+        };
+    }
+
+    /// Returns the read timeout of `dlci`.
+    pub fn read_timeout(&self, dlci: u8) -> Option<Duration> {
+        self.read_timeouts.get(&(dlci & 0x3F)).copied()
+    }
+
+    /// Polls the transport until data arrives on `dlci`.
+    ///
+    /// The other events raised meanwhile are kept, to be taken with [`Link::poll_event`].
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Vec<u8>)`: The data received, or no data if the DLC is closed.
+    /// - `Err(io::Error)`: If no data arrived within the read timeout of the DLC, with
+    ///   [`ErrorKind::TimedOut`], or the transport failed.
+    pub fn read(&mut self, dlci: u8) -> io::Result<Vec<u8>> {
+        let dlci = dlci & 0x3F;
+        let deadline = self
+            .read_timeout(dlci)
+            .map(|timeout| Instant::now() + timeout);
+        loop {
+            while let Some(event) = self.mux.poll_event() {
+                match event {
+                    MuxEvent::Data { dlci: from, data } if from == dlci => return Ok(data),
+                    event => self.events.push_back(event),
+                }
+            }
+            if self.mux.state(dlci) == DlcState::Closed {
+                return Ok(Vec::new());
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(io::Error::new(
+                    ErrorKind::TimedOut,
+                    format!("no data on DLCI {}", dlci),
+                ));
+            }
+            self.poll(Instant::now())?;
+        }
+    }
+
+    /// Takes the next event, the ones kept by [`Link::read`] first.
+    ///
+    /// Programs calling [`Link::read`] take the events here rather than from the engine.
+    pub fn poll_event(&mut self) -> Option<MuxEvent> {
+        self.events.pop_front().or_else(|| self.mux.poll_event())
+    }
+
+    /// Takes the next frame traced since [`Link::with_trace`].
+    pub fn poll_frame(&mut self) -> Option<SniffedFrame> {
+        self.frames.pop_front()
+    }
+
+    /// Unwraps the engine and the transport.
+    pub fn into_parts(self) -> (Mux, T) {
+        (self.mux, self.transport)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anomaly::Anomaly;
+    use crate::mux::{MuxConfig, PowerState, WritePolicy};
+
+    #[test]
+    fn test_link_over_memory() {
+        let now = Instant::now();
+        let (a, b) = MemoryTransport::pair();
+        let escapes = EscapeSet::new().with_xon_xoff(0x11, 0x13);
+        let mut host = Link::new(Mux::new(), a).advanced().with_escape_set(escapes);
+        let mut modem = Link::new(Mux::new().with_initiator(false), b)
+            .advanced()
+            .with_escape_set(escapes);
+
+        host.mux_mut().open(0, now).unwrap();
+        host.mux_mut().open(1, now).unwrap();
+        host.flush().unwrap();
+        modem.poll(now).unwrap();
+        host.poll(now).unwrap();
+        assert_eq!(host.mux().state(1), DlcState::Connected);
+
+        host.mux_mut().send(1, &[0x7E, 0x7D, 0x11, 0x13]).unwrap();
+        let frame = host.mux_mut().poll_transmit().unwrap();
+        let mut data = frame.to_bytes_advanced_with(&escapes);
+        // An XOFF inserted by the UART is not data.
+        data.insert(3, 0x13);
+        host.transport_mut().write(&data).unwrap();
+        assert!(modem.poll(now).unwrap() > 0);
+        let (mut mux, transport) = modem.into_parts();
+        assert_eq!(transport.available(), 0);
+        let events: Vec<_> = std::iter::from_fn(|| mux.poll_event()).collect();
+        assert_eq!(
+            events.last(),
+            Some(&MuxEvent::Data {
+                dlci: 1,
+                data: vec![0x7E, 0x7D, 0x11, 0x13]
+            })
+        );
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        let now = Instant::now();
+        let (a, b) = MemoryTransport::pair();
+        let mut host = Link::new(Mux::new(), a);
+        let mut modem = Link::new(Mux::new().with_initiator(false), b);
+        host.mux_mut().open(0, now).unwrap();
+        host.mux_mut().open(1, now).unwrap();
+        host.flush().unwrap();
+        modem.poll(now).unwrap();
+        host.poll(now).unwrap();
+
+        host.set_read_timeout(1, Some(Duration::from_millis(10)));
+        let e = host.read(1).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::TimedOut);
+        assert_eq!(host.poll_event(), Some(MuxEvent::DlcOpened(0)));
+
+        modem.mux_mut().send(1, b"OK").unwrap();
+        modem.flush().unwrap();
+        assert_eq!(host.read(1).unwrap(), b"OK");
+        modem.mux_mut().close(1, now).unwrap();
+        modem.flush().unwrap();
+        assert_eq!(host.read(1).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_modem_reset() {
+        let (a, mut b) = MemoryTransport::pair();
+        let mut host = Link::new(Mux::new(), a);
+        b.write(b"\r\nRDY\r\n").unwrap();
+        host.poll(Instant::now()).unwrap();
+        assert_eq!(
+            host.poll_event(),
+            Some(MuxEvent::Error(MuxError::ModemReset))
+        );
+        assert_eq!(
+            host.poll_event(),
+            Some(MuxEvent::Anomaly(Anomaly::ResetBanner { banner: "RDY" }))
+        );
+    }
+
+    #[test]
+    fn test_skipped() {
+        let (a, mut b) = MemoryTransport::pair();
+        let mut host = Link::new(Mux::new(), a);
+        // Noise, then a SABM whose FCS is wrong.
+        b.write(b"AT\r\xF9\x03\x3F\x01\x00\xF9").unwrap();
+        host.poll(Instant::now()).unwrap();
+        assert_eq!(host.poll_event(), Some(MuxEvent::Skipped(7)));
+        assert_eq!(host.poll_event(), None);
+    }
+
+    /// Transport reporting `WouldBlock` for the first writes.
+    struct Blocked(u32, MemoryTransport);
+
+    impl Transport for Blocked {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.1.read(buf)
+        }
+
+        fn write(&mut self, data: &[u8]) -> io::Result<()> {
+            if self.0 > 0 {
+                self.0 -= 1;
+                return Err(ErrorKind::WouldBlock.into());
+            }
+            self.1.write(data)
+        }
+    }
+
+    #[test]
+    fn test_write_policy() {
+        let now = Instant::now();
+        let policy = WritePolicy::default()
+            .with_retries(2)
+            .with_retry_interval(Duration::from_millis(1));
+        let mux = Mux::new().with_config(MuxConfig::default().with_write_policy(policy));
+        let (a, b) = MemoryTransport::pair();
+        let mut host = Link::new(mux, Blocked(2, a));
+        host.mux_mut().open(0, now).unwrap();
+        host.flush().unwrap();
+        assert_eq!(b.available(), 6);
+
+        host.transport_mut().0 = 3;
+        host.mux_mut().open(1, now).unwrap();
+        let e = host.flush().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::TimedOut);
+        let error = e.get_ref().and_then(|e| e.downcast_ref::<MuxError>());
+        assert_eq!(error, Some(&MuxError::WriteTimeout { dlci: 1 }));
+    }
+
+    #[test]
+    fn test_io_transport() {
+        let mut transport = IoTransport::new(io::Cursor::new(Vec::new()));
+        transport.write(&[0xF9]).unwrap();
+        assert_eq!(transport.get_mut().get_ref(), &[0xF9]);
+        // The cursor is at its end, which is reported as a closed pipe.
+        let mut buf = [0; 4];
+        assert_eq!(
+            transport.read(&mut buf).unwrap_err().kind(),
+            ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn test_trace() {
+        let start = Instant::now();
+        let (a, b) = MemoryTransport::pair();
+        let mut host = Link::new(Mux::new(), a).with_trace(start);
+        let mut modem = Link::new(Mux::new().with_initiator(false), b);
+        host.mux_mut().open(0, start).unwrap();
+        host.flush().unwrap();
+        modem.poll(start).unwrap();
+        host.poll(start).unwrap();
+        let traced: Vec<_> = std::iter::from_fn(|| host.poll_frame())
+            .map(|sniffed| (sniffed.direction, sniffed.frame.control.frame_type()))
+            .collect();
+        assert_eq!(
+            traced,
+            [
+                (Direction::Tx, crate::types::FrameType::SABM),
+                (Direction::Rx, crate::types::FrameType::UA)
+            ]
+        );
+
+        // Frames sent as they are bypass the engine.
+        let disc = Frame::from_bytes(hex::decode("F9035301FDF9").unwrap()).unwrap();
+        host.send_frame(&disc).unwrap();
+        assert_eq!(host.poll_frame().unwrap().frame, disc);
+        assert_eq!(host.mux().state(0), DlcState::Connected);
+        assert_eq!(modem.transport_mut().available(), 6);
+    }
+
+    #[test]
+    fn test_wake_up() {
+        let now = Instant::now();
+        let policy = WritePolicy::default()
+            .with_retries(1)
+            .with_retry_interval(Duration::from_millis(1));
+        let mux = Mux::new().with_config(MuxConfig::default().with_write_policy(policy));
+        let (a, b) = MemoryTransport::pair();
+        let mut host = Link::new(mux, Blocked(0, a));
+        let mut modem = Link::new(Mux::new().with_initiator(false), b);
+        host.mux_mut().open(0, now).unwrap();
+        host.flush().unwrap();
+        modem.poll(now).unwrap();
+        host.poll(now).unwrap();
+        host.mux_mut().sleep(now).unwrap();
+        host.flush().unwrap();
+        modem.poll(now).unwrap();
+        host.poll(now).unwrap();
+        assert_eq!(modem.mux().power_state(), PowerState::Sleeping);
+        assert_eq!(host.mux().power_state(), PowerState::Sleeping);
+
+        // The host sends flags, retried like frames, which the modem answers with flags.
+        host.mux_mut().wake_up(now);
+        host.transport_mut().0 = 1;
+        host.poll(now).unwrap();
+        modem.poll(now).unwrap();
+        assert_eq!(modem.mux().power_state(), PowerState::Active);
+        host.poll(now).unwrap();
+        assert_eq!(host.mux().power_state(), PowerState::Active);
+    }
+}
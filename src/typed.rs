@@ -0,0 +1,162 @@
+use crate::error::FrameError;
+use crate::types::{self, Address, Control, Frame};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+/// State of a [`FrameBuilder`] whose content has not been set.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoContent;
+
+/// State of a [`FrameBuilder`] whose content has been set.
+#[derive(Debug, Clone, Copy)]
+pub struct WithContent;
+
+/// Typestate Frame Builder for GSM 07.10 [`Frame`]
+///
+/// Unlike [`types::FrameBuilder`], the content is set at most once: the methods setting it
+/// only exist until it is set, so setting it twice is a compile error instead of replacing
+/// the first content. Without content, the frame has an empty information field, as SABM,
+/// UA, DM and DISC frames do. `build()` can still fail with [`FrameError::ContentTooLong`].
+///
+/// # Example
+///
+/// ```
+/// use cmux::typed::FrameBuilder;
+/// use cmux::types::{Address, Control, FrameType};
+///
+/// let p = FrameBuilder::new()
+///     .with_address(Address::default())
+///     .with_content("AT+CMUX?".to_string())
+///     .build()
+///     .unwrap();
+/// assert_eq!(p.to_hex_string(), "f907ef1541542b434d55583f0d0a2cf9");
+///
+/// let sabm = FrameBuilder::new()
+///     .with_control(Control::default().with_frame_type(FrameType::SABM).with_pf(true))
+///     .build()
+///     .unwrap();
+/// assert!(sabm.content.is_empty());
+/// ```
+///
+/// ```compile_fail
+/// use cmux::typed::FrameBuilder;
+///
+/// let p = FrameBuilder::new()
+///     .with_content("AT".to_string())
+///     .with_raw_content("ATZ\r".to_string())
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameBuilder<S> {
+    inner: types::FrameBuilder,
+    state: PhantomData<S>,
+}
+
+impl FrameBuilder<NoContent> {
+    /// Creates a builder with the default address and control fields.
+    pub fn new() -> Self {
+        FrameBuilder {
+            inner: types::FrameBuilder::default(),
+            state: PhantomData,
+        }
+    }
+
+    /// Sets the content of the frame.
+    ///
+    /// Like [`types::FrameBuilder::with_content`], `\r\n` is appended if it is not present.
+    pub fn with_content(mut self, content: String) -> FrameBuilder<WithContent> {
+        self.inner.with_content(content);
+        self.content_set()
+    }
+
+    /// Sets text content of the frame as is, like [`types::FrameBuilder::with_raw_content`].
+    pub fn with_raw_content(mut self, content: String) -> FrameBuilder<WithContent> {
+        self.inner.with_raw_content(content);
+        self.content_set()
+    }
+
+    /// Sets binary content of the frame, like [`types::FrameBuilder::with_binary_content`].
+    pub fn with_binary_content(mut self, content: Vec<u8>) -> FrameBuilder<WithContent> {
+        self.inner.with_binary_content(content);
+        self.content_set()
+    }
+
+    fn content_set(self) -> FrameBuilder<WithContent> {
+        FrameBuilder {
+            inner: self.inner,
+            state: PhantomData,
+        }
+    }
+}
+
+impl Default for FrameBuilder<NoContent> {
+    fn default() -> Self {
+        FrameBuilder::new()
+    }
+}
+
+impl<S> FrameBuilder<S> {
+    /// Sets the address of the frame.
+    pub fn with_address(mut self, address: Address) -> Self {
+        self.inner.with_address(address);
+        self
+    }
+
+    /// Sets the control of the frame.
+    pub fn with_control(mut self, control: Control) -> Self {
+        self.inner.with_control(control);
+        self
+    }
+
+    /// Builds the frame.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Frame)`: The built frame.
+    /// - `Err(FrameError)`: If the content is too long.
+    pub fn build(&self) -> Result<Frame, FrameError> {
+        self.inner.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FrameType;
+
+    #[test]
+    fn test_typed_matches_runtime_builder() {
+        let control = Control::default().with_frame_type(FrameType::UI);
+        let typed = FrameBuilder::new()
+            .with_content("AT".to_string())
+            .with_control(control)
+            .build()
+            .unwrap();
+        let runtime = types::FrameBuilder::default()
+            .with_control(control)
+            .with_content("AT".to_string())
+            .build()
+            .unwrap();
+        assert_eq!(typed, runtime);
+
+        let typed = FrameBuilder::new()
+            .with_raw_content("AT\r".to_string())
+            .build()
+            .unwrap();
+        assert_eq!(typed.content.as_bytes(), b"AT\r");
+        let typed = FrameBuilder::new()
+            .with_binary_content(vec![0xF9, 0x00])
+            .build()
+            .unwrap();
+        assert_eq!(typed.content.as_bytes(), [0xF9, 0x00]);
+
+        let control = Control::default().with_frame_type(FrameType::DISC);
+        let typed = FrameBuilder::new().with_control(control).build().unwrap();
+        let runtime = types::FrameBuilder::default()
+            .with_control(control)
+            .build()
+            .unwrap();
+        assert_eq!(typed, runtime);
+    }
+}
@@ -1,26 +1,134 @@
+use crate::error::{BufferTooSmall, FrameError};
+use crate::parser::FrameIter;
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
 use bitfield_struct::bitfield;
+use core::fmt::{Debug, Display, Formatter};
 use crc::Crc;
 use hex::ToHex;
-use std::error::Error;
-use std::fmt::Debug;
 
 /// Maximum length of a single octet.
 const MAX_SINGLE_BIT_LENGTH: u16 = 127;
 
-#[derive(PartialEq, Eq, Clone)]
-pub struct ContentStr(String);
+/// Maximum length of content described by a two-octet length indicator.
+pub(crate) const MAX_CONTENT_LENGTH: usize = 0x7FFF;
 
-impl Debug for ContentStr {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("ContentStr")
-            .field("str", &self.0)
-            .field("raw", &format_args!("{:02X?}", self.0.as_bytes()))
+/// Flag octet opening and closing every basic option [`Frame`].
+pub const FLAG: u8 = 0xF9;
+
+/// Minimum number of octets of a frame: flag, address, control, length, FCS and flag.
+pub const MIN_FRAME_LENGTH: usize = 6;
+
+/// Octets before the length indicator: flag, address and control.
+const HEADER_OCTETS: usize = 3;
+
+/// Information field of [`Frame`]
+///
+/// The content is stored as raw octets, so binary payloads such as PPP frames or SMS PDUs
+/// survive a round trip through [`Frame::to_bytes`] and [`Frame::from_bytes`] unchanged.
+///
+/// # Example
+///
+/// ```
+/// use cmux::types::Content;
+///
+/// let content = Content::from(vec![0x7E, 0xFF, 0x03]);
+/// assert_eq!(content.as_bytes(), &[0x7E, 0xFF, 0x03]);
+/// assert_eq!(content.as_str(), None);
+///
+/// let content = Content::from("AT\r\n");
+/// assert_eq!(content, "AT\r\n");
+/// ```
+#[derive(PartialEq, Eq, Clone, Default)]
+pub struct Content(Vec<u8>);
+
+/// Former name of [`Content`], kept for compatibility.
+#[deprecated(note = "use `Content` instead")]
+pub type ContentStr = Content;
+
+impl Content {
+    /// Returns the raw octets of the content.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns the content as a string slice if it is valid UTF-8.
+    pub fn as_str(&self) -> Option<&str> {
+        core::str::from_utf8(&self.0).ok()
+    }
+
+    /// Returns the content as text, replacing invalid UTF-8 sequences with U+FFFD.
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.0)
+    }
+
+    /// Returns the number of octets in the content.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if the content has no octets.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<u8>> for Content {
+    fn from(value: Vec<u8>) -> Self {
+        Content(value)
+    }
+}
+
+impl From<&[u8]> for Content {
+    fn from(value: &[u8]) -> Self {
+        Content(value.to_vec())
+    }
+}
+
+impl From<String> for Content {
+    fn from(value: String) -> Self {
+        Content(value.into_bytes())
+    }
+}
+
+impl From<&str> for Content {
+    fn from(value: &str) -> Self {
+        Content(value.as_bytes().to_vec())
+    }
+}
+
+impl From<Content> for Vec<u8> {
+    fn from(value: Content) -> Self {
+        value.0
+    }
+}
+
+impl Debug for Content {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Content")
+            .field("str", &self.to_string_lossy())
+            .field("raw", &format_args!("{:02X?}", self.0))
             .finish()
     }
 }
 
-impl PartialEq<&str> for ContentStr {
+#[cfg(feature = "defmt")]
+impl defmt::Format for Content {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=[u8]:a}", self.as_bytes())
+    }
+}
+
+impl PartialEq<&str> for Content {
     fn eq(&self, other: &&str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<&[u8]> for Content {
+    fn eq(&self, other: &&[u8]) -> bool {
         self.0 == *other
     }
 }
@@ -44,33 +152,210 @@ pub const fn bit_set_to(value: u8, bit: u8, set: bool) -> u8 {
     }
 }
 
+/// Byte order of two-octet [`Length`] indicators
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LengthEncoding {
+    /// L1-L7 with EA=0 in the first octet, L8-L15 in the second, as 07.10 specifies.
+    #[default]
+    Spec,
+    /// The two octets swapped, as written by cmux 0.2 and earlier for content longer than
+    /// 63 octets. Only useful to read old output.
+    ///
+    /// As in cmux 0.2, an odd first octet is read as a single-octet indicator, so legacy
+    /// frames of 128 to 255 octets of content, 384 to 511 octets and so on are not decoded.
+    Legacy,
+}
+
+/// Length Indicator of [`Frame`]
+///
+/// <table>
+///   <tr>
+///     <th>Bit No.</th>
+///     <td>1</td>
+///     <td>2</td>
+///     <td>...</td>
+///     <td>8</td>
+///     <td>9</td>
+///     <td>...</td>
+///     <td>16</td>
+///   </tr>
+///   <tr>
+///     <th>Data</th>
+///     <td>EA</td>
+///     <td>L1</td>
+///     <td>...</td>
+///     <td>L7</td>
+///     <td>L8</td>
+///     <td>...</td>
+///     <td>L15</td>
+///   </tr>
+/// </table>
+///
+/// Up to 127 octets of content, the indicator is a single octet with EA=1. Longer content
+/// clears EA and continues the length in a second octet. The indicator value, as stored in
+/// [`Frame::length`], holds the first octet in its low byte.
+///
+/// # Example
+///
+/// ```
+/// use cmux::types::{Length, LengthEncoding};
+///
+/// let length = Length::new(300).unwrap();
+/// assert_eq!(length.to_bytes(), [0x58, 0x02]);
+/// assert_eq!(Length::decode(&[0x58, 0x02], LengthEncoding::Spec), Some((length, 2)));
+/// assert_eq!(Length::new(2).unwrap().to_bytes(), [0x05]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Length(u16);
+
+impl Length {
+    /// Creates the indicator of `len` octets of content.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Length)`: The length indicator.
+    /// - `Err(FrameError)`: If `len` does not fit in 15 bits.
+    pub const fn new(len: usize) -> Result<Self, FrameError> {
+        if len > MAX_CONTENT_LENGTH {
+            return Err(FrameError::ContentTooLong { len });
+        }
+        let len = len as u16;
+        if len > MAX_SINGLE_BIT_LENGTH {
+            Ok(Length(len << 1))
+        } else {
+            Ok(Length((len << 1) | 0x1))
+        }
+    }
+
+    /// Creates a length indicator from its value, the first octet in the low byte.
+    pub const fn from_indicator(value: u16) -> Self {
+        Length(value)
+    }
+
+    /// Returns the indicator value, the first octet in the low byte.
+    pub const fn indicator(self) -> u16 {
+        self.0
+    }
+
+    /// Returns the number of content octets.
+    pub const fn len(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    /// Returns true if there is no content.
+    pub const fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of octets of the indicator, 1 if EA is set, 2 otherwise.
+    pub const fn octets(self) -> usize {
+        if self.0 & 0x1 == 1 {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Encodes the indicator as 07.10 specifies.
+    pub fn to_bytes(self) -> Vec<u8> {
+        let (octets, count) = self.encode(LengthEncoding::Spec);
+        octets[..count].to_vec()
+    }
+
+    /// Encodes the indicator, returning the octets and how many of them are used.
+    pub(crate) fn encode(self, encoding: LengthEncoding) -> ([u8; 2], usize) {
+        let [low, high] = self.0.to_le_bytes();
+        match (self.octets(), encoding) {
+            (1, _) => ([low, 0], 1),
+            (_, LengthEncoding::Spec) => ([low, high], 2),
+            (_, LengthEncoding::Legacy) => ([high, low], 2),
+        }
+    }
+
+    /// Decodes the length indicator at the start of `data`.
+    ///
+    /// # Returns
+    ///
+    /// - `Some((Length, usize))`: The length indicator and the number of octets it occupies.
+    /// - `None`: If `data` is too short to hold the length indicator.
+    pub fn decode(data: &[u8], encoding: LengthEncoding) -> Option<(Length, usize)> {
+        let first = *data.first()?;
+        if first & 0x1 == 1 {
+            return Some((Length(first as u16), 1));
+        }
+        let second = *data.get(1)?;
+        let value = match encoding {
+            LengthEncoding::Spec => u16::from_le_bytes([first, second]),
+            LengthEncoding::Legacy => u16::from_be_bytes([first, second]),
+        };
+        Some((Length(value), 2))
+    }
+}
+
 /// Generates a checksum for [`Frame`] by the address, control, and length fields.
-pub fn checksum_uih(addr: u8, control: u8, length: u16) -> Result<u8, Box<dyn Error>> {
-    let crc = Crc::<u8>::new(&crc::CRC_8_ROHC);
-    let mut data: Vec<u8> = vec![addr, control];
-    if length > MAX_SINGLE_BIT_LENGTH {
-        let len = length.to_be_bytes();
-        data.extend_from_slice(&len);
-    } else {
-        data.push(length as u8);
-    };
-    let crc_value = crc.checksum(&data);
-    Ok(!crc_value)
+pub fn checksum_uih(addr: u8, control: u8, length: u16) -> Result<u8, FrameError> {
+    Ok(fcs(
+        addr,
+        control,
+        Length::from_indicator(length),
+        LengthEncoding::Spec,
+        &[],
+    ))
 }
 
 /// Generates a checksum for [`Frame`] by the address, control, length, and content fields.
-pub fn checksum_ui(addr: u8, control: u8, length: u8, content: &str) -> Result<u8, Box<dyn Error>> {
+pub fn checksum_ui(addr: u8, control: u8, length: u8, content: &[u8]) -> Result<u8, FrameError> {
     let crc = Crc::<u8>::new(&crc::CRC_8_ROHC);
     let mut data: Vec<u8> = vec![addr, control, length];
-    data.extend_from_slice(content.as_bytes());
+    data.extend_from_slice(content);
     let crc_value = crc.checksum(&data);
     Ok(!crc_value)
 }
 
+/// Generates the FCS over the address, control and length fields, followed by `content`.
+pub(crate) fn fcs(
+    addr: u8,
+    control: u8,
+    length: Length,
+    encoding: LengthEncoding,
+    content: &[u8],
+) -> u8 {
+    let crc = Crc::<u8>::new(&crc::CRC_8_ROHC);
+    let mut digest = crc.digest();
+    let (octets, count) = length.encode(encoding);
+    digest.update(&[addr, control]);
+    digest.update(&octets[..count]);
+    digest.update(content);
+    !digest.finalize()
+}
+
+/// Generates the checksum of a [`Frame`], covering the information field of UI frames only.
+pub(crate) fn frame_checksum(
+    address: Address,
+    control: Control,
+    length: u16,
+    content: &[u8],
+    encoding: LengthEncoding,
+) -> u8 {
+    let content = if control.frame_type() == FrameType::UI {
+        content
+    } else {
+        &[]
+    };
+    fcs(
+        address.into_bits(),
+        control.into_bits(),
+        Length::from_indicator(length),
+        encoding,
+        content,
+    )
+}
+
 /// Data Link Connection Identifier
 ///
 /// The Data Link Connection Identifier (DLCI) is a 6-bit field that identifies the logical channel between the DTE and DCE.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum DLCI {
     AT(u8),
@@ -81,7 +366,8 @@ pub enum DLCI {
 }
 
 impl DLCI {
-    const fn into_bits(self) -> u8 {
+    /// Returns the 6-bit DLCI value.
+    pub const fn into_bits(self) -> u8 {
         match self {
             DLCI::AT(_) => 0x1,
             DLCI::SMS(_) => 0x3,
@@ -91,7 +377,8 @@ impl DLCI {
         }
     }
 
-    const fn from_bits(value: u8) -> Self {
+    /// Creates a DLCI from its 6-bit value.
+    pub const fn from_bits(value: u8) -> Self {
         match value {
             0x1 => DLCI::AT(0x1),
             0x3 => DLCI::SMS(0x3),
@@ -147,7 +434,7 @@ impl DLCI {
 /// let addr = addr.with_cr(false);
 /// assert_eq!(addr.into_bits(), 0b101);
 ///
-/// let addr = addr.with_dlci(DLCI::DATA);
+/// let addr = addr.with_dlci(DLCI::DATA(0x5));
 /// assert_eq!(addr.into_bits(), 0b10101);
 /// ```
 
@@ -166,8 +453,35 @@ impl Default for Address {
     }
 }
 
+impl Display for Address {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "DLCI={} C/R={} EA={}",
+            self.dlci().into_bits(),
+            self.cr() as u8,
+            self.ea() as u8
+        )
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Address {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "DLCI={=u8} C/R={=u8} EA={=u8}",
+            self.dlci().into_bits(),
+            self.cr() as u8,
+            self.ea() as u8
+        )
+    }
+}
+
 /// Frame Type of [`Frame`]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum FrameType {
     SABM,
@@ -176,6 +490,14 @@ pub enum FrameType {
     DISC,
     UIH,
     UI,
+    /// Information frame of error recovery mode.
+    I,
+    /// Receive Ready supervisory frame of error recovery mode.
+    RR,
+    /// Receive Not Ready supervisory frame of error recovery mode.
+    RNR,
+    /// Reject supervisory frame of error recovery mode.
+    REJ,
 }
 
 impl FrameType {
@@ -187,11 +509,34 @@ impl FrameType {
             FrameType::DISC => 0b01000011,
             FrameType::UIH => 0b11101111,
             FrameType::UI => 0b00000011,
+            FrameType::I => 0b00000000,
+            FrameType::RR => 0b00000001,
+            FrameType::RNR => 0b00000101,
+            FrameType::REJ => 0b00001001,
         }
     }
 
+    /// Returns true for I, RR, RNR and REJ frames, which carry sequence numbers.
+    pub const fn is_numbered(self) -> bool {
+        matches!(
+            self,
+            FrameType::I | FrameType::RR | FrameType::RNR | FrameType::REJ
+        )
+    }
+
     const fn from_bits(value: u8) -> Self {
-        match value {
+        // I frames have bit 1 cleared, supervisory frames have bits 1-2 set to 1, 0.
+        if value & 0b1 == 0 {
+            return FrameType::I;
+        }
+        if value & 0b11 == 0b01 {
+            return match value & 0b1111 {
+                0b0101 => FrameType::RNR,
+                0b1001 => FrameType::REJ,
+                _ => FrameType::RR,
+            };
+        }
+        match value & 0b11101111 {
             0b00101111 => FrameType::SABM,
             0b01100011 => FrameType::UA,
             0b00001111 => FrameType::DM,
@@ -215,6 +560,10 @@ impl FrameType {
 /// | DISC (Disconnect)                              | 1     | 1     | 0     | 0     | P/F   | 0     | 1     | 0     |           |
 /// | UIH (Unnumbered Information with Header check) | 1     | 1     | 1     | 1     | P/F   | 1     | 1     | 1     |           |
 /// | UI (Unnumbered Information)                    | 1     | 1     | 0     | 0     | P/F   | 0     | 0     | 0     | Optional  |
+/// | I (Information)                                | 0     | N(S)  | N(S)  | N(S)  | P/F   | N(R)  | N(R)  | N(R)  | Error recovery |
+/// | RR (Receive Ready)                             | 1     | 0     | 0     | 0     | P/F   | N(R)  | N(R)  | N(R)  | Error recovery |
+/// | RNR (Receive Not Ready)                        | 1     | 0     | 1     | 0     | P/F   | N(R)  | N(R)  | N(R)  | Error recovery |
+/// | REJ (Reject)                                   | 1     | 0     | 0     | 1     | P/F   | N(R)  | N(R)  | N(R)  | Error recovery |
 ///
 /// * P/F stands for Poll/Final bit.
 /// * SABM (Set Asynchronous Balance Mode): SABM command shall be send by the TE (the host) to the UE (the target) to confirm the acceptance of SABM by transmission of UA response.
@@ -223,6 +572,7 @@ impl FrameType {
 /// * DISC (Disconnect): The DISC is used to close a previously established connection. If the application sends a DISC for the DLCI 1 and DLCI 1 is already established, then it will be closed. The module will answer to this command with an UA frame.
 /// * UIH (Unnumbered Information with Header check): The UIH command/response will be used to send information. For the UIH frame, the FCS will be calculated over **only the address, control and length fields**. There is no specified response to the UIH command/response.
 /// * UI (Unnumbered Information): The UI command/response will be used to send information. There is no specified response to the UI command/response. For the UI frame, the FCS shall be calculated over **all fields (Address, Control, Length Indicator, and Information)**. Support of UI frames is optional.
+/// * I, RR, RNR and REJ frames are only used in error recovery mode. N(S) is the send sequence number of an I frame, N(R) the receive sequence number acknowledging every frame before it. Both are counted modulo 8.
 ///
 /// # Example
 ///
@@ -254,7 +604,41 @@ impl Control {
     }
 
     pub const fn frame_type(&self) -> FrameType {
-        FrameType::from_bits(self.0 & 0b11101111)
+        FrameType::from_bits(self.0)
+    }
+
+    /// Returns the send sequence number N(S) of an I frame.
+    pub const fn ns(&self) -> Option<u8> {
+        match self.frame_type() {
+            FrameType::I => Some((self.0 >> 1) & 0b111),
+            _ => None,
+        }
+    }
+
+    /// Sets the send sequence number N(S), modulo 8. It has no effect on other than I frames.
+    pub const fn with_ns(self, ns: u8) -> Self {
+        match self.frame_type() {
+            FrameType::I => Control((self.0 & !0b1110) | ((ns & 0b111) << 1)),
+            _ => self,
+        }
+    }
+
+    /// Returns the receive sequence number N(R) of an I, RR, RNR or REJ frame.
+    pub const fn nr(&self) -> Option<u8> {
+        if self.frame_type().is_numbered() {
+            Some(self.0 >> 5)
+        } else {
+            None
+        }
+    }
+
+    /// Sets the receive sequence number N(R), modulo 8. It has no effect on unnumbered frames.
+    pub const fn with_nr(self, nr: u8) -> Self {
+        if self.frame_type().is_numbered() {
+            Control((self.0 & 0b11111) | ((nr & 0b111) << 5))
+        } else {
+            self
+        }
     }
 
     pub fn set_frame_type(&mut self, frame_type: FrameType) {
@@ -304,11 +688,43 @@ impl Default for Control {
 }
 
 impl Debug for Control {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Control")
-            .field("frame_type", &self.frame_type())
-            .field("pf", &self.pf())
-            .finish()
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut s = f.debug_struct("Control");
+        s.field("frame_type", &self.frame_type())
+            .field("pf", &self.pf());
+        if let Some(ns) = self.ns() {
+            s.field("ns", &ns);
+        }
+        if let Some(nr) = self.nr() {
+            s.field("nr", &nr);
+        }
+        s.finish()
+    }
+}
+
+impl Display for Control {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?} P/F={}", self.frame_type(), self.pf() as u8)?;
+        if let Some(ns) = self.ns() {
+            write!(f, " N(S)={}", ns)?;
+        }
+        if let Some(nr) = self.nr() {
+            write!(f, " N(R)={}", nr)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Control {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{} P/F={=u8}", self.frame_type(), self.pf() as u8);
+        if let Some(ns) = self.ns() {
+            defmt::write!(f, " N(S)={=u8}", ns);
+        }
+        if let Some(nr) = self.nr() {
+            defmt::write!(f, " N(R)={=u8}", nr);
+        }
     }
 }
 
@@ -324,18 +740,23 @@ impl Debug for Control {
 ///    .with_address(Address::default())
 ///    .with_content("AT+CMUX?".to_string())
 ///    .with_control(Control::default())
-///    .build();
+///    .build()
+///    .unwrap();
 /// assert_eq!(p.header, 0xF9);
 /// ```
 ///
 /// # Note
 ///
-/// FrameBuilder will automatically add `\r\n` to the end of content if it is not present.
-#[derive(Debug)]
+/// FrameBuilder will automatically add `\r\n` to the end of text content set by
+/// [`FrameBuilder::with_content`] if it is not present. Text set by
+/// [`FrameBuilder::with_raw_content`] and binary content set by
+/// [`FrameBuilder::with_binary_content`] are framed as is. Without content, the frame has an
+/// empty information field, as SABM, UA, DM and DISC frames do.
+#[derive(Debug, Clone)]
 pub struct FrameBuilder {
     address: Option<Address>,
     control: Option<Control>,
-    content: Option<String>,
+    content: Option<Vec<u8>>,
 }
 
 impl Default for FrameBuilder {
@@ -354,38 +775,38 @@ impl FrameBuilder {
     ///
     /// # Returns
     ///
-    /// - `Ok(u16)`: The length of the frame if the content is present.
-    /// - `Err(Box<dyn Error>)`: An error indicating that the content is required.
-    fn length(&self) -> Result<u16, Box<dyn Error>> {
-        match &self.content {
-            Some(content) => {
-                let len = content.len() as u16;
-                if len > MAX_SINGLE_BIT_LENGTH {
-                    Ok(len << 1)
-                } else {
-                    Ok((len << 1) + 1)
-                }
-            }
-            None => Err("Content is required".into()),
-        }
+    /// - `Ok(u16)`: The length of the frame, 0x01 if there is no content.
+    /// - `Err(FrameError)`: An error indicating that the content is too long.
+    fn length(&self) -> Result<u16, FrameError> {
+        Length::new(self.content().len()).map(Length::indicator)
+    }
+
+    fn address(&self) -> Result<Address, FrameError> {
+        self.address.ok_or(FrameError::MissingField("Address"))
+    }
+
+    fn control(&self) -> Result<Control, FrameError> {
+        self.control.ok_or(FrameError::MissingField("Control"))
+    }
+
+    fn content(&self) -> &[u8] {
+        self.content.as_deref().unwrap_or_default()
     }
 
     /// Calculates the checksum of the frame.
     ///
     /// # Returns
     ///
-    /// - `Ok(u8)`: The checksum of the frame if the address is present.
-    /// - `Err(Box<dyn Error>)`: An error indicating that the address is required.
-    fn checksum(&self) -> Result<u8, Box<dyn Error>> {
-        let addr = self.address.expect("Address is required").into_bits();
-        let control = self.control.expect("Control is required").into_bits();
-        let length = self.length().expect("Length is required");
-
-        if self.control.unwrap().frame_type() == FrameType::UI {
-            checksum_ui(addr, control, length as u8, self.content.as_ref().unwrap())
-        } else {
-            checksum_uih(addr, control, length)
-        }
+    /// - `Ok(u8)`: The checksum of the frame if all fields are present.
+    /// - `Err(FrameError)`: An error indicating which field is missing.
+    fn checksum(&self) -> Result<u8, FrameError> {
+        Ok(frame_checksum(
+            self.address()?,
+            self.control()?,
+            self.length()?,
+            self.content(),
+            LengthEncoding::Spec,
+        ))
     }
 
     /// Sets the address of the frame.
@@ -412,11 +833,54 @@ impl FrameBuilder {
     ///
     /// - `&mut Self`: A mutable reference to the `FrameBuilder` object.
     pub fn with_content(&mut self, content: String) -> &mut Self {
-        if content.ends_with("\r\n") {
-            self.content = Some(content);
-        } else {
-            self.content = Some(format!("{}\r\n", content));
+        let mut content = content.into_bytes();
+        if !content.ends_with(b"\r\n") {
+            content.extend_from_slice(b"\r\n");
         }
+        self.content = Some(content);
+        self
+    }
+
+    /// Sets text content of the frame as is, without appending `\r\n`.
+    ///
+    /// Use it for text that must be framed unmodified, such as an AT command already
+    /// terminated by a lone `\r`.
+    ///
+    /// # Arguments
+    ///
+    /// - `content`: The text to set.
+    ///
+    /// # Returns
+    ///
+    /// - `&mut Self`: A mutable reference to the `FrameBuilder` object.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cmux::types::FrameBuilder;
+    ///
+    /// let frame = FrameBuilder::default()
+    ///     .with_raw_content("AT\r".to_string())
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(frame.content.as_bytes(), b"AT\r");
+    /// ```
+    pub fn with_raw_content(&mut self, content: String) -> &mut Self {
+        self.content = Some(content.into_bytes());
+        self
+    }
+
+    /// Sets binary content of the frame, without appending `\r\n`.
+    ///
+    /// # Arguments
+    ///
+    /// - `content`: The raw octets to set.
+    ///
+    /// # Returns
+    ///
+    /// - `&mut Self`: A mutable reference to the `FrameBuilder` object.
+    pub fn with_binary_content(&mut self, content: Vec<u8>) -> &mut Self {
+        self.content = Some(content);
         self
     }
 
@@ -438,17 +902,18 @@ impl FrameBuilder {
     ///
     /// # Returns
     ///
-    /// - [`Frame`]: The built frame.
-    pub fn build(&self) -> Frame {
-        Frame {
-            header: 0xF9,
-            address: self.address.expect("Address is required"),
-            control: self.control.expect("Control is required"),
-            length: self.length().expect("Length is required"),
-            content: ContentStr(self.content.clone().expect("Content is required")),
-            checksum: self.checksum().expect("Checksum is required"),
-            footer: 0xF9,
-        }
+    /// - `Ok(Frame)`: The built frame.
+    /// - `Err(FrameError)`: If a field is missing or the content is too long.
+    pub fn build(&self) -> Result<Frame, FrameError> {
+        Ok(Frame {
+            header: FLAG,
+            address: self.address()?,
+            control: self.control()?,
+            length: self.length()?,
+            content: Content(self.content().to_vec()),
+            checksum: self.checksum()?,
+            footer: FLAG,
+        })
     }
 }
 
@@ -459,17 +924,40 @@ impl FrameBuilder {
 /// | **Name** | Flag    | [`Address`] | [`Control`] | Length Indicator | Information                                      | FCS     | Flag    |
 /// |----------|---------|-------------|---------|------------------|--------------------------------------------------|---------|---------|
 /// | **Size** | 1 octet |   1 octet   | 1 octet | 1 or 2 octets    | Unspecified length but integral number of octets | 1 octet | 1 octet |
-#[derive(Debug, PartialEq, Eq)]
+///
+/// A frame displays as a single line; [`crate::pretty`] breaks it down field by field.
+///
+/// ```
+/// use cmux::types::Frame;
+///
+/// let frame = Frame::from_bytes(vec![0xF9, 0x07, 0xEF, 0x05, 0x41, 0x54, 0x30, 0xF9]).unwrap();
+/// assert_eq!(frame.to_string(), r#"DLCI=1 C/R=1 EA=1 UIH P/F=0 Length=2 Content="AT""#);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Frame {
     pub header: u8,
     pub address: Address,
     pub control: Control,
     pub length: u16,
-    pub content: ContentStr,
+    pub content: Content,
     pub checksum: u8,
     pub footer: u8,
 }
 
+impl Display for Frame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} {} Length={} Content={:?}",
+            self.address,
+            self.control,
+            self.content.len(),
+            self.content.to_string_lossy()
+        )
+    }
+}
+
 impl Frame {
     /// Converts the frame to a byte vector.
     ///
@@ -477,23 +965,42 @@ impl Frame {
     ///
     /// A `Vec<u8>` containing the byte representation of the frame.
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut data = vec![
-            self.header,
-            self.address.into_bits(),
-            self.control.into_bits(),
-        ];
-        if self.length > MAX_SINGLE_BIT_LENGTH {
-            data.push((self.length >> 8) as u8);
-            data.push((self.length & 0xFF) as u8);
-        } else {
-            data.push(self.length as u8);
-        }
-        data.extend(self.content.0.as_bytes());
-        data.push(self.checksum);
-        data.push(self.footer);
+        let mut data = vec![0; self.encoded_len()];
+        self.encode_into(&mut data)
+            .expect("the buffer holds the encoded frame");
         data
     }
 
+    /// Returns the number of octets written by [`Frame::to_bytes`] and [`Frame::encode_into`].
+    pub fn encoded_len(&self) -> usize {
+        self.as_frame_ref().encoded_len()
+    }
+
+    /// Encodes the frame at the start of `buf`, without allocating.
+    ///
+    /// Embedded targets can encode frames straight into a transmit or DMA buffer, reserving
+    /// [`Frame::encoded_len`] octets, or the encoded length of the largest frame they send.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(usize)`: The number of octets written.
+    /// - `Err(BufferTooSmall)`: If `buf` cannot hold the frame. Nothing is written.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cmux::types::Frame;
+    ///
+    /// let frame = Frame::from_bytes(vec![0xF9, 0x03, 0x3F, 0x01, 0x1C, 0xF9]).unwrap();
+    /// let mut buf = [0; 64];
+    /// let len = frame.encode_into(&mut buf).unwrap();
+    /// assert_eq!(&buf[..len], [0xF9, 0x03, 0x3F, 0x01, 0x1C, 0xF9]);
+    /// assert!(frame.encode_into(&mut buf[..5]).is_err());
+    /// ```
+    pub fn encode_into(&self, buf: &mut [u8]) -> Result<usize, BufferTooSmall> {
+        self.as_frame_ref().encode_into(buf)
+    }
+
     /// Converts the frame to a hexadecimal string.
     ///
     /// # Returns
@@ -511,28 +1018,207 @@ impl Frame {
     ///
     /// # Returns
     ///
-    /// A `Frame` object created from the byte vector.
-    pub fn from_bytes(data: Vec<u8>) -> Frame {
-        let mut p = 0;
-        let header = data[p];
-        p += 1;
-        let address = Address::from_bits(data[p]);
-        p += 1;
-        let control = Control::from_bits(data[p]);
-        p += 1;
-        let length = if data[p] & 0x1 == 0 {
-            let l = ((data[p] as u16) << 8) | data[p + 1] as u16;
-            p += 2;
-            l
-        } else {
-            let l = data[p] as u16;
-            p += 1;
-            l
-        };
-        let content = ContentStr(String::from_utf8_lossy(&data[p..data.len() - 2]).to_string());
+    /// - `Ok(Frame)`: The frame created from the byte vector.
+    /// - `Err(FrameError)`: If the data is truncated or not delimited by flags.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Frame, FrameError> {
+        FrameRef::parse(&data).map(|frame| frame.to_owned())
+    }
+
+    /// Iterates over the basic option frames of `data`, borrowing their content.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cmux::types::Frame;
+    ///
+    /// let data = hex::decode("F9033F011CF9 00 F9073F01DEF9".replace(' ', "")).unwrap();
+    /// let dlcis: Vec<u8> = Frame::iter_from(&data)
+    ///     .filter_map(Result::ok)
+    ///     .map(|frame| frame.address.dlci().into_bits())
+    ///     .collect();
+    /// assert_eq!(dlcis, [0, 1]);
+    /// ```
+    pub fn iter_from(data: &[u8]) -> FrameIter<'_> {
+        FrameIter::new(data)
+    }
+
+    /// Creates a frame from a byte vector whose length indicator is in `encoding`.
+    ///
+    /// Frames read with [`LengthEncoding::Legacy`] are converted: [`Frame::to_bytes`]
+    /// writes them as 07.10 specifies. Their FCS is kept as read, so it must be checked
+    /// with [`Frame::verify_with`].
+    pub fn from_bytes_with(data: Vec<u8>, encoding: LengthEncoding) -> Result<Frame, FrameError> {
+        FrameRef::parse_with(&data, encoding).map(|frame| frame.to_owned())
+    }
+
+    /// Borrows the frame as a [`FrameRef`].
+    pub fn as_frame_ref(&self) -> FrameRef<'_> {
+        FrameRef {
+            header: self.header,
+            address: self.address,
+            control: self.control,
+            length: self.length,
+            content: self.content.as_bytes(),
+            checksum: self.checksum,
+            footer: self.footer,
+        }
+    }
+
+    /// Verifies the integrity of the frame.
+    ///
+    /// * If the length field matches the content length, the length field is valid.
+    /// * If the checksum matches the calculated checksum, the checksum is valid. The checksum
+    ///   of UI frames covers the information field, the one of other frames does not.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(())`: If the frame is valid.
+    /// - `Err(FrameError)`: If the frame is invalid.
+    pub fn verify(&self) -> Result<(), FrameError> {
+        self.as_frame_ref().verify()
+    }
+
+    /// Verifies the integrity of the frame, computing the FCS over the length indicator
+    /// encoded as `encoding`.
+    pub fn verify_with(&self, encoding: LengthEncoding) -> Result<(), FrameError> {
+        self.as_frame_ref().verify_with(encoding)
+    }
+
+    /// Replaces the FCS with the one computed over the frame, for example after its fields
+    /// were edited by hand.
+    ///
+    /// The length indicator is kept as it is, and covered by the new FCS.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cmux::types::Frame;
+    ///
+    /// let data = vec![0xF9, 0x07, 0xEF, 0x05, 0x41, 0x54, 0x00, 0xF9];
+    /// let mut frame = Frame::from_bytes(data).unwrap();
+    /// assert!(frame.verify().is_err());
+    /// frame.recompute_checksum();
+    /// assert_eq!(frame.checksum, 0x30);
+    /// assert!(frame.verify().is_ok());
+    /// ```
+    pub fn recompute_checksum(&mut self) {
+        self.checksum = frame_checksum(
+            self.address,
+            self.control,
+            self.length,
+            self.content.as_bytes(),
+            LengthEncoding::Spec,
+        );
+    }
+}
+
+/// Outcome of [`FrameRef::parse_prefix`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseOutcome<'a> {
+    /// A frame of `len` octets starts the data.
+    Complete { frame: FrameRef<'a>, len: usize },
+    /// The data starts a frame that needs at least `needed` more octets.
+    Incomplete { needed: usize },
+}
+
+/// Frame borrowing its information field from the parsed bytes
+///
+/// [`FrameRef::parse`] decodes a basic option frame without allocating, which suits large
+/// captures where most frames are only inspected. [`FrameRef::to_owned`] copies it into a
+/// [`Frame`].
+///
+/// # Example
+///
+/// ```
+/// use cmux::types::FrameRef;
+///
+/// let data = [0xF9, 0x07, 0xEF, 0x05, 0x41, 0x54, 0x30, 0xF9];
+/// let frame = FrameRef::parse(&data).unwrap();
+/// assert_eq!(frame.content, b"AT");
+/// assert!(frame.verify().is_ok());
+/// assert_eq!(frame.to_owned().to_bytes(), data);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FrameRef<'a> {
+    pub header: u8,
+    pub address: Address,
+    pub control: Control,
+    pub length: u16,
+    pub content: &'a [u8],
+    pub checksum: u8,
+    pub footer: u8,
+}
+
+impl<'a> FrameRef<'a> {
+    /// Parses a frame from a byte slice.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The byte representation of the frame, from opening to closing flag.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(FrameRef)`: The frame, borrowing its content from `data`.
+    /// - `Err(FrameError)`: If the data is truncated or not delimited by flags.
+    pub fn parse(data: &'a [u8]) -> Result<FrameRef<'a>, FrameError> {
+        FrameRef::parse_with(data, LengthEncoding::Spec)
+    }
+
+    /// Parses a frame from a byte slice whose length indicator is in `encoding`.
+    pub fn parse_with(
+        data: &'a [u8],
+        encoding: LengthEncoding,
+    ) -> Result<FrameRef<'a>, FrameError> {
+        if data.len() < MIN_FRAME_LENGTH {
+            return Err(FrameError::TruncatedFrame {
+                expected: MIN_FRAME_LENGTH,
+                actual: data.len(),
+            });
+        }
+        let header = data[0];
+        if header != FLAG {
+            return Err(FrameError::BadFlag {
+                expected: FLAG,
+                found: header,
+            });
+        }
+        FrameRef::parse_fields(header, &data[1..], FLAG, encoding)
+    }
+
+    /// Parses the fields following the opening flag `header`, from the address field to the
+    /// closing flag, which must be `flag`.
+    pub(crate) fn parse_fields(
+        header: u8,
+        data: &'a [u8],
+        flag: u8,
+        encoding: LengthEncoding,
+    ) -> Result<FrameRef<'a>, FrameError> {
+        if data.len() < MIN_FRAME_LENGTH - 1 {
+            return Err(FrameError::TruncatedFrame {
+                expected: MIN_FRAME_LENGTH,
+                actual: data.len() + 1,
+            });
+        }
+        let address = Address::from_bits(data[0]);
+        let control = Control::from_bits(data[1]);
+        let (length, octets) = Length::decode(&data[2..data.len() - 2], encoding).ok_or(
+            FrameError::TruncatedFrame {
+                expected: MIN_FRAME_LENGTH + 1,
+                actual: data.len() + 1,
+            },
+        )?;
+        let length = length.indicator();
+        let content = &data[2 + octets..data.len() - 2];
         let checksum = data[data.len() - 2];
         let footer = data[data.len() - 1];
-        Frame {
+        if footer != flag {
+            return Err(FrameError::BadFlag {
+                expected: flag,
+                found: footer,
+            });
+        }
+        Ok(FrameRef {
             header,
             address,
             control,
@@ -540,44 +1226,236 @@ impl Frame {
             content,
             checksum,
             footer,
-        }
+        })
     }
 
-    /// Verifies the integrity of the frame.
+    /// Parses the frame at the start of `data`, which may end before the frame does or hold
+    /// more octets after it.
     ///
-    /// * If the length field matches the content length, the length field is valid.
-    /// * If the checksum matches the calculated checksum, the checksum is valid.
+    /// Callers reading from a socket or a UART can read at least the needed octets of an
+    /// incomplete frame before trying again, instead of guessing where the frame ends.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The received octets, starting with the opening flag.
     ///
     /// # Returns
     ///
-    /// - `Ok(())`: If the frame is valid.
-    /// - `Err(Box<dyn Error>)`: If the frame is invalid.
-    pub fn verify(&self) -> Result<(), Box<dyn Error>> {
-        let content_len = self.content.0.len() as u16;
-        if content_len > MAX_SINGLE_BIT_LENGTH {
-            if self.length != (content_len << 1) {
-                return Err("Length field is invalid".into());
+    /// - `Ok(ParseOutcome::Complete)`: The frame and the number of octets it occupies.
+    /// - `Ok(ParseOutcome::Incomplete)`: The number of octets missing, at least.
+    /// - `Err(FrameError)`: If `data` does not start with a flag, or the frame does not end
+    ///   with one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cmux::types::{FrameRef, ParseOutcome};
+    ///
+    /// let data = [0xF9, 0x07, 0xEF, 0x05, 0x41, 0x54, 0x30, 0xF9, 0xF9];
+    /// assert_eq!(
+    ///     FrameRef::parse_prefix(&data[..5]),
+    ///     Ok(ParseOutcome::Incomplete { needed: 3 })
+    /// );
+    /// let Ok(ParseOutcome::Complete { frame, len }) = FrameRef::parse_prefix(&data) else {
+    ///     panic!("the frame is complete");
+    /// };
+    /// assert_eq!((frame.content, len), (&b"AT"[..], 8));
+    /// ```
+    pub fn parse_prefix(data: &'a [u8]) -> Result<ParseOutcome<'a>, FrameError> {
+        FrameRef::parse_prefix_with(data, LengthEncoding::Spec)
+    }
+
+    /// Parses the frame at the start of `data` whose length indicator is in `encoding`, like
+    /// [`FrameRef::parse_prefix`].
+    pub fn parse_prefix_with(
+        data: &'a [u8],
+        encoding: LengthEncoding,
+    ) -> Result<ParseOutcome<'a>, FrameError> {
+        match data.first() {
+            None => {
+                return Ok(ParseOutcome::Incomplete {
+                    needed: MIN_FRAME_LENGTH,
+                })
+            }
+            Some(&FLAG) => {}
+            Some(&found) => {
+                return Err(FrameError::BadFlag {
+                    expected: FLAG,
+                    found,
+                })
             }
-        } else if self.length != (content_len << 1) + 1 {
-            return Err("Length field is invalid".into());
         }
+        let Some((length, octets)) = data
+            .get(3..)
+            .and_then(|data| Length::decode(data, encoding))
+        else {
+            // A first length octet with EA unset announces a second one.
+            let two_octets = data.get(3).is_some_and(|octet| octet & 0x1 == 0);
+            let len = MIN_FRAME_LENGTH + usize::from(two_octets);
+            return Ok(ParseOutcome::Incomplete {
+                needed: len - data.len(),
+            });
+        };
+        let len = 3 + octets + length.len() + 2;
+        if data.len() < len {
+            return Ok(ParseOutcome::Incomplete {
+                needed: len - data.len(),
+            });
+        }
+        let frame = FrameRef::parse_with(&data[..len], encoding)?;
+        Ok(ParseOutcome::Complete { frame, len })
+    }
 
-        if let Ok(c) = checksum_uih(
+    /// Returns the number of octets written by [`FrameRef::encode_into`].
+    pub(crate) fn encoded_len(&self) -> usize {
+        HEADER_OCTETS + Length::from_indicator(self.length).octets() + self.content.len() + 2
+    }
+
+    /// Encodes the frame at the start of `buf`, like [`Frame::encode_into`].
+    pub(crate) fn encode_into(&self, buf: &mut [u8]) -> Result<usize, BufferTooSmall> {
+        let needed = self.encoded_len();
+        let Some(buf) = buf.get_mut(..needed) else {
+            return Err(BufferTooSmall {
+                needed,
+                available: buf.len(),
+            });
+        };
+        let (octets, count) = Length::from_indicator(self.length).encode(LengthEncoding::Spec);
+        let content = self.content;
+        let (header, rest) = buf.split_at_mut(HEADER_OCTETS + count);
+        header[..HEADER_OCTETS].copy_from_slice(&[
+            self.header,
             self.address.into_bits(),
             self.control.into_bits(),
+        ]);
+        header[HEADER_OCTETS..].copy_from_slice(&octets[..count]);
+        let (body, trailer) = rest.split_at_mut(content.len());
+        body.copy_from_slice(content);
+        trailer.copy_from_slice(&[self.checksum, self.footer]);
+        Ok(needed)
+    }
+
+    /// Copies the frame into an owned [`Frame`].
+    pub fn to_owned(&self) -> Frame {
+        Frame {
+            header: self.header,
+            address: self.address,
+            control: self.control,
+            length: self.length,
+            content: Content(self.content.to_vec()),
+            checksum: self.checksum,
+            footer: self.footer,
+        }
+    }
+
+    /// Verifies the integrity of the frame, like [`Frame::verify`].
+    pub fn verify(&self) -> Result<(), FrameError> {
+        self.verify_with(LengthEncoding::Spec)
+    }
+
+    /// Verifies the integrity of the frame, like [`Frame::verify_with`].
+    pub fn verify_with(&self, encoding: LengthEncoding) -> Result<(), FrameError> {
+        let expected = Length::new(self.content.len())?.indicator();
+        if self.length != expected {
+            return Err(FrameError::BadLength {
+                expected,
+                actual: self.length,
+            });
+        }
+
+        let c = frame_checksum(
+            self.address,
+            self.control,
             self.length,
-        ) {
-            if c != self.checksum {
-                Err("Checksum is invalid".into())
-            } else {
-                Ok(())
+            self.content,
+            encoding,
+        );
+        if c != self.checksum {
+            return Err(FrameError::ChecksumMismatch {
+                expected: c,
+                actual: self.checksum,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Frame {
+    /// Writes the frame to `writer`, like [`Frame::to_bytes`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cmux::types::Frame;
+    ///
+    /// let frame = Frame::from_bytes(vec![0xF9, 0x03, 0x3F, 0x01, 0x1C, 0xF9]).unwrap();
+    /// let mut out = Vec::new();
+    /// frame.write_to(&mut out).unwrap();
+    /// assert_eq!(out, frame.to_bytes());
+    /// ```
+    pub fn write_to<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+
+    /// Reads the next frame from `reader`, blocking until it is complete.
+    ///
+    /// Octets before the opening flag and repeated flags are dropped. Past the opening flag,
+    /// only the octets of the frame are read, as told by its length indicator, so the next
+    /// frame can be read from the same stream. Each frame must have its own opening flag: a
+    /// stream whose frames share their flags is decoded by
+    /// [`FrameParser`](crate::parser::FrameParser). The FCS is kept as read, to be checked
+    /// with [`Frame::verify`].
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(Frame)`: The frame.
+    /// - `Err(io::Error)`: If `reader` fails, ends before the frame does, with
+    ///   `UnexpectedEof`, or the frame does not end with a flag, with `InvalidData`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cmux::types::Frame;
+    ///
+    /// let data = hex::decode("00F9F9033F011CF9F9073F01DEF9").unwrap();
+    /// let mut reader = &data[..];
+    /// assert_eq!(Frame::read_from(&mut reader).unwrap().to_hex_string(), "f9033f011cf9");
+    /// assert_eq!(Frame::read_from(&mut reader).unwrap().to_hex_string(), "f9073f01def9");
+    /// assert!(Frame::read_from(&mut reader).is_err());
+    /// ```
+    pub fn read_from<R: std::io::Read>(reader: &mut R) -> std::io::Result<Frame> {
+        let mut octet = [0];
+        loop {
+            reader.read_exact(&mut octet)?;
+            if octet[0] == FLAG {
+                break;
+            }
+        }
+        while octet[0] == FLAG {
+            reader.read_exact(&mut octet)?;
+        }
+        let mut data = vec![FLAG, octet[0]];
+        loop {
+            match FrameRef::parse_prefix(&data) {
+                Ok(ParseOutcome::Complete { frame, .. }) => return Ok(frame.to_owned()),
+                Ok(ParseOutcome::Incomplete { needed }) => {
+                    let len = data.len();
+                    data.resize(len + needed, 0);
+                    reader.read_exact(&mut data[len..])?;
+                }
+                Err(e) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
             }
-        } else {
-            Err("Checksum calculation failed".into())
         }
     }
 }
 
+impl From<FrameRef<'_>> for Frame {
+    fn from(value: FrameRef<'_>) -> Self {
+        value.to_owned()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -586,7 +1464,8 @@ mod tests {
     fn test_packet_builder() {
         let p = FrameBuilder::default()
             .with_content("AT+CMUX?".to_string())
-            .build();
+            .build()
+            .unwrap();
         assert_eq!(p.header, 0xF9);
         assert_eq!(p.address, Address::default());
         assert_eq!(p.control, Control::default());
@@ -605,7 +1484,8 @@ mod tests {
         let p = FrameBuilder::default()
             .with_address(Address::default())
             .with_content(content)
-            .build();
+            .build()
+            .unwrap();
         assert_eq!(p.length, len as u16);
     }
 
@@ -614,11 +1494,62 @@ mod tests {
         let p = FrameBuilder::default()
             .with_address(Address::default())
             .with_content("AT+CMUX?".to_string())
-            .build();
+            .build()
+            .unwrap();
         let data = p.to_hex_string();
         assert_eq!(data, "f907ef1541542b434d55583f0d0a2cf9".to_string());
     }
 
+    #[test]
+    fn test_encode_into() {
+        let p = FrameBuilder::default()
+            .with_binary_content(vec![0xAA; 200])
+            .build()
+            .unwrap();
+        assert_eq!(p.encoded_len(), 207);
+        let mut buf = [0; 256];
+        assert_eq!(p.encode_into(&mut buf), Ok(207));
+        assert_eq!(&buf[..207], p.to_bytes());
+        assert_eq!(
+            p.encode_into(&mut buf[..206]),
+            Err(BufferTooSmall {
+                needed: 207,
+                available: 206
+            })
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_read_from() {
+        // Delivers one octet per read, like a slow serial port.
+        struct Trickle<'a>(&'a [u8]);
+        impl std::io::Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let Some((&first, rest)) = self.0.split_first() else {
+                    return Ok(0);
+                };
+                buf[0] = first;
+                self.0 = rest;
+                Ok(1)
+            }
+        }
+
+        let p = FrameBuilder::default()
+            .with_binary_content(vec![0xF9; 200])
+            .build()
+            .unwrap();
+        let mut data = Vec::new();
+        p.write_to(&mut data).unwrap();
+        data.extend([0xF9, 0x03, 0x3F, 0x01, 0x1C, 0x00]);
+        let mut reader = Trickle(&data);
+        assert_eq!(Frame::read_from(&mut reader).unwrap(), p);
+        let e = Frame::read_from(&mut reader).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+        let e = Frame::read_from(&mut reader).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
     #[test]
     fn test_packet_from_bytes() {
         let content = "AT+CMUX?".to_string();
@@ -626,8 +1557,9 @@ mod tests {
         let p = FrameBuilder::default()
             .with_address(Address::default())
             .with_content("AT+CMUX?".to_string())
-            .build();
-        let d = Frame::from_bytes(p.to_bytes());
+            .build()
+            .unwrap();
+        let d = Frame::from_bytes(p.to_bytes()).unwrap();
         assert_eq!(p, d);
         assert_eq!(d.length, len as u16);
         assert_eq!(d.address, Address::default());
@@ -642,19 +1574,244 @@ mod tests {
         let p = FrameBuilder::default()
             .with_address(Address::default())
             .with_content(content)
-            .build();
-        let d = Frame::from_bytes(p.to_bytes());
+            .build()
+            .unwrap();
+        let d = Frame::from_bytes(p.to_bytes()).unwrap();
         assert_eq!(p, d);
         assert_eq!(d.length, len as u16);
         assert!(d.verify().is_ok());
     }
 
+    #[test]
+    fn test_length_encoding() {
+        let p = FrameBuilder::default()
+            .with_binary_content(vec![0x55; 300])
+            .build()
+            .unwrap();
+        let data = p.to_bytes();
+        assert_eq!(data[3..5], [0x58, 0x02]);
+        assert_eq!(Length::from_indicator(p.length).len(), 300);
+
+        // cmux 0.2 wrote the octets swapped, with the FCS computed over them.
+        let mut legacy = data.clone();
+        legacy.swap(3, 4);
+        let checksum = legacy.len() - 2;
+        legacy[checksum] = fcs(
+            0x07,
+            0xEF,
+            Length::from_indicator(p.length),
+            LengthEncoding::Legacy,
+            &[],
+        );
+        let d = Frame::from_bytes_with(legacy, LengthEncoding::Legacy).unwrap();
+        assert_eq!(d.content, p.content);
+        assert!(d.verify_with(LengthEncoding::Legacy).is_ok());
+        assert!(d.verify().is_err());
+
+        // Up to 127 octets the indicator is a single octet in both encodings.
+        let (length, octets) = Length::decode(&[0xFF, 0x00], LengthEncoding::Legacy).unwrap();
+        assert_eq!((length.len(), octets), (127, 1));
+        assert_eq!(
+            Length::new(0x8000),
+            Err(FrameError::ContentTooLong { len: 0x8000 })
+        );
+    }
+
+    #[test]
+    fn test_packet_binary_content() {
+        let content: Vec<u8> = (0..=0xFF).collect();
+        let p = FrameBuilder::default()
+            .with_binary_content(content.clone())
+            .build()
+            .unwrap();
+        assert_eq!(p.content.as_bytes(), content.as_slice());
+        assert_eq!(p.length, (content.len() as u16) << 1);
+
+        let d = Frame::from_bytes(p.to_bytes()).unwrap();
+        assert_eq!(d.content, content.as_slice());
+        assert_eq!(p, d);
+        assert!(d.verify().is_ok());
+    }
+
+    #[test]
+    fn test_packet_raw_content() {
+        let p = FrameBuilder::default()
+            .with_raw_content("AT\r".to_string())
+            .build()
+            .unwrap();
+        assert_eq!(p.content.as_bytes(), b"AT\r");
+        assert!(p.verify().is_ok());
+        let binary = FrameBuilder::default()
+            .with_binary_content(b"AT\r".to_vec())
+            .build()
+            .unwrap();
+        assert_eq!(p, binary);
+        // Text set afterwards with `with_content` is terminated again.
+        let p = FrameBuilder::default()
+            .with_raw_content("AT".to_string())
+            .with_content("AT".to_string())
+            .build()
+            .unwrap();
+        assert_eq!(p.content.as_bytes(), b"AT\r\n");
+    }
+
+    #[test]
+    fn test_packet_without_content() {
+        let sabm = FrameBuilder::default()
+            .with_address(Address::default().with_dlci(DLCI::OTHER(0)))
+            .with_control(
+                Control::default()
+                    .with_frame_type(FrameType::SABM)
+                    .with_pf(true),
+            )
+            .build()
+            .unwrap();
+        assert_eq!(sabm.length, 0x01);
+        assert_eq!(sabm.to_hex_string(), "f9033f011cf9");
+
+        let ua = FrameBuilder::default()
+            .with_address(Address::default().with_dlci(DLCI::OTHER(0)))
+            .with_control(
+                Control::default()
+                    .with_frame_type(FrameType::UA)
+                    .with_pf(true),
+            )
+            .build()
+            .unwrap();
+        assert_eq!(ua.to_hex_string(), "f9037301d7f9");
+    }
+
+    #[test]
+    fn test_packet_errors() {
+        assert_eq!(
+            FrameBuilder::default()
+                .with_binary_content(vec![0; MAX_CONTENT_LENGTH + 1])
+                .build(),
+            Err(FrameError::ContentTooLong {
+                len: MAX_CONTENT_LENGTH + 1
+            })
+        );
+        assert_eq!(
+            Frame::from_bytes(vec![0xF9, 0x03, 0x3F]),
+            Err(FrameError::TruncatedFrame {
+                expected: 6,
+                actual: 3
+            })
+        );
+        assert_eq!(
+            Frame::from_bytes(vec![0xF9, 0x03, 0x3F, 0x01, 0x1C, 0x7E]),
+            Err(FrameError::BadFlag {
+                expected: 0xF9,
+                found: 0x7E
+            })
+        );
+        // Frames delimited by another flag report it.
+        let error = FrameRef::parse_fields(
+            0x7E,
+            &[0x03, 0x3F, 0x01, 0x1C, 0xF9],
+            0x7E,
+            LengthEncoding::Spec,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Flag is invalid: expected 0x7E, found 0xF9"
+        );
+
+        let mut p = Frame::from_bytes(vec![0xF9, 0x03, 0x3F, 0x01, 0x1C, 0xF9]).unwrap();
+        assert!(p.verify().is_ok());
+        p.checksum = 0x1D;
+        assert_eq!(
+            p.verify(),
+            Err(FrameError::ChecksumMismatch {
+                expected: 0x1C,
+                actual: 0x1D
+            })
+        );
+        p.length = 0x03;
+        assert_eq!(
+            p.verify(),
+            Err(FrameError::BadLength {
+                expected: 0x01,
+                actual: 0x03
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_prefix() {
+        let frame = FrameBuilder::default()
+            .with_binary_content(vec![0xAA; 200])
+            .build()
+            .unwrap();
+        let data = frame.to_bytes();
+        assert_eq!(
+            FrameRef::parse_prefix(&data[..4]),
+            Ok(ParseOutcome::Incomplete { needed: 3 })
+        );
+        assert_eq!(
+            FrameRef::parse_prefix(&data[..5]),
+            Ok(ParseOutcome::Incomplete { needed: 202 })
+        );
+        assert_eq!(
+            FrameRef::parse_prefix(&data),
+            Ok(ParseOutcome::Complete {
+                frame: frame.as_frame_ref(),
+                len: data.len()
+            })
+        );
+        assert_eq!(
+            FrameRef::parse_prefix(&[0x41]),
+            Err(FrameError::BadFlag {
+                expected: 0xF9,
+                found: 0x41
+            })
+        );
+    }
+
+    #[test]
+    fn test_verify_ui() {
+        let mut ui = FrameBuilder::default()
+            .with_control(Control::default().with_frame_type(FrameType::UI))
+            .with_content("AT".to_string())
+            .build()
+            .unwrap();
+        assert!(ui.verify().is_ok());
+        ui.content = Content::from("AU\r\n");
+        assert!(matches!(
+            ui.verify(),
+            Err(FrameError::ChecksumMismatch { .. })
+        ));
+
+        let mut uih = FrameBuilder::default()
+            .with_content("AT".to_string())
+            .build()
+            .unwrap();
+        uih.content = Content::from("AU\r\n");
+        assert!(uih.verify().is_ok());
+    }
+
+    #[test]
+    fn test_recompute_checksum() {
+        let mut ui = FrameBuilder::default()
+            .with_control(Control::default().with_frame_type(FrameType::UI))
+            .with_content("AT".to_string())
+            .build()
+            .unwrap();
+        let checksum = ui.checksum;
+        ui.content = Content::from("AU\r\n");
+        ui.recompute_checksum();
+        assert_ne!(ui.checksum, checksum);
+        assert!(ui.verify().is_ok());
+    }
+
     #[test]
     fn test_packet_checksum() {
         let p = FrameBuilder::default()
             .with_address(Address::default())
             .with_content("AT+CMUX?".to_string())
-            .build();
+            .build()
+            .unwrap();
         let ori = p.checksum;
         let exp = checksum_uih(p.address.into_bits(), p.control.into_bits(), p.length).unwrap();
         assert_eq!(ori, exp);
@@ -663,13 +1820,14 @@ mod tests {
             .with_address(Address::default())
             .with_content("AT+CMUX?".to_string())
             .with_control(Control::default().with_frame_type(FrameType::UI))
-            .build();
+            .build()
+            .unwrap();
         let ori = p.checksum;
         let exp = checksum_ui(
             p.address.into_bits(),
             p.control.into_bits(),
             p.length as u8,
-            &p.content.0,
+            p.content.as_bytes(),
         )
         .unwrap();
         assert_eq!(ori, exp);
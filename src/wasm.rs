@@ -0,0 +1,57 @@
+use crate::types::Frame;
+use wasm_bindgen::prelude::*;
+
+/// Decodes a hex string, ignoring whitespace, into a frame.
+fn frame_from_hex(text: &str) -> Result<Frame, String> {
+    let digits: String = text.split_whitespace().collect();
+    let data = hex::decode(digits).map_err(|e| e.to_string())?;
+    Frame::from_bytes(data).map_err(|e| e.to_string())
+}
+
+/// Parses one basic option frame from its hex string.
+///
+/// # Arguments
+///
+/// * `hex` - The frame, including both flags. Whitespace between the digits is ignored.
+///
+/// # Returns
+///
+/// An object with the `address`, `control`, `content`, `length` and `checksum` fields of the
+/// frame, in the form written by [`crate::serialization`]. The content is a hex string.
+/// Throws an `Error` if the string is not hex or not a frame.
+#[wasm_bindgen(js_name = parseHex)]
+pub fn parse_hex(hex: &str) -> Result<JsValue, JsError> {
+    let frame = frame_from_hex(hex).map_err(|e| JsError::new(&e))?;
+    Ok(serde_wasm_bindgen::to_value(&frame)?)
+}
+
+/// Builds a basic option frame and returns its hex string.
+///
+/// # Arguments
+///
+/// * `opts` - An object in the form returned by [`parse_hex`], such as
+///   `{ address: { cr: true, dlci: 1 }, control: { frame_type: "UIH", pf: false }, content: "4154" }`.
+///   The `length` and `checksum` fields are computed when they are missing.
+///
+/// # Returns
+///
+/// The upper case hex string of the frame. Throws an `Error` if `opts` does not describe a frame.
+#[wasm_bindgen(js_name = buildFrame)]
+pub fn build_frame(opts: JsValue) -> Result<String, JsError> {
+    let frame: Frame = serde_wasm_bindgen::from_value(opts)?;
+    Ok(hex::encode_upper(frame.to_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_from_hex() {
+        let frame = frame_from_hex("F9 07 EF 05 41 54 30 F9").unwrap();
+        assert_eq!(frame.address.dlci().into_bits(), 1);
+        assert_eq!(frame.content.as_bytes(), b"AT");
+        assert!(frame_from_hex("F9033F").is_err());
+        assert!(frame_from_hex("F9033G011CF9").is_err());
+    }
+}